@@ -168,7 +168,13 @@ async def run_bot_instance(network_name: str, available_networks: list) -> Optio
         auto_fuel_enabled=config_db.get('auto_fuel_enabled', False),
         auto_fuel_threshold=config_db.get('auto_fuel_threshold', 0.005),
         auto_fuel_amount=config_db.get('auto_fuel_amount', 0.01),
-        fuel_quote_address=quote_address
+        fuel_quote_address=quote_address,
+        max_attempts=config_db.get('auto_fuel_max_attempts'),
+        cooldown_secs=config_db.get('auto_fuel_cooldown_secs'),
+        quarantine_secs=config_db.get('auto_fuel_quarantine_secs'),
+        fuel_mode=config_db.get('auto_fuel_mode'),
+        treasury_wallet=config_db.get('auto_fuel_treasury_wallet'),
+        treasury_reserve=config_db.get('auto_fuel_treasury_reserve')
     )
 
     init_cmd = EngineCommand.init(
@@ -185,14 +191,45 @@ async def run_bot_instance(network_name: str, available_networks: list) -> Optio
         public_rpc_urls=app_config.PUBLIC_RPC_URLS,
         fuel=fuel,
         quote_symbol=default_quote,
-        quote_tokens=app_config.QUOTE_TOKENS
+        quote_tokens=app_config.QUOTE_TOKENS,
+        v3_fee_tiers=app_config.V3_FEE_TIERS,
+        discover_v3_fee_tiers=app_config.DISCOVER_V3_FEE_TIERS,
+        journal_path=str(Path(app_config.DB_PATH).with_name("trade_journal.db")),
+        metrics_port=app_config.METRICS_PORT,
+        private_relay_urls=app_config.PRIVATE_RELAY_URLS,
+        broadcast_mode=app_config.BROADCAST_MODE,
+        max_block_lag=app_config.MAX_BLOCK_LAG,
+        rpc_rate_limits=app_config.RPC_RATE_LIMITS,
+        public_rpc_rps=app_config.PUBLIC_RPC_RPS,
+        token_cache_path=str(Path(app_config.DB_PATH).with_name("token_cache.json")),
+        notify=app_config.NOTIFY,
+        price_feed=app_config.PRICE_FEED,
+        candle_intervals=app_config.CANDLE_INTERVALS,
+        candle_history=app_config.CANDLE_HISTORY,
+        lp_lockers=app_config.LP_LOCKERS,
+        dry_run=app_config.DRY_RUN,
+        v2_pool_fee_bps=app_config.V2_POOL_FEE_BPS,
+        router_tax_bps=app_config.ROUTER_TAX_BPS,
+        monitor=app_config.MONITOR,
+        chain_quirks=app_config.CHAIN_QUIRKS,
+        validate_config=app_config.VALIDATE_CONFIG,
+        network_name=network_name,
+        ens_registry=app_config.ENS_REGISTRY,
+        ens_reverse_lookup=app_config.ENS_REVERSE_LOOKUP,
+        session_path=str(Path(app_config.DB_PATH).with_name("session.json")),
+        min_native_reserve_wei=app_config.MIN_NATIVE_RESERVE_WEI
     )
     bridge.send(init_cmd)
+    bridge.send(EngineCommand.restore_session())
     
     bridge.send(EngineCommand.update_settings(
         gas_price_gwei=float(config_db.get('default_gas_price_gwei', 0.1)),
         slippage=float(config_db.get('slippage', 15.0)),
-        fuel_enabled=fuel.auto_fuel_enabled
+        fuel_enabled=fuel.auto_fuel_enabled,
+        pending_tx_timeout_secs=app_config.PENDING_TX_TIMEOUT_SECS,
+        balance_quotes=app_config.BALANCE_QUOTES,
+        min_balance_usd=app_config.MIN_BALANCE_USD,
+        min_balance_wei=app_config.MIN_BALANCE_WEI
     ))
 
     await log.info(f"--- Ядро Rust инициализировано для сети: {app_config.NAME} ---")
@@ -1,5 +1,6 @@
 import asyncio
 import socket
+import uuid
 import orjson
 from typing import Optional, Callable, Dict, List
 from dataclasses import dataclass
@@ -21,14 +22,26 @@ class AutoFuelSettings:
     auto_fuel_threshold: float = 0.005
     auto_fuel_amount: float = 0.01
     fuel_quote_address: str = ""
+    # Лимит попыток на кошелёк; None - значения ядра (5 попыток, 60 с, карантин 30 мин)
+    max_attempts: Optional[int] = None
+    cooldown_secs: Optional[int] = None
+    quarantine_secs: Optional[int] = None
+    # "swap" - продать quote кошелька, "treasury" - перевод native с кошелька-казны
+    fuel_mode: Optional[str] = None
+    treasury_wallet: Optional[str] = None
+    treasury_reserve: Optional[float] = None  # остаток казны, ниже которого не пополнять (по умолчанию - порог)
     
     def to_dict(self) -> dict:
-        return {
+        data = {
             "auto_fuel_enabled": self.auto_fuel_enabled,
             "auto_fuel_threshold": self.auto_fuel_threshold,
             "auto_fuel_amount": self.auto_fuel_amount,
             "fuel_quote_address": self.fuel_quote_address
         }
+        for key in ("max_attempts", "cooldown_secs", "quarantine_secs", "fuel_mode", "treasury_wallet", "treasury_reserve"):
+            if getattr(self, key) is not None:
+                data[key] = getattr(self, key)
+        return data
 
 
 # ===================== ENGINE COMMANDS =====================
@@ -57,7 +70,33 @@ class EngineCommand:
         public_rpc_urls: List[str],
         fuel,
         quote_symbol: str,
-        quote_tokens: list
+        quote_tokens: list,
+        v3_fee_tiers: Optional[List[int]] = None,
+        discover_v3_fee_tiers: bool = False,
+        journal_path: Optional[str] = None,
+        metrics_port: Optional[int] = None,
+        private_relay_urls: Optional[List[str]] = None,
+        broadcast_mode: Optional[str] = None,
+        max_block_lag: Optional[int] = None,
+        rpc_rate_limits: Optional[dict] = None,
+        public_rpc_rps: Optional[float] = None,
+        token_cache_path: Optional[str] = None,
+        notify: Optional[dict] = None,
+        price_feed: Optional[dict] = None,
+        candle_intervals: Optional[List[int]] = None,
+        candle_history: Optional[int] = None,
+        lp_lockers: Optional[List[str]] = None,
+        dry_run: bool = False,
+        v2_pool_fee_bps: Optional[int] = None,
+        router_tax_bps: Optional[int] = None,
+        monitor: Optional[dict] = None,
+        chain_quirks: Optional[dict] = None,
+        validate_config: bool = False,
+        network_name: Optional[str] = None,
+        ens_registry: Optional[str] = None,
+        ens_reverse_lookup: bool = False,
+        session_path: Optional[str] = None,
+        min_native_reserve_wei: Optional[int] = None
     ) -> dict:
         fuel_dict = fuel.to_dict() if hasattr(fuel, 'to_dict') else fuel
         return {
@@ -76,14 +115,41 @@ class EngineCommand:
                 "public_rpc_urls": public_rpc_urls,
                 "fuel_settings": fuel_dict,
                 "quote_symbol": quote_symbol,
-                "quote_tokens": quote_tokens
+                "quote_tokens": quote_tokens,
+                "v3_fee_tiers": v3_fee_tiers,
+                "discover_v3_fee_tiers": discover_v3_fee_tiers,
+                "journal_path": journal_path,
+                "metrics_port": metrics_port,
+                "private_relay_urls": private_relay_urls or [],
+                "broadcast_mode": broadcast_mode,
+                "max_block_lag": max_block_lag,
+                "rpc_rate_limits": rpc_rate_limits or {},
+                "public_rpc_rps": public_rpc_rps,
+                "token_cache_path": token_cache_path,
+                "notify": notify or {},
+                "price_feed": price_feed or {},
+                "candle_intervals": candle_intervals,
+                "candle_history": candle_history,
+                "lp_lockers": lp_lockers or [],
+                "dry_run": dry_run,
+                "v2_pool_fee_bps": v2_pool_fee_bps,
+                "router_tax_bps": router_tax_bps,
+                "monitor": monitor or {},
+                "chain_quirks": chain_quirks or {},
+                "validate_config": validate_config,
+                "network_name": network_name,
+                "ens_registry": ens_registry,
+                "ens_reverse_lookup": ens_reverse_lookup,
+                "session_path": session_path,
+                "min_native_reserve_wei": str(min_native_reserve_wei) if min_native_reserve_wei is not None else None
             }
         }
     
     @staticmethod
-    def switch_token(token_address: str, quote_address: str, quote_symbol: str) -> dict:
+    def add_token_subscription(token_address: str, quote_address: str, quote_symbol: str) -> dict:
+        """Подписаться на токен (к уже активным, не более max_token_subscriptions)"""
         return {
-            "type": "SwitchToken",
+            "type": "AddTokenSubscription",
             "data": {
                 "token_address": token_address,
                 "quote_address": quote_address,
@@ -92,15 +158,26 @@ class EngineCommand:
         }
     
     @staticmethod
-    def unsubscribe_token(token_address: str) -> dict:
+    def remove_token_subscription(token_address: str) -> dict:
         """Отписаться от обновлений токена"""
         return {
-            "type": "UnsubscribeToken",
+            "type": "RemoveTokenSubscription",
             "data": {
                 "token_address": token_address
             }
         }
     
+    @staticmethod
+    def select_pool(token_address: str, pool_address: Optional[str] = None) -> dict:
+        """Закрепить пул из PoolCandidates токена вместо автоматического выбора (None - снять выбор)"""
+        return {
+            "type": "SelectPool",
+            "data": {
+                "token_address": token_address,
+                "pool_address": pool_address
+            }
+        }
+    
     @staticmethod
     def calc_impact(
         token_address: str,
@@ -127,9 +204,19 @@ class EngineCommand:
         wallets: List[str],
         gas_gwei: float,
         slippage: float,
-        v3_fee: int = 2500,
-        amounts_wei: Optional[Dict[str, str]] = None
+        amounts_wei: Optional[Dict[str, str]] = None,
+        twap_slices: int = 0,
+        twap_interval_secs: int = 0,
+        routing: str = "single",
+        gas_strategy: str = "manual",
+        percent: Optional[float] = None,
+        deadline_secs: Optional[int] = None,
+        slippage_mode: Optional[str] = None
     ) -> dict:
+        """percent - продажа доли баланса каждого кошелька (amount=0, без amounts_wei).
+        gas_gwei/slippage = 0 и deadline_secs/slippage_mode = None - глобальные настройки ядра.
+        slippage_mode: "fixed" | "auto" (impact сделки + буфер)"""
+        twap = {"slices": twap_slices, "interval_secs": twap_interval_secs} if twap_slices > 1 else None
         return {
             "type": "ExecuteTrade",
             "data": {
@@ -140,8 +227,13 @@ class EngineCommand:
                 "wallets": wallets,
                 "gas_gwei": gas_gwei,
                 "slippage": slippage,
-                "v3_fee": v3_fee,
-                "amounts_wei": amounts_wei if amounts_wei else {}
+                "amounts_wei": amounts_wei if amounts_wei else {},
+                "twap": twap,
+                "routing": routing,
+                "gas_strategy": gas_strategy,
+                "percent": percent,
+                "deadline_secs": deadline_secs,
+                "slippage_mode": slippage_mode
             }
         }
     
@@ -153,7 +245,41 @@ class EngineCommand:
         fuel_quote_address: Optional[str] = None,
         rpc_url: Optional[str] = None,
         wss_url: Optional[str] = None,
-        quote_symbol: Optional[str] = None
+        quote_symbol: Optional[str] = None,
+        rug_alert_pct: Optional[float] = None,
+        rug_auto_sell: Optional[bool] = None,
+        split_min_improvement_bps: Optional[float] = None,
+        pool_switch_hysteresis_pct: Optional[float] = None,
+        max_token_subscriptions: Optional[int] = None,
+        bridge_queue_capacity: Optional[int] = None,
+        gas_limit_swap: Optional[int] = None,
+        gas_limit_approve: Optional[int] = None,
+        gas_limit_safety_factor: Optional[float] = None,
+        approval_mode: Optional[str] = None,
+        wrap_gas_reserve: Optional[float] = None,
+        disperse_address: Optional[str] = None,
+        broadcast_mode: Optional[str] = None,
+        notify: Optional[dict] = None,
+        portfolio_pnl_delta_pct: Optional[float] = None,
+        pnl_update_epsilon_pct: Optional[float] = None,
+        attribute_approve_gas: Optional[bool] = None,
+        attribute_fuel_gas: Optional[bool] = None,
+        deadline_secs: Optional[int] = None,
+        slippage_mode: Optional[str] = None,
+        auto_slippage_buffer_bps: Optional[float] = None,
+        auto_slippage_max_pct: Optional[float] = None,
+        sandwich_precheck: Optional[bool] = None,
+        sandwich_max_move_pct: Optional[float] = None,
+        sandwich_detect: Optional[bool] = None,
+        batch_burst: Optional[bool] = None,
+        auto_approve: Optional[bool] = None,
+        dry_run: Optional[bool] = None,
+        pending_tx_timeout_secs: Optional[int] = None,
+        monitor: Optional[dict] = None,
+        min_native_reserve_wei: Optional[int] = None,
+        balance_quotes: Optional[List[str]] = None,
+        min_balance_usd: Optional[float] = None,
+        min_balance_wei: Optional[Dict[str, int]] = None
     ) -> dict:
         return {
             "type": "UpdateSettings",
@@ -164,7 +290,43 @@ class EngineCommand:
                 "fuel_quote_address": fuel_quote_address,
                 "rpc_url": rpc_url,
                 "wss_url": wss_url,
-                "quote_symbol": quote_symbol
+                "quote_symbol": quote_symbol,
+                "rug_alert_pct": rug_alert_pct,
+                "rug_auto_sell": rug_auto_sell,
+                "split_min_improvement_bps": split_min_improvement_bps,
+                "pool_switch_hysteresis_pct": pool_switch_hysteresis_pct,
+                "max_token_subscriptions": max_token_subscriptions,
+                "bridge_queue_capacity": bridge_queue_capacity,
+                "gas_limit_swap": gas_limit_swap,
+                "gas_limit_approve": gas_limit_approve,
+                "gas_limit_safety_factor": gas_limit_safety_factor,
+                "approval_mode": approval_mode,
+                "wrap_gas_reserve": wrap_gas_reserve,
+                "disperse_address": disperse_address,
+                "broadcast_mode": broadcast_mode,
+                "notify": notify,
+                "portfolio_pnl_delta_pct": portfolio_pnl_delta_pct,
+                "pnl_update_epsilon_pct": pnl_update_epsilon_pct,
+                "attribute_approve_gas": attribute_approve_gas,
+                "attribute_fuel_gas": attribute_fuel_gas,
+                "deadline_secs": deadline_secs,
+                "slippage_mode": slippage_mode,
+                "auto_slippage_buffer_bps": auto_slippage_buffer_bps,
+                "auto_slippage_max_pct": auto_slippage_max_pct,
+                "sandwich_precheck": sandwich_precheck,
+                "sandwich_max_move_pct": sandwich_max_move_pct,
+                "sandwich_detect": sandwich_detect,
+                "batch_burst": batch_burst,
+                "auto_approve": auto_approve,
+                "dry_run": dry_run,
+                "pending_tx_timeout_secs": pending_tx_timeout_secs,
+                "monitor": monitor,
+                # wei больше 2^53 - строкой
+                "min_native_reserve_wei": str(min_native_reserve_wei) if min_native_reserve_wei is not None else None,
+                "balance_quotes": balance_quotes,
+                "min_balance_usd": min_balance_usd,
+                # токен ("native") -> wei строкой
+                "min_balance_wei": {t: str(w) for t, w in min_balance_wei.items()} if min_balance_wei is not None else None
             }
         }
     
@@ -188,6 +350,376 @@ class EngineCommand:
             }
         }
     
+    @staticmethod
+    def get_token_info(address: str) -> dict:
+        """Ответ - событие TokenInfo (symbol, name, decimals, total_supply)"""
+        return {"type": "GetTokenInfo", "data": {"address": address}}
+    
+    @staticmethod
+    def watch_address(address: str, label: str = "", copy_trade: Optional[dict] = None) -> dict:
+        """Наблюдение за чужим адресом (события WatchedAddressActivity).
+        copy_trade: {"amount", "wallets", "slippage"} - повторять его покупки фиксированной суммой"""
+        return {"type": "WatchAddress", "data": {"address": address, "label": label, "copy_trade": copy_trade}}
+    
+    @staticmethod
+    def unwatch_address(address: str) -> dict:
+        return {"type": "UnwatchAddress", "data": {"address": address}}
+    
+    @staticmethod
+    def ignore_token(address: str) -> dict:
+        """Спам-токен: без BalanceUpdate и без balanceOf по его Transfer. Список сохраняется в сессии"""
+        return {"type": "IgnoreToken", "data": {"address": address}}
+    
+    @staticmethod
+    def unignore_token(address: str) -> dict:
+        return {"type": "UnignoreToken", "data": {"address": address}}
+    
+    @staticmethod
+    def audit_token(token: str) -> dict:
+        """Ответ - TokenAudit (флаги риска, owner, прокси, доля заблокированной LP). Кэшируется на сессию"""
+        return {"type": "AuditToken", "data": {"token": token}}
+    
+    @staticmethod
+    def analyze_holders(token: str, top_n: Optional[int] = None, max_blocks: Optional[int] = None, refresh: bool = False) -> dict:
+        """Ответ - HolderReport (в процессе - HolderScanProgress). Повторный запрос отдаёт кэш сессии, если не refresh"""
+        return {"type": "AnalyzeHolders", "data": {"token": token, "top_n": top_n, "max_blocks": max_blocks, "refresh": refresh}}
+    
+    @staticmethod
+    def import_history(wallet: str, token: str, quote: Optional[str] = None,
+                       from_block: Optional[int] = None, max_blocks: Optional[int] = None) -> dict:
+        """Позиция из Transfer логов и журнала; ответ - PositionUpdate (в процессе - HistoryImportProgress)"""
+        return {
+            "type": "ImportHistory",
+            "data": {
+                "wallet": wallet,
+                "token": token,
+                "quote": quote,
+                "from_block": from_block,
+                "max_blocks": max_blocks
+            }
+        }
+    
+    @staticmethod
+    def get_candles(pool: str, interval: int, count: Optional[int] = None) -> dict:
+        """Ответ - событие Candles: закрытые свечи пула, старые первыми (count None - весь буфер)"""
+        return {"type": "GetCandles", "data": {"pool": pool, "interval": interval, "count": count}}
+    
+    @staticmethod
+    def clear_token_cache(address: Optional[str] = None) -> dict:
+        """Забыть сохранённые decimals/symbol токена (None - всех токенов сети)"""
+        return {"type": "ClearTokenCache", "data": {"address": address}}
+    
+    @staticmethod
+    def set_price_alert(
+        alert_id: str,
+        token: str,
+        quote: str,
+        price: float,
+        direction: str,
+        repeat: bool = False
+    ) -> dict:
+        """Ценовой алерт. direction: above | below"""
+        return {
+            "type": "SetPriceAlert",
+            "data": {
+                "id": alert_id,
+                "token": token,
+                "quote": quote,
+                "price": price,
+                "direction": direction,
+                "repeat": repeat
+            }
+        }
+    
+    @staticmethod
+    def remove_price_alert(alert_id: str) -> dict:
+        return {
+            "type": "RemovePriceAlert",
+            "data": {"id": alert_id}
+        }
+    
+    @staticmethod
+    def wrap_native(wallets: List[str], amount) -> dict:
+        """Native -> wrapped native. amount: число или "max" (весь баланс минус резерв на газ)"""
+        return {
+            "type": "WrapNative",
+            "data": {"wallets": wallets, "amount": str(amount)}
+        }
+    
+    @staticmethod
+    def unwrap_native(wallets: List[str], amount) -> dict:
+        """Wrapped native -> native. amount: число или 'max'"""
+        return {
+            "type": "UnwrapNative",
+            "data": {"wallets": wallets, "amount": str(amount)}
+        }
+    
+    @staticmethod
+    def transfer(from_wallet: str, to_address: str, amount, token: Optional[str] = None) -> dict:
+        """Перевод native (token=None) или ERC-20. amount: число или 'max'"""
+        return {
+            "type": "Transfer",
+            "data": {
+                "from_wallet": from_wallet,
+                "to_address": to_address,
+                "token": token,
+                "amount": str(amount)
+            }
+        }
+    
+    @staticmethod
+    def disperse_native(from_wallet: str, recipients: List[tuple]) -> dict:
+        """Рассылка native: recipients - [(address, amount), ...]"""
+        return {
+            "type": "DisperseNative",
+            "data": {
+                "from_wallet": from_wallet,
+                "recipients": [[addr, float(amount)] for addr, amount in recipients]
+            }
+        }
+    
+    @staticmethod
+    def sweep_all(
+        destination: str,
+        token: Optional[str] = None,
+        wallets: Optional[List[str]] = None,
+        leave_native_for_gas: Optional[float] = None
+    ) -> dict:
+        """Собрать весь баланс token (None - native) с кошельков (None - со всех) на destination"""
+        return {
+            "type": "SweepAll",
+            "data": {
+                "token": token,
+                "wallets": wallets or [],
+                "destination": destination,
+                "leave_native_for_gas": leave_native_for_gas
+            }
+        }
+    
+    @staticmethod
+    def add_rpc_node(url: str, is_private: bool = False) -> dict:
+        """Добавить RPC в пул без перезапуска (ядро сразу сверит chain_id)"""
+        return {"type": "AddRpcNode", "data": {"url": url, "is_private": is_private}}
+    
+    @staticmethod
+    def remove_rpc_node(url: str) -> dict:
+        return {"type": "RemoveRpcNode", "data": {"url": url}}
+    
+    @staticmethod
+    def reload_network_config(network: Optional[str] = None) -> dict:
+        """Перечитать networks/<network>.json (None - сеть из Init); ответ - ConfigReloaded"""
+        return {"type": "ReloadNetworkConfig", "data": {"network": network}}
+    
+    @staticmethod
+    def restore_session(stale_after_blocks: Optional[int] = None, discard: bool = False) -> dict:
+        """Вернуть pending tx, позиции, ордера и DCA из session_path Init; ответ - SessionRestored"""
+        return {"type": "RestoreSession", "data": {"stale_after_blocks": stale_after_blocks, "discard": discard}}
+    
+    @staticmethod
+    def set_rpc_priority(url: str, pinned: bool) -> dict:
+        """Закреплённая нода выбирается первой, пока она жива"""
+        return {"type": "SetRpcPriority", "data": {"url": url, "pinned": pinned}}
+    
+    @staticmethod
+    def revoke_approval(wallet: str, token: str, spender: Optional[str] = None) -> dict:
+        """approve(spender, 0) для токена (None - роутер)"""
+        return {
+            "type": "RevokeApproval",
+            "data": {
+                "wallet": wallet,
+                "token": token,
+                "spender": spender
+            }
+        }
+    
+    @staticmethod
+    def check_approvals(wallets: List[str], tokens: List[str], spender: Optional[str] = None) -> dict:
+        """allowance() пар кошелёк × токен без отправки tx (None - роутер)"""
+        return {
+            "type": "CheckApprovals",
+            "data": {
+                "wallets": wallets,
+                "tokens": tokens,
+                "spender": spender
+            }
+        }
+    
+    @staticmethod
+    def place_limit_order(
+        order_id: str,
+        action: str,
+        token: str,
+        quote: str,
+        trigger_price: float,
+        amount: float,
+        wallets: List[str],
+        slippage: float,
+        expiry: int = 0,
+        amounts_wei: Optional[Dict[str, str]] = None
+    ) -> dict:
+        """Лимитный ордер. expiry - unix timestamp (сек), 0 = бессрочно"""
+        return {
+            "type": "PlaceLimitOrder",
+            "data": {
+                "id": order_id,
+                "action": action,
+                "token": token,
+                "quote": quote,
+                "trigger_price": trigger_price,
+                "amount": amount,
+                "wallets": wallets,
+                "slippage": slippage,
+                "expiry": expiry,
+                "amounts_wei": amounts_wei
+            }
+        }
+    
+    @staticmethod
+    def cancel_limit_order(order_id: str) -> dict:
+        return {
+            "type": "CancelLimitOrder",
+            "data": {"id": order_id}
+        }
+    
+    @staticmethod
+    def prepare_bundle(
+        bundle_id: str,
+        action: str,
+        token: str,
+        quote: str,
+        amount: float,
+        wallets: List[str],
+        gas_gwei: float = 0.0,
+        slippage: float = 0.0,
+        deadline_secs: Optional[int] = None
+    ) -> dict:
+        """Подписать свапы заранее (nonce резервируются). Бандл живёт до дедлайна свапов"""
+        return {
+            "type": "PrepareBundle",
+            "data": {
+                "id": bundle_id,
+                "action": action,
+                "token": token,
+                "quote": quote,
+                "amount": amount,
+                "wallets": wallets,
+                "gas_gwei": gas_gwei,
+                "slippage": slippage,
+                "deadline_secs": deadline_secs
+            }
+        }
+    
+    @staticmethod
+    def fire_bundle(bundle_id: str) -> dict:
+        return {
+            "type": "FireBundle",
+            "data": {"id": bundle_id}
+        }
+    
+    @staticmethod
+    def discard_bundle(bundle_id: str) -> dict:
+        return {
+            "type": "DiscardBundle",
+            "data": {"id": bundle_id}
+        }
+    
+    @staticmethod
+    def arm_snipe(
+        token: str,
+        quote: str,
+        amount: float,
+        wallets: List[str],
+        max_buy_tax_pct: float,
+        min_liquidity_usd: float,
+        max_gas_gwei: float = 0.0,
+        timeout_secs: Optional[int] = None
+    ) -> dict:
+        """Подписать buy до появления ликвидности и разослать на первой ликвидности пары"""
+        return {
+            "type": "ArmSnipe",
+            "data": {
+                "token": token,
+                "quote": quote,
+                "amount": amount,
+                "wallets": wallets,
+                "max_gas_gwei": max_gas_gwei,
+                "max_buy_tax_pct": max_buy_tax_pct,
+                "min_liquidity_usd": min_liquidity_usd,
+                "timeout_secs": timeout_secs
+            }
+        }
+    
+    @staticmethod
+    def disarm_snipe(token: str) -> dict:
+        return {
+            "type": "DisarmSnipe",
+            "data": {"token": token}
+        }
+    
+    @staticmethod
+    def set_exit_strategy(
+        wallet: str,
+        token: str,
+        quote: str,
+        cost_basis: Optional[float] = None,
+        stop_loss_pct: Optional[float] = None,
+        take_profit_pct: Optional[float] = None,
+        trail_pct: Optional[float] = None
+    ) -> dict:
+        """SL/TP/trailing для позиции. cost_basis - потрачено в единицах quote, None - средняя цена позиции ядра"""
+        return {
+            "type": "SetExitStrategy",
+            "data": {
+                "wallet": wallet,
+                "token": token,
+                "quote": quote,
+                "cost_basis": cost_basis,
+                "stop_loss_pct": stop_loss_pct,
+                "take_profit_pct": take_profit_pct,
+                "trail_pct": trail_pct
+            }
+        }
+    
+    @staticmethod
+    def clear_exit_strategy(wallet: str, token: str) -> dict:
+        return {
+            "type": "ClearExitStrategy",
+            "data": {"wallet": wallet, "token": token}
+        }
+    
+    @staticmethod
+    def schedule_recurring_trade(
+        schedule_id: str,
+        action: str,
+        token: str,
+        quote: str,
+        amount: float,
+        wallets: List[str],
+        interval_secs: int,
+        max_executions: int = 0
+    ) -> dict:
+        """DCA: сделка каждые interval_secs. max_executions=0 - без ограничения"""
+        return {
+            "type": "ScheduleRecurringTrade",
+            "data": {
+                "id": schedule_id,
+                "action": action,
+                "token": token,
+                "quote": quote,
+                "amount": amount,
+                "wallets": wallets,
+                "interval_secs": interval_secs,
+                "max_executions": max_executions
+            }
+        }
+    
+    @staticmethod
+    def cancel_recurring_trade(schedule_id: str) -> dict:
+        return {
+            "type": "CancelRecurringTrade",
+            "data": {"id": schedule_id}
+        }
+    
     @staticmethod
     def add_wallet(address: str, private_key: str) -> dict:
         return {
@@ -195,6 +727,22 @@ class EngineCommand:
             "data": {"address": address, "private_key": private_key}
         }
     
+    @staticmethod
+    def remove_wallet(address: str) -> dict:
+        """Удалить кошелёк из ядра (ключ, nonce, SL/TP, участие в ордерах)"""
+        return {
+            "type": "RemoveWallet",
+            "data": {"address": address}
+        }
+    
+    @staticmethod
+    def reset_auto_fuel(wallet: str) -> dict:
+        """Сбросить попытки и карантин auto-fuel кошелька"""
+        return {
+            "type": "ResetAutoFuel",
+            "data": {"wallet": wallet}
+        }
+    
     @staticmethod
     def refresh_balance(wallet: str, token: str) -> dict:
         return {
@@ -207,6 +755,21 @@ class EngineCommand:
         """Unit variant - БЕЗ data!"""
         return {"type": "RefreshAllBalances"}
     
+    @staticmethod
+    def get_state() -> dict:
+        """Unit variant - БЕЗ data! Ответ приходит событием StateSnapshot"""
+        return {"type": "GetState"}
+    
+    @staticmethod
+    def pause_trading() -> dict:
+        """Unit variant - БЕЗ data! Kill-switch: движок перестаёт подписывать транзакции"""
+        return {"type": "PauseTrading"}
+    
+    @staticmethod
+    def resume_trading() -> dict:
+        """Unit variant - БЕЗ data!"""
+        return {"type": "ResumeTrading"}
+    
     @staticmethod
     def shutdown() -> dict:
         """Unit variant - БЕЗ data!"""
@@ -230,6 +793,8 @@ class BridgeManager:
         self._gas_price: float = 1.0
         self._connected: bool = False
         self._event_queue: asyncio.Queue = asyncio.Queue()
+        # request_id -> (ожидаемые типы событий, future) для send_and_wait
+        self._waiters: Dict[str, tuple] = {}
         
     @property
     def gas_price(self) -> float:
@@ -295,15 +860,16 @@ class BridgeManager:
             self._rsock.recv(4096)
             
             while True:
-                raw_json = dexbot_core.pop_from_bridge()
-                if not raw_json:
+                batch = dexbot_core.pop_batch_from_bridge(256)
+                if not batch:
                     break
                 
-                try:
-                    event = orjson.loads(raw_json)
-                    self._process_event(event)
-                except Exception as e:
-                    print(f"[Bridge] JSON parse error: {e}")
+                for raw_json in batch:
+                    try:
+                        event = orjson.loads(raw_json)
+                        self._process_event(event)
+                    except Exception as e:
+                        print(f"[Bridge] JSON parse error: {e}")
                     
         except BlockingIOError:
             pass
@@ -315,7 +881,16 @@ class BridgeManager:
         etype = event.get("type", "")
         data = event.get("data", {})
         
-        if etype == "ConnectionStatus":
+        request_id = event.get("request_id")
+        if request_id and request_id in self._waiters:
+            event_types, fut = self._waiters[request_id]
+            if not fut.done() and (etype == "CommandError" or not event_types or etype in event_types):
+                fut.set_result(event)
+        
+        # Кэши ниже - состояние основного движка; события движков из create_engine только уходят в очередь
+        if event.get("engine_id", 0) != 0:
+            pass
+        elif etype == "ConnectionStatus":
             self._connected = data.get("connected", False)
             
         elif etype == "GasPriceUpdate":
@@ -335,10 +910,11 @@ class BridgeManager:
         except Exception as e:
             print(f"[Bridge] Queue put error: {e}")
     
-    def send(self, command):
-        """Отправка команды в Rust ядро"""
+    def send(self, command, request_id: Optional[str] = None, engine_id: Optional[int] = None) -> Optional[str]:
+        """Отправка команды в Rust ядро. request_id эхом вернётся во всех событиях-результатах.
+        engine_id - движок из create_engine (None - основной)"""
         if not RUST_AVAILABLE:
-            return
+            return None
             
         try:
             if hasattr(command, 'model_dump'):
@@ -353,10 +929,108 @@ class BridgeManager:
             else:
                 cmd_dict = command
             
+            if request_id:
+                cmd_dict = {**cmd_dict, "request_id": request_id}
+            
             cmd_json = orjson.dumps(cmd_dict).decode('utf-8')
-            dexbot_core.push_to_engine(cmd_json)
+            dexbot_core.push_to_engine(cmd_json, engine_id)
         except Exception as e:
             print(f"[Bridge] Send error: {e}")
+        return request_id
+    
+    def create_engine(self, network_config: dict) -> Optional[int]:
+        """Ещё один экземпляр ядра (своя сеть, состояние и задачи); config - data команды Init.
+        Его события приходят в общую очередь с полем engine_id"""
+        if not RUST_AVAILABLE:
+            return None
+        try:
+            return dexbot_core.create_engine(orjson.dumps(network_config).decode('utf-8'))
+        except Exception as e:
+            print(f"[Bridge] Create engine error: {e}")
+            return None
+    
+    def destroy_engine(self, engine_id: int) -> bool:
+        """Останавливает движок из create_engine и освобождает его состояние"""
+        if not RUST_AVAILABLE:
+            return False
+        try:
+            dexbot_core.destroy_engine(engine_id)
+            return True
+        except Exception as e:
+            print(f"[Bridge] Destroy engine error: {e}")
+            return False
+    
+    def set_event_filter(self, types: List[str], mode: str = "allow", engine_id: Optional[int] = None):
+        """Фильтр событий на стороне Rust (пустой список - получать всё); у каждого движка свой"""
+        if not RUST_AVAILABLE:
+            return
+        dexbot_core.set_event_filter_mode(mode, engine_id)
+        dexbot_core.set_event_filter(types, engine_id)
+    
+    def get_event_filter(self, engine_id: Optional[int] = None) -> Optional[tuple]:
+        if not RUST_AVAILABLE:
+            return None
+        return dexbot_core.get_event_filter(engine_id)
+    
+    # ---- Кошельки: ключи генерирует и хранит Rust, в Python уходят только адреса ----
+    
+    def open_wallet_store(self, path: str, master_password: str) -> List[str]:
+        """Открыть (или создать) зашифрованное хранилище ключей; кошельки сразу отслеживаются ядром"""
+        return dexbot_core.open_wallet_store(path, master_password)
+    
+    def create_wallets(self, count: int = 1) -> List[str]:
+        return dexbot_core.create_wallet(count)
+    
+    def import_wallet(self, private_key: str) -> str:
+        return dexbot_core.import_wallet(private_key)
+    
+    def export_keystore(self, address: str, password: str, path: str) -> str:
+        """UTC/JSON keystore; path - файл или каталог"""
+        return dexbot_core.export_keystore(address, password, path)
+    
+    def import_keystore(self, path: str, password: str) -> str:
+        return dexbot_core.import_keystore(path, password)
+    
+    def export_private_key(self, address: str, master_password: str) -> str:
+        return dexbot_core.export_private_key(address, master_password)
+    
+    def generate_mnemonic(self, words: int = 12, passphrase: Optional[str] = None, overwrite: bool = False) -> str:
+        """Новая BIP-39 фраза в хранилище; возвращается один раз - для бэкапа"""
+        return dexbot_core.generate_mnemonic(words, passphrase, overwrite)
+    
+    def derive_wallets(self, start_index: int = 0, count: int = 1, mnemonic: Optional[str] = None,
+                       passphrase: Optional[str] = None) -> List[str]:
+        """m/44'/60'/0'/0/i из сохранённой фразы (или явной mnemonic)"""
+        return dexbot_core.derive_wallets(mnemonic, start_index, count, passphrase)
+    
+    # ---- Подпись ключом бота (ed25519) ----
+    
+    def set_event_signing(self, enabled: bool):
+        """Каждое событие моста получает поле sig; ключ должен быть загружен init_or_load_keys"""
+        if RUST_AVAILABLE:
+            dexbot_core.set_event_signing(enabled)
+    
+    @staticmethod
+    def verify_event(raw_json: str, public_key_pem: str) -> bool:
+        """Проверка подписанного события: подписан JSON без хвоста ,"sig":"..." """
+        head, sep, tail = raw_json.rpartition(',"sig":"')
+        if not sep or not tail.endswith('"}'):
+            return False
+        return dexbot_core.verify_signature(public_key_pem, (head + "}").encode(), tail[:-2])
+    
+    async def send_and_wait(self, command, event_types: Optional[List[str]] = None, timeout: float = 30.0,
+                            engine_id: Optional[int] = None) -> Optional[dict]:
+        """Отправка команды и ожидание первого события с её request_id (CommandError приходит всегда)"""
+        request_id = uuid.uuid4().hex
+        fut = asyncio.get_running_loop().create_future()
+        self._waiters[request_id] = (event_types or [], fut)
+        try:
+            self.send(command, request_id=request_id, engine_id=engine_id)
+            return await asyncio.wait_for(fut, timeout)
+        except asyncio.TimeoutError:
+            return None
+        finally:
+            self._waiters.pop(request_id, None)
     
     async def _log(self, message: str):
         from utils.aiologger import log
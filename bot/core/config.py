@@ -34,13 +34,47 @@ class Config:
         self.V3_FACTORY_ADDRESS = network_settings.get('v3_factory_address', '')
         self.V2_ROUTER_ADDRESS = network_settings.get('v2_router_address', '') 
         self.V3_QUOTER_ADDRESS = network_settings.get('v3_quoter_address', '')
+        self.V3_FEE_TIERS = network_settings.get('v3_fee_tiers')  # None -> дефолтный список ядра
+        self.DISCOVER_V3_FEE_TIERS = network_settings.get('discover_v3_fee_tiers', False)
+        self.METRICS_PORT = network_settings.get('metrics_port')  # None -> /metrics не поднимается
         
         self.PUBLIC_RPC_URLS = network_settings.get('public_rpc_urls', [])
+        self.PRIVATE_RELAY_URLS = network_settings.get('private_relay_urls', [])
+        self.BROADCAST_MODE = network_settings.get('broadcast_mode')  # None -> public
+        self.MAX_BLOCK_LAG = network_settings.get('max_block_lag')  # None -> дефолт ядра (5 блоков)
+        self.RPC_RATE_LIMITS = network_settings.get('rpc_rate_limits', {})  # url -> запросов в секунду
+        self.PUBLIC_RPC_RPS = network_settings.get('public_rpc_rps')  # None -> публичные без лимита
+        self.NOTIFY = network_settings.get('notify', {})  # webhook_url / telegram_bot_token + telegram_chat_id / events
+        self.CANDLE_INTERVALS = network_settings.get('candle_intervals')  # секунды, None -> 1s/5s/1m
+        self.CANDLE_HISTORY = network_settings.get('candle_history')  # None -> 500 свечей на серию
+        self.LP_LOCKERS = network_settings.get('lp_lockers', [])  # контракты-локеры LP для AuditToken
+        self.DRY_RUN = network_settings.get('dry_run', False)  # бумажная торговля: tx подписываются, но не рассылаются
+        self.V2_POOL_FEE_BPS = network_settings.get('v2_pool_fee_bps')  # комиссия V2 пулов, None - 0.3%
+        self.ROUTER_TAX_BPS = network_settings.get('router_tax_bps')  # комиссия TaxRouter, None - 0.1%
+        self.PENDING_TX_TIMEOUT_SECS = network_settings.get('pending_tx_timeout_secs')  # None -> 180с до проверки на dropped / stuck
+        self.MIN_NATIVE_RESERVE_WEI = network_settings.get('min_native_reserve_wei')  # None -> без резерва
+        self.BALANCE_QUOTES = network_settings.get('balance_quotes')  # символы quote_tokens для опроса балансов, None -> все
+        self.MIN_BALANCE_USD = network_settings.get('min_balance_usd')  # BalanceUpdate дешевле не показывается, None -> без порога
+        self.MIN_BALANCE_WEI = network_settings.get('min_balance_wei')  # токен ("native") -> минимум в wei
+        self.VALIDATE_CONFIG = network_settings.get('validate_config', False)  # Init проверяет адреса, chain_id и байткод
+        self.ENS_REGISTRY = network_settings.get('ens_registry')  # None -> имена вместо адресов не разрешаются
+        self.ENS_REVERSE_LOOKUP = network_settings.get('ens_reverse_lookup', False)  # имена адресов в ленте и наблюдении
+        self.CHAIN_QUIRKS = network_settings.get('chain_quirks', {})  # force_legacy_tx / min_gas_price_gwei / gas_multiplier / supports_eip1559 / block_time_secs
+        self.MONITOR = network_settings.get('monitor', {})  # idle_timeout_secs / reconnect_delay_secs / receipt_poll_ms / worker_tick_ms / rpc_health_secs
         self.FEE_RECEIVER = network_settings.get('fee_receiver', '').lower()
         
         self.QUOTE_TOKENS = network_settings['quote_tokens']
         self.DEFAULT_QUOTE_CURRENCY = network_settings['default_quote_currency']
         self.ERC20_QUOTES_TICKERS: List[str] = self._generate_tickers()
+        # Фид USD цен в ядре: provider None -> цены только из MarketDataService (UpdatePrice)
+        self.PRICE_FEED = {
+            "provider": network_settings.get('price_feed_provider'),
+            "chainlink_feeds": network_settings.get('chainlink_feeds', {}),
+            "http_url": network_settings.get('price_feed_http_url'),
+            "symbols": [t.split("/")[0] for t in self.ERC20_QUOTES_TICKERS],
+            "interval_secs": network_settings.get('price_feed_interval_secs'),
+            "stale_after_secs": network_settings.get('price_stale_after_secs'),
+        }
 
         self.MIN_NATIVE_FOR_GAS = network_settings.get("min_native_for_gas", 0.00005)
         self.REQUIRES_PRIVATE_RPC = network_settings.get("requires_private_rpc", False) # Это поле в bsc_testnet.json нужно обязательно задавать как `true` т.к данная сеть требует наличия приватной ноды в БД
@@ -1,4 +1,6 @@
 import sys
+import json
+from typing import Optional
 
 try:
     import dexbot_core
@@ -31,5 +33,67 @@ def enumerate_adapters() -> list:
     try:
         return dexbot_core.get_available_networks() # type: ignore
     except Exception as e:
+        # Ядро перечисляет проверенные каталоги - видно, где искать конфиги
         print(f"Error enumerating adapters from Core: {e}")
         return []
+
+def set_resource_dir(path: Optional[str]) -> None:
+    """Каталог конфигов сетей для ядра; None - поиск по умолчанию (./networks, рядом с exe, ~/.config/evm_terminal/networks)"""
+    if not dexbot_core:
+        raise RuntimeError("CRITICAL: Secure Core (dexbot_core) not found. Cannot set config directory.")
+    dexbot_core.set_config_dir(path) # type: ignore
+
+def validate_resource_bundle(bundle_name: str) -> list:
+    """
+    Проверка конфигурации сети в Rust-ядре: адреса, chain_id на rpc_url, байткод контрактов.
+    Список {"severity": "error" | "warning", "field", "message"}; пустой - конфиг в порядке.
+    """
+    if not dexbot_core:
+        raise RuntimeError("CRITICAL: Secure Core (dexbot_core) not found. Cannot validate network config.")
+    
+    try:
+        return json.loads(dexbot_core.validate_network_config(bundle_name)) # type: ignore
+    except Exception as e:
+        raise ValueError(f"Secure resource '{bundle_name}' validation failed: {e}")
+
+def save_resource_bundle(bundle_name: str, config: dict, overwrite: bool = False) -> list:
+    """
+    Сохранить конфигурацию сети через Rust-ядро: проверка по схеме, атомарная запись networks/<bundle_name>.json.
+    Существующий файл перезаписывается только с overwrite=True. Возвращает предупреждения (формат validate_resource_bundle).
+    """
+    if not dexbot_core:
+        raise RuntimeError("CRITICAL: Secure Core (dexbot_core) not found. Cannot save network config.")
+    
+    try:
+        return json.loads(dexbot_core.save_network_config(bundle_name, json.dumps(config), overwrite)) # type: ignore
+    except Exception as e:
+        raise ValueError(f"Secure resource '{bundle_name}' save failed: {e}")
+
+def delete_resource_bundle(bundle_name: str) -> None:
+    if not dexbot_core:
+        raise RuntimeError("CRITICAL: Secure Core (dexbot_core) not found. Cannot delete network config.")
+    
+    try:
+        dexbot_core.delete_network_config(bundle_name) # type: ignore
+    except Exception as e:
+        raise ValueError(f"Secure resource '{bundle_name}' delete failed: {e}")
+
+def list_quote_tokens(bundle_name: str) -> dict:
+    """Quote токены сети: символ -> адрес"""
+    if not dexbot_core:
+        raise RuntimeError("CRITICAL: Secure Core (dexbot_core) not found. Cannot load network config.")
+    
+    try:
+        return dexbot_core.list_quote_tokens(bundle_name) # type: ignore
+    except Exception as e:
+        raise ValueError(f"Secure resource '{bundle_name}' load failed: {e}")
+
+def add_quote_token(bundle_name: str, symbol: str, address: str) -> list:
+    """Добавить quote токен (например, новый стейбл) в существующую сеть одним вызовом. Возвращает предупреждения."""
+    if not dexbot_core:
+        raise RuntimeError("CRITICAL: Secure Core (dexbot_core) not found. Cannot save network config.")
+    
+    try:
+        return json.loads(dexbot_core.add_quote_token(bundle_name, symbol, address)) # type: ignore
+    except Exception as e:
+        raise ValueError(f"Secure resource '{bundle_name}' update failed: {e}")
@@ -29,7 +29,15 @@ interface ISwapRouter {
         uint256 amountOutMinimum;
         uint160 sqrtPriceLimitX96;
     }
+    struct ExactInputParams {
+        bytes path;
+        address recipient;
+        uint256 deadline;
+        uint256 amountIn;
+        uint256 amountOutMinimum;
+    }
     function exactInputSingle(ExactInputSingleParams calldata params) external payable returns (uint256 amountOut);
+    function exactInput(ExactInputParams calldata params) external payable returns (uint256 amountOut);
 }
 library UniswapV2Library {
     function sortTokens(address tokenA, address tokenB) internal pure returns (address token0, address token1) {
@@ -145,6 +153,47 @@ contract TaxRouter {
         }
     }
 
+    // --- V3 MULTI-HOP SWAP (path: tokenIn | fee | mid | fee | tokenOut) ---
+
+    function swapV3Multi(
+        bytes calldata path, uint256 amountIn, uint256 amountOutMin, address recipient, uint256 deadline
+    ) external returns (uint256 amountOut) {
+        if (deadline < block.timestamp) revert DeadlineExpired(deadline, block.timestamp);
+        if (path.length < 43 || (path.length - 20) % 23 != 0) revert InvalidPath();
+        address tokenIn = address(bytes20(path[:20]));
+        address tokenOut = address(bytes20(path[path.length - 20:]));
+
+        uint balBefore = IERC20(tokenIn).balanceOf(address(this));
+        IERC20(tokenIn).transferFrom(msg.sender, address(this), amountIn);
+        uint amountToSwap = IERC20(tokenIn).balanceOf(address(this)) - balBefore;
+        bool isInputQuote = isQuoteToken[tokenIn];
+        bool isOutputQuote = isQuoteToken[tokenOut];
+        if (isInputQuote) {
+            uint fee = (amountToSwap * FEE_BASIS_POINTS) / FEE_DENOMINATOR;
+            amountToSwap -= fee;
+            if (fee > 0) IERC20(tokenIn).transfer(feeReceiver, fee);
+        }
+
+        IERC20(tokenIn).approve(v3Router, 0);
+        IERC20(tokenIn).approve(v3Router, amountToSwap);
+
+        address recipientLoc = isOutputQuote ? address(this) : recipient;
+        ISwapRouter.ExactInputParams memory params = ISwapRouter.ExactInputParams({
+            path: path, recipient: recipientLoc, deadline: deadline, amountIn: amountToSwap, amountOutMinimum: 0
+        });
+        amountOut = ISwapRouter(v3Router).exactInput(params);
+        if (isOutputQuote) {
+            uint fee = (amountOut * FEE_BASIS_POINTS) / FEE_DENOMINATOR;
+            uint amountUser = amountOut - fee;
+            if (amountUser < amountOutMin) revert InsufficientOutputAmount(amountOutMin, amountUser);
+            if (fee > 0) IERC20(tokenOut).transfer(feeReceiver, fee);
+            IERC20(tokenOut).transfer(recipient, amountUser);
+            amountOut = amountUser;
+        } else {
+            if (amountOut < amountOutMin) revert InsufficientOutputAmount(amountOutMin, amountOut);
+        }
+    }
+
     // --- V2 SWAP (STRICT ERC20 & Tax Supported) ---
 
     function _swapSupportingFeeOnTransferTokens(address[] memory path, address _to) internal {
@@ -0,0 +1,79 @@
+// Адреса между Python и ядром: разбор в любом регистре, проверка EIP-55, вывод в checksum формате
+use ethers::types::Address;
+use ethers::utils::to_checksum;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use serde_json::Value;
+use std::str::FromStr;
+
+/// Адрес в формате EIP-55 - так ядро отдаёт адреса во всех событиях
+pub fn checksum(address: &Address) -> String {
+    to_checksum(address, None)
+}
+
+/// 0x + 40 hex символов в любом регистре. Без 0x или другой длины - None (from_str принимает и без префикса)
+pub fn parse(value: &str) -> Option<Address> {
+    let value = value.trim();
+    let hex = value.strip_prefix("0x").or_else(|| value.strip_prefix("0X"))?;
+    if hex.len() != 40 { return None; }
+    Address::from_str(hex).ok()
+}
+
+/// Адрес с проверкой контрольной суммы: смешанный регистр значит, что адрес набирали с checksum,
+/// и несовпадение - опечатка. Целиком нижний или верхний регистр принимается как есть
+pub fn parse_checked(value: &str) -> Result<Address, String> {
+    let value = value.trim();
+    let Some(address) = parse(value) else {
+        return Err(format!("некорректный адрес {:?}", value));
+    };
+    let hex = &value[2..];
+    let mixed_case = hex.chars().any(|c| c.is_ascii_lowercase()) && hex.chars().any(|c| c.is_ascii_uppercase());
+    let checksummed = checksum(&address);
+    if mixed_case && checksummed[2..] != *hex {
+        return Err(format!("неверная контрольная сумма адреса {} (ожидается {}) - вероятна опечатка", value, checksummed));
+    }
+    Ok(address)
+}
+
+fn is_address_string(s: &str) -> bool {
+    s.len() == 42 && s.starts_with("0x") && s[2..].bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Все адреса события (значения и ключи объектов) - в checksum формат.
+/// Адрес - строка ровно из 0x и 40 hex: хэши (64 hex) и суммы в hex не затрагиваются,
+/// а смена регистра hex не меняет значения, даже если это не адрес
+pub fn normalize_event(value: &mut Value) {
+    match value {
+        Value::String(s) if is_address_string(s) => {
+            if let Some(a) = parse(s) { *s = checksum(&a); }
+        }
+        Value::Array(items) => items.iter_mut().for_each(normalize_event),
+        Value::Object(map) => {
+            if map.keys().any(|k| is_address_string(k)) {
+                let entries = std::mem::take(map);
+                for (k, mut v) in entries {
+                    normalize_event(&mut v);
+                    let key = if is_address_string(&k) { parse(&k).map_or(k, |a| checksum(&a)) } else { k };
+                    map.insert(key, v);
+                }
+            } else {
+                map.values_mut().for_each(normalize_event);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Адрес в формате EIP-55. Не 20 байт hex или неверная контрольная сумма смешанного регистра - ValueError
+#[pyfunction]
+pub fn to_checksum_address(address: String) -> PyResult<String> {
+    parse_checked(&address)
+        .map(|a| checksum(&a))
+        .map_err(PyValueError::new_err)
+}
+
+/// 0x + 40 hex; в смешанном регистре ещё и верная контрольная сумма EIP-55
+#[pyfunction]
+pub fn is_valid_address(address: String) -> bool {
+    parse_checked(&address).is_ok()
+}
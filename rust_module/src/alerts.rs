@@ -0,0 +1,40 @@
+use ethers::types::Address;
+use crate::state::CORE_STATE;
+use crate::bridge::{emit_event, emit_log, EngineEvent};
+
+/// Проверяет ценовые алерты пары token/quote на пересечение порога новой спотовой ценой.
+/// Сработавший алерт снимается, если у него не стоит флаг repeat.
+pub fn on_spot_price(token: Address, quote: Address, price: f64) {
+    if !price.is_finite() || price <= 0.0 { return; }
+
+    let mut triggered = Vec::new();
+    {
+        let mut s = CORE_STATE.write().unwrap();
+        s.price_alerts.retain_mut(|alert| {
+            if alert.token != token || alert.quote != quote { return true; }
+
+            let crossed = match alert.last_price.replace(price) {
+                Some(prev) => alert.direction.crossed(prev, price, alert.threshold),
+                None => false,
+            };
+            if !crossed { return true; }
+
+            triggered.push(alert.clone());
+            alert.repeat
+        });
+    }
+
+    for alert in triggered {
+        emit_log("SUCCESS", format!(
+            "🔔 Алерт {}: цена {:.10} пересекла {:.10} ({})",
+            alert.id, price, alert.threshold, alert.direction.as_str()
+        ));
+        emit_event(EngineEvent::PriceAlertTriggered {
+            id: alert.id,
+            token: format!("{:?}", alert.token),
+            price,
+            threshold: alert.threshold,
+            direction: alert.direction.as_str().to_string(),
+        });
+    }
+}
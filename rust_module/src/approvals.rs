@@ -0,0 +1,88 @@
+use ethers::prelude::*;
+use ethers::abi::{AbiDecode, AbiEncode};
+use ethers::utils::keccak256;
+use once_cell::sync::Lazy;
+use crate::state::EngineLocal;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::bridge::{emit_event, emit_log, EngineEvent};
+use crate::execution::{AllowanceCall, AllowanceReturn};
+use crate::rpc_batch::{self, BatchCall};
+use crate::state::RPC_POOL;
+
+// Дольше этого allowance из кэша не используется - перечитываем с ноды
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+type AllowanceKey = (Address, Address, Address); // (кошелёк, токен, spender)
+
+/// Ключ -> (allowance, когда прочитан)
+static ALLOWANCES: EngineLocal<Mutex<HashMap<AllowanceKey, (U256, Instant)>>> = EngineLocal::new(|| Mutex::new(HashMap::new()));
+
+static APPROVAL_TOPIC: Lazy<H256> = Lazy::new(|| H256::from(keccak256("Approval(address,address,uint256)")));
+
+pub fn is_unlimited(allowance: U256) -> bool {
+    allowance >= U256::MAX / 2
+}
+
+/// Свежий allowance из кэша, None - нет или устарел
+pub fn cached(wallet: Address, token: Address, spender: Address) -> Option<U256> {
+    ALLOWANCES.lock().unwrap().get(&(wallet, token, spender))
+        .filter(|(_, at)| at.elapsed() < CACHE_TTL)
+        .map(|(allowance, _)| *allowance)
+}
+
+pub fn store(wallet: Address, token: Address, spender: Address, allowance: U256) {
+    ALLOWANCES.lock().unwrap().insert((wallet, token, spender), (allowance, Instant::now()));
+}
+
+/// Продажа ушла по кэшу: роутер спишет amount через transferFrom (безлимитный allowance не меняется)
+pub fn consume(wallet: Address, token: Address, spender: Address, amount: U256) {
+    if let Some((allowance, _)) = ALLOWANCES.lock().unwrap().get_mut(&(wallet, token, spender)) {
+        if !is_unlimited(*allowance) {
+            *allowance = allowance.saturating_sub(amount);
+        }
+    }
+}
+
+pub fn invalidate(wallet: Address, token: Address) {
+    ALLOWANCES.lock().unwrap().retain(|(w, t, _), _| !(*w == wallet && *t == token));
+}
+
+/// Approval-логи подтверждённой tx (approve/revoke): allowance этих пар изменился
+pub fn on_receipt_logs(logs: &[Log]) {
+    for log in logs.iter().filter(|l| l.topics.len() == 3 && l.topics[0] == *APPROVAL_TOPIC) {
+        invalidate(Address::from(log.topics[1]), log.address);
+    }
+}
+
+/// allowance() всех пар кошелёк × токен одним батчем, результат - ApprovalStatus на пару
+pub async fn check(wallets: Vec<Address>, tokens: Vec<Address>, spender: Address) {
+    let url_opt = { RPC_POOL.read().unwrap().get_fastest_node() };
+    let Some(url) = url_opt else {
+        emit_log("ERROR", "❌ CheckApprovals: нет доступных RPC нод".into());
+        return;
+    };
+    let pairs: Vec<(Address, Address)> = wallets.iter()
+        .flat_map(|&wallet| tokens.iter().map(move |&token| (wallet, token)))
+        .collect();
+    let calls: Vec<BatchCall> = pairs.iter()
+        .map(|&(owner, token)| BatchCall::Call { to: token, data: AllowanceCall { owner, spender }.encode().into() })
+        .collect();
+    let results = rpc_batch::batch(&url, &calls).await;
+
+    for ((wallet, token), res) in pairs.into_iter().zip(&results) {
+        let Some(allowance) = rpc_batch::as_bytes(res).and_then(|b| AllowanceReturn::decode(&b).ok()).map(|r| r.0) else {
+            emit_log("DEBUG", format!("CheckApprovals {:?}/{:?}: {:?}", wallet, token, res));
+            continue;
+        };
+        store(wallet, token, spender, allowance);
+        emit_event(EngineEvent::ApprovalStatus {
+            wallet: format!("{:?}", wallet),
+            token: format!("{:?}", token),
+            spender: format!("{:?}", spender),
+            allowance_wei: allowance.to_string(),
+            is_unlimited: is_unlimited(allowance)
+        });
+    }
+}
@@ -0,0 +1,153 @@
+use crate::state::EngineLocal;
+use ethers::prelude::*;
+use ethers::abi::{AbiDecode, AbiEncode};
+use ethers::utils::id;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use crate::bridge::{emit_event, emit_log, EngineEvent};
+use crate::execution::{self, BalanceOfCall, BalanceOfReturn, TotalSupplyCall};
+use crate::holders;
+use crate::state::{fastest_http_provider, CORE_STATE};
+
+// Функции, наличие которых в байткоде - повод насторожиться: флаг -> сигнатуры
+const DANGEROUS_SELECTORS: [(&str, &[&str]); 5] = [
+    ("mint", &["mint(address,uint256)", "mint(uint256)", "mintTo(address,uint256)"]),
+    ("pause", &["pause()", "setPaused(bool)", "setTradingEnabled(bool)", "enableTrading(bool)"]),
+    ("tax_change", &["setTaxes(uint256,uint256)", "setFee(uint256)", "setFees(uint256,uint256)", "setTaxFeePercent(uint256)", "setBuyTax(uint256)", "setSellTax(uint256)"]),
+    ("blacklist", &["blacklist(address)", "addToBlacklist(address)", "setBlacklist(address,bool)", "setBots(address[],bool)", "addBots(address[])"]),
+    ("max_tx", &["setMaxTxAmount(uint256)", "setMaxTx(uint256)", "setMaxWalletSize(uint256)", "setMaxWallet(uint256)"]),
+];
+const UPGRADE_SELECTORS: [&str; 2] = ["upgradeTo(address)", "upgradeToAndCall(address,bytes)"];
+
+// bytes32(uint256(keccak256("eip1967.proxy.implementation")) - 1)
+const EIP1967_IMPLEMENTATION_SLOT: &str = "0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc";
+// Начало байткода минимального прокси EIP-1167
+const EIP1167_PREFIX: [u8; 10] = [0x36, 0x3d, 0x3d, 0x37, 0x3d, 0x3d, 0x3d, 0x36, 0x3d, 0x73];
+const PUSH4: u8 = 0x63;
+
+const DEAD_ADDRESS: &str = "0x000000000000000000000000000000000000dEaD";
+// LP считается заблокированной, если в локерах и на burn адресах не меньше этой доли
+const LP_LOCKED_MIN_PCT: f64 = 90.0;
+
+// Результаты за сессию: контракт не меняется, повторный AuditToken - из кэша
+static AUDITS: EngineLocal<Mutex<HashMap<Address, EngineEvent>>> = EngineLocal::new(|| Mutex::new(HashMap::new()));
+
+pub fn forget_all() {
+    AUDITS.lock().unwrap().clear();
+}
+
+/// Селектор функции встречается как аргумент PUSH4 в диспетчере
+fn has_selector(code: &[u8], signature: &str) -> bool {
+    let selector = &id(signature)[..4];
+    code.windows(5).any(|w| w[0] == PUSH4 && &w[1..] == selector)
+}
+
+async fn read_address(token: Address, signature: &str) -> Option<Address> {
+    let out = execution::read_call(token, Bytes::from(id(signature).to_vec())).await.ok()?;
+    (out.len() >= 32).then(|| Address::from_slice(&out[12..32]))
+}
+
+async fn read_u256(target: Address, data: Bytes) -> Option<U256> {
+    let out = execution::read_call(target, data).await.ok()?;
+    U256::decode(&out).ok()
+}
+
+/// Доля LP токенов пары в локерах и на burn адресе. None - пулов нет или это не V2 пары
+async fn lp_locked_pct(token: Address) -> Option<f64> {
+    let lockers: Vec<Address> = CORE_STATE.read().unwrap().lp_lockers.clone();
+    let dead = Address::from_str(DEAD_ADDRESS).ok()?;
+    let mut best: Option<f64> = None;
+    for pair in holders::lp_addresses(token).await {
+        // У V3 пулов нет totalSupply - такие пропускаем
+        let Some(supply) = read_u256(pair, TotalSupplyCall.encode().into()).await
+            .filter(|s| !s.is_zero()) else { continue; };
+        let mut locked = U256::zero();
+        for holder in lockers.iter().copied().chain([dead, Address::zero()]) {
+            let balance = execution::read_call(pair, BalanceOfCall { owner: holder }.encode().into()).await.ok()
+                .and_then(|out| BalanceOfReturn::decode(&out).ok())
+                .map(|r| r.0)
+                .unwrap_or_default();
+            locked = locked.saturating_add(balance);
+        }
+        let pct = execution::u256_to_f64_safe(locked, 0) / execution::u256_to_f64_safe(supply, 0) * 100.0;
+        // Решает самая крупная пара: берём максимум по парам
+        best = Some(best.map_or(pct, |b: f64| b.max(pct)));
+    }
+    best
+}
+
+/// Отчёт и признак того, что байткод прочитан (иначе в кэш не кладём)
+async fn run_audit(token: Address) -> (EngineEvent, bool) {
+    let mut flags = Vec::new();
+    let provider = fastest_http_provider();
+
+    let code = match &provider {
+        Some(p) => p.get_code(token, None).await.ok(),
+        None => None,
+    };
+    let mut proxy = false;
+    let code_read = code.is_some();
+    match code.as_ref().filter(|c| !c.is_empty()) {
+        Some(code) => {
+            for (flag, signatures) in DANGEROUS_SELECTORS {
+                if signatures.iter().any(|sig| has_selector(code, sig)) {
+                    flags.push(flag.to_string());
+                }
+            }
+            proxy = code.starts_with(&EIP1167_PREFIX) || UPGRADE_SELECTORS.iter().any(|sig| has_selector(code, sig));
+        }
+        None => flags.push("code_unavailable".into()),
+    }
+
+    // Прозрачный/UUPS прокси: реализация в слоте EIP-1967, селекторы выше - только от прокси
+    if let (Some(p), Ok(slot)) = (&provider, H256::from_str(EIP1967_IMPLEMENTATION_SLOT)) {
+        if let Ok(value) = p.get_storage_at(token, slot, None).await {
+            proxy |= !value.is_zero();
+        }
+    }
+    if proxy { flags.push("proxy".into()); }
+
+    let owner = match read_address(token, "owner()").await {
+        Some(o) => Some(o),
+        None => read_address(token, "getOwner()").await,
+    };
+    let dead = Address::from_str(DEAD_ADDRESS).unwrap_or_default();
+    let ownership_renounced = owner.is_some_and(|o| o.is_zero() || o == dead);
+    match owner {
+        None => flags.push("owner_unknown".into()),
+        Some(_) if !ownership_renounced => flags.push("owner_active".into()),
+        _ => {}
+    }
+
+    let lp_locked_pct = lp_locked_pct(token).await;
+    match lp_locked_pct {
+        Some(pct) if pct < LP_LOCKED_MIN_PCT => flags.push("lp_unlocked".into()),
+        None => flags.push("lp_unknown".into()),
+        _ => {}
+    }
+
+    (EngineEvent::TokenAudit {
+        token: format!("{:?}", token),
+        flags,
+        owner: owner.map(|o| format!("{:?}", o)),
+        ownership_renounced,
+        proxy,
+        lp_locked_pct
+    }, code_read)
+}
+
+/// Только чтение: байткод, owner, слот прокси и балансы LP. Повторный запрос - из кэша сессии
+pub async fn audit(token: Address) {
+    let cached = AUDITS.lock().unwrap().get(&token).cloned();
+    if let Some(report) = cached {
+        emit_event(report);
+        return;
+    }
+    emit_log("INFO", format!("🛡️ Проверка контракта {:?}...", token));
+    let (report, code_read) = run_audit(token).await;
+    if code_read {
+        AUDITS.lock().unwrap().insert(token, report.clone());
+    }
+    emit_event(report);
+}
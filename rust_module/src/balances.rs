@@ -0,0 +1,48 @@
+// Последние балансы кошельков и фильтр пыли для BalanceUpdate: игнор-лист токенов (IgnoreToken)
+// и пороги min_balance_usd (по известной цене) / min_balance_wei (на токен).
+// Отфильтрованное событие не уходит в Python, но баланс записан - GetState его видит
+use crate::state::CORE_STATE;
+use crate::state::app::BotState;
+use crate::address;
+use crate::monitor;
+use ethers::types::{Address, U256};
+
+/// Адрес native в BalanceUpdate и в min_balance_wei
+pub const NATIVE: &str = "0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee";
+
+pub fn native() -> Address {
+    address::parse(NATIVE).unwrap()
+}
+
+/// USD стоимость баланса: native и quote - по своей цене, подписанный токен - через spot цену в quote.
+/// None - цены нет, USD порог к токену не применяется
+fn usd_value(s: &BotState, token: Address, amount: f64) -> Option<f64> {
+    let priced = if token == native() { s.wrapped_native_address } else { token };
+    if let Some(price) = monitor::fresh_quote_price_usd(&monitor::quote_symbol_in(s, priced), s) {
+        return Some(amount * price);
+    }
+    let ctx = s.tokens.get(&token).filter(|c| c.spot_price > 0.0)?;
+    let quote_price = monitor::fresh_quote_price_usd(&monitor::quote_symbol_in(s, ctx.quote), s)?;
+    Some(amount * ctx.spot_price * quote_price)
+}
+
+fn is_dust(s: &BotState, token: Address, wei: U256, amount: f64) -> bool {
+    if s.min_balance_wei.get(&token).is_some_and(|min| wei < *min) { return true; }
+    s.min_balance_usd > 0.0 && usd_value(s, token, amount).is_some_and(|usd| usd < s.min_balance_usd)
+}
+
+/// Записывает баланс; false - BalanceUpdate не отправляется (игнорируемый токен или пыль).
+/// Падение до пыли с видимого баланса отправляется - иначе UI остался бы со старым значением
+pub fn admit(wallet: &str, token: &str, wei: &str, amount: f64) -> bool {
+    let (Some(w), Some(t), Ok(wei)) = (address::parse(wallet), address::parse(token), U256::from_dec_str(wei)) else { return true; };
+    let mut s = CORE_STATE.write().unwrap();
+    let prev = s.balances.insert((w, t), (wei, amount));
+    if s.ignored_tokens.contains(&t) { return false; }
+    if !is_dust(&s, t, wei, amount) { return true; }
+    prev.is_some_and(|(prev_wei, prev_amount)| !is_dust(&s, t, prev_wei, prev_amount))
+}
+
+/// Transfer игнорируемого токена не запрашивает balanceOf
+pub fn is_ignored(token: Address) -> bool {
+    CORE_STATE.read().unwrap().ignored_tokens.contains(&token)
+}
@@ -1,20 +1,60 @@
 pub mod models;
 pub mod transport;
 
+use std::collections::HashSet;
 use std::sync::RwLock;
-use once_cell::sync::Lazy;
+use crate::state::{EngineId, EngineLocal, DEFAULT_ENGINE, current_engine, engine_exists, engine_ids, with_engine};
 use pyo3::prelude::*;
-pub use models::{EngineEvent, EngineCommand};
-use transport::{send_to_python, BRIDGE_QUEUE};
+pub use models::{EngineEvent, EngineCommand, EngineRequest, GasOracleInfo, PoolCandidateInfo, EngineStateSnapshot, TokenSubscriptionInfo, RpcNodeInfo, NotifySettings, PriceFeedSettings, MonitorSettings, MonitorConfigInfo, ChainQuirksSettings, CandleInfo, PortfolioQuotePnl, PortfolioTokenPnl, AppliedTradeParams, TwapParams};
+use transport::{send_to_python, Priority};
 
 // ===================== КЭШ ДЕДУПЛИКАЦИИ =====================
 // Каждый тип события хранит только ОДИН предыдущий кадр
 
-static LAST_BALANCE: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
-static LAST_POOL: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
-static LAST_GAS: Lazy<RwLock<Option<u64>>> = Lazy::new(|| RwLock::new(None));
-static LAST_CONN: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
-static LAST_IMPACT: Lazy<RwLock<Option<u64>>> = Lazy::new(|| RwLock::new(None));
+static LAST_BALANCE: EngineLocal<RwLock<Option<String>>> = EngineLocal::new(|| RwLock::new(None));
+static LAST_POOL: EngineLocal<RwLock<Option<String>>> = EngineLocal::new(|| RwLock::new(None));
+static LAST_GAS: EngineLocal<RwLock<Option<(u64, u64)>>> = EngineLocal::new(|| RwLock::new(None));
+static LAST_CONN: EngineLocal<RwLock<Option<String>>> = EngineLocal::new(|| RwLock::new(None));
+// (token, is_buy, биты amount_in, биты impact_pct)
+type ImpactFrame = (String, bool, u64, u64);
+static LAST_IMPACT: EngineLocal<RwLock<Option<ImpactFrame>>> = EngineLocal::new(|| RwLock::new(None));
+
+/// Новая сессия ядра (Init): кадры прошлой сети не должны глушить первые события новой как дубликаты
+pub fn reset_session() {
+    *LAST_BALANCE.write().unwrap() = None;
+    *LAST_POOL.write().unwrap() = None;
+    *LAST_GAS.write().unwrap() = None;
+    *LAST_CONN.write().unwrap() = None;
+    *LAST_IMPACT.write().unwrap() = None;
+    transport::reset_stats();
+}
+
+// ===================== ФИЛЬТР СОБЫТИЙ =====================
+// Пустой список типов - фильтр выключен, в Python уходит всё. У каждого движка свой фильтр
+
+struct EventFilter {
+    allow: bool, // true - пропускаем только types, false - всё кроме types
+    types: HashSet<String>,
+}
+
+impl EventFilter {
+    fn passes(&self, kind: &str) -> bool {
+        self.types.is_empty() || self.types.contains(kind) == self.allow
+    }
+}
+
+static EVENT_FILTER: EngineLocal<RwLock<EventFilter>> = EngineLocal::new(|| RwLock::new(EventFilter { allow: true, types: HashSet::new() }));
+
+// ===================== CORRELATION ID =====================
+
+tokio::task_local! {
+    /// request_id команды, в рамках которой выполняется текущая задача
+    pub static REQUEST_ID: Option<String>;
+}
+
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok().flatten()
+}
 
 // ===================== PYO3 ФУНКЦИИ =====================
 
@@ -23,19 +63,96 @@ pub fn init_bridge_signal(_py: Python<'_>, socket_fd: u64) -> PyResult<()> {
     transport::set_signal_socket(socket_fd).map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e))
 }
 
-#[pyfunction]
-pub fn pop_from_bridge(_py: Python<'_>) -> PyResult<Option<String>> {
-    match BRIDGE_QUEUE.1.try_recv() {
-        Ok(json_str) => Ok(Some(json_str)),
-        Err(_) => Ok(None),
+/// engine_id из Python: None - движок по умолчанию, неизвестный id - ошибка
+pub(crate) fn resolve_engine(engine_id: Option<EngineId>) -> PyResult<EngineId> {
+    let id = engine_id.unwrap_or(DEFAULT_ENGINE);
+    if !engine_exists(id) {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!("Unknown engine_id: {}", id)));
+    }
+    Ok(id)
+}
+
+/// До max_items событий: из одного движка или (engine_id=None) из всех по очереди, начиная с движка по умолчанию.
+/// Движок события - поле engine_id в конверте
+fn pop_events(max_items: usize, engine_id: Option<EngineId>) -> PyResult<Vec<String>> {
+    if engine_id.is_some() {
+        let id = resolve_engine(engine_id)?;
+        return Ok(with_engine(id, || transport::pop_batch(max_items)));
+    }
+    let mut out = Vec::new();
+    for id in engine_ids() {
+        if out.len() >= max_items { break; }
+        let left = max_items - out.len();
+        out.extend(with_engine(id, || transport::pop_batch(left)));
     }
+    Ok(out)
+}
+
+#[pyfunction]
+#[pyo3(signature = (engine_id=None))]
+pub fn pop_from_bridge(_py: Python<'_>, engine_id: Option<EngineId>) -> PyResult<Option<String>> {
+    Ok(pop_events(1, engine_id)?.pop())
+}
+
+/// Забрать до max_items событий за один вызов (меньше переходов Python <-> Rust)
+#[pyfunction]
+#[pyo3(signature = (max_items, engine_id=None))]
+pub fn pop_batch_from_bridge(_py: Python<'_>, max_items: usize, engine_id: Option<EngineId>) -> PyResult<Vec<String>> {
+    pop_events(max_items, engine_id)
+}
+
+/// Список типов событий для фильтра (пустой список - без фильтрации)
+#[pyfunction]
+#[pyo3(signature = (types, engine_id=None))]
+pub fn set_event_filter(_py: Python<'_>, types: Vec<String>, engine_id: Option<EngineId>) -> PyResult<()> {
+    let id = resolve_engine(engine_id)?;
+    with_engine(id, || set_filter_types(types));
+    Ok(())
+}
+
+/// "allow" - пропускать только типы из фильтра, "deny" - всё кроме них
+#[pyfunction]
+#[pyo3(signature = (mode, engine_id=None))]
+pub fn set_event_filter_mode(_py: Python<'_>, mode: String, engine_id: Option<EngineId>) -> PyResult<()> {
+    let allow = match mode.to_lowercase().as_str() {
+        "allow" => true,
+        "deny" => false,
+        _ => return Err(pyo3::exceptions::PyValueError::new_err(format!("Unknown filter mode: {}", mode))),
+    };
+    let id = resolve_engine(engine_id)?;
+    with_engine(id, || set_filter_mode(allow));
+    Ok(())
+}
+
+/// (режим, типы) текущего фильтра движка
+#[pyfunction]
+#[pyo3(signature = (engine_id=None))]
+pub fn get_event_filter(_py: Python<'_>, engine_id: Option<EngineId>) -> PyResult<(String, Vec<String>)> {
+    let id = resolve_engine(engine_id)?;
+    Ok(with_engine(id, event_filter))
+}
+
+fn event_filter() -> (String, Vec<String>) {
+    let f = EVENT_FILTER.read().unwrap();
+    let mut types: Vec<String> = f.types.iter().cloned().collect();
+    types.sort();
+    (if f.allow { "allow" } else { "deny" }.to_string(), types)
+}
+
+pub(crate) fn set_filter_types(types: Vec<String>) {
+    EVENT_FILTER.write().unwrap().types = types.into_iter().collect();
+}
+
+pub(crate) fn set_filter_mode(allow: bool) {
+    EVENT_FILTER.write().unwrap().allow = allow;
 }
 
 // ----- ПРОБРОС И ДЕДУПЛИКАЦИЯ ИВЕНТОВ В ПАЙТОН -----
 
 pub fn emit_event(event: EngineEvent) {
     let should_send = match &event {
-        EngineEvent::BalanceUpdate { wei, .. } => {
+        EngineEvent::BalanceUpdate { wallet, token, wei, float_val, .. } => {
+            if !crate::balances::admit(wallet, token, wei, *float_val) { return; }
             let mut cache = LAST_BALANCE.write().unwrap();
             match cache.as_ref() {
                 Some(prev) if prev == wei => false,
@@ -57,16 +174,18 @@ pub fn emit_event(event: EngineEvent) {
             }
         }
 
-        EngineEvent::GasPriceUpdate { gas_price_gwei } => {
+        EngineEvent::GasPriceUpdate { gas_price_gwei, oracle } => {
+            let current = (gas_price_gwei.to_bits(), oracle.as_ref().map(|o| o.standard_gwei.to_bits()).unwrap_or(0));
             let mut cache = LAST_GAS.write().unwrap();
             match *cache {
-                Some(prev) if prev == gas_price_gwei.to_bits() => false,
-                _ => { *cache = Some(gas_price_gwei.to_bits()); true }
+                Some(prev) if prev == current => false,
+                _ => { *cache = Some(current); true }
             }
         }
 
-        EngineEvent::ConnectionStatus { connected, message } => {
-            let current = format!("{}:{}", connected, message);
+        EngineEvent::ConnectionStatus { connected, message, polling } => {
+            crate::state::CONNECTED.store(*connected, std::sync::atomic::Ordering::Relaxed);
+            let current = format!("{}:{}:{}", connected, polling, message);
             let mut cache = LAST_CONN.write().unwrap();
             match cache.as_ref() {
                 Some(prev) if prev == &current => false,
@@ -74,26 +193,66 @@ pub fn emit_event(event: EngineEvent) {
             }
         }
 
-        EngineEvent::ImpactUpdate { impact_pct, .. } => {
+        EngineEvent::ImpactUpdate { token, is_buy, amount_in, impact_pct, .. } => {
+            // Та же сумма с тем же impact - повтор; другая сумма отвечается даже при равном impact
+            let current = (token.clone(), *is_buy, amount_in.to_bits(), impact_pct.to_bits());
             let mut cache = LAST_IMPACT.write().unwrap();
-            match *cache {
-                Some(prev) if prev == impact_pct.to_bits() => false,
-                _ => { *cache = Some(impact_pct.to_bits()); true }
+            match cache.as_ref() {
+                Some(prev) if prev == &current => false,
+                _ => { *cache = Some(current); true }
             }
         }
 
         _ => true,
     };
 
-    if should_send {
-        if let Ok(json) = serde_json::to_string(&event) {
-            send_to_python(json);
+    // Ответ на конкретную команду не дедуплицируем и не выкидываем - Python может его ждать
+    let request_id = current_request_id();
+    crate::journal::record(&event, request_id.as_deref());
+    if should_send || request_id.is_some() {
+        let (priority, coalesce_key) = match &event {
+            _ if request_id.is_some() => (Priority::Critical, None),
+            EngineEvent::TradeStatus { .. } | EngineEvent::TxSent { .. } | EngineEvent::TxConfirmed { .. }
+                | EngineEvent::TxDropped { .. } | EngineEvent::TxStuck { .. } | EngineEvent::CommandError { .. } => (Priority::Critical, None),
+            EngineEvent::Log { level, .. } if level == "ERROR" => (Priority::Critical, None),
+            EngineEvent::PoolUpdate { pool_address, .. } => (Priority::Low, Some(format!("PoolUpdate:{}", pool_address))),
+            EngineEvent::GasPriceUpdate { .. } => (Priority::Low, Some("GasPriceUpdate".to_string())),
+            EngineEvent::TradeTape { .. } => (Priority::Low, None),
+            EngineEvent::PnLUpdate { wallet, token, .. } => (Priority::Low, Some(format!("PnLUpdate:{}:{}", wallet, token))),
+            EngineEvent::UsdPriceUpdate { symbol, .. } => (Priority::Low, Some(format!("UsdPriceUpdate:{}", symbol))),
+            EngineEvent::ImpactUpdate { token, is_buy, .. } => (Priority::Low, Some(format!("ImpactUpdate:{}:{}", token, is_buy))),
+            _ => (Priority::Normal, None),
+        };
+        let Ok(mut value) = serde_json::to_value(&event) else { return; };
+        crate::address::normalize_event(&mut value);
+        let kind = value["type"].as_str().unwrap_or_default().to_string();
+        crate::notifier::offer(&kind, &value);
+        // Кэши дедупликации выше уже обновлены - включение типа позже не выдаст старый кадр за новый
+        if !EVENT_FILTER.read().unwrap().passes(&kind) { return; }
+        if let Some(id) = request_id {
+            value["request_id"] = serde_json::Value::String(id);
         }
+        // Один потребитель в Python разбирает события всех движков по этому полю
+        value["engine_id"] = serde_json::Value::from(current_engine());
+        crate::metrics::inc(&crate::metrics::METRICS.events_sent);
+        let mut json = value.to_string();
+        // Подписаны ровно байты события без sig: получатель отрезает хвост ,"sig":"..." и проверяет остаток
+        if let Some(sig) = crate::crypto::event_signature(&json) {
+            json.pop();
+            json.push_str(&format!(",\"sig\":\"{}\"}}", sig));
+        }
+        send_to_python(kind, priority, coalesce_key, json);
     }
 }
 
 // ----- ПРОБРОС ЛОГОВ В ПАЙТОН -----
 
+/// Периодический отчёт о состоянии очереди моста (отстаёт ли UI)
+pub fn emit_bridge_stats() {
+    let (queued, dropped_by_type) = transport::stats();
+    emit_event(EngineEvent::BridgeStats { queued, dropped_by_type });
+}
+
 pub const DEBUG_MODE: bool = false;
 
 pub fn emit_log(level: &str, message: String) {
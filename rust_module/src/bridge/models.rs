@@ -25,11 +25,14 @@ pub enum EngineEvent {
         address: String, 
         token: String, 
         quote: String,
+        quote_symbol: String,
         liquidity_usd: f64, 
         fee: u32, 
         spot_price: f64,
         token_symbol: String,
-        token_name: String
+        token_name: String,
+        route: Vec<String>,
+        via: Option<String>
     },
 
     PoolUpdate { 
@@ -37,6 +40,7 @@ pub enum EngineEvent {
         pool_type: String, 
         token: String, 
         quote: String,
+        quote_symbol: String,
         reserve0: Option<String>, 
         reserve1: Option<String>, 
         sqrt_price_x96: Option<String>, 
@@ -68,7 +72,8 @@ pub enum EngineEvent {
         amount_in: f64, 
         impact_pct: f64, 
         expected_out: String, 
-        is_buy: bool 
+        is_buy: bool,
+        is_estimate: bool // локальная оценка с пересечением тика (quoter недоступен) - в UI "~"
     },
 
     TradeStatus { 
@@ -81,12 +86,21 @@ pub enum EngineEvent {
         amount: f64,
         tokens_received: Option<String>,
         tokens_sold: Option<String>,
-        token_decimals: u8
+        token_decimals: u8,
+        slice_index: Option<u32>,
+        leg: Option<String>,
+        gas_limit: Option<u64>,     // лимит в подписанной tx
+        gas_estimated: Option<u64>, // сырой estimate_gas (None - взят fallback)
+        applied: Option<AppliedTradeParams>, // фактические slippage/газ/дедлайн свапа
+        dry_run: bool // бумажная сделка: ничего не отправлялось в сеть
     },
 
-    GasPriceUpdate { gas_price_gwei: f64 },
+    GasPriceUpdate { 
+        gas_price_gwei: f64,
+        oracle: Option<GasOracleInfo> // только EIP-1559 сети
+    },
 
-    ConnectionStatus { connected: bool, message: String },
+    ConnectionStatus { connected: bool, message: String, polling: bool }, // polling - WSS недоступен, данные идут HTTP опросом
 
     TxSent { 
         tx_hash: String, 
@@ -94,7 +108,8 @@ pub enum EngineEvent {
         action: String, 
         amount: f64, 
         token: String, 
-        timestamp_ms: u64 
+        timestamp_ms: u64,
+        route: String // "public" | "private" | "both" - куда tx дошла
     },
 
     TxConfirmed { 
@@ -103,15 +118,492 @@ pub enum EngineEvent {
         gas_used: u64, 
         status: String, 
         confirm_block: u64, 
-        timestamp_ms: u64 
+        timestamp_ms: u64,
+        gas_limit: Option<u64>,
+        gas_estimated: Option<u64>,
+        effective_gas_price_gwei: f64, // из receipt, без effectiveGasPrice - цена газа при отправке
+        fee_wei: String,               // gas_used × effective_gas_price
+        fee_native_float: f64,
+        fee_usd: Option<f64>           // None - нет свежей USD цены нативной монеты
+    },
+
+    // Pending tx без receipt дольше таймаута, и нода её уже не знает (вытеснена, nonce занят извне) - снята с учёта
+    TxDropped {
+        tx_hash: String,
+        wallet: String,
+        age_secs: u64
+    },
+
+    // Pending tx дольше таймаута висит в мемпуле: ускорить - повторить с тем же nonce и большим газом
+    TxStuck {
+        tx_hash: String,
+        wallet: String,
+        nonce: u64,
+        age_secs: u64,
+        gas_price_gwei: f64
     },
 
     AutoFuelError {
         wallet: String,
         reason: String
+    },
+
+    /// Перед подтверждённым свапом в том же блоке - крупный свап того же направления
+    SandwichSuspected {
+        tx_hash: String,
+        wallet: String,
+        token: String,
+        pool: String,
+        block: u64,
+        frontrun_tx: String,
+        frontrun_amount_in: String,
+        backrun_tx: Option<String>
+    },
+
+    LiquidityAlert {
+        pool: String,
+        old_usd: f64,
+        new_usd: f64,
+        drop_pct: f64
+    },
+
+    PriceAlertTriggered {
+        id: String,
+        token: String,
+        price: f64,
+        threshold: f64,
+        direction: String
+    },
+
+    LimitOrderFilled {
+        id: String,
+        tx_hashes: Vec<String>
+    },
+
+    LimitOrderExpired { id: String },
+
+    BundlePrepared {
+        id: String,
+        action: String,
+        token: String,
+        nonces: std::collections::HashMap<String, u64>, // кошелёк -> зарезервированный nonce
+        expires_at: u64 // unix сек, дедлайн свапов
+    },
+
+    BundleFired {
+        id: String,
+        sent: usize,
+        failed: usize,
+        elapsed_ms: u64
+    },
+
+    /// Бандл снят без рассылки: nonce/газ изменились, дедлайн истёк или DiscardBundle
+    BundleInvalidated { id: String, reason: String },
+
+    SnipeArmed {
+        token: String,
+        quote: String,
+        wallets: usize,
+        expires_at: u64 // unix сек, после - авто-снятие
+    },
+
+    SnipeFired {
+        token: String,
+        pool: String,
+        liquidity_usd: f64,
+        buy_tax_pct: Option<f64> // None - симуляция недоступна (не wrapped native quote / V3)
+    },
+
+    ApprovalStatus {
+        wallet: String,
+        token: String,
+        spender: String,
+        allowance_wei: String,
+        is_unlimited: bool
+    },
+
+    /// Снайп снят без рассылки: таймаут, налог выше лимита, ошибка подписи или DisarmSnipe
+    SnipeAborted { token: String, reason: String },
+
+    ExitTriggered {
+        wallet: String,
+        token: String,
+        reason: String,
+        pnl_pct: f64,
+        peak_pnl_pct: f64,
+        current_value: f64
+    },
+
+    ScheduledTradeExecuted {
+        id: String,
+        execution_number: u32,
+        tx_hashes: Vec<String>
+    },
+
+    SweepSummary {
+        token: String, // 0xeee... - native
+        destination: String,
+        swept: u32,
+        skipped: u32,
+        failed: u32,
+        total_moved: f64
+    },
+
+    RouteReport {
+        token: String,
+        action: String,
+        split: bool,
+        primary_pool: Option<String>,
+        primary_pct: f64,
+        secondary_pool: Option<String>,
+        secondary_pct: f64,
+        improvement_bps: f64
+    },
+
+    TwapCompleted {
+        token: String,
+        action: String,
+        slices_total: u32,
+        slices_done: u32,
+        avg_price: f64,
+        aborted: bool,
+        reason: Option<String>
+    },
+
+    PoolCandidates {
+        token: String,
+        quote: String,
+        pools: Vec<PoolCandidateInfo>
+    },
+
+    PoolSwitched {
+        old_address: String,
+        new_address: String,
+        reason: String
+    },
+
+    // Снимок CORE_STATE для отладки (приватные ключи не включаются никогда)
+    StateSnapshot(Box<EngineStateSnapshot>),
+
+    // Kill-switch торговли (для баннера в TUI)
+    TradingPaused,
+    TradingResumed,
+
+    // Ответ на GetTokenInfo. total_supply - в wei, строкой
+    TokenInfo {
+        address: String,
+        symbol: String,
+        name: String,
+        decimals: u8,
+        total_supply: String
+    },
+
+    // eth_chainId ноды не совпал с chain_id сети - нода выведена из пула навсегда
+    RpcNodeQuarantined {
+        url: String,
+        reason: String
+    },
+
+    // Команда отклонена: не разобран JSON, неверное поле или паника обработчика (request_id - если удалось достать из JSON).
+    // command - тип команды, field - поле data с неверным значением, если известны
+    CommandError {
+        message: String,
+        command: Option<String>,
+        field: Option<String>
+    },
+
+    BridgeStats {
+        queued: usize,
+        dropped_by_type: std::collections::HashMap<String, u64>
+    },
+
+    // Цена от фида ядра. age_secs - возраст у источника (для Chainlink - с последнего раунда)
+    UsdPriceUpdate {
+        symbol: String,
+        price: f64,
+        source: String,
+        age_secs: u64
+    },
+
+    // Закрытая свеча пула (цены в quote). Интервал без сделок - плоская свеча по прошлому close
+    Candle {
+        pool: String,
+        interval: u64,
+        open: f64,
+        high: f64,
+        low: f64,
+        close: f64,
+        volume_quote: f64,
+        ts: u64
+    },
+
+    // Сделка выбранного пула токена. maker - получатель свапа, is_own - сделка наших кошельков
+    TradeTape {
+        pool: String,
+        token: String,
+        side: String, // buy | sell относительно токена
+        token_amount: f64,
+        quote_amount: f64,
+        usd_value: f64,
+        maker: String,
+        tx_hash: String,
+        ts: u64,
+        is_own: bool,
+        // Имя maker из реестра (ens_reverse_lookup); без реестра поля нет
+        #[serde(skip_serializing_if = "Option::is_none")]
+        maker_name: Option<String>
+    },
+
+    // Наблюдаемый адрес двигал подписанный токен: buy/sell - через пул токена, in/out - прямой перевод
+    WatchedAddressActivity {
+        label: String,
+        address: String,
+        direction: String,
+        token: String,
+        amount: f64,
+        tx_hash: String,
+        // Имя адреса из реестра (ens_reverse_lookup); без реестра поля нет
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>
+    },
+
+    // Прогресс AnalyzeHolders (не чаще раза в 2 секунды)
+    HolderScanProgress {
+        token: String,
+        scanned_blocks: u64,
+        total_blocks: u64,
+        transfers: usize
+    },
+
+    // Прогресс ImportHistory (не чаще раза в 2 секунды); итог - PositionUpdate
+    HistoryImportProgress {
+        wallet: String,
+        token: String,
+        scanned_blocks: u64,
+        total_blocks: u64,
+        transfers: usize
+    },
+
+    // Держатели токена по Transfer логам. top_holders: (адрес, баланс, % supply) без пулов и burn адресов.
+    // complete = false - скан упёрся в лимит блоков, балансы старых держателей неточные
+    HolderReport {
+        token: String,
+        holder_count: usize,
+        top_holders: Vec<(String, f64, f64)>,
+        lp_pct: f64,
+        burned_pct: f64,
+        from_block: u64,
+        complete: bool,
+        transfers: usize
+    },
+
+    // Проверка контракта. flags: mint, pause, tax_change, blacklist, max_tx, proxy, owner_active, lp_unlocked;
+    // *_unknown / code_unavailable - вызов не удался, проверка неполная
+    TokenAudit {
+        token: String,
+        flags: Vec<String>,
+        owner: Option<String>,
+        ownership_renounced: bool,
+        proxy: bool,
+        lp_locked_pct: Option<f64>
+    },
+
+    // Ответ на GetCandles: закрытые свечи, старые первыми
+    Candles {
+        pool: String,
+        interval: u64,
+        candles: Vec<CandleInfo>
+    },
+
+    // Позиция ядра изменилась (подтверждённый свап). avg_cost - quote за токен,
+    // unrealized_pnl - по последней оценке PnL воркера (None - ещё не оценена)
+    PositionUpdate {
+        wallet: String,
+        token: String,
+        quote: String,
+        quantity: f64,
+        avg_cost: f64,
+        realized_pnl: f64,
+        unrealized_pnl: Option<f64>,
+        simulated: bool
+    },
+
+    // Сводка по всем оцениваемым позициям, по quote (разные quote не складываются).
+    // Итог в USD; pending - позиции, ещё не оценённые (в суммы не входят)
+    PortfolioPnL {
+        quotes: Vec<PortfolioQuotePnl>,
+        cost_basis_usd: f64,
+        current_value_usd: f64,
+        pnl_pct: f64,
+        gas_cost_usd: f64,
+        net_pnl_pct: f64,
+        pending: usize
+    },
+
+    // Ответ на ReloadNetworkConfig: changed - применено на лету,
+    // requires_reinit - отличается от файла, но вступит в силу только после нового Init
+    ConfigReloaded {
+        network: String,
+        changed: Vec<String>,
+        requires_reinit: Vec<String>
+    },
+
+    // Ответ на RestoreSession: сколько восстановлено; dropped_txs - pending tx, которых нода уже не знает
+    SessionRestored {
+        pending_txs: usize,
+        dropped_txs: usize,
+        positions: usize,
+        limit_orders: usize,
+        recurring_trades: usize
     }
 }
 
+/// Параметры, с которыми реально подписан свап (после подстановки глобальных значений)
+#[derive(Serialize, Clone, Debug)]
+pub struct AppliedTradeParams {
+    pub slippage: f64,
+    pub gas_gwei: f64,
+    pub deadline_secs: u64,
+    pub auto_slippage: bool,
+    pub impact_pct: Option<f64>, // auto: impact сделки, из которого посчитан slippage
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct GasOracleInfo {
+    pub base_fee_gwei: f64,
+    pub base_fee_trend_pct: f64,
+    pub priority_p25_gwei: f64,
+    pub priority_p50_gwei: f64,
+    pub priority_p75_gwei: f64,
+    pub priority_p95_gwei: f64,
+    pub slow_gwei: f64,
+    pub standard_gwei: f64,
+    pub fast_gwei: f64,
+}
+
+/// Свеча в ответе GetCandles. ts - начало интервала, unix секунды
+#[derive(Serialize, Clone, Debug)]
+pub struct CandleInfo {
+    pub ts: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume_quote: f64,
+}
+
+/// Позиции одного токена в сводке портфеля (суммы по кошелькам, в quote)
+#[derive(Serialize, Clone, Debug)]
+pub struct PortfolioTokenPnl {
+    pub token: String,
+    pub positions: usize,
+    pub cost_basis: f64,
+    pub current_value: f64,
+    pub pnl_pct: f64,
+    pub gas_cost: f64, // газ позиций в quote (approve/auto-fuel - по флагам)
+    pub net_pnl_pct: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PortfolioQuotePnl {
+    pub quote: String,
+    pub quote_symbol: String,
+    pub cost_basis: f64,
+    pub current_value: f64,
+    pub pnl_pct: f64,
+    pub gas_cost: f64,
+    pub net_pnl_pct: f64,
+    pub tokens: Vec<PortfolioTokenPnl>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PoolCandidateInfo {
+    pub address: String,
+    pub pool_type: String,
+    pub fee: u32,
+    pub liquidity_usd: f64,
+    pub spot_price: f64,
+    pub score: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct EngineStateSnapshot {
+    pub chain_id: u64,
+    pub router: String,
+    pub quoter: String,
+    pub v2_factory: String,
+    pub v3_factory: String,
+    pub wrapped_native: String,
+    pub gas_price_gwei: f64,
+    pub manual_gas_price_gwei: f64,
+    pub slippage: f64,
+    pub swap_deadline_secs: u64,
+    pub v2_pool_fee_bps: u32,
+    pub router_tax_bps: u32,
+    pub trading_paused: bool,
+    pub dry_run: bool,
+    pub fuel_enabled: bool,
+    pub fuel_threshold: String,
+    pub fuel_amount: String,
+    pub fuel_quote_address: String,
+    pub fuel_max_attempts: u32,
+    pub fuel_cooldown_secs: u64,
+    pub fuel_quarantine_secs: u64,
+    pub fuel_quarantined: Vec<String>, // кошельки в карантине auto-fuel
+    pub fuel_mode: String,
+    pub fuel_treasury: Option<String>,
+    pub min_native_reserve_wei: String,
+    pub nonces: std::collections::HashMap<String, u64>,
+    pub pending_txs: usize,
+    pub tracked_wallets: Vec<String>,
+    pub quote_tokens: std::collections::HashMap<String, String>,
+    pub subscriptions: Vec<TokenSubscriptionInfo>,
+    pub max_token_subscriptions: usize,
+    pub rpc_nodes: Vec<RpcNodeInfo>,
+    pub decimals_cached: usize,
+    pub v2_pools_cached: usize,
+    pub v3_pools_cached: usize,
+    pub price_alerts: usize,
+    pub limit_orders: usize,
+    pub exit_strategies: usize,
+    pub recurring_trades: usize,
+    pub monitor: MonitorConfigInfo,
+    pub chain_quirks: ChainQuirksSettings,
+    pub balances: std::collections::HashMap<String, std::collections::HashMap<String, String>>, // кошелёк -> токен -> wei, с пылью
+    pub ignored_tokens: Vec<String>,
+    pub min_balance_usd: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct TokenSubscriptionInfo {
+    pub token: String,
+    pub quote: String,
+    pub quote_symbol: String,
+    pub pool_address: Option<String>,
+    pub pool_type: Option<String>,
+    pub pool_fee: u32,
+    pub liquidity_usd: f64,
+    pub spot_price: f64,
+    pub pinned_pool: Option<String>,
+    pub route: Vec<String>,
+    pub pool_targets: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RpcNodeInfo {
+    pub url: String,
+    pub latency_us: Option<u64>, // None - нода ещё не измерена
+    pub fails: u32,
+    pub is_private: bool,
+    pub quarantined: bool,
+    pub block: u64,
+    pub lag: u64,
+    pub pinned: bool,
+    pub requests: u64,
+    pub rate_limit_rps: Option<f64>,
+    pub penalty_ms: u64, // сколько ещё нода отдыхает после 429
+}
+
 // ===================== AUTO-FUEL SETTINGS =====================
 
 #[derive(Deserialize, Debug, Clone)]
@@ -124,11 +616,140 @@ pub struct AutoFuelSettingsData {
     pub auto_fuel_amount: f64,
     #[serde(default)]
     pub fuel_quote_address: String,
+    #[serde(default)]
+    pub max_attempts: Option<u32>, // неудачных попыток подряд до карантина (по умолчанию 5)
+    #[serde(default)]
+    pub cooldown_secs: Option<u64>, // между попытками (60)
+    #[serde(default)]
+    pub quarantine_secs: Option<u64>, // пауза после исчерпания попыток (1800)
+    #[serde(default)]
+    pub fuel_mode: Option<String>, // "swap" (по умолчанию) | "treasury"
+    #[serde(default)]
+    pub treasury_wallet: Option<String>, // treasury: откуда переводить нативную монету
+    #[serde(default)]
+    pub treasury_reserve: Option<f64>, // treasury: неприкосновенный остаток казны (по умолчанию - порог)
+}
+
+// ===================== NOTIFIER =====================
+
+/// Уведомления во внешние каналы. В UpdateSettings None - не менять, "" - отключить канал
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct NotifySettings {
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    #[serde(default)]
+    pub telegram_bot_token: Option<String>,
+    #[serde(default)]
+    pub telegram_chat_id: Option<String>,
+    #[serde(default)]
+    pub events: Option<Vec<String>>, // None - TxConfirmed (ошибки), LiquidityAlert, AutoFuelError, ExitTriggered
+}
+
+// ===================== PRICE FEED =====================
+
+/// USD цены quote/нативки из ядра. provider None - цены только через UpdatePrice
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PriceFeedSettings {
+    #[serde(default)]
+    pub provider: Option<String>, // "chainlink" | "http"
+    #[serde(default)]
+    pub chainlink_feeds: std::collections::HashMap<String, String>, // символ -> агрегатор SYMBOL/USD
+    #[serde(default)]
+    pub http_url: Option<String>, // Binance-совместимый /ticker/price
+    #[serde(default)]
+    pub symbols: Vec<String>, // для http: BNB, ETH... (цена к USDT)
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+    #[serde(default)]
+    pub stale_after_secs: Option<u64>, // None/0 - цены не устаревают
+}
+
+// ===================== MONITOR =====================
+
+/// Тайминги монитора. В UpdateSettings None - не менять; значения вне границ ограничиваются
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct MonitorSettings {
+    #[serde(default)]
+    pub idle_timeout_secs: Option<u64>, // не меньше двух блоков сети
+    #[serde(default)]
+    pub reconnect_delay_secs: Option<u64>,
+    #[serde(default)]
+    pub receipt_poll_ms: Option<u64>,
+    #[serde(default)]
+    pub worker_tick_ms: Option<u64>,
+    #[serde(default)]
+    pub rpc_health_secs: Option<u64>,
+}
+
+/// Действующие тайминги монитора в GetState (idle timeout - с учётом времени блока)
+#[derive(Serialize, Clone, Debug)]
+pub struct MonitorConfigInfo {
+    pub idle_timeout_secs: f64,
+    pub reconnect_delay_secs: f64,
+    pub receipt_poll_ms: u64,
+    pub worker_tick_ms: u64,
+    pub rpc_health_secs: f64,
+    pub block_time_ms: Option<u64>, // None - ещё не измерено
+}
+
+/// Особенности сети (chain_quirks конфига). Неизвестные поля игнорируются - старое ядро читает новый конфиг.
+/// В GetState - действующие значения
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ChainQuirksSettings {
+    #[serde(default)]
+    pub force_legacy_tx: bool, // только type-0, даже при supports_eip1559
+    #[serde(default)]
+    pub min_gas_price_gwei: Option<f64>, // нода отвергает tx дешевле
+    #[serde(default)]
+    pub gas_multiplier: Option<f64>, // поправка к eth_gasPrice / feeHistory ноды, None - 1.0
+    #[serde(default)]
+    pub supports_eip1559: Option<bool>, // None - проверяется по feeHistory, tx legacy; true - tx type-2
+    #[serde(default)]
+    pub block_time_secs: Option<f64>, // None - измеряется по блокам
+}
+
+// ===================== WATCHLIST =====================
+
+/// Копирование покупок наблюдаемого адреса: amount в quote токена с кошельков wallets
+#[derive(Deserialize, Debug, Clone)]
+pub struct CopyTradeParams {
+    pub amount: f64,
+    pub wallets: Vec<String>,
+    #[serde(default)]
+    pub slippage: Option<f64>,
+}
+
+// ===================== TWAP =====================
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TwapParams {
+    pub slices: u32,
+    pub interval_secs: u64,
 }
 
 fn default_threshold() -> f64 { 0.005 }
 fn default_amount() -> f64 { 0.01 }
 
+impl EngineEvent {
+    /// Команда отклонена целиком (сочетание полей, состояние ядра)
+    pub fn rejected(command: &str, reason: String) -> Self {
+        EngineEvent::CommandError {
+            message: format!("{}: {}", command, reason),
+            command: Some(command.to_string()),
+            field: None,
+        }
+    }
+
+    /// Неверное значение поля команды
+    pub fn invalid_field(command: &str, field: &str, reason: String) -> Self {
+        EngineEvent::CommandError {
+            message: format!("{}.{}: {}", command, field, reason),
+            command: Some(command.to_string()),
+            field: Some(field.to_string()),
+        }
+    }
+}
+
 // ===================== ENGINE COMMANDS =====================
 
 #[derive(Deserialize, Debug)]
@@ -146,11 +767,63 @@ pub enum EngineCommand {
         native_address: String,
         wallets: Vec<(String, String)>,
         public_rpc_urls: Vec<String>,
-        fuel_settings: AutoFuelSettingsData,
+        fuel_settings: Box<AutoFuelSettingsData>,
         #[serde(default)]
         quote_symbol: String,
         #[serde(default)]
-        quote_tokens: std::collections::HashMap<String, String>
+        quote_tokens: std::collections::HashMap<String, String>,
+        #[serde(default)]
+        v3_fee_tiers: Option<Vec<u32>>,
+        #[serde(default)]
+        discover_v3_fee_tiers: bool,
+        #[serde(default)]
+        journal_path: Option<String>,
+        #[serde(default)]
+        metrics_port: Option<u16>,
+        #[serde(default)]
+        private_relay_urls: Vec<String>,
+        #[serde(default)]
+        broadcast_mode: Option<String>,
+        #[serde(default)]
+        max_block_lag: Option<u64>, // 0 - не учитывать отставание нод
+        #[serde(default)]
+        rpc_rate_limits: std::collections::HashMap<String, f64>, // url -> запросов в секунду
+        #[serde(default)]
+        public_rpc_rps: Option<f64>, // лимит публичных нод без своей записи
+        #[serde(default)]
+        token_cache_path: Option<String>,
+        #[serde(default)]
+        notify: Box<NotifySettings>,
+        #[serde(default)]
+        price_feed: Box<PriceFeedSettings>,
+        #[serde(default)]
+        candle_intervals: Option<Vec<u64>>, // секунды, None - 1s/5s/1m
+        #[serde(default)]
+        candle_history: Option<usize>,
+        #[serde(default)]
+        lp_lockers: Vec<String>,
+        #[serde(default)]
+        dry_run: bool,
+        #[serde(default)]
+        v2_pool_fee_bps: Option<u32>, // комиссия V2 пулов сети, None - 30 (0.3%)
+        #[serde(default)]
+        router_tax_bps: Option<u32>,  // комиссия TaxRouter, None - 10 (0.1%)
+        #[serde(default)]
+        monitor: Box<MonitorSettings>,
+        #[serde(default)]
+        chain_quirks: Box<ChainQuirksSettings>,
+        #[serde(default)]
+        validate_config: bool, // проверить адреса, chain_id и байткод до остановки текущей сессии
+        #[serde(default)]
+        network_name: Option<String>, // networks/{network_name}.json - для ReloadNetworkConfig
+        #[serde(default)]
+        ens_registry: Option<String>, // ENS-совместимый реестр: имена в полях адресов команд
+        #[serde(default)]
+        ens_reverse_lookup: bool, // имена адресов в TradeTape и WatchedAddressActivity
+        #[serde(default)]
+        session_path: Option<String>, // снимки сессии для RestoreSession после перезапуска
+        #[serde(default)]
+        min_native_reserve_wei: Option<String>, // None или "0" - без резерва
     },
     
     ExecuteTrade {
@@ -159,11 +832,24 @@ pub enum EngineCommand {
         quote_token: String, 
         amount: f64,
         wallets: Vec<String>,
-        gas_gwei: f64,
-        slippage: f64,
-        v3_fee: u32,
+        #[serde(default)]
+        gas_gwei: f64, // 0 - manual_gas_price_gwei ядра
+        #[serde(default)]
+        slippage: f64, // 0 - slippage ядра; ограничивается [0, 100]
         #[serde(default)]
         amounts_wei: Option<std::collections::HashMap<String, String>>,
+        #[serde(default)]
+        twap: Option<TwapParams>,
+        #[serde(default)]
+        routing: Option<String>, // "single" (по умолчанию) | "split"
+        #[serde(default)]
+        gas_strategy: Option<String>, // "manual" (по умолчанию) | "slow" | "standard" | "fast"
+        #[serde(default)]
+        percent: Option<f64>, // sell: доля живого баланса (100 - весь, с пылью). Взаимоисключающе с amount/amounts_wei
+        #[serde(default)]
+        deadline_secs: Option<u64>, // None - swap_deadline_secs ядра
+        #[serde(default)]
+        slippage_mode: Option<String>, // "fixed" | "auto"; None - режим ядра
     },
     
     CalcImpact {
@@ -175,6 +861,11 @@ pub enum EngineCommand {
     
     UpdatePrice { symbol: String, price: f64 },
     UpdateTokenDecimals { address: String, decimals: u8 },
+    /// Сбросить сохранённые decimals/symbol токена (None - все токены сети), например после миграции контракта
+    ClearTokenCache {
+        #[serde(default)]
+        address: Option<String>
+    },
     
     UpdateSettings {
         gas_price_gwei: Option<f64>,
@@ -185,19 +876,287 @@ pub enum EngineCommand {
         wss_url: Option<String>,
         #[serde(default)]
         quote_symbol: Option<String>,
+        #[serde(default)]
+        rug_alert_pct: Option<f64>,
+        #[serde(default)]
+        rug_auto_sell: Option<bool>,
+        #[serde(default)]
+        split_min_improvement_bps: Option<f64>,
+        #[serde(default)]
+        pool_switch_hysteresis_pct: Option<f64>,
+        #[serde(default)]
+        max_token_subscriptions: Option<usize>,
+        #[serde(default)]
+        bridge_queue_capacity: Option<usize>,
+        #[serde(default)]
+        gas_limit_swap: Option<u64>,
+        #[serde(default)]
+        gas_limit_approve: Option<u64>,
+        #[serde(default)]
+        gas_limit_safety_factor: Option<f64>,
+        #[serde(default)]
+        approval_mode: Option<String>, // "unlimited" | "exact"
+        #[serde(default)]
+        wrap_gas_reserve: Option<f64>, // в нативной монете
+        #[serde(default)]
+        disperse_address: Option<String>, // "" - отключить
+        #[serde(default)]
+        broadcast_mode: Option<String>, // "public" | "private" | "both"
+        #[serde(default)]
+        notify: Option<NotifySettings>,
+        #[serde(default)]
+        portfolio_pnl_delta_pct: Option<f64>,
+        #[serde(default)]
+        pnl_update_epsilon_pct: Option<f64>,
+        #[serde(default)]
+        attribute_approve_gas: Option<bool>,
+        #[serde(default)]
+        attribute_fuel_gas: Option<bool>,
+        #[serde(default)]
+        deadline_secs: Option<u64>,
+        #[serde(default)]
+        slippage_mode: Option<String>, // "fixed" | "auto"
+        #[serde(default)]
+        auto_slippage_buffer_bps: Option<f64>,
+        #[serde(default)]
+        auto_slippage_max_pct: Option<f64>,
+        #[serde(default)]
+        sandwich_precheck: Option<bool>,
+        #[serde(default)]
+        sandwich_max_move_pct: Option<f64>,
+        #[serde(default)]
+        sandwich_detect: Option<bool>,
+        #[serde(default)]
+        batch_burst: Option<bool>,
+        #[serde(default)]
+        auto_approve: Option<bool>,
+        #[serde(default)]
+        dry_run: Option<bool>,
+        #[serde(default)]
+        pending_tx_timeout_secs: Option<u64>,
+        #[serde(default)]
+        monitor: Option<MonitorSettings>,
+        #[serde(default)]
+        min_native_reserve_wei: Option<String>, // "0" - без резерва
+        #[serde(default)]
+        balance_quotes: Option<Vec<String>>, // символы quote_tokens; [] - все
+        #[serde(default)]
+        min_balance_usd: Option<f64>, // BalanceUpdate дешевле не отправляется, 0 - без порога
+        #[serde(default)]
+        min_balance_wei: Option<std::collections::HashMap<String, String>>, // токен ("native") -> wei, заменяет прежние
     },
     
-    SwitchToken { 
+    AddTokenSubscription { 
         token_address: String, 
         quote_address: String,
         #[serde(default)]
         quote_symbol: String,
     },
-    UnsubscribeToken {
+    RemoveTokenSubscription {
         token_address: String
     },
+    SelectPool {
+        token_address: String,
+        #[serde(default)]
+        pool_address: Option<String> // None - вернуть автоматический выбор
+    },
+    SetPriceAlert {
+        id: String,
+        token: String,
+        quote: String,
+        price: f64,
+        direction: String,
+        #[serde(default)]
+        repeat: bool
+    },
+    RemovePriceAlert { id: String },
+    WrapNative { wallets: Vec<String>, amount: String }, // amount: число или "max"
+    UnwrapNative { wallets: Vec<String>, amount: String },
+    Transfer {
+        from_wallet: String,
+        to_address: String,
+        #[serde(default)]
+        token: Option<String>, // None / "native" - нативная монета
+        amount: String // число или "max"
+    },
+    DisperseNative {
+        from_wallet: String,
+        recipients: Vec<(String, f64)>
+    },
+    SweepAll {
+        #[serde(default)]
+        token: Option<String>, // None / "native" - нативная монета
+        #[serde(default)]
+        wallets: Vec<String>, // пусто - все кошельки
+        destination: String,
+        #[serde(default)]
+        leave_native_for_gas: Option<f64> // native sweep: сколько оставить сверх газа самой tx
+    },
+    AddRpcNode {
+        url: String,
+        #[serde(default)]
+        is_private: bool
+    },
+    RemoveRpcNode { url: String },
+    // Перечитать networks/{network}.json (None - сеть из Init) и применить безопасные изменения
+    ReloadNetworkConfig {
+        #[serde(default)]
+        network: Option<String>
+    },
+    // Вернуть сессию из session_path Init: pending tx, позиции, ордера, DCA. Ответ - SessionRestored
+    RestoreSession {
+        #[serde(default)]
+        stale_after_blocks: Option<u64>, // pending tx старше - сверяются по receipt, None - 20
+        #[serde(default)]
+        discard: bool // отбросить сохранённую сессию
+    },
+    GetTokenInfo { address: String },
+    WatchAddress {
+        address: String,
+        #[serde(default)]
+        label: String,
+        #[serde(default)]
+        copy_trade: Option<CopyTradeParams> // None - только события
+    },
+    UnwatchAddress { address: String },
+    // Спам-токены: без BalanceUpdate и без balanceOf по их Transfer; список сохраняется в сессии
+    IgnoreToken { address: String },
+    UnignoreToken { address: String },
+    AuditToken { token: String },
+    AnalyzeHolders {
+        token: String,
+        #[serde(default)]
+        top_n: Option<usize>, // None - 10
+        #[serde(default)]
+        max_blocks: Option<u64>, // предел скана от текущего блока, None - 500k
+        #[serde(default)]
+        refresh: bool // true - сканировать заново, а не из кэша сессии
+    },
+    // Позиция кошелька из Transfer логов и журнала: средняя стоимость для PnL вместо ручной
+    ImportHistory {
+        wallet: String,
+        token: String,
+        #[serde(default)]
+        quote: Option<String>, // None - quote позиции или подписки, иначе wrapped native
+        #[serde(default)]
+        from_block: Option<u64>, // None - max_blocks от головы
+        #[serde(default)]
+        max_blocks: Option<u64> // предел скана, None - 200k
+    },
+    GetCandles {
+        pool: String,
+        interval: u64,
+        #[serde(default)]
+        count: Option<usize> // None - весь буфер
+    },
+    SetRpcPriority { url: String, pinned: bool },
+    RevokeApproval {
+        wallet: String,
+        token: String,
+        #[serde(default)]
+        spender: Option<String> // None - роутер
+    },
+    /// allowance() пар кошелёк × токен без отправки tx
+    CheckApprovals {
+        wallets: Vec<String>,
+        tokens: Vec<String>,
+        #[serde(default)]
+        spender: Option<String> // None - роутер
+    },
+    PlaceLimitOrder {
+        id: String,
+        action: String,
+        token: String,
+        quote: String,
+        trigger_price: f64,
+        amount: f64,
+        wallets: Vec<String>,
+        slippage: f64,
+        #[serde(default)]
+        expiry: u64,
+        #[serde(default)]
+        amounts_wei: Option<std::collections::HashMap<String, String>>,
+    },
+    CancelLimitOrder { id: String },
+    /// Подписать свапы заранее: FireBundle только рассылает
+    PrepareBundle {
+        id: String,
+        action: String,
+        token: String,
+        quote: String,
+        amount: f64,
+        wallets: Vec<String>,
+        #[serde(default)]
+        gas_gwei: f64, // 0 - manual_gas_price_gwei ядра
+        #[serde(default)]
+        slippage: f64, // 0 - slippage ядра
+        #[serde(default)]
+        deadline_secs: Option<u64>, // бандл живёт до дедлайна свапов
+    },
+    FireBundle { id: String },
+    DiscardBundle { id: String },
+    /// Подписать buy до появления ликвидности и разослать на первом Sync/выборе пула
+    ArmSnipe {
+        token: String,
+        quote: String,
+        amount: f64,
+        wallets: Vec<String>,
+        #[serde(default)]
+        max_gas_gwei: f64, // 0 - manual_gas_price_gwei ядра
+        max_buy_tax_pct: f64,
+        min_liquidity_usd: f64,
+        #[serde(default)]
+        timeout_secs: Option<u64>, // None - SNIPE_TIMEOUT_SECS
+    },
+    DisarmSnipe { token: String },
+    SetExitStrategy {
+        wallet: String,
+        token: String,
+        quote: String,
+        #[serde(default)]
+        cost_basis: Option<f64>, // None - средняя цена позиции ядра
+        #[serde(default)]
+        stop_loss_pct: Option<f64>,
+        #[serde(default)]
+        take_profit_pct: Option<f64>,
+        #[serde(default)]
+        trail_pct: Option<f64>,
+    },
+    ClearExitStrategy { wallet: String, token: String },
+    ScheduleRecurringTrade {
+        id: String,
+        action: String,
+        token: String,
+        quote: String,
+        amount: f64,
+        wallets: Vec<String>,
+        interval_secs: u64,
+        #[serde(default)]
+        max_executions: u32,
+    },
+    CancelRecurringTrade { id: String },
     AddWallet { address: String, private_key: String },
+    RemoveWallet { address: String },
+    /// Сбросить счётчик попыток и карантин auto-fuel кошелька
+    ResetAutoFuel { wallet: String },
     RefreshBalance { wallet: String, token: String },
     RefreshAllBalances,
+    GetState,
+    PauseTrading,
+    ResumeTrading,
     Shutdown
+}
+
+/// Команда в очереди движка вместе с correlation id и типом из JSON Python (у внутренних команд - None)
+#[derive(Debug)]
+pub struct EngineRequest {
+    pub command: EngineCommand,
+    pub request_id: Option<String>,
+    pub kind: Option<String>,
+}
+
+impl From<EngineCommand> for EngineRequest {
+    fn from(command: EngineCommand) -> Self {
+        Self { command, request_id: None, kind: None }
+    }
 }
\ No newline at end of file
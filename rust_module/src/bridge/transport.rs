@@ -1,20 +1,102 @@
+use std::collections::{HashMap, VecDeque};
 use std::io::Write;
 use std::net::TcpStream;
 use std::sync::Mutex;
-use crossbeam_channel::{unbounded, Receiver, Sender};
 use once_cell::sync::Lazy;
+use crate::state::EngineLocal;
 
-pub static BRIDGE_QUEUE: Lazy<(Sender<String>, Receiver<String>)> = Lazy::new(unbounded);
+pub const DEFAULT_QUEUE_CAPACITY: usize = 4096;
+
+/// Насколько событие важно для Python при переполнении очереди
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Priority {
+    /// Поток рыночных данных (PoolUpdate, GasPriceUpdate, ImpactUpdate): схлопывается по ключу, выкидывается первым
+    Low,
+    Normal,
+    /// TradeStatus, TxSent, TxConfirmed, Log ERROR - не выкидываются никогда
+    Critical,
+}
+
+struct QueuedEvent {
+    kind: String,
+    priority: Priority,
+    coalesce_key: Option<String>,
+    json: String,
+}
+
+pub struct BridgeQueue {
+    events: VecDeque<QueuedEvent>,
+    capacity: usize,
+    dropped: HashMap<String, u64>,
+}
+
+impl BridgeQueue {
+    fn drop_at(&mut self, idx: usize) {
+        if let Some(ev) = self.events.remove(idx) {
+            *self.dropped.entry(ev.kind).or_insert(0) += 1;
+        }
+    }
+
+    fn push(&mut self, ev: QueuedEvent) {
+        // Устаревший кадр того же пула/газа Python уже не нужен
+        if let Some(key) = &ev.coalesce_key {
+            if let Some(idx) = self.events.iter().position(|e| e.coalesce_key.as_ref() == Some(key)) {
+                self.drop_at(idx);
+            }
+        }
+
+        // Переполнение: сначала самый старый Low, затем самый старый Normal. Critical не трогаем
+        if self.events.len() >= self.capacity {
+            let victim = self.events.iter().position(|e| e.priority == Priority::Low)
+                .or_else(|| self.events.iter().position(|e| e.priority == Priority::Normal));
+            match victim {
+                Some(idx) => self.drop_at(idx),
+                None if ev.priority != Priority::Critical => {
+                    *self.dropped.entry(ev.kind).or_insert(0) += 1;
+                    return;
+                }
+                None => {}
+            }
+        }
+        self.events.push_back(ev);
+    }
+}
+
+pub static BRIDGE_QUEUE: EngineLocal<Mutex<BridgeQueue>> = EngineLocal::new(|| Mutex::new(BridgeQueue {
+    events: VecDeque::new(),
+    capacity: DEFAULT_QUEUE_CAPACITY,
+    dropped: HashMap::new(),
+}));
 pub static SIGNAL_TX: Lazy<Mutex<Option<TcpStream>>> = Lazy::new(|| Mutex::new(None));
 
-pub fn send_to_python(json: String) {
-    let _ = BRIDGE_QUEUE.0.send(json);
+pub fn send_to_python(kind: String, priority: Priority, coalesce_key: Option<String>, json: String) {
+    BRIDGE_QUEUE.lock().unwrap().push(QueuedEvent { kind, priority, coalesce_key, json });
     let mut guard = SIGNAL_TX.lock().unwrap();
     if let Some(ref mut stream) = *guard {
         let _ = stream.write(&[1]);
     }
 }
 
+pub fn pop_batch(max_items: usize) -> Vec<String> {
+    let mut q = BRIDGE_QUEUE.lock().unwrap();
+    let n = max_items.min(q.events.len());
+    q.events.drain(..n).map(|e| e.json).collect()
+}
+
+pub fn set_capacity(capacity: usize) {
+    BRIDGE_QUEUE.lock().unwrap().capacity = capacity.max(1);
+}
+
+pub fn reset_stats() {
+    BRIDGE_QUEUE.lock().unwrap().dropped.clear();
+}
+
+/// (событий в очереди, выкинуто по типам с момента старта)
+pub fn stats() -> (usize, HashMap<String, u64>) {
+    let q = BRIDGE_QUEUE.lock().unwrap();
+    (q.events.len(), q.dropped.clone())
+}
+
 pub fn set_signal_socket(fd: u64) -> Result<(), String> {
     let stream = unsafe {
         #[cfg(unix)] { use std::os::unix::io::FromRawFd; TcpStream::from_raw_fd(fd as i32) }
@@ -0,0 +1,206 @@
+use ethers::prelude::*;
+use ethers::utils::parse_units;
+use futures::future::join_all;
+use crate::state::EngineLocal;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use crate::bridge::{emit_event, emit_log, EngineEvent, AppliedTradeParams};
+use crate::execution::{self, BatchRequest, SignedSwap, SwapLeg, SwapOrder, WalletOutcome};
+use crate::monitor;
+use crate::state::SlippageMode;
+
+/// Свапы, подписанные заранее: FireBundle только рассылает их
+struct Bundle {
+    txs: Vec<(u64, SignedSwap, AppliedTradeParams)>, // (зарезервированный nonce, tx)
+    direct_pool: Option<Address>,
+    expires_at: u64, // unix сек: после дедлайна в calldata свапы ревертнутся
+}
+
+static BUNDLES: EngineLocal<Mutex<HashMap<String, Bundle>>> = EngineLocal::new(|| Mutex::new(HashMap::new()));
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Снимает бандл: неиспользованные nonce возвращаются, UI получает причину
+fn drop_bundle(id: String, bundle: Bundle, reason: &str) {
+    for (nonce, signed, _) in &bundle.txs {
+        execution::release_nonce(signed.wallet, *nonce);
+    }
+    emit_log("WARNING", format!("📦 Бандл {} снят: {}", id, reason));
+    emit_event(EngineEvent::BundleInvalidated { id, reason: reason.into() });
+}
+
+/// Резервирует nonce, считает calldata и подписывает свапы всех кошельков заранее.
+/// Суммы sell (amount токенов с каждого кошелька) считаются здесь, amounts_wei запроса не используется
+pub async fn prepare(id: String, mut req: BatchRequest, gas: f64, slippage: f64, deadline_secs: Option<u64>) {
+    if let Some(old) = BUNDLES.lock().unwrap().remove(&id) {
+        drop_bundle(id.clone(), old, "Replaced");
+    }
+    let params = execution::applied_params(slippage, gas, deadline_secs, None);
+
+    // Sell: amount токенов с каждого кошелька
+    req.amounts_wei = if req.action == "sell" {
+        let dec = monitor::get_decimals_cached(req.token).await;
+        let Ok(per_wallet) = parse_units(req.amount, dec as u32) else {
+            emit_event(EngineEvent::invalid_field("PrepareBundle", "amount", format!("бандл {}: неверная сумма {}", id, req.amount)));
+            return;
+        };
+        let per_wallet: U256 = per_wallet.into();
+        Some(req.keys.iter().map(|k| (format!("{:?}", k.address()).to_lowercase(), per_wallet.to_string())).collect())
+    } else {
+        None
+    };
+
+    let (action, token) = (req.action.clone(), req.token);
+    let batch = execution::prepare_batch(req, gas, &params, false).await;
+    for ev in batch.rejected { emit_event(ev); }
+    let mut txs = Vec::new();
+    for (wallet, nonce, outcome) in batch.wallets {
+        match outcome {
+            WalletOutcome::Signed(signed, applied) => txs.push((nonce, signed, applied)),
+            WalletOutcome::Done(ev) => {
+                execution::release_if_unsent(wallet, nonce, &ev);
+                if let Some(ev) = ev { emit_event(ev); }
+            }
+        }
+    }
+    store(id, action, token, txs, batch.direct_pool, params.deadline_secs);
+}
+
+/// Регистрирует подписанные tx под id и сообщает о них UI
+fn store(id: String, action: String, token: Address, txs: Vec<(u64, SignedSwap, AppliedTradeParams)>, direct_pool: Option<Address>, deadline_secs: u64) {
+    if txs.is_empty() {
+        emit_event(EngineEvent::rejected("PrepareBundle", format!("бандл {}: нет подписанных транзакций", id)));
+        return;
+    }
+
+    let expires_at = now_secs() + txs.iter().map(|(_, _, a)| a.deadline_secs).min().unwrap_or(deadline_secs);
+    let nonces = txs.iter().map(|(n, s, _)| (format!("{:?}", s.wallet), *n)).collect();
+    emit_log("INFO", format!("📦 Бандл {}: {} {} подписано {} tx", id, action, token, txs.len()));
+    BUNDLES.lock().unwrap().insert(id.clone(), Bundle { txs, direct_pool, expires_at });
+    emit_event(EngineEvent::BundlePrepared {
+        id,
+        action,
+        token: format!("{:?}", token),
+        nonces,
+        expires_at
+    });
+}
+
+/// Buy до появления пула: V2 calldata через прямую пару quote -> token без min_out
+/// (роутер не проверяет его на buy), газ - фолбэк gas_limit_swap, оценка ещё ревертится.
+/// Из req берутся кошельки, пара и amount в quote - action всегда buy
+pub async fn presign_buy(id: String, req: BatchRequest, gas: f64, deadline_secs: u64) {
+    let BatchRequest { keys, router, token, quote, amount, chain_id, .. } = req;
+    if let Some(old) = BUNDLES.lock().unwrap().remove(&id) {
+        drop_bundle(id.clone(), old, "Replaced");
+    }
+    let dec = monitor::get_decimals_cached(quote).await;
+    let Ok(amount_wei) = parse_units(amount, dec as u32) else {
+        emit_event(EngineEvent::invalid_field("ArmSnipe", "amount", format!("снайп {}: неверная сумма {}", id, amount)));
+        return;
+    };
+    let amount_wei: U256 = amount_wei.into();
+    let params = execution::applied_params(100.0, gas, Some(deadline_secs), Some(SlippageMode::Fixed));
+
+    let wallets: Vec<LocalWallet> = keys.iter().map(|k| execution::signer(k, chain_id)).collect();
+    let addresses: Vec<Address> = wallets.iter().map(|w| w.address()).collect();
+    let nonces = execution::reserve_nonces(&addresses);
+    let signed = join_all(wallets.iter().zip(&nonces).map(|(wallet, nonce)| async move {
+        let leg = SwapLeg { pool_type: "V2", fee: 0, t_in: quote, t_out: token };
+        let calldata = execution::encode_swap_calldata(&leg, amount_wei, U256::zero(), wallet.address(), deadline_secs);
+        execution::sign_swap(wallet, SwapOrder {
+            router, nonce: *nonce, calldata, gas, action: "buy", token, amount, amount_wei, exp_out: U256::zero(), dec
+        }).await
+    })).await;
+
+    let mut txs = Vec::new();
+    for ((wallet, nonce), result) in addresses.into_iter().zip(nonces).zip(signed) {
+        match result {
+            Ok(signed) => txs.push((nonce, signed, params.clone())),
+            Err(ev) => {
+                execution::release_nonce(wallet, nonce);
+                if let Some(ev) = ev { emit_event(ev); }
+            }
+        }
+    }
+    store(id, "buy".into(), token, txs, None, params.deadline_secs);
+}
+
+/// Бандл с таким id подписан и ещё не снят
+pub fn exists(id: &str) -> bool {
+    BUNDLES.lock().unwrap().contains_key(id)
+}
+
+/// Рассылает все tx бандла одной волной parallel_broadcast
+pub async fn fire(id: String) {
+    let Some(bundle) = BUNDLES.lock().unwrap().remove(&id) else {
+        emit_event(EngineEvent::invalid_field("FireBundle", "id", format!("бандл {} не найден", id)));
+        return;
+    };
+    if now_secs() >= bundle.expires_at {
+        drop_bundle(id, bundle, "Deadline expired");
+        return;
+    }
+    let start = std::time::Instant::now();
+    let direct_pool = bundle.direct_pool;
+    let results = join_all(bundle.txs.into_iter().map(|(nonce, signed, applied)| async move {
+        let wallet = signed.wallet;
+        (wallet, nonce, Some(execution::finish_wallet_swap(signed, applied, direct_pool).await))
+    })).await;
+
+    let (mut sent, mut failed) = (0, 0);
+    for (wallet, nonce, ev) in results {
+        execution::release_if_unsent(wallet, nonce, &ev);
+        match &ev {
+            Some(EngineEvent::TradeStatus { tx_hash: Some(_), .. }) => sent += 1,
+            _ => failed += 1,
+        }
+        if let Some(ev) = ev { emit_event(ev); }
+    }
+    let elapsed_ms = start.elapsed().as_millis() as u64;
+    emit_log("INFO", format!("🚀 Бандл {}: отправлено {}, ошибок {} за {} мс", id, sent, failed, elapsed_ms));
+    emit_event(EngineEvent::BundleFired { id, sent, failed, elapsed_ms });
+}
+
+/// Ручная отмена бандла
+pub fn discard(id: &str) {
+    let removed = BUNDLES.lock().unwrap().remove(id);
+    match removed {
+        Some(bundle) => drop_bundle(id.to_string(), bundle, "Discarded"),
+        None => emit_event(EngineEvent::invalid_field("DiscardBundle", "id", format!("бандл {} не найден", id))),
+    }
+}
+
+/// Параметры газа изменились - подписи всех бандлов устарели
+pub fn invalidate_all(reason: &str) {
+    let removed: Vec<(String, Bundle)> = BUNDLES.lock().unwrap().drain().collect();
+    for (id, bundle) in removed {
+        drop_bundle(id, bundle, reason);
+    }
+}
+
+/// Nonce после последнего, зарезервированного кошельком в бандлах (снайп держит buy тоже в бандле)
+pub fn next_free_nonce(wallet: Address) -> Option<u64> {
+    BUNDLES.lock().unwrap().values()
+        .flat_map(|b| b.txs.iter())
+        .filter(|(_, s, _)| s.wallet == wallet)
+        .map(|(n, _, _)| n + 1)
+        .max()
+}
+
+/// Nonce кошелька в сети ушёл дальше зарезервированного - tx бандла уже не пройдёт
+pub fn on_nonce_synced(wallet: Address, chain_nonce: u64) {
+    let removed: Vec<(String, Bundle)> = {
+        let mut bundles = BUNDLES.lock().unwrap();
+        let stale: Vec<String> = bundles.iter()
+            .filter(|(_, b)| b.txs.iter().any(|(n, s, _)| s.wallet == wallet && *n < chain_nonce))
+            .map(|(id, _)| id.clone())
+            .collect();
+        stale.into_iter().filter_map(|id| bundles.remove(&id).map(|b| (id, b))).collect()
+    };
+    for (id, bundle) in removed {
+        drop_bundle(id, bundle, &format!("Nonce changed for {:?}", wallet));
+    }
+}
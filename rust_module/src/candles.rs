@@ -0,0 +1,103 @@
+use ethers::types::H160;
+use std::sync::atomic::Ordering;
+use tokio::time::{sleep, Duration};
+use crate::bridge::{emit_event, EngineEvent, CandleInfo};
+use crate::state::{CORE_STATE, SHUTDOWN_FLAG, Candle, CandleSeries};
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl From<&Candle> for CandleInfo {
+    fn from(c: &Candle) -> Self {
+        Self { ts: c.ts, open: c.open, high: c.high, low: c.low, close: c.close, volume_quote: c.volume_quote }
+    }
+}
+
+/// Закрывает все истёкшие интервалы серии. Интервал без сделок - плоская свеча по прошлому close
+fn roll(series: &mut CandleSeries, interval: u64, now: u64, capacity: usize, closed: &mut Vec<Candle>) {
+    let bucket = now - now % interval;
+    let Some(mut cur) = series.current else { return; };
+    // Простой дольше буфера: плоские свечи старше него всё равно были бы вытеснены
+    let skip_to = bucket.saturating_sub(interval * capacity as u64);
+    while cur.ts < bucket {
+        if series.history.len() >= capacity { series.history.pop_front(); }
+        series.history.push_back(cur);
+        closed.push(cur);
+        cur = Candle::flat((cur.ts + interval).max(skip_to), cur.close);
+    }
+    series.current = Some(cur);
+}
+
+fn emit_closed(closed: Vec<(H160, u64, Candle)>) {
+    for (pool, interval, c) in closed {
+        emit_event(EngineEvent::Candle {
+            pool: format!("{:?}", pool),
+            interval,
+            open: c.open,
+            high: c.high,
+            low: c.low,
+            close: c.close,
+            volume_quote: c.volume_quote,
+            ts: c.ts
+        });
+    }
+}
+
+/// Сделка/обновление цены пула (Sync для V2, Swap для V3) во все интервалы
+pub fn record(pool: H160, price: f64, volume_quote: f64) {
+    if !price.is_finite() || price <= 0.0 { return; }
+    let now = now_secs();
+    let mut closed = Vec::new();
+    {
+        let mut s = CORE_STATE.write().unwrap();
+        let intervals = s.candle_intervals.clone();
+        let capacity = s.candle_history;
+        for interval in intervals {
+            let series = s.candles.entry((pool, interval)).or_default();
+            let mut rolled = Vec::new();
+            roll(series, interval, now, capacity, &mut rolled);
+            closed.extend(rolled.into_iter().map(|c| (pool, interval, c)));
+
+            let cur = series.current.get_or_insert(Candle::flat(now - now % interval, price));
+            cur.high = cur.high.max(price);
+            cur.low = cur.low.min(price);
+            cur.close = price;
+            cur.volume_quote += volume_quote.abs();
+        }
+    }
+    emit_closed(closed);
+}
+
+/// Закрытые свечи серии (последние count), старые первыми
+pub fn history(pool: H160, interval: u64, count: usize) -> Vec<CandleInfo> {
+    let s = CORE_STATE.read().unwrap();
+    let Some(series) = s.candles.get(&(pool, interval)) else { return Vec::new(); };
+    let skip = series.history.len().saturating_sub(count);
+    series.history.iter().skip(skip).map(CandleInfo::from).collect()
+}
+
+/// Закрывает интервалы по часам, даже если в пуле нет сделок - на графике не будет дыр
+pub async fn start_candle_worker() {
+    loop {
+        if SHUTDOWN_FLAG.load(Ordering::SeqCst) { break; }
+
+        let now = now_secs();
+        let mut closed = Vec::new();
+        {
+            let mut s = CORE_STATE.write().unwrap();
+            let capacity = s.candle_history;
+            for (&(pool, interval), series) in s.candles.iter_mut() {
+                let mut rolled = Vec::new();
+                roll(series, interval, now, capacity, &mut rolled);
+                closed.extend(rolled.into_iter().map(|c| (pool, interval, c)));
+            }
+        }
+        emit_closed(closed);
+
+        sleep(Duration::from_millis(250)).await;
+    }
+}
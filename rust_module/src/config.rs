@@ -1,10 +1,13 @@
 use pyo3::prelude::*;
 use pyo3::types::PyDict;
-use pyo3::exceptions::{PyValueError, PyFileNotFoundError};
-use serde::Deserialize;
+use pyo3::exceptions::{PyValueError, PyFileNotFoundError, PyFileExistsError};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use ethers::prelude::*;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
+use crate::state::CONFIG_DIR;
 
 #[derive(Deserialize, Debug)]
 pub struct NetworkConfig {
@@ -24,18 +27,90 @@ pub struct NetworkConfig {
     pub fee_receiver: Option<String>,
     pub default_quote_currency: String,
     pub quote_tokens: HashMap<String, String>,
-    pub requires_private_rpc: Option<bool>
+    pub requires_private_rpc: Option<bool>,
+    pub v3_fee_tiers: Option<Vec<u32>>,
+    pub discover_v3_fee_tiers: Option<bool>,
+    pub chainlink_feeds: Option<HashMap<String, String>>,
+    pub price_feed_provider: Option<String>,
+    pub price_feed_http_url: Option<String>,
+    pub price_feed_interval_secs: Option<u64>,
+    pub price_stale_after_secs: Option<u64>,
+    pub candle_intervals: Option<Vec<u64>>,
+    pub candle_history: Option<usize>,
+    pub lp_lockers: Option<Vec<String>>,
+    pub dry_run: Option<bool>,
+    pub v2_pool_fee_bps: Option<u32>,
+    pub router_tax_bps: Option<u32>,
+    pub chain_quirks: Option<crate::bridge::ChainQuirksSettings>,
+    pub validate_config: Option<bool>,
+    pub ens_registry: Option<String>,
+    pub ens_reverse_lookup: Option<bool>,
+    pub min_native_reserve_wei: Option<u128>
 }
 
+// Каталог в пользовательских конфигах ОС: ~/.config/evm_terminal/networks, %APPDATA%\evm_terminal\networks
+const APP_CONFIG_DIR: &str = "evm_terminal";
+
+fn user_config_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        return std::env::var_os("APPDATA").map(PathBuf::from);
+    }
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+}
+
+/// Где искать конфиги сетей: явный base_path, иначе set_config_dir, иначе ./networks,
+/// networks/ рядом с исполняемым файлом (сборка pyinstaller) и каталог конфигов пользователя
+fn network_dirs(base_path: Option<&str>) -> Vec<PathBuf> {
+    if let Some(base) = base_path.filter(|b| !b.is_empty()) {
+        return vec![PathBuf::from(base)];
+    }
+    if let Some(dir) = CONFIG_DIR.read().unwrap().clone() {
+        return vec![dir];
+    }
+    let mut dirs = vec![PathBuf::from("networks")];
+    if let Some(exe_dir) = std::env::current_exe().ok().and_then(|p| p.parent().map(Path::to_path_buf)) {
+        dirs.push(exe_dir.join("networks"));
+    }
+    if let Some(config_home) = user_config_dir() {
+        dirs.push(config_home.join(APP_CONFIG_DIR).join("networks"));
+    }
+    dirs
+}
+
+/// Первый существующий каталог конфигов; нет ни одного - ошибка со списком проверенных путей
+fn networks_dir(base_path: Option<&str>) -> Result<PathBuf, String> {
+    let dirs = network_dirs(base_path);
+    if let Some(dir) = dirs.iter().find(|d| d.is_dir()) {
+        return Ok(dir.clone());
+    }
+    let searched: Vec<String> = dirs.iter().map(|d| d.display().to_string()).collect();
+    Err(format!("Network config directory not found, searched: {}", searched.join(", ")))
+}
+
+/// Каталог конфигов сетей для всех функций без base_path. None или "" - снова поиск по умолчанию
 #[pyfunction]
-pub fn get_available_networks(_py: Python<'_>) -> PyResult<Vec<String>> {
-    let path = Path::new("networks");
-    if !path.exists() {
-        return Ok(Vec::new());
+#[pyo3(signature = (path=None))]
+pub fn set_config_dir(path: Option<String>) -> PyResult<()> {
+    let dir = path.filter(|p| !p.is_empty()).map(PathBuf::from);
+    if let Some(d) = &dir {
+        if !d.is_dir() {
+            return Err(PyFileNotFoundError::new_err(format!("Config directory not found: {}", d.display())));
+        }
     }
+    *CONFIG_DIR.write().unwrap() = dir;
+    Ok(())
+}
+
+#[pyfunction]
+#[pyo3(signature = (base_path=None))]
+pub fn get_available_networks(base_path: Option<String>) -> PyResult<Vec<String>> {
+    let dir = networks_dir(base_path.as_deref()).map_err(PyFileNotFoundError::new_err)?;
 
     let mut networks = Vec::new();
-    let entries = fs::read_dir(path)?;
+    let entries = fs::read_dir(&dir)?;
 
     for entry in entries {
         let entry = entry?;
@@ -47,25 +122,87 @@ pub fn get_available_networks(_py: Python<'_>) -> PyResult<Vec<String>> {
         }
     }
     
+    if networks.is_empty() {
+        return Err(PyFileNotFoundError::new_err(format!("No network configs (*.json) in {}", dir.display())));
+    }
     // Сортировка для предсказуемости
     networks.sort();
     Ok(networks)
 }
 
-#[pyfunction]
-pub fn get_network_config(py: Python<'_>, network_name: String) -> PyResult<PyObject> {
-    let filename = format!("networks/{}.json", network_name);
-    let path = Path::new(&filename);
+fn read_network_file(network_name: &str, base_path: Option<&str>) -> PyResult<String> {
+    let dir = networks_dir(base_path).map_err(PyFileNotFoundError::new_err)?;
+    let path = dir.join(format!("{}.json", network_name));
 
     if !path.exists() {
-        return Err(PyFileNotFoundError::new_err(format!("Network config not found: {}", filename)));
+        return Err(PyFileNotFoundError::new_err(format!("Network config not found: {}", path.display())));
     }
 
-    let file_content = fs::read_to_string(path)
-        .map_err(|e| PyValueError::new_err(format!("Failed to read config file: {}", e)))?;
+    fs::read_to_string(path)
+        .map_err(|e| PyValueError::new_err(format!("Failed to read config file: {}", e)))
+}
 
-    let config: NetworkConfig = serde_json::from_str(&file_content)
-        .map_err(|e| PyValueError::new_err(format!("Invalid JSON format: {}", e)))?;
+/// Подстановка "${VAR}" из окружения; незаданная переменная - ошибка с полем, где она стоит
+fn expand_env(raw: &str, field: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start + 2..].find('}') else {
+            return Err(format!("{}: незакрытая подстановка {:?}", field, &rest[start..]));
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        match std::env::var(name) {
+            Ok(value) => out.push_str(&value),
+            Err(_) => return Err(format!("{}: переменная окружения {} не задана", field, name)),
+        }
+        rest = &rest[start + 3 + len..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn substitute_env(value: &mut serde_json::Value, field: &str) -> Result<(), String> {
+    match value {
+        serde_json::Value::String(s) if s.contains("${") => *s = expand_env(s, field)?,
+        serde_json::Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                substitute_env(item, &format!("{}[{}]", field, i))?;
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                let path = if field.is_empty() { key.clone() } else { format!("{}.{}", field, key) };
+                substitute_env(item, &path)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Разбор конфига сети с подстановкой переменных окружения
+pub fn parse_network_config(file_content: &str) -> Result<NetworkConfig, String> {
+    let mut value: serde_json::Value = serde_json::from_str(file_content)
+        .map_err(|e| format!("Invalid JSON format: {}", e))?;
+    substitute_env(&mut value, "")?;
+    serde_json::from_value(value).map_err(|e| format!("Invalid JSON format: {}", e))
+}
+
+/// {network_name}.json из каталога конфигов для ядра (ReloadNetworkConfig) - ошибки строкой, без Python
+pub fn load_network_config(network_name: &str) -> Result<NetworkConfig, String> {
+    let path = networks_dir(None)?.join(format!("{}.json", network_name));
+    let file_content = fs::read_to_string(&path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse_network_config(&file_content)
+}
+
+#[pyfunction]
+#[pyo3(signature = (network_name, base_path=None))]
+pub fn get_network_config(py: Python<'_>, network_name: String, base_path: Option<String>) -> PyResult<PyObject> {
+    let file_content = read_network_file(&network_name, base_path.as_deref())?;
+
+    let config = parse_network_config(&file_content).map_err(PyValueError::new_err)?;
 
     let dict = PyDict::new(py);
 
@@ -86,6 +223,33 @@ pub fn get_network_config(py: Python<'_>, network_name: String) -> PyResult<PyOb
     if let Some(v) = config.public_rpc_urls { dict.set_item("public_rpc_urls", v)?; }
     if let Some(v) = config.fee_receiver { dict.set_item("fee_receiver", v)?; }
     if let Some(v) = config.requires_private_rpc { dict.set_item("requires_private_rpc", v)?; }
+    if let Some(v) = config.v3_fee_tiers { dict.set_item("v3_fee_tiers", v)?; }
+    if let Some(v) = config.discover_v3_fee_tiers { dict.set_item("discover_v3_fee_tiers", v)?; }
+    if let Some(v) = config.chainlink_feeds { dict.set_item("chainlink_feeds", v)?; }
+    if let Some(v) = config.price_feed_provider { dict.set_item("price_feed_provider", v)?; }
+    if let Some(v) = config.price_feed_http_url { dict.set_item("price_feed_http_url", v)?; }
+    if let Some(v) = config.price_feed_interval_secs { dict.set_item("price_feed_interval_secs", v)?; }
+    if let Some(v) = config.price_stale_after_secs { dict.set_item("price_stale_after_secs", v)?; }
+    if let Some(v) = config.candle_intervals { dict.set_item("candle_intervals", v)?; }
+    if let Some(v) = config.candle_history { dict.set_item("candle_history", v)?; }
+    if let Some(v) = config.lp_lockers { dict.set_item("lp_lockers", v)?; }
+    if let Some(v) = config.dry_run { dict.set_item("dry_run", v)?; }
+    if let Some(v) = config.v2_pool_fee_bps { dict.set_item("v2_pool_fee_bps", v)?; }
+    if let Some(v) = config.router_tax_bps { dict.set_item("router_tax_bps", v)?; }
+    if let Some(v) = config.validate_config { dict.set_item("validate_config", v)?; }
+    if let Some(v) = config.ens_registry { dict.set_item("ens_registry", v)?; }
+    if let Some(v) = config.ens_reverse_lookup { dict.set_item("ens_reverse_lookup", v)?; }
+    if let Some(v) = config.min_native_reserve_wei { dict.set_item("min_native_reserve_wei", v)?; }
+    // Только известные ядру поля: неизвестные отброшены ещё при разборе
+    if let Some(q) = config.chain_quirks {
+        let quirks = PyDict::new(py);
+        quirks.set_item("force_legacy_tx", q.force_legacy_tx)?;
+        if let Some(v) = q.min_gas_price_gwei { quirks.set_item("min_gas_price_gwei", v)?; }
+        if let Some(v) = q.gas_multiplier { quirks.set_item("gas_multiplier", v)?; }
+        if let Some(v) = q.supports_eip1559 { quirks.set_item("supports_eip1559", v)?; }
+        if let Some(v) = q.block_time_secs { quirks.set_item("block_time_secs", v)?; }
+        dict.set_item("chain_quirks", quirks)?;
+    }
 
     let quote_tokens_dict = PyDict::new(py);
     for (key, value) in config.quote_tokens {
@@ -95,3 +259,277 @@ pub fn get_network_config(py: Python<'_>, network_name: String) -> PyResult<PyOb
 
     Ok(dict.to_object(py))
 }
+
+// ===================== ПРОВЕРКА КОНФИГА =====================
+
+// Сколько ждём ответа ноды на каждую живую проверку
+const LIVE_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Проблема конфига: error - ядро с ним работать не будет, warning - не заработает часть функций
+#[derive(Serialize, Debug, Clone)]
+pub struct ConfigIssue {
+    pub severity: &'static str, // "error" | "warning"
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Default, Debug)]
+pub struct ConfigReport {
+    pub issues: Vec<ConfigIssue>,
+}
+
+/// Контракт для проверки байткода: без кода - ошибка (required) или предупреждение
+pub struct ContractCheck {
+    pub field: String,
+    pub address: Address,
+    pub required: bool,
+}
+
+impl ConfigReport {
+    pub fn error(&mut self, field: &str, message: String) {
+        self.issues.push(ConfigIssue { severity: "error", field: field.to_string(), message });
+    }
+
+    pub fn warning(&mut self, field: &str, message: String) {
+        self.issues.push(ConfigIssue { severity: "warning", field: field.to_string(), message });
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|i| i.severity == "error")
+    }
+
+    /// Адрес поля. Не hex, неверная контрольная сумма EIP-55 (смешанный регистр - значит, адрес набирали
+    /// с checksum и в нём опечатка) или нулевой обязательный адрес - ошибка. None - адреса нет или он невалиден
+    pub fn address(&mut self, field: &str, value: &str, required: bool) -> Option<Address> {
+        let value = value.trim();
+        if value.is_empty() {
+            if required { self.error(field, "адрес не задан".into()); }
+            return None;
+        }
+        let address = match crate::address::parse_checked(value) {
+            Ok(a) => a,
+            Err(e) => {
+                self.error(field, e);
+                return None;
+            }
+        };
+        if address.is_zero() {
+            if required { self.error(field, "нулевой адрес".into()); }
+            return None;
+        }
+        Some(address)
+    }
+
+    /// Адрес контракта: проверка формата и, если он задан, - в список проверки байткода
+    pub fn contract(&mut self, checks: &mut Vec<ContractCheck>, field: &str, value: &str, required: bool, code_required: bool) {
+        if let Some(address) = self.address(field, value, required) {
+            checks.push(ContractCheck { field: field.to_string(), address, required: code_required });
+        }
+    }
+
+    /// Живые проверки через rpc_url: eth_chainId совпадает с chain_id, по адресам контрактов есть байткод.
+    /// Нода недоступна - предупреждение, живые проверки пропускаются
+    pub async fn check_live(&mut self, rpc_url: &str, chain_id: u64, contracts: &[ContractCheck]) {
+        let provider = match crate::state::http_provider(rpc_url) {
+            Some(p) => p,
+            None => {
+                self.error("rpc_url", format!("некорректный URL {:?}", rpc_url));
+                return;
+            }
+        };
+        match tokio::time::timeout(LIVE_CHECK_TIMEOUT, provider.get_chainid()).await {
+            Ok(Ok(id)) if id.as_u64() == chain_id => {}
+            Ok(Ok(id)) => {
+                self.error("chain_id", format!("rpc_url отдаёт chain_id {}, в конфиге {}", id, chain_id));
+                return;
+            }
+            Ok(Err(e)) => {
+                self.warning("rpc_url", format!("нода не ответила на eth_chainId ({}) - живые проверки пропущены", e));
+                return;
+            }
+            Err(_) => {
+                self.warning("rpc_url", "нода не ответила на eth_chainId (timeout) - живые проверки пропущены".into());
+                return;
+            }
+        }
+        let codes = futures::future::join_all(contracts.iter().map(|c| {
+            tokio::time::timeout(LIVE_CHECK_TIMEOUT, provider.get_code(c.address, None))
+        })).await;
+        for (contract, code) in contracts.iter().zip(codes) {
+            match code {
+                Ok(Ok(code)) if !code.is_empty() => {}
+                Ok(Ok(_)) => {
+                    let message = format!("по адресу {:?} нет контракта (EOA или другая сеть)", contract.address);
+                    if contract.required { self.error(&contract.field, message); } else { self.warning(&contract.field, message); }
+                }
+                _ => self.warning(&contract.field, format!("байткод {:?} не прочитан", contract.address)),
+            }
+        }
+    }
+}
+
+/// Статические проверки конфига сети; возвращает контракты для проверки байткода
+fn check_network_config(report: &mut ConfigReport, c: &NetworkConfig) -> Vec<ContractCheck> {
+    let mut contracts = Vec::new();
+    let optional = |v: &Option<String>| v.clone().unwrap_or_default();
+    report.contract(&mut contracts, "dex_router_address", &c.dex_router_address, true, true);
+    report.contract(&mut contracts, "v2_factory_address", &optional(&c.v2_factory_address), false, true);
+    report.contract(&mut contracts, "v3_factory_address", &optional(&c.v3_factory_address), false, true);
+    report.contract(&mut contracts, "v3_quoter_address", &optional(&c.v3_quoter_address), false, false);
+    report.contract(&mut contracts, "v2_router_address", &optional(&c.v2_router_address), false, false);
+    report.contract(&mut contracts, "ens_registry", &optional(&c.ens_registry), false, true);
+    let mut quotes: Vec<(&String, &String)> = c.quote_tokens.iter().collect();
+    quotes.sort();
+    for (symbol, address) in quotes {
+        report.contract(&mut contracts, &format!("quote_tokens.{}", symbol), address, true, true);
+    }
+
+    if optional(&c.v2_factory_address).is_empty() && optional(&c.v3_factory_address).is_empty() {
+        report.error("v2_factory_address", "не задана ни одна фабрика (v2_factory_address / v3_factory_address) - пулы не найдутся".into());
+    }
+    if !c.quote_tokens.contains_key(&c.default_quote_currency) {
+        report.error("default_quote_currency", format!("{} нет в quote_tokens", c.default_quote_currency));
+    }
+    let wrapped = format!("W{}", c.native_currency_symbol);
+    if !c.quote_tokens.contains_key(&wrapped) {
+        report.warning("quote_tokens", format!("нет {} (wrapped native) - не работают wrap и auto-fuel", wrapped));
+    }
+    report.address("native_currency_address", &c.native_currency_address, false);
+    if let Some(v) = &c.fee_receiver { report.address("fee_receiver", v, false); }
+    let mut feeds: Vec<(&String, &String)> = c.chainlink_feeds.iter().flatten().collect();
+    feeds.sort();
+    for (symbol, address) in feeds {
+        report.address(&format!("chainlink_feeds.{}", symbol), address, true);
+    }
+    if url::Url::parse(&c.rpc_url).is_err() {
+        report.error("rpc_url", format!("некорректный URL {:?}", c.rpc_url));
+    }
+    for u in c.public_rpc_urls.iter().flatten() {
+        if url::Url::parse(u).is_err() {
+            report.warning("public_rpc_urls", format!("некорректный URL {:?} - нода не попадёт в пул", u));
+        }
+    }
+    contracts
+}
+
+/// Проверка networks/{network_name}.json: адреса (формат, контрольная сумма EIP-55), обязательные поля,
+/// eth_chainId на rpc_url и байткод по адресам роутера, фабрик и quote токенов.
+/// JSON списка [{"severity": "error" | "warning", "field", "message"}], пустой - конфиг в порядке
+#[pyfunction]
+#[pyo3(signature = (network_name, base_path=None))]
+pub fn validate_network_config(py: Python<'_>, network_name: String, base_path: Option<String>) -> PyResult<String> {
+    let file_content = read_network_file(&network_name, base_path.as_deref())?;
+    let mut report = ConfigReport::default();
+    match parse_network_config(&file_content) {
+        Ok(config) => {
+            let contracts = check_network_config(&mut report, &config);
+            if !report.issues.iter().any(|i| i.severity == "error" && (i.field == "rpc_url" || i.field == "chain_id")) {
+                py.allow_threads(|| crate::state::RUNTIME.block_on(report.check_live(&config.rpc_url, config.chain_id, &contracts)));
+            }
+        }
+        Err(e) => report.error("", e),
+    }
+    serde_json::to_string(&report.issues).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+// ===================== ЗАПИСЬ КОНФИГА =====================
+
+/// {network_name}.json в каталоге конфигов (нет ни одного - создаётся первый из network_dirs);
+/// имя без разделителей пути - запись не выйдет за пределы каталога
+fn network_path(network_name: &str, base_path: Option<&str>) -> PyResult<PathBuf> {
+    let valid = !network_name.is_empty()
+        && network_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        return Err(PyValueError::new_err(format!("Invalid network name {:?}: only letters, digits, '_' and '-'", network_name)));
+    }
+    let dir = networks_dir(base_path).unwrap_or_else(|_| network_dirs(base_path).swap_remove(0));
+    Ok(dir.join(format!("{}.json", network_name)))
+}
+
+/// Проверяет конфиг по схеме NetworkConfig и статическими проверками validate_network_config, затем пишет
+/// атомарно (tmp + rename) с отступами. Неизвестные схеме поля сохраняются как есть. Возвращает предупреждения
+fn write_network_file(network_name: &str, config: &serde_json::Value, overwrite: bool, base_path: Option<&str>) -> PyResult<Vec<ConfigIssue>> {
+    let path = network_path(network_name, base_path)?;
+    if !overwrite && path.exists() {
+        return Err(PyFileExistsError::new_err(format!("Network config already exists: {}", path.display())));
+    }
+    let content = serde_json::to_string_pretty(config)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let parsed = parse_network_config(&content).map_err(PyValueError::new_err)?;
+    let mut report = ConfigReport::default();
+    check_network_config(&mut report, &parsed);
+    if report.has_errors() {
+        let errors: Vec<String> = report.issues.iter()
+            .filter(|i| i.severity == "error")
+            .map(|i| format!("{}: {}", i.field, i.message))
+            .collect();
+        return Err(PyValueError::new_err(format!("Invalid network config: {}", errors.join("; "))));
+    }
+
+    if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, content + "\n")?;
+    fs::rename(&tmp, &path)?;
+    Ok(report.issues)
+}
+
+/// Сохранить networks/{network_name}.json. Существующий файл перезаписывается только с overwrite.
+/// Невалидный конфиг - ValueError со списком ошибок; JSON предупреждений (формат validate_network_config)
+#[pyfunction]
+#[pyo3(signature = (network_name, config_json, overwrite=false, base_path=None))]
+pub fn save_network_config(network_name: String, config_json: String, overwrite: bool, base_path: Option<String>) -> PyResult<String> {
+    let config: serde_json::Value = serde_json::from_str(&config_json)
+        .map_err(|e| PyValueError::new_err(format!("Invalid JSON format: {}", e)))?;
+    let warnings = write_network_file(&network_name, &config, overwrite, base_path.as_deref())?;
+    serde_json::to_string(&warnings).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+#[pyfunction]
+#[pyo3(signature = (network_name, base_path=None))]
+pub fn delete_network_config(network_name: String, base_path: Option<String>) -> PyResult<()> {
+    let path = network_path(&network_name, base_path.as_deref())?;
+    if !path.exists() {
+        return Err(PyFileNotFoundError::new_err(format!("Network config not found: {}", path.display())));
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// quote_tokens сети: символ -> адрес
+#[pyfunction]
+#[pyo3(signature = (network_name, base_path=None))]
+pub fn list_quote_tokens(network_name: String, base_path: Option<String>) -> PyResult<HashMap<String, String>> {
+    let file_content = read_network_file(&network_name, base_path.as_deref())?;
+    let config = parse_network_config(&file_content).map_err(PyValueError::new_err)?;
+    Ok(config.quote_tokens)
+}
+
+/// Добавить quote токен в существующую сеть. Символ уже есть или адрес невалиден - ValueError.
+/// Файл переписывается целиком, "${VAR}" в остальных полях сохраняются без подстановки
+#[pyfunction]
+#[pyo3(signature = (network_name, symbol, address, base_path=None))]
+pub fn add_quote_token(network_name: String, symbol: String, address: String, base_path: Option<String>) -> PyResult<String> {
+    let symbol = symbol.trim().to_string();
+    if symbol.is_empty() {
+        return Err(PyValueError::new_err("Quote token symbol is empty"));
+    }
+    let mut report = ConfigReport::default();
+    let field = format!("quote_tokens.{}", symbol);
+    if report.address(&field, &address, true).is_none() {
+        let message = report.issues.first().map(|i| i.message.clone()).unwrap_or_default();
+        return Err(PyValueError::new_err(format!("{}: {}", field, message)));
+    }
+
+    let file_content = read_network_file(&network_name, base_path.as_deref())?;
+    let mut config: serde_json::Value = serde_json::from_str(&file_content)
+        .map_err(|e| PyValueError::new_err(format!("Invalid JSON format: {}", e)))?;
+    let Some(quotes) = config.get_mut("quote_tokens").and_then(|q| q.as_object_mut()) else {
+        return Err(PyValueError::new_err("quote_tokens is missing or not an object"));
+    };
+    if quotes.contains_key(&symbol) {
+        return Err(PyValueError::new_err(format!("Quote token {} already exists in {}", symbol, network_name)));
+    }
+    quotes.insert(symbol, serde_json::Value::String(address.trim().to_string()));
+    let warnings = write_network_file(&network_name, &config, true, base_path.as_deref())?;
+    serde_json::to_string(&warnings).map_err(|e| PyValueError::new_err(e.to_string()))
+}
@@ -1,10 +1,12 @@
 use pyo3::prelude::*;
-use ed25519_dalek::{SigningKey, VerifyingKey};
-use pkcs8::{EncodePublicKey, LineEnding};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use pkcs8::{DecodePublicKey, EncodePublicKey, LineEnding};
 use once_cell::sync::Lazy;
 use std::sync::RwLock;
-use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::io::Write;
 use aes_gcm::{Aes256Gcm, Key, Nonce};
 use aes_gcm::aead::{Aead, KeyInit, AeadCore};
 use pbkdf2::pbkdf2_hmac;
@@ -12,21 +14,67 @@ use sha2::Sha256;
 use rand::RngCore;
 use rand::rngs::OsRng;
 use typenum::Unsigned;
+use zeroize::Zeroizing;
 
 
 // Глобальная переменная для хранения приватного ключа в оперативной памяти
 static BOT_KEYPAIR: Lazy<RwLock<Option<SigningKey>>> = Lazy::new(|| RwLock::new(None));
+// Подпись каждого события моста (поле sig). Выключено по умолчанию
+static EVENT_SIGNING: AtomicBool = AtomicBool::new(false);
 
-const SALT_SIZE: usize = 16;
-const NONCE_SIZE: usize = <Aes256Gcm as AeadCore>::NonceSize::USIZE;
+pub(crate) const SALT_SIZE: usize = 16;
+pub(crate) const NONCE_SIZE: usize = <Aes256Gcm as AeadCore>::NonceSize::USIZE;
 
 /// Выводит 32-байтный ключ из пароля и соли
-fn derive_key_from_password(password: &str, salt: &[u8]) -> Key<Aes256Gcm> {
+pub(crate) fn derive_key_from_password(password: &str, salt: &[u8]) -> Key<Aes256Gcm> {
     let mut key = [0u8; 32];
     pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, 480_000, &mut key);
     key.into()
 }
 
+/// Расшифровывает файл ключа: salt | nonce | AES-GCM(ключ)
+fn decrypt_key_file(file_content: &[u8], master_password: &str) -> PyResult<Zeroizing<Vec<u8>>> {
+    if file_content.len() < SALT_SIZE + NONCE_SIZE {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>("Invalid key file format"));
+    }
+    
+    let (salt, rest) = file_content.split_at(SALT_SIZE);
+    let (nonce_bytes, encrypted_key) = rest.split_at(NONCE_SIZE);
+    
+    let key = derive_key_from_password(master_password, salt);
+    let cipher = Aes256Gcm::new(&key);
+    
+    // Создаем пустой Nonce и копируем в него данные из среза
+    let mut nonce = Nonce::default();
+    nonce.copy_from_slice(nonce_bytes);
+
+    // Передаем ссылку &nonce, так как метод decrypt ожидает именно ее
+    cipher.decrypt(&nonce, encrypted_key)
+        .map(Zeroizing::new)
+        .map_err(|_| PyErr::new::<pyo3::exceptions::PyValueError, _>("Decryption failed. Wrong master password?"))
+}
+
+/// Шифрует ключ с новыми солью и nonce
+fn encrypt_key_file(secret: &[u8], master_password: &str) -> PyResult<Vec<u8>> {
+    let mut csprng = OsRng;
+    let mut salt = [0u8; SALT_SIZE];
+    csprng.fill_bytes(&mut salt);
+    
+    let key = derive_key_from_password(master_password, &salt);
+    let cipher = Aes256Gcm::new(&key);
+    
+    let nonce = Aes256Gcm::generate_nonce(&mut csprng);
+
+    let encrypted_key = cipher.encrypt(&nonce, secret)
+        .map_err(|_| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>("Encryption failed"))?;
+
+    let mut file_content = Vec::new();
+    file_content.extend_from_slice(&salt);
+    file_content.extend_from_slice(&nonce);
+    file_content.extend_from_slice(&encrypted_key);
+    Ok(file_content)
+}
+
 #[pyfunction]
 /// Инициализирует или загружает ключ из зашифрованного файла.
 pub fn init_or_load_keys(key_path_str: String, master_password: &str) -> PyResult<()> {
@@ -35,26 +83,10 @@ pub fn init_or_load_keys(key_path_str: String, master_password: &str) -> PyResul
 
     if key_path.exists() {
         let file_content = fs::read(&key_path)?;
-        if file_content.len() < SALT_SIZE + NONCE_SIZE {
-            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>("Invalid key file format"));
-        }
-        
-        let (salt, rest) = file_content.split_at(SALT_SIZE);
-        let (nonce_bytes, encrypted_key) = rest.split_at(NONCE_SIZE);
-        
-        let key = derive_key_from_password(master_password, salt);
-        let cipher = Aes256Gcm::new(&key);
-        
-        // Создаем пустой Nonce и копируем в него данные из среза
-        let mut nonce = Nonce::default();
-        nonce.copy_from_slice(nonce_bytes);
-
-        // Передаем ссылку &nonce, так как метод decrypt ожидает именно ее
-        let decrypted_key_bytes = cipher.decrypt(&nonce, encrypted_key)
-            .map_err(|_| PyErr::new::<pyo3::exceptions::PyValueError, _>("Decryption failed. Wrong master password?"))?;
+        let decrypted_key_bytes = decrypt_key_file(&file_content, master_password)?;
 
         let signing_key = SigningKey::from_bytes(
-            &decrypted_key_bytes.try_into().map_err(|_| PyErr::new::<pyo3::exceptions::PyValueError, _>("Invalid key data after decryption"))?
+            decrypted_key_bytes.as_slice().try_into().map_err(|_| PyErr::new::<pyo3::exceptions::PyValueError, _>("Invalid key data after decryption"))?
         );
         *keypair_guard = Some(signing_key);
 
@@ -62,25 +94,11 @@ pub fn init_or_load_keys(key_path_str: String, master_password: &str) -> PyResul
         let mut csprng = OsRng;
         let signing_key = SigningKey::generate(&mut csprng);
         
-        let mut salt = [0u8; SALT_SIZE];
-        csprng.fill_bytes(&mut salt);
-        
-        let key = derive_key_from_password(master_password, &salt);
-        let cipher = Aes256Gcm::new(&key);
-        
-        let nonce = Aes256Gcm::generate_nonce(&mut csprng);
-
-        let encrypted_key = cipher.encrypt(&nonce, signing_key.to_bytes().as_ref())
-            .map_err(|_| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>("Encryption failed"))?;
+        let file_content = encrypt_key_file(signing_key.to_bytes().as_ref(), master_password)?;
         
         if let Some(parent_dir) = key_path.parent() {
             fs::create_dir_all(parent_dir)?;
         }
-
-        let mut file_content = Vec::new();
-        file_content.extend_from_slice(&salt);
-        file_content.extend_from_slice(&nonce);
-        file_content.extend_from_slice(&encrypted_key);
         
         fs::write(&key_path, file_content)?;
         
@@ -89,6 +107,43 @@ pub fn init_or_load_keys(key_path_str: String, master_password: &str) -> PyResul
     Ok(())
 }
 
+/// Пишет файл и дожидается его попадания на диск
+fn write_synced(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+#[pyfunction]
+/// Перешифровывает файл ключа новым паролем. Старый файл лежит в .bak, пока новый не записан на диск:
+/// при сбое посреди записи ключ восстанавливается из .bak
+pub fn change_master_password(key_path: String, old_password: &str, new_password: &str) -> PyResult<()> {
+    let key_path = PathBuf::from(key_path);
+    let file_content = fs::read(&key_path)?;
+    // Неверный старый пароль - ошибка до любых изменений на диске
+    let secret = decrypt_key_file(&file_content, old_password)?;
+    let new_content = encrypt_key_file(&secret, new_password)?;
+
+    let tmp_path = key_path.with_extension("tmp");
+    let bak_path = key_path.with_extension("bak");
+    write_synced(&bak_path, &file_content)?;
+    write_synced(&tmp_path, &new_content)?;
+    fs::rename(&tmp_path, &key_path)?;
+    #[cfg(unix)]
+    if let Some(dir) = key_path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::File::open(dir)?.sync_all()?;
+    }
+    fs::remove_file(&bak_path)?;
+    Ok(())
+}
+
+#[pyfunction]
+/// Проверяет пароль без загрузки ключа в память бота
+pub fn verify_master_password(key_path: String, password: &str) -> PyResult<bool> {
+    let file_content = fs::read(key_path)?;
+    Ok(decrypt_key_file(&file_content, password).is_ok())
+}
+
 #[pyfunction]
 pub fn get_public_key() -> PyResult<String> {
     let keypair_guard = BOT_KEYPAIR.read().unwrap();
@@ -101,3 +156,46 @@ pub fn get_public_key() -> PyResult<String> {
         Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>("Keys not initialized."))
     }
 }
+
+fn keys_not_initialized() -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyRuntimeError, _>("Keys not initialized. Call init_or_load_keys first")
+}
+
+#[pyfunction]
+/// Detached ed25519 подпись, hex
+pub fn sign_message(message: &[u8]) -> PyResult<String> {
+    let keypair_guard = BOT_KEYPAIR.read().unwrap();
+    let key = keypair_guard.as_ref().ok_or_else(keys_not_initialized)?;
+    Ok(hex::encode(key.sign(message).to_bytes()))
+}
+
+#[pyfunction]
+pub fn verify_signature(public_key_pem: &str, message: &[u8], signature: &str) -> PyResult<bool> {
+    let public_key = VerifyingKey::from_public_key_pem(public_key_pem)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid public key PEM: {}", e)))?;
+    let Some(signature) = hex::decode(signature.trim_start_matches("0x")).ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok()) else { return Ok(false); };
+    Ok(public_key.verify(message, &signature).is_ok())
+}
+
+#[pyfunction]
+/// Включает подпись событий моста. Без загруженного ключа включить нельзя
+pub fn set_event_signing(enabled: bool) -> PyResult<()> {
+    if enabled && BOT_KEYPAIR.read().unwrap().is_none() {
+        return Err(keys_not_initialized());
+    }
+    EVENT_SIGNING.store(enabled, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Подпись JSON события для моста, если она включена
+pub(crate) fn event_signature(json: &str) -> Option<String> {
+    if !EVENT_SIGNING.load(Ordering::Relaxed) { return None; }
+    sign_payload(json.as_bytes())
+}
+
+/// Подпись исходящих данных (webhook), если ключ загружен
+pub(crate) fn sign_payload(payload: &[u8]) -> Option<String> {
+    let keypair_guard = BOT_KEYPAIR.read().unwrap();
+    keypair_guard.as_ref().map(|key| hex::encode(key.sign(payload).to_bytes()))
+}
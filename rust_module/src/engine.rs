@@ -1,381 +1,1201 @@
 use pyo3::prelude::*;
 use tokio::sync::mpsc;
-use once_cell::sync::Lazy;
+use crate::state::{instance, EngineId, EngineLocal, DEFAULT_ENGINE, with_engine};
 use ethers::prelude::*;
 use std::sync::atomic::Ordering;
 use std::str::FromStr;
+use zeroize::Zeroize;
+use tokio::task::AbortHandle;
+use futures::FutureExt;
+use std::panic::AssertUnwindSafe;
 
-use crate::bridge::{EngineCommand, EngineEvent, emit_event, emit_log};
-use crate::state::{RUNTIME, SHUTDOWN_FLAG, CORE_STATE, RPC_POOL, RpcNode, TRACKED_WALLETS, MONITOR_HANDLE, INTERNAL_HANDLE, RPC_CHECKER_HANDLE, PNL_HANDLE};
+use crate::bridge::{EngineCommand, EngineRequest, EngineEvent, EngineStateSnapshot, MonitorSettings, MonitorConfigInfo, ChainQuirksSettings, REQUEST_ID, current_request_id, TokenSubscriptionInfo, RpcNodeInfo, emit_event, emit_log};
+use crate::state::{spawn, SHUTDOWN_FLAG, TRADING_PAUSED, DRY_RUN, CORE_STATE, RPC_POOL, DEFAULT_MAX_BLOCK_LAG, invalidate_providers, TRACKED_WALLETS, MONITOR_HANDLE, DISCOVERY_HANDLES, SUBSCRIPTIONS_CHANGED, TRANSFER_SET_CHANGED, INTERNAL_HANDLE, RECEIPT_HANDLE, RPC_CHECKER_HANDLE, PNL_HANDLE, SCHEDULER_HANDLE, METRICS_HANDLE, PRICE_FEED_HANDLE, CANDLE_HANDLE, TWAP_HANDLES, FEE_TIERS_HANDLE, SESSION_HANDLE, USD_PRICE_MANUAL, DEFAULT_CANDLE_INTERVALS, DEFAULT_CANDLE_HISTORY, TokenContext, PriceAlert, PriceDirection, LimitOrder, ExitStrategy, PnlEntry, RecurringTrade, GasStrategy, ApprovalMode, BroadcastMode, SlippageMode, WalletKey, WatchedAddress, CopyTrade, MonitorConfig, ChainQuirks, FuelLimits, FuelMode, parse_wallet_key, DEFAULT_V3_FEE_TIERS};
 use crate::monitor;
 use crate::execution;
 use crate::pnl;
+use crate::orders;
+use crate::scheduler;
+use crate::routing;
+use crate::bundles;
+use crate::snipe;
+use crate::approvals;
+use crate::impact;
+use crate::math;
+use crate::journal;
+use crate::token_cache;
+use crate::token_metadata;
+use crate::metrics;
+use crate::notifier;
+use crate::price_feed;
+use crate::candles;
+use crate::tape;
+use crate::holders;
+use crate::audit;
+use crate::resolver;
+use crate::positions;
+use crate::session;
+use crate::history;
+use crate::balances;
+use crate::rpc_batch::{self, BatchCall};
+use crate::config::{ConfigReport, NetworkConfig};
 
-pub static COMMAND_TX: Lazy<mpsc::UnboundedSender<EngineCommand>> = Lazy::new(|| {
-    let (tx, rx) = mpsc::unbounded_channel::<EngineCommand>();
-    RUNTIME.spawn(engine_loop(rx));
+pub static COMMAND_TX: EngineLocal<mpsc::UnboundedSender<EngineRequest>> = EngineLocal::new(|| {
+    let (tx, rx) = mpsc::unbounded_channel::<EngineRequest>();
+    spawn(engine_loop(rx));
     tx
 });
 
+// Сколько Init/Shutdown ждут остановки задач прошлой сессии
+const TASK_STOP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+// Допустимые тайминги монитора (MonitorSettings)
+const IDLE_TIMEOUT_BOUNDS_SECS: (u64, u64) = (5, 600);
+const RECONNECT_DELAY_BOUNDS_SECS: (u64, u64) = (1, 60);
+const RECEIPT_POLL_BOUNDS_MS: (u64, u64) = (100, 10_000);
+const WORKER_TICK_BOUNDS_MS: (u64, u64) = (200, 60_000);
+const RPC_HEALTH_BOUNDS_SECS: (u64, u64) = (1, 300);
+
+/// spawn задачи-результата команды: события из неё несут request_id этой команды, паника уходит в лог
+fn spawn_scoped<F>(fut: F) -> tokio::task::JoinHandle<()>
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    spawn(REQUEST_ID.scope(current_request_id(), async move {
+        if let Err(panic) = AssertUnwindSafe(fut).catch_unwind().await {
+            recover_after_panic();
+            emit_log("ERROR", format!("💥 Паника в задаче команды: {}", panic_message(panic.as_ref())));
+        }
+    }))
+}
+
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    panic.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "неизвестная причина".to_string())
+}
+
+/// Паника под write-блокировкой отравляет общие RwLock, и каждая следующая команда падала бы на unwrap.
+/// Состояние могло остаться частично обновлённым, но ядро продолжает работу
+fn recover_after_panic() {
+    CORE_STATE.clear_poison();
+    RPC_POOL.clear_poison();
+    TRACKED_WALLETS.clear_poison();
+}
+
+/// Адрес из поля команды; неверный - CommandError с командой и полем
+fn parse_address_field(command: &str, field: &str, value: &str) -> Option<Address> {
+    let address = crate::address::parse(value);
+    if address.is_none() {
+        emit_event(EngineEvent::invalid_field(command, field, format!("некорректный адрес {:?}", value)));
+    }
+    address
+}
+
+/// Необязательный адрес: пусто - нулевой (не задан), мусор - CommandError, а не молча нулевой адрес
+fn parse_optional_address_field(command: &str, field: &str, value: &str) -> Option<Address> {
+    if value.trim().is_empty() { return Some(Address::zero()); }
+    parse_address_field(command, field, value)
+}
+
+/// Необязательный адрес-Option: None / "" - не задан (Some(None)), мусор - CommandError и None
+fn parse_address_option(command: &str, field: &str, value: Option<&str>) -> Option<Option<Address>> {
+    match value.filter(|v| !v.trim().is_empty()) {
+        Some(v) => parse_address_field(command, field, v).map(Some),
+        None => Some(None),
+    }
+}
+
+/// Список адресов команды: первый невалидный - CommandError по полю и None (а не молча выброшенный кошелёк)
+fn parse_address_list(command: &str, field: &str, values: &[String]) -> Option<Vec<Address>> {
+    values.iter().map(|v| parse_address_field(command, field, v)).collect()
+}
+
+/// validate_config в Init: ошибки уходят CommandError по полю, предупреждения - в лог. false - Init отклоняется
+async fn init_config_valid(rpc_url: &str, chain_id: u64, contracts: [&str; 5], quote_tokens: &std::collections::HashMap<String, String>) -> bool {
+    let [router, quoter, v2_factory, v3_factory, wrapped_native] = contracts;
+    let mut report = ConfigReport::default();
+    let mut checks = Vec::new();
+    report.contract(&mut checks, "router", router, true, true);
+    report.contract(&mut checks, "quoter", quoter, false, false);
+    report.contract(&mut checks, "v2_factory", v2_factory, false, true);
+    report.contract(&mut checks, "v3_factory", v3_factory, false, true);
+    report.contract(&mut checks, "wrapped_native", wrapped_native, false, true);
+    let mut quotes: Vec<(&String, &String)> = quote_tokens.iter().collect();
+    quotes.sort();
+    for (symbol, address) in quotes {
+        report.contract(&mut checks, &format!("quote_tokens.{}", symbol), address, true, true);
+    }
+    if !report.has_errors() {
+        report.check_live(rpc_url, chain_id, &checks).await;
+    }
+    for issue in &report.issues {
+        if issue.severity == "error" {
+            emit_event(EngineEvent::invalid_field("Init", &issue.field, issue.message.clone()));
+        } else {
+            emit_log("WARNING", format!("⚠️ Init.{}: {}", issue.field, issue.message));
+        }
+    }
+    !report.has_errors()
+}
+
+/// ReloadNetworkConfig: сверяет файл сети с CORE_STATE и применяет то, что меняется без остановки сессии
+/// (публичные RPC, quote токены, комиссии). Возвращает (применено, требует нового Init)
+fn apply_network_config(c: &NetworkConfig) -> (Vec<String>, Vec<String>) {
+    let mut changed = Vec::new();
+    let mut requires_reinit = Vec::new();
+    let address = |v: Option<&String>| v.and_then(|a| crate::address::parse(a)).unwrap_or_default();
+    let wrapped = format!("W{}", c.native_currency_symbol);
+    let wrapped_field = format!("quote_tokens.{}", wrapped);
+    let quote_tokens: std::collections::HashMap<String, Address> = c.quote_tokens.iter()
+        .filter_map(|(k, v)| crate::address::parse(v).map(|a| (k.clone(), a)))
+        .collect();
+    {
+        let mut s = CORE_STATE.write().unwrap();
+        let contracts = [
+            ("dex_router_address", address(Some(&c.dex_router_address)), s.router_address),
+            ("v3_quoter_address", address(c.v3_quoter_address.as_ref()), s.quoter_address),
+            ("v2_factory_address", address(c.v2_factory_address.as_ref()), s.v2_factory_address),
+            ("v3_factory_address", address(c.v3_factory_address.as_ref()), s.v3_factory_address),
+            (wrapped_field.as_str(), address(c.quote_tokens.get(&wrapped)), s.wrapped_native_address),
+            ("native_currency_address", address(Some(&c.native_currency_address)), s.native_address),
+            ("ens_registry", address(c.ens_registry.as_ref()), s.ens_registry.unwrap_or_default()),
+        ];
+        if c.chain_id != s.chain_id { requires_reinit.push("chain_id".to_string()); }
+        for (field, file, current) in contracts {
+            if file != current { requires_reinit.push(field.to_string()); }
+        }
+
+        if quote_tokens != s.quote_tokens {
+            s.quote_tokens = quote_tokens;
+            changed.push("quote_tokens".to_string());
+        }
+        let v2_fee = c.v2_pool_fee_bps.filter(|f| *f < 10_000).unwrap_or(math::DEFAULT_V2_FEE_BPS);
+        if v2_fee != s.v2_pool_fee_bps {
+            s.v2_pool_fee_bps = v2_fee;
+            changed.push("v2_pool_fee_bps".to_string());
+        }
+        let router_tax = c.router_tax_bps.filter(|f| *f < 10_000).unwrap_or(math::DEFAULT_ROUTER_TAX_BPS);
+        if router_tax != s.router_tax_bps {
+            s.router_tax_bps = router_tax;
+            changed.push("router_tax_bps".to_string());
+        }
+    }
+    if changed.iter().any(|f| f == "quote_tokens") {
+        TRANSFER_SET_CHANGED.notify_one();
+    }
+
+    // Пул: приватные ноды (RPC из Init, AddRpcNode is_private) не трогаем, публичные сверяем с файлом
+    let mut wanted: Vec<String> = c.public_rpc_urls.clone().unwrap_or_default();
+    wanted.push(c.rpc_url.clone());
+    wanted.retain(|u| url::Url::parse(u).is_ok());
+    let (added, removed) = {
+        let mut pool = RPC_POOL.write().unwrap();
+        let removed: Vec<String> = pool.nodes.iter()
+            .filter(|n| !n.is_private && !wanted.contains(&n.url))
+            .map(|n| n.url.clone())
+            .collect();
+        pool.nodes.retain(|n| n.is_private || wanted.contains(&n.url));
+        let mut added = Vec::new();
+        for u in wanted {
+            if !pool.nodes.iter().any(|n| n.url == u) {
+                pool.add_node(u.clone(), false);
+                added.push(u);
+            }
+        }
+        (added, removed)
+    };
+    for u in &removed {
+        invalidate_providers(Some(u));
+        emit_log("INFO", format!("➖ RPC удалена: {}", metrics::node_label(u)));
+    }
+    for u in &added {
+        emit_log("INFO", format!("➕ RPC добавлена: {}", metrics::node_label(u)));
+        let u = u.clone();
+        spawn_scoped(async move { monitor::probe_node(&u, true).await });
+    }
+    if !added.is_empty() || !removed.is_empty() {
+        changed.push("public_rpc_urls".to_string());
+    }
+    (changed, requires_reinit)
+}
+
+/// Останавливает фоновые задачи сессии: abort и ожидание фактического завершения.
+/// Задача, прерванная посреди работы, иначе успела бы дописать в CORE_STATE уже после очистки
+async fn stop_background_tasks() {
+    let mut handles: Vec<AbortHandle> = Vec::new();
+    for slot in [&MONITOR_HANDLE, &INTERNAL_HANDLE, &RECEIPT_HANDLE, &PNL_HANDLE, &RPC_CHECKER_HANDLE, &SCHEDULER_HANDLE,
+                 &METRICS_HANDLE, &PRICE_FEED_HANDLE, &CANDLE_HANDLE, &FEE_TIERS_HANDLE, &SESSION_HANDLE] {
+        handles.extend(slot.lock().unwrap().take());
+    }
+    handles.extend(DISCOVERY_HANDLES.lock().unwrap().drain().map(|(_, h)| h));
+    handles.extend(TWAP_HANDLES.lock().unwrap().drain(..));
+    for h in &handles { h.abort(); }
+
+    let deadline = tokio::time::Instant::now() + TASK_STOP_TIMEOUT;
+    while handles.iter().any(|h| !h.is_finished()) {
+        if tokio::time::Instant::now() >= deadline {
+            let alive = handles.iter().filter(|h| !h.is_finished()).count();
+            emit_log("WARNING", format!("⏳ {} фоновых задач не остановились за {:?}", alive, TASK_STOP_TIMEOUT));
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+}
+
 fn bnb_to_wei(bnb: f64) -> U256 {
     if bnb <= 0.0 { return U256::zero(); }
     U256::from((bnb * 1e18) as u128)
 }
 
-async fn engine_loop(mut rx: mpsc::UnboundedReceiver<EngineCommand>) {
+/// Сумма из команды: число > 0 или "max" (Some(None)). Мусор - CommandError по полю amount и None
+fn parse_amount_arg(command: &str, amount: &str) -> Option<Option<f64>> {
+    if amount.eq_ignore_ascii_case("max") {
+        return Some(None);
+    }
+    match amount.parse::<f64>() {
+        Ok(v) if v > 0.0 => Some(Some(v)),
+        _ => {
+            emit_event(EngineEvent::invalid_field(command, "amount", format!("нужно число > 0 или \"max\", получено {:?}", amount)));
+            None
+        }
+    }
+}
+
+/// Токен из команды: None / "" / "native" / 0xeee... - нативная монета (Some(None)).
+/// Мусор - CommandError по полю и None
+fn parse_token_arg(command: &str, token: Option<&str>) -> Option<Option<Address>> {
+    let token = token.filter(|t| !t.eq_ignore_ascii_case("native"));
+    Some(parse_address_option(command, "token", token)?.filter(|a| *a != Address::repeat_byte(0xee)))
+}
+
+/// WrapNative/UnwrapNative: amount - число или "max"
+fn spawn_wrap(wallets: Vec<String>, amount: String, wrap: bool) {
+    let command = if wrap { "WrapNative" } else { "UnwrapNative" };
+    let Some(amount) = parse_amount_arg(command, &amount) else { return; };
+    let Some(wallets) = parse_address_list(command, "wallets", &wallets) else { return; };
+    let (keys, gas, chain_id) = {
+        let s = CORE_STATE.read().unwrap();
+        let keys = wallets.iter()
+            .filter_map(|a| s.wallet_keys.get(a).cloned())
+            .collect();
+        (keys, s.manual_gas_price_gwei, s.chain_id)
+    };
+    spawn_scoped(async move {
+        let evs = execution::run_wrap(keys, wrap, amount, gas, chain_id).await;
+        for e in evs { emit_event(e); }
+    });
+}
+
+/// Переводы с кошелька; после подтверждения обновляет балансы отправителя и получателей
+fn spawn_transfers(command: &str, from: Address, token: Option<Address>, legs: Vec<execution::TransferLeg>) {
+    let (pk, chain_id, gas, quote, disperse) = {
+        let s = CORE_STATE.read().unwrap();
+        (s.wallet_keys.get(&from).cloned(), s.chain_id, s.manual_gas_price_gwei, s.fuel_quote_address, s.disperse_address)
+    };
+    let Some(pk) = pk else {
+        emit_event(EngineEvent::invalid_field(command, "from_wallet", format!("нет ключа для кошелька {:?}", from)));
+        return;
+    };
+    let mut wallets = vec![from];
+    wallets.extend(legs.iter().map(|l| l.to));
+    spawn_scoped(async move {
+        let (evs, hashes) = match disperse {
+            Some(d) if token.is_none() && legs.len() > 1 => execution::run_disperse_native(pk, chain_id, d, legs, gas).await,
+            _ => execution::run_transfers(pk, chain_id, token, legs, gas, U256::zero()).await,
+        };
+        for e in evs { emit_event(e); }
+        if hashes.is_empty() { return; }
+        execution::wait_for_confirmations(&hashes, std::time::Duration::from_secs(120)).await;
+        spawn_balances_refresh(wallets, vec![token.unwrap_or(quote)]);
+    });
+}
+
+/// Разовая рассылка native и ERC-20 балансов кошельков (RefreshAllBalances, новый кошелёк, переводы).
+/// Символ - из опрашиваемых quote, после перевода произвольного ERC-20 - "TOKEN"
+fn spawn_balances_refresh(wallets: Vec<Address>, tokens: Vec<Address>) {
+    spawn_scoped(async move {
+        let url_opt = { let p = RPC_POOL.read().unwrap(); p.get_fastest_node() };
+        let Some(url) = url_opt else { return; };
+
+        let quotes = monitor::balance_quote_tokens();
+        let tokens: Vec<(Address, String)> = tokens.into_iter()
+            .filter(|t| !t.is_zero())
+            .map(|t| {
+                let symbol = quotes.iter().find(|(q, _)| *q == t).map(|(_, sym)| sym.clone());
+                (t, symbol.unwrap_or_else(|| "TOKEN".into()))
+            })
+            .collect();
+
+        let calls: Vec<BatchCall> = wallets.iter().map(|&w| BatchCall::Balance(w)).collect();
+        let native = rpc_batch::batch(&url, &calls).await;
+        for (wallet, res) in wallets.iter().zip(&native) {
+            if let Some(balance) = rpc_batch::as_u256(res) {
+                let float_val = execution::u256_to_f64_safe(balance, 18);
+                emit_event(EngineEvent::BalanceUpdate {
+                    wallet: format!("{:?}", wallet),
+                    token: "0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee".into(),
+                    wei: balance.to_string(),
+                    float_val,
+                    symbol: "NATIVE".into()
+                });
+            }
+        }
+
+        monitor::emit_token_balances(&url, &wallets, &tokens, false).await;
+    });
+}
+
+/// Добавляет кошелёк в ядро: ключ, отслеживание, балансы. Общий путь AddWallet и хранилища ключей
+pub(crate) fn register_wallet(addr: Address, key: WalletKey) {
+    CORE_STATE.write().unwrap().wallet_keys.insert(addr, key);
+    {
+        let mut tracked = TRACKED_WALLETS.write().unwrap();
+        if !tracked.contains(&addr) { tracked.push(addr); }
+    }
+    emit_log("INFO", format!("🔑 Кошелек добавлен: {:?}", addr));
+    let quotes = monitor::balance_quote_tokens().into_iter().map(|(q, _)| q).collect();
+    spawn_balances_refresh(vec![addr], quotes);
+    notify_monitor();
+}
+
+/// Монитор держит снимок кошельков в фильтрах - переподписываем его, если он запущен
+fn notify_monitor() {
+    if MONITOR_HANDLE.lock().unwrap().as_ref().is_some_and(|h| !h.is_finished()) {
+        SUBSCRIPTIONS_CHANGED.notify_one();
+    }
+}
+
+/// Тайминги монитора из Init / UpdateSettings: значения вне границ ограничиваются с предупреждением,
+/// idle timeout короче двух блоков (если время блока уже измерено) - тоже
+fn apply_monitor_settings(c: &mut MonitorConfig, m: &MonitorSettings, block_time: Option<std::time::Duration>) {
+    let bounded = |field: &str, v: u64, (lo, hi): (u64, u64)| {
+        if !(lo..=hi).contains(&v) {
+            emit_log("WARNING", format!("⚠️ monitor.{} = {} вне [{}, {}], ограничен", field, v, lo, hi));
+        }
+        v.clamp(lo, hi)
+    };
+    if let Some(v) = m.idle_timeout_secs {
+        c.idle_timeout = std::time::Duration::from_secs(bounded("idle_timeout_secs", v, IDLE_TIMEOUT_BOUNDS_SECS));
+        if let Some(b) = block_time.filter(|b| c.idle_timeout < *b * 2) {
+            emit_log("WARNING", format!("⚠️ monitor.idle_timeout_secs = {} меньше двух блоков сети ({:.1}с) - действует {:.1}с",
+                v, b.as_secs_f64(), (b * 2).as_secs_f64()));
+        }
+    }
+    if let Some(v) = m.reconnect_delay_secs {
+        c.reconnect_delay = std::time::Duration::from_secs(bounded("reconnect_delay_secs", v, RECONNECT_DELAY_BOUNDS_SECS));
+    }
+    if let Some(v) = m.receipt_poll_ms {
+        c.receipt_poll = std::time::Duration::from_millis(bounded("receipt_poll_ms", v, RECEIPT_POLL_BOUNDS_MS));
+    }
+    if let Some(v) = m.worker_tick_ms {
+        c.worker_tick = std::time::Duration::from_millis(bounded("worker_tick_ms", v, WORKER_TICK_BOUNDS_MS));
+    }
+    if let Some(v) = m.rpc_health_secs {
+        c.rpc_health_interval = std::time::Duration::from_secs(bounded("rpc_health_secs", v, RPC_HEALTH_BOUNDS_SECS));
+    }
+}
+
+/// chain_quirks из Init. Невалидное значение - CommandError по полю и значение по умолчанию, остальной Init применяется
+fn chain_quirks(q: &ChainQuirksSettings) -> ChainQuirks {
+    let positive = |field: &str, v: Option<f64>, allow_zero: bool| v.filter(|v| {
+        let ok = v.is_finite() && (*v > 0.0 || (allow_zero && *v == 0.0));
+        if !ok {
+            emit_event(EngineEvent::invalid_field("Init", &format!("chain_quirks.{}", field), format!("недопустимое значение {}", v)));
+        }
+        ok
+    });
+    let defaults = ChainQuirks::default();
+    ChainQuirks {
+        force_legacy_tx: q.force_legacy_tx,
+        min_gas_price_gwei: positive("min_gas_price_gwei", q.min_gas_price_gwei, true).unwrap_or(defaults.min_gas_price_gwei),
+        gas_multiplier: positive("gas_multiplier", q.gas_multiplier, false).unwrap_or(defaults.gas_multiplier),
+        supports_eip1559: q.supports_eip1559,
+        block_time: positive("block_time_secs", q.block_time_secs, false).map(std::time::Duration::from_secs_f64),
+    }
+}
+
+/// Снимок состояния ядра для отладки. wallet_keys сюда не попадают ни в каком виде -
+/// кошельки берём из TRACKED_WALLETS, большие кэши отдаём только размером
+fn state_snapshot() -> EngineEvent {
+    let tracked_wallets = TRACKED_WALLETS.read().unwrap().iter().map(|w| format!("{:?}", w)).collect();
+    let rpc_nodes = RPC_POOL.read().unwrap().nodes.iter().map(|n| RpcNodeInfo {
+        url: n.url.clone(), latency_us: u64::try_from(n.latency).ok(), fails: n.fails, is_private: n.is_private, quarantined: n.quarantined,
+        block: n.block, lag: n.lag, pinned: n.pinned,
+        requests: n.requests.load(Ordering::Relaxed),
+        rate_limit_rps: n.limiter.as_ref().map(|l| l.rps()),
+        penalty_ms: n.penalty_until
+            .map(|t| t.saturating_duration_since(std::time::Instant::now()).as_millis() as u64)
+            .unwrap_or(0)
+    }).collect();
+
+    let s = CORE_STATE.read().unwrap();
+    let subscriptions = s.tokens.iter().map(|(token, ctx)| TokenSubscriptionInfo {
+        token: format!("{:?}", token),
+        quote: format!("{:?}", ctx.quote),
+        quote_symbol: ctx.quote_symbol.clone(),
+        pool_address: ctx.pool_address.map(|a| format!("{:?}", a)),
+        pool_type: ctx.pool_type.clone(),
+        pool_fee: ctx.pool_fee,
+        liquidity_usd: ctx.liquidity_usd,
+        spot_price: ctx.spot_price,
+        pinned_pool: ctx.pinned_pool.map(|a| format!("{:?}", a)),
+        route: ctx.route.iter().map(|a| format!("{:?}", a)).collect(),
+        pool_targets: ctx.pool_targets.iter().map(|a| format!("{:?}", a)).collect(),
+    }).collect();
+
+    EngineEvent::StateSnapshot(Box::new(EngineStateSnapshot {
+        chain_id: s.chain_id,
+        router: format!("{:?}", s.router_address),
+        quoter: format!("{:?}", s.quoter_address),
+        v2_factory: format!("{:?}", s.v2_factory_address),
+        v3_factory: format!("{:?}", s.v3_factory_address),
+        wrapped_native: format!("{:?}", s.wrapped_native_address),
+        gas_price_gwei: execution::u256_to_f64_safe(s.gas_price, 9),
+        manual_gas_price_gwei: s.manual_gas_price_gwei,
+        slippage: s.slippage,
+        swap_deadline_secs: s.swap_deadline_secs,
+        v2_pool_fee_bps: s.v2_pool_fee_bps,
+        router_tax_bps: s.router_tax_bps,
+        trading_paused: execution::trading_paused(),
+        dry_run: execution::dry_run(),
+        fuel_enabled: s.fuel_enabled,
+        fuel_threshold: s.fuel_threshold.to_string(),
+        fuel_amount: s.fuel_amount.to_string(),
+        fuel_quote_address: format!("{:?}", s.fuel_quote_address),
+        fuel_max_attempts: s.fuel_limits.max_attempts,
+        fuel_cooldown_secs: s.fuel_limits.cooldown.as_secs(),
+        fuel_quarantine_secs: s.fuel_limits.quarantine.as_secs(),
+        fuel_mode: match s.fuel_mode { FuelMode::Swap => "swap", FuelMode::Treasury => "treasury" }.to_string(),
+        min_native_reserve_wei: s.min_native_reserve.to_string(),
+        fuel_treasury: (!s.fuel_treasury.is_zero()).then(|| format!("{:?}", s.fuel_treasury)),
+        fuel_quarantined: s.auto_fuel_attempts.iter().filter(|(_, a)| a.quarantined_at_ms.is_some()).map(|(w, _)| format!("{:?}", w)).collect(),
+        nonces: s.nonce_map.iter().map(|(w, n)| (format!("{:?}", w), *n)).collect(),
+        pending_txs: s.pending_txs.len(),
+        tracked_wallets,
+        quote_tokens: s.quote_tokens.iter().map(|(sym, a)| (sym.clone(), format!("{:?}", a))).collect(),
+        subscriptions,
+        max_token_subscriptions: s.max_tokens,
+        rpc_nodes,
+        decimals_cached: s.decimals_cache.len(),
+        v2_pools_cached: s.v2_reserves.len(),
+        v3_pools_cached: s.v3_states.len(),
+        price_alerts: s.price_alerts.len(),
+        limit_orders: s.limit_orders.len(),
+        exit_strategies: s.exit_strategies.len(),
+        recurring_trades: s.recurring_trades.len(),
+        monitor: MonitorConfigInfo {
+            idle_timeout_secs: s.monitor.effective_idle_timeout(s.block_time).as_secs_f64(),
+            reconnect_delay_secs: s.monitor.reconnect_delay.as_secs_f64(),
+            receipt_poll_ms: s.monitor.receipt_poll.as_millis() as u64,
+            worker_tick_ms: s.monitor.worker_tick.as_millis() as u64,
+            rpc_health_secs: s.monitor.rpc_health_interval.as_secs_f64(),
+            block_time_ms: s.block_time.map(|b| b.as_millis() as u64),
+        },
+        chain_quirks: ChainQuirksSettings {
+            force_legacy_tx: s.chain_quirks.force_legacy_tx,
+            min_gas_price_gwei: Some(s.chain_quirks.min_gas_price_gwei),
+            gas_multiplier: Some(s.chain_quirks.gas_multiplier),
+            supports_eip1559: s.chain_quirks.supports_eip1559,
+            block_time_secs: s.chain_quirks.block_time.map(|b| b.as_secs_f64()),
+        },
+        balances: s.balances.iter().fold(std::collections::HashMap::new(), |mut acc, ((w, t), (wei, _))| {
+            acc.entry(format!("{:?}", w)).or_insert_with(std::collections::HashMap::new).insert(format!("{:?}", t), wei.to_string());
+            acc
+        }),
+        ignored_tokens: s.ignored_tokens.iter().map(|t| format!("{:?}", t)).collect(),
+        min_balance_usd: s.min_balance_usd,
+    }))
+}
+
+async fn engine_loop(mut rx: mpsc::UnboundedReceiver<EngineRequest>) {
     emit_log("SUCCESS", "Rust Engine Core: Active".into());
     
-    while let Some(cmd) = rx.recv().await {
-        match cmd {
-            EngineCommand::Init { 
-                rpc_url, wss_url, chain_id, router, quoter, v2_factory, v3_factory, 
-                wrapped_native, native_address, wallets, public_rpc_urls, fuel_settings,
-                quote_symbol, quote_tokens
-            } => {
-                SHUTDOWN_FLAG.store(true, Ordering::Relaxed);
+    while let Some(EngineRequest { command, request_id, kind }) = rx.recv().await {
+        let is_shutdown = matches!(command, EngineCommand::Shutdown);
+        // События, порождённые командой (в т.ч. из её задач), эхом несут request_id.
+        // Паника одной команды не должна останавливать цикл: иначе COMMAND_TX остаётся без получателя
+        REQUEST_ID.scope(request_id, async move {
+            if let Err(panic) = AssertUnwindSafe(handle_command(command)).catch_unwind().await {
+                recover_after_panic();
+                emit_event(EngineEvent::CommandError {
+                    message: format!("Паника при обработке команды: {}", panic_message(panic.as_ref())),
+                    command: kind,
+                    field: None,
+                });
+            }
+        }).await;
+        if is_shutdown { break; }
+    }
+}
+
+async fn handle_command(mut cmd: EngineCommand) {
+    // Имена вместо hex в полях адресов (только с ens_registry из Init)
+    if !resolver::resolve_names(&mut cmd).await { return; }
+    match cmd {
+        EngineCommand::Init { 
+            rpc_url, wss_url, chain_id, router, quoter, v2_factory, v3_factory, 
+            wrapped_native, native_address, wallets, public_rpc_urls, fuel_settings,
+            quote_symbol, quote_tokens, v3_fee_tiers, discover_v3_fee_tiers, journal_path, metrics_port,
+            private_relay_urls, broadcast_mode, max_block_lag, rpc_rate_limits, public_rpc_rps, token_cache_path, notify, price_feed,
+            candle_intervals, candle_history, lp_lockers, dry_run, v2_pool_fee_bps, router_tax_bps, monitor, chain_quirks: quirks, validate_config, network_name,
+            ens_registry, ens_reverse_lookup, session_path, min_native_reserve_wei
+        } => {
+            // Невалидный Init отклоняется до остановки текущей сессии - она продолжает работать
+            let Some(router_addr) = parse_address_field("Init", "router", &router) else { return; };
+            let Some(quoter_addr) = parse_address_field("Init", "quoter", &quoter) else { return; };
+            let Some(v2_fact) = parse_optional_address_field("Init", "v2_factory", &v2_factory) else { return; };
+            let Some(v3_fact) = parse_optional_address_field("Init", "v3_factory", &v3_factory) else { return; };
+            let Some(w_native) = parse_optional_address_field("Init", "wrapped_native", &wrapped_native) else { return; };
+            let Some(native) = parse_optional_address_field("Init", "native_address", &native_address) else { return; };
+            let Some(fuel_quote_address) = parse_optional_address_field("Init", "fuel_settings.fuel_quote_address", &fuel_settings.fuel_quote_address) else { return; };
+            let Some(lp_lockers) = parse_address_list("Init", "lp_lockers", &lp_lockers) else { return; };
+            let Some(ens_registry) = parse_optional_address_field("Init", "ens_registry", ens_registry.as_deref().unwrap_or_default()) else { return; };
+            let Ok(min_native_reserve) = U256::from_dec_str(min_native_reserve_wei.as_deref().unwrap_or("0")) else {
+                emit_event(EngineEvent::invalid_field("Init", "min_native_reserve_wei", format!("не число wei: {:?}", min_native_reserve_wei)));
+                return;
+            };
+            let mut quote_addresses = std::collections::HashMap::new();
+            for (symbol, address) in &quote_tokens {
+                let Some(a) = parse_address_field("Init", &format!("quote_tokens.{}", symbol), address) else { return; };
+                quote_addresses.insert(symbol.clone(), a);
+            }
+            if wallets.iter().any(|(address, _)| parse_address_field("Init", "wallets", address).is_none()) { return; }
+            if validate_config && !init_config_valid(&rpc_url, chain_id, [&router, &quoter, &v2_factory, &v3_factory, &wrapped_native], &quote_tokens).await {
+                return;
+            }
+            SHUTDOWN_FLAG.store(true, Ordering::Relaxed);
+            
+            stop_background_tasks().await;
+            // Последний снимок прошлой сессии - до очистки состояния
+            session::save_now();
+            // Задачи прошлой сессии остановлены - дальше ни одно её событие не обгонит EngineReady новой
+            crate::bridge::reset_session();
+            SHUTDOWN_FLAG.store(false, Ordering::Relaxed);
+
+            let fuel_threshold = bnb_to_wei(fuel_settings.auto_fuel_threshold);
+            let fuel_amount = bnb_to_wei(fuel_settings.auto_fuel_amount);
+            let fuel_enabled = fuel_settings.auto_fuel_enabled;
+            let fuel_defaults = FuelLimits::default();
+            let fuel_limits = FuelLimits {
+                max_attempts: fuel_settings.max_attempts.unwrap_or(fuel_defaults.max_attempts).max(1),
+                cooldown: fuel_settings.cooldown_secs.map_or(fuel_defaults.cooldown, std::time::Duration::from_secs),
+                quarantine: fuel_settings.quarantine_secs.map_or(fuel_defaults.quarantine, std::time::Duration::from_secs),
+            };
+            let fuel_mode = match fuel_settings.fuel_mode.as_deref() {
+                None | Some("") => FuelMode::Swap,
+                Some(m) => FuelMode::parse(m).unwrap_or_else(|| {
+                    emit_event(EngineEvent::invalid_field("Init", "fuel_settings.fuel_mode", format!("неизвестный режим {:?}, используется swap", m)));
+                    FuelMode::Swap
+                }),
+            };
+            let fuel_treasury = match fuel_settings.treasury_wallet.as_deref().filter(|t| !t.is_empty()) {
+                Some(t) => parse_address_field("Init", "fuel_settings.treasury_wallet", t).unwrap_or_default(),
+                None => Address::zero(),
+            };
+            let fuel_treasury_reserve = fuel_settings.treasury_reserve.map_or(fuel_threshold, bnb_to_wei);
+            let quirks = chain_quirks(&quirks);
+
+            let mut all_urls = public_rpc_urls; 
+            all_urls.push(rpc_url.clone());
+            invalidate_providers(None);
+            {
+                let mut pool = RPC_POOL.write().unwrap();
+                pool.nodes.clear();
+                pool.rate_limits = rpc_rate_limits;
+                pool.public_rps = public_rpc_rps;
+                for u in &all_urls {
+                    pool.add_node(u.clone(), u == &rpc_url);
+                }
+                pool.max_block_lag = max_block_lag.unwrap_or(DEFAULT_MAX_BLOCK_LAG);
+            }
+
+            {
+                let mut s = CORE_STATE.write().unwrap();
                 
-                if let Some(h) = MONITOR_HANDLE.lock().unwrap().take() { h.abort(); }
-                if let Some(h) = INTERNAL_HANDLE.lock().unwrap().take() { h.abort(); }
-                if let Some(h) = PNL_HANDLE.lock().unwrap().take() { h.abort(); }
-                if let Some(h) = RPC_CHECKER_HANDLE.lock().unwrap().take() { h.abort(); }
+                s.v2_reserves.clear();
+                s.v3_states.clear();
+                s.v3_tick_windows.clear();
+                s.liquidity_baselines.clear();
+                s.tokens.clear();
+                s.decimals_cache.clear(); 
+                token_metadata::forget(None);
+                holders::forget_all();
+                audit::forget_all();
+                s.usd_prices.clear();
+                s.usd_price_meta.clear();
+                s.balances.clear();
+                s.ignored_tokens.clear();
+                s.candles.clear();
+                let mut intervals: Vec<u64> = candle_intervals.unwrap_or_else(|| DEFAULT_CANDLE_INTERVALS.to_vec())
+                    .into_iter().filter(|i| *i > 0).collect();
+                intervals.sort_unstable();
+                intervals.dedup();
+                s.candle_intervals = intervals;
+                s.candle_history = candle_history.filter(|n| *n > 0).unwrap_or(DEFAULT_CANDLE_HISTORY);
+                s.lp_lockers = lp_lockers;
+                s.usd_price_max_age = price_feed.stale_after_secs.filter(|s| *s > 0).map(std::time::Duration::from_secs);
+                s.head_block = 0;
+                // Время блока из конфига не измеряется: от него сразу считаются интервал опроса и idle timeout
+                s.block_time = quirks.block_time;
+                // Резерв сети из её конфига; UpdateSettings меняет его до следующего Init
+                s.min_native_reserve = min_native_reserve;
+                // Газ прошлой сети: до первого блока новой - resolve_gas_price
+                s.gas_price = U256::zero();
+                s.gas_oracle = None;
+                s.monitor = MonitorConfig::default();
+                apply_monitor_settings(&mut s.monitor, &monitor, quirks.block_time);
+                s.chain_quirks = quirks;
+                s.nonce_map.clear();
+                s.pending_txs.clear();
+                s.wallet_keys.clear();
+                s.watched_addresses.clear();
+
+                s.chain_id = chain_id; 
+                s.router_address = router_addr; 
+                s.quoter_address = quoter_addr; 
+                s.v2_factory_address = v2_fact; 
+                s.v3_factory_address = v3_fact; 
+                s.wrapped_native_address = w_native; 
+                s.native_address = native;
+                s.wss_url = wss_url.clone(); 
+                s.network_name = network_name.unwrap_or_default();
+                s.ens_registry = Some(ens_registry).filter(|r| !r.is_zero());
+                s.ens_reverse_lookup = ens_reverse_lookup;
+                resolver::forget_all();
                 
-                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
-                SHUTDOWN_FLAG.store(false, Ordering::Relaxed);
-
-                let router_addr = Address::from_str(&router).unwrap();
-                let quoter_addr = Address::from_str(&quoter).unwrap();
-                let v2_fact = Address::from_str(&v2_factory).unwrap_or(Address::zero());
-                let v3_fact = Address::from_str(&v3_factory).unwrap_or(Address::zero());
-                let w_native = Address::from_str(&wrapped_native).unwrap_or(Address::zero());
-                let native = Address::from_str(&native_address).unwrap_or(Address::zero());
-
-                let fuel_threshold = bnb_to_wei(fuel_settings.auto_fuel_threshold);
-                let fuel_amount = bnb_to_wei(fuel_settings.auto_fuel_amount);
-                let fuel_quote_address = Address::from_str(&fuel_settings.fuel_quote_address).unwrap_or(Address::zero());
-                let fuel_enabled = fuel_settings.auto_fuel_enabled;
-
-                let mut all_urls = public_rpc_urls; 
-                all_urls.push(rpc_url.clone());
-                RPC_POOL.write().unwrap().nodes = all_urls.iter().map(|u| RpcNode { 
-                    url: u.clone(), latency: u128::MAX, is_private: u == &rpc_url, fails: 0 
-                }).collect();
-
-                {
-                    let mut s = CORE_STATE.write().unwrap();
-                    
-                    s.v2_reserves.clear();
-                    s.v3_states.clear();
-                    s.decimals_cache.clear(); 
-                    s.usd_prices.clear();
-                    s.nonce_map.clear();
-                    s.pending_txs.clear();
-                    s.wallet_keys.clear();
-
-                    s.chain_id = chain_id; 
-                    s.router_address = router_addr; 
-                    s.quoter_address = quoter_addr; 
-                    s.v2_factory_address = v2_fact; 
-                    s.v3_factory_address = v3_fact; 
-                    s.wrapped_native_address = w_native; 
-                    s.native_address = native;
-                    s.wss_url = wss_url.clone(); 
-                    
-                    s.fuel_enabled = fuel_enabled;
-                    s.fuel_threshold = fuel_threshold;
-                    s.fuel_amount = fuel_amount;
-                    s.fuel_quote_address = fuel_quote_address;
-                    s.quote_symbol = quote_symbol.clone();
-                    s.quote_tokens = quote_tokens.into_iter()
-                        .filter_map(|(k, v)| Address::from_str(&v).ok().map(|a| (k, a)))
-                        .collect();
-                    
-                    TRACKED_WALLETS.write().unwrap().clear();
-                    for (a, k) in wallets { 
-                        if let Ok(addr) = Address::from_str(&a) { 
-                            s.wallet_keys.insert(addr, k); 
-                            TRACKED_WALLETS.write().unwrap().push(addr); 
-                        } 
-                    }
+                s.fuel_enabled = fuel_enabled;
+                s.fuel_threshold = fuel_threshold;
+                s.fuel_amount = fuel_amount;
+                s.fuel_limits = fuel_limits;
+                s.fuel_mode = fuel_mode;
+                s.fuel_treasury = fuel_treasury;
+                s.fuel_treasury_reserve = fuel_treasury_reserve;
+                s.auto_fuel_attempts.clear();
+                s.fuel_quote_address = fuel_quote_address;
+                s.quote_symbol = quote_symbol.clone();
+                s.quote_tokens = quote_addresses;
+                s.v3_fee_tiers = v3_fee_tiers
+                    .filter(|tiers| !tiers.is_empty())
+                    .unwrap_or_else(|| DEFAULT_V3_FEE_TIERS.to_vec());
+                s.v2_pool_fee_bps = v2_pool_fee_bps.filter(|f| *f < 10_000).unwrap_or(math::DEFAULT_V2_FEE_BPS);
+                s.router_tax_bps = router_tax_bps.filter(|f| *f < 10_000).unwrap_or(math::DEFAULT_ROUTER_TAX_BPS);
+                s.private_relay_urls = private_relay_urls;
+                s.broadcast_mode = broadcast_mode.as_deref().and_then(BroadcastMode::parse).unwrap_or(BroadcastMode::Public);
+                if s.broadcast_mode != BroadcastMode::Public && s.private_relay_urls.is_empty() {
+                    emit_log("WARNING", "🕶️ Режим private/both без private_relay_urls - рассылка через публичные RPC".into());
+                    s.broadcast_mode = BroadcastMode::Public;
                 }
                 
-                if fuel_enabled {
-                    emit_log("INFO", format!(
-                        "⛽ Auto-Fuel включен: порог={:.6}, amount={:.6}, quote={:?}", 
-                        fuel_settings.auto_fuel_threshold,
-                        fuel_settings.auto_fuel_amount,
-                        fuel_quote_address
-                    ));
+                TRACKED_WALLETS.write().unwrap().clear();
+                for (a, k) in wallets { 
+                    let key = parse_wallet_key(k);
+                    if let (Some(addr), Some(key)) = (crate::address::parse(&a), key) { 
+                        s.wallet_keys.insert(addr, key); 
+                        TRACKED_WALLETS.write().unwrap().push(addr); 
+                    } 
                 }
-                
-                if !quote_symbol.is_empty() {
-                    emit_log("INFO", format!("💱 Quote symbol установлен: {}", quote_symbol));
+            }
+            
+            if fuel_enabled {
+                emit_log("INFO", format!(
+                    "⛽ Auto-Fuel включен: порог={:.6}, amount={:.6}, quote={:?}, попыток={}, cooldown={}s, карантин={}s", 
+                    fuel_settings.auto_fuel_threshold,
+                    fuel_settings.auto_fuel_amount,
+                    fuel_quote_address,
+                    fuel_limits.max_attempts,
+                    fuel_limits.cooldown.as_secs(),
+                    fuel_limits.quarantine.as_secs()
+                ));
+                if fuel_mode == FuelMode::Treasury {
+                    let has_key = CORE_STATE.read().unwrap().wallet_keys.contains_key(&fuel_treasury);
+                    if has_key {
+                        emit_log("INFO", format!("⛽ Auto-Fuel из казны {:?}, резерв {:.6}", fuel_treasury, execution::u256_to_f64_safe(fuel_treasury_reserve, 18)));
+                    } else {
+                        emit_log("WARNING", format!("⛽ Auto-Fuel treasury: нет ключа казны {:?} - пополнение не работает, пока её не добавят (AddWallet)", fuel_treasury));
+                    }
                 }
-                
-                *RPC_CHECKER_HANDLE.lock().unwrap() = Some(RUNTIME.spawn(monitor::rpc_health_checker(all_urls)).abort_handle());
-                
-                let wss_bg = wss_url.clone();
-                *INTERNAL_HANDLE.lock().unwrap() = Some(RUNTIME.spawn(monitor::start_background_worker(wss_bg)).abort_handle());
-                
-                *PNL_HANDLE.lock().unwrap() = Some(RUNTIME.spawn(pnl::start_pnl_worker()).abort_handle());
-                
-                emit_event(EngineEvent::EngineReady);
-                emit_event(EngineEvent::ConnectionStatus {
-                    connected: true,
-                    message: "Ядро инициализировано".into()
-                });
             }
+            
+            if !quote_symbol.is_empty() {
+                emit_log("INFO", format!("💱 Quote symbol установлен: {}", quote_symbol));
+            }
+            
+            if let Some(path) = journal_path.filter(|p| !p.is_empty()) {
+                journal::open(path, chain_id);
+            }
+            DRY_RUN.store(dry_run, Ordering::SeqCst);
+            if dry_run {
+                emit_log("WARNING", "🧪 Dry-run: транзакции подписываются, но не отправляются".into());
+            }
+            // decimals прошлых сессий: токены отрисуются верно, даже если RPC на старте недоступны
+            if let Some(path) = token_cache_path.filter(|p| !p.is_empty()) {
+                let known = token_cache::open(path, chain_id);
+                CORE_STATE.write().unwrap().decimals_cache.extend(known);
+            }
+            CORE_STATE.write().unwrap().restored_pins.clear();
+            match session_path.filter(|p| !p.is_empty()) {
+                Some(path) => {
+                    session::open(path, chain_id);
+                    *SESSION_HANDLE.lock().unwrap() = Some(spawn(session::start_saver()).abort_handle());
+                }
+                None => session::close(),
+            }
+            
+            notifier::apply_settings(*notify, true);
+            metrics::reset();
+            if let Some(port) = metrics_port.filter(|p| *p > 0) {
+                *METRICS_HANDLE.lock().unwrap() = Some(spawn(metrics::serve(port)).abort_handle());
+            }
+            
+            *RPC_CHECKER_HANDLE.lock().unwrap() = Some(spawn(monitor::rpc_health_checker()).abort_handle());
+            
+            let wss_bg = wss_url.clone();
+            *INTERNAL_HANDLE.lock().unwrap() = Some(spawn(monitor::start_background_worker(wss_bg)).abort_handle());
+            
+            *RECEIPT_HANDLE.lock().unwrap() = Some(spawn(monitor::receipt_poller()).abort_handle());
+            
+            *PNL_HANDLE.lock().unwrap() = Some(spawn(pnl::start_pnl_worker()).abort_handle());
+            
+            *PRICE_FEED_HANDLE.lock().unwrap() = Some(spawn(price_feed::start_price_feed(*price_feed)).abort_handle());
+            
+            *CANDLE_HANDLE.lock().unwrap() = Some(spawn(candles::start_candle_worker()).abort_handle());
+            
+            *SCHEDULER_HANDLE.lock().unwrap() = Some(spawn(scheduler::start_scheduler()).abort_handle());
+            
+            if discover_v3_fee_tiers {
+                *FEE_TIERS_HANDLE.lock().unwrap() = Some(spawn(monitor::discover_v3_fee_tiers()).abort_handle());
+            }
+            
+            emit_event(EngineEvent::EngineReady);
+            emit_event(EngineEvent::ConnectionStatus {
+                connected: true,
+                message: "Ядро инициализировано".into(),
+                polling: false,
+            });
+        }
 
-            EngineCommand::SwitchToken { token_address, quote_address, quote_symbol } => {
-                let t = Address::from_str(&token_address).unwrap();
-                let q = Address::from_str(&quote_address).unwrap();
+        EngineCommand::AddTokenSubscription { token_address, quote_address, quote_symbol } => {
+            let Some(t) = parse_address_field("AddTokenSubscription", "token_address", &token_address) else { return; };
+            let Some(q) = parse_address_field("AddTokenSubscription", "quote_address", &quote_address) else { return; };
 
-                let all_quotes: Vec<(String, Address)> = {
-                    let s = CORE_STATE.read().unwrap();
-                    s.quote_tokens.iter().map(|(k, v)| (k.clone(), *v)).collect()
-                };
-                
-                {
-                    let mut s = CORE_STATE.write().unwrap();
-                    // ОЧИСТКА ДАННЫХ ПРЕДЫДУЩЕГО ПУЛА
-                    s.selected_pool_type = None;
-                    s.selected_pool_fee = 0;
-                    s.selected_pool_address = None;
-                    s.selected_pool_liquidity_usd = 0.0;
-                    s.selected_pool_spot_price = 0.0;
-                    s.v2_reserves.clear();
-                    s.v3_states.clear();
-                    
-                    s.fuel_quote_address = q;
-                    s.quote_symbol = quote_symbol.clone();
-                    emit_log("INFO", format!("🔄 Quote токен установлен: {:?} ({})", q, quote_symbol));
+            let all_quotes: Vec<(String, Address)> = {
+                let s = CORE_STATE.read().unwrap();
+                s.quote_tokens.iter().map(|(k, v)| (k.clone(), *v)).collect()
+            };
+            
+            {
+                let mut s = CORE_STATE.write().unwrap();
+                // Повторная подписка на тот же токен (например, смена quote) начинает контекст заново
+                if !s.tokens.contains_key(&t) && s.tokens.len() >= s.max_tokens {
+                    emit_event(EngineEvent::rejected("AddTokenSubscription", format!("достигнут лимит подписок ({}), сначала отпишитесь от другого токена", s.max_tokens)));
+                    return;
                 }
-                
-                RUNTIME.spawn(async move {
-                    execution::check_and_auto_approve_background(t, q).await;
-                });
+                let mut ctx = TokenContext::new(q, quote_symbol.clone());
+                // Пул, закреплённый в прошлой сессии (RestoreSession до подписки)
+                ctx.pinned_pool = s.restored_pins.remove(&t);
+                s.tokens.insert(t, ctx);
+                s.fuel_quote_address = q;
+                emit_log("INFO", format!("🔄 Подписка на {:?}, quote {:?} ({}), всего токенов: {}", t, q, quote_symbol, s.tokens.len()));
+            }
+            
+            spawn_scoped(async move {
+                execution::check_and_auto_approve_background(t, q).await;
+            });
 
-                if let Some(old) = MONITOR_HANDLE.lock().unwrap().take() { 
-                    old.abort(); 
-                }
-                
+            let discovery = spawn_scoped(monitor::subscribe_token(t, q, all_quotes));
+            if let Some(old) = DISCOVERY_HANDLES.lock().unwrap().insert(t, discovery.abort_handle()) {
+                old.abort();
+            }
+
+            // Один монитор на все подписки - запускаем при первой
+            let mut monitor_handle = MONITOR_HANDLE.lock().unwrap();
+            if monitor_handle.as_ref().is_none_or(|h| h.is_finished()) {
                 let wss = { CORE_STATE.read().unwrap().wss_url.clone() };
-                let handle = RUNTIME.spawn(monitor::start_unified_websocket_monitor(wss, t, q, all_quotes));
-                *MONITOR_HANDLE.lock().unwrap() = Some(handle.abort_handle());
+                *monitor_handle = Some(spawn(monitor::start_unified_websocket_monitor(wss)).abort_handle());
             }
+        }
 
-            EngineCommand::UnsubscribeToken { token_address } => {
-                // Отписка от токена - очистка состояния
-                emit_log("INFO", format!("📭 Отписка от токена: {}", token_address));
-                
+        EngineCommand::RemoveTokenSubscription { token_address } => {
+            let Some(t) = parse_address_field("RemoveTokenSubscription", "token_address", &token_address) else { return; };
+            emit_log("INFO", format!("📭 Отписка от токена: {}", token_address));
+            
+            if let Some(h) = DISCOVERY_HANDLES.lock().unwrap().remove(&t) { h.abort(); }
+
+            let remaining = {
                 let mut s = CORE_STATE.write().unwrap();
-                s.selected_pool_address = None;
-                s.selected_pool_type = None;
-                s.selected_pool_fee = 0;
-                s.selected_pool_liquidity_usd = 0.0;
-                s.selected_pool_spot_price = 0.0;
-                
-                emit_log("SUCCESS", "📭 Состояние токена очищено".into());
+                let Some(ctx) = s.tokens.remove(&t) else {
+                    emit_log("WARNING", format!("📭 Подписки на {} нет", token_address));
+                    return;
+                };
+                // Кэши пулов чистим, только если пул не нужен другим подпискам
+                let still_used: std::collections::HashSet<H160> = s.tokens.values()
+                    .flat_map(|c| c.pool_targets.iter().chain(c.route_pools.iter()).copied())
+                    .collect();
+                for pool in ctx.pool_targets.iter().chain(ctx.route_pools.iter()) {
+                    if still_used.contains(pool) { continue; }
+                    s.v2_reserves.remove(pool);
+                    s.v3_states.remove(pool);
+                    s.v3_tick_windows.remove(pool);
+                    s.liquidity_baselines.remove(pool);
+                    s.candles.retain(|(p, _), _| p != pool);
+                    tape::forget(*pool);
+                }
+                s.tokens.len()
+            };
+
+            if remaining == 0 {
+                if let Some(h) = MONITOR_HANDLE.lock().unwrap().take() { h.abort(); }
+            } else {
+                SUBSCRIPTIONS_CHANGED.notify_one();
             }
-            
-            EngineCommand::CalcImpact { token_address, quote_address, amount_in, is_buy } => {
-                let t_addr = Address::from_str(&token_address).unwrap();
-                let q_addr = Address::from_str(&quote_address).unwrap();
-                RUNTIME.spawn(async move {
-                    let (t_in, t_out) = if is_buy { (q_addr, t_addr) } else { (t_addr, q_addr) };
-                    let (p_type, p_fee, quoter) = { 
-                        let s = CORE_STATE.read().unwrap(); 
-                        (s.selected_pool_type.clone().unwrap_or_default(), s.selected_pool_fee, s.quoter_address) 
-                    };
-                    
-                    let dec_in = monitor::get_decimals_cached(t_in).await;
-                    let dec_out = monitor::get_decimals_cached(t_out).await;
-                    
-                    let amt_wei: U256 = match ethers::utils::parse_units(amount_in, dec_in as u32) { 
-                        Ok(v) => v.into(), 
-                        Err(_) => {
-                            emit_event(EngineEvent::ImpactUpdate { 
-                                token: token_address, quote: quote_address.clone(), amount_in, impact_pct: 0.0, expected_out: "0".into(), is_buy
-                            });
-                            return;
-                        }
-                    };
-                    
-                    if amt_wei.is_zero() { 
-                        emit_event(EngineEvent::ImpactUpdate { 
-                            token: token_address, quote: quote_address.clone(), amount_in, impact_pct: 0.0, expected_out: "0".into(), is_buy
-                        });
-                        return; 
-                    }
+            emit_log("SUCCESS", format!("📭 Состояние токена очищено, осталось подписок: {}", remaining));
+        }
 
-                    if p_type.is_empty() {
-                        emit_event(EngineEvent::ImpactUpdate { 
-                            token: token_address, quote: quote_address.clone(), amount_in, impact_pct: 0.0, expected_out: "0".into(), is_buy
-                        });
-                        return;
-                    }
+        EngineCommand::SelectPool { token_address, pool_address } => {
+            let Some(t) = parse_address_field("SelectPool", "token_address", &token_address) else { return; };
+            let Some(pin) = parse_address_option("SelectPool", "pool_address", pool_address.as_deref()) else { return; };
 
-                    let exp_out = if p_type == "V3" { 
-                        // Проверяем что V3 пул реально существует
-                        let has_v3_state = { 
-                            let s = CORE_STATE.read().unwrap(); 
-                            !s.v3_states.is_empty() && s.selected_pool_address.is_some()
-                        };
-                        if !has_v3_state {
-                            emit_log("DEBUG", "CalcImpact: V3 pool not selected, skipping quoter".to_string());
-                            U256::zero()
-                        } else {
-                            execution::calculate_expected_out_v3_quoted(t_in, t_out, amt_wei, p_fee, quoter).await 
-                        }
-                    } else { 
-                        // Проверяем что V2 пул реально существует
-                        let has_v2_reserves = { 
-                            let s = CORE_STATE.read().unwrap(); 
-                            !s.v2_reserves.is_empty() && s.selected_pool_address.is_some()
-                        };
-                        if !has_v2_reserves {
-                            emit_log("DEBUG", "CalcImpact: V2 pool not selected".to_string());
-                            U256::zero()
-                        } else {
-                            execution::calculate_expected_out_v2_pure(t_in, t_out, amt_wei) 
-                        }
+            // Выбирать можно только из последнего PoolCandidates токена - все они уже в фильтре подписки
+            let (choice, quote) = {
+                let mut s = CORE_STATE.write().unwrap();
+                let Some(ctx) = s.tokens.get_mut(&t) else {
+                    emit_event(EngineEvent::invalid_field("SelectPool", "token_address", format!("нет подписки на токен {}", token_address)));
+                    return;
+                };
+                let choice = match pin {
+                    Some(addr) => ctx.ranked_pools.iter().find(|p| p.address == addr).cloned(),
+                    None => ctx.ranked_pools.first().cloned(),
+                };
+                if choice.is_some() || pin.is_none() {
+                    ctx.pinned_pool = pin;
+                }
+                (choice, ctx.quote)
+            };
+
+            let Some(pool) = choice else {
+                emit_event(EngineEvent::invalid_field("SelectPool", "pool_address", format!("пул {} не найден среди кандидатов токена {}", pool_address.unwrap_or_default(), token_address)));
+                return;
+            };
+
+            match pin {
+                Some(_) => emit_log("INFO", format!("📌 Пул закреплён вручную: {} {:?}, fee {}", pool.pool_type, pool.address, pool.fee)),
+                None => emit_log("INFO", "📌 Ручной выбор пула снят, используем автоматический".into()),
+            }
+            spawn_scoped(monitor::apply_pool_selection(pool, t, quote));
+        }
+        
+        EngineCommand::CalcImpact { token_address, quote_address, amount_in, is_buy } => {
+            let Some(t_addr) = parse_address_field("CalcImpact", "token_address", &token_address) else { return; };
+            let Some(q_addr) = parse_address_field("CalcImpact", "quote_address", &quote_address) else { return; };
+            spawn_scoped(impact::request(t_addr, q_addr, token_address, quote_address, amount_in, is_buy));
+        }
+        
+        EngineCommand::ExecuteTrade { action, token, quote_token, amount, wallets, gas_gwei, slippage, amounts_wei, twap, routing, gas_strategy, percent, deadline_secs, slippage_mode } => {
+            let Some(t_addr) = parse_address_field("ExecuteTrade", "token", &token) else { return; };
+            let Some(q_addr) = parse_address_field("ExecuteTrade", "quote_token", &quote_token) else { return; };
+            let Some(wallet_addresses) = parse_address_list("ExecuteTrade", "wallets", &wallets) else { return; };
+            if let Some(pct) = percent {
+                let has_amounts = amounts_wei.as_ref().is_some_and(|a| !a.is_empty());
+                let message = if action != "sell" {
+                    Some("percent - только для продажи")
+                } else if has_amounts || amount > 0.0 {
+                    Some("percent нельзя сочетать с amount/amounts_wei")
+                } else if !(pct > 0.0 && pct <= 100.0) {
+                    Some("percent должен быть в (0, 100]")
+                } else {
+                    None
+                };
+                if let Some(message) = message {
+                    emit_event(EngineEvent::invalid_field("ExecuteTrade", "percent", message.into()));
+                    return;
+                }
+            }
+            let mode = match slippage_mode.as_deref().map(|m| SlippageMode::parse(m).ok_or(m)).transpose() {
+                Ok(m) => m,
+                Err(m) => {
+                    emit_event(EngineEvent::invalid_field("ExecuteTrade", "slippage_mode", format!("неизвестный режим {:?}", m)));
+                    return;
+                }
+            };
+            let (r, k, g, c) = {
+                let s = CORE_STATE.read().unwrap();
+                let keys: Vec<WalletKey> = wallet_addresses.iter()
+                    .filter_map(|a| s.wallet_keys.get(a).cloned())
+                    .collect();
+                let manual = if gas_gwei > 0.0 { gas_gwei } else { s.manual_gas_price_gwei };
+                (s.router_address, keys, GasStrategy::parse(gas_strategy.as_deref(), manual), s.chain_id)
+            };
+            let trade = execution::TradeSettings { gas: g, slippage, deadline_secs, slippage_mode: mode };
+            if let Some(tw) = twap.filter(|tw| tw.slices > 1) {
+                let handle = spawn_scoped(async move {
+                    // Части TWAP делят объём, зафиксированный при старте
+                    let amounts_wei = match percent {
+                        Some(pct) => Some(execution::percent_amounts(t_addr, &k, pct).await),
+                        None => amounts_wei,
                     };
-                    
-                    let idl_out = execution::calculate_ideal_out(t_in, t_out, amt_wei, dec_in, is_buy, dec_out);
-                    
-                    let impact = if !idl_out.is_zero() && exp_out <= idl_out {
-                        let idl_f = execution::u256_to_f64_safe(idl_out, dec_out as u32);
-                        let exp_f = execution::u256_to_f64_safe(exp_out, dec_out as u32);
-                        if idl_f > 0.0 { ((idl_f - exp_f) / idl_f) * 100.0 } else { 0.0 }
-                    } else { 0.0 };
-                    
-                    emit_event(EngineEvent::ImpactUpdate { 
-                        token: token_address, quote: quote_address, amount_in, impact_pct: impact, expected_out: exp_out.to_string(), is_buy
-                    });
+                    let req = execution::BatchRequest {
+                        keys: k, router: r, action, token: t_addr, quote: q_addr, amount, chain_id: c, amounts_wei, percent: None
+                    };
+                    execution::run_twap_trade(req, tw, trade).await
                 });
+                let mut handles = TWAP_HANDLES.lock().unwrap();
+                handles.retain(|h| !h.is_finished());
+                handles.push(handle.abort_handle());
+                return;
             }
-            
-            EngineCommand::ExecuteTrade { action, token, quote_token, amount, wallets, gas_gwei, slippage, v3_fee, amounts_wei } => {
-                let t_addr = Address::from_str(&token).unwrap();
-                let q_addr = Address::from_str(&quote_token).unwrap();
-                let (r, k, g, c) = {
-                    let s = CORE_STATE.read().unwrap();
-                    let keys = wallets.iter()
-                        .filter_map(|w| Address::from_str(w).ok().and_then(|a| s.wallet_keys.get(&a).cloned()))
-                        .collect();
-                    (s.router_address, keys, if gas_gwei > 0.0 { gas_gwei } else { s.manual_gas_price_gwei }, s.chain_id)
-                };
-                RUNTIME.spawn(async move {
-                    let evs = execution::run_batch_trade(k, r, action, t_addr, q_addr, amount, g, slippage, v3_fee, c, amounts_wei).await;
+            if routing.as_deref() == Some("split") {
+                spawn_scoped(async move {
+                    let amounts_wei = match percent {
+                        Some(pct) => Some(execution::percent_amounts(t_addr, &k, pct).await),
+                        None => amounts_wei,
+                    };
+                    let req = execution::BatchRequest { keys: k, router: r, action, token: t_addr, quote: q_addr, amount, chain_id: c, amounts_wei, percent: None };
+                    let evs = routing::run_split_trade(req, trade).await;
                     for e in evs { emit_event(e); }
                 });
+                return;
             }
+            spawn_scoped(async move {
+                let req = execution::BatchRequest { keys: k, router: r, action, token: t_addr, quote: q_addr, amount, chain_id: c, amounts_wei, percent };
+                let evs = execution::run_batch_trade(req, trade).await;
+                for e in evs { emit_event(e); }
+            });
+        }
+
+        EngineCommand::UpdatePrice { symbol, price } => { 
+            // Ручная цена перекрывает фид ядра, пока не устареет
+            CORE_STATE.write().unwrap().set_usd_price(symbol, price, USD_PRICE_MANUAL, std::time::Duration::ZERO);
+        }
+        
+        EngineCommand::UpdateTokenDecimals { address, decimals } => {
+            let Some(a) = parse_address_field("UpdateTokenDecimals", "address", &address) else { return; };
+            CORE_STATE.write().unwrap().decimals_cache.insert(a, decimals);
+            token_cache::store_decimals(a, decimals);
+        }
+
+        EngineCommand::GetTokenInfo { address } => {
+            let Some(token) = parse_address_field("GetTokenInfo", "address", &address) else { return; };
+            spawn_scoped(async move {
+                emit_event(token_metadata::describe(token).await);
+            });
+        }
 
-            EngineCommand::UpdatePrice { symbol, price } => { 
-                CORE_STATE.write().unwrap().usd_prices.insert(symbol, price); 
+        EngineCommand::ClearTokenCache { address } => {
+            let Some(token) = parse_address_option("ClearTokenCache", "address", address.as_deref()) else { return; };
+            {
+                let mut s = CORE_STATE.write().unwrap();
+                match token {
+                    Some(t) => { s.decimals_cache.remove(&t); }
+                    None => s.decimals_cache.clear(),
+                }
+            }
+            token_metadata::forget(token);
+            let removed = token_cache::clear(token);
+            emit_log("INFO", format!("🗃️ Кэш токенов очищен: {} записей", removed));
+        }
+        
+        EngineCommand::UpdateSettings { gas_price_gwei, slippage, fuel_enabled, fuel_quote_address, rpc_url, wss_url, quote_symbol, rug_alert_pct, rug_auto_sell, split_min_improvement_bps, pool_switch_hysteresis_pct, max_token_subscriptions, bridge_queue_capacity, gas_limit_swap, gas_limit_approve, gas_limit_safety_factor, approval_mode, wrap_gas_reserve, disperse_address, broadcast_mode, notify, portfolio_pnl_delta_pct, pnl_update_epsilon_pct, attribute_approve_gas, attribute_fuel_gas, deadline_secs, slippage_mode, auto_slippage_buffer_bps, auto_slippage_max_pct, sandwich_precheck, sandwich_max_move_pct, sandwich_detect, batch_burst, auto_approve, dry_run, pending_tx_timeout_secs, monitor, min_native_reserve_wei, balance_quotes, min_balance_usd, min_balance_wei } => {
+            let mut s = CORE_STATE.write().unwrap();
+            if let Some(v) = gas_price_gwei { s.manual_gas_price_gwei = v; }
+            if let Some(v) = slippage {
+                if !(0.0..=100.0).contains(&v) {
+                    emit_log("WARNING", format!("⚠️ Slippage {}% вне [0, 100], ограничен", v));
+                }
+                s.slippage = if v.is_nan() { 0.0 } else { v.clamp(0.0, 100.0) };
+            }
+            if let Some(v) = deadline_secs.filter(|d| *d > 0) { s.swap_deadline_secs = v; }
+            if let Some(mode) = slippage_mode {
+                match SlippageMode::parse(&mode) {
+                    Some(m) => {
+                        s.slippage_mode = m;
+                        emit_log("INFO", format!("🎯 Режим slippage: {}", mode));
+                    }
+                    None => emit_event(EngineEvent::invalid_field("UpdateSettings", "slippage_mode", format!("неизвестный режим {:?}", mode))),
+                }
+            }
+            if let Some(v) = auto_slippage_buffer_bps { s.auto_slippage_buffer_bps = v.max(0.0); }
+            if let Some(v) = auto_slippage_max_pct.filter(|v| *v > 0.0) { s.auto_slippage_max_pct = v.min(100.0); }
+            if let Some(v) = sandwich_precheck { s.sandwich_precheck = v; }
+            if let Some(v) = sandwich_max_move_pct { s.sandwich_max_move_pct = v.max(0.0); }
+            if let Some(v) = sandwich_detect { s.sandwich_detect = v; }
+            if let Some(v) = batch_burst { s.batch_burst = v; }
+            if let Some(v) = auto_approve { s.auto_approve = v; }
+            if let Some(v) = pending_tx_timeout_secs.filter(|v| *v > 0) { s.pending_tx_timeout_secs = v; }
+            if let Some(m) = monitor {
+                let block_time = s.block_time;
+                apply_monitor_settings(&mut s.monitor, &m, block_time);
+                let c = &s.monitor;
+                emit_log("INFO", format!(
+                    "⏱️ Монитор: idle {}с, переподключение {}с, receipt {}мс, воркер {}мс, RPC health {}с",
+                    c.effective_idle_timeout(block_time).as_secs(), c.reconnect_delay.as_secs(),
+                    c.receipt_poll.as_millis(), c.worker_tick.as_millis(), c.rpc_health_interval.as_secs()
+                ));
+            }
+            if let Some(v) = dry_run {
+                DRY_RUN.store(v, Ordering::SeqCst);
+                emit_log("WARNING", format!("🧪 Dry-run: {}", if v { "ВКЛЮЧЕН - транзакции не отправляются" } else { "ВЫКЛЮЧЕН" }));
+            }
+            
+            if let Some(enabled) = fuel_enabled {
+                s.fuel_enabled = enabled;
+                emit_log("INFO", format!("⛽ Auto-Fuel: {}", if enabled { "ВКЛЮЧЕН" } else { "ВЫКЛЮЧЕН" }));
             }
             
-            EngineCommand::UpdateTokenDecimals { address, decimals } => {
-                if let Ok(a) = Address::from_str(&address) { 
-                    CORE_STATE.write().unwrap().decimals_cache.insert(a, decimals); 
+            if let Some(quote_addr_str) = fuel_quote_address {
+                if let Some(quote_addr) = parse_address_field("UpdateSettings", "fuel_quote_address", &quote_addr_str) {
+                    let changed = s.fuel_quote_address != quote_addr;
+                    s.fuel_quote_address = quote_addr;
+                    emit_log("INFO", format!("🔄 Quote токен для мониторинга: {:?}", quote_addr));
+                    if changed {
+                        // Приходы нового quote видны сразу, а не после переподключения монитора
+                        TRANSFER_SET_CHANGED.notify_one();
+                        spawn_balances_refresh(TRACKED_WALLETS.read().unwrap().clone(), vec![quote_addr]);
+                    }
                 }
             }
             
-            EngineCommand::UpdateSettings { gas_price_gwei, slippage, fuel_enabled, fuel_quote_address, rpc_url, wss_url, quote_symbol } => {
-                let mut s = CORE_STATE.write().unwrap();
-                if let Some(v) = gas_price_gwei { s.manual_gas_price_gwei = v; }
-                if let Some(v) = slippage { s.slippage = v; }
-                
-                if let Some(enabled) = fuel_enabled {
-                    s.fuel_enabled = enabled;
-                    emit_log("INFO", format!("⛽ Auto-Fuel: {}", if enabled { "ВКЛЮЧЕН" } else { "ВЫКЛЮЧЕН" }));
+            if let Some(sym) = quote_symbol {
+                s.quote_symbol = sym.clone();
+                emit_log("INFO", format!("💱 Quote symbol обновлен: {}", sym));
+            }
+            
+            if let Some(pct) = rug_alert_pct {
+                s.rug_alert_pct = pct;
+                emit_log("INFO", format!("🚨 Rug-alert порог: {:.1}%", pct));
+            }
+            
+            if let Some(enabled) = rug_auto_sell {
+                s.rug_auto_sell = enabled;
+                emit_log("INFO", format!("🚨 Rug auto-sell: {}", if enabled { "ВКЛЮЧЕН" } else { "ВЫКЛЮЧЕН" }));
+            }
+            if let Some(v) = split_min_improvement_bps { s.split_min_improvement_bps = v.max(0.0); }
+            if let Some(v) = pool_switch_hysteresis_pct { s.pool_switch_hysteresis_pct = v.max(0.0); }
+            // Уже активные подписки не снимаем - лимит действует на новые
+            if let Some(v) = max_token_subscriptions { s.max_tokens = v.max(1); }
+            if let Some(v) = bridge_queue_capacity { crate::bridge::transport::set_capacity(v); }
+            if let Some(v) = gas_limit_swap { s.gas_limit_swap = v.max(21_000); }
+            if let Some(v) = gas_limit_approve { s.gas_limit_approve = v.max(21_000); }
+            if let Some(v) = gas_limit_safety_factor { s.gas_limit_safety = v.max(1.0); }
+            if let Some(v) = wrap_gas_reserve {
+                s.wrap_gas_reserve = ethers::utils::parse_units(v.max(0.0), 18).map(U256::from).unwrap_or_default();
+            }
+            if let Some(v) = min_native_reserve_wei {
+                match U256::from_dec_str(&v) {
+                    Ok(reserve) => {
+                        s.min_native_reserve = reserve;
+                        emit_log("INFO", format!("⛽ Минимальный резерв native: {:.6}", execution::u256_to_f64_safe(reserve, 18)));
+                    }
+                    Err(_) => emit_event(EngineEvent::invalid_field("UpdateSettings", "min_native_reserve_wei", format!("не число wei: {:?}", v))),
                 }
-                
-                if let Some(quote_addr_str) = fuel_quote_address {
-                    if let Ok(quote_addr) = Address::from_str(&quote_addr_str) {
-                        s.fuel_quote_address = quote_addr;
-                        emit_log("INFO", format!("🔄 Quote токен для мониторинга: {:?}", quote_addr));
+            }
+            if let Some(list) = balance_quotes {
+                let unknown: Vec<&String> = list.iter()
+                    .filter(|sym| !s.quote_tokens.keys().any(|k| k.eq_ignore_ascii_case(sym)))
+                    .collect();
+                if !unknown.is_empty() {
+                    emit_log("WARNING", format!("⚠️ balance_quotes: нет в quote_tokens сети: {:?}", unknown));
+                }
+                emit_log("INFO", format!("💰 Балансы quote: {}", if list.is_empty() { "все".to_string() } else { list.join(", ") }));
+                s.balance_quotes = Some(list).filter(|l| !l.is_empty());
+            }
+            if let Some(v) = min_balance_usd { s.min_balance_usd = if v.is_nan() { 0.0 } else { v.max(0.0) }; }
+            if let Some(thresholds) = min_balance_wei {
+                let mut parsed = std::collections::HashMap::new();
+                for (token, wei) in &thresholds {
+                    let field = format!("min_balance_wei.{}", token);
+                    let address = if token.eq_ignore_ascii_case("native") { Some(balances::native()) } else { parse_address_field("UpdateSettings", &field, token) };
+                    let Some(address) = address else { continue; };
+                    match U256::from_dec_str(wei) {
+                        Ok(min) => { parsed.insert(address, min); }
+                        Err(_) => emit_event(EngineEvent::invalid_field("UpdateSettings", &field, format!("не число wei: {:?}", wei))),
                     }
                 }
-                
-                if let Some(sym) = quote_symbol {
-                    s.quote_symbol = sym.clone();
-                    emit_log("INFO", format!("💱 Quote symbol обновлен: {}", sym));
+                s.min_balance_wei = parsed;
+            }
+            if let Some(addr) = disperse_address {
+                if addr.trim().is_empty() {
+                    s.disperse_address = None;
+                } else if let Some(a) = parse_address_field("UpdateSettings", "disperse_address", &addr) {
+                    s.disperse_address = Some(a).filter(|a| !a.is_zero());
                 }
-                
-                if let Some(new_rpc) = rpc_url {
-                    let mut pool = RPC_POOL.write().unwrap(); 
-                    pool.nodes.clear();
-                    pool.nodes.push(RpcNode { url: new_rpc.clone(), latency: 0, is_private: true, fails: 0 });
+                emit_log("INFO", format!("📤 Disperse контракт: {:?}", s.disperse_address));
+            }
+            if let Some(mode) = broadcast_mode {
+                match BroadcastMode::parse(&mode) {
+                    Some(m) if m != BroadcastMode::Public && s.private_relay_urls.is_empty() => {
+                        emit_event(EngineEvent::invalid_field("UpdateSettings", "broadcast_mode", "нет private_relay_urls в конфиге сети - режим не изменён".into()));
+                    }
+                    Some(m) => {
+                        s.broadcast_mode = m;
+                        emit_log("INFO", format!("🕶️ Режим рассылки: {}", mode));
+                    }
+                    None => emit_event(EngineEvent::invalid_field("UpdateSettings", "broadcast_mode", format!("неизвестный режим {:?}", mode))),
                 }
-                
-                if let Some(new_wss) = wss_url {
-                    s.wss_url = new_wss.clone();
-                    if let Some(h) = INTERNAL_HANDLE.lock().unwrap().take() { h.abort(); }
-                    *INTERNAL_HANDLE.lock().unwrap() = Some(RUNTIME.spawn(monitor::start_background_worker(new_wss)).abort_handle());
+            }
+            if let Some(n) = notify { notifier::apply_settings(n, false); }
+            if let Some(v) = portfolio_pnl_delta_pct { s.portfolio_pnl_delta_pct = v.max(0.0); }
+            if let Some(v) = pnl_update_epsilon_pct { s.pnl_update_epsilon_pct = v.max(0.0); }
+            if let Some(v) = attribute_approve_gas { s.attribute_approve_gas = v; }
+            if let Some(v) = attribute_fuel_gas { s.attribute_fuel_gas = v; }
+            if let Some(mode) = approval_mode {
+                match ApprovalMode::parse(&mode) {
+                    Some(m) => {
+                        s.approval_mode = m;
+                        emit_log("INFO", format!("🛡️ Режим approve: {}", mode));
+                    }
+                    None => emit_event(EngineEvent::invalid_field("UpdateSettings", "approval_mode", format!("неизвестный режим {:?}", mode))),
                 }
             }
             
-            EngineCommand::RefreshBalance { wallet, token } => {
-                let wallet_addr = Address::from_str(&wallet).ok();
-                let token_addr = Address::from_str(&token).ok();
-                
-                if let (Some(w), Some(t)) = (wallet_addr, token_addr) {
-                    RUNTIME.spawn(async move {
-                        if t == Address::zero() || t == Address::from_str("0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee").unwrap() {
-                            let url_opt = { let p = RPC_POOL.read().unwrap(); p.get_fastest_node() };
-                            if let Some(url_str) = url_opt {
-                                if let Ok(url) = url::Url::parse(&url_str) {
-                                    let provider = Provider::new(Http::new_with_client(url, crate::state::GLOBAL_HTTP_CLIENT.clone()));
-                                    if let Ok(balance) = provider.get_balance(w, None).await {
-                                        let float_val = balance.as_u128() as f64 / 1e18;
-                                        emit_event(EngineEvent::BalanceUpdate {
-                                            wallet: format!("{:?}", w),
-                                            token: "0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee".into(),
-                                            wei: balance.to_string(),
-                                            float_val,
-                                            symbol: "NATIVE".into()
-                                        });
-                                    }
-                                }
+            if let Some(new_rpc) = rpc_url {
+                // Ноду чужой сети в пул не пускаем: старый пул остаётся как есть.
+                // Заменяется только приватная нода, публичные остаются запасными
+                let expected = s.chain_id;
+                spawn_scoped(async move {
+                    match monitor::fetch_chain_id(&new_rpc).await {
+                        Ok(id) if id == expected => {
+                            {
+                                let mut pool = RPC_POOL.write().unwrap();
+                                pool.nodes.retain(|n| !n.is_private && n.url != new_rpc);
+                                pool.add_node(new_rpc.clone(), true);
                             }
-                        } else {
-                            let balance = execution::get_token_balance(t, w).await;
-                            let decimals = monitor::get_decimals_cached(t).await;
-                            let float_val = execution::u256_to_f64_safe(balance, decimals as u32);
-                            emit_event(EngineEvent::BalanceUpdate {
-                                wallet: format!("{:?}", w),
-                                token: format!("{:?}", t),
-                                wei: balance.to_string(),
-                                float_val,
-                                symbol: "TOKEN".into()
-                            });
+                            invalidate_providers(None);
+                            monitor::probe_node(&new_rpc, false).await;
                         }
-                    });
-                }
+                        Ok(id) => emit_event(EngineEvent::invalid_field("UpdateSettings", "rpc_url", format!("chain_id {} вместо {}", id, expected))),
+                        Err(e) => emit_event(EngineEvent::invalid_field("UpdateSettings", "rpc_url", format!("eth_chainId недоступен ({})", e))),
+                    }
+                });
             }
-
-            EngineCommand::AddWallet { address, private_key } => {
-                if let Ok(addr) = Address::from_str(&address) {
-                    CORE_STATE.write().unwrap().wallet_keys.insert(addr, private_key);
-                    emit_log("INFO", format!("🔑 Кошелек добавлен: {:?}", addr));
-                }
+            
+            if let Some(new_wss) = wss_url {
+                s.wss_url = new_wss.clone();
+                if let Some(h) = INTERNAL_HANDLE.lock().unwrap().take() { h.abort(); }
+                *INTERNAL_HANDLE.lock().unwrap() = Some(spawn(monitor::start_background_worker(new_wss)).abort_handle());
+            }
+            drop(s);
+            // Подписи бандлов сделаны под прежние цену и лимит газа
+            if gas_price_gwei.is_some() || gas_limit_swap.is_some() || gas_limit_safety_factor.is_some() {
+                bundles::invalidate_all("Gas settings changed");
             }
+        }
+        
+        EngineCommand::RefreshBalance { wallet, token } => {
+            let wallet_addr = parse_address_field("RefreshBalance", "wallet", &wallet);
+            let token_addr = parse_address_field("RefreshBalance", "token", &token);
             
-            EngineCommand::RefreshAllBalances => {
-                let wallets: Vec<Address> = TRACKED_WALLETS.read().unwrap().clone();
-                let quote_token = { CORE_STATE.read().unwrap().fuel_quote_address };
-                
-                RUNTIME.spawn(async move {
-                    let url_opt = { let p = RPC_POOL.read().unwrap(); p.get_fastest_node() };
-                    if let Some(url_str) = url_opt {
-                        if let Ok(url) = url::Url::parse(&url_str) {
-                            let provider = Provider::new(Http::new_with_client(url, crate::state::GLOBAL_HTTP_CLIENT.clone()));
-                            
-                            for wallet in &wallets {
-                                if let Ok(balance) = provider.get_balance(*wallet, None).await {
-                                    let float_val = balance.as_u128() as f64 / 1e18;
+            if let (Some(w), Some(t)) = (wallet_addr, token_addr) {
+                spawn_scoped(async move {
+                    if t == Address::zero() || t == Address::from_str("0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee").unwrap() {
+                        let url_opt = { let p = RPC_POOL.read().unwrap(); p.get_fastest_node() };
+                        if let Some(url_str) = url_opt {
+                            if let Ok(url) = url::Url::parse(&url_str) {
+                                let provider = Provider::new(Http::new_with_client(url, crate::state::GLOBAL_HTTP_CLIENT.clone()));
+                                if let Ok(balance) = provider.get_balance(w, None).await {
+                                    let float_val = execution::u256_to_f64_safe(balance, 18);
                                     emit_event(EngineEvent::BalanceUpdate {
-                                        wallet: format!("{:?}", wallet),
+                                        wallet: format!("{:?}", w),
                                         token: "0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee".into(),
                                         wei: balance.to_string(),
                                         float_val,
@@ -383,44 +1203,603 @@ async fn engine_loop(mut rx: mpsc::UnboundedReceiver<EngineCommand>) {
                                     });
                                 }
                             }
-                            
-                            if quote_token != Address::zero() {
-                                let decimals = monitor::get_decimals_cached(quote_token).await;
-                                for wallet in &wallets {
-                                    let balance = execution::get_token_balance(quote_token, *wallet).await;
-                                    let float_val = execution::u256_to_f64_safe(balance, decimals as u32);
-                                    emit_event(EngineEvent::BalanceUpdate {
-                                        wallet: format!("{:?}", wallet),
-                                        token: format!("{:?}", quote_token),
-                                        wei: balance.to_string(),
-                                        float_val,
-                                        symbol: "QUOTE".into()
-                                    });
-                                }
-                            }
                         }
+                    } else {
+                        let balance = execution::get_token_balance(t, w).await;
+                        let decimals = monitor::get_decimals_cached(t).await;
+                        let float_val = execution::u256_to_f64_safe(balance, decimals as u32);
+                        emit_event(EngineEvent::BalanceUpdate {
+                            wallet: format!("{:?}", w),
+                            token: format!("{:?}", t),
+                            wei: balance.to_string(),
+                            float_val,
+                            symbol: "TOKEN".into()
+                        });
                     }
                 });
             }
+        }
+
+        EngineCommand::SetPriceAlert { id, token, quote, price, direction, repeat } => {
+            let Some(t) = parse_address_field("SetPriceAlert", "token", &token) else { return; };
+            let Some(q) = parse_address_field("SetPriceAlert", "quote", &quote) else { return; };
+            let Some(dir) = PriceDirection::parse(&direction) else {
+                emit_event(EngineEvent::invalid_field("SetPriceAlert", "direction", format!("нужно above/below, получено {:?}", direction)));
+                return;
+            };
             
-            EngineCommand::Shutdown => { 
-                SHUTDOWN_FLAG.store(true, Ordering::Relaxed); 
-                if let Some(h) = MONITOR_HANDLE.lock().unwrap().take() { h.abort(); }
-                if let Some(h) = INTERNAL_HANDLE.lock().unwrap().take() { h.abort(); }
-                if let Some(h) = PNL_HANDLE.lock().unwrap().take() { h.abort(); }
-                if let Some(h) = RPC_CHECKER_HANDLE.lock().unwrap().take() { h.abort(); }
-                break;
+            let mut s = CORE_STATE.write().unwrap();
+            s.price_alerts.retain(|a| a.id != id);
+            s.price_alerts.push(PriceAlert {
+                id: id.clone(), token: t, quote: q, threshold: price, direction: dir, repeat, last_price: None
+            });
+            emit_log("INFO", format!("🔔 Алерт {} установлен: {} {}", id, dir.as_str(), price));
+        }
+        
+        EngineCommand::RemovePriceAlert { id } => {
+            let mut s = CORE_STATE.write().unwrap();
+            let before = s.price_alerts.len();
+            s.price_alerts.retain(|a| a.id != id);
+            if s.price_alerts.len() < before {
+                emit_log("INFO", format!("🔕 Алерт {} удалён", id));
+            }
+        }
+        
+        EngineCommand::PlaceLimitOrder { id, action, token, quote, trigger_price, amount, wallets, slippage, expiry, amounts_wei } => {
+            let Some(t) = parse_address_field("PlaceLimitOrder", "token", &token) else { return; };
+            let Some(q) = parse_address_field("PlaceLimitOrder", "quote", &quote) else { return; };
+            let Some(order_wallets) = parse_address_list("PlaceLimitOrder", "wallets", &wallets) else { return; };
+            let order = LimitOrder {
+                id: id.clone(),
+                action: action.to_lowercase(),
+                token: t,
+                quote: q,
+                trigger_price,
+                amount,
+                wallets: order_wallets,
+                slippage,
+                expiry_ts: expiry,
+                amounts_wei,
+            };
+            if let Err(e) = orders::validate(&order) {
+                emit_event(EngineEvent::rejected("PlaceLimitOrder", format!("ордер {}: {}", id, e)));
+                return;
+            }
+            
+            let mut s = CORE_STATE.write().unwrap();
+            s.limit_orders.retain(|o| o.id != id);
+            emit_log("INFO", format!("🎯 Лимитный ордер {}: {} при цене {}", id, order.action, trigger_price));
+            s.limit_orders.push(order);
+        }
+        
+        EngineCommand::PrepareBundle { id, action, token, quote, amount, wallets, gas_gwei, slippage, deadline_secs } => {
+            let Some(t) = parse_address_field("PrepareBundle", "token", &token) else { return; };
+            let Some(q) = parse_address_field("PrepareBundle", "quote", &quote) else { return; };
+            let Some(wallet_addresses) = parse_address_list("PrepareBundle", "wallets", &wallets) else { return; };
+            let action = action.to_lowercase();
+            if (action != "buy" && action != "sell") || amount <= 0.0 {
+                emit_event(EngineEvent::rejected("PrepareBundle", format!("бандл {}: нужен buy/sell и amount > 0", id)));
+                return;
+            }
+            let (router, keys, gas, chain_id) = {
+                let s = CORE_STATE.read().unwrap();
+                let keys: Vec<WalletKey> = wallet_addresses.iter()
+                    .filter_map(|a| s.wallet_keys.get(a).cloned())
+                    .collect();
+                let gas = if gas_gwei > 0.0 { gas_gwei } else { s.manual_gas_price_gwei };
+                (s.router_address, keys, gas, s.chain_id)
+            };
+            let req = execution::BatchRequest { keys, router, action, token: t, quote: q, amount, chain_id, amounts_wei: None, percent: None };
+            spawn_scoped(bundles::prepare(id, req, gas, slippage, deadline_secs));
+        }
+
+        EngineCommand::FireBundle { id } => {
+            spawn_scoped(bundles::fire(id));
+        }
+
+        EngineCommand::DiscardBundle { id } => bundles::discard(&id),
+
+        EngineCommand::ArmSnipe { token, quote, amount, wallets, max_gas_gwei, max_buy_tax_pct, min_liquidity_usd, timeout_secs } => {
+            let Some(t) = parse_address_field("ArmSnipe", "token", &token) else { return; };
+            let Some(q) = parse_address_field("ArmSnipe", "quote", &quote) else { return; };
+            let Some(wallet_addresses) = parse_address_list("ArmSnipe", "wallets", &wallets) else { return; };
+            if amount <= 0.0 || max_buy_tax_pct < 0.0 || min_liquidity_usd < 0.0 {
+                emit_event(EngineEvent::rejected("ArmSnipe", format!("снайп {}: нужны amount > 0 и неотрицательные лимиты", token)));
+                return;
+            }
+            let (router, keys, gas, chain_id) = {
+                let s = CORE_STATE.read().unwrap();
+                let keys: Vec<WalletKey> = wallet_addresses.iter()
+                    .filter_map(|a| s.wallet_keys.get(a).cloned())
+                    .collect();
+                let gas = if max_gas_gwei > 0.0 { max_gas_gwei } else { s.manual_gas_price_gwei };
+                (s.router_address, keys, gas, s.chain_id)
+            };
+            if keys.is_empty() {
+                emit_event(EngineEvent::invalid_field("ArmSnipe", "wallets", format!("снайп {}: нет кошельков с ключами", token)));
+                return;
+            }
+            let timeout_secs = timeout_secs.filter(|t| *t > 0).unwrap_or(snipe::SNIPE_TIMEOUT_SECS);
+            let order = snipe::SnipeOrder { quote: q, amount, keys, gas, max_buy_tax_pct, min_liquidity_usd, router, chain_id };
+            spawn_scoped(snipe::arm(t, order, timeout_secs));
+        }
+
+        EngineCommand::DisarmSnipe { token } => {
+            let Some(t) = parse_address_field("DisarmSnipe", "token", &token) else { return; };
+            if !snipe::disarm(t, "Disarmed") {
+                emit_event(EngineEvent::invalid_field("DisarmSnipe", "token", format!("снайп {} не взведён", token)));
+            }
+        }
+
+        EngineCommand::CancelLimitOrder { id } => {
+            let mut s = CORE_STATE.write().unwrap();
+            let before = s.limit_orders.len();
+            s.limit_orders.retain(|o| o.id != id);
+            if s.limit_orders.len() < before {
+                emit_log("INFO", format!("🎯 Лимитный ордер {} отменён", id));
+            }
+        }
+        
+        EngineCommand::SetExitStrategy { wallet, token, quote, cost_basis, stop_loss_pct, take_profit_pct, trail_pct } => {
+            let Some(w) = parse_address_field("SetExitStrategy", "wallet", &wallet) else { return; };
+            let Some(t) = parse_address_field("SetExitStrategy", "token", &token) else { return; };
+            let Some(q) = parse_address_field("SetExitStrategy", "quote", &quote) else { return; };
+            // Без cost_basis - средняя цена позиции, которую ведёт ядро
+            let has_position = CORE_STATE.read().unwrap().positions.get(&(w, t)).is_some_and(|p| !p.quantity.is_zero());
+            if cost_basis.map_or(!has_position, |c| c <= 0.0) || (stop_loss_pct.is_none() && take_profit_pct.is_none() && trail_pct.is_none()) {
+                emit_event(EngineEvent::rejected("SetExitStrategy", "нужен cost_basis > 0 (или позиция в ядре) и хотя бы один из SL/TP/trail".into()));
+                return;
+            }
+            
+            // Повторная установка (докупка/DCA) сбрасывает пик на новый cost basis
+            let mut s = CORE_STATE.write().unwrap();
+            s.exit_strategies.retain(|e| !(e.wallet == w && e.token == t));
+            s.exit_strategies.push(ExitStrategy {
+                wallet: w, token: t, quote: q, stop_loss_pct, take_profit_pct, trail_pct, peak_value: 0.0
+            });
+            match cost_basis {
+                Some(cost_basis) => {
+                    s.pnl_entries.insert((w, t), PnlEntry {
+                        quote: q, cost_basis, cost_override: true, balance: U256::zero(), current_value: None
+                    });
+                }
+                None => {
+                    if let Some(e) = s.pnl_entries.get_mut(&(w, t)) { e.cost_override = false; }
+                    positions::sync_entry(&mut s, (w, t));
+                }
+            }
+            let peak_value = s.pnl_entries.get(&(w, t)).map(|e| e.cost_basis).unwrap_or_default();
+            if let Some(e) = s.exit_strategies.last_mut() { e.peak_value = peak_value; }
+            emit_log("INFO", format!(
+                "🛑 Exit strategy {:?}: SL={:?}% TP={:?}% trail={:?}%", w, stop_loss_pct, take_profit_pct, trail_pct
+            ));
+        }
+        
+        EngineCommand::ClearExitStrategy { wallet, token } => {
+            let Some(w) = parse_address_field("ClearExitStrategy", "wallet", &wallet) else { return; };
+            let Some(t) = parse_address_field("ClearExitStrategy", "token", &token) else { return; };
+            let mut s = CORE_STATE.write().unwrap();
+            s.exit_strategies.retain(|e| !(e.wallet == w && e.token == t));
+            // Позиция ядра остаётся в портфеле со своей средней ценой
+            if let Some(e) = s.pnl_entries.get_mut(&(w, t)) { e.cost_override = false; }
+            positions::sync_entry(&mut s, (w, t));
+        }
+        
+        EngineCommand::ScheduleRecurringTrade { id, action, token, quote, amount, wallets, interval_secs, max_executions } => {
+            let Some(t) = parse_address_field("ScheduleRecurringTrade", "token", &token) else { return; };
+            let Some(q) = parse_address_field("ScheduleRecurringTrade", "quote", &quote) else { return; };
+            let action = action.to_lowercase();
+            let Some(wallets) = parse_address_list("ScheduleRecurringTrade", "wallets", &wallets) else { return; };
+            if (action != "buy" && action != "sell") || interval_secs == 0 || wallets.is_empty() {
+                emit_event(EngineEvent::rejected("ScheduleRecurringTrade", format!("расписание {}: нужны action buy/sell, interval_secs > 0 и кошельки", id)));
+                return;
+            }
+            
+            let mut s = CORE_STATE.write().unwrap();
+            s.recurring_trades.retain(|j| j.id != id);
+            s.recurring_trades.push(RecurringTrade {
+                id: id.clone(), action, token: t, quote: q, amount, wallets, interval_secs, max_executions,
+                executions: 0,
+                next_run_ms: 0,
+            });
+            emit_log("INFO", format!("⏰ Расписание {}: каждые {} сек, лимит {}", id, interval_secs, max_executions));
+        }
+        
+        EngineCommand::CancelRecurringTrade { id } => {
+            let mut s = CORE_STATE.write().unwrap();
+            let before = s.recurring_trades.len();
+            s.recurring_trades.retain(|j| j.id != id);
+            if s.recurring_trades.len() < before {
+                emit_log("INFO", format!("⏰ Расписание {} отменено", id));
+            }
+        }
+        
+        EngineCommand::WrapNative { wallets, amount } => spawn_wrap(wallets, amount, true),
+        
+        EngineCommand::UnwrapNative { wallets, amount } => spawn_wrap(wallets, amount, false),
+        
+        EngineCommand::Transfer { from_wallet, to_address, token, amount } => {
+            let Some(from) = parse_address_field("Transfer", "from_wallet", &from_wallet) else { return; };
+            let Some(to) = parse_address_field("Transfer", "to_address", &to_address) else { return; };
+            let Some(token) = parse_token_arg("Transfer", token.as_deref()) else { return; };
+            let Some(amount) = parse_amount_arg("Transfer", &amount) else { return; };
+            spawn_transfers("Transfer", from, token, vec![execution::TransferLeg { to, amount }]);
+        }
+        
+        EngineCommand::DisperseNative { from_wallet, recipients } => {
+            let Some(from) = parse_address_field("DisperseNative", "from_wallet", &from_wallet) else { return; };
+            let mut legs = Vec::with_capacity(recipients.len());
+            for (addr, amount) in recipients {
+                let Some(to) = parse_address_field("DisperseNative", "recipients", &addr) else { return; };
+                if amount.is_nan() || amount <= 0.0 {
+                    emit_event(EngineEvent::invalid_field("DisperseNative", "recipients", format!("сумма для {} должна быть > 0, получено {}", addr, amount)));
+                    return;
+                }
+                legs.push(execution::TransferLeg { to, amount: Some(amount) });
             }
+            spawn_transfers("DisperseNative", from, None, legs);
+        }
+        
+        EngineCommand::SweepAll { token, wallets, destination, leave_native_for_gas } => {
+            let Some(dest) = parse_address_field("SweepAll", "destination", &destination) else { return; };
+            if dest.is_zero() {
+                emit_event(EngineEvent::invalid_field("SweepAll", "destination", "нулевой адрес".into()));
+                return;
+            }
+            let Some(wallet_addresses) = parse_address_list("SweepAll", "wallets", &wallets) else { return; };
+            let Some(token) = parse_token_arg("SweepAll", token.as_deref()) else { return; };
+            let keep = ethers::utils::parse_units(leave_native_for_gas.unwrap_or(0.0).max(0.0), 18).map(U256::from).unwrap_or_default();
+            let (sources, keys, chain_id, gas, quote) = {
+                let s = CORE_STATE.read().unwrap();
+                let sources: Vec<Address> = if wallets.is_empty() {
+                    s.wallet_keys.keys().copied().collect()
+                } else {
+                    wallet_addresses
+                };
+                // Сам получатель в сборе не участвует
+                let sources: Vec<Address> = sources.into_iter().filter(|w| *w != dest && s.wallet_keys.contains_key(w)).collect();
+                let keys: Vec<WalletKey> = sources.iter().filter_map(|w| s.wallet_keys.get(w).cloned()).collect();
+                (sources, keys, s.chain_id, s.manual_gas_price_gwei, s.fuel_quote_address)
+            };
+            if keys.is_empty() {
+                emit_log("WARNING", "🧹 Sweep: нет кошельков для сбора".into());
+                return;
+            }
+            spawn_scoped(async move {
+                let (evs, hashes) = execution::run_sweep(keys, chain_id, token, dest, keep, gas).await;
+                for e in evs { emit_event(e); }
+                if hashes.is_empty() { return; }
+                execution::wait_for_confirmations(&hashes, std::time::Duration::from_secs(120)).await;
+                let mut wallets = sources;
+                wallets.push(dest);
+                spawn_balances_refresh(wallets, vec![token.unwrap_or(quote)]);
+            });
+        }
+        
+        EngineCommand::AddRpcNode { url, is_private } => {
+            if url::Url::parse(&url).is_err() {
+                emit_event(EngineEvent::invalid_field("AddRpcNode", "url", format!("некорректный RPC url {:?}", url)));
+                return;
+            }
+            {
+                let mut pool = RPC_POOL.write().unwrap();
+                if pool.nodes.iter().any(|n| n.url == url) {
+                    emit_event(EngineEvent::invalid_field("AddRpcNode", "url", "RPC нода уже в пуле".into()));
+                    return;
+                }
+                pool.add_node(url.clone(), is_private);
+            }
+            emit_log("INFO", format!("➕ RPC добавлена: {}", metrics::node_label(&url)));
+            // Не ждём цикла health checker: сверяем сеть и меряем задержку сразу
+            spawn_scoped(async move { monitor::probe_node(&url, true).await });
+        }
+
+        EngineCommand::RemoveRpcNode { url } => {
+            let mut pool = RPC_POOL.write().unwrap();
+            let Some(idx) = pool.nodes.iter().position(|n| n.url == url) else {
+                emit_event(EngineEvent::invalid_field("RemoveRpcNode", "url", "RPC нода не найдена в пуле".into()));
+                return;
+            };
+            let others_alive = pool.nodes.iter().enumerate().any(|(i, n)| i != idx && pool.usable(n));
+            if pool.usable(&pool.nodes[idx]) && !others_alive {
+                emit_event(EngineEvent::invalid_field("RemoveRpcNode", "url", "нельзя удалить единственную живую RPC ноду".into()));
+                return;
+            }
+            pool.nodes.remove(idx);
+            invalidate_providers(Some(&url));
+            emit_log("INFO", format!("➖ RPC удалена: {}", metrics::node_label(&url)));
+        }
+
+        EngineCommand::ReloadNetworkConfig { network } => {
+            let network = network.filter(|n| !n.is_empty())
+                .unwrap_or_else(|| CORE_STATE.read().unwrap().network_name.clone());
+            if network.is_empty() {
+                emit_event(EngineEvent::invalid_field("ReloadNetworkConfig", "network", "сеть не указана и не передана в Init (network_name)".into()));
+                return;
+            }
+            let config = match crate::config::load_network_config(&network) {
+                Ok(c) => c,
+                Err(e) => {
+                    emit_event(EngineEvent::invalid_field("ReloadNetworkConfig", "network", e));
+                    return;
+                }
+            };
+            let (changed, requires_reinit) = apply_network_config(&config);
+            if changed.is_empty() {
+                emit_log("INFO", format!("🔄 Конфиг {}: без изменений, применимых на лету", network));
+            } else {
+                emit_log("INFO", format!("🔄 Конфиг {} перечитан: {}", network, changed.join(", ")));
+            }
+            if !requires_reinit.is_empty() {
+                emit_log("WARNING", format!("🔄 Конфиг {}: {} - нужен новый Init", network, requires_reinit.join(", ")));
+            }
+            emit_event(EngineEvent::ConfigReloaded { network, changed, requires_reinit });
+        }
+
+        EngineCommand::WatchAddress { address, label, copy_trade } => {
+            let Some(addr) = parse_address_field("WatchAddress", "address", &address) else { return; };
+            if let Some(c) = &copy_trade {
+                if c.amount <= 0.0 || c.wallets.is_empty() {
+                    emit_event(EngineEvent::invalid_field("WatchAddress", "copy_trade", "нужны amount > 0 и кошельки".into()));
+                    return;
+                }
+            }
+            let label = if label.is_empty() { format!("{:?}", addr) } else { label };
+            {
+                let mut s = CORE_STATE.write().unwrap();
+                if s.wallet_keys.contains_key(&addr) {
+                    emit_event(EngineEvent::invalid_field("WatchAddress", "address", "адрес - наш кошелёк, он уже отслеживается".into()));
+                    return;
+                }
+                let copy_trade = copy_trade.map(|c| CopyTrade { amount: c.amount, wallets: c.wallets, slippage: c.slippage });
+                emit_log("INFO", format!("🐋 Наблюдение за {} ({:?}){}", label, addr, if copy_trade.is_some() { ", копирование покупок" } else { "" }));
+                s.watched_addresses.insert(addr, WatchedAddress { label, copy_trade });
+            }
+            notify_monitor();
+        }
+
+        EngineCommand::UnwatchAddress { address } => {
+            let Some(addr) = parse_address_field("UnwatchAddress", "address", &address) else { return; };
+            if CORE_STATE.write().unwrap().watched_addresses.remove(&addr).is_some() {
+                emit_log("INFO", format!("🐋 Наблюдение за {:?} снято", addr));
+                notify_monitor();
+            }
+        }
+
+        EngineCommand::IgnoreToken { address } => {
+            let Some(token) = parse_address_field("IgnoreToken", "address", &address) else { return; };
+            if CORE_STATE.write().unwrap().ignored_tokens.insert(token) {
+                emit_log("INFO", format!("🙈 Токен {:?} игнорируется: балансы не отправляются", token));
+            }
+        }
+
+        EngineCommand::UnignoreToken { address } => {
+            let Some(token) = parse_address_field("UnignoreToken", "address", &address) else { return; };
+            if CORE_STATE.write().unwrap().ignored_tokens.remove(&token) {
+                emit_log("INFO", format!("👁️ Токен {:?} снова отслеживается", token));
+            }
+        }
+
+        EngineCommand::AuditToken { token } => {
+            let Some(t) = parse_address_field("AuditToken", "token", &token) else { return; };
+            spawn_scoped(audit::audit(t));
+        }
+
+        EngineCommand::AnalyzeHolders { token, top_n, max_blocks, refresh } => {
+            let Some(t) = parse_address_field("AnalyzeHolders", "token", &token) else { return; };
+            spawn_scoped(holders::analyze(t, top_n, max_blocks, refresh));
+        }
+
+        EngineCommand::ImportHistory { wallet, token, quote, from_block, max_blocks } => {
+            let Some(w) = parse_address_field("ImportHistory", "wallet", &wallet) else { return; };
+            let Some(t) = parse_address_field("ImportHistory", "token", &token) else { return; };
+            let Some(q) = parse_address_option("ImportHistory", "quote", quote.as_deref()) else { return; };
+            spawn_scoped(history::import(w, t, q, from_block, max_blocks));
+        }
+
+        EngineCommand::GetCandles { pool, interval, count } => {
+            let Some(pool_addr) = parse_address_field("GetCandles", "pool", &pool) else { return; };
+            let candles = candles::history(pool_addr, interval, count.unwrap_or(usize::MAX));
+            emit_event(EngineEvent::Candles { pool: format!("{:?}", pool_addr), interval, candles });
+        }
+
+        EngineCommand::SetRpcPriority { url, pinned } => {
+            let mut pool = RPC_POOL.write().unwrap();
+            let Some(node) = pool.nodes.iter_mut().find(|n| n.url == url) else {
+                emit_event(EngineEvent::invalid_field("SetRpcPriority", "url", "RPC нода не найдена в пуле".into()));
+                return;
+            };
+            node.pinned = pinned;
+            emit_log("INFO", format!("📌 RPC {}: {}", metrics::node_label(&url), if pinned { "закреплена" } else { "откреплена" }));
+        }
+
+        EngineCommand::CheckApprovals { wallets, tokens, spender } => {
+            let router = { CORE_STATE.read().unwrap().router_address };
+            let Some(spender) = parse_address_option("CheckApprovals", "spender", spender.as_deref()) else { return; };
+            let spender = spender.unwrap_or(router);
+            let Some(wallets) = parse_address_list("CheckApprovals", "wallets", &wallets) else { return; };
+            let Some(tokens) = parse_address_list("CheckApprovals", "tokens", &tokens) else { return; };
+            if wallets.is_empty() || tokens.is_empty() {
+                emit_event(EngineEvent::rejected("CheckApprovals", "нужны кошельки и токены".into()));
+                return;
+            }
+            spawn_scoped(approvals::check(wallets, tokens, spender));
+        }
+
+        EngineCommand::RevokeApproval { wallet, token, spender } => {
+            let Some(w) = parse_address_field("RevokeApproval", "wallet", &wallet) else { return; };
+            let Some(t) = parse_address_field("RevokeApproval", "token", &token) else { return; };
+            let Some(spender) = parse_address_option("RevokeApproval", "spender", spender.as_deref()) else { return; };
+            let (pk, router, chain_id, gas) = {
+                let s = CORE_STATE.read().unwrap();
+                (s.wallet_keys.get(&w).cloned(), s.router_address, s.chain_id, s.manual_gas_price_gwei)
+            };
+            let spender = spender.unwrap_or(router);
+            let Some(pk) = pk else {
+                emit_event(EngineEvent::invalid_field("RevokeApproval", "wallet", format!("нет ключа для кошелька {:?}", w)));
+                return;
+            };
+            spawn_scoped(async move {
+                emit_event(execution::revoke_approval(pk, chain_id, t, spender, GasStrategy::Manual(gas)).await);
+            });
+        }
+        
+        EngineCommand::AddWallet { address, private_key } => {
+            let Some(addr) = parse_address_field("AddWallet", "address", &address) else { return; };
+            let Some(key) = parse_wallet_key(private_key) else {
+                emit_event(EngineEvent::invalid_field("AddWallet", "private_key", format!("неверный приватный ключ для кошелька {:?}", addr)));
+                return;
+            };
+            register_wallet(addr, key);
+        }
+
+        EngineCommand::RemoveWallet { address } => {
+            let Some(addr) = parse_address_field("RemoveWallet", "address", &address) else { return; };
+            // Уже запущенные сделки держат свои копии ключей и просто доработают
+            let known = {
+                let mut s = CORE_STATE.write().unwrap();
+                let known = s.wallet_keys.remove(&addr).is_some();
+                s.nonce_map.remove(&addr);
+                s.auto_fuel_attempts.remove(&addr);
+                s.exit_strategies.retain(|e| e.wallet != addr);
+                s.pnl_entries.retain(|(w, _), _| *w != addr);
+                // Ордера и DCA без оставшихся кошельков исполнять некому
+                for order in s.limit_orders.iter_mut() { order.wallets.retain(|w| *w != addr); }
+                s.limit_orders.retain(|o| !o.wallets.is_empty());
+                for trade in s.recurring_trades.iter_mut() { trade.wallets.retain(|w| *w != addr); }
+                s.recurring_trades.retain(|t| !t.wallets.is_empty());
+                known
+            };
+            let tracked = {
+                let mut tracked = TRACKED_WALLETS.write().unwrap();
+                let before = tracked.len();
+                tracked.retain(|w| *w != addr);
+                before != tracked.len()
+            };
+            if !known && !tracked {
+                emit_log("WARNING", format!("🔑 Кошелек {:?} не найден", addr));
+                return;
+            }
+            notify_monitor();
+            emit_log("SUCCESS", format!("🔑 Кошелек удалён: {:?}", addr));
+        }
+        
+        EngineCommand::ResetAutoFuel { wallet } => {
+            let Some(addr) = parse_address_field("ResetAutoFuel", "wallet", &wallet) else { return; };
+            if CORE_STATE.write().unwrap().auto_fuel_attempts.remove(&addr).is_some() {
+                emit_log("INFO", format!("⛽ Auto-Fuel {:?}: попытки сброшены", addr));
+            } else {
+                emit_log("INFO", format!("⛽ Auto-Fuel {:?}: попыток не было", addr));
+            }
+        }
+        
+        EngineCommand::RefreshAllBalances => {
+            let wallets: Vec<Address> = TRACKED_WALLETS.read().unwrap().clone();
+            let quotes = monitor::balance_quote_tokens().into_iter().map(|(q, _)| q).collect();
+            spawn_balances_refresh(wallets, quotes);
+        }
+        
+        EngineCommand::GetState => {
+            emit_event(state_snapshot());
+        }
+        
+        EngineCommand::PauseTrading => {
+            if !TRADING_PAUSED.swap(true, Ordering::SeqCst) {
+                emit_log("WARNING", "⏸️ Торговля приостановлена: новые транзакции не подписываются".into());
+            }
+            emit_event(EngineEvent::TradingPaused);
+        }
+        
+        EngineCommand::ResumeTrading => {
+            if TRADING_PAUSED.swap(false, Ordering::SeqCst) {
+                emit_log("SUCCESS", "▶️ Торговля возобновлена".into());
+            }
+            emit_event(EngineEvent::TradingResumed);
+        }
+        
+        EngineCommand::RestoreSession { stale_after_blocks, discard } => {
+            spawn_scoped(session::restore(stale_after_blocks, discard));
+        }
+
+        EngineCommand::Shutdown => { 
+            // Сначала пауза - чтобы во время teardown ничего не успело подписаться
+            TRADING_PAUSED.store(true, Ordering::SeqCst);
+            SHUTDOWN_FLAG.store(true, Ordering::Relaxed); 
+            stop_background_tasks().await;
+            notifier::stop();
+            session::save_now();
+            CORE_STATE.write().unwrap().recurring_trades.clear();
         }
     }
 }
 
+/// engine_id не задан - движок по умолчанию (однодвижковый API)
+#[pyfunction]
+#[pyo3(signature = (command_json, engine_id=None))]
+pub fn push_to_engine(_py: Python<'_>, command_json: String, engine_id: Option<EngineId>) -> PyResult<()> {
+    let id = crate::bridge::resolve_engine(engine_id)?;
+    with_engine(id, || dispatch_command(command_json)).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Новый экземпляр ядра: своё состояние, RPC пул, очередь команд и задачи. Init уходит сразу,
+/// конфиг - data команды Init. Возвращает engine_id для push_to_engine / pop_from_bridge
+#[pyfunction]
+pub fn create_engine(_py: Python<'_>, mut network_config_json: String) -> PyResult<EngineId> {
+    let command_json = format!("{{\"type\":\"Init\",\"data\":{}}}", network_config_json);
+    network_config_json.zeroize();
+    start_engine(command_json).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Останавливает движок из create_engine (Shutdown) и освобождает его состояние после последней задачи.
+/// Движок по умолчанию живёт весь процесс
 #[pyfunction]
-pub fn push_to_engine(_py: Python<'_>, command_json: String) -> PyResult<()> {
-    let cmd: EngineCommand = match serde_json::from_str(&command_json) { 
+pub fn destroy_engine(_py: Python<'_>, engine_id: EngineId) -> PyResult<()> {
+    stop_engine(engine_id).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+pub(crate) fn stop_engine(id: EngineId) -> Result<(), String> {
+    if id == DEFAULT_ENGINE || !instance::engine_exists(id) {
+        return Err(format!("Unknown engine_id: {}", id));
+    }
+    // Shutdown гасит фоновые задачи и завершает engine_loop - иначе они держали бы движок живым
+    with_engine(id, || dispatch_command(r#"{"type":"Shutdown"}"#.to_string()))?;
+    instance::destroy_engine_id(id);
+    Ok(())
+}
+
+/// Регистрирует движок и ставит ему Init; Init не разобрался - движка нет
+pub(crate) fn start_engine(init_json: String) -> Result<EngineId, String> {
+    let id = instance::create_engine_id();
+    if let Err(e) = with_engine(id, || dispatch_command(init_json)) {
+        instance::destroy_engine_id(id);
+        return Err(e);
+    }
+    Ok(id)
+}
+
+/// Разбор JSON команды и постановка в очередь движка; ошибка разбора уже ушла событием CommandError
+pub(crate) fn dispatch_command(mut command_json: String) -> Result<(), String> {
+    // request_id лежит рядом с type/data и достаётся даже если сама команда невалидна.
+    // Только он: полный Value оставил бы в куче незатёртые копии ключей
+    #[derive(serde::Deserialize)]
+    struct RequestMeta {
+        request_id: Option<String>,
+        #[serde(rename = "type")]
+        kind: Option<String>,
+    }
+    let (request_id, kind) = serde_json::from_str::<RequestMeta>(&command_json).ok()
+        .map(|m| (m.request_id, m.kind))
+        .unwrap_or_default();
+    let parsed: Result<EngineCommand, _> = serde_json::from_str(&command_json);
+    // В Init/AddWallet здесь лежат приватные ключи - копию от Python затираем сразу после разбора
+    command_json.zeroize();
+    let command = match parsed { 
         Ok(c) => c, 
-        Err(e) => return Err(pyo3::exceptions::PyValueError::new_err(e.to_string())) 
+        Err(e) => {
+            REQUEST_ID.sync_scope(request_id, || emit_event(EngineEvent::CommandError { message: e.to_string(), command: kind, field: None }));
+            return Err(e.to_string());
+        }
     };
-    let _ = COMMAND_TX.send(cmd);
+    let _ = COMMAND_TX.send(EngineRequest { command, request_id, kind });
     Ok(())
 }
\ No newline at end of file
@@ -1,12 +1,18 @@
 use ethers::prelude::*;
-use ethers::abi::AbiEncode;
+use ethers::abi::{AbiDecode, AbiEncode};
 use ethers::types::transaction::eip2718::TypedTransaction;
-use ethers::utils::{parse_units, format_units};
-use crate::state::{RPC_POOL, GLOBAL_HTTP_CLIENT, CORE_STATE};
-use crate::bridge::{EngineEvent, emit_event, emit_log};
+use ethers::providers::RpcError;
+use ethers::utils::parse_units;
+use crate::state::{RPC_POOL, GLOBAL_HTTP_CLIENT, CORE_STATE, TRACKED_WALLETS, SHUTDOWN_FLAG, TRADING_PAUSED, DRY_RUN, GasStrategy, SlippageMode, PendingTx, PendingSwap, ApprovalMode, BroadcastMode, WalletKey, fastest_http_provider, http_provider, ws_provider};
+use crate::bridge::{EngineEvent, AppliedTradeParams, TwapParams, emit_event, emit_log, current_request_id};
+use crate::metrics;
+use crate::math;
+use crate::approvals;
 use futures::future::join_all;
 use url::Url;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use crate::state::EngineLocal;
 
 abigen!(
     ITaxRouter, 
@@ -15,12 +21,14 @@ abigen!(
         function swapExactETHForTokens(uint amountOutMin, address[] calldata path, address to, uint deadline) external payable
         function swapExactTokensForETH(uint amountIn, uint amountOutMin, address[] calldata path, address to, uint deadline) external
         function swapV3Single(address tokenIn, address tokenOut, uint24 pool_fee, uint256 amountIn, uint256 amountOutMinimum, address recipient, uint256 deadline) external returns (uint256 amountOut)
+        function swapV3Multi(bytes path, uint256 amountIn, uint256 amountOutMinimum, address recipient, uint256 deadline) external returns (uint256 amountOut)
+        error InsufficientOutputAmount(uint256 amountRequired, uint256 amountReceived)
     ]"#
 );
 
 abigen!(
     IQuoter, 
-    r#"[{"inputs":[{"components":[{"internalType":"address","name":"tokenIn","type":"address"},{"internalType":"address","name":"tokenOut","type":"address"},{"internalType":"uint256","name":"amountIn","type":"uint256"},{"internalType":"uint24","name":"fee","type":"uint24"},{"internalType":"uint160","name":"sqrtPriceLimitX96","type":"uint160"}],"internalType":"struct IQuoterV2.QuoteExactInputSingleParams","name":"params","type":"tuple"}],"name":"quoteExactInputSingle","outputs":[{"internalType":"uint256","name":"amountOut","type":"uint256"},{"internalType":"uint160","name":"sqrtPriceX96After","type":"uint160"},{"internalType":"uint32","name":"initializedTicksCrossed","type":"uint32"},{"internalType":"uint256","name":"gasEstimate","type":"uint256"}],"stateMutability":"nonpayable","type":"function"}]"#
+    r#"[{"inputs":[{"components":[{"internalType":"address","name":"tokenIn","type":"address"},{"internalType":"address","name":"tokenOut","type":"address"},{"internalType":"uint256","name":"amountIn","type":"uint256"},{"internalType":"uint24","name":"fee","type":"uint24"},{"internalType":"uint160","name":"sqrtPriceLimitX96","type":"uint160"}],"internalType":"struct IQuoterV2.QuoteExactInputSingleParams","name":"params","type":"tuple"}],"name":"quoteExactInputSingle","outputs":[{"internalType":"uint256","name":"amountOut","type":"uint256"},{"internalType":"uint160","name":"sqrtPriceX96After","type":"uint160"},{"internalType":"uint32","name":"initializedTicksCrossed","type":"uint32"},{"internalType":"uint256","name":"gasEstimate","type":"uint256"}],"stateMutability":"nonpayable","type":"function"},{"inputs":[{"internalType":"bytes","name":"path","type":"bytes"},{"internalType":"uint256","name":"amountIn","type":"uint256"}],"name":"quoteExactInput","outputs":[{"internalType":"uint256","name":"amountOut","type":"uint256"},{"internalType":"uint160[]","name":"sqrtPriceX96AfterList","type":"uint160[]"},{"internalType":"uint32[]","name":"initializedTicksCrossedList","type":"uint32[]"},{"internalType":"uint256","name":"gasEstimate","type":"uint256"}],"stateMutability":"nonpayable","type":"function"}]"#
 );
 
 abigen!(
@@ -28,23 +36,59 @@ abigen!(
     r#"[
         function allowance(address owner, address spender) external view returns (uint256)
         function approve(address spender, uint256 amount) external returns (bool)
+        function transfer(address to, uint256 amount) external returns (bool)
         function balanceOf(address owner) external view returns (uint256)
         function symbol() external view returns (string)
         function name() external view returns (string)
         function decimals() external view returns (uint8)
+        function totalSupply() external view returns (uint256)
     ]"#
 );
 
-pub fn u256_to_f64_safe(val: U256, decimals: u32) -> f64 {
-    if val.is_zero() { return 0.0; }
-    let s = format_units(val, decimals).unwrap_or_else(|_| "0.0".to_string());
-    s.parse::<f64>().unwrap_or(0.0)
-}
+pub use crate::math::{u256_to_f64_safe, v2_oriented_reserves, price_impact_pct};
 
+/// Цена газа tx в wei: не ниже min_gas_price_gwei сети
 fn gas_gwei_to_wei(gas_gwei: f64) -> u64 {
-    if gas_gwei <= 0.0 { return 1_000_000_000; }
-    let wei = (gas_gwei * 1e9) as u64;
-    wei
+    let min_gwei = CORE_STATE.read().unwrap().chain_quirks.min_gas_price_gwei;
+    let gas_gwei = if gas_gwei <= 0.0 { 1.0 } else { gas_gwei };
+    (gas_gwei.max(min_gwei) * 1e9) as u64
+}
+
+/// Tx в формате сети перед подписью: type-2 только при явном supports_eip1559 без force_legacy_tx.
+/// max fee - цена газа плана, чаевые - её часть сверх base fee оракула (без оракула - вся цена)
+fn network_tx(tx: TypedTransaction) -> TypedTransaction {
+    let (eip1559, base_fee_gwei, chain_id) = {
+        let s = CORE_STATE.read().unwrap();
+        (s.chain_quirks.eip1559_tx(), s.gas_oracle.as_ref().map_or(0.0, |o| o.base_fee_gwei), s.chain_id)
+    };
+    if !eip1559 { return tx; }
+    let TypedTransaction::Legacy(req) = tx else { return tx; };
+    let max_fee = req.gas_price.unwrap_or_default();
+    let base_fee = U256::from((base_fee_gwei * 1e9) as u64);
+    let priority = if base_fee.is_zero() || base_fee >= max_fee { max_fee } else { max_fee - base_fee };
+    let mut out = Eip1559TransactionRequest::new().max_fee_per_gas(max_fee).max_priority_fee_per_gas(priority);
+    out.from = req.from;
+    out.to = req.to;
+    out.gas = req.gas;
+    out.value = req.value;
+    out.data = req.data;
+    out.nonce = req.nonce;
+    // rlp_signed type-2 берёт chain id из самой tx, подпись его не подставляет
+    out.chain_id = req.chain_id.or(Some(chain_id.into()));
+    TypedTransaction::Eip1559(out)
+}
+
+/// Цена газа (wei) для фоновых tx: газ последнего блока, до первого блока после Init - ручной газ,
+/// затем eth_gasPrice. None - цену узнать неоткуда: tx с нулевым газом висела бы вечно и держала nonce
+pub async fn resolve_gas_price() -> Option<U256> {
+    let (gas, manual_gwei) = {
+        let s = CORE_STATE.read().unwrap();
+        (s.gas_price, s.manual_gas_price_gwei)
+    };
+    if !gas.is_zero() { return Some(gas); }
+    if manual_gwei > 0.0 { return Some(U256::from(gas_gwei_to_wei(manual_gwei))); }
+    let live = fastest_http_provider()?.get_gas_price().await.ok().filter(|g| !g.is_zero())?;
+    Some(CORE_STATE.read().unwrap().chain_quirks.network_gas(live))
 }
 
 /// Текущее время в миллисекундах (Unix timestamp)
@@ -55,43 +99,707 @@ fn current_timestamp_ms() -> u64 {
         .as_millis() as u64
 }
 
+// Сколько лучших нод пула смотреть при повторе read_call после обрыва
+const READ_RETRY_NODES: usize = 3;
+
+/// eth_call для read-only запросов: через WS монитора, пока он подключён, иначе HTTP.
+/// Ответ ноды с ошибкой (revert) не повторяется. Обрыв транспорта HTTP - нода уходит
+/// в счётчик ошибок пула, запрос повторяется на следующей ноде
+pub async fn read_call(to: Address, data: Bytes) -> Result<Bytes, String> {
+    let tx: TypedTransaction = TransactionRequest::new().to(to).data(data).into();
+    if let Some(ws) = ws_provider() {
+        match ws.call(&tx, None).await {
+            Ok(out) => return Ok(out),
+            Err(e) if RpcError::as_error_response(&e).is_some() => return Err(e.to_string()),
+            Err(_) => {}
+        }
+    }
+    let first = { RPC_POOL.read().unwrap().get_fastest_node() }.ok_or("no RPC nodes")?;
+    let mut url = first.clone();
+    loop {
+        let p = http_provider(&url).ok_or("no RPC nodes")?;
+        let err = match p.call(&tx, None).await {
+            Ok(out) => return Ok(out),
+            Err(e) if RpcError::as_error_response(&e).is_some() => return Err(e.to_string()),
+            Err(e) => e.to_string(),
+        };
+        RPC_POOL.write().unwrap().report_error(&url, &err);
+        let next = { RPC_POOL.read().unwrap().get_fastest_pool(READ_RETRY_NODES) }.into_iter().find(|u| *u != first);
+        match next {
+            Some(n) if url == first => url = n,
+            _ => return Err(err),
+        }
+    }
+}
+
 /// Получить баланс ERC20 токена для адреса
 pub async fn get_token_balance(token: Address, wallet: Address) -> U256 {
-    let url_opt = { RPC_POOL.read().unwrap().get_fastest_node() };
-    if let Some(url) = url_opt {
-        if let Ok(u) = Url::parse(&url) {
-            let p = Arc::new(Provider::new(Http::new_with_client(u, GLOBAL_HTTP_CLIENT.clone())));
-            let erc20 = IERC20::new(token, p);
-            if let Ok(balance) = erc20.balance_of(wallet).call().await {
-                return balance;
-            }
+    read_call(token, BalanceOfCall { owner: wallet }.encode().into()).await.ok()
+        .and_then(|out| BalanceOfReturn::decode(&out).ok())
+        .map_or(U256::zero(), |r| r.0)
+}
+
+/// Нативный баланс адреса (ноль, если RPC недоступен)
+pub async fn get_native_balance(wallet: Address) -> U256 {
+    if let Some(p) = fastest_http_provider() {
+        if let Ok(balance) = p.get_balance(wallet, None).await {
+            return balance;
         }
     }
     U256::zero()
 }
 
-/// Получить symbol и name токена
-pub async fn get_token_info(token: Address) -> (String, String) {
-    let url_opt = { RPC_POOL.read().unwrap().get_fastest_node() };
-    if let Some(url) = url_opt {
-        if let Ok(u) = Url::parse(&url) {
-            let p = Arc::new(Provider::new(Http::new_with_client(u, GLOBAL_HTTP_CLIENT.clone())));
-            let erc20 = IERC20::new(token, p);
-            
-            let symbol = erc20.symbol().call().await.unwrap_or_default();
-            let name = erc20.name().call().await.unwrap_or_default();
-            
-            return (symbol, name);
+/// Подписант с chain_id сети. Копия секрета живёт до конца операции и затирается при drop
+pub fn signer(key: &LocalWallet, chain_id: u64) -> LocalWallet {
+    key.clone().with_chain_id(chain_id)
+}
+
+pub fn trading_paused() -> bool {
+    TRADING_PAUSED.load(std::sync::atomic::Ordering::SeqCst)
+}
+
+pub fn dry_run() -> bool {
+    DRY_RUN.load(std::sync::atomic::Ordering::SeqCst)
+}
+
+/// Префикс хешей dry-run в событиях: бумажную сделку не спутать с реальной
+pub const DRY_RUN_PREFIX: &str = "dryrun-";
+
+/// Хеши dry-run tx -> (отправитель, блок "отправки"): pending watcher подтверждает их через блок
+static DRY_RUN_TXS: EngineLocal<Mutex<HashMap<TxHash, (Address, u64)>>> = EngineLocal::new(|| Mutex::new(HashMap::new()));
+
+/// Хеш для событий: у dry-run tx с префиксом DRY_RUN_PREFIX
+pub fn tx_hash_label(hash: &TxHash) -> String {
+    if DRY_RUN_TXS.lock().unwrap().contains_key(hash) {
+        format!("{}{:?}", DRY_RUN_PREFIX, hash)
+    } else {
+        format!("{:?}", hash)
+    }
+}
+
+/// Отправитель и блок dry-run tx, None - обычная tx
+pub fn dry_run_tx(hash: &TxHash) -> Option<(Address, u64)> {
+    DRY_RUN_TXS.lock().unwrap().get(hash).copied()
+}
+
+pub fn forget_dry_run_tx(hash: &TxHash) {
+    DRY_RUN_TXS.lock().unwrap().remove(hash);
+}
+
+/// TradeStatus для операции, отклонённой до подписи
+pub fn rejected_status(wallet: String, action: &str, token: Address, amount: f64, status: &str, message: String) -> EngineEvent {
+    EngineEvent::TradeStatus {
+        wallet,
+        action: action.to_string(),
+        status: status.into(),
+        message,
+        tx_hash: None,
+        token_address: format!("{:?}", token),
+        amount,
+        tokens_received: None,
+        tokens_sold: None,
+        token_decimals: 18,
+        slice_index: None,
+        leg: None,
+        gas_limit: None,
+        gas_estimated: None,
+        applied: None,
+        dry_run: dry_run()
+    }
+}
+
+/// TradeStatus для операции, отклонённой kill-switch'ем до подписи
+pub fn paused_status(wallet: String, action: &str, token: Address, amount: f64) -> EngineEvent {
+    rejected_status(wallet, action, token, amount, "Paused", "Trading paused".into())
+}
+
+/// Лимит газа под конкретную транзакцию
+pub struct GasPlan {
+    pub limit: u64,
+    pub estimated: Option<u64>,
+    pub affordable: bool,
+    pub reverted: bool, // оценка упала на revert, а не на сети
+    pub reserve_short: bool, // на газ хватает, но баланс опустится ниже min_native_reserve
+}
+
+/// estimate_gas × запас (если оценка не удалась - fallback) и проверка по живому балансу, что после
+/// limit × price и value на кошельке остаётся не меньше min_native_reserve
+pub async fn plan_gas(tx: &TypedTransaction, from: Address, fallback: u64, gas_price_wei: U256) -> GasPlan {
+    let (safety, reserve) = {
+        let s = CORE_STATE.read().unwrap();
+        (s.gas_limit_safety, s.min_native_reserve)
+    };
+    let Some(p) = fastest_http_provider() else {
+        return GasPlan { limit: fallback, estimated: None, affordable: true, reverted: false, reserve_short: false };
+    };
+
+    let mut est_tx = tx.clone();
+    est_tx.set_from(from);
+    let (estimate, balance) = tokio::join!(p.estimate_gas(&est_tx, None), p.get_balance(from, None));
+
+    let mut reverted = false;
+    let estimated = match estimate {
+        Ok(g) if g <= U256::from(u64::MAX) => Some(g.as_u64()),
+        Ok(_) => None,
+        Err(e) => {
+            reverted = e.to_string().to_lowercase().contains("revert");
+            emit_log("DEBUG", format!("[TRADE] ESTIMATE_GAS failed for {:?}, fallback {}: {}", from, fallback, e));
+            None
+        }
+    };
+    let limit = estimated.map(|g| (g as f64 * safety).ceil() as u64).unwrap_or(fallback);
+    // Баланс не прочитался - не блокируем, отклонит сама нода. value tx (wrap) тоже списывается с нативного
+    let cost = U256::from(limit) * gas_price_wei + tx.value().copied().unwrap_or_default();
+    let covers_cost = balance.as_ref().map(|b| *b >= cost).unwrap_or(true);
+    let reserve_short = covers_cost && balance.map(|b| b.saturating_sub(cost) < reserve).unwrap_or(false);
+    GasPlan { limit, estimated, affordable: covers_cost && !reserve_short, reverted, reserve_short }
+}
+
+/// TradeStatus "InsufficientGas": нативного баланса не хватит на limit × price, tx не подписана
+/// "InsufficientGasReserve": на газ хватает, но после tx на кошельке осталось бы меньше min_native_reserve
+fn insufficient_gas_status(wallet_addr: Address, action: &str, token: Address, amount: f64, plan: &GasPlan, gas_price_wei: U256) -> EngineEvent {
+    let need = u256_to_f64_safe(U256::from(plan.limit) * gas_price_wei, 18);
+    let (status, message) = if plan.reserve_short {
+        let reserve = u256_to_f64_safe(CORE_STATE.read().unwrap().min_native_reserve, 18);
+        emit_log("WARNING", format!("⛽ {:?}: tx опустила бы нативный баланс ниже резерва {:.6} (газ {:.6}, лимит {})", wallet_addr, reserve, need, plan.limit));
+        ("InsufficientGasReserve", format!("Native balance would drop below reserve {:.6} (fee {:.6}, gas limit {})", reserve, need, plan.limit))
+    } else {
+        emit_log("WARNING", format!("⛽ {:?}: не хватает нативного баланса на газ ({:.6}, лимит {})", wallet_addr, need, plan.limit));
+        ("InsufficientGas", format!("Native balance below {:.6} (gas limit {})", need, plan.limit))
+    };
+    let mut ev = rejected_status(format!("{:?}", wallet_addr), action, token, amount, status, message);
+    if let EngineEvent::TradeStatus { gas_limit, gas_estimated, .. } = &mut ev {
+        *gas_limit = Some(plan.limit);
+        *gas_estimated = plan.estimated;
+    }
+    ev
+}
+
+// Запас exact-апрува сверх суммы сделки (bps)
+const EXACT_APPROVE_BUFFER_BPS: u64 = 100;
+
+/// Значение approve по режиму: unlimited - max, exact - сумма + буфер
+pub fn approve_value(needed: U256) -> U256 {
+    match CORE_STATE.read().unwrap().approval_mode {
+        ApprovalMode::Unlimited => U256::max_value(),
+        ApprovalMode::Exact => needed.saturating_add(needed.saturating_mul(U256::from(EXACT_APPROVE_BUFFER_BPS)) / U256::from(10_000)),
+    }
+}
+
+pub enum ApproveFailure {
+    InsufficientGas(GasPlan),
+    Broadcast(String),
+}
+
+pub struct ApproveSent {
+    pub tx_hash: String,
+    pub route: &'static str,
+    pub plan: GasPlan,
+}
+
+fn approve_tx(token: Address, spender: Address, value: U256, nonce: u64, gas_price_wei: U256) -> TypedTransaction {
+    TransactionRequest::new()
+        .to(token)
+        .value(0)
+        .nonce(nonce)
+        .data(Bytes::from(ApproveCall { spender, amount: value }.encode()))
+        .gas_price(gas_price_wei)
+        .into()
+}
+
+/// Подписывает tx с лимитом из плана, рассылает и регистрирует в pending_txs. Ok - (хеш, маршрут), Err - текст ошибки
+async fn broadcast_tracked(
+    wallet: &LocalWallet, mut tx: TypedTransaction, plan: &GasPlan, position: Option<(Address, Address)>
+) -> Result<(String, &'static str), String> {
+    tx.set_gas(plan.limit);
+    let tx = network_tx(tx);
+    let sig = wallet.sign_transaction_sync(&tx).map_err(|e| e.to_string())?;
+    let (hash, route) = parallel_broadcast(wallet.address(), tx.rlp_signed(&sig)).await
+        .map_err(|e| e.status_message())?;
+    CORE_STATE.write().unwrap().pending_txs.insert(hash, PendingTx {
+        request_id: current_request_id(),
+        gas_limit: Some(plan.limit),
+        gas_estimated: plan.estimated,
+        position,
+        wallet: wallet.address(),
+        gas_price: tx.gas_price(),
+        ..Default::default()
+    });
+    Ok((tx_hash_label(&hash), route))
+}
+
+/// approve(spender, value) токена к подписи: текущий allowance нужен для сброса в 0 у USDT-подобных
+pub struct ApproveOrder {
+    pub token: Address,
+    pub spender: Address,
+    pub value: U256,
+    pub current_allowance: U256,
+    pub nonce: u64,
+    pub gas_price_wei: U256,
+    pub position: Option<Address>, // токен позиции, которой начисляется газ (при включённом attribute_approve_gas)
+}
+
+/// Подписывает и рассылает approve(spender, value), регистрирует pending tx и сдвигает nonce.
+/// USDT-подобные токены ревертят смену ненулевого allowance на другое ненулевое значение:
+/// если оценка такого approve ревертится, сначала уходит approve(spender, 0) на том же nonce.
+pub async fn send_approve(wallet: &LocalWallet, order: ApproveOrder) -> Result<ApproveSent, ApproveFailure> {
+    let ApproveOrder { token, spender, value, current_allowance, nonce, gas_price_wei, position } = order;
+    let wallet_addr = wallet.address();
+    let (fallback, attribute) = {
+        let s = CORE_STATE.read().unwrap();
+        (s.gas_limit_approve, s.attribute_approve_gas)
+    };
+    let position = position.filter(|_| attribute).map(|t| (wallet_addr, t));
+    let mut nonce = nonce;
+
+    let mut plan = plan_gas(&approve_tx(token, spender, value, nonce, gas_price_wei), wallet_addr, fallback, gas_price_wei).await;
+    if plan.reverted && !value.is_zero() && !current_allowance.is_zero() {
+        emit_log("INFO", format!("🛡️ {:?}: токен требует сброса allowance в 0 перед новым approve", token));
+        let reset = approve_tx(token, spender, U256::zero(), nonce, gas_price_wei);
+        let reset_plan = plan_gas(&reset, wallet_addr, fallback, gas_price_wei).await;
+        if !reset_plan.affordable {
+            return Err(ApproveFailure::InsufficientGas(reset_plan));
+        }
+        let (reset_hash, _) = broadcast_tracked(wallet, reset, &reset_plan, position).await.map_err(ApproveFailure::Broadcast)?;
+        emit_log("INFO", format!("🛡️ Сброс allowance: {}", reset_hash));
+        nonce += 1;
+        CORE_STATE.write().unwrap().nonce_map.insert(wallet_addr, nonce);
+        // До включения сброса в блок оценка снова ревертнется - лимит берём из настроек
+        plan = GasPlan { limit: fallback, estimated: None, affordable: true, reverted: false, reserve_short: false };
+    }
+    if !plan.affordable {
+        return Err(ApproveFailure::InsufficientGas(plan));
+    }
+
+    let (tx_hash, route) = broadcast_tracked(wallet, approve_tx(token, spender, value, nonce, gas_price_wei), &plan, position).await
+        .map_err(ApproveFailure::Broadcast)?;
+    CORE_STATE.write().unwrap().nonce_map.insert(wallet_addr, nonce + 1);
+    Ok(ApproveSent { tx_hash, route, plan })
+}
+
+/// Wrap (deposit) / unwrap (withdraw) нативной монеты с кошельков. amount None - весь баланс (для wrap - минус резерв на газ)
+pub async fn run_wrap(keys: Vec<WalletKey>, wrap: bool, amount: Option<f64>, gas: f64, chain_id: u64) -> Vec<EngineEvent> {
+    let action = if wrap { "wrap" } else { "unwrap" };
+    let (w_native, reserve, fallback) = {
+        let s = CORE_STATE.read().unwrap();
+        // wrap "max": газ на саму tx и минимальный резерв после неё
+        (s.wrapped_native_address, s.wrap_gas_reserve.saturating_add(s.min_native_reserve), s.gas_limit_approve)
+    };
+    if w_native == Address::zero() {
+        return vec![rejected_status("SYSTEM".into(), action, w_native, amount.unwrap_or(0.0), "Error", "Wrapped native is not configured".into())];
+    }
+    let gas_price_wei = U256::from(gas_gwei_to_wei(gas));
+    let mut events = Vec::new();
+
+    for key in keys {
+        let wallet = signer(&key, chain_id);
+        let wallet_addr = wallet.address();
+        if trading_paused() {
+            events.push(paused_status(format!("{:?}", wallet_addr), action, w_native, amount.unwrap_or(0.0)));
+            break;
+        }
+
+        let balance = if wrap { get_native_balance(wallet_addr).await } else { get_token_balance(w_native, wallet_addr).await };
+        let available = if wrap { balance.saturating_sub(reserve) } else { balance };
+        let amount_wei = match amount {
+            Some(a) => parse_units(a, 18).map(U256::from).unwrap_or_default(),
+            None => available,
+        };
+        if amount_wei.is_zero() || amount_wei > available {
+            events.push(rejected_status(
+                format!("{:?}", wallet_addr), action, w_native, u256_to_f64_safe(amount_wei, 18),
+                "Error", format!("Insufficient balance: {:.6} available", u256_to_f64_safe(available, 18))
+            ));
+            continue;
+        }
+        let amount_f = u256_to_f64_safe(amount_wei, 18);
+
+        let (value, calldata): (U256, Bytes) = if wrap {
+            (amount_wei, Bytes::from(ethers::utils::id("deposit()").to_vec()))
+        } else {
+            let mut data = ethers::utils::id("withdraw(uint256)").to_vec();
+            data.extend_from_slice(&ethers::abi::encode(&[ethers::abi::Token::Uint(amount_wei)]));
+            (U256::zero(), Bytes::from(data))
+        };
+
+        let nonce = { *CORE_STATE.read().unwrap().nonce_map.get(&wallet_addr).unwrap_or(&0) };
+        let mut typed_tx: TypedTransaction = TransactionRequest::new()
+            .to(w_native)
+            .value(value)
+            .nonce(nonce)
+            .data(calldata)
+            .gas_price(gas_price_wei)
+            .into();
+        let plan = plan_gas(&typed_tx, wallet_addr, fallback, gas_price_wei).await;
+        if !plan.affordable {
+            events.push(insufficient_gas_status(wallet_addr, action, w_native, amount_f, &plan, gas_price_wei));
+            continue;
+        }
+        typed_tx.set_gas(plan.limit);
+        let typed_tx = network_tx(typed_tx);
+
+        let Ok(sig) = wallet.sign_transaction_sync(&typed_tx) else { continue; };
+        let sent = parallel_broadcast(wallet_addr, typed_tx.rlp_signed(&sig)).await;
+        let is_success = sent.is_ok();
+        let (hash, message) = match &sent {
+            Ok((h, _)) => (tx_hash_label(h), tx_hash_label(h)),
+            Err(e) => (String::new(), e.status_message()),
+        };
+        if let Ok((tx_hash, route)) = sent {
+            emit_log("INFO", format!("🔁 {} {:.6} {:?}: {}", action, amount_f, wallet_addr, hash));
+            {
+                let mut s = CORE_STATE.write().unwrap();
+                s.pending_txs.insert(tx_hash, PendingTx {
+                    request_id: current_request_id(),
+                    gas_limit: Some(plan.limit),
+                    gas_estimated: plan.estimated,
+                    wallet: wallet_addr,
+                    gas_price: Some(gas_price_wei),
+                    ..Default::default()
+                });
+                s.nonce_map.insert(wallet_addr, nonce + 1);
+            }
+            emit_event(EngineEvent::TxSent {
+                tx_hash: hash.clone(),
+                wallet: format!("{:?}", wallet_addr),
+                action: action.to_string(),
+                amount: amount_f,
+                token: format!("{:?}", w_native),
+                timestamp_ms: current_timestamp_ms(),
+                route: route.into()
+            });
+        }
+
+        events.push(EngineEvent::TradeStatus {
+            wallet: format!("{:?}", wallet_addr),
+            action: action.to_string(),
+            status: if is_success { "Sent".into() } else { "Error".into() },
+            message,
+            tx_hash: if is_success { Some(hash) } else { None },
+            token_address: format!("{:?}", w_native),
+            amount: amount_f,
+            tokens_received: None,
+            tokens_sold: None,
+            token_decimals: 18,
+            slice_index: None,
+            leg: None,
+            gas_limit: Some(plan.limit),
+            gas_estimated: plan.estimated,
+            applied: None,
+            dry_run: dry_run()
+        });
+    }
+    events
+}
+
+/// Получатель перевода: amount None - весь баланс (для native - за вычетом газа)
+pub struct TransferLeg {
+    pub to: Address,
+    pub amount: Option<f64>,
+}
+
+fn transfer_sent(wallet_addr: Address, (hash, route): (String, &str), token: Address, amount: f64, dec: u8, plan: &GasPlan, message: String) -> EngineEvent {
+    emit_event(EngineEvent::TxSent {
+        tx_hash: hash.clone(),
+        wallet: format!("{:?}", wallet_addr),
+        action: "transfer".into(),
+        amount,
+        token: format!("{:?}", token),
+        timestamp_ms: current_timestamp_ms(),
+        route: route.into()
+    });
+    EngineEvent::TradeStatus {
+        wallet: format!("{:?}", wallet_addr),
+        action: "transfer".into(),
+        status: "Sent".into(),
+        message,
+        tx_hash: Some(hash),
+        token_address: format!("{:?}", token),
+        amount,
+        tokens_received: None,
+        tokens_sold: None,
+        token_decimals: dec,
+        slice_index: None,
+        leg: None,
+        gas_limit: Some(plan.limit),
+        gas_estimated: plan.estimated,
+        applied: None,
+        dry_run: dry_run()
+    }
+}
+
+/// Переводы с одного кошелька последовательными nonce. token None - нативная монета.
+/// Возвращает TradeStatus по каждому получателю и хеши отправленных tx
+/// native_keep - сколько native оставить на кошельке при "max"
+pub async fn run_transfers(
+    key: WalletKey,
+    chain_id: u64,
+    token: Option<Address>,
+    legs: Vec<TransferLeg>,
+    gas: f64,
+    native_keep: U256
+) -> (Vec<EngineEvent>, Vec<H256>) {
+    let shown = token.unwrap_or(Address::repeat_byte(0xee));
+    let wallet = signer(&key, chain_id);
+    let wallet_addr = wallet.address();
+    let dec = match token {
+        Some(t) => crate::monitor::get_decimals_cached(t).await,
+        None => 18,
+    };
+    let gas_price_wei = U256::from(gas_gwei_to_wei(gas));
+    // Простой перевод native на EOA стоит ровно 21000
+    let (fallback, reserve) = {
+        let s = CORE_STATE.read().unwrap();
+        (if token.is_some() { s.gas_limit_approve } else { 21_000 }, s.min_native_reserve)
+    };
+    let mut balance = match token {
+        Some(t) => get_token_balance(t, wallet_addr).await,
+        None => get_native_balance(wallet_addr).await,
+    };
+    let mut nonce = { *CORE_STATE.read().unwrap().nonce_map.get(&wallet_addr).unwrap_or(&0) };
+    let mut events = Vec::new();
+    let mut hashes = Vec::new();
+
+    for leg in legs {
+        let fail = |amount: f64, reason: String| rejected_status(
+            format!("{:?}", wallet_addr), "transfer", shown, amount, "Error", format!("{:?}: {}", leg.to, reason)
+        );
+        if trading_paused() {
+            events.push(paused_status(format!("{:?}", wallet_addr), "transfer", shown, leg.amount.unwrap_or(0.0)));
+            break;
+        }
+        if leg.to.is_zero() {
+            events.push(fail(leg.amount.unwrap_or(0.0), "refusing to send to the zero address".into()));
+            continue;
+        }
+
+        let native_max = token.is_none() && leg.amount.is_none();
+        let requested = match leg.amount {
+            Some(a) => parse_units(a, dec as u32).map(U256::from).unwrap_or_default(),
+            None => balance,
+        };
+        let build = |amount: U256| -> TypedTransaction {
+            let req = match token {
+                Some(t) => TransactionRequest::new().to(t).value(0).data(Bytes::from(TransferCall { to: leg.to, amount }.encode())),
+                None => TransactionRequest::new().to(leg.to).value(amount),
+            };
+            req.nonce(nonce).gas_price(gas_price_wei).into()
+        };
+
+        // Для native "max" газ вычитается из суммы - оцениваем без value
+        let mut plan = plan_gas(&build(if native_max { U256::zero() } else { requested }), wallet_addr, fallback, gas_price_wei).await;
+        let gas_cost = U256::from(plan.limit) * gas_price_wei;
+        // "max" оставляет и native_keep, и минимальный резерв
+        let amount_wei = if native_max { balance.saturating_sub(gas_cost + native_keep.max(reserve)) } else { requested };
+        let amount_f = u256_to_f64_safe(amount_wei, dec as u32);
+
+        if amount_wei.is_zero() && leg.amount.is_none() {
+            events.push(rejected_status(format!("{:?}", wallet_addr), "transfer", shown, 0.0, "Skipped", "Nothing to transfer".into()));
+            continue;
+        }
+        if amount_wei.is_zero() || amount_wei > balance {
+            events.push(fail(amount_f, format!("insufficient balance ({:.6} available)", u256_to_f64_safe(balance, dec as u32))));
+            continue;
+        }
+        let gas_short = match token {
+            // Баланс ведётся локально: прошлые переводы цикла живой баланс ещё не уменьшили
+            None => {
+                let spent = amount_wei + gas_cost;
+                plan.reserve_short = spent <= balance && balance - spent < reserve;
+                spent > balance || plan.reserve_short
+            }
+            Some(_) => !plan.affordable,
+        };
+        if gas_short {
+            events.push(insufficient_gas_status(wallet_addr, "transfer", shown, amount_f, &plan, gas_price_wei));
+            continue;
+        }
+
+        match broadcast_tracked(&wallet, build(amount_wei), &plan, None).await {
+            Ok(sent) => {
+                emit_log("INFO", format!("📤 Перевод {:.6} {:?} → {:?}: {}", amount_f, wallet_addr, leg.to, sent.0));
+                hashes.push(sent.0.parse().unwrap_or(H256::zero()));
+                nonce += 1;
+                balance = balance.saturating_sub(if token.is_none() { amount_wei + gas_cost } else { amount_wei });
+                events.push(transfer_sent(wallet_addr, sent, shown, amount_f, dec, &plan, format!("Transfer to {:?}", leg.to)));
+            }
+            Err(e) => events.push(fail(amount_f, e)),
+        }
+    }
+    CORE_STATE.write().unwrap().nonce_map.insert(wallet_addr, nonce);
+    (events, hashes)
+}
+
+/// Сбор всего баланса (token None - native) с кошельков на один адрес.
+/// Кошельки независимы: ошибка одного не останавливает остальные. Последним событием идёт SweepSummary
+pub async fn run_sweep(
+    keys: Vec<WalletKey>,
+    chain_id: u64,
+    token: Option<Address>,
+    destination: Address,
+    native_keep: U256,
+    gas: f64
+) -> (Vec<EngineEvent>, Vec<H256>) {
+    let results = join_all(keys.into_iter().map(|key| {
+        run_transfers(key, chain_id, token, vec![TransferLeg { to: destination, amount: None }], gas, native_keep)
+    })).await;
+
+    let mut events = Vec::new();
+    let mut hashes = Vec::new();
+    let (mut swept, mut skipped, mut failed, mut total_moved) = (0, 0, 0, 0.0);
+    for (evs, hs) in results {
+        for ev in &evs {
+            if let EngineEvent::TradeStatus { status, amount, .. } = ev {
+                match status.as_str() {
+                    "Sent" => { swept += 1; total_moved += amount; }
+                    "Skipped" => skipped += 1,
+                    _ => failed += 1,
+                }
+            }
+        }
+        events.extend(evs);
+        hashes.extend(hs);
+    }
+    emit_log("INFO", format!("🧹 Sweep → {:?}: {} отправлено, {} пусто, {} ошибок, всего {:.6}", destination, swept, skipped, failed, total_moved));
+    events.push(EngineEvent::SweepSummary {
+        token: format!("{:?}", token.unwrap_or(Address::repeat_byte(0xee))),
+        destination: format!("{:?}", destination),
+        swept,
+        skipped,
+        failed,
+        total_moved
+    });
+    (events, hashes)
+}
+
+/// DisperseNative одной tx через disperseEther(address[], uint256[])
+pub async fn run_disperse_native(key: WalletKey, chain_id: u64, disperse: Address, legs: Vec<TransferLeg>, gas: f64) -> (Vec<EngineEvent>, Vec<H256>) {
+    use ethers::abi::Token;
+
+    let native = Address::repeat_byte(0xee);
+    let wallet = signer(&key, chain_id);
+    let wallet_addr = wallet.address();
+    let fail = |amount: f64, reason: String| {
+        (vec![rejected_status(format!("{:?}", wallet_addr), "transfer", native, amount, "Error", reason)], Vec::new())
+    };
+    if trading_paused() {
+        return (vec![paused_status(format!("{:?}", wallet_addr), "transfer", native, 0.0)], Vec::new());
+    }
+    if legs.iter().any(|l| l.to.is_zero()) {
+        return fail(0.0, "refusing to send to the zero address".into());
+    }
+
+    let values: Vec<U256> = legs.iter()
+        .map(|l| parse_units(l.amount.unwrap_or(0.0), 18).map(U256::from).unwrap_or_default())
+        .collect();
+    let total = values.iter().fold(U256::zero(), |a, v| a + *v);
+    let total_f = u256_to_f64_safe(total, 18);
+
+    let mut data = ethers::utils::id("disperseEther(address[],uint256[])").to_vec();
+    data.extend_from_slice(&ethers::abi::encode(&[
+        Token::Array(legs.iter().map(|l| Token::Address(l.to)).collect()),
+        Token::Array(values.iter().map(|v| Token::Uint(*v)).collect()),
+    ]));
+
+    let gas_price_wei = U256::from(gas_gwei_to_wei(gas));
+    let nonce = { *CORE_STATE.read().unwrap().nonce_map.get(&wallet_addr).unwrap_or(&0) };
+    let tx: TypedTransaction = TransactionRequest::new()
+        .to(disperse)
+        .value(total)
+        .nonce(nonce)
+        .data(Bytes::from(data))
+        .gas_price(gas_price_wei)
+        .into();
+    // Лимит на контрактную рассылку: ~30k на получателя
+    let fallback = 50_000 + 30_000 * legs.len() as u64;
+    let (plan, balance) = tokio::join!(plan_gas(&tx, wallet_addr, fallback, gas_price_wei), get_native_balance(wallet_addr));
+
+    if total > balance {
+        return fail(total_f, format!("insufficient balance ({:.6} available)", u256_to_f64_safe(balance, 18)));
+    }
+    if !plan.affordable {
+        return (vec![insufficient_gas_status(wallet_addr, "transfer", native, total_f, &plan, gas_price_wei)], Vec::new());
+    }
+
+    match broadcast_tracked(&wallet, tx, &plan, None).await {
+        Ok(sent) => {
+            emit_log("INFO", format!("📤 Disperse {:.6} на {} адресов: {}", total_f, legs.len(), sent.0));
+            CORE_STATE.write().unwrap().nonce_map.insert(wallet_addr, nonce + 1);
+            let tx_hash = sent.0.parse().unwrap_or(H256::zero());
+            let ev = transfer_sent(wallet_addr, sent, native, total_f, 18, &plan, format!("Disperse to {} recipients", legs.len()));
+            (vec![ev], vec![tx_hash])
+        }
+        Err(e) => fail(total_f, e),
+    }
+}
+
+/// Ждёт, пока монитор не подтвердит tx (уберёт из pending_txs), но не дольше timeout
+pub async fn wait_for_confirmations(hashes: &[H256], timeout: std::time::Duration) {
+    let deadline = tokio::time::Instant::now() + timeout;
+    while tokio::time::Instant::now() < deadline {
+        let pending = {
+            let s = CORE_STATE.read().unwrap();
+            hashes.iter().any(|h| s.pending_txs.contains_key(h))
+        };
+        if !pending { return; }
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+}
+
+/// approve(spender, 0) - отзыв разрешения. Возвращает TradeStatus с action "revoke"
+pub async fn revoke_approval(key: WalletKey, chain_id: u64, token: Address, spender: Address, gas: GasStrategy) -> EngineEvent {
+    let wallet = signer(&key, chain_id);
+    let wallet_addr = wallet.address();
+    if trading_paused() {
+        return paused_status(format!("{:?}", wallet_addr), "revoke", token, 0.0);
+    }
+
+    let gas_price_wei = U256::from(gas_gwei_to_wei(gas.resolve_gwei()));
+    let nonce = { *CORE_STATE.read().unwrap().nonce_map.get(&wallet_addr).unwrap_or(&0) };
+    let order = ApproveOrder { token, spender, value: U256::zero(), current_allowance: U256::zero(), nonce, gas_price_wei, position: None };
+    match send_approve(&wallet, order).await {
+        Ok(sent) => {
+            emit_log("SUCCESS", format!("🛡️ Revoke {:?} для {:?}: {}", token, spender, sent.tx_hash));
+            emit_event(EngineEvent::TxSent {
+                tx_hash: sent.tx_hash.clone(),
+                wallet: format!("{:?}", wallet_addr),
+                action: "revoke".into(),
+                amount: 0.0,
+                token: format!("{:?}", token),
+                timestamp_ms: current_timestamp_ms(),
+                route: sent.route.into()
+            });
+            EngineEvent::TradeStatus {
+                wallet: format!("{:?}", wallet_addr),
+                action: "revoke".into(),
+                status: "Sent".into(),
+                message: format!("Approval revoked for {:?}", spender),
+                tx_hash: Some(sent.tx_hash),
+                token_address: format!("{:?}", token),
+                amount: 0.0,
+                tokens_received: None,
+                tokens_sold: None,
+                token_decimals: 18,
+                slice_index: None,
+                leg: None,
+                gas_limit: Some(sent.plan.limit),
+                gas_estimated: sent.plan.estimated,
+                applied: None,
+                dry_run: dry_run()
+            }
         }
+        Err(ApproveFailure::InsufficientGas(plan)) => insufficient_gas_status(wallet_addr, "revoke", token, 0.0, &plan, gas_price_wei),
+        Err(ApproveFailure::Broadcast(e)) => rejected_status(format!("{:?}", wallet_addr), "revoke", token, 0.0, "Error", e),
     }
-    (String::new(), String::new())
 }
 
 pub async fn check_and_auto_approve_background(token: Address, quote: Address) {
-    let (router, chain_id, wallets_keys) = {
+    let (router, chain_id, wallets_keys, mode, enabled) = {
         let s = CORE_STATE.read().unwrap();
-        (s.router_address, s.chain_id, s.wallet_keys.clone())
+        (s.router_address, s.chain_id, s.wallet_keys.clone(), s.approval_mode, s.auto_approve)
     };
+    // В exact режиме сумма известна только в момент продажи - approve делает ensure_sell_allowance
+    if !enabled || mode == ApprovalMode::Exact { return; }
     
     let url_opt = { RPC_POOL.read().unwrap().get_fastest_node() };
     if let Some(url) = url_opt {
@@ -99,7 +807,7 @@ pub async fn check_and_auto_approve_background(token: Address, quote: Address) {
             let p = Arc::new(Provider::new(Http::new_with_client(u, GLOBAL_HTTP_CLIENT.clone())));
             let tokens_to_check = vec![token, quote];
 
-            for (w_addr, pk) in wallets_keys {
+            for (w_addr, key) in wallets_keys {
                 for t_addr in &tokens_to_check {
                     if *t_addr == Address::from_low_u64_be(0xeeeeeeeeeeeeeeee) || *t_addr == Address::zero() { continue; }
                     
@@ -108,28 +816,51 @@ pub async fn check_and_auto_approve_background(token: Address, quote: Address) {
                         if allowance < (U256::max_value() / 2) {
                             emit_log("INFO", format!("🛡️ Фоновый Check: Апрув для {:?}...", w_addr));
                             
+                            if trading_paused() {
+                                emit_event(paused_status(format!("{:?}", w_addr), "approve", *t_addr, 0.0));
+                                return;
+                            }
+                            
                             // Восстановленная логика фонового апрува
-                            if let Ok(wallet) = pk.parse::<LocalWallet>() {
-                                let wallet = wallet.with_chain_id(chain_id);
-                                let data = erc20.approve(router, U256::max_value()).tx.data().cloned().unwrap();
-                                
-                                // Берем текущий газ сети
-                                if let Ok(gas_price) = p.get_gas_price().await {
-                                     let nonce = p.get_transaction_count(w_addr, None).await.unwrap_or(U256::zero());
-                                     let tx = TransactionRequest::new()
-                                        .to(*t_addr)
-                                        .value(0)
-                                        .nonce(nonce)
-                                        .data(data)
-                                        .gas(60000)
-                                        .gas_price(gas_price);
-                                     
-                                     let typed_tx: TypedTransaction = tx.into();
-                                     if let Ok(sig) = wallet.sign_transaction_sync(&typed_tx) {
-                                         // Отправляем "fire and forget"
-                                         let _ = p.send_raw_transaction(typed_tx.rlp_signed(&sig)).await;
+                            let wallet = signer(&key, chain_id);
+                            
+                            // Газ сети; до первого блока - ручной или eth_gasPrice
+                            if let Some(gas_price) = resolve_gas_price().await {
+                                 // Nonce через общий менеджер: сделка пользователя следом не займёт тот же.
+                                 // Кошелёк ещё не синхронизирован - засеваем менеджер pending счётчиком сети
+                                 let known = { CORE_STATE.read().unwrap().nonce_map.contains_key(&w_addr) };
+                                 if !known {
+                                     if let Ok(n) = p.get_transaction_count(w_addr, Some(BlockNumber::Pending.into())).await {
+                                         CORE_STATE.write().unwrap().nonce_map.entry(w_addr).or_insert(n.as_u64());
                                      }
-                                }
+                                 }
+                                 let nonce = reserve_nonces(&[w_addr])[0];
+                                 let order = ApproveOrder {
+                                     token: *t_addr, spender: router, value: U256::max_value(), current_allowance: allowance,
+                                     nonce, gas_price_wei: gas_price, position: Some(token)
+                                 };
+                                 match send_approve(&wallet, order).await {
+                                     Ok(sent) => {
+                                         emit_event(EngineEvent::TxSent {
+                                             tx_hash: sent.tx_hash,
+                                             wallet: format!("{:?}", w_addr),
+                                             action: "auto_approve".into(),
+                                             amount: 0.0,
+                                             token: format!("{:?}", t_addr),
+                                             timestamp_ms: current_timestamp_ms(),
+                                             route: sent.route.into()
+                                         });
+                                     }
+                                     Err(ApproveFailure::InsufficientGas(plan)) => {
+                                         release_nonce(w_addr, nonce);
+                                         let need = u256_to_f64_safe(U256::from(plan.limit) * gas_price, 18);
+                                         emit_log("WARNING", format!("🛡️ Фоновый approve {:?} пропущен: нативного баланса меньше {:.6} на газ", w_addr, need));
+                                     }
+                                     Err(ApproveFailure::Broadcast(e)) => {
+                                         release_nonce(w_addr, nonce);
+                                         emit_log("WARNING", format!("🛡️ Фоновый approve {:?} не отправлен: {}", w_addr, e));
+                                     }
+                                 }
                             }
                         }
                     }
@@ -139,63 +870,167 @@ pub async fn check_and_auto_approve_background(token: Address, quote: Address) {
     }
 }
 
-/// Берёт reserves из выбранного пула (selected_pool_address)
+/// Формула V2 с комиссией пулов текущей сети (v2_pool_fee_bps). Не звать под блокировкой CORE_STATE
+pub fn v2_amount_out(amount_in: U256, r_in: U256, r_out: U256) -> U256 {
+    let fee_bps = CORE_STATE.read().unwrap().v2_pool_fee_bps;
+    math::v2_amount_out(amount_in, r_in, r_out, fee_bps)
+}
+
+/// Путь свапа с учётом выбранного мульти-хоп маршрута (для buy маршрут разворачивается)
+pub fn swap_path(token_in: Address, token_out: Address) -> Vec<Address> {
+    let s = CORE_STATE.read().unwrap();
+    let Some(route) = s.pair_context(token_in, token_out).map(|c| &c.route) else { return vec![token_in, token_out] };
+    if route.len() > 2 {
+        if route.first() == Some(&token_in) && route.last() == Some(&token_out) {
+            return route.clone();
+        }
+        if route.first() == Some(&token_out) && route.last() == Some(&token_in) {
+            return route.iter().rev().cloned().collect();
+        }
+    }
+    vec![token_in, token_out]
+}
+
+/// Берёт reserves из выбранного пула пары или цепочки пулов маршрута
 pub fn calculate_expected_out_v2_pure(token_in: Address, token_out: Address, amount_in: U256) -> U256 {
     if amount_in.is_zero() { return U256::zero(); }
     
     let s = CORE_STATE.read().unwrap();
+    let Some(ctx) = s.pair_context(token_in, token_out) else {
+        emit_log("WARNING", "calculate_expected_out_v2_pure: нет подписки на пару".to_string());
+        return U256::zero();
+    };
+    
+    // Мульти-хоп: прогоняем формулу последовательно через резервы каждого плеча
+    if ctx.route.len() > 2 {
+        let forward = ctx.route.first() == Some(&token_in);
+        let mut path = ctx.route.clone();
+        let mut pools = ctx.route_pools.clone();
+        if !forward {
+            path.reverse();
+            pools.reverse();
+        }
+        
+        let mut amount = amount_in;
+        for (i, pool) in pools.iter().enumerate() {
+            let reserves = match s.v2_reserves.get(pool) {
+                Some(reserves) => *reserves,
+                None => {
+                    emit_log("WARNING", format!("calculate_expected_out_v2_pure: no reserves for route pool {:?}", pool));
+                    return U256::zero();
+                }
+            };
+            let (r_in, r_out) = v2_oriented_reserves(reserves, path[i], path[i + 1]);
+            if r_in.is_zero() || r_out.is_zero() {
+                emit_log("WARNING", "calculate_expected_out_v2_pure: zero reserves in route".to_string());
+                return U256::zero();
+            }
+            amount = math::v2_amount_out(amount, r_in, r_out, s.v2_pool_fee_bps);
+        }
+        return amount;
+    }
     
     // Берём reserves конкретного выбранного пула
-    let pool_addr = match s.selected_pool_address {
+    let pool_addr = match ctx.pool_address {
         Some(addr) => addr,
         None => {
-            emit_log("WARNING", "calculate_expected_out_v2_pure: selected_pool_address is None".to_string());
+            emit_log("WARNING", "calculate_expected_out_v2_pure: pool_address is None".to_string());
             return U256::zero();
         }
     };
     
-    let (r0, r1) = match s.v2_reserves.get(&pool_addr) {
-        Some(reserves) => reserves.clone(),
+    let reserves = match s.v2_reserves.get(&pool_addr) {
+        Some(reserves) => *reserves,
         None => {
             emit_log("WARNING", format!("calculate_expected_out_v2_pure: no reserves for pool {:?}", pool_addr));
             return U256::zero();
         }
     };
     
-    if r0.is_zero() || r1.is_zero() {
+    let (r_in, r_out) = v2_oriented_reserves(reserves, token_in, token_out);
+    if r_in.is_zero() || r_out.is_zero() {
         emit_log("WARNING", "calculate_expected_out_v2_pure: zero reserves".to_string());
         return U256::zero();
     }
     
-    // token0 < token1 по адресу, reserve0 для token0, reserve1 для token1
-    let (token0, _token1) = if token_in < token_out {
-        (token_in, token_out)
-    } else {
-        (token_out, token_in)
-    };
+    math::v2_amount_out(amount_in, r_in, r_out, s.v2_pool_fee_bps)
+}
+
+/// Упакованный V3 путь: tokenIn | fee (uint24) | mid | fee | tokenOut
+pub fn encode_v3_path(tokens: &[Address], fees: &[u32]) -> Bytes {
+    let mut packed = Vec::with_capacity(tokens.len() * 20 + fees.len() * 3);
+    for (i, token) in tokens.iter().enumerate() {
+        packed.extend_from_slice(token.as_bytes());
+        if let Some(fee) = fees.get(i) {
+            packed.extend_from_slice(&fee.to_be_bytes()[1..]);
+        }
+    }
+    Bytes::from(packed)
+}
+
+/// Упакованный путь выбранного V3 мульти-хоп маршрута в направлении token_in -> token_out
+fn v3_route_path(token_in: Address, token_out: Address) -> Option<Bytes> {
+    let s = CORE_STATE.read().unwrap();
+    let ctx = s.pair_context(token_in, token_out)?;
+    let (route, fees) = (&ctx.route, &ctx.route_fees);
+    if route.len() < 3 || fees.len() + 1 != route.len() { return None; }
     
-    // Если token_in == token0, то r_in = reserve0, r_out = reserve1
-    // Иначе r_in = reserve1, r_out = reserve0
-    let (r_in, r_out) = if token_in == token0 {
-        (r0, r1)
-    } else {
-        (r1, r0)
+    if route.first() == Some(&token_in) && route.last() == Some(&token_out) {
+        return Some(encode_v3_path(route, fees));
+    }
+    if route.first() == Some(&token_out) && route.last() == Some(&token_in) {
+        let tokens: Vec<Address> = route.iter().rev().cloned().collect();
+        let fees: Vec<u32> = fees.iter().rev().cloned().collect();
+        return Some(encode_v3_path(&tokens, &fees));
+    }
+    None
+}
+
+/// V3 single-hop: quoteExactInputSingle
+pub async fn quote_v3_single(token_in: Address, token_out: Address, amount_in: U256, fee: u32, quoter: Address) -> U256 {
+    let params = QuoteExactInputSingleParams {
+        token_in,
+        token_out,
+        amount_in,
+        fee,
+        sqrt_price_limit_x96: U256::zero(),
     };
     
-    // Формула Uniswap V2: amountOut = (amountIn * 997 * reserveOut) / (reserveIn * 1000 + amountIn * 997)
-    let amount_in_with_fee = U512::from(amount_in) * U512::from(9970);
-    let numerator = amount_in_with_fee * U512::from(r_out);
-    let denominator = (U512::from(r_in) * U512::from(10000)) + amount_in_with_fee;
-    
-    if !denominator.is_zero() {
-        let res = (numerator / denominator).0;
-        return U256([res[0], res[1], res[2], res[3]]);
+    let t_start = std::time::Instant::now();
+    let result = read_call(quoter, QuoteExactInputSingleCall { params }.encode().into()).await
+        .and_then(|out| QuoteExactInputSingleReturn::decode(&out).map_err(|e| e.to_string()));
+    metrics::METRICS.quoter_latency.observe(t_start.elapsed());
+    match result {
+        Ok(QuoteExactInputSingleReturn { amount_out, .. }) => {
+            emit_log("DEBUG", format!("V3 quoter result: {}", amount_out));
+            amount_out
+        }
+        Err(e) => {
+            emit_log("WARNING", format!("V3 quoter error: {}", e));
+            U256::zero()
+        }
     }
-    
-    U256::zero()
 }
 
-/// V3: вызывает quoter для получения ожидаемого выхода
+/// V3 multi-hop: quoteExactInput по упакованному пути
+pub async fn quote_v3_path(path: Bytes, amount_in: U256, quoter: Address) -> U256 {
+    let t_start = std::time::Instant::now();
+    let result = read_call(quoter, QuoteExactInputCall { path, amount_in }.encode().into()).await
+        .and_then(|out| QuoteExactInputReturn::decode(&out).map_err(|e| e.to_string()));
+    metrics::METRICS.quoter_latency.observe(t_start.elapsed());
+    match result {
+        Ok(QuoteExactInputReturn { amount_out, .. }) => {
+            emit_log("DEBUG", format!("V3 path quoter result: {}", amount_out));
+            amount_out
+        }
+        Err(e) => {
+            emit_log("WARNING", format!("V3 path quoter error: {}", e));
+            U256::zero()
+        }
+    }
+}
+
+/// V3: вызывает quoter для получения ожидаемого выхода (с учётом мульти-хоп маршрута)
 pub async fn calculate_expected_out_v3_quoted(
     token_in: Address, 
     token_out: Address, 
@@ -205,90 +1040,623 @@ pub async fn calculate_expected_out_v3_quoted(
 ) -> U256 {
     if amount_in.is_zero() { return U256::zero(); }
     
-    let url_opt = { RPC_POOL.read().unwrap().get_fastest_node() };
-    if let Some(url_str) = url_opt {
-        if let Ok(url) = Url::parse(&url_str) {
-            let provider = Arc::new(Provider::new(Http::new_with_client(url, GLOBAL_HTTP_CLIENT.clone())));
-            let quoter_contract = IQuoter::new(quoter, provider);
-            
-            let params = QuoteExactInputSingleParams {
-                token_in,
-                token_out,
-                amount_in,
-                fee,
-                sqrt_price_limit_x96: U256::zero(),
-            };
-            
-            match quoter_contract.quote_exact_input_single(params).call().await {
-                Ok((amount_out, _, _, _)) => {
-                    emit_log("DEBUG", format!("V3 quoter result: {}", amount_out));
-                    return amount_out;
-                }
-                Err(e) => {
-                    emit_log("WARNING", format!("V3 quoter error: {:?}", e));
+    match v3_route_path(token_in, token_out) {
+        Some(path) => quote_v3_path(path, amount_in, quoter).await,
+        None => quote_v3_single(token_in, token_out, amount_in, fee, quoter).await,
+    }
+}
+
+/// Вычисляет идеальный выход на основе спотовой цены (без учёта slippage/impact)
+pub fn calculate_ideal_out(token_in: Address, token_out: Address, amount_in: U256, decimals_in: u8, is_buy: bool, decimals_out: u8) -> U256 {
+    // emit_log("DEBUG", format!("ideal_out: in={:?}, out={:?}, is_buy={}", token_in, token_out, is_buy));
+    if amount_in.is_zero() { return U256::zero(); }
+    
+    let price = {
+        let s = CORE_STATE.read().unwrap();
+        s.pair_context(token_in, token_out).map(|c| c.spot_price).unwrap_or(0.0)  // quote_per_token
+    };
+    math::ideal_out(amount_in, decimals_in, decimals_out, price, is_buy)
+}
+
+/// Дедлайн свапа: сейчас + deadline_secs
+pub fn swap_deadline(deadline_secs: u64) -> U256 {
+    U256::from(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() + deadline_secs
+    )
+}
+
+/// Slippage сделки: 0/не задан - глобальный из CORE_STATE; всегда в [0, 100]
+pub fn effective_slippage(slippage: f64) -> f64 {
+    let value = if slippage > 0.0 { slippage } else { CORE_STATE.read().unwrap().slippage };
+    if !(0.0..=100.0).contains(&value) {
+        emit_log("WARNING", format!("⚠️ Slippage {}% вне [0, 100], ограничен", value));
+    }
+    if value.is_nan() { 0.0 } else { value.clamp(0.0, 100.0) }
+}
+
+/// Фактические параметры сделки: slippage, режим и дедлайн с подстановкой глобальных значений
+pub fn applied_params(slippage: f64, gas_gwei: f64, deadline_secs: Option<u64>, mode: Option<SlippageMode>) -> AppliedTradeParams {
+    let (default_deadline, default_mode) = {
+        let s = CORE_STATE.read().unwrap();
+        (s.swap_deadline_secs, s.slippage_mode)
+    };
+    AppliedTradeParams {
+        slippage: effective_slippage(slippage),
+        gas_gwei,
+        deadline_secs: deadline_secs.filter(|d| *d > 0).unwrap_or(default_deadline),
+        auto_slippage: mode.unwrap_or(default_mode) == SlippageMode::Auto,
+        impact_pct: None,
+    }
+}
+
+/// Проставляет фактические параметры в TradeStatus сделки, где их ещё нет (auto - уже проставлены по кошельку)
+pub fn stamp_applied(events: &mut [EngineEvent], params: &AppliedTradeParams) {
+    for e in events.iter_mut() {
+        if let EngineEvent::TradeStatus { applied: applied @ None, .. } = e {
+            *applied = Some(params.clone());
+        }
+    }
+}
+
+/// min_out свапа. Auto: slippage = impact + буфер, не выше потолка; Err - потолок не покрывает impact
+pub async fn resolve_min_out(
+    params: &AppliedTradeParams,
+    t_in: Address,
+    t_out: Address,
+    amount_in: U256,
+    exp_out: U256,
+    is_buy: bool
+) -> Result<(U256, AppliedTradeParams), String> {
+    if !params.auto_slippage {
+        return Ok((apply_slippage(exp_out, params.slippage), params.clone()));
+    }
+    let (buffer_bps, max_pct) = {
+        let s = CORE_STATE.read().unwrap();
+        (s.auto_slippage_buffer_bps, s.auto_slippage_max_pct)
+    };
+    let dec_in = crate::monitor::get_decimals_cached(t_in).await;
+    let dec_out = crate::monitor::get_decimals_cached(t_out).await;
+    let ideal_out = calculate_ideal_out(t_in, t_out, amount_in, dec_in, is_buy, dec_out);
+    if ideal_out.is_zero() || exp_out.is_zero() {
+        return Err("Auto-slippage: no price to compute impact".into());
+    }
+    let impact = price_impact_pct(ideal_out, exp_out, dec_out);
+    if impact > max_pct {
+        return Err(format!("Auto-slippage: impact {:.2}% exceeds max {:.2}%, trade too large for pool", impact, max_pct));
+    }
+    let slippage = (impact + buffer_bps / 100.0).min(max_pct);
+    emit_log("DEBUG", format!("[TRADE] AUTO_SLIPPAGE | impact={:.3}% | slippage={:.3}%", impact, slippage));
+    let applied = AppliedTradeParams { slippage, impact_pct: Some(impact), ..params.clone() };
+    Ok((apply_slippage(exp_out, slippage), applied))
+}
+
+/// min_out с учётом slippage (в процентах, вне [0, 100] ограничивается)
+pub fn apply_slippage(exp_out: U256, slippage: f64) -> U256 {
+    let slippage_factor = (10000.0 - slippage.clamp(0.0, 100.0) * 100.0).round() as u64;
+    (exp_out * U256::from(slippage_factor)) / U256::from(10000)
+}
+
+/// Сумма сделки кошелька в wei: buy - amount, sell - только точная сумма из amounts_wei (100% продажи без потерь на округлении)
+pub fn resolve_amount_wei(
+    action: &str,
+    amount: f64,
+    dec: u8,
+    wallet_addr: Address,
+    amounts_wei: &Option<std::collections::HashMap<String, String>>
+) -> U256 {
+    if action == "sell" {
+        let wallet_str = format!("{:?}", wallet_addr).to_lowercase();
+        return amounts_wei.as_ref()
+            .and_then(|amounts| amounts.get(&wallet_str))
+            .and_then(|wei_str| U256::from_dec_str(wei_str).ok())
+            .unwrap_or_default();
+    }
+    match parse_units(amount, dec as u32) {
+        Ok(v) => v.into(),
+        Err(_) => U256::zero()
+    }
+}
+
+/// Доля живого баланса токена: 100% - весь баланс до последнего wei
+pub async fn percent_of_balance(token: Address, wallet: Address, percent: f64) -> U256 {
+    let balance = get_token_balance(token, wallet).await;
+    if percent >= 100.0 { return balance; }
+    // Базисные пункты: 33.33% -> 3333 bps
+    let bps = (percent.max(0.0) * 100.0).round() as u64;
+    balance.full_mul(U256::from(bps)).checked_div(U512::from(10_000u64))
+        .and_then(|v| U256::try_from(v).ok())
+        .unwrap_or_default()
+}
+
+/// Доли балансов для режимов, где сумма фиксируется заранее (TWAP, split): wallet (lowercase) -> wei
+pub async fn percent_amounts(token: Address, keys: &[WalletKey], percent: f64) -> std::collections::HashMap<String, String> {
+    let mut amounts = std::collections::HashMap::new();
+    for key in keys {
+        let wallet = key.address();
+        let amount = percent_of_balance(token, wallet, percent).await;
+        amounts.insert(format!("{:?}", wallet).to_lowercase(), amount.to_string());
+    }
+    amounts
+}
+
+/// Продажа кошелька до подписи свапа: allowance роутера на amount_wei токена, approve - на nonce по цене gas
+pub struct SellAllowance {
+    pub router: Address,
+    pub token: Address,
+    pub amount_wei: U256,
+    pub nonce: u64,
+    pub gas: f64,
+    pub dec: u8,
+}
+
+/// При продаже проверяет allowance и при нехватке отправляет approve вместо свапа.
+/// Возвращает TradeStatus апрува, если свап нужно пропустить.
+pub async fn ensure_sell_allowance(wallet: &LocalWallet, sell: SellAllowance) -> Option<EngineEvent> {
+    let SellAllowance { router, token, amount_wei, nonce, gas, dec } = sell;
+    // На продаже входной токен свапа - сам токен
+    let t_in = token;
+    let wallet_addr = wallet.address();
+    let t_allow = std::time::Instant::now();
+    let mut allowance = U256::zero();
+    // Свежий результат CheckApprovals/прошлой продажи избавляет от лишнего RPC
+    if let Some(a) = approvals::cached(wallet_addr, t_in, router) {
+        allowance = a;
+        emit_log("DEBUG", format!("[TRADE] ALLOWANCE CACHED | allowance={}", allowance));
+    } else {
+        // Получаем провайдера для проверки allowance
+        let url_opt = { RPC_POOL.read().unwrap().get_fastest_node() };
+        if let Some(url) = &url_opt {
+            if let Ok(u) = Url::parse(url) {
+                let p = Arc::new(Provider::new(Http::new_with_client(u, GLOBAL_HTTP_CLIENT.clone())));
+                let erc20 = IERC20::new(t_in, p); // t_in is Token address on Sell
+                if let Ok(a) = erc20.allowance(wallet_addr, router).call().await {
+                    allowance = a;
+                    approvals::store(wallet_addr, t_in, router, a);
                 }
+            emit_log("DEBUG", format!("[TRADE] ALLOWANCE CHECK | {}ms | allowance={}", t_allow.elapsed().as_millis(), allowance));
             }
         }
     }
-    U256::zero()
-}
+    
+    if allowance >= amount_wei {
+        approvals::consume(wallet_addr, t_in, router, amount_wei);
+        return None;
+    }
+    if trading_paused() {
+        return Some(paused_status(format!("{:?}", wallet_addr), "approve", t_in, 0.0));
+    }
+    
+    emit_log("WARNING", format!("🛡️ Auto-Approve required for {:?} (allowance: {})", wallet_addr, allowance));
+    
+    // Approve INSTEAD of Swap
+    let gas_price_wei = U256::from(gas_gwei_to_wei(gas));
+    let order = ApproveOrder {
+        token: t_in, spender: router, value: approve_value(amount_wei), current_allowance: allowance, nonce, gas_price_wei, position: Some(token)
+    };
+    let sent = send_approve(wallet, order).await;
+    let (hash, route, plan) = match sent {
+        Ok(s) => (s.tx_hash, s.route, s.plan),
+        Err(ApproveFailure::InsufficientGas(plan)) => {
+            return Some(insufficient_gas_status(wallet_addr, "approve", t_in, 0.0, &plan, gas_price_wei));
+        }
+        Err(ApproveFailure::Broadcast(e)) => {
+            return Some(rejected_status(format!("{:?}", wallet_addr), "approve", t_in, 0.0, "Error", e));
+        }
+    };
+    
+    emit_event(EngineEvent::TxSent {
+        tx_hash: hash.clone(),
+        wallet: format!("{:?}", wallet_addr),
+        action: "approve".into(),
+        amount: 0.0,
+        token: format!("{:?}", token),
+        timestamp_ms: current_timestamp_ms(),
+        route: route.into()
+    });
+    
+    Some(EngineEvent::TradeStatus {
+        wallet: format!("{:?}", wallet_addr),
+        action: "approve".into(),
+        status: "Sent".into(),
+        message: "Auto-Approve sent. Please retry SELL after confirmation.".into(),
+        tx_hash: Some(hash),
+        token_address: format!("{:?}", t_in),
+        amount: 0.0,
+        tokens_received: None,
+        tokens_sold: None,
+        token_decimals: dec,
+        slice_index: None,
+        leg: None,
+        gas_limit: Some(plan.limit),
+        gas_estimated: plan.estimated,
+        applied: None,
+        dry_run: dry_run()
+    })
+}
+
+/// Плечо свапа: тип и fee пула, через который t_in меняется на t_out
+pub struct SwapLeg<'a> {
+    pub pool_type: &'a str,
+    pub fee: u32,
+    pub t_in: Address,
+    pub t_out: Address,
+}
+
+/// Calldata свапа через TaxRouter для пула заданного типа (V3 мульти-хоп и V2 маршрут берутся из состояния)
+pub fn encode_swap_calldata(leg: &SwapLeg<'_>, amount_in: U256, min_out: U256, recipient: Address, deadline_secs: u64) -> Bytes {
+    let SwapLeg { pool_type: p_type, fee, t_in, t_out } = *leg;
+    let deadline = swap_deadline(deadline_secs);
+    if let (true, Some(path)) = (p_type == "V3", v3_route_path(t_in, t_out)) {
+        SwapV3MultiCall {
+            path,
+            amount_in,
+            amount_out_minimum: min_out,
+            recipient,
+            deadline
+        }.encode().into()
+    } else if p_type == "V3" {
+        SwapV3SingleCall { 
+            token_in: t_in, 
+            token_out: t_out, 
+            pool_fee: fee, 
+            amount_in, 
+            amount_out_minimum: min_out, 
+            recipient, 
+            deadline 
+        }.encode().into()
+    } else {
+        SwapExactTokensForTokensCall { 
+            amount_in, 
+            amount_out_min: min_out, 
+            path: swap_path(t_in, t_out), 
+            to: recipient, 
+            deadline 
+        }.encode().into()
+    }
+}
+
+/// Подписанный свап: всё, что нужно для рассылки и учёта pending tx
+pub struct SignedSwap {
+    pub wallet: Address,
+    pub raw_tx: Bytes,
+    pub plan: GasPlan,
+    pub gas_price: U256,
+    pub action: String,
+    pub token: Address,
+    pub amount: f64,
+    pub amount_wei: U256,
+    pub exp_out: U256,
+    pub dec: u8,
+}
+
+/// Свап кошелька к подписи: tx на роутер и то, что уйдёт в TradeStatus и pending tx
+pub struct SwapOrder<'a> {
+    pub router: Address,
+    pub nonce: u64,
+    pub calldata: Bytes,
+    pub gas: f64,
+    pub action: &'a str,
+    pub token: Address,
+    pub amount: f64,
+    pub amount_wei: U256,
+    pub exp_out: U256,
+    pub dec: u8,
+}
+
+/// Оценивает газ и подписывает свап. Err - TradeStatus отказа (None - подпись не удалась)
+pub async fn sign_swap(wallet: &LocalWallet, order: SwapOrder<'_>) -> Result<SignedSwap, Option<EngineEvent>> {
+    let SwapOrder { router, nonce, calldata, gas, action, token, amount, amount_wei, exp_out, dec } = order;
+    let wallet_addr = wallet.address();
+    // Пауза могла включиться посреди batch - остальные кошельки не подписываем
+    if trading_paused() {
+        return Err(Some(paused_status(format!("{:?}", wallet_addr), action, token, amount)));
+    }
+    let gas_price_wei = U256::from(gas_gwei_to_wei(gas));
+    let tx = TransactionRequest::new()
+        .to(router)
+        .value(0)
+        .nonce(nonce)
+        .data(calldata)
+        .gas_price(gas_price_wei);
+        
+    let mut typed_tx: TypedTransaction = tx.into();
+    let t_estimate = std::time::Instant::now();
+    let fallback = { CORE_STATE.read().unwrap().gas_limit_swap };
+    let plan = plan_gas(&typed_tx, wallet_addr, fallback, gas_price_wei).await;
+    emit_log("DEBUG", format!("[TRADE] GAS_LIMIT | {}ms | estimated={:?} | limit={}", t_estimate.elapsed().as_millis(), plan.estimated, plan.limit));
+    if !plan.affordable {
+        return Err(Some(insufficient_gas_status(wallet_addr, action, token, amount, &plan, gas_price_wei)));
+    }
+    typed_tx.set_gas(plan.limit);
+    let typed_tx = network_tx(typed_tx);
+    
+    let sig = wallet.sign_transaction_sync(&typed_tx).map_err(|_| None)?;
+    Ok(SignedSwap {
+        wallet: wallet_addr,
+        raw_tx: typed_tx.rlp_signed(&sig),
+        plan,
+        gas_price: gas_price_wei,
+        action: action.to_string(),
+        token,
+        amount,
+        amount_wei,
+        exp_out,
+        dec,
+    })
+}
+
+/// Рассылает подписанный свап, регистрирует pending tx. Возвращает TradeStatus
+pub async fn broadcast_swap(signed: SignedSwap) -> EngineEvent {
+    let SignedSwap { wallet: wallet_addr, raw_tx, plan, gas_price, action, token, amount, amount_wei, exp_out, dec } = signed;
+    if trading_paused() {
+        return paused_status(format!("{:?}", wallet_addr), &action, token, amount);
+    }
+    let t_broadcast = std::time::Instant::now();
+    let sent = parallel_broadcast(wallet_addr, raw_tx).await;
+    let (hash, message) = match &sent {
+        Ok((h, _)) => (tx_hash_label(h), tx_hash_label(h)),
+        Err(e) => (String::new(), e.status_message()),
+    };
+    emit_log("DEBUG", format!("[TRADE] BROADCAST | {}ms | {}", t_broadcast.elapsed().as_millis(), &message[..16.min(message.len())]));
+    
+    let is_success = sent.is_ok();
+    metrics::inc(if is_success { &metrics::METRICS.swaps_sent } else { &metrics::METRICS.swaps_failed });
+    
+    if let Ok((tx_hash_h256, route)) = sent {
+        {
+            let mut s = CORE_STATE.write().unwrap();
+            // buy: amount_wei - quote на входе, exp_out - токены; sell - наоборот
+            let swap = s.tokens.get(&token).map(|c| PendingSwap {
+                is_buy: action == "buy",
+                quote: c.quote,
+                amount_in: amount_wei,
+                expected_out: exp_out,
+            });
+            s.pending_txs.insert(tx_hash_h256, PendingTx {
+                request_id: current_request_id(),
+                gas_limit: Some(plan.limit),
+                gas_estimated: plan.estimated,
+                position: Some((wallet_addr, token)),
+                swap,
+                wallet: wallet_addr,
+                gas_price: Some(gas_price),
+                ..Default::default()
+            });
+            s.last_traded_token.insert(wallet_addr, token);
+        }
+        
+        emit_event(EngineEvent::TxSent {
+            tx_hash: hash.clone(),
+            wallet: format!("{:?}", wallet_addr),
+            action: action.clone(),
+            amount,
+            token: format!("{:?}", token),
+            timestamp_ms: current_timestamp_ms(),
+            route: route.into()
+        });
+    }
+    
+    let (tok_received, tok_sold) = if action == "buy" {
+        (Some(exp_out.to_string()), None)
+    } else {
+        (None, Some(amount_wei.to_string()))
+    };
+
+    EngineEvent::TradeStatus { 
+        wallet: format!("{:?}", wallet_addr), 
+        action, 
+        status: if is_success { "Sent".into() } else { "Error".into() }, 
+        message, 
+        tx_hash: if is_success { Some(hash) } else { None },
+        token_address: format!("{:?}", token),
+        amount,
+        tokens_received: tok_received,
+        tokens_sold: tok_sold,
+        token_decimals: dec,
+        slice_index: None,
+        leg: None,
+        gas_limit: Some(plan.limit),
+        gas_estimated: plan.estimated,
+        applied: None,
+        dry_run: dry_run()
+    }
+}
+
+/// Подписывает и рассылает свап, регистрирует pending tx. Возвращает TradeStatus
+pub async fn send_swap(wallet: &LocalWallet, order: SwapOrder<'_>) -> Option<EngineEvent> {
+    match sign_swap(wallet, order).await {
+        Ok(signed) => Some(broadcast_swap(signed).await),
+        Err(ev) => ev,
+    }
+}
+
+/// Выполняет batch trade для списка кошельков
+pub async fn run_batch_trade(req: BatchRequest, trade: TradeSettings) -> Vec<EngineEvent> {
+    if trading_paused() {
+        emit_log("WARNING", format!("⏸️ Торговля на паузе, {} не отправлен", req.action));
+        return vec![paused_status("SYSTEM".into(), &req.action, req.token, req.amount)];
+    }
+    // Стратегия газа превращается в цену прямо перед подписью (у TWAP - для каждой части)
+    let gas = trade.gas.resolve_gwei();
+    let params = applied_params(trade.slippage, gas, trade.deadline_secs, trade.slippage_mode);
+    let mut events = batch_trade(req, gas, &params).await;
+    stamp_applied(&mut events, &params);
+    events
+}
+
+/// Общие для всех кошельков параметры batch trade: читаются из состояния один раз до параллельной подготовки
+struct BatchContext<'a> {
+    router: Address,
+    action: &'a str,
+    token: Address,
+    t_in: Address,
+    t_out: Address,
+    p_type: &'a str,
+    p_fee: u32,
+    direct_pool: Option<Address>,
+    quoted_price: Option<f64>, // цена пула на момент quote (None - anti-sandwich сверка выключена)
+    max_move_pct: f64,
+    gas: f64,
+    dec: u8,
+    params: &'a AppliedTradeParams,
+}
+
+/// Кошелёк batch trade: подписанный свап ждёт рассылки (burst, bundle) или уже есть итоговый статус
+pub enum WalletOutcome {
+    Signed(SignedSwap, AppliedTradeParams),
+    Done(Option<EngineEvent>),
+}
+
+/// Batch trade к подготовке: кошельки, пара и суммы (газ и параметры сделки - отдельно)
+pub struct BatchRequest {
+    pub keys: Vec<WalletKey>,
+    pub router: Address,
+    pub action: String,
+    pub token: Address,
+    pub quote: Address,
+    pub amount: f64,
+    pub chain_id: u64,
+    pub amounts_wei: Option<std::collections::HashMap<String, String>>,
+    pub percent: Option<f64>,
+}
+
+/// Параметры сделки из команды до применения: газ - стратегия, 0/None - значения ядра (см. applied_params)
+#[derive(Clone, Copy)]
+pub struct TradeSettings {
+    pub gas: GasStrategy,
+    pub slippage: f64,
+    pub deadline_secs: Option<u64>,
+    pub slippage_mode: Option<SlippageMode>,
+}
+
+impl TradeSettings {
+    /// Ручной газ и slippage, дедлайн и режим - по умолчанию ядра
+    pub fn manual(gas_gwei: f64, slippage: f64) -> Self {
+        Self { gas: GasStrategy::Manual(gas_gwei), slippage, deadline_secs: None, slippage_mode: None }
+    }
+}
+
+/// Batch trade после подготовки: отказы до резервирования nonce и кошельки с зарезервированным nonce
+pub struct PreparedBatch {
+    pub rejected: Vec<EngineEvent>,
+    pub wallets: Vec<(Address, u64, WalletOutcome)>,
+    pub direct_pool: Option<Address>,
+}
+
+/// Allowance, min_out, anti-sandwich сверка и подпись свапа одного кошелька
+async fn prepare_wallet_swap(
+    ctx: &BatchContext<'_>,
+    wallet: &LocalWallet,
+    nonce: u64,
+    amount_wei: U256,
+    amount: f64,
+    exp_out: U256
+) -> Result<(SignedSwap, AppliedTradeParams), Option<EngineEvent>> {
+    let wallet_addr = wallet.address();
+    if ctx.action == "sell" {
+        let sell = SellAllowance { router: ctx.router, token: ctx.token, amount_wei, nonce, gas: ctx.gas, dec: ctx.dec };
+        if let Some(approve_ev) = ensure_sell_allowance(wallet, sell).await {
+            return Err(Some(approve_ev)); // Пропуск свапа для кошелька, ожидаем апрув
+        }
+    }
 
-/// Вычисляет идеальный выход на основе спотовой цены (без учёта slippage/impact)
-pub fn calculate_ideal_out(_token_in: Address, _token_out: Address, amount_in: U256, decimals_in: u8, is_buy: bool, decimals_out: u8) -> U256 {
-    // emit_log("DEBUG", format!("ideal_out: in={:?}, out={:?}, is_buy={}", _token_in, _token_out, is_buy));
-    if amount_in.is_zero() { return U256::zero(); }
-    
-    let s = CORE_STATE.read().unwrap();
-    let price = s.selected_pool_spot_price;  // quote_per_token
-    
-    if price > 0.0 {
-        // BUY: вводим quote → получаем token → нужен token_per_quote = 1/price
-        // SELL: вводим token → получаем quote → нужен quote_per_token = price
-        let actual_p = if is_buy { 1.0 / price } else { price };
-        
-        let amount_f = u256_to_f64_safe(amount_in, decimals_in as u32);
-        let out_f = amount_f * actual_p;
-        
-        if out_f.is_nan() || out_f.is_infinite() || out_f <= 0.0 {
-            return U256::zero();
+    let (min_out, applied) = match resolve_min_out(ctx.params, ctx.t_in, ctx.t_out, amount_wei, exp_out, ctx.action == "buy").await {
+        Ok(v) => v,
+        Err(message) => {
+            emit_log("WARNING", format!("⚠️ {:?}: {}", wallet_addr, message));
+            return Err(Some(rejected_status(format!("{:?}", wallet_addr), ctx.action, ctx.token, amount, "Error", message)));
         }
-        
-        // Конвертируем обратно в wei (используем decimals_out, но можно approx)
-        match ethers::utils::parse_units(out_f, decimals_out as u32) {
-            Ok(v) => v.into(),
-            Err(_) => U256::zero()
+    };
+    // Anti-sandwich: пул заметно сдвинулся после quote - в него не отправляем
+    if let (Some(pool), Some(quoted)) = (ctx.direct_pool, ctx.quoted_price) {
+        let moved = crate::sandwich::price_moved_pct(pool, ctx.p_type == "V3", quoted).await;
+        if let Some(moved) = moved.filter(|m| *m > ctx.max_move_pct) {
+            emit_log("WARNING", format!("🥪 {:?}: цена пула сдвинулась на {:.2}% после quote, свап отменён", wallet_addr, moved));
+            return Err(Some(rejected_status(format!("{:?}", wallet_addr), ctx.action, ctx.token, amount, "Aborted", format!("Aborted: price moved {:.2}%", moved))));
         }
-    } else {
-        U256::zero()
     }
+    let leg = SwapLeg { pool_type: ctx.p_type, fee: ctx.p_fee, t_in: ctx.t_in, t_out: ctx.t_out };
+    let calldata = encode_swap_calldata(&leg, amount_wei, min_out, wallet_addr, applied.deadline_secs);
+    let signed = sign_swap(wallet, SwapOrder {
+        router: ctx.router, nonce, calldata, gas: ctx.gas, action: ctx.action, token: ctx.token, amount, amount_wei, exp_out, dec: ctx.dec
+    }).await?;
+    Ok((signed, applied))
 }
 
-/// Выполняет batch trade для списка кошельков
-pub async fn run_batch_trade(
-    keys: Vec<String>, 
-    router: Address, 
-    action: String, 
-    token: Address, 
-    quote: Address, 
-    amount: f64, 
-    gas: f64, 
-    slippage: f64, 
-    _v3_f: u32, 
-    chain_id: u64,
-    amounts_wei: Option<std::collections::HashMap<String, String>>
-) -> Vec<EngineEvent> {
+/// Рассылка подписанного свапа batch trade: фактические параметры в статус, пул - под проверку на sandwich
+pub async fn finish_wallet_swap(signed: SignedSwap, applied: AppliedTradeParams, direct_pool: Option<Address>) -> EngineEvent {
+    let mut ev = broadcast_swap(signed).await;
+    if let EngineEvent::TradeStatus { applied: stamped, tx_hash, .. } = &mut ev {
+        *stamped = Some(applied);
+        if let (Some(hash), Some(pool)) = (tx_hash, direct_pool) {
+            crate::sandwich::watch(hash, pool);
+        }
+    }
+    ev
+}
+
+async fn batch_trade(req: BatchRequest, gas: f64, params: &AppliedTradeParams) -> Vec<EngineEvent> {
     let start_time = std::time::Instant::now();
-    emit_log("DEBUG", format!("[TRADE] START | action={} | amount={} | gas_gwei={}", action, amount, gas));
-    let mut events = Vec::new();
-    let (p_type, p_fee) = { 
+    emit_log("DEBUG", format!("[TRADE] START | action={} | amount={} | gas_gwei={} | slippage={} | deadline={}s",
+        req.action, req.amount, gas, params.slippage, params.deadline_secs));
+    let action = req.action.clone();
+    let burst = { CORE_STATE.read().unwrap().batch_burst };
+    let batch = prepare_batch(req, gas, params, !burst).await;
+    let wallets = batch.wallets.len();
+    let events = finish_batch(batch).await;
+    let elapsed = start_time.elapsed().as_millis();
+    emit_log("DEBUG", format!("[TRADE] TOTAL | {}ms | events={}", elapsed, events.len()));
+    emit_log("INFO", format!("⚡ {} batch: {} кошельков за {} мс{}", action, wallets, elapsed, if burst { " (burst)" } else { "" }));
+    events
+}
+
+/// Рассылает подписанные свапы batch разом, возвращает nonce кошельков, которые ничего не отправили
+pub async fn finish_batch(batch: PreparedBatch) -> Vec<EngineEvent> {
+    let PreparedBatch { mut rejected, wallets, direct_pool } = batch;
+    let results = join_all(wallets.into_iter().map(|(wallet, nonce, outcome)| async move {
+        let ev = match outcome {
+            WalletOutcome::Signed(signed, applied) => Some(finish_wallet_swap(signed, applied, direct_pool).await),
+            WalletOutcome::Done(ev) => ev,
+        };
+        (wallet, nonce, ev)
+    })).await;
+    for (wallet, nonce, ev) in results {
+        release_if_unsent(wallet, nonce, &ev);
+        rejected.extend(ev);
+    }
+    rejected
+}
+
+/// Кошелёк ничего не отправил (нет tx_hash в статусе) - зарезервированный nonce возвращается
+pub fn release_if_unsent(wallet: Address, nonce: u64, ev: &Option<EngineEvent>) {
+    let sent = matches!(ev, Some(EngineEvent::TradeStatus { tx_hash: Some(_), .. }));
+    if !sent { release_nonce(wallet, nonce); }
+}
+
+/// Подготовка batch trade: суммы, nonce, котировки и подписи всех кошельков параллельно.
+/// send_when_ready - кошелёк рассылается сразу после подписи, иначе свап ждёт в WalletOutcome::Signed
+pub async fn prepare_batch(req: BatchRequest, gas: f64, params: &AppliedTradeParams, send_when_ready: bool) -> PreparedBatch {
+    let BatchRequest { keys, router, action, token, quote, amount, chain_id, amounts_wei, percent } = req;
+    let action = action.as_str();
+    let (t_in, t_out) = if action == "buy" { (quote, token) } else { (token, quote) };
+    // Общее состояние читается один раз: параллельные кошельки не встают в очередь за локом
+    let (p_type, p_fee, direct_pool, precheck, max_move_pct, quoter, dec) = { 
         let s = CORE_STATE.read().unwrap(); 
-        (s.selected_pool_type.clone().unwrap_or_default(), s.selected_pool_fee) 
+        let (p_type, p_fee, direct_pool) = s.pair_context(token, quote)
+            .map(|c| (c.pool_type.clone().unwrap_or_default(), c.pool_fee, c.pool_address.filter(|_| c.route.is_empty())))
+            .unwrap_or_default();
+        let dec = *s.decimals_cache.get(&t_in).unwrap_or(&18);
+        (p_type, p_fee, direct_pool, s.sandwich_precheck, s.sandwich_max_move_pct, s.quoter_address, dec)
     };
+    let mut batch = PreparedBatch { rejected: Vec::new(), wallets: Vec::new(), direct_pool };
     
     if p_type.is_empty() { 
-        return vec![EngineEvent::TradeStatus { 
+        batch.rejected.push(EngineEvent::TradeStatus { 
             wallet: "SYSTEM".into(), 
-            action, 
+            action: action.to_string(), 
             status: "Error".into(), 
             message: "No pool selected!".into(), 
             tx_hash: None,
@@ -296,50 +1664,40 @@ pub async fn run_batch_trade(
             amount,
             tokens_received: None,
             tokens_sold: None,
-            token_decimals: 18
-        }]; 
+            token_decimals: 18,
+            slice_index: None,
+            leg: None,
+            gas_limit: None,
+            gas_estimated: None,
+            applied: None,
+            dry_run: dry_run()
+        });
+        return batch;
     }
     
-    let url_opt = { RPC_POOL.read().unwrap().get_fastest_node() };
-    
-    for pk in keys {
-        let wallet: LocalWallet = match pk.parse::<LocalWallet>() { 
-            Ok(w) => w.with_chain_id(chain_id), 
-            Err(_) => continue 
-        };
-        
-        let wallet_addr = wallet.address();
-        let (t_in, t_out) = if action == "buy" { (quote, token) } else { (token, quote) };
-        let dec = { *CORE_STATE.read().unwrap().decimals_cache.get(&t_in).unwrap_or(&18) };
-        
-        // Безопасный парсинг суммы с учетом точной продажи 100%
-        let mut amount_wei: U256 = match parse_units(amount, dec as u32) {
-            Ok(v) => v.into(),
-            Err(_) => U256::zero()
-        };
-        
-        if action == "sell" {
-            let mut exact_wei_from_python = None;
-            if let Some(ref amounts) = amounts_wei {
-                let wallet_str = format!("{:?}", wallet_addr).to_lowercase();
-                if let Some(wei_str) = amounts.get(&wallet_str) {
-                    if let Ok(w) = U256::from_dec_str(wei_str) {
-                        exact_wei_from_python = Some(w);
-                    }
+    // 1. Суммы кошельков: точная продажа 100%; percent - от живого баланса прямо перед подписью
+    let amounts = join_all(keys.iter().map(|key| {
+        let wallet = signer(key, chain_id);
+        let amounts_wei = &amounts_wei;
+        async move {
+            let wallet_addr = wallet.address();
+            let (amount_wei, amount) = match percent {
+                Some(pct) => {
+                    let wei = percent_of_balance(token, wallet_addr, pct).await;
+                    (wei, u256_to_f64_safe(wei, dec as u32))
                 }
-            }
-            
-            if let Some(w) = exact_wei_from_python {
-                amount_wei = w;
-            } else {
-                amount_wei = U256::zero();
-            }
+                None => (resolve_amount_wei(action, amount, dec, wallet_addr, amounts_wei), amount),
+            };
+            (wallet, amount_wei, amount)
         }
-        
+    })).await;
+
+    let mut ready = Vec::new();
+    for (wallet, amount_wei, amount) in amounts {
         if amount_wei.is_zero() {
-            events.push(EngineEvent::TradeStatus {
-                wallet: format!("{:?}", wallet_addr),
-                action: action.clone(),
+            batch.rejected.push(EngineEvent::TradeStatus {
+                wallet: format!("{:?}", wallet.address()),
+                action: action.to_string(),
                 status: "Error".into(),
                 message: if action == "sell" { "Zero balance to sell".into() } else { "Invalid amount".into() },
                 tx_hash: None,
@@ -347,265 +1705,602 @@ pub async fn run_batch_trade(
                 amount,
                 tokens_received: None,
                 tokens_sold: None,
-                token_decimals: dec
+                token_decimals: dec,
+                slice_index: None,
+                leg: None,
+                gas_limit: None,
+                gas_estimated: None,
+                applied: None,
+                dry_run: dry_run()
             });
             continue;
         }
-        
-        let t_nonce = std::time::Instant::now();
-        let nonce = { *CORE_STATE.read().unwrap().nonce_map.get(&wallet_addr).unwrap_or(&0) };
-        emit_log("DEBUG", format!("[TRADE] NONCE | {}ms | nonce={}", t_nonce.elapsed().as_millis(), nonce));
-        let deadline = U256::from(
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs() + 300
-        );
-        
-        // ================= АВТОМАТИЧЕСКАЯ ПРОВЕРКА ALLOWANCE ПРИ ПРОДАЖЕ =================
-        if action == "sell" {
-            let t_allow = std::time::Instant::now();
-            let mut allowance = U256::zero();
-            // Получаем провайдера для проверки allowance
-            if let Some(url) = &url_opt {
-                if let Ok(u) = Url::parse(url) {
-                    let p = Arc::new(Provider::new(Http::new_with_client(u, GLOBAL_HTTP_CLIENT.clone())));
-                    let erc20 = IERC20::new(t_in, p); // t_in is Token address on Sell
-                    if let Ok(a) = erc20.allowance(wallet_addr, router).call().await {
-                        allowance = a;
+        ready.push((wallet, amount_wei, amount));
+    }
+    if ready.is_empty() { return batch; }
+
+    // 2. Nonce всех кошельков резервируются разом, до подготовки
+    let addresses: Vec<Address> = ready.iter().map(|(w, ..)| w.address()).collect();
+    let nonces = reserve_nonces(&addresses);
+
+    // 3. Одна котировка на уникальную сумму: одинаковый buy с N кошельков - один вызов quoter
+    let t_exp = std::time::Instant::now();
+    let mut unique: Vec<U256> = ready.iter().map(|(_, wei, _)| *wei).collect();
+    unique.sort();
+    unique.dedup();
+    let quoted_price = direct_pool.filter(|_| precheck).and_then(crate::sandwich::cached_price);
+    let quotes: std::collections::HashMap<U256, U256> = join_all(unique.into_iter().map(|amount_wei| {
+        let p_type = &p_type;
+        async move {
+            let out = if p_type == "V3" {
+                calculate_expected_out_v3_quoted(t_in, t_out, amount_wei, p_fee, quoter).await
+            } else {
+                calculate_expected_out_v2_pure(t_in, t_out, amount_wei)
+            };
+            (amount_wei, out)
+        }
+    })).await.into_iter().collect();
+    emit_log("DEBUG", format!("[TRADE] EXPECTED_OUT | {}ms | pool_type={} | quotes={}", t_exp.elapsed().as_millis(), p_type, quotes.len()));
+
+    // 4. Подготовка и подпись параллельно; рассылка - как только кошелёк готов (если send_when_ready)
+    let ctx = BatchContext {
+        router, action, token, t_in, t_out, p_type: &p_type, p_fee, direct_pool, quoted_price, max_move_pct, gas, dec, params,
+    };
+    let outcomes = join_all(ready.iter().zip(&nonces).map(|((wallet, amount_wei, amount), nonce)| {
+        let (ctx, exp_out) = (&ctx, quotes.get(amount_wei).copied().unwrap_or_default());
+        async move {
+            match prepare_wallet_swap(ctx, wallet, *nonce, *amount_wei, *amount, exp_out).await {
+                Ok((signed, applied)) if send_when_ready => WalletOutcome::Done(Some(finish_wallet_swap(signed, applied, direct_pool).await)),
+                Ok((signed, applied)) => WalletOutcome::Signed(signed, applied),
+                Err(ev) => WalletOutcome::Done(ev),
+            }
+        }
+    })).await;
+    batch.wallets = addresses.into_iter().zip(nonces).zip(outcomes).map(|((w, n), o)| (w, n, o)).collect();
+    batch
+}
+
+/// Собирает ключи и точные суммы (100% баланса токена) для продажи с указанных кошельков
+pub async fn collect_full_sell_amounts(token: Address, wallets: &[Address]) -> (Vec<WalletKey>, std::collections::HashMap<String, String>) {
+    let mut keys = Vec::new();
+    let mut amounts_wei = std::collections::HashMap::new();
+    for wallet in wallets {
+        let balance = get_token_balance(token, *wallet).await;
+        if balance.is_zero() { continue; }
+        let key = { CORE_STATE.read().unwrap().wallet_keys.get(wallet).cloned() };
+        if let Some(key) = key {
+            keys.push(key);
+            amounts_wei.insert(format!("{:?}", wallet).to_lowercase(), balance.to_string());
+        }
+    }
+    (keys, amounts_wei)
+}
+
+fn keys_for_wallets(wallets: &[Address]) -> Vec<WalletKey> {
+    let s = CORE_STATE.read().unwrap();
+    wallets.iter().filter_map(|w| s.wallet_keys.get(w).cloned()).collect()
+}
+
+/// Сделка по сохранённым параметрам (лимитные ордера, расписания). Эмитит события, возвращает хеши tx.
+/// Sell без точных сумм: amount > 0 - столько токенов с каждого кошелька (но не больше баланса), 0 - весь баланс.
+pub async fn run_stored_trade(
+    action: &str,
+    token: Address,
+    quote: Address,
+    amount: f64,
+    wallets: &[Address],
+    slippage: f64,
+    amounts_wei: Option<std::collections::HashMap<String, String>>
+) -> Vec<String> {
+    let (router, chain_id, gas) = {
+        let s = CORE_STATE.read().unwrap();
+        (s.router_address, s.chain_id, s.manual_gas_price_gwei)
+    };
+
+    let (keys, amounts_wei) = match (action, amounts_wei) {
+        ("sell", Some(amounts)) => (keys_for_wallets(wallets), Some(amounts)),
+        ("sell", None) => {
+            let (keys, mut amounts) = collect_full_sell_amounts(token, wallets).await;
+            if amount > 0.0 {
+                let dec = crate::monitor::get_decimals_cached(token).await;
+                if let Ok(per_wallet) = parse_units(amount, dec as u32) {
+                    let per_wallet: U256 = per_wallet.into();
+                    for wei in amounts.values_mut() {
+                        let balance = U256::from_dec_str(wei).unwrap_or_default();
+                        *wei = balance.min(per_wallet).to_string();
                     }
-                emit_log("DEBUG", format!("[TRADE] ALLOWANCE CHECK | {}ms | allowance={}", t_allow.elapsed().as_millis(), allowance));
                 }
             }
-            
-            if allowance < amount_wei {
-                emit_log("WARNING", format!("🛡️ Auto-Approve required for {:?} (allowance: {})", wallet_addr, allowance));
-                
-                // Construct Approve Transaction INSTEAD of Swap
-                let erc20_dummy = IERC20::new(t_in, Arc::new(Provider::new(Http::new(Url::parse("http://localhost").unwrap()))));
-                let data = erc20_dummy.approve(router, U256::max_value()).tx.data().cloned().unwrap();
-                
-                let tx = TransactionRequest::new()
-                    .to(t_in)
-                    .value(0)
-                    .nonce(nonce)
-                    .data(data)
-                    .gas(100000)
-                    .gas_price(gas_gwei_to_wei(gas));
-                    
-                let typed_tx: TypedTransaction = tx.into();
-                
-                if let Ok(sig) = wallet.sign_transaction_sync(&typed_tx) {
-                    let raw_tx = typed_tx.rlp_signed(&sig);
-                    let hash = parallel_broadcast(raw_tx.clone()).await;
-                    
-                    emit_event(EngineEvent::TxSent {
-                        tx_hash: hash.clone(),
-                        wallet: format!("{:?}", wallet_addr),
-                        action: "approve".into(),
-                        amount: 0.0,
-                        token: format!("{:?}", token),
-                        timestamp_ms: current_timestamp_ms()
-                    });
-                    
-                    events.push(EngineEvent::TradeStatus {
-                        wallet: format!("{:?}", wallet_addr),
-                        action: "approve".into(),
-                        status: "Sent".into(),
-                        message: "Auto-Approve sent. Please retry SELL after confirmation.".into(),
-                        tx_hash: Some(hash),
-                        token_address: format!("{:?}", t_in),
-                        amount: 0.0,
-                        tokens_received: None,
-                        tokens_sold: None,
-                        token_decimals: dec
-                    });
+            (keys, Some(amounts))
+        }
+        _ => (keys_for_wallets(wallets), None),
+    };
+
+    let req = BatchRequest { keys, router, action: action.to_string(), token, quote, amount, chain_id, amounts_wei, percent: None };
+    let events = run_batch_trade(req, TradeSettings::manual(gas, slippage)).await;
+
+    let tx_hashes: Vec<String> = events.iter().filter_map(|e| match e {
+        EngineEvent::TradeStatus { tx_hash: Some(h), .. } => Some(h.clone()),
+        _ => None,
+    }).collect();
+
+    for e in events { emit_event(e); }
+    tx_hashes
+}
+
+/// Экстренная продажа 100% баланса токена со всех отслеживаемых кошельков
+pub async fn sell_all_tracked(token: Address, quote: Address, slippage: f64) -> Vec<EngineEvent> {
+    let wallets: Vec<Address> = TRACKED_WALLETS.read().unwrap().clone();
+    let (router, chain_id, gas) = {
+        let s = CORE_STATE.read().unwrap();
+        (s.router_address, s.chain_id, s.manual_gas_price_gwei)
+    };
+
+    let (keys, amounts_wei) = collect_full_sell_amounts(token, &wallets).await;
+    if keys.is_empty() {
+        emit_log("WARNING", "🚨 Auto-sell: нет кошельков с балансом токена".into());
+        return Vec::new();
+    }
+
+    let req = BatchRequest { keys, router, action: "sell".into(), token, quote, amount: 0.0, chain_id, amounts_wei: Some(amounts_wei), percent: None };
+    run_batch_trade(req, TradeSettings::manual(gas, slippage)).await
+}
+
+/// TWAP: делит объём на равные части и исполняет их последовательно через run_batch_trade.
+/// Expected/min out пересчитываются по живым резервам перед каждой частью. Первая ошибка обрывает остаток.
+/// percent запроса уже переведён в amounts_wei: все части делят объём, зафиксированный при старте
+pub async fn run_twap_trade(req: BatchRequest, twap: TwapParams, trade: TradeSettings) {
+    let BatchRequest { keys, router, action, token, quote, amount, chain_id, amounts_wei, .. } = req;
+    let TwapParams { slices, interval_secs } = twap;
+    let slices = slices.max(1);
+    let slice_amount = amount / slices as f64;
+    let mut weighted_price = 0.0;
+    let mut filled_amount = 0.0;
+    let mut slices_done = 0u32;
+    let mut abort_reason: Option<String> = None;
+
+    emit_log("INFO", format!("⏱️ TWAP {}: {} частей по {:.6} каждые {} сек", action, slices, slice_amount, interval_secs));
+
+    for slice in 0..slices {
+        if SHUTDOWN_FLAG.load(std::sync::atomic::Ordering::Relaxed) {
+            abort_reason = Some("Shutdown".into());
+            break;
+        }
+
+        // Точные суммы продажи делим поровну, остаток - в последнюю часть
+        let slice_wei = amounts_wei.as_ref().map(|amounts| {
+            amounts.iter().map(|(wallet, wei)| {
+                let total = U256::from_dec_str(wei).unwrap_or_default();
+                let part = total / U256::from(slices);
+                let value = if slice + 1 == slices { total - part * U256::from(slices - 1) } else { part };
+                (wallet.clone(), value.to_string())
+            }).collect()
+        });
+
+        let spot_price = { CORE_STATE.read().unwrap().pair_context(token, quote).map(|c| c.spot_price).unwrap_or(0.0) };
+        let slice_req = BatchRequest {
+            keys: keys.clone(), router, action: action.clone(), token, quote, amount: slice_amount, chain_id, amounts_wei: slice_wei, percent: None
+        };
+        let mut evs = run_batch_trade(slice_req, trade).await;
+
+        let mut failed = None;
+        for e in evs.iter_mut() {
+            if let EngineEvent::TradeStatus { slice_index, status, message, .. } = e {
+                *slice_index = Some(slice);
+                if (status == "Error" || status == "Paused" || status == "InsufficientGas") && failed.is_none() {
+                    failed = Some(message.clone());
                 }
-                continue; // Пропуск свапа для кошелька, ожидаем апрув
             }
         }
-        // ===================================================================================
-        
-        // Извлекаем quoter ПЕРЕД await
-        let t_exp = std::time::Instant::now();
-        let exp_out = if p_type == "V3" {
-            let quoter = CORE_STATE.read().unwrap().quoter_address;
-            calculate_expected_out_v3_quoted(t_in, t_out, amount_wei, p_fee, quoter).await
-        } else {
-            calculate_expected_out_v2_pure(t_in, t_out, amount_wei)
-        };
-        emit_log("DEBUG", format!("[TRADE] EXPECTED_OUT | {}ms | pool_type={} | exp_out={}", t_exp.elapsed().as_millis(), p_type, exp_out));
-        
-        // Безопасное вычисление min_out
-        let slippage_factor = (10000.0 - slippage * 100.0).max(0.0).min(10000.0) as u64;
-        let min_out = (exp_out * U256::from(slippage_factor)) / U256::from(10000);
-
-        let calldata = if p_type == "V3" {
-            SwapV3SingleCall { 
-                token_in: t_in, 
-                token_out: t_out, 
-                pool_fee: p_fee, 
-                amount_in: amount_wei, 
-                amount_out_minimum: min_out, 
-                recipient: wallet_addr, 
-                deadline 
-            }.encode()
+        if evs.is_empty() {
+            failed = Some("no trades executed".into());
+        }
+        for e in evs { emit_event(e); }
+
+        if let Some(reason) = failed {
+            abort_reason = Some(format!("slice {}: {}", slice, reason));
+            break;
+        }
+
+        slices_done += 1;
+        weighted_price += spot_price * slice_amount;
+        filled_amount += slice_amount;
+
+        if slice + 1 < slices {
+            tokio::time::sleep(tokio::time::Duration::from_secs(interval_secs)).await;
+        }
+    }
+
+    let avg_price = if filled_amount > 0.0 { weighted_price / filled_amount } else { 0.0 };
+    match &abort_reason {
+        Some(r) => emit_log("ERROR", format!("⏱️ TWAP прерван после {}/{} частей: {}", slices_done, slices, r)),
+        None => emit_log("SUCCESS", format!("⏱️ TWAP завершён: {} частей, средняя цена {:.10}", slices, avg_price)),
+    }
+
+    emit_event(EngineEvent::TwapCompleted {
+        token: format!("{:?}", token),
+        action,
+        slices_total: slices,
+        slices_done,
+        avg_price,
+        aborted: abort_reason.is_some(),
+        reason: abort_reason,
+    });
+}
+
+// Фора приватным релеям в режиме both: билдер должен увидеть tx раньше публичного mempool
+const RELAY_HEAD_START: std::time::Duration = std::time::Duration::from_millis(150);
+
+/// Причина отказа ноды принять raw tx
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BroadcastErrorKind {
+    // Порядок - приоритет при сводке: транспортные ошибки наименее информативны
+    Connection,
+    Other,
+    Underpriced,
+    InsufficientFunds,
+    NonceTooLow,
+    AlreadyKnown,
+}
+
+impl BroadcastErrorKind {
+    fn classify(message: &str) -> Self {
+        let m = message.to_lowercase();
+        if m.contains("already known") || m.contains("known transaction") || m.contains("alreadyknown") {
+            Self::AlreadyKnown
+        } else if m.contains("nonce too low") || m.contains("nonce is too low") || m.contains("invalid nonce") {
+            Self::NonceTooLow
+        } else if m.contains("insufficient funds") {
+            Self::InsufficientFunds
+        } else if m.contains("underpriced") || m.contains("fee too low") || m.contains("gas price too low") {
+            Self::Underpriced
         } else {
-            SwapExactTokensForTokensCall { 
-                amount_in: amount_wei, 
-                amount_out_min: min_out, 
-                path: vec![t_in, t_out], 
-                to: wallet_addr, 
-                deadline 
-            }.encode()
-        };
+            Self::Other
+        }
+    }
+}
 
-        let tx = TransactionRequest::new()
-            .to(router)
-            .value(0)
-            .nonce(nonce)
-            .data(calldata)
-            .gas(500000)
-            .gas_price(gas_gwei_to_wei(gas));
-            
-        let typed_tx: TypedTransaction = tx.into();
-        
-        if let Ok(sig) = wallet.sign_transaction_sync(&typed_tx) {
-            let raw_tx = typed_tx.rlp_signed(&sig);
-            let t_broadcast = std::time::Instant::now();
-            let hash = parallel_broadcast(raw_tx.clone()).await;
-            emit_log("DEBUG", format!("[TRADE] BROADCAST | {}ms | hash={}", t_broadcast.elapsed().as_millis(), &hash[..16]));
-            
-            let is_success = hash.starts_with("0x");
-            
-            if is_success {
-                let tx_hash_h256: H256 = hash.parse().unwrap_or(H256::zero());
-                CORE_STATE.write().unwrap().pending_txs.insert(tx_hash_h256);
-                
-                emit_event(EngineEvent::TxSent {
-                    tx_hash: hash.clone(),
-                    wallet: format!("{:?}", wallet_addr),
-                    action: action.clone(),
-                    amount,
-                    token: format!("{:?}", token),
-                    timestamp_ms: current_timestamp_ms()
-                });
+/// Отказы всех нод по одной tx: (хост, причина, текст ошибки)
+#[derive(Clone, Debug, Default)]
+pub struct BroadcastError {
+    pub failures: Vec<(String, BroadcastErrorKind, String)>,
+}
+
+impl BroadcastError {
+    /// Самая значимая причина среди отказов
+    pub fn kind(&self) -> BroadcastErrorKind {
+        self.failures.iter().map(|f| f.1).max().unwrap_or(BroadcastErrorKind::Connection)
+    }
+
+    /// Короткий текст для TradeStatus
+    pub fn status_message(&self) -> String {
+        match self.kind() {
+            BroadcastErrorKind::NonceTooLow => "nonce too low — resync triggered".into(),
+            BroadcastErrorKind::InsufficientFunds => "insufficient funds for gas * price + value".into(),
+            BroadcastErrorKind::Underpriced => "transaction underpriced — raise gas price".into(),
+            BroadcastErrorKind::Connection if !self.failures.is_empty() => "all RPCs unreachable".into(),
+            BroadcastErrorKind::Connection => "no RPC nodes to broadcast".into(),
+            _ => self.failures.iter()
+                .find(|f| f.1 == BroadcastErrorKind::Other)
+                .map(|f| f.2.clone())
+                .unwrap_or_else(|| "broadcast failed".into()),
+        }
+    }
+}
+
+impl std::fmt::Display for BroadcastError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.status_message())?;
+        for (host, _, message) in &self.failures {
+            write!(f, " | {}: {}", host, message)?;
+        }
+        Ok(())
+    }
+}
+
+/// eth_sendRawTransaction на все url сразу: хеш первой ноды, принявшей tx.
+/// "already known" - tx уже в mempool этой ноды (её разослала другая), считаем успехом
+async fn send_raw_to(urls: Vec<String>, data: Bytes) -> Result<TxHash, BroadcastError> {
+    let local_hash = H256::from(ethers::utils::keccak256(&data));
+    let tasks: Vec<_> = urls.into_iter().map(|url| {
+        let d = data.clone();
+        crate::state::spawn(async move {
+            let host = metrics::node_label(&url);
+            let u = Url::parse(&url).map_err(|e| (host.clone(), BroadcastErrorKind::Connection, e.to_string()))?;
+            let p = Provider::new(Http::new_with_client(u, GLOBAL_HTTP_CLIENT.clone()));
+            let sent = p.send_raw_transaction(d).await.map(|pending| pending.tx_hash());
+            match sent {
+                Ok(hash) => Ok(hash),
+                Err(e) => {
+                    if crate::state::network::is_rate_limit_error(&e.to_string()) {
+                        RPC_POOL.write().unwrap().mark_rate_limited(&url);
+                    }
+                    // Нет JSON-RPC ответа - нода недоступна
+                    let kind = match RpcError::as_error_response(&e) {
+                        Some(rpc) => BroadcastErrorKind::classify(&rpc.message),
+                        None => BroadcastErrorKind::Connection,
+                    };
+                    if kind == BroadcastErrorKind::Connection { metrics::rpc_fail(&url); }
+                    Err((host, kind, e.to_string()))
+                }
             }
-            
-            let (tok_received, tok_sold) = if action == "buy" {
-                (Some(exp_out.to_string()), None)
-            } else {
-                (None, Some(amount_wei.to_string()))
-            };
+        })
+    }).collect();
 
-            events.push(EngineEvent::TradeStatus { 
-                wallet: format!("{:?}", wallet_addr), 
-                action: action.clone(), 
-                status: if is_success { "Sent".into() } else { "Error".into() }, 
-                message: hash.clone(), 
-                tx_hash: if is_success { Some(hash.clone()) } else { None },
-                token_address: format!("{:?}", token),
-                amount,
-                tokens_received: tok_received,
-                tokens_sold: tok_sold,
-                token_decimals: dec
-            });
+    let mut error = BroadcastError::default();
+    let mut accepted = None;
+    for res in join_all(tasks).await {
+        match res {
+            Ok(Ok(hash)) => { accepted.get_or_insert(hash); }
+            Ok(Err((_, BroadcastErrorKind::AlreadyKnown, _))) => { accepted.get_or_insert(local_hash); }
+            Ok(Err(failure)) => error.failures.push(failure),
+            Err(e) => error.failures.push(("task".into(), BroadcastErrorKind::Other, e.to_string())),
         }
     }
-    emit_log("DEBUG", format!("[TRADE] TOTAL | {}ms | events={}", start_time.elapsed().as_millis(), events.len()));
-    events
+    accepted.ok_or(error)
+}
+
+/// Перечитывает pending nonce кошелька после отказа "nonce too low"
+pub async fn resync_nonce(wallet: Address) {
+    let Some(url) = ({ RPC_POOL.read().unwrap().get_fastest_node() }) else { return; };
+    let Ok(u) = Url::parse(&url) else { return; };
+    let p = Provider::new(Http::new_with_client(u, GLOBAL_HTTP_CLIENT.clone()));
+    if let Ok(nonce) = p.get_transaction_count(wallet, Some(BlockNumber::Pending.into())).await {
+        sync_nonce(wallet, nonce.as_u64());
+        emit_log("WARNING", format!("🔢 Nonce {:?} пересинхронизирован: {}", wallet, nonce));
+    }
+}
+
+/// Pending nonce из сети: nonce_map = max(локальный, сетевой, последний nonce живых бандлов + 1).
+/// Зарезервированные, но ещё не разосланные nonce сеть не видит - опускать счётчик нельзя
+pub fn sync_nonce(wallet: Address, chain_nonce: u64) {
+    let reserved = crate::bundles::next_free_nonce(wallet).unwrap_or(0);
+    {
+        let mut s = CORE_STATE.write().unwrap();
+        let next = s.nonce_map.entry(wallet).or_insert(0);
+        *next = (*next).max(chain_nonce).max(reserved);
+    }
+    crate::bundles::on_nonce_synced(wallet, chain_nonce);
 }
 
-/// Параллельная отправка транзакции на несколько RPC
-async fn parallel_broadcast(data: Bytes) -> String {
+/// Резервирует следующие nonce кошельков под одним локом: параллельные сделки не получат одинаковый.
+/// Резерв держится и после тика воркера - sync_nonce счётчик не опускает
+pub fn reserve_nonces(wallets: &[Address]) -> Vec<u64> {
+    let mut s = CORE_STATE.write().unwrap();
+    wallets.iter().map(|w| {
+        let next = s.nonce_map.entry(*w).or_insert(0);
+        let nonce = *next;
+        *next += 1;
+        nonce
+    }).collect()
+}
+
+/// Возвращает неиспользованный nonce, если после него ничего не резервировали (иначе дыру закроет resync)
+pub fn release_nonce(wallet: Address, nonce: u64) {
+    let mut s = CORE_STATE.write().unwrap();
+    if s.nonce_map.get(&wallet) == Some(&(nonce + 1)) {
+        s.nonce_map.insert(wallet, nonce);
+    }
+}
+
+/// Рассылка подписанной tx по режиму broadcast_mode. Ok - (хеш, маршрут).
+/// При "nonce too low" nonce отправителя пересинхронизируется до возврата
+async fn parallel_broadcast(from: Address, data: Bytes) -> Result<(TxHash, &'static str), BroadcastError> {
     let t_start = std::time::Instant::now();
+    let (mode, relays) = {
+        let s = CORE_STATE.read().unwrap();
+        (s.broadcast_mode, s.private_relay_urls.clone())
+    };
+    // Dry-run: хеш подписанной tx, в сеть ничего не уходит
+    if dry_run() {
+        let hash = TxHash::from(ethers::utils::keccak256(&data));
+        let block = { CORE_STATE.read().unwrap().head_block };
+        DRY_RUN_TXS.lock().unwrap().insert(hash, (from, block));
+        emit_log("INFO", format!("🧪 Dry-run: tx {:?} подписана, но не отправлена", hash));
+        return Ok((hash, "dryrun"));
+    }
     let urls = { RPC_POOL.read().unwrap().get_fastest_pool(3) };
-    emit_log("DEBUG", format!("[BROADCAST] START | {} nodes", urls.len()));
+    emit_log("DEBUG", format!("[BROADCAST] START | mode={:?} | {} nodes | {} relays", mode, urls.len(), relays.len()));
 
-    let mut tasks = Vec::new();
+    let result = match mode {
+        BroadcastMode::Public => send_raw_to(urls, data).await.map(|h| (h, "public")),
+        BroadcastMode::Private => send_raw_to(relays, data).await.map(|h| (h, "private")),
+        BroadcastMode::Both => {
+            let relay_task = crate::state::spawn(send_raw_to(relays, data.clone()));
+            tokio::time::sleep(RELAY_HEAD_START).await;
+            let public = send_raw_to(urls, data).await;
+            let private = relay_task.await.unwrap_or_else(|e| Err(BroadcastError {
+                failures: vec![("relays".into(), BroadcastErrorKind::Other, e.to_string())],
+            }));
+            match (private, public) {
+                (Ok(h), Ok(_)) => Ok((h, "both")),
+                (Ok(h), Err(_)) => Ok((h, "private")),
+                (Err(_), Ok(h)) => Ok((h, "public")),
+                (Err(mut a), Err(b)) => {
+                    a.failures.extend(b.failures);
+                    Err(a)
+                }
+            }
+        }
+    };
+
+    match &result {
+        Ok((_, route)) => {
+            metrics::METRICS.broadcast_latency.observe(t_start.elapsed());
+            emit_log("DEBUG", format!("[BROADCAST] SUCCESS | {}ms | route={}", t_start.elapsed().as_millis(), route));
+        }
+        Err(e) => {
+            emit_log("DEBUG", format!("[BROADCAST] FAILED | {}ms | {}", t_start.elapsed().as_millis(), e));
+            if e.kind() == BroadcastErrorKind::NonceTooLow {
+                resync_nonce(from).await;
+            }
+        }
+    }
+    result
+}
+
+/// AutoFuelError с причиной (и в лог - счётчик попыток монитора уже увеличен)
+pub fn auto_fuel_error(wallet: Address, reason: String) {
+    emit_log("ERROR", format!("⛽ Auto-Fuel {:?}: {}", wallet, reason));
+    emit_event(EngineEvent::AutoFuelError {
+        wallet: format!("{:?}", wallet),
+        reason,
+    });
+}
+
+/// withdraw(amount) у wrapped native
+pub fn withdraw_tx(w_n: Address, amount: U256, nonce: u64, gas_price: U256) -> TypedTransaction {
+    use ethers::abi::{Token, encode};
     
-    for url in urls {
-        let d = data.clone();
-        tasks.push(tokio::spawn(async move {
-            let p = Provider::new(Http::new_with_client(
-                Url::parse(&url).unwrap(), 
-                GLOBAL_HTTP_CLIENT.clone()
-            ));
-            p.send_raw_transaction(d)
-                .await
-                .map(|r| format!("{:?}", r.tx_hash()))
-                .map_err(|e| e.to_string())
-        }));
+    let withdraw_sig = ethers::utils::keccak256("withdraw(uint256)".as_bytes());
+    let mut calldata: Vec<u8> = withdraw_sig[..4].to_vec();
+    calldata.extend_from_slice(&encode(&[Token::Uint(amount)]));
+    
+    TransactionRequest::new()
+        .to(w_n)
+        .nonce(nonce)
+        .data(calldata)
+        .gas(100000)
+        .gas_price(gas_price)
+        .into()
+}
+
+/// withdraw wrapped native -> нативная монета. true - tx отправлена
+async fn fuel_withdraw(wallet_signer: &LocalWallet, wallet: Address, w_n: Address, amount: U256, gas_p: U256, fuel_position: Option<(Address, Address)>) -> bool {
+    emit_log("INFO", format!("⛽ Auto-Fuel: withdraw {} WBNB → BNB", amount));
+    
+    let nonce = { 
+        let s = CORE_STATE.read().unwrap(); 
+        *s.nonce_map.get(&wallet).unwrap_or(&0) 
+    };
+    
+    let typed_tx = network_tx(withdraw_tx(w_n, amount, nonce, gas_p));
+    
+    let Ok(sig) = wallet_signer.sign_transaction_sync(&typed_tx) else { return false; };
+    let raw_tx = typed_tx.rlp_signed(&sig);
+    match parallel_broadcast(wallet, raw_tx).await {
+        Ok((tx_hash, _)) => {
+            emit_log("INFO", format!("⛽ Auto-Fuel withdraw tx: {}", tx_hash_label(&tx_hash)));
+            CORE_STATE.write().unwrap().pending_txs.insert(tx_hash, PendingTx { request_id: current_request_id(), position: fuel_position, wallet, gas_price: Some(gas_p), ..Default::default() });
+            CORE_STATE.write().unwrap().nonce_map.insert(wallet, nonce + 1);
+            true
+        }
+        Err(e) => {
+            auto_fuel_error(wallet, format!("Withdraw failed: {}", e.status_message()));
+            false
+        }
+    }
+}
+
+/// Auto-fuel из казны: перевод amount нативной монеты с кошелька-казны. Свой nonce казны через общий менеджер,
+/// pending tx на казну. После перевода и газа в казне должно остаться не меньше reserve - иначе отказ
+pub async fn run_treasury_fuel(treasury_key: WalletKey, wallet: Address, amount: U256, reserve: U256, chain_id: u64) -> bool {
+    let native = Address::repeat_byte(0xee);
+    if amount.is_zero() { return false; }
+    if trading_paused() {
+        emit_log("WARNING", format!("⏸️ Auto-Fuel {:?} пропущен: торговля на паузе", wallet));
+        emit_event(paused_status(format!("{:?}", wallet), "auto_fuel", native, u256_to_f64_safe(amount, 18)));
+        return false;
+    }
+    
+    let treasury = signer(&treasury_key, chain_id);
+    let treasury_addr = treasury.address();
+    let Some(gas_p) = resolve_gas_price().await else {
+        auto_fuel_error(wallet, "Gas price unknown: no block yet, manual gas price is 0 and eth_gasPrice failed".into());
+        return false;
+    };
+    // Перевод native на EOA стоит ровно 21000
+    let plan = GasPlan { limit: 21_000, estimated: None, affordable: true, reverted: false, reserve_short: false };
+    let need = amount + U256::from(plan.limit) * gas_p + reserve;
+    let balance = get_native_balance(treasury_addr).await;
+    if balance < need {
+        auto_fuel_error(wallet, format!(
+            "Treasury {:?} below reserve floor: balance {:.6}, top-up needs {:.6} (reserve {:.6})",
+            treasury_addr, u256_to_f64_safe(balance, 18), u256_to_f64_safe(need, 18), u256_to_f64_safe(reserve, 18)
+        ));
+        return false;
     }
     
-    for res in join_all(tasks).await { 
-        if let Ok(Ok(h)) = res {
-            emit_log("DEBUG", format!("[BROADCAST] SUCCESS | {}ms", t_start.elapsed().as_millis()));
-            return h;
+    let nonce = reserve_nonces(&[treasury_addr])[0];
+    let tx: TypedTransaction = TransactionRequest::new().to(wallet).value(amount).nonce(nonce).gas_price(gas_p).into();
+    match broadcast_tracked(&treasury, tx, &plan, None).await {
+        Ok((hash, route)) => {
+            let amount_f = u256_to_f64_safe(amount, 18);
+            emit_log("SUCCESS", format!("⛽ Auto-Fuel из казны {:?} → {:?}: {:.6}, tx {}", treasury_addr, wallet, amount_f, hash));
+            emit_event(EngineEvent::TxSent {
+                tx_hash: hash,
+                wallet: format!("{:?}", treasury_addr),
+                action: "auto_fuel".into(),
+                amount: amount_f,
+                token: format!("{:?}", native),
+                timestamp_ms: current_timestamp_ms(),
+                route: route.into()
+            });
+            true
+        }
+        Err(e) => {
+            release_nonce(treasury_addr, nonce);
+            auto_fuel_error(wallet, format!("Treasury transfer failed: {}", e));
+            false
         }
     }
-    emit_log("DEBUG", format!("[BROADCAST] FAILED | {}ms", t_start.elapsed().as_millis()));
-    "Error: all RPCs failed".into()
 }
 
-/// Auto-fuel: свапает токен на нативную валюту когда баланс ниже порога
+/// Auto-fuel: свапает токен на нативную валюту когда баланс ниже порога.
+/// Пул quote -> wrapped native выбирается как для сделок: V2 - swapExactTokensForETH, V3 - swapV3Single
+/// в wrapped native и withdraw полученного после подтверждения. min_out - котировка минус комиссия роутера и slippage
 pub async fn run_auto_fuel(
-    pk: String, 
+    key: WalletKey, 
     wallet: Address, 
     router: Address, 
     quote: Address, 
     amount: U256, 
     chain_id: u64
 ) -> bool {
-    use ethers::abi::{Token, encode};
-    
     if amount.is_zero() { return false; }
+    if trading_paused() {
+        emit_log("WARNING", format!("⏸️ Auto-Fuel {:?} пропущен: торговля на паузе", wallet));
+        emit_event(paused_status(format!("{:?}", wallet), "auto_fuel", quote, u256_to_f64_safe(amount, 18)));
+        return false;
+    }
     
-    let wallet_signer: LocalWallet = pk.parse::<LocalWallet>().unwrap().with_chain_id(chain_id);
-    let (w_n, gas_p) = { 
+    let wallet_signer = signer(&key, chain_id);
+    let (w_n, fuel_position, router_tax_bps) = { 
         let s = CORE_STATE.read().unwrap(); 
-        (s.wrapped_native_address, s.gas_price)
+        // Газ auto-fuel - на позицию последнего свапа кошелька, если включено
+        let fuel_position = s.last_traded_token.get(&wallet).filter(|_| s.attribute_fuel_gas).map(|t| (wallet, *t));
+        (s.wrapped_native_address, fuel_position, s.router_tax_bps)
+    };
+    let Some(gas_p) = resolve_gas_price().await else {
+        auto_fuel_error(wallet, "Gas price unknown: no block yet, manual gas price is 0 and eth_gasPrice failed".into());
+        return false;
     };
     
     // === WBNB → прямой withdraw ===
     if quote == w_n {
-        emit_log("INFO", format!("⛽ Auto-Fuel: withdraw {} WBNB → BNB", amount));
-        
-        let withdraw_sig = ethers::utils::keccak256("withdraw(uint256)".as_bytes());
-        let mut calldata: Vec<u8> = withdraw_sig[..4].to_vec();
-        calldata.extend_from_slice(&encode(&[Token::Uint(amount)]));
-        
-        let nonce = { 
-            let s = CORE_STATE.read().unwrap(); 
-            *s.nonce_map.get(&wallet).unwrap_or(&0) 
-        };
-        
-        let tx = TransactionRequest::new()
-            .to(w_n)
-            .nonce(nonce)
-            .data(calldata)
-            .gas(100000)
-            .gas_price(gas_p);
-            
-        let typed_tx: TypedTransaction = tx.into();
-        
-        if let Ok(sig) = wallet_signer.sign_transaction_sync(&typed_tx) { 
-            let raw_tx = typed_tx.rlp_signed(&sig);
-            let hash = parallel_broadcast(raw_tx).await;
-            emit_log("INFO", format!("⛽ Auto-Fuel withdraw tx: {}", hash));
-            
-            if hash.starts_with("0x") {
-                let tx_hash: H256 = hash.parse().unwrap_or(H256::zero());
-                CORE_STATE.write().unwrap().pending_txs.insert(tx_hash);
-                CORE_STATE.write().unwrap().nonce_map.insert(wallet, nonce + 1);
-                return true;
-            }
-        }
-        return false;
+        return fuel_withdraw(&wallet_signer, wallet, w_n, amount, gas_p, fuel_position).await;
     }
     
     // === Swap через TaxRouter ===
-    emit_log("INFO", format!("⛽ Auto-Fuel: swap {:?} → BNB via TaxRouter", quote));
+    let route = match crate::monitor::fuel_route(quote, w_n, amount).await {
+        Ok(route) => route,
+        Err(reason) => {
+            auto_fuel_error(wallet, reason);
+            return false;
+        }
+    };
+    // Комиссия роутера: V2 swapExactTokensForETH берёт её с выхода, swapV3Single - со входа (quote) и с выхода (WETH)
+    let after_tax = match route.v3_fee {
+        Some(_) => math::net_of_fee(math::net_of_fee(route.expected_out, router_tax_bps), router_tax_bps),
+        None => math::net_of_fee(route.expected_out, router_tax_bps),
+    };
+    let min_out = apply_slippage(after_tax, effective_slippage(0.0));
+    if min_out.is_zero() {
+        auto_fuel_error(wallet, format!("Quote too small: expected {} wei native for {} wei", route.expected_out, amount));
+        return false;
+    }
+    let pool_label = route.v3_fee.map(|f| format!("V3 fee {}", f)).unwrap_or_else(|| "V2".into());
+    emit_log("INFO", format!("⛽ Auto-Fuel: swap {:?} → BNB via TaxRouter ({}), min out {:.6}", quote, pool_label, u256_to_f64_safe(min_out, 18)));
     
     let url_opt = { RPC_POOL.read().unwrap().get_fastest_node() };
     if let Some(url) = url_opt {
@@ -616,13 +2311,8 @@ pub async fn run_auto_fuel(
             // Проверяем баланс токена
             if let Ok(balance) = erc20.balance_of(wallet).call().await {
                 if balance < amount {
-                    let reason = format!("Недостаточно токена: есть {:.6}, нужно {:.6}", 
-                        u256_to_f64_safe(balance, 18), u256_to_f64_safe(amount, 18));
-                    emit_log("ERROR", format!("⛽ Auto-Fuel: {}", reason));
-                    emit_event(EngineEvent::AutoFuelError {
-                        wallet: format!("{:?}", wallet),
-                        reason,
-                    });
+                    auto_fuel_error(wallet, format!("Недостаточно токена: есть {:.6}, нужно {:.6}", 
+                        u256_to_f64_safe(balance, 18), u256_to_f64_safe(amount, 18)));
                     return false;
                 }
             }
@@ -637,61 +2327,64 @@ pub async fn run_auto_fuel(
                         *s.nonce_map.get(&wallet).unwrap_or(&0) 
                     };
                     
-                    let approve_data = erc20.approve(router, U256::max_value()).tx.data().cloned().unwrap();
-                    
-                    let tx = TransactionRequest::new()
-                        .to(quote)
-                        .nonce(nonce)
-                        .data(approve_data)
-                        .gas(60000)
-                        .gas_price(gas_p);
-                    
-                    let typed_tx: TypedTransaction = tx.into();
-                    
-                    if let Ok(sig) = wallet_signer.sign_transaction_sync(&typed_tx) {
-                        let raw_tx = typed_tx.rlp_signed(&sig);
-                        let hash = parallel_broadcast(raw_tx).await;
-                        emit_log("INFO", format!("⛽ Auto-Fuel approve tx: {}", hash));
-                        
-                        if hash.starts_with("0x") {
-                            CORE_STATE.write().unwrap().nonce_map.insert(wallet, nonce + 1);
+                    let order = ApproveOrder {
+                        token: quote, spender: router, value: approve_value(amount), current_allowance: allowance,
+                        nonce, gas_price_wei: gas_p, position: fuel_position.map(|(_, t)| t)
+                    };
+                    match send_approve(&wallet_signer, order).await {
+                        Ok(sent) => {
+                            emit_log("INFO", format!("⛽ Auto-Fuel approve tx: {}", sent.tx_hash));
                             tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-                        } else {
-                            let reason = "Approve failed: все RPC недоступны".to_string();
-                            emit_event(EngineEvent::AutoFuelError {
-                                wallet: format!("{:?}", wallet),
-                                reason: reason.clone(),
-                            });
+                        }
+                        Err(failure) => {
+                            let reason = match failure {
+                                ApproveFailure::InsufficientGas(_) => "Approve failed: недостаточно нативного баланса на газ".to_string(),
+                                ApproveFailure::Broadcast(_) => "Approve failed: все RPC недоступны".to_string(),
+                            };
+                            auto_fuel_error(wallet, reason);
                             return false;
                         }
                     }
                 }
             }
             
+            // Пока ждали approve, могли включить паузу
+            if trading_paused() {
+                emit_event(paused_status(format!("{:?}", wallet), "auto_fuel", quote, u256_to_f64_safe(amount, 18)));
+                return false;
+            }
+            
             // Делаем swap
             let nonce = { 
                 let s = CORE_STATE.read().unwrap(); 
                 *s.nonce_map.get(&wallet).unwrap_or(&0) 
             };
             
-            let deadline = U256::from(
-                std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs() + 300
-            );
+            let deadline = swap_deadline(CORE_STATE.read().unwrap().swap_deadline_secs);
             
-            let func_sig = ethers::utils::keccak256("swapExactTokensForETH(uint256,uint256,address[],address,uint256)".as_bytes());
-            let mut calldata: Vec<u8> = func_sig[..4].to_vec();
-            
-            let tokens = vec![
-                Token::Uint(amount),
-                Token::Uint(U256::zero()),
-                Token::Array(vec![Token::Address(quote), Token::Address(w_n)]),
-                Token::Address(wallet),
-                Token::Uint(deadline),
-            ];
-            calldata.extend_from_slice(&encode(&tokens));
+            let calldata: Bytes = match route.v3_fee {
+                Some(pool_fee) => SwapV3SingleCall {
+                    token_in: quote,
+                    token_out: w_n,
+                    pool_fee,
+                    amount_in: amount,
+                    amount_out_minimum: min_out,
+                    recipient: wallet,
+                    deadline
+                }.encode().into(),
+                None => SwapExactTokensForETHCall {
+                    amount_in: amount,
+                    amount_out_min: min_out,
+                    path: vec![quote, w_n],
+                    to: wallet,
+                    deadline
+                }.encode().into(),
+            };
+            // V3 отдаёт wrapped native: withdraw - на разницу баланса после подтверждения
+            let w_n_before = match route.v3_fee {
+                Some(_) => get_token_balance(w_n, wallet).await,
+                None => U256::zero(),
+            };
             
             let tx = TransactionRequest::new()
                 .to(router)
@@ -700,35 +2393,44 @@ pub async fn run_auto_fuel(
                 .gas(300000)
                 .gas_price(gas_p);
                 
-            let typed_tx: TypedTransaction = tx.into();
+            let typed_tx = network_tx(tx.into());
             
             if let Ok(sig) = wallet_signer.sign_transaction_sync(&typed_tx) { 
                 let raw_tx = typed_tx.rlp_signed(&sig);
-                let hash = parallel_broadcast(raw_tx).await;
-                
-                if hash.starts_with("0x") {
-                    emit_log("SUCCESS", format!("⛽ Auto-Fuel swap tx: {}", hash));
+                match parallel_broadcast(wallet, raw_tx).await {
+                    Ok((tx_hash, route_label)) => {
+                        let hash = tx_hash_label(&tx_hash);
+                        emit_log("SUCCESS", format!("⛽ Auto-Fuel swap tx: {}", hash));
                     
-                    let tx_hash: H256 = hash.parse().unwrap_or(H256::zero());
-                    CORE_STATE.write().unwrap().pending_txs.insert(tx_hash);
-                    CORE_STATE.write().unwrap().nonce_map.insert(wallet, nonce + 1);
+                        CORE_STATE.write().unwrap().pending_txs.insert(tx_hash, PendingTx { request_id: current_request_id(), position: fuel_position, wallet, gas_price: Some(gas_p), ..Default::default() });
+                        CORE_STATE.write().unwrap().nonce_map.insert(wallet, nonce + 1);
                     
-                    emit_event(EngineEvent::TxSent {
-                        tx_hash: hash,
-                        wallet: format!("{:?}", wallet),
-                        action: "auto_fuel".into(),
-                        amount: u256_to_f64_safe(amount, 18),
-                        token: format!("{:?}", quote),
-                        timestamp_ms: current_timestamp_ms()
-                    });
+                        emit_event(EngineEvent::TxSent {
+                            tx_hash: hash,
+                            wallet: format!("{:?}", wallet),
+                            action: "auto_fuel".into(),
+                            amount: u256_to_f64_safe(amount, 18),
+                            token: format!("{:?}", quote),
+                            timestamp_ms: current_timestamp_ms(),
+                            route: route_label.into()
+                        });
+                        
+                        if route.v3_fee.is_some() && !dry_run() {
+                            // Блок монитора не ждёт подтверждения - unwrap отдельной задачей
+                            crate::state::spawn(async move {
+                                wait_for_confirmations(&[tx_hash], std::time::Duration::from_secs(120)).await;
+                                let received = get_token_balance(w_n, wallet).await.saturating_sub(w_n_before);
+                                if !received.is_zero() {
+                                    fuel_withdraw(&wallet_signer, wallet, w_n, received, gas_p, fuel_position).await;
+                                }
+                            });
+                        }
                     
-                    return true;
-                } else {
-                    let reason = "Swap failed: все RPC недоступны".to_string();
-                    emit_event(EngineEvent::AutoFuelError {
-                        wallet: format!("{:?}", wallet),
-                        reason,
-                    });
+                        return true;
+                    }
+                    Err(e) => {
+                        auto_fuel_error(wallet, format!("Swap failed: {}", e.status_message()));
+                    }
                 }
             }
         }
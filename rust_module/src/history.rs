@@ -0,0 +1,285 @@
+// Позиция из истории: Transfer логи токена по кошелькам и свапы из журнала сделок (ImportHistory).
+// Переводы между своими кошельками переносят стоимость, а не дают бесплатные токены
+use ethers::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::bridge::{emit_event, emit_log, EngineEvent};
+use crate::rpc_batch::{self, BatchCall};
+use crate::monitor::{self, TransferFilter};
+use crate::state::{fastest_http_provider, EngineLocal, Position, CORE_STATE, RPC_POOL, TRACKED_WALLETS};
+use crate::{execution, journal, math, positions};
+
+// Предел скана по умолчанию: история старше - только с явным from_block в пределах max_blocks
+pub const DEFAULT_MAX_BLOCKS: u64 = 200_000;
+const INITIAL_CHUNK: u64 = 5_000;
+const MIN_CHUNK: u64 = 50;
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
+// receipt и tx запрашиваются батчами по столько хэшей
+const RECEIPT_BATCH: usize = 50;
+
+// WETH.withdraw роутера: продажа в нативную монету приходит без Transfer quote на кошелёк
+#[derive(Clone, Debug, EthEvent)]
+#[ethevent(name = "Withdrawal", abi = "Withdrawal(address,uint256)")]
+struct WithdrawalFilter {
+    #[ethevent(indexed)]
+    src: Address,
+    wad: U256,
+}
+
+static IN_PROGRESS: EngineLocal<Mutex<HashSet<(Address, Address)>>> = EngineLocal::new(|| Mutex::new(HashSet::new()));
+
+/// Перевод токена с участием своего кошелька
+struct Move {
+    block: u64,
+    log_index: u64,
+    tx: H256,
+    from: Address,
+    to: Address,
+    value: U256,
+}
+
+fn progress(wallet: Address, token: Address, scanned: u64, total: u64, transfers: usize) {
+    emit_event(EngineEvent::HistoryImportProgress {
+        wallet: format!("{:?}", wallet),
+        token: format!("{:?}", token),
+        scanned_blocks: scanned,
+        total_blocks: total,
+        transfers
+    });
+}
+
+/// Transfer логи токена, где отправитель или получатель - один из wallets, в порядке блоков.
+/// Кусок, который нода отвергла, делится пополам (как скан держателей)
+async fn scan(wallet: Address, token: Address, wallets: &[Address], from: u64, head: u64) -> Result<Vec<Move>, String> {
+    let p = fastest_http_provider().ok_or("нет RPC нод")?;
+    let topics: Vec<H256> = wallets.iter().map(|w| H256::from(*w)).collect();
+    let total = head.saturating_sub(from) + 1;
+    let mut moves: BTreeMap<(u64, u64), Move> = BTreeMap::new();
+    let mut chunk = INITIAL_CHUNK;
+    let mut block = from;
+    let mut last_progress = Instant::now();
+
+    while block <= head {
+        let to = (block + chunk - 1).min(head);
+        let base = Filter::new()
+            .address(token)
+            .event("Transfer(address,address,uint256)")
+            .from_block(block)
+            .to_block(to);
+        let (sent_filter, received_filter) = (base.clone().topic1(topics.clone()), base.topic2(topics.clone()));
+        let (sent, received) = tokio::join!(p.get_logs(&sent_filter), p.get_logs(&received_filter));
+        let logs = match (sent, received) {
+            (Ok(mut sent), Ok(received)) => { sent.extend(received); sent }
+            (Err(e), _) | (_, Err(e)) if chunk > MIN_CHUNK => {
+                chunk /= 2;
+                emit_log("DEBUG", format!("📜 getLogs {}..{} отклонён ({}), кусок {}", block, to, e, chunk));
+                continue;
+            }
+            (Err(e), _) | (_, Err(e)) => return Err(format!("getLogs {}..{}: {}", block, to, e)),
+        };
+        let found = logs.len();
+        for log in logs {
+            let (Some(n), Some(i), Some(tx)) = (log.block_number, log.log_index, log.transaction_hash) else { continue; };
+            let Ok(t) = <TransferFilter as EthEvent>::decode_log(&log.into()) else { continue; };
+            // Перевод между своими кошельками приходит в обоих запросах - ключ его схлопывает
+            moves.insert((n.as_u64(), i.as_u64()), Move { block: n.as_u64(), log_index: i.as_u64(), tx, from: t.from, to: t.to, value: t.value });
+        }
+        block = to + 1;
+        if found < 1_000 && chunk < INITIAL_CHUNK { chunk *= 2; }
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            progress(wallet, token, block - from, total, moves.len());
+            last_progress = Instant::now();
+        }
+    }
+    progress(wallet, token, total, total, moves.len());
+    Ok(moves.into_values().collect())
+}
+
+/// Движение quote кошелька в tx (+ получено, - потрачено): Transfer quote, нативная оплата покупки
+/// и WETH.withdraw при продаже в нативную монету
+fn quote_delta(wallet: Address, quote: Address, wrapped_native: Address, receipt: &TransactionReceipt, tx: Option<&Transaction>, selling: bool) -> I256 {
+    let signed = |v: U256| I256::try_from(v).unwrap_or(I256::MAX);
+    let mut delta = I256::zero();
+    let mut received = false;
+    for log in receipt.logs.iter().filter(|l| l.address == quote) {
+        let Ok(t) = <TransferFilter as EthEvent>::decode_log(&log.clone().into()) else { continue; };
+        if t.to == wallet { delta = delta.saturating_add(signed(t.value)); received = true; }
+        if t.from == wallet { delta = delta.saturating_sub(signed(t.value)); }
+    }
+    if quote != wrapped_native || receipt.from != wallet { return delta; }
+    if let Some(tx) = tx {
+        delta = delta.saturating_sub(signed(tx.value));
+    }
+    if selling && !received {
+        for log in receipt.logs.iter().filter(|l| l.address == wrapped_native) {
+            if let Ok(w) = <WithdrawalFilter as EthEvent>::decode_log(&log.clone().into()) {
+                delta = delta.saturating_add(signed(w.wad));
+            }
+        }
+    }
+    delta
+}
+
+/// Доля стоимости позиции, уходящая с amount токенов (средняя цена)
+fn released_cost(p: &Position, amount: U256) -> U256 {
+    if p.quantity.is_zero() { return U256::zero(); }
+    U256::try_from(p.cost.full_mul(amount.min(p.quantity)) / U512::from(p.quantity)).unwrap_or(p.cost)
+}
+
+fn take(p: &mut Position, amount: U256) -> U256 {
+    let released = released_cost(p, amount);
+    p.quantity = p.quantity.saturating_sub(amount);
+    p.cost = p.cost.saturating_sub(released);
+    released
+}
+
+/// receipt и tx по хэшам батчами. Нет receipt хоть одной tx - ошибка: позиция без неё была бы неверной
+async fn fetch_receipts(hashes: &[H256]) -> Result<HashMap<H256, (TransactionReceipt, Option<Transaction>)>, String> {
+    let url = RPC_POOL.read().unwrap().get_fastest_node().ok_or("нет RPC нод")?;
+    let mut out = HashMap::new();
+    for part in hashes.chunks(RECEIPT_BATCH) {
+        let mut calls: Vec<BatchCall> = part.iter().map(|h| BatchCall::Receipt(*h)).collect();
+        calls.extend(part.iter().map(|h| BatchCall::Transaction(*h)));
+        let results = rpc_batch::batch(&url, &calls).await;
+        for (i, hash) in part.iter().enumerate() {
+            let Some(Ok(Some(receipt))) = results.get(i).map(rpc_batch::as_object::<TransactionReceipt>) else {
+                return Err(format!("нет receipt {:?}", hash));
+            };
+            let tx = results.get(part.len() + i).and_then(|r| rpc_batch::as_object::<Transaction>(r).ok().flatten());
+            out.insert(*hash, (receipt, tx));
+        }
+    }
+    Ok(out)
+}
+
+/// Восстанавливает позицию wallet в token с from_block (не глубже max_blocks от головы) и заменяет ею позицию ядра.
+/// Свои кошельки (TRACKED_WALLETS) ведутся вместе: перевод между ними переносит среднюю стоимость.
+/// Покупка/продажа - tx, где токен и quote кошелька сдвинулись в разные стороны. Покупка ядра,
+/// у которой quote в receipt не виден, берёт сумму из подтверждённого TxSent журнала
+pub async fn import(wallet: Address, token: Address, quote: Option<Address>, from_block: Option<u64>, max_blocks: Option<u64>) {
+    if !IN_PROGRESS.lock().unwrap().insert((wallet, token)) {
+        emit_log("INFO", format!("📜 Импорт истории {:?} / {:?} уже идёт", wallet, token));
+        return;
+    }
+    let result = rebuild(wallet, token, quote, from_block, max_blocks).await;
+    IN_PROGRESS.lock().unwrap().remove(&(wallet, token));
+    if let Err(e) = result {
+        emit_event(EngineEvent::rejected("ImportHistory", format!("импорт истории {:?} / {:?}: {}", wallet, token, e)));
+    }
+}
+
+async fn rebuild(wallet: Address, token: Address, quote: Option<Address>, from_block: Option<u64>, max_blocks: Option<u64>) -> Result<(), String> {
+    let (quote, wrapped_native, mut own) = {
+        let s = CORE_STATE.read().unwrap();
+        let quote = quote
+            .or_else(|| s.positions.get(&(wallet, token)).map(|p| p.quote))
+            .or_else(|| s.tokens.get(&token).map(|c| c.quote))
+            .unwrap_or(s.wrapped_native_address);
+        (quote, s.wrapped_native_address, TRACKED_WALLETS.read().unwrap().clone())
+    };
+    if !own.contains(&wallet) { own.push(wallet); }
+
+    let p = fastest_http_provider().ok_or("нет RPC нод")?;
+    let head = p.get_block_number().await.map_err(|e| e.to_string())?.as_u64();
+    let max_blocks = max_blocks.filter(|b| *b > 0).unwrap_or(DEFAULT_MAX_BLOCKS);
+    let limit_from = head.saturating_sub(max_blocks);
+    let from = from_block.unwrap_or(limit_from).max(limit_from);
+    if from_block.is_some_and(|b| b < limit_from) {
+        emit_log("WARNING", format!("📜 Скан ограничен {} блоками: с блока {} вместо {}", max_blocks, from, from_block.unwrap_or_default()));
+    }
+    emit_log("INFO", format!("📜 Импорт истории {:?} / {:?}: блоки {}..{}", wallet, token, from, head));
+
+    let moves = scan(wallet, token, &own, from, head).await?;
+    let own_set: HashSet<Address> = own.iter().copied().collect();
+    let journal_swaps = journal::confirmed_swaps(&own, token);
+
+    // Чистое изменение токена у кошелька в tx без учёта переводов между своими
+    let mut external: HashMap<(H256, Address), (I256, u64, u64)> = HashMap::new();
+    for m in &moves {
+        if own_set.contains(&m.from) && own_set.contains(&m.to) { continue; }
+        let signed = I256::try_from(m.value).unwrap_or(I256::MAX);
+        for (w, d) in [(m.from, -signed), (m.to, signed)] {
+            if !own_set.contains(&w) { continue; }
+            let e = external.entry((m.tx, w)).or_insert((I256::zero(), m.block, m.log_index));
+            e.0 = e.0.saturating_add(d);
+        }
+    }
+    let mut hashes: Vec<H256> = external.keys().map(|(h, _)| *h).collect::<HashSet<_>>().into_iter().collect();
+    hashes.sort_unstable();
+    let receipts = fetch_receipts(&hashes).await?;
+    let quote_decimals = monitor::get_decimals_cached(quote).await as u32;
+
+    // События по порядку: свои переводы - по логам, внешние изменения - на место первого лога tx
+    enum Step { Own(usize), External(H256, Address, I256) }
+    let mut steps: Vec<((u64, u64), Step)> = moves.iter().enumerate()
+        .filter(|(_, m)| own_set.contains(&m.from) && own_set.contains(&m.to))
+        .map(|(i, m)| ((m.block, m.log_index), Step::Own(i)))
+        .collect();
+    steps.extend(external.iter().map(|(&(tx, w), &(d, block, index))| ((block, index), Step::External(tx, w, d))));
+    steps.sort_unstable_by_key(|(k, _)| *k);
+
+    let mut book: HashMap<Address, Position> = HashMap::new();
+    let (mut trades, mut moved, mut free) = (0usize, 0usize, 0usize);
+    for (_, step) in steps {
+        match step {
+            Step::Own(i) => {
+                let m = &moves[i];
+                let released = take(book.entry(m.from).or_default(), m.value);
+                let dst = book.entry(m.to).or_default();
+                dst.quantity = dst.quantity.saturating_add(m.value);
+                dst.cost = dst.cost.saturating_add(released);
+                moved += 1;
+            }
+            Step::External(tx, w, delta) => {
+                if delta.is_zero() { continue; }
+                let selling = delta.is_negative();
+                let amount = delta.unsigned_abs();
+                let mut q = receipts.get(&tx).map_or(I256::zero(), |(r, t)| quote_delta(w, quote, wrapped_native, r, t.as_ref(), selling));
+                let hint = journal_swaps.get(&tx);
+                // Покупка ядра без видимого quote (например, через другой путь) - сумма из TxSent
+                if q.is_zero() && !selling {
+                    if let Some((_, spent)) = hint.filter(|(a, _)| a == "buy") {
+                        q = -I256::try_from(math::f64_to_u256(*spent, quote_decimals)).unwrap_or_default();
+                    }
+                }
+                let position = book.entry(w).or_default();
+                match (selling, q.is_negative(), q.is_positive()) {
+                    (false, true, _) => {
+                        position.quantity = position.quantity.saturating_add(amount);
+                        position.cost = position.cost.saturating_add(q.unsigned_abs());
+                        trades += 1;
+                    }
+                    (true, _, true) => {
+                        let released = take(position, amount);
+                        let released = I256::try_from(released).unwrap_or(I256::MAX);
+                        position.realized_pnl = position.realized_pnl.saturating_add(q.saturating_sub(released));
+                        trades += 1;
+                    }
+                    // Чужой перевод без оплаты: входящий - без стоимости, исходящий уносит свою долю стоимости
+                    (false, _, _) => {
+                        position.quantity = position.quantity.saturating_add(amount);
+                        free += 1;
+                    }
+                    (true, _, _) => { take(position, amount); }
+                }
+            }
+        }
+    }
+
+    let mut rebuilt = book.remove(&wallet).unwrap_or_default();
+    rebuilt.quote = quote;
+    let balance = execution::get_token_balance(token, wallet).await;
+    if balance > rebuilt.quantity {
+        emit_log("WARNING", format!(
+            "📜 {:?}: на балансе больше, чем видно в истории с блока {} - остаток без стоимости (начните скан раньше)",
+            wallet, from
+        ));
+    }
+    emit_log("SUCCESS", format!(
+        "📜 История {:?} / {:?}: {} сделок, {} переводов между своими кошельками, {} входящих без оплаты",
+        wallet, token, trades, moved, free
+    ));
+    positions::replace(wallet, token, rebuilt);
+    Ok(())
+}
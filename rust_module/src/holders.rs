@@ -0,0 +1,228 @@
+use crate::state::EngineLocal;
+use ethers::prelude::*;
+use ethers::abi::{AbiDecode, AbiEncode};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::bridge::{emit_event, emit_log, EngineEvent};
+use crate::execution::{self, TotalSupplyCall, TotalSupplyReturn};
+use crate::monitor::{self, GetPairCall, GetPairReturn, TransferFilter};
+use crate::state::{fastest_http_provider, CORE_STATE};
+
+// Жёсткий предел скана: токен с миллионами переводов не должен занимать RPC часами
+pub const DEFAULT_MAX_BLOCKS: u64 = 500_000;
+const INITIAL_CHUNK: u64 = 5_000;
+const MIN_CHUNK: u64 = 50;
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
+const DEFAULT_TOP_N: usize = 10;
+// Сколько крупнейших держателей храним в кэше (top_n больше - обрезается)
+const MAX_TOP_N: usize = 100;
+
+const DEAD_ADDRESSES: [&str; 2] = [
+    "0x000000000000000000000000000000000000dEaD",
+    "0xdEAD000000000000000042069420694206942069",
+];
+
+/// Результат скана: держатели по убыванию баланса (без пулов и burn адресов), доли в % от supply
+#[derive(Clone)]
+struct HolderSnapshot {
+    holder_count: usize,
+    top_holders: Vec<(String, f64, f64)>,
+    lp_pct: f64,
+    burned_pct: f64,
+    from_block: u64,
+    complete: bool,
+    transfers: usize,
+}
+
+impl HolderSnapshot {
+    fn report(&self, token: Address, top_n: usize) -> EngineEvent {
+        EngineEvent::HolderReport {
+            token: format!("{:?}", token),
+            holder_count: self.holder_count,
+            top_holders: self.top_holders.iter().take(top_n).cloned().collect(),
+            lp_pct: self.lp_pct,
+            burned_pct: self.burned_pct,
+            from_block: self.from_block,
+            complete: self.complete,
+            transfers: self.transfers
+        }
+    }
+}
+
+// Снимки за сессию: повторный AnalyzeHolders отдаёт кэш без скана
+static REPORTS: EngineLocal<Mutex<HashMap<Address, HolderSnapshot>>> = EngineLocal::new(|| Mutex::new(HashMap::new()));
+static IN_PROGRESS: EngineLocal<Mutex<HashSet<Address>>> = EngineLocal::new(|| Mutex::new(HashSet::new()));
+
+pub fn forget_all() {
+    REPORTS.lock().unwrap().clear();
+}
+
+/// Блок деплоя бинарным поиском по eth_getCode. None - нода без архива
+async fn find_deployment_block(p: &Provider<Http>, token: Address, head: u64) -> Option<u64> {
+    let (mut lo, mut hi) = (0u64, head);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let code = p.get_code(token, Some(BlockId::Number(mid.into()))).await.ok()?;
+        if code.is_empty() { lo = mid + 1; } else { hi = mid; }
+    }
+    Some(lo)
+}
+
+/// Пулы токена, чьи балансы считаются ликвидностью: из подписки, иначе V2 getPair по всем quote
+pub(crate) async fn lp_addresses(token: Address) -> HashSet<Address> {
+    let (known, factory, quotes) = {
+        let s = CORE_STATE.read().unwrap();
+        let known: HashSet<Address> = s.tokens.get(&token)
+            .map(|c| c.pool_targets.iter().chain(c.ranked_pools.iter().map(|r| &r.address)).copied().collect())
+            .unwrap_or_default();
+        (known, s.v2_factory_address, s.quote_tokens.values().copied().collect::<Vec<_>>())
+    };
+    if !known.is_empty() || factory.is_zero() { return known; }
+
+    let mut pairs = HashSet::new();
+    for quote in quotes {
+        let pair = execution::read_call(factory, GetPairCall(token, quote).encode().into()).await.ok()
+            .and_then(|out| GetPairReturn::decode(&out).ok())
+            .map(|r| r.0);
+        if let Some(pair) = pair.filter(|p| !p.is_zero()) { pairs.insert(pair); }
+    }
+    pairs
+}
+
+fn progress(token: Address, scanned: u64, total: u64, transfers: usize) {
+    emit_event(EngineEvent::HolderScanProgress {
+        token: format!("{:?}", token),
+        scanned_blocks: scanned,
+        total_blocks: total,
+        transfers
+    });
+}
+
+/// Восстанавливает балансы из Transfer логов кусками eth_getLogs.
+/// Кусок, который нода отвергла (слишком много логов), делится пополам
+async fn scan(token: Address, max_blocks: u64) -> Result<(HashMap<Address, U256>, u64, bool, usize), String> {
+    let p = fastest_http_provider().ok_or("нет RPC нод")?;
+    let head = p.get_block_number().await.map_err(|e| e.to_string())?.as_u64();
+    let limit_from = head.saturating_sub(max_blocks);
+    let deployed = find_deployment_block(&p, token, head).await;
+    // Скан с деплоя - балансы точные; упёрлись в лимит - только изменения за окно
+    let (from, complete) = match deployed {
+        Some(d) if d >= limit_from => (d, true),
+        _ => (limit_from, limit_from == 0),
+    };
+    if !complete {
+        emit_log("WARNING", format!("👥 {:?}: скан ограничен {} блоками - балансы старых держателей неполные", token, max_blocks));
+    }
+
+    let total = head.saturating_sub(from) + 1;
+    let mut balances: HashMap<Address, U256> = HashMap::new();
+    let mut transfers = 0usize;
+    let mut chunk = INITIAL_CHUNK;
+    let mut block = from;
+    let mut last_progress = Instant::now();
+
+    while block <= head {
+        let to = (block + chunk - 1).min(head);
+        let filter = Filter::new()
+            .address(token)
+            .event("Transfer(address,address,uint256)")
+            .from_block(block)
+            .to_block(to);
+        let logs = match p.get_logs(&filter).await {
+            Ok(logs) => logs,
+            Err(e) if chunk > MIN_CHUNK => {
+                chunk /= 2;
+                emit_log("DEBUG", format!("👥 getLogs {}..{} отклонён ({}), кусок {}", block, to, e, chunk));
+                continue;
+            }
+            Err(e) => return Err(format!("getLogs {}..{}: {}", block, to, e)),
+        };
+        for log in &logs {
+            let Ok(t) = <TransferFilter as EthEvent>::decode_log(&log.clone().into()) else { continue; };
+            if !t.from.is_zero() {
+                let b = balances.entry(t.from).or_default();
+                *b = b.saturating_sub(t.value);
+            }
+            let b = balances.entry(t.to).or_default();
+            *b = b.saturating_add(t.value);
+            transfers += 1;
+        }
+        block = to + 1;
+        if logs.len() < 1_000 && chunk < INITIAL_CHUNK { chunk *= 2; }
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            progress(token, block - from, total, transfers);
+            last_progress = Instant::now();
+        }
+    }
+    progress(token, total, total, transfers);
+    Ok((balances, from, complete, transfers))
+}
+
+async fn build_snapshot(token: Address, max_blocks: u64) -> Result<HolderSnapshot, String> {
+    let (balances, from_block, complete, transfers) = scan(token, max_blocks).await?;
+    let (decimals, supply, lps) = tokio::join!(
+        monitor::get_decimals_cached(token),
+        execution::read_call(token, TotalSupplyCall.encode().into()),
+        lp_addresses(token)
+    );
+    let dead: HashSet<Address> = DEAD_ADDRESSES.iter().filter_map(|a| Address::from_str(a).ok())
+        .chain(std::iter::once(Address::zero()))
+        .collect();
+
+    let held: U256 = balances.values().fold(U256::zero(), |acc, b| acc.saturating_add(*b));
+    let supply = supply.ok().and_then(|out| TotalSupplyReturn::decode(&out).ok()).map(|r| r.0)
+        .filter(|s| !s.is_zero())
+        .unwrap_or(held);
+    let to_f = |v: U256| execution::u256_to_f64_safe(v, decimals as u32);
+    let supply_f = to_f(supply);
+    let pct = |v: U256| if supply_f > 0.0 { to_f(v) / supply_f * 100.0 } else { 0.0 };
+
+    let sum_of = |set: &HashSet<Address>| set.iter().filter_map(|a| balances.get(a))
+        .fold(U256::zero(), |acc, b| acc.saturating_add(*b));
+    let lp_pct = pct(sum_of(&lps));
+    let burned_pct = pct(sum_of(&dead));
+
+    let mut holders: Vec<(Address, U256)> = balances.iter()
+        .filter(|(a, b)| !b.is_zero() && !dead.contains(a))
+        .map(|(a, b)| (*a, *b))
+        .collect();
+    let holder_count = holders.len();
+    holders.retain(|(a, _)| !lps.contains(a));
+    holders.sort_unstable_by_key(|h| std::cmp::Reverse(h.1));
+    let top_holders = holders.iter().take(MAX_TOP_N)
+        .map(|(a, b)| (format!("{:?}", a), to_f(*b), pct(*b)))
+        .collect();
+
+    Ok(HolderSnapshot { holder_count, top_holders, lp_pct, burned_pct, from_block, complete, transfers })
+}
+
+/// Отчёт по держателям: из кэша сессии или скан в фоне (повторный запрос во время скана игнорируется)
+pub async fn analyze(token: Address, top_n: Option<usize>, max_blocks: Option<u64>, refresh: bool) {
+    let top_n = top_n.filter(|n| *n > 0).unwrap_or(DEFAULT_TOP_N);
+    if !refresh {
+        let cached = REPORTS.lock().unwrap().get(&token).map(|snap| snap.report(token, top_n));
+        if let Some(report) = cached {
+            emit_event(report);
+            return;
+        }
+    }
+    if !IN_PROGRESS.lock().unwrap().insert(token) {
+        emit_log("INFO", format!("👥 Скан держателей {:?} уже идёт", token));
+        return;
+    }
+
+    emit_log("INFO", format!("👥 Скан держателей {:?}...", token));
+    let max_blocks = max_blocks.filter(|b| *b > 0).unwrap_or(DEFAULT_MAX_BLOCKS);
+    let result = build_snapshot(token, max_blocks).await;
+    IN_PROGRESS.lock().unwrap().remove(&token);
+
+    match result {
+        Ok(snap) => {
+            emit_event(snap.report(token, top_n));
+            REPORTS.lock().unwrap().insert(token, snap);
+        }
+        Err(e) => emit_log("ERROR", format!("👥 Скан держателей {:?} не удался: {}", token, e)),
+    }
+}
@@ -0,0 +1,137 @@
+use crate::state::EngineLocal;
+use ethers::types::{Address, U256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use crate::bridge::{emit_event, emit_log, EngineEvent};
+use crate::execution;
+use crate::monitor;
+use crate::state::CORE_STATE;
+use crate::v3_math;
+
+// CalcImpact приходит на каждое нажатие в поле суммы: считаем только то, что пережило окно
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+// (token, quote, is_buy)
+type ImpactKey = (Address, Address, bool);
+type PoolLock = Arc<tokio::sync::Mutex<()>>;
+
+// Поколение последнего запроса по ключу. Результат устаревшего поколения не отправляется
+static LATEST: EngineLocal<Mutex<HashMap<ImpactKey, u64>>> = EngineLocal::new(|| Mutex::new(HashMap::new()));
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+// (token, quote) -> замок пула подписки: не больше одного расчёта (вызова quoter) на пул одновременно
+static IN_FLIGHT: EngineLocal<Mutex<HashMap<(Address, Address), PoolLock>>> = EngineLocal::new(|| Mutex::new(HashMap::new()));
+
+fn is_latest(key: ImpactKey, generation: u64) -> bool {
+    LATEST.lock().unwrap().get(&key) == Some(&generation)
+}
+
+/// CalcImpact: запрос, перебитый более новым для той же пары и направления (в окне DEBOUNCE,
+/// в очереди за расчётом пула или пока шёл его собственный расчёт), ImpactUpdate не даёт.
+/// Поколение выдаётся при вызове, до spawn - порядок задаёт очередь команд, а не планировщик задач
+pub fn request(
+    token: Address, quote: Address, token_label: String, quote_label: String, amount_in: f64, is_buy: bool
+) -> impl std::future::Future<Output = ()> {
+    let key = (token, quote, is_buy);
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    LATEST.lock().unwrap().insert(key, generation);
+
+    async move {
+        tokio::time::sleep(DEBOUNCE).await;
+        if !is_latest(key, generation) { return; }
+
+        let pool_lock = IN_FLIGHT.lock().unwrap().entry((token, quote)).or_default().clone();
+        let _in_flight = pool_lock.lock().await;
+        if !is_latest(key, generation) { return; }
+
+        let (impact_pct, expected_out, is_estimate) = calculate(token, quote, amount_in, is_buy).await;
+        if !is_latest(key, generation) { return; }
+
+        emit_event(EngineEvent::ImpactUpdate {
+            token: token_label, quote: quote_label, amount_in, impact_pct, expected_out: expected_out.to_string(), is_buy, is_estimate
+        });
+    }
+}
+
+/// (impact %, ожидаемый выход, приблизительно ли) по пулу подписки токена. Нет пула или сумма не разобралась - нули.
+/// V3 без RPC, пока хватает локальных данных: окно тиков, иначе текущий диапазон; quoter - только для
+/// сделок, пересекающих тик без загруженного окна, и для маршрутов
+async fn calculate(t_addr: Address, q_addr: Address, amount_in: f64, is_buy: bool) -> (f64, U256, bool) {
+    let t_start = std::time::Instant::now();
+    // Транспорт read-only вызовов: сравнение задержки с WS и без него
+    let mut via = if crate::state::ws_provider().is_some() { "ws" } else { "http" };
+    let (t_in, t_out) = if is_buy { (q_addr, t_addr) } else { (t_addr, q_addr) };
+    // Пул и маршрут берём из подписки на этот токен
+    let (p_type, p_fee, pool, is_route, quoter) = {
+        let s = CORE_STATE.read().unwrap();
+        match s.tokens.get(&t_addr).filter(|c| c.quote == q_addr) {
+            Some(ctx) => (ctx.pool_type.clone().unwrap_or_default(), ctx.pool_fee, ctx.pool_address, !ctx.route.is_empty(), s.quoter_address),
+            None => (String::new(), 0, None, false, s.quoter_address),
+        }
+    };
+
+    let dec_in = monitor::get_decimals_cached(t_in).await;
+    let dec_out = monitor::get_decimals_cached(t_out).await;
+
+    let amt_wei: U256 = match ethers::utils::parse_units(amount_in, dec_in as u32) {
+        Ok(v) => v.into(),
+        Err(_) => return (0.0, U256::zero(), false),
+    };
+    if amt_wei.is_zero() || p_type.is_empty() { return (0.0, U256::zero(), false); }
+
+    let mut is_estimate = false;
+    let exp_out = if p_type == "V3" {
+        // Проверяем что V3 пул реально существует
+        let has_v3_state = {
+            let s = CORE_STATE.read().unwrap();
+            !s.v3_states.is_empty() && pool.is_some()
+        };
+        // Прямой пул с загруженным окном тиков - считаем локально, без RPC на каждое нажатие
+        let direct_pool = pool.filter(|_| !is_route);
+        let local_out = direct_pool.and_then(|pool| v3_math::local_expected_out(pool, t_in, t_out, amt_wei));
+        let range_out = direct_pool.and_then(|pool| v3_math::single_range_expected_out(pool, t_in, t_out, amt_wei));
+
+        if !has_v3_state {
+            emit_log("DEBUG", "CalcImpact: V3 pool not selected, skipping quoter".to_string());
+            U256::zero()
+        } else if let Some(out) = local_out {
+            via = "local";
+            out
+        } else if let Some((out, false)) = range_out {
+            via = "range";
+            out
+        } else {
+            let quoted = execution::calculate_expected_out_v3_quoted(t_in, t_out, amt_wei, p_fee, quoter).await;
+            match range_out {
+                // Quoter не ответил - приблизительная оценка лучше нуля
+                Some((out, true)) if quoted.is_zero() => {
+                    via = "range~";
+                    is_estimate = true;
+                    out
+                }
+                _ => quoted,
+            }
+        }
+    } else {
+        // Проверяем что V2 пул реально существует
+        let has_v2_reserves = {
+            let s = CORE_STATE.read().unwrap();
+            !s.v2_reserves.is_empty() && pool.is_some()
+        };
+        if !has_v2_reserves {
+            emit_log("DEBUG", "CalcImpact: V2 pool not selected".to_string());
+            U256::zero()
+        } else {
+            via = "local";
+            execution::calculate_expected_out_v2_pure(t_in, t_out, amt_wei)
+        }
+    };
+
+    let idl_out = execution::calculate_ideal_out(t_in, t_out, amt_wei, dec_in, is_buy, dec_out);
+    let impact = execution::price_impact_pct(idl_out, exp_out, dec_out);
+
+    emit_log("DEBUG", format!("[IMPACT] {}ms | via={}", t_start.elapsed().as_millis(), via));
+    (impact, exp_out, is_estimate)
+}
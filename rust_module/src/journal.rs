@@ -0,0 +1,348 @@
+use pyo3::prelude::*;
+use crate::state::EngineLocal;
+use rusqlite::{params, Connection, OpenFlags};
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+use crate::bridge::{EngineEvent, emit_log};
+
+// Если БД заблокирована внешним просмотрщиком, держим записи в памяти - но не бесконечно
+const MAX_PENDING: usize = 10_000;
+const BUSY_TIMEOUT: Duration = Duration::from_secs(2);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS trades (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ts_ms INTEGER NOT NULL,
+    chain_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    wallet TEXT NOT NULL,
+    token TEXT,
+    action TEXT,
+    status TEXT,
+    amount REAL,
+    tx_hash TEXT,
+    gas_used INTEGER,
+    fee_wei TEXT,
+    pool TEXT,
+    request_id TEXT,
+    payload TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_trades_wallet_ts ON trades (wallet, ts_ms);
+CREATE INDEX IF NOT EXISTS idx_trades_token_ts ON trades (token, ts_ms);
+";
+
+// Колонки, добавленные после первой версии схемы: старые файлы журнала дополняются при открытии
+const MIGRATIONS: [&str; 1] = ["ALTER TABLE trades ADD COLUMN fee_wei TEXT"];
+
+const COLUMNS: [&str; 15] = [
+    "id", "ts_ms", "chain_id", "kind", "wallet", "token", "action", "status",
+    "amount", "tx_hash", "gas_used", "fee_wei", "pool", "request_id", "payload",
+];
+
+#[derive(Clone, Debug, Default)]
+struct JournalEntry {
+    ts_ms: u64,
+    chain_id: u64,
+    kind: String,
+    wallet: String,
+    token: Option<String>,
+    action: Option<String>,
+    status: Option<String>,
+    amount: Option<f64>,
+    tx_hash: Option<String>,
+    gas_used: Option<u64>,
+    fee_wei: Option<String>,
+    pool: Option<String>,
+    request_id: Option<String>,
+    payload: String,
+}
+
+struct Journal {
+    path: String,
+    chain_id: u64,
+    tx: mpsc::Sender<JournalEntry>,
+}
+
+static JOURNAL: EngineLocal<Mutex<Option<Journal>>> = EngineLocal::new(|| Mutex::new(None));
+
+fn current_timestamp_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// Открывает журнал (из Init). Повторный Init с тем же файлом продолжает писать в него же
+pub fn open(path: String, chain_id: u64) {
+    let mut guard = JOURNAL.lock().unwrap();
+    if let Some(j) = guard.as_mut().filter(|j| j.path == path) {
+        j.chain_id = chain_id;
+        return;
+    }
+
+    // Старый writer сам допишет буфер и завершится, когда отпустим его Sender
+    let (tx, rx) = mpsc::channel();
+    let writer_path = path.clone();
+    let spawned = std::thread::Builder::new()
+        .name("trade-journal".into())
+        .spawn(move || writer_loop(writer_path, rx));
+    if let Err(e) = spawned {
+        emit_log("ERROR", format!("📒 Не удалось запустить журнал сделок: {}", e));
+        return;
+    }
+    emit_log("INFO", format!("📒 Журнал сделок: {}", path));
+    *guard = Some(Journal { path, chain_id, tx });
+}
+
+/// Вызывается из emit_event: на горячем пути только отправка в канал, без диска
+pub fn record(event: &EngineEvent, request_id: Option<&str>) {
+    let (kind, mut entry) = match event {
+        EngineEvent::TxSent { tx_hash, wallet, action, amount, token, timestamp_ms, .. } => ("TxSent", JournalEntry {
+            ts_ms: *timestamp_ms,
+            wallet: wallet.clone(),
+            token: Some(token.clone()),
+            action: Some(action.clone()),
+            status: Some("sent".into()),
+            amount: Some(*amount),
+            tx_hash: Some(tx_hash.clone()),
+            ..Default::default()
+        }),
+        EngineEvent::TxConfirmed { tx_hash, wallet, gas_used, status, timestamp_ms, fee_wei, .. } => ("TxConfirmed", JournalEntry {
+            ts_ms: *timestamp_ms,
+            wallet: wallet.clone(),
+            status: Some(status.clone()),
+            tx_hash: Some(tx_hash.clone()),
+            gas_used: Some(*gas_used),
+            fee_wei: Some(fee_wei.clone()),
+            ..Default::default()
+        }),
+        EngineEvent::TradeStatus { wallet, action, status, tx_hash, token_address, amount, leg, .. } => ("TradeStatus", JournalEntry {
+            wallet: wallet.clone(),
+            token: Some(token_address.clone()),
+            action: Some(action.clone()),
+            status: Some(status.clone()),
+            amount: Some(*amount),
+            tx_hash: tx_hash.clone(),
+            pool: leg.clone(),
+            ..Default::default()
+        }),
+        EngineEvent::AutoFuelError { wallet, .. } => ("AutoFuelError", JournalEntry {
+            wallet: wallet.clone(),
+            action: Some("auto_fuel".into()),
+            status: Some("error".into()),
+            ..Default::default()
+        }),
+        _ => return,
+    };
+
+    let guard = JOURNAL.lock().unwrap();
+    let Some(journal) = guard.as_ref() else { return; };
+
+    // Бумажные сделки dry-run пишутся со статусом simulated_*
+    if entry.tx_hash.as_deref().is_some_and(|h| h.starts_with(crate::execution::DRY_RUN_PREFIX)) {
+        entry.status = entry.status.map(|s| format!("simulated_{}", s.to_lowercase()));
+    }
+    entry.kind = kind.to_string();
+    entry.chain_id = journal.chain_id;
+    if entry.ts_ms == 0 { entry.ts_ms = current_timestamp_ms(); }
+    entry.request_id = request_id.map(String::from);
+    entry.payload = serde_json::to_string(event).unwrap_or_default();
+    let _ = journal.tx.send(entry);
+}
+
+fn connect(path: &str) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    // WAL: внешний просмотрщик читает, не блокируя запись
+    let _ = conn.query_row("PRAGMA journal_mode=WAL", [], |_| Ok(()));
+    conn.execute_batch(SCHEMA)?;
+    for migration in MIGRATIONS {
+        // duplicate column - файл уже в новой схеме
+        if let Err(e) = conn.execute(migration, []) {
+            if !e.to_string().contains("duplicate column") { return Err(e); }
+        }
+    }
+    Ok(conn)
+}
+
+fn insert_batch(conn: &mut Connection, entries: &[JournalEntry]) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO trades (ts_ms, chain_id, kind, wallet, token, action, status, amount, tx_hash, gas_used, fee_wei, pool, request_id, payload)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)"
+        )?;
+        for e in entries {
+            stmt.execute(params![
+                e.ts_ms as i64, e.chain_id as i64, e.kind, e.wallet, e.token, e.action, e.status,
+                e.amount, e.tx_hash, e.gas_used.map(|g| g as i64), e.fee_wei, e.pool, e.request_id, e.payload
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+fn writer_loop(path: String, rx: mpsc::Receiver<JournalEntry>) {
+    let mut conn: Option<Connection> = None;
+    let mut pending: Vec<JournalEntry> = Vec::new();
+    let mut warned = false;
+
+    loop {
+        let disconnected = match rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(entry) => { pending.push(entry); false }
+            Err(mpsc::RecvTimeoutError::Timeout) => false,
+            Err(mpsc::RecvTimeoutError::Disconnected) => true,
+        };
+        pending.extend(rx.try_iter());
+
+        if !pending.is_empty() {
+            if conn.is_none() {
+                conn = connect(&path).ok();
+            }
+            let result = match conn.as_mut() {
+                Some(c) => insert_batch(c, &pending),
+                None => Err(rusqlite::Error::InvalidPath(path.clone().into())),
+            };
+            match result {
+                Ok(()) => {
+                    pending.clear();
+                    if warned { emit_log("INFO", "📒 Журнал сделок снова доступен".into()); }
+                    warned = false;
+                }
+                Err(e) => {
+                    if !warned {
+                        emit_log("WARNING", format!("📒 Журнал недоступен ({}), записи ждут в памяти", e));
+                        warned = true;
+                    }
+                    if pending.len() > MAX_PENDING {
+                        let excess = pending.len() - MAX_PENDING;
+                        pending.drain(..excess);
+                    }
+                }
+            }
+        }
+
+        if disconnected { break; }
+    }
+}
+
+/// Подтверждённые свапы ядра по токену для кошельков: tx_hash -> (buy/sell, amount из TxSent).
+/// Журнал не открыт или недоступен - пусто
+pub fn confirmed_swaps(wallets: &[ethers::types::Address], token: ethers::types::Address) -> std::collections::HashMap<ethers::types::H256, (String, f64)> {
+    let mut out = std::collections::HashMap::new();
+    let Some((path, chain_id)) = JOURNAL.lock().unwrap().as_ref().map(|j| (j.path.clone(), j.chain_id)) else { return out; };
+    if !std::path::Path::new(&path).exists() { return out; }
+    let query = |conn: &Connection, wallet: String| -> rusqlite::Result<Vec<(String, String, f64)>> {
+        let mut stmt = conn.prepare(
+            "SELECT s.tx_hash, s.action, s.amount FROM trades s
+             WHERE s.kind = 'TxSent' AND s.chain_id = ?1 AND lower(s.wallet) = ?2 AND lower(s.token) = ?3
+               AND s.action IN ('buy', 'sell')
+               AND EXISTS (SELECT 1 FROM trades c WHERE c.kind = 'TxConfirmed' AND c.tx_hash = s.tx_hash AND c.status = 'success')"
+        )?;
+        let rows = stmt.query_map(params![chain_id as i64, wallet, format!("{:?}", token)], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<f64>>(2)?.unwrap_or(0.0)))
+        })?;
+        rows.collect()
+    };
+    let result = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY).and_then(|conn| {
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        let mut rows = Vec::new();
+        for wallet in wallets {
+            rows.extend(query(&conn, format!("{:?}", wallet))?);
+        }
+        Ok(rows)
+    });
+    match result {
+        Ok(rows) => {
+            for (hash, action, amount) in rows {
+                if let Ok(h) = hash.parse() { out.insert(h, (action, amount)); }
+            }
+        }
+        Err(e) => emit_log("WARNING", format!("📒 Журнал не прочитан: {}", e)),
+    }
+    out
+}
+
+fn journal_path() -> PyResult<String> {
+    JOURNAL.lock().unwrap().as_ref()
+        .map(|j| j.path.clone())
+        .ok_or_else(|| pyo3::exceptions::PyRuntimeError::new_err("Trade journal is not initialized"))
+}
+
+fn to_py_err(e: rusqlite::Error) -> PyErr {
+    pyo3::exceptions::PyRuntimeError::new_err(format!("Trade journal: {}", e))
+}
+
+fn query_rows(wallet: Option<String>, token: Option<String>, from_ts: Option<u64>, to_ts: Option<u64>) -> PyResult<Vec<Vec<serde_json::Value>>> {
+    let path = journal_path()?;
+    // Файл создаётся при первой записи
+    if !std::path::Path::new(&path).exists() { return Ok(Vec::new()); }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(to_py_err)?;
+    conn.busy_timeout(BUSY_TIMEOUT).map_err(to_py_err)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM trades
+         WHERE (?1 IS NULL OR lower(wallet) = lower(?1))
+           AND (?2 IS NULL OR lower(token) = lower(?2))
+           AND (?3 IS NULL OR ts_ms >= ?3)
+           AND (?4 IS NULL OR ts_ms <= ?4)
+         ORDER BY ts_ms, id",
+        COLUMNS.join(", ")
+    )).map_err(to_py_err)?;
+
+    let rows = stmt.query_map(
+        params![wallet, token, from_ts.map(|t| t as i64), to_ts.map(|t| t as i64)],
+        |row| {
+            (0..COLUMNS.len()).map(|i| {
+                Ok(match row.get_ref(i)? {
+                    rusqlite::types::ValueRef::Null => serde_json::Value::Null,
+                    rusqlite::types::ValueRef::Integer(v) => v.into(),
+                    rusqlite::types::ValueRef::Real(v) => v.into(),
+                    rusqlite::types::ValueRef::Text(v) | rusqlite::types::ValueRef::Blob(v) => {
+                        String::from_utf8_lossy(v).into_owned().into()
+                    }
+                })
+            }).collect()
+        },
+    ).map_err(to_py_err)?;
+
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(to_py_err)
+}
+
+/// Записи журнала по фильтрам (None - без ограничения), JSON-массив объектов
+#[pyfunction]
+#[pyo3(signature = (wallet=None, token=None, from_ts=None, to_ts=None))]
+pub fn query_trades(wallet: Option<String>, token: Option<String>, from_ts: Option<u64>, to_ts: Option<u64>) -> PyResult<String> {
+    let rows: Vec<serde_json::Map<String, serde_json::Value>> = query_rows(wallet, token, from_ts, to_ts)?
+        .into_iter()
+        .map(|row| COLUMNS.iter().map(|c| c.to_string()).zip(row).collect())
+        .collect();
+    serde_json::to_string(&rows).map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+}
+
+fn csv_field(v: &serde_json::Value) -> String {
+    let s = match v {
+        serde_json::Value::Null => return String::new(),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s
+    }
+}
+
+/// Выгрузка всего журнала в CSV, возвращает число строк
+#[pyfunction]
+pub fn export_trades_csv(path: String) -> PyResult<usize> {
+    let rows = query_rows(None, None, None, None)?;
+    let mut out = COLUMNS.join(",");
+    out.push('\n');
+    for row in &rows {
+        out.push_str(&row.iter().map(csv_field).collect::<Vec<_>>().join(","));
+        out.push('\n');
+    }
+    std::fs::write(&path, out)?;
+    Ok(rows.len())
+}
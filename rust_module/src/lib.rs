@@ -8,15 +8,80 @@ mod execution;
 mod crypto;
 mod pnl;
 mod config;
+mod alerts;
+mod orders;
+mod scheduler;
+mod routing;
+mod v3_math;
+mod impact;
+pub mod math;
+mod journal;
+mod metrics;
+mod rpc_batch;
+mod token_cache;
+mod token_metadata;
+mod wallet_store;
+mod notifier;
+mod price_feed;
+mod candles;
+mod tape;
+mod watchlist;
+mod holders;
+mod audit;
+mod positions;
+mod sandwich;
+mod bundles;
+mod snipe;
+mod approvals;
+pub mod address;
+mod resolver;
+mod session;
+mod history;
+mod balances;
+#[cfg(feature = "engine-tests")]
+pub mod testing;
 
 #[pymodule]
 fn dexbot_core(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(bridge::init_bridge_signal, m)?)?;
     m.add_function(wrap_pyfunction!(bridge::pop_from_bridge, m)?)?;
+    m.add_function(wrap_pyfunction!(bridge::pop_batch_from_bridge, m)?)?;
+    m.add_function(wrap_pyfunction!(bridge::set_event_filter, m)?)?;
+    m.add_function(wrap_pyfunction!(bridge::set_event_filter_mode, m)?)?;
+    m.add_function(wrap_pyfunction!(bridge::get_event_filter, m)?)?;
     m.add_function(wrap_pyfunction!(engine::push_to_engine, m)?)?;
+    m.add_function(wrap_pyfunction!(engine::create_engine, m)?)?;
+    m.add_function(wrap_pyfunction!(engine::destroy_engine, m)?)?;
     m.add_function(wrap_pyfunction!(crypto::init_or_load_keys, m)?)?;
     m.add_function(wrap_pyfunction!(crypto::get_public_key, m)?)?; 
+    m.add_function(wrap_pyfunction!(crypto::change_master_password, m)?)?;
+    m.add_function(wrap_pyfunction!(crypto::verify_master_password, m)?)?;
+    m.add_function(wrap_pyfunction!(crypto::sign_message, m)?)?;
+    m.add_function(wrap_pyfunction!(crypto::verify_signature, m)?)?;
+    m.add_function(wrap_pyfunction!(crypto::set_event_signing, m)?)?;
+    m.add_function(wrap_pyfunction!(wallet_store::open_wallet_store, m)?)?;
+    m.add_function(wrap_pyfunction!(wallet_store::create_wallet, m)?)?;
+    m.add_function(wrap_pyfunction!(wallet_store::import_wallet, m)?)?;
+    m.add_function(wrap_pyfunction!(wallet_store::export_keystore, m)?)?;
+    m.add_function(wrap_pyfunction!(wallet_store::import_keystore, m)?)?;
+    m.add_function(wrap_pyfunction!(wallet_store::export_private_key, m)?)?;
+    m.add_function(wrap_pyfunction!(wallet_store::generate_mnemonic, m)?)?;
+    m.add_function(wrap_pyfunction!(wallet_store::derive_wallets, m)?)?;
     m.add_function(wrap_pyfunction!(config::get_network_config, m)?)?;
     m.add_function(wrap_pyfunction!(config::get_available_networks, m)?)?;
+    m.add_function(wrap_pyfunction!(config::set_config_dir, m)?)?;
+    m.add_function(wrap_pyfunction!(config::validate_network_config, m)?)?;
+    m.add_function(wrap_pyfunction!(config::save_network_config, m)?)?;
+    m.add_function(wrap_pyfunction!(config::delete_network_config, m)?)?;
+    m.add_function(wrap_pyfunction!(config::list_quote_tokens, m)?)?;
+    m.add_function(wrap_pyfunction!(config::add_quote_token, m)?)?;
+    m.add_function(wrap_pyfunction!(address::to_checksum_address, m)?)?;
+    m.add_function(wrap_pyfunction!(address::is_valid_address, m)?)?;
+    m.add_function(wrap_pyfunction!(journal::query_trades, m)?)?;
+    m.add_function(wrap_pyfunction!(journal::export_trades_csv, m)?)?;
+    m.add_function(wrap_pyfunction!(metrics::get_metrics_text, m)?)?;
+    m.add_function(wrap_pyfunction!(pnl::get_pnl_status, m)?)?;
+    m.add_function(wrap_pyfunction!(pnl::get_portfolio_pnl, m)?)?;
+    m.add_function(wrap_pyfunction!(positions::get_position, m)?)?;
     Ok(())
 }
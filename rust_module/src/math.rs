@@ -0,0 +1,191 @@
+// Чистая математика пулов без состояния ядра: V2 формула, цены и ликвидность V2/V3,
+// перевод wei <-> f64. Состояние (резервы, спот, комиссия роутера) передают вызывающие
+use ethers::types::{Address, U256, U512};
+use ethers::utils::{format_units, parse_units};
+
+/// Комиссия V2 по умолчанию: 0.3% (Uniswap V2); у форков своя - v2_pool_fee_bps в конфиге сети
+pub const DEFAULT_V2_FEE_BPS: u32 = 30;
+
+/// Комиссия TaxRouter по умолчанию: FEE_BASIS_POINTS контракта (0.1%); router_tax_bps в конфиге сети
+pub const DEFAULT_ROUTER_TAX_BPS: u32 = 10;
+
+// Больше format_units не принимает
+const MAX_UNITS_DECIMALS: u32 = 77;
+
+/// Cost basis меньше (в quote) - пыль: процент от него бессмысленен и уходит в inf
+pub const DUST_COST_BASIS: f64 = 1e-12;
+
+/// U256 -> f64 через строку: без паники на значениях больше u128.
+/// decimals больше 77 досчитываются делением
+pub fn u256_to_f64_safe(val: U256, decimals: u32) -> f64 {
+    if val.is_zero() { return 0.0; }
+    let s = format_units(val, decimals.min(MAX_UNITS_DECIMALS)).unwrap_or_else(|_| "0.0".to_string());
+    let value = s.parse::<f64>().unwrap_or(0.0);
+    if decimals > MAX_UNITS_DECIMALS { value / 10f64.powi((decimals - MAX_UNITS_DECIMALS) as i32) } else { value }
+}
+
+/// PnL в %: для пылевого cost basis - 0, процент всегда конечный
+pub fn pnl_pct(cost_basis: f64, current_value: f64) -> f64 {
+    if cost_basis.is_nan() || cost_basis <= DUST_COST_BASIS { return 0.0; }
+    let pnl = (current_value - cost_basis) / cost_basis * 100.0;
+    if pnl.is_finite() { pnl } else { 0.0 }
+}
+
+/// f64 -> wei: дробная часть глубже decimals отбрасывается, NaN/отрицательные/переполнение - 0
+pub fn f64_to_u256(value: f64, decimals: u32) -> U256 {
+    if !value.is_finite() || value <= 0.0 { return U256::zero(); }
+    parse_units(value, decimals).map(Into::into).unwrap_or_default()
+}
+
+/// Формула Uniswap V2: amountOut = (amountIn * (10000 - fee) * reserveOut) / (reserveIn * 10000 + amountIn * (10000 - fee)).
+/// Считается в U512 - amount_in и резервы могут быть любыми U256
+pub fn v2_amount_out(amount_in: U256, r_in: U256, r_out: U256, fee_bps: u32) -> U256 {
+    let fee_mul = U512::from(10_000 - fee_bps.min(9_999));
+    let mut amount_in_with_fee = U512::from(amount_in) * fee_mul;
+    let mut scaled_r_in = U512::from(r_in) * U512::from(10_000);
+    // Около U256::MAX произведение на r_out не влезает и в U512: вход и резерв сдвигаются одинаково, отношение сохраняется
+    let overflow_bits = (amount_in_with_fee.bits() + r_out.bits()).saturating_sub(511);
+    if overflow_bits > 0 {
+        amount_in_with_fee >>= overflow_bits;
+        scaled_r_in >>= overflow_bits;
+    }
+    let numerator = amount_in_with_fee * U512::from(r_out);
+    let denominator = scaled_r_in + amount_in_with_fee;
+
+    if denominator.is_zero() { return U256::zero(); }
+    // Выход не больше r_out - в U256 помещается всегда
+    U256::try_from(numerator / denominator).unwrap_or_default()
+}
+
+/// Сумма за вычетом комиссии в bps (роутер берёт её с входа или выхода свапа)
+pub fn net_of_fee(amount: U256, fee_bps: u32) -> U256 {
+    let fee = amount.full_mul(U256::from(fee_bps.min(10_000))) / U512::from(10_000);
+    // fee <= amount - в U256 помещается всегда
+    amount - U256::try_from(fee).unwrap_or(amount)
+}
+
+/// Шаг тиков V3 по fee tier (одинаков у Uniswap и PancakeSwap), неизвестный tier - None
+pub fn v3_tick_spacing(fee: u32) -> Option<i32> {
+    match fee {
+        100 => Some(1),
+        500 => Some(10),
+        2500 => Some(50),
+        3000 => Some(60),
+        10000 => Some(200),
+        _ => None,
+    }
+}
+
+fn div_rounding_up(a: U512, b: U512) -> U512 {
+    let (q, r) = a.div_mod(b);
+    if r.is_zero() { q } else { q + 1 }
+}
+
+/// Exact-input свап V3 в пределах текущего диапазона тиков: между соседними кратными tick_spacing
+/// инициализированных тиков нет и ликвидность постоянна. Целочисленные SqrtPriceMath/SwapMath пула -
+/// пока сделка не выходит за диапазон, выход совпадает с quoter до wei.
+/// (выход, пересекает ли сделка границу диапазона); при пересечении выход посчитан без смены ликвидности и приблизителен
+pub fn v3_single_range_out(
+    sqrt_price_x96: U256, liquidity: u128, tick: i32, tick_spacing: i32, fee: u32, amount_in: U256, zero_for_one: bool
+) -> Option<(U256, bool)> {
+    if sqrt_price_x96.is_zero() || liquidity == 0 || amount_in.is_zero() || tick_spacing <= 0 || fee >= 1_000_000 { return None; }
+    let sqrt_p = U512::from(sqrt_price_x96);
+    let l = U512::from(liquidity);
+    let q96 = U512::one() << 96;
+    let u256_max = U512::from(U256::MAX);
+    // SwapMath: комиссия снимается со входа шага, остаток округляется вниз
+    let amount = U512::from(amount_in) * U512::from(1_000_000 - fee) / U512::from(1_000_000u32);
+    if amount.is_zero() { return Some((U256::zero(), false)); }
+
+    let (sqrt_next, out) = if zero_for_one {
+        // getNextSqrtPriceFromAmount0RoundingUp: ветка без переполнения uint256 и запасная - как в контракте
+        let numerator1 = l << 96;
+        let product = amount * sqrt_p;
+        let sqrt_next = if product <= u256_max && numerator1 + product <= u256_max {
+            div_rounding_up(numerator1 * sqrt_p, numerator1 + product)
+        } else {
+            div_rounding_up(numerator1, numerator1 / sqrt_p + amount)
+        };
+        // getAmount1Delta, округление вниз
+        (sqrt_next, l * (sqrt_p - sqrt_next) / q96)
+    } else {
+        // getNextSqrtPriceFromAmount1RoundingDown
+        let sqrt_next = sqrt_p + (amount << 96) / l;
+        if sqrt_next > u256_max { return None; }
+        // getAmount0Delta, округление вниз
+        (sqrt_next, (l << 96) * (sqrt_next - sqrt_p) / sqrt_next / sqrt_p)
+    };
+
+    // Граница текущего диапазона: для продажи token0 - нижняя, для покупки - верхняя.
+    // Сравнение в f64: от точности зависит только флаг, не выход
+    let lower = tick.div_euclid(tick_spacing) * tick_spacing;
+    let boundary = if zero_for_one { lower } else { lower + tick_spacing };
+    let boundary_sqrt = 1.0001f64.powf(boundary as f64 / 2.0) * 2f64.powi(96);
+    let next_f = u256_to_f64_safe(U256::try_from(sqrt_next).ok()?, 0);
+    let crosses = if zero_for_one { next_f < boundary_sqrt } else { next_f >= boundary_sqrt };
+
+    Some((U256::try_from(out).ok()?, crosses))
+}
+
+/// Резервы пары в порядке (r_in, r_out): token0 < token1 по адресу, reserve0 для token0
+pub fn v2_oriented_reserves(reserves: (U256, U256), token_in: Address, token_out: Address) -> (U256, U256) {
+    let (r0, r1) = reserves;
+    if token_in < token_out { (r0, r1) } else { (r1, r0) }
+}
+
+/// V2: (ликвидность в USD, цена токена в quote). Decimals - токена и quote,
+/// порядок token0/token1 функция раскладывает сама по token0_is_quote
+pub fn v2_liquidity_usd_and_price(
+    reserve0: U256, reserve1: U256,
+    token_decimals: u8, quote_decimals: u8,
+    token0_is_quote: bool, quote_price_usd: f64
+) -> (f64, f64) {
+    let (r_quote, r_token) = if token0_is_quote { (reserve0, reserve1) } else { (reserve1, reserve0) };
+    let quote_f = u256_to_f64_safe(r_quote, quote_decimals as u32);
+    let token_f = u256_to_f64_safe(r_token, token_decimals as u32);
+
+    if quote_f == 0.0 || token_f == 0.0 {
+        return (0.0, 0.0);
+    }
+    (2.0 * quote_f * quote_price_usd, quote_f / token_f)
+}
+
+/// V3: (ликвидность quote-стороны в USD, цена токена в quote) по sqrtPriceX96 и активной ликвидности.
+/// sqrtPrice - сырая цена token0 в token1 в wei, к единицам приводится разницей decimals
+pub fn v3_liquidity_usd_and_price(
+    sqrt_price_x96: U256, liquidity: u128,
+    token_decimals: u8, quote_decimals: u8,
+    token0_is_quote: bool, quote_price_usd: f64
+) -> (f64, f64) {
+    if sqrt_price_x96.is_zero() || liquidity == 0 { return (0.0, 0.0); }
+    // uint160: as_u128 паникует на дорогих парах
+    let sqrt_p = u256_to_f64_safe(sqrt_price_x96, 0) / 2.0f64.powi(96);
+    let raw_price = sqrt_p * sqrt_p;
+    let (dec0, dec1) = if token0_is_quote { (quote_decimals, token_decimals) } else { (token_decimals, quote_decimals) };
+    let price_token0_in_token1 = raw_price * 10f64.powi(dec0 as i32 - dec1 as i32);
+    if price_token0_in_token1 == 0.0 || !price_token0_in_token1.is_finite() { return (0.0, 0.0); }
+
+    let (price_in_quote, quote_raw) = if token0_is_quote {
+        (1.0 / price_token0_in_token1, liquidity as f64 / sqrt_p)
+    } else {
+        (price_token0_in_token1, liquidity as f64 * sqrt_p)
+    };
+    let tvl = quote_raw / 10f64.powi(quote_decimals as i32) * quote_price_usd;
+    (tvl, price_in_quote)
+}
+
+/// Идеальный выход по спотовой цене (quote за токен) без impact и комиссий.
+/// BUY: вводим quote -> нужен token_per_quote = 1/price; SELL: вводим token -> quote_per_token = price
+pub fn ideal_out(amount_in: U256, decimals_in: u8, decimals_out: u8, price: f64, is_buy: bool) -> U256 {
+    if amount_in.is_zero() || price <= 0.0 || !price.is_finite() { return U256::zero(); }
+    let actual_p = if is_buy { 1.0 / price } else { price };
+    f64_to_u256(u256_to_f64_safe(amount_in, decimals_in as u32) * actual_p, decimals_out as u32)
+}
+
+/// Price impact (%): насколько ожидаемый выход хуже идеального по спотовой цене
+pub fn price_impact_pct(ideal_out: U256, exp_out: U256, dec_out: u8) -> f64 {
+    if ideal_out.is_zero() || exp_out > ideal_out { return 0.0; }
+    let idl_f = u256_to_f64_safe(ideal_out, dec_out as u32);
+    let exp_f = u256_to_f64_safe(exp_out, dec_out as u32);
+    if idl_f > 0.0 { ((idl_f - exp_f) / idl_f) * 100.0 } else { 0.0 }
+}
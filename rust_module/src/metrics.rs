@@ -0,0 +1,198 @@
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use crate::bridge::emit_log;
+use crate::state::{EngineId, EngineLocal, RPC_POOL, with_engine};
+
+// Границы бакетов гистограмм задержек (секунды)
+const LATENCY_BUCKETS: [f64; 10] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+/// Гистограмма на атомиках: observe без блокировок
+pub struct Histogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    sum_us: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    const fn new() -> Self {
+        Self {
+            buckets: [const { AtomicU64::new(0) }; LATENCY_BUCKETS.len()],
+            sum_us: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, d: Duration) {
+        let secs = d.as_secs_f64();
+        if let Some(i) = LATENCY_BUCKETS.iter().position(|b| secs <= *b) {
+            self.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        self.sum_us.fetch_add(d.as_micros() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        for b in &self.buckets { b.store(0, Ordering::Relaxed); }
+        self.sum_us.store(0, Ordering::Relaxed);
+        self.count.store(0, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}\n# TYPE {} histogram", name, help, name);
+        let mut cumulative = 0;
+        for (bound, bucket) in LATENCY_BUCKETS.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative);
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let _ = writeln!(out, "{}_sum {}", name, self.sum_us.load(Ordering::Relaxed) as f64 / 1e6);
+        let _ = writeln!(out, "{}_count {}", name, count);
+    }
+}
+
+/// Метрики движка: у каждого экземпляра ядра свои, Init одного не сбрасывает чужие
+pub struct Metrics {
+    pub swaps_sent: AtomicU64,
+    pub swaps_failed: AtomicU64,
+    pub ws_reconnects: AtomicU64,
+    pub events_sent: AtomicU64,
+    pub notify_dropped: AtomicU64,
+    pub tape_skipped: AtomicU64,
+    pub broadcast_latency: Histogram,
+    pub quoter_latency: Histogram,
+    // Ошибки по RPC нодам (не горячий путь - под мьютексом). В RpcNode.fails счётчик сбрасывается при успехе
+    rpc_fails: Mutex<HashMap<String, u64>>,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        Self {
+            swaps_sent: AtomicU64::new(0),
+            swaps_failed: AtomicU64::new(0),
+            ws_reconnects: AtomicU64::new(0),
+            events_sent: AtomicU64::new(0),
+            notify_dropped: AtomicU64::new(0),
+            tape_skipped: AtomicU64::new(0),
+            broadcast_latency: Histogram::new(),
+            quoter_latency: Histogram::new(),
+            rpc_fails: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn counters(&self) -> [&AtomicU64; 6] {
+        [&self.swaps_sent, &self.swaps_failed, &self.ws_reconnects, &self.events_sent, &self.notify_dropped, &self.tape_skipped]
+    }
+}
+
+pub static METRICS: EngineLocal<Metrics> = EngineLocal::new(Metrics::new);
+
+pub fn inc(counter: &AtomicU64) {
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn rpc_fail(url: &str) {
+    *METRICS.rpc_fails.lock().unwrap().entry(url.to_string()).or_insert(0) += 1;
+}
+
+/// Сброс при повторном Init
+pub fn reset() {
+    for c in METRICS.counters() {
+        c.store(0, Ordering::Relaxed);
+    }
+    METRICS.broadcast_latency.reset();
+    METRICS.quoter_latency.reset();
+    METRICS.rpc_fails.lock().unwrap().clear();
+    crate::bridge::transport::reset_stats();
+}
+
+/// В URL RPC часто лежит API ключ - в метки попадает только хост
+pub fn node_label(url: &str) -> String {
+    let host = url::Url::parse(url).ok()
+        .and_then(|u| u.host_str().map(String::from))
+        .unwrap_or_else(|| "unknown".into());
+    host.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn render_counter(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter\n{} {}", name, help, name, name, value);
+}
+
+pub fn render() -> String {
+    let m = &*METRICS;
+    let mut out = String::new();
+    render_counter(&mut out, "dexbot_swaps_sent_total", "Swaps broadcast successfully", m.swaps_sent.load(Ordering::Relaxed));
+    render_counter(&mut out, "dexbot_swaps_failed_total", "Swaps rejected by all RPC nodes", m.swaps_failed.load(Ordering::Relaxed));
+    render_counter(&mut out, "dexbot_ws_reconnects_total", "WebSocket reconnect cycles", m.ws_reconnects.load(Ordering::Relaxed));
+    render_counter(&mut out, "dexbot_bridge_events_total", "Events pushed to the Python bridge", m.events_sent.load(Ordering::Relaxed));
+    render_counter(&mut out, "dexbot_notify_dropped_total", "Notifications dropped because the notifier queue was full", m.notify_dropped.load(Ordering::Relaxed));
+    render_counter(&mut out, "dexbot_tape_skipped_total", "Trade tape events skipped by the per-pool rate cap", m.tape_skipped.load(Ordering::Relaxed));
+    m.broadcast_latency.render(&mut out, "dexbot_broadcast_latency_seconds", "Raw transaction broadcast latency");
+    m.quoter_latency.render(&mut out, "dexbot_quoter_latency_seconds", "V3 quoter call latency");
+
+    let (queued, dropped) = crate::bridge::transport::stats();
+    let _ = writeln!(out, "# HELP dexbot_bridge_queue_depth Events waiting for Python\n# TYPE dexbot_bridge_queue_depth gauge\ndexbot_bridge_queue_depth {}", queued);
+    let _ = writeln!(out, "# HELP dexbot_bridge_dropped_total Events dropped or coalesced by type\n# TYPE dexbot_bridge_dropped_total counter");
+    for (kind, n) in dropped {
+        let _ = writeln!(out, "dexbot_bridge_dropped_total{{type=\"{}\"}} {}", kind, n);
+    }
+
+    let _ = writeln!(out, "# HELP dexbot_rpc_fails_total RPC failures per node\n# TYPE dexbot_rpc_fails_total counter");
+    for (url, n) in m.rpc_fails.lock().unwrap().iter() {
+        let _ = writeln!(out, "dexbot_rpc_fails_total{{node=\"{}\"}} {}", node_label(url), n);
+    }
+    let _ = writeln!(out, "# HELP dexbot_rpc_requests_total Requests routed to each node\n# TYPE dexbot_rpc_requests_total counter");
+    for node in RPC_POOL.read().unwrap().nodes.iter() {
+        let _ = writeln!(out, "dexbot_rpc_requests_total{{node=\"{}\"}} {}", node_label(&node.url), node.requests.load(Ordering::Relaxed));
+    }
+    let _ = writeln!(out, "# HELP dexbot_rpc_latency_ms Last measured RPC latency per node\n# TYPE dexbot_rpc_latency_ms gauge");
+    for node in RPC_POOL.read().unwrap().nodes.iter() {
+        let _ = writeln!(out, "dexbot_rpc_latency_ms{{node=\"{}\"}} {}", node_label(&node.url), node.latency as f64 / 1000.0);
+    }
+    out
+}
+
+/// Метрики движка в формате Prometheus exposition (engine_id=None - движок по умолчанию)
+#[pyfunction]
+#[pyo3(signature = (engine_id=None))]
+pub fn get_metrics_text(engine_id: Option<EngineId>) -> PyResult<String> {
+    let id = crate::bridge::resolve_engine(engine_id)?;
+    Ok(with_engine(id, render))
+}
+
+/// Минимальный HTTP сервер: GET /metrics, только localhost
+pub async fn serve(port: u16) {
+    let listener = match tokio::net::TcpListener::bind(("127.0.0.1", port)).await {
+        Ok(l) => l,
+        Err(e) => {
+            emit_log("ERROR", format!("📈 Metrics: не удалось занять порт {}: {}", port, e));
+            return;
+        }
+    };
+    emit_log("INFO", format!("📈 Metrics: http://127.0.0.1:{}/metrics", port));
+
+    loop {
+        let Ok((mut stream, _)) = listener.accept().await else { continue; };
+        crate::state::spawn(async move {
+            let mut buf = [0u8; 1024];
+            let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf)).await
+                .ok().and_then(|r| r.ok()).unwrap_or(0);
+            let request = String::from_utf8_lossy(&buf[..n]);
+            let response = if request.starts_with("GET /metrics") {
+                let body = render();
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(), body
+                )
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+            };
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+    }
+}
@@ -1,12 +1,27 @@
 use ethers::prelude::*;
-use ethers::utils::format_units;
+use ethers::abi::{AbiDecode, AbiEncode};
 use std::time::{Instant, Duration};
 use tokio::time::{sleep, timeout, interval};
 use std::collections::HashMap;
 
-use crate::state::{RPC_POOL, SHUTDOWN_FLAG, GLOBAL_HTTP_CLIENT, CORE_STATE, TRACKED_WALLETS, V3PoolState};
-use crate::bridge::{emit_event, EngineEvent, emit_log};
+use crate::state::{RPC_POOL, http_provider, fastest_http_provider, set_ws_provider, SHUTDOWN_FLAG, GLOBAL_HTTP_CLIENT, CORE_STATE, TRACKED_WALLETS, SUBSCRIPTIONS_CHANGED, TRANSFER_SET_CHANGED, AbortOnDrop, V3PoolState, LiquidityBaseline, RankedPool, GasOracle, FuelGate, FuelMode, WalletKey};
+use crate::state::app::{BotState, PendingTx, TokenContext};
+use crate::bridge::{emit_event, EngineEvent, emit_log, PoolCandidateInfo, GasOracleInfo, REQUEST_ID};
 use crate::execution;
+use crate::math;
+use crate::token_cache;
+use crate::token_metadata;
+use crate::rpc_batch::{self, BatchCall};
+use crate::alerts;
+use crate::orders;
+use crate::v3_math;
+use crate::candles;
+use crate::tape;
+use crate::positions;
+use crate::sandwich;
+use crate::snipe;
+use crate::approvals;
+use crate::watchlist::WatchSnapshot;
 use futures::StreamExt;
 use std::sync::Arc;
 use url::Url;
@@ -18,6 +33,10 @@ abigen!(
         event Transfer(address indexed from, address indexed to, uint256 value)
         event Sync(uint112 reserve0, uint112 reserve1)
         event Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick)
+        event Burn(address indexed sender, uint256 amount0, uint256 amount1, address indexed to)
+        event PairCreated(address indexed token0, address indexed token1, address pair, uint256 allPairsLength)
+        event PoolCreated(address indexed token0, address indexed token1, uint24 indexed fee, int24 tickSpacing, address pool)
+        event FeeAmountEnabled(uint24 indexed fee, int24 indexed tickSpacing)
         function decimals() external view returns (uint8)
         function getPair(address, address) external view returns (address)
         function getPool(address, address, uint24) external view returns (address)
@@ -28,6 +47,20 @@ abigen!(
     ]"#
 );
 
+// V2 Swap: тоже "Swap", поэтому не в UniversalABI рядом с V3 вариантом
+#[derive(Clone, Debug, EthEvent)]
+#[ethevent(name = "Swap", abi = "Swap(address,uint256,uint256,uint256,uint256,address)")]
+pub struct V2SwapFilter {
+    #[ethevent(indexed)]
+    pub sender: Address,
+    pub amount_0_in: U256,
+    pub amount_1_in: U256,
+    pub amount_0_out: U256,
+    pub amount_1_out: U256,
+    #[ethevent(indexed)]
+    pub to: Address,
+}
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 struct PoolCandidate {
@@ -40,15 +73,42 @@ struct PoolCandidate {
     reserves: Option<(U256, U256)>,
     score: f64,
     spot_price: f64,
+    impact_pct: Option<f64>, // точный impact по тикам (только V3 с крупной относительно диапазона сделкой)
+}
+
+/// 2-hop маршрут token -> mid -> quote (fees заполнены только для V3)
+#[derive(Debug, Clone)]
+pub struct PoolRoute {
+    pub pool_type: String,
+    pub path: Vec<Address>,
+    pub pools: Vec<H160>,
+    pub fees: Vec<u32>,
+    pub via: String,
+    pub liquidity_usd: f64,
+    pub spot_price: f64,
+    pub amount_out: U256, // котировка эталонной покупки quote -> token
 }
 
 const DEFAULT_TRADE_USD: f64 = 1000.0;
 const WEIGHT_LIQUIDITY: f64 = 0.50;
 const WEIGHT_FEE: f64 = 0.20;
 const WEIGHT_PRICE_IMPACT: f64 = 0.30;
-const RECONNECT_DELAY_SECS: u64 = 3;
+const RECONNECT_MAX_DELAY_SECS: u64 = 30;
 const PREFETCH_TIMEOUT_SECS: u64 = 5;
-const IDLE_TIMEOUT_SECS: u64 = 30;
+const WSS_FAILURES_BEFORE_POLLING: u32 = 3;
+const WSS_STABLE_SESSION_SECS: u64 = 60; // сессия дольше - WSS рабочий, счётчик неудач сбрасывается
+const WSS_PROBE_INTERVAL_SECS: u64 = 60;
+const WSS_PROBE_TIMEOUT_SECS: u64 = 5;
+const POLL_BLOCK_SAMPLE: u64 = 20;
+const POLL_MAX_LOG_RANGE: u64 = 100;
+const POLL_MIN_INTERVAL: Duration = Duration::from_secs(1);
+const POLL_MAX_INTERVAL: Duration = Duration::from_secs(15);
+const RUG_SELL_SLIPPAGE: f64 = 50.0;
+const RESCORE_EVERY_BLOCKS: u64 = 10;
+const FEE_HISTORY_BLOCKS: u64 = 10;
+const FEE_PERCENTILES: [f64; 4] = [25.0, 50.0, 75.0, 95.0];
+const FEE_HISTORY_MAX_MISSES: u32 = 3; // после стольких пустых ответов считаем сеть не-1559
+const MAX_RECEIPT_CHECKS_PER_TICK: usize = 16;
 
 fn current_timestamp_ms() -> u64 {
     std::time::SystemTime::now()
@@ -57,82 +117,207 @@ fn current_timestamp_ms() -> u64 {
         .as_millis() as u64
 }
 
-fn get_quote_price_usd(quote_symbol: &str, usd_prices: &HashMap<String, f64>) -> f64 {
-    if quote_symbol.is_empty() { return 1.0; }
-    if let Some(&price) = usd_prices.get(quote_symbol) { return price; }
+/// Символ quote по адресу: quote_tokens, затем символ из подписки (UI не нужно держать свой справочник)
+pub(crate) fn quote_symbol_in(s: &BotState, quote: Address) -> String {
+    s.quote_tokens.iter()
+        .find(|(_, addr)| **addr == quote)
+        .map(|(sym, _)| sym.clone())
+        .or_else(|| s.tokens.values().find(|c| c.quote == quote && !c.quote_symbol.is_empty()).map(|c| c.quote_symbol.clone()))
+        .unwrap_or_default()
+}
+
+/// Quote токены, чьи балансы опрашиваются: quote_tokens сети (кроме выключенных в balance_quotes),
+/// wrapped native и fuel quote. Символ - из quote_tokens, для остальных "QUOTE"
+pub(crate) fn balance_quote_tokens() -> Vec<(Address, String)> {
+    let s = CORE_STATE.read().unwrap();
+    let mut quotes: Vec<(Address, String)> = s.quote_tokens.iter()
+        .filter(|(sym, _)| s.balance_quotes.as_ref().is_none_or(|list| list.iter().any(|e| e.eq_ignore_ascii_case(sym))))
+        .map(|(sym, a)| (*a, sym.clone()))
+        .collect();
+    for a in [s.wrapped_native_address, s.fuel_quote_address] {
+        if quotes.iter().any(|(q, _)| *q == a) { continue; }
+        let sym = quote_symbol_in(&s, a);
+        quotes.push((a, if sym.is_empty() { "QUOTE".into() } else { sym }));
+    }
+    quotes.retain(|(a, _)| !a.is_zero());
+    quotes.sort_by(|a, b| a.1.cmp(&b.1));
+    quotes
+}
+
+/// Балансы ERC-20 всех кошельков одним батч-запросом, BalanceUpdate с символом токена.
+/// skip_zero - фоновый опрос не шлёт нули по каждому из quote
+pub(crate) async fn emit_token_balances(url: &str, wallets: &[Address], tokens: &[(Address, String)], skip_zero: bool) {
+    let calls: Vec<BatchCall> = tokens.iter()
+        .flat_map(|(token, _)| wallets.iter().map(|&owner| BatchCall::Call { to: *token, data: BalanceOfCall(owner).encode().into() }))
+        .collect();
+    if calls.is_empty() { return; }
+    let results = rpc_batch::batch(url, &calls).await;
+    for ((token, symbol), chunk) in tokens.iter().zip(results.chunks(wallets.len())) {
+        let decimals = get_decimals_cached(*token).await;
+        for (wallet, res) in wallets.iter().zip(chunk) {
+            let Some(balance) = rpc_batch::as_u256(res) else { continue; };
+            if skip_zero && balance.is_zero() { continue; }
+            emit_event(EngineEvent::BalanceUpdate {
+                wallet: format!("{:?}", wallet),
+                token: format!("{:?}", token),
+                wei: balance.to_string(),
+                float_val: wei_to_float(balance, decimals),
+                symbol: symbol.clone()
+            });
+        }
+    }
+}
+
+fn quote_symbol_for(quote: Address) -> String {
+    quote_symbol_in(&CORE_STATE.read().unwrap(), quote)
+}
+
+/// USD цена quote конкретной подписки (у разных токенов могут быть разные quote)
+fn quote_price_in(s: &BotState, quote: Address) -> f64 {
+    get_quote_price_usd(&quote_symbol_in(s, quote), s)
+}
+
+/// Устаревшая цена не возвращается: пробуем вариант символа с/без W, иначе 1.0 как для неизвестной цены
+pub(crate) fn get_quote_price_usd(quote_symbol: &str, s: &BotState) -> f64 {
+    fresh_quote_price_usd(quote_symbol, s).unwrap_or(1.0)
+}
+
+/// Свежая USD цена символа или его варианта с/без W, None - цены нет
+pub(crate) fn fresh_quote_price_usd(quote_symbol: &str, s: &BotState) -> Option<f64> {
+    if quote_symbol.is_empty() { return None; }
+    if let Some(price) = s.fresh_usd_price(quote_symbol) { return Some(price); }
     if quote_symbol.starts_with('W') && quote_symbol.len() > 1 {
         let without_w = &quote_symbol[1..];
-        if let Some(&price) = usd_prices.get(without_w) { return price; }
+        if let Some(price) = s.fresh_usd_price(without_w) { return Some(price); }
     }
-    let with_w = format!("W{}", quote_symbol);
-    if let Some(&price) = usd_prices.get(&with_w) { return price; }
-    1.0
+    s.fresh_usd_price(&format!("W{}", quote_symbol))
 }
 
 pub async fn get_decimals_cached(token: Address) -> u8 {
     if let Some(dec) = CORE_STATE.read().unwrap().decimals_cache.get(&token) { return *dec; }
-    let urls = { RPC_POOL.read().unwrap().get_fastest_pool(3) };
-    for url_str in urls {
-        if let Ok(url) = Url::parse(&url_str) {
-            let provider = Arc::new(Provider::new(Http::new_with_client(url, GLOBAL_HTTP_CLIENT.clone())));
-            let contract = UniversalABI::new(token, provider);
-            if let Ok(dec) = contract.decimals().call().await {
-                if dec <= 77 {
-                    CORE_STATE.write().unwrap().decimals_cache.insert(token, dec);
-                    return dec;
-                }
-            }
+    let decoded = execution::read_call(token, DecimalsCall.encode().into()).await.ok()
+        .and_then(|out| DecimalsReturn::decode(&out).ok());
+    match decoded {
+        Some(DecimalsReturn(dec)) if dec <= 77 => {
+            CORE_STATE.write().unwrap().decimals_cache.insert(token, dec);
+            token_cache::store_decimals(token, dec);
+            dec
         }
+        _ => 18,
     }
-    18
 }
 
 fn wei_to_float(wei_value: U256, decimals: u8) -> f64 {
-    if wei_value.is_zero() { return 0.0; }
-    let safe_decimals = decimals.min(77) as i32;
-    let wei_f64 = if wei_value > U256::from(u128::MAX) {
-        let s = format_units(wei_value, safe_decimals as u32).unwrap_or_else(|_| "0.0".to_string());
-        return s.parse::<f64>().unwrap_or(0.0);
-    } else {
-        wei_value.as_u128() as f64
-    };
-    let divisor = 10f64.powi(safe_decimals);
-    if divisor == 0.0 { return 0.0; }
-    wei_f64 / divisor
+    math::u256_to_f64_safe(wei_value, decimals as u32)
+}
+
+// ===================== GAS ORACLE =====================
+
+/// eth_feeHistory -> оракул газа. None - сеть без EIP-1559 (base fee нулевой или метод не поддерживается).
+/// Перцентиль priority fee - медиана по блокам окна, чтобы один блок с MEV не задирал цену
+async fn fetch_gas_oracle<M: Middleware>(provider: &M) -> Option<GasOracle> {
+    let history = provider.fee_history(FEE_HISTORY_BLOCKS, BlockNumber::Latest, &FEE_PERCENTILES).await.ok()?;
+    let next_base = history.base_fee_per_gas.last().copied().filter(|b| !b.is_zero())?;
+    let first_base = history.base_fee_per_gas.first().copied().unwrap_or(next_base);
+    let to_gwei = |v: U256| v.low_u128() as f64 / 1e9;
+
+    let mut priority = [0.0; 4];
+    for (i, p) in priority.iter_mut().enumerate() {
+        let mut column: Vec<f64> = history.reward.iter().filter_map(|r| r.get(i)).map(|v| to_gwei(*v)).collect();
+        column.sort_by(|a, b| a.total_cmp(b));
+        *p = column.get(column.len() / 2).copied().unwrap_or(0.0);
+    }
+
+    let base = to_gwei(next_base);
+    let first = to_gwei(first_base);
+    // Транзакции legacy: цена = base fee с запасом на рост + чаевые валидатору
+    Some(GasOracle {
+        base_fee_gwei: base,
+        base_fee_trend_pct: if first > 0.0 { (base - first) / first * 100.0 } else { 0.0 },
+        priority_gwei: priority,
+        slow_gwei: base + priority[0],
+        standard_gwei: base * 1.125 + priority[1],
+        fast_gwei: base * 1.25 + priority[3],
+    })
 }
 
 // ===================== HTTP PREFETCH =====================
 
+/// Состояние пула из батча prefetch: у V2 есть reserves, у V3 - slot0 и liquidity
+#[derive(Default, Clone, Copy)]
+struct PoolRead {
+    reserves: Option<(u128, u128)>,
+    slot0: Option<(U256, i32)>,
+    liquidity: Option<u128>,
+}
+
+/// getReserves + slot0 + liquidity всех пулов одним батчем (лишние вызовы просто ревертятся)
+async fn batch_pool_reads(url: &str, pools: &[Address]) -> HashMap<Address, PoolRead> {
+    let calls: Vec<BatchCall> = pools.iter().flat_map(|&to| [
+        BatchCall::Call { to, data: GetReservesCall.encode().into() },
+        BatchCall::Call { to, data: Slot0Call.encode().into() },
+        BatchCall::Call { to, data: LiquidityCall.encode().into() },
+    ]).collect();
+    let results = rpc_batch::batch(url, &calls).await;
+
+    pools.iter().zip(results.chunks(3)).map(|(&pool, r)| {
+        let decode = |i: usize| rpc_batch::as_bytes(&r[i]);
+        let read = PoolRead {
+            reserves: decode(0).and_then(|b| GetReservesReturn::decode(&b).ok()).map(|r| (r.reserve_0, r.reserve_1)),
+            slot0: decode(1).and_then(|b| Slot0Return::decode(&b).ok()).map(|r| (r.sqrt_price_x96, r.tick)),
+            liquidity: decode(2).and_then(|b| LiquidityReturn::decode(&b).ok()).map(|r| r.0),
+        };
+        (pool, read)
+    }).collect()
+}
+
+/// Начальные данные двумя батч-запросами к одной ноде:
+/// 1) газ, native и quote балансы, decimals; 2) состояние всех пулов подписанных токенов
 async fn prefetch_all_data(
     provider: Arc<Provider<Http>>,
     wallets: Vec<Address>,
-    quote_token: Option<Address>,
-    pool_targets: Vec<Address>,
-    token: Address,
-    quote: Address
+    tokens: Vec<Address>
 ) {
     emit_log("INFO", "⚡ HTTP Prefetch: Начинаем загрузку начальных данных...".into());
     let start = Instant::now();
+    let url = provider.url().to_string();
     
-    let gas_provider = provider.clone();
-    let gas_price = gas_provider.get_gas_price().await.ok();
-    if let Some(gas) = gas_price {
+    // Все опрашиваемые quote и quote подписок - в том же батче, число запросов к ноде не растёт
+    let mut quotes = balance_quote_tokens();
+    {
+        let s = CORE_STATE.read().unwrap();
+        for q in s.tokens.values().map(|c| c.quote).filter(|q| *q != Address::zero()) {
+            if !quotes.iter().any(|(a, _)| *a == q) {
+                let sym = quote_symbol_in(&s, q);
+                quotes.push((q, if sym.is_empty() { "QUOTE".into() } else { sym }));
+            }
+        }
+    }
+    let missing_decimals: Vec<Address> = {
+        let s = CORE_STATE.read().unwrap();
+        quotes.iter().map(|(q, _)| q).chain(tokens.iter()).copied()
+            .filter(|t| !s.decimals_cache.contains_key(t))
+            .collect::<std::collections::HashSet<_>>()
+            .into_iter().collect()
+    };
+
+    let mut calls = vec![BatchCall::GasPrice];
+    calls.extend(wallets.iter().map(|&w| BatchCall::Balance(w)));
+    for &(quote, _) in &quotes {
+        calls.extend(wallets.iter().map(|&owner| BatchCall::Call { to: quote, data: BalanceOfCall(owner).encode().into() }));
+    }
+    calls.extend(missing_decimals.iter().map(|&to| BatchCall::Call { to, data: DecimalsCall.encode().into() }));
+    let results = rpc_batch::batch(&url, &calls).await;
+    let mut results = results.iter();
+
+    if let Some(gas) = results.next().and_then(rpc_batch::as_u256) {
+        let gas = CORE_STATE.read().unwrap().chain_quirks.network_gas(gas);
         CORE_STATE.write().unwrap().gas_price = gas;
         emit_log("DEBUG", format!("⚡ Prefetch: Gas price = {} Gwei", gas.as_u64() / 1_000_000_000));
     }
     
-    let mut native_balance_tasks = Vec::new();
-    for wallet in wallets.clone() {
-        let p = provider.clone();
-        native_balance_tasks.push(async move {
-            let balance = p.get_balance(wallet, None).await.ok()?;
-            Some((wallet, balance))
-        });
-    }
-    
-    let native_results = futures::future::join_all(native_balance_tasks).await;
-    for result in native_results {
-        if let Some((wallet, balance)) = result {
+    for wallet in &wallets {
+        if let Some(balance) = results.next().and_then(rpc_batch::as_u256) {
             let float_val = wei_to_float(balance, 18);
             emit_event(EngineEvent::BalanceUpdate {
                 wallet: format!("{:?}", wallet),
@@ -144,116 +329,543 @@ async fn prefetch_all_data(
         }
     }
     
-    if let Some(quote_addr) = quote_token {
-        if quote_addr != Address::zero() {
-            let quote_decimals = get_decimals_cached(quote_addr).await;
-            for wallet in wallets.clone() {
-                let contract = UniversalABI::new(quote_addr, provider.clone());
-                if let Ok(balance) = contract.balance_of(wallet).call().await {
-                    let float_val = wei_to_float(balance, quote_decimals);
-                    emit_event(EngineEvent::BalanceUpdate {
-                        wallet: format!("{:?}", wallet),
-                        token: format!("{:?}", quote_addr),
-                        wei: balance.to_string(),
-                        float_val,
-                        symbol: "QUOTE".into()
-                    });
-                }
+    let quote_balances: Vec<Option<U256>> = quotes.iter()
+        .flat_map(|_| wallets.iter())
+        .map(|_| results.next().and_then(rpc_batch::as_bytes).and_then(|b| BalanceOfReturn::decode(&b).ok()).map(|r| r.0))
+        .collect();
+    for token in &missing_decimals {
+        let dec = results.next().and_then(rpc_batch::as_bytes).and_then(|b| DecimalsReturn::decode(&b).ok()).map(|r| r.0);
+        if let Some(dec) = dec.filter(|d| *d <= 77) {
+            CORE_STATE.write().unwrap().decimals_cache.insert(*token, dec);
+            token_cache::store_decimals(*token, dec);
+        }
+    }
+
+    for (i, (quote_addr, symbol)) in quotes.iter().enumerate() {
+        let quote_decimals = get_decimals_cached(*quote_addr).await;
+        for (j, wallet) in wallets.iter().enumerate() {
+            if let Some(balance) = quote_balances[i * wallets.len() + j] {
+                let float_val = wei_to_float(balance, quote_decimals);
+                emit_event(EngineEvent::BalanceUpdate {
+                    wallet: format!("{:?}", wallet),
+                    token: format!("{:?}", quote_addr),
+                    wei: balance.to_string(),
+                    float_val,
+                    symbol: symbol.clone()
+                });
             }
         }
     }
     
-    if !pool_targets.is_empty() {
-        let quote_price = {
-            let s = CORE_STATE.read().unwrap();
-            get_quote_price_usd(&s.quote_symbol, &s.usd_prices)
-        };
-        let t_dec = get_decimals_cached(token).await;
-        let q_dec = get_decimals_cached(quote).await;
-        let (t0, _) = if token < quote { (token, quote) } else { (quote, token) };
-        let t0_is_quote = t0 == quote;
-        let mut candidates = Vec::new();
+    let plans: Vec<(Address, Address, Vec<H160>, Vec<H160>)> = {
+        let s = CORE_STATE.read().unwrap();
+        tokens.iter()
+            .filter_map(|t| s.tokens.get(t).map(|c| (*t, c.quote, c.route_pools.clone(), c.pool_targets.clone())))
+            .collect()
+    };
+    let all_pools: Vec<Address> = plans.iter()
+        .flat_map(|(_, _, route, targets)| if route.is_empty() { targets.iter() } else { route.iter() })
+        .copied()
+        .collect::<std::collections::HashSet<_>>()
+        .into_iter().collect();
+    // symbol/name прогреваем параллельно с пулами - PoolDetected возьмёт их из кэша
+    let (reads, _) = tokio::join!(
+        batch_pool_reads(&url, &all_pools),
+        futures::future::join_all(tokens.iter().map(|&t| token_metadata::token_info(t)))
+    );
+
+    for (token, quote, route_pools, pool_targets) in plans {
+        if !route_pools.is_empty() {
+            prefetch_route(&reads, &route_pools, token, quote).await;
+        } else if !pool_targets.is_empty() {
+            prefetch_token_pools(&reads, &pool_targets, token, quote).await;
+        }
+    }
+    
+    let elapsed = start.elapsed();
+    emit_log("SUCCESS", format!("⚡ HTTP Prefetch завершен за {}ms", elapsed.as_millis()));
+}
+
+/// Загружает состояние пулов-кандидатов токена, ранжирует их и выбирает лучший (или закреплённый вручную)
+async fn prefetch_token_pools(reads: &HashMap<Address, PoolRead>, pool_targets: &[H160], token: Address, quote: Address) {
+    let quote_price = {
+        let s = CORE_STATE.read().unwrap();
+        quote_price_in(&s, quote)
+    };
+    let t_dec = get_decimals_cached(token).await;
+    let q_dec = get_decimals_cached(quote).await;
+    let (t0, _) = if token < quote { (token, quote) } else { (quote, token) };
+    let t0_is_quote = t0 == quote;
+    let mut candidates = Vec::new();
+
+    for &addr in pool_targets {
+        let read = reads.get(&addr).copied().unwrap_or_default();
 
-        for &addr in &pool_targets {
-            let contract = UniversalABI::new(addr, provider.clone());
-
-            // V2
-            if let Ok((r0, r1, _)) = contract.get_reserves().call().await {
-                let (d0, d1) = if t0_is_quote { (q_dec, t_dec) } else { (t_dec, q_dec) };
-                let (liq, prc) = calculate_v2_liquidity_usd_and_price(r0.into(), r1.into(), d0, d1, t0_is_quote, quote_price);
-                CORE_STATE.write().unwrap().v2_reserves.insert(addr, (r0.into(), r1.into()));
-                candidates.push(PoolCandidate { 
-                    address: addr, pool_type: "V2".into(), liquidity_usd: liq, fee_bps: 30, 
-                    sqrt_price_x96: None, tick: None, reserves: Some((r0.into(), r1.into())), 
-                    score: 0.0, spot_price: prc 
+        // V2
+        if let Some((r0, r1)) = read.reserves {
+            let (liq, prc) = math::v2_liquidity_usd_and_price(r0.into(), r1.into(), t_dec, q_dec, t0_is_quote, quote_price);
+            let fee_bps = {
+                let mut s = CORE_STATE.write().unwrap();
+                s.v2_reserves.insert(addr, (r0.into(), r1.into()));
+                let quote_reserve = if t0_is_quote { wei_to_float(r0.into(), q_dec) } else { wei_to_float(r1.into(), q_dec) };
+                s.liquidity_baselines.insert(addr, LiquidityBaseline {
+                    baseline_quote: quote_reserve, last_quote: quote_reserve, ..Default::default()
                 });
+                s.v2_pool_fee_bps
+            };
+            candidates.push(PoolCandidate { 
+                address: addr, pool_type: "V2".into(), liquidity_usd: liq, fee_bps, 
+                sqrt_price_x96: None, tick: None, reserves: Some((r0.into(), r1.into())), 
+                score: 0.0, spot_price: prc, impact_pct: None 
+            });
+            continue;
+        }
+        
+        /* V3 
+        ** NOTE **: Ликвидность для простоты в этом типе пулов берется из первого активного слота - там где цена 
+        иначе пришлось бы городить дополнительные апи запросы и усложнять алгоритм.
+        Данная реализация - компромис между точностью и производительностью. 
+
+        Этого достаточно для:
+          1. сравнения пулов (какой ликвиднее) и выбора лучшего из них
+          2. оценки примерного price impact небольших сделок
+
+        Если сделка больше 1% ликвидности диапазона, impact считается точно по окну тиков (v3_math).
+
+        Для чего НЕ достаточно:
+          - Показывать точный TVL как на DexScreener к примеру */
+        if let Some((sqrt_p, tick)) = read.slot0 {
+            let liq_raw = read.liquidity.unwrap_or(0);
+            let fee = { CORE_STATE.read().unwrap().v3_states.get(&addr).map(|s| s.pool_fee).unwrap_or(2500) };
+            let (liq, prc) = math::v3_liquidity_usd_and_price(sqrt_p, liq_raw, t_dec, q_dec, t0_is_quote, quote_price);
+            let pool_state = V3PoolState { 
+                liquidity: liq_raw.into(), sqrt_price_x96: sqrt_p, tick, pool_fee: fee 
+            };
+            CORE_STATE.write().unwrap().v3_states.insert(addr, pool_state.clone());
+
+            let mut impact_pct = None;
+            if quote_price > 0.0 && DEFAULT_TRADE_USD > liq * v3_math::LOCAL_IMPACT_MIN_SHARE {
+                v3_math::refresh_tick_window(addr).await;
+                let amount_in = DEFAULT_TRADE_USD / quote_price * 10f64.powi(q_dec as i32);
+                let window = { CORE_STATE.read().unwrap().v3_tick_windows.get(&addr).cloned() };
+                impact_pct = window
+                    .and_then(|w| v3_math::simulate_exact_input(&pool_state, &w, amount_in, t0_is_quote))
+                    .map(|out| v3_math::impact_pct(&pool_state, amount_in, out, t0_is_quote));
+            }
+
+            candidates.push(PoolCandidate { 
+                address: addr, pool_type: "V3".into(), liquidity_usd: liq, fee_bps: fee, 
+                sqrt_price_x96: Some(sqrt_p), tick: Some(tick), reserves: None, 
+                score: 0.0, spot_price: prc, impact_pct 
+            });
+        } else {
+            emit_log("WARNING", format!("⚠️ Пул {:?} не V2 и не V3", addr));
+        }
+    }
+    
+    emit_log("DEBUG", format!("📊 candidates: {}", candidates.len()));
+
+    let ranked = to_ranked(rank_pools(candidates, DEFAULT_TRADE_USD));
+
+    emit_event(EngineEvent::PoolCandidates {
+        token: format!("{:?}", token),
+        quote: format!("{:?}", quote),
+        pools: ranked.iter().map(|p| PoolCandidateInfo {
+            address: format!("{:?}", p.address),
+            pool_type: p.pool_type.clone(),
+            fee: p.fee,
+            liquidity_usd: p.liquidity_usd,
+            spot_price: p.spot_price,
+            score: p.score,
+        }).collect()
+    });
+
+    // Ручной выбор пользователя переживает переподключения, пока пул остаётся среди кандидатов
+    let chosen = {
+        let mut s = CORE_STATE.write().unwrap();
+        // Подписку могли снять, пока шёл prefetch
+        let Some(ctx) = s.tokens.get_mut(&token) else { return };
+        ctx.ranked_pools = ranked.clone();
+        let pinned = ctx.pinned_pool.and_then(|pin| ranked.iter().find(|p| p.address == pin).cloned());
+        if let (None, Some(pin)) = (&pinned, ctx.pinned_pool) {
+            emit_log("WARNING", format!("📌 Выбранный вручную пул {:?} выпал из кандидатов, используем автоматический выбор", pin));
+        }
+        pinned.or_else(|| ranked.first().cloned())
+    };
+
+    if let Some(best) = chosen {
+        apply_pool_selection(best, token, quote).await;
+    }
+}
+
+/// Делает пул выбранным для торговли и сообщает об этом в UI
+pub async fn apply_pool_selection(pool: RankedPool, token: Address, quote: Address) {
+    let needs_ticks = {
+        let mut s = CORE_STATE.write().unwrap();
+        let Some(ctx) = s.tokens.get_mut(&token) else { return };
+        ctx.pool_address = Some(pool.address);
+        ctx.pool_type = Some(pool.pool_type.clone());
+        ctx.pool_fee = pool.fee;
+        ctx.liquidity_usd = pool.liquidity_usd;
+        ctx.spot_price = pool.spot_price;
+        // Пул выбран - ждать первой ликвидности в новых пулах фабрики больше незачем
+        ctx.created_pools.clear();
+        pool.pool_type == "V3" && !s.v3_tick_windows.contains_key(&pool.address)
+    };
+    // Окно тиков выбранного пула нужно CalcImpact для локального расчёта
+    if needs_ticks {
+        crate::state::spawn(v3_math::refresh_tick_window(pool.address));
+    }
+    // Получаем информацию о токене
+    let (token_symbol, token_name) = token_metadata::token_info(token).await;
+
+    emit_event(EngineEvent::PoolDetected { 
+        pool_type: pool.pool_type.clone(), 
+        address: format!("{:?}", pool.address), 
+        token: format!("{:?}", token), 
+        quote: format!("{:?}", quote),
+        quote_symbol: quote_symbol_for(quote),
+        liquidity_usd: pool.liquidity_usd, 
+        fee: pool.fee,
+        spot_price: pool.spot_price,
+        token_symbol,
+        token_name,
+        route: vec![],
+        via: None
+    });
+    emit_log("DEBUG", format!(" Лучший пул: {:?}, тип: {}, Liq.: {} $, ", pool.address, pool.pool_type, pool.liquidity_usd));
+    // Первая ликвидность пары - взведённый снайп срабатывает
+    snipe::on_liquidity(token, pool.liquidity_usd);
+}
+
+/// Обновляет резервы плеч маршрута, цену/ликвидность выбранного пула и сообщает маршрут в UI
+async fn prefetch_route(reads: &HashMap<Address, PoolRead>, route_pools: &[H160], token: Address, quote: Address) {
+    let is_v3 = { CORE_STATE.read().unwrap().tokens.get(&token).is_some_and(|c| !c.route_fees.is_empty()) };
+    for &pool in route_pools {
+        let read = reads.get(&pool).copied().unwrap_or_default();
+        if is_v3 {
+            if let Some((sqrt_p, tick)) = read.slot0 {
+                let liq_raw = read.liquidity.unwrap_or(0);
+                if let Some(state) = CORE_STATE.write().unwrap().v3_states.get_mut(&pool) {
+                    state.sqrt_price_x96 = sqrt_p;
+                    state.liquidity = liq_raw.into();
+                    state.tick = tick;
+                }
+            }
+        } else if let Some((r0, r1)) = read.reserves {
+            CORE_STATE.write().unwrap().v2_reserves.insert(pool, (r0.into(), r1.into()));
+        }
+    }
+
+    let (pool_type, fee, liquidity_usd, spot_price, route, via) = {
+        let mut s = CORE_STATE.write().unwrap();
+        let Some(ctx) = s.tokens.get(&token) else { return };
+        let quote_price = quote_price_in(&s, quote);
+        let (liq, price) = calculate_route_liquidity_usd_and_price(&s, &ctx.route, &ctx.route_pools, quote_price);
+        let via = ctx.route.get(1)
+            .and_then(|mid| s.quote_tokens.iter().find(|(_, a)| *a == mid).map(|(sym, _)| sym.clone()));
+        let route: Vec<String> = ctx.route.iter().map(|a| format!("{:?}", a)).collect();
+        let (pool_type, fee) = (ctx.pool_type.clone().unwrap_or_default(), ctx.pool_fee);
+        if let Some(ctx) = s.tokens.get_mut(&token) {
+            ctx.liquidity_usd = liq;
+            ctx.spot_price = price;
+        }
+        (pool_type, fee, liq, price, route, via)
+    };
+
+    let (token_symbol, token_name) = token_metadata::token_info(token).await;
+    emit_event(EngineEvent::PoolDetected {
+        pool_type,
+        address: format!("{:?}", route_pools[0]),
+        token: format!("{:?}", token),
+        quote: format!("{:?}", quote),
+        quote_symbol: quote_symbol_for(quote),
+        liquidity_usd,
+        fee,
+        spot_price,
+        token_symbol,
+        token_name,
+        route,
+        via
+    });
+}
+
+/// Снять tx с учёта pending: запись нужна для расчёта подтверждения
+fn take_pending_tx(tx_hash: &H256) -> PendingTx {
+    CORE_STATE.write().unwrap().pending_txs.remove(tx_hash).unwrap_or_default()
+}
+
+/// Подтверждённая (или dry-run) tx: газ на позицию, позиция по свапу, проверка sandwich и TxConfirmed.
+/// receipt None - dry-run: успех в текущем блоке, газ - оценка/лимит из плана. Комиссия - по effectiveGasPrice receipt,
+/// без него (старые ноды, dry-run) - по цене газа, с которой tx отправлена
+fn settle_pending_tx(tx_hash: H256, pending: PendingTx, receipt: Option<&TransactionReceipt>) {
+    let simulated = receipt.is_none();
+    let success = receipt.is_none_or(|r| r.status.unwrap_or(U64::zero()) == U64::from(1));
+    let logs: &[Log] = receipt.map_or(&[], |r| &r.logs);
+    let status = if success { "success" } else { "failed" };
+    let label = execution::tx_hash_label(&tx_hash);
+    emit_log("INFO", format!("✅ TX подтверждена: {} (статус: {})", label, status));
+
+    let (block_num, gas_used, price, fee, native_usd) = {
+        let mut s = CORE_STATE.write().unwrap();
+        let block_num = receipt.map_or(s.head_block, |r| r.block_number.unwrap_or(U64::zero()).as_u64());
+        let gas_used = receipt.and_then(|r| r.gas_used).map(|g| g.as_u64())
+            .or(pending.gas_estimated).or(pending.gas_limit).unwrap_or(0);
+        let price = receipt.and_then(|r| r.effective_gas_price).or(pending.gas_price).unwrap_or(s.gas_price);
+        let fee = U256::from(gas_used).saturating_mul(price);
+        // Газ упавшей tx тоже списан - начисляем позиции независимо от статуса
+        if let Some(position) = pending.position {
+            let total = s.position_gas.entry(position).or_default();
+            *total = total.saturating_add(fee);
+        }
+        let native_usd = fresh_quote_price_usd(&quote_symbol_in(&s, s.wrapped_native_address), &s);
+        (block_num, gas_used, price, fee, native_usd)
+    };
+    let fee_native_float = math::u256_to_f64_safe(fee, 18);
+    if success {
+        approvals::on_receipt_logs(logs);
+    }
+    if let (Some((wallet, token)), Some(swap), true) = (pending.position, &pending.swap, success) {
+        positions::on_swap_receipt(wallet, token, swap, logs, simulated);
+        if let Some(pool) = pending.sandwich_pool.filter(|_| !simulated) {
+            crate::state::spawn(sandwich::check_block(tx_hash, wallet, token, pool, logs.to_vec(), block_num));
+        }
+    }
+    REQUEST_ID.sync_scope(pending.request_id, || emit_event(EngineEvent::TxConfirmed {
+        tx_hash: label,
+        wallet: format!("{:?}", pending.wallet),
+        gas_used,
+        status: status.to_string(),
+        confirm_block: block_num,
+        timestamp_ms: current_timestamp_ms(),
+        gas_limit: pending.gas_limit,
+        gas_estimated: pending.gas_estimated,
+        effective_gas_price_gwei: math::u256_to_f64_safe(price, 9),
+        fee_wei: fee.to_string(),
+        fee_native_float,
+        fee_usd: native_usd.map(|p| fee_native_float * p)
+    }));
+}
+
+/// Период тикера из живого MonitorConfig: изменение через UpdateSettings действует со следующего тика
+fn retune(ticker: &mut tokio::time::Interval, period: Duration) {
+    if ticker.period() != period {
+        *ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    }
+}
+
+/// Опрос receipt отправленных tx через HTTP пул - не зависит от WSS: подтверждения идут и во время его обрыва.
+/// Все хеши, которым пора (PendingTx::receipt_poll_due), - одним JSON-RPC батчем
+pub async fn receipt_poller() {
+    let mut check_interval = interval(CORE_STATE.read().unwrap().monitor.receipt_poll);
+    loop {
+        retune(&mut check_interval, CORE_STATE.read().unwrap().monitor.receipt_poll);
+        check_interval.tick().await;
+        if SHUTDOWN_FLAG.load(std::sync::atomic::Ordering::Relaxed) { break; }
+
+        // Не больше MAX_RECEIPT_CHECKS_PER_TICK хешей за тик: давно не проверенные первыми
+        let due: Vec<H256> = {
+            let now = std::time::Instant::now();
+            let mut s = CORE_STATE.write().unwrap();
+            let mut due: Vec<(Option<std::time::Instant>, H256)> = s.pending_txs.iter()
+                .filter(|(_, p)| p.receipt_poll_due(now))
+                .map(|(h, p)| (p.last_checked, *h))
+                .collect();
+            due.sort_unstable_by_key(|(last, _)| *last);
+            due.truncate(MAX_RECEIPT_CHECKS_PER_TICK);
+            for (_, h) in &due {
+                if let Some(p) = s.pending_txs.get_mut(h) { p.last_checked = Some(now); }
+            }
+            due.into_iter().map(|(_, h)| h).collect()
+        };
+
+        let mut hashes = Vec::with_capacity(due.len());
+        for tx_hash in due {
+            // Dry-run tx в сети нет: "подтверждаем" её следующим блоком по плану газа
+            let Some((_, sent_block)) = execution::dry_run_tx(&tx_hash) else {
+                hashes.push(tx_hash);
                 continue;
+            };
+            let head = { CORE_STATE.read().unwrap().head_block };
+            if head > sent_block {
+                settle_pending_tx(tx_hash, take_pending_tx(&tx_hash), None);
+                execution::forget_dry_run_tx(&tx_hash);
             }
-            
-            /* V3 
-            ** NOTE **: Ликвидность для простоты в этом типе пулов берется из первого активного слота - там где цена 
-            иначе пришлось бы городить дополнительные апи запросы и усложнять алгоритм.
-            Данная реализация - компромис между точностью и производительностью. 
-
-            Этого достаточно для:
-              1. сравнения пулов (какой ликвиднее) и выбора лучшего из них
-              2. оценки примерного price impact
-
-            Для чего НЕ достаточно:
-              - Показывать точный TVL как на DexScreener к примеру */
-            if let Ok((sqrt_p, tick, _, _, _, _, _)) = contract.slot_0().call().await {
-                let liq_raw = contract.liquidity().call().await.unwrap_or(0);
-                let fee = { CORE_STATE.read().unwrap().v3_states.get(&addr).map(|s| s.pool_fee).unwrap_or(2500) };
-                let (d0, d1) = if t0_is_quote { (q_dec, t_dec) } else { (t_dec, q_dec) };
-                let (liq, prc) = calculate_v3_liquidity_usd_and_price(sqrt_p, liq_raw, d0, d1, t0_is_quote, quote_price);
-                CORE_STATE.write().unwrap().v3_states.insert(addr, V3PoolState { 
-                    liquidity: liq_raw.into(), sqrt_price_x96: sqrt_p, tick, pool_fee: fee 
-                });
-                candidates.push(PoolCandidate { 
-                    address: addr, pool_type: "V3".into(), liquidity_usd: liq, fee_bps: fee, 
-                    sqrt_price_x96: Some(sqrt_p), tick: Some(tick), reserves: None, 
-                    score: 0.0, spot_price: prc 
-                });
-            } else {
-                emit_log("WARNING", format!("⚠️ Пул {:?} не V2 и не V3", addr));
+        }
+        if hashes.is_empty() { continue; }
+
+        let url_opt = { RPC_POOL.read().unwrap().get_fastest_node() };
+        let Some(url) = url_opt else { continue; };
+
+        let calls: Vec<BatchCall> = hashes.iter().map(|&h| BatchCall::Receipt(h)).collect();
+        let results = rpc_batch::batch(&url, &calls).await;
+        let mut unconfirmed = Vec::new();
+        for (tx_hash, res) in hashes.into_iter().zip(&results) {
+            match rpc_batch::as_object::<TransactionReceipt>(res) {
+                Ok(Some(receipt)) => settle_pending_tx(tx_hash, take_pending_tx(&tx_hash), Some(&receipt)),
+                Ok(None) => unconfirmed.push(tx_hash),
+                Err(e) => emit_log("WARNING", format!("Ошибка проверки receipt {:?}: {}", tx_hash, e)),
             }
         }
-        
-        emit_log("DEBUG", format!("📊 candidates: {}", candidates.len()));
+        check_stale_pending(&url, &unconfirmed).await;
+    }
+}
+
+/// Tx без receipt дольше pending_tx_timeout_secs: нода её не знает - TxDropped и снятие с учёта,
+/// ещё в мемпуле - TxStuck (не чаще раза за таймаут)
+async fn check_stale_pending(url: &str, unconfirmed: &[H256]) {
+    let stale: Vec<(H256, Address, Duration, Option<String>)> = {
+        let s = CORE_STATE.read().unwrap();
+        let timeout = Duration::from_secs(s.pending_tx_timeout_secs);
+        unconfirmed.iter().filter_map(|h| {
+            let p = s.pending_txs.get(h)?;
+            let age = p.inserted_at.elapsed();
+            if age < timeout || p.stuck_reported_at.is_some_and(|t| t.elapsed() < timeout) { return None; }
+            Some((*h, p.wallet, age, p.request_id.clone()))
+        }).collect()
+    };
+    if stale.is_empty() { return; }
+
+    let calls: Vec<BatchCall> = stale.iter().map(|(h, ..)| BatchCall::Transaction(*h)).collect();
+    let results = rpc_batch::batch(url, &calls).await;
+    for ((tx_hash, wallet, age, request_id), res) in stale.into_iter().zip(&results) {
+        let label = execution::tx_hash_label(&tx_hash);
+        match rpc_batch::as_object::<Transaction>(res) {
+            Ok(None) => {
+                if CORE_STATE.write().unwrap().pending_txs.remove(&tx_hash).is_none() { continue; }
+                emit_log("WARNING", format!("🕳️ TX {} пропала из мемпула через {}с - снята с учёта", label, age.as_secs()));
+                REQUEST_ID.sync_scope(request_id, || emit_event(EngineEvent::TxDropped {
+                    tx_hash: label,
+                    wallet: format!("{:?}", wallet),
+                    age_secs: age.as_secs()
+                }));
+                // nonce выпавшей tx свободен: следующие сделки кошелька не должны упереться в дыру
+                if !wallet.is_zero() { execution::resync_nonce(wallet).await; }
+            }
+            Ok(Some(tx)) if tx.block_number.is_none() => {
+                if let Some(p) = CORE_STATE.write().unwrap().pending_txs.get_mut(&tx_hash) {
+                    p.stuck_reported_at = Some(std::time::Instant::now());
+                }
+                let gas_price = tx.max_fee_per_gas.or(tx.gas_price).unwrap_or_default();
+                emit_log("WARNING", format!("🐌 TX {} висит в мемпуле {}с (nonce {}) - стоит ускорить", label, age.as_secs(), tx.nonce));
+                REQUEST_ID.sync_scope(request_id, || emit_event(EngineEvent::TxStuck {
+                    tx_hash: label,
+                    wallet: format!("{:?}", tx.from),
+                    nonce: tx.nonce.as_u64(),
+                    age_secs: age.as_secs(),
+                    gas_price_gwei: execution::u256_to_f64_safe(gas_price, 9)
+                }));
+            }
+            // Уже в блоке - receipt догонит следующим опросом
+            Ok(Some(_)) => {}
+            Err(e) => emit_log("WARNING", format!("Ошибка проверки pending tx {}: {}", label, e)),
+        }
+    }
+}
+
+/// Источник газа для кошелька ниже порога
+enum FuelJob {
+    Swap { key: WalletKey, router: Address, quote: Address },
+    Treasury { key: WalletKey, reserve: U256 },
+}
+
+/// Новый блок - из WS подписки или HTTP опроса: head, газ и оракул, native балансы и авто-заправка
+async fn on_new_block<M: Middleware>(client: &M, number: Option<U64>, blocks_seen: u64, fee_history_misses: &mut u32, wallets: &[Address]) {
+    if let Some(n) = number {
+        CORE_STATE.write().unwrap().head_block = n.as_u64();
+    }
+    if blocks_seen.is_multiple_of(RESCORE_EVERY_BLOCKS) {
+        crate::state::spawn(rescore_pools());
+    }
+
+    if let Ok(gas) = client.get_gas_price().await {
+        let quirks = CORE_STATE.read().unwrap().chain_quirks.clone();
+        let oracle = if *fee_history_misses < FEE_HISTORY_MAX_MISSES && quirks.probe_fee_history() {
+            let oracle = fetch_gas_oracle(client).await;
+            *fee_history_misses = if oracle.is_some() { 0 } else { *fee_history_misses + 1 };
+            oracle.map(|o| quirks.network_oracle(o))
+        } else { None };
+        let gas = quirks.network_gas(gas);
+        {
+            let mut s = CORE_STATE.write().unwrap();
+            s.gas_price = gas;
+            s.gas_oracle = oracle.clone();
+        }
+        emit_event(EngineEvent::GasPriceUpdate {
+            gas_price_gwei: gas.as_u64() as f64 / 1e9,
+            oracle: oracle.map(|o| GasOracleInfo {
+                base_fee_gwei: o.base_fee_gwei,
+                base_fee_trend_pct: o.base_fee_trend_pct,
+                priority_p25_gwei: o.priority_gwei[0],
+                priority_p50_gwei: o.priority_gwei[1],
+                priority_p75_gwei: o.priority_gwei[2],
+                priority_p95_gwei: o.priority_gwei[3],
+                slow_gwei: o.slow_gwei,
+                standard_gwei: o.standard_gwei,
+                fast_gwei: o.fast_gwei,
+            })
+        });
+    }
 
-        if let Some(best) = select_best_pool(candidates, DEFAULT_TRADE_USD) {
-            {
+    for &wallet in wallets {
+        if let Ok(balance) = client.get_balance(wallet, None).await {
+            let float_val = wei_to_float(balance, 18);
+
+            let (fuel_job, exhausted) = {
                 let mut s = CORE_STATE.write().unwrap();
-                s.selected_pool_address = Some(best.address);
-                s.selected_pool_type = Some(best.pool_type.clone());
-                s.selected_pool_fee = best.fee_bps;
-                s.selected_pool_liquidity_usd = best.liquidity_usd;
-                s.selected_pool_spot_price = best.spot_price;
-            }
-            // Получаем информацию о токене
-            let (token_symbol, token_name) = execution::get_token_info(token).await;
-
-            let pool_type = best.pool_type.clone();
-            let pool_address = best.address;
-            let liquidity_usd = best.liquidity_usd;
-
-            emit_event(EngineEvent::PoolDetected { 
-                pool_type: best.pool_type, 
-                address: format!("{:?}", best.address), 
-                token: format!("{:?}", token), 
-                quote: format!("{:?}", quote),
-                liquidity_usd: best.liquidity_usd, 
-                fee: best.fee_bps,
-                spot_price: best.spot_price,
-                token_symbol,
-                token_name
+                let source_ready = match s.fuel_mode {
+                    FuelMode::Swap => s.fuel_quote_address != Address::zero() && s.wallet_keys.contains_key(&wallet),
+                    // Казна себя не пополняет
+                    FuelMode::Treasury => wallet != s.fuel_treasury && s.wallet_keys.contains_key(&s.fuel_treasury),
+                };
+                if !s.fuel_enabled || !source_ready {
+                    (None, None)
+                } else if balance >= s.fuel_threshold {
+                    // Пополнен извне (или прошлым auto-fuel) - попытки и карантин сначала
+                    s.auto_fuel_attempts.remove(&wallet);
+                    (None, None)
+                } else {
+                    let limits = s.fuel_limits;
+                    match s.auto_fuel_attempts.entry(wallet).or_default().gate(&limits, current_timestamp_ms()) {
+                        FuelGate::Attempt => {
+                            let job = match s.fuel_mode {
+                                FuelMode::Swap => s.wallet_keys.get(&wallet).map(|key| FuelJob::Swap { key: key.clone(), router: s.router_address, quote: s.fuel_quote_address }),
+                                FuelMode::Treasury => s.wallet_keys.get(&s.fuel_treasury).map(|key| FuelJob::Treasury { key: key.clone(), reserve: s.fuel_treasury_reserve }),
+                            };
+                            (job.map(|job| (job, s.fuel_amount, s.chain_id)), None)
+                        }
+                        FuelGate::Wait => (None, None),
+                        FuelGate::Exhausted => (None, Some(limits)),
+                    }
+                }
+            };
+
+            if let Some(limits) = exhausted {
+                execution::auto_fuel_error(wallet, format!(
+                    "attempts exhausted ({} failed), quarantined for {} minutes", limits.max_attempts, limits.quarantine.as_secs().div_ceil(60)
+                ));
+            }
+
+            if let Some((job, amount, chain_id)) = fuel_job {
+                let fueled = match job {
+                    FuelJob::Swap { key, router, quote } => execution::run_auto_fuel(key, wallet, router, quote, amount, chain_id).await,
+                    FuelJob::Treasury { key, reserve } => execution::run_treasury_fuel(key, wallet, amount, reserve, chain_id).await,
+                };
+                // При успехе счётчик сбрасывается
+                if fueled {
+                    CORE_STATE.write().unwrap().auto_fuel_attempts.remove(&wallet);
+                }
+            }
+
+            emit_event(EngineEvent::BalanceUpdate {
+                wallet: format!("{:?}", wallet),
+                token: "0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee".into(),
+                wei: balance.to_string(),
+                float_val,
+                symbol: "NATIVE".into()
             });
-            emit_log("DEBUG", format!(" Лучший пул: {:?}, тип: {}, Liq.: {} $, ", pool_address, pool_type, liquidity_usd));
         }
     }
-    
-    let elapsed = start.elapsed();
-    emit_log("SUCCESS", format!("⚡ HTTP Prefetch завершен за {}ms", elapsed.as_millis()));
 }
 
 // ===================== WEBSOCKET MANAGER =====================
@@ -263,6 +875,9 @@ enum DisconnectReason {
     StreamEnded(String),
     Error(String),
     IdleTimeout,
+    NewPool(Address, H160), // (token, pool)
+    SubscriptionsChanged,
+    WssAvailable, // проба WSS из режима HTTP polling успешна
     Shutdown,
 }
 
@@ -276,15 +891,15 @@ impl WebSocketManager {
         Self { wss_url, provider: None }
     }
 
-    pub async fn run_forever(
-        &mut self,
-        wallets: Vec<Address>,
-        quote_token: Address,
-        pool_targets: Vec<Address>,
-        token: Address,
-        quote: Address
-    ) {
+    /// Набор подписок читается из CORE_STATE на каждой итерации, поэтому добавление/снятие токена
+    /// сводится к переподписке (SUBSCRIPTIONS_CHANGED).
+    /// Без wss_url или после WSS_FAILURES_BEFORE_POLLING неудач подряд - HTTP опрос (run_polling) до успешной пробы WSS
+    pub async fn run_forever(&mut self) {
         let mut attempt = 0u32;
+        // Неудачи WSS подряд: ошибка подключения или сессия, оборвавшаяся раньше WSS_STABLE_SESSION_SECS
+        let mut ws_failures = 0u32;
+        // Токены, для которых нужен prefetch перед подпиской (None - все)
+        let mut prefetch: Option<Vec<Address>> = None;
         
         loop {
             if SHUTDOWN_FLAG.load(std::sync::atomic::Ordering::Relaxed) { 
@@ -292,78 +907,139 @@ impl WebSocketManager {
                 break; 
             }
             
-            attempt += 1;
-            emit_log("INFO", format!("🔌 WebSocket: Попытка подключения #{} к {}", attempt, self.wss_url));
-            emit_event(EngineEvent::ConnectionStatus {
-                connected: false,
-                message: format!("Подключение... (попытка #{})", attempt)
-            });
+            let wallets: Vec<Address> = TRACKED_WALLETS.read().unwrap().clone();
+            let tokens = prefetch.take().unwrap_or_else(|| CORE_STATE.read().unwrap().tokens.keys().copied().collect());
+            let polling = self.wss_url.is_empty() || ws_failures >= WSS_FAILURES_BEFORE_POLLING;
+            
+            if !polling {
+                attempt += 1;
+                emit_log("INFO", format!("🔌 WebSocket: Попытка подключения #{} к {}", attempt, self.wss_url));
+                emit_event(EngineEvent::ConnectionStatus {
+                    connected: false,
+                    message: format!("Подключение... (попытка #{})", attempt),
+                    polling: false,
+                });
+            }
             
             if let Some(http_provider) = self.get_http_provider().await {
+                if let Ok(head) = http_provider.get_block_number().await {
+                    block_time(http_provider.as_ref(), head.as_u64()).await;
+                }
+                // Бюджет prefetch растёт с числом токенов - каждый опрашивает свои пулы
+                let budget = PREFETCH_TIMEOUT_SECS * tokens.len().max(1) as u64;
                 timeout(
-                    Duration::from_secs(PREFETCH_TIMEOUT_SECS),
-                    prefetch_all_data(
-                        http_provider,
-                        wallets.clone(),
-                        Some(quote_token),
-                        pool_targets.clone(),
-                        token,
-                        quote
-                    )
+                    Duration::from_secs(budget),
+                    prefetch_all_data(http_provider, wallets.clone(), tokens)
                 ).await.ok();
             }
             
-            match Provider::<Ws>::connect(&self.wss_url).await {
-                Ok(ws_provider) => {
-                    let ws = Arc::new(ws_provider);
-                    self.provider = Some(ws.clone());
-                    
-                    attempt = 0;
-                    emit_log("SUCCESS", "🔌 WebSocket: Подключено успешно!".into());
-                    emit_event(EngineEvent::ConnectionStatus {
-                        connected: true,
-                        message: "WebSocket подключен".into()
-                    });
-                    
-                    let reason = self.run_subscriptions_and_wait(
-                        ws, 
-                        wallets.clone(), 
-                        quote_token, 
-                        pool_targets.clone(),
-                        token
-                    ).await;
-                    
-                    match &reason {
-                        DisconnectReason::StreamEnded(stream_name) => {
-                            emit_log("WARNING", format!("🔌 WebSocket: Поток '{}' завершён, переподключение...", stream_name));
-                        }
-                        DisconnectReason::Error(err) => {
-                            emit_log("ERROR", format!("🔌 WebSocket: Ошибка - {}, переподключение...", err));
-                        }
-                        DisconnectReason::IdleTimeout => {
-                            emit_log("WARNING", "🔌 WebSocket: Нет данных более 30 сек, переподключение...".into());
-                        }
-                        DisconnectReason::Shutdown => {
-                            emit_log("INFO", "🔌 WebSocket: Завершение работы".into());
-                            break;
-                        }
+            let reason = if polling {
+                Some(self.run_polling(wallets).await)
+            } else {
+                match Provider::<Ws>::connect(&self.wss_url).await {
+                    Ok(ws_provider) => {
+                        let ws = Arc::new(ws_provider);
+                        self.provider = Some(ws.clone());
+                        set_ws_provider(Some(ws.clone()));
+                        
+                        attempt = 0;
+                        emit_log("SUCCESS", "🔌 WebSocket: Подключено успешно!".into());
+                        emit_event(EngineEvent::ConnectionStatus {
+                            connected: true,
+                            message: "WebSocket подключен".into(),
+                            polling: false,
+                        });
+                        
+                        let started = Instant::now();
+                        let reason = self.run_subscriptions_and_wait(ws, wallets).await;
+                        // Соединение закрывается - read-only вызовы уходят на HTTP до переподключения
+                        set_ws_provider(None);
+                        // Нода принимает соединение, но рвёт подписки - тоже неудача WSS
+                        let failed = matches!(reason, DisconnectReason::StreamEnded(_) | DisconnectReason::Error(_) | DisconnectReason::IdleTimeout);
+                        ws_failures = if failed && started.elapsed() < Duration::from_secs(WSS_STABLE_SESSION_SECS) { ws_failures + 1 } else { 0 };
+                        Some(reason)
+                    }
+                    Err(e) => {
+                        ws_failures += 1;
+                        emit_log("ERROR", format!("🔌 WebSocket: Ошибка подключения - {:?}", e));
+                        emit_event(EngineEvent::ConnectionStatus {
+                            connected: false,
+                            message: format!("Ошибка подключения: {:?}", e),
+                            polling: false,
+                        });
+                        None
                     }
-                    
-                    emit_event(EngineEvent::ConnectionStatus {
-                        connected: false,
-                        message: format!("{:?}", reason)
-                    });
                 }
-                Err(e) => {
-                    emit_log("ERROR", format!("🔌 WebSocket: Ошибка подключения - {:?}", e));
-                    emit_event(EngineEvent::ConnectionStatus {
-                        connected: false,
-                        message: format!("Ошибка подключения: {:?}", e)
-                    });
+            };
+            
+            if let Some(reason) = reason {
+                match &reason {
+                    DisconnectReason::StreamEnded(stream_name) => {
+                        emit_log("WARNING", format!("🔌 WebSocket: Поток '{}' завершён, переподключение...", stream_name));
+                    }
+                    DisconnectReason::Error(err) => {
+                        emit_log("ERROR", format!("🔌 WebSocket: Ошибка - {}, переподключение...", err));
+                    }
+                    DisconnectReason::IdleTimeout => {
+                        emit_log("WARNING", "🔌 WebSocket: Нет блоков дольше idle timeout, переподключение...".into());
+                    }
+                    DisconnectReason::NewPool(token, pool) => {
+                        // Новый пул - переоцениваем кандидатов токена через prefetch и переподписываемся без задержки
+                        emit_log("SUCCESS", format!("🆕 Новый пул {:?} для {:?}, переоценка кандидатов...", pool, token));
+                        {
+                            let mut s = CORE_STATE.write().unwrap();
+                            if let Some(ctx) = s.tokens.get_mut(token) {
+                                if !ctx.pool_targets.contains(pool) {
+                                    ctx.pool_targets.push(*pool);
+                                }
+                                // Появился прямой пул - маршрут через промежуточный quote больше не нужен
+                                let route_pools = std::mem::take(&mut ctx.route_pools);
+                                ctx.route.clear();
+                                ctx.route_fees.clear();
+                                ctx.pool_targets.retain(|p| !route_pools.contains(p));
+                            }
+                        }
+                        prefetch = Some(vec![*token]);
+                        continue;
+                    }
+                    DisconnectReason::SubscriptionsChanged => {
+                        // Prefetch только для токенов, у которых ещё не выбран пул (новые подписки)
+                        emit_log("INFO", "🔌 WebSocket: Набор токенов или кошельков изменился, переподписка...".into());
+                        prefetch = Some({
+                            let s = CORE_STATE.read().unwrap();
+                            s.tokens.iter().filter(|(_, c)| c.pool_address.is_none()).map(|(t, _)| *t).collect()
+                        });
+                        continue;
+                    }
+                    DisconnectReason::WssAvailable => {
+                        // Состояние свежее после опроса - подключаемся сразу, prefetch пулов не нужен
+                        emit_log("SUCCESS", "🔌 WebSocket: Проба WSS успешна, выход из режима HTTP polling".into());
+                        ws_failures = 0;
+                        attempt = 0;
+                        prefetch = Some(Vec::new());
+                        continue;
+                    }
+                    DisconnectReason::Shutdown => {
+                        emit_log("INFO", "🔌 WebSocket: Завершение работы".into());
+                        break;
+                    }
                 }
+                
+                emit_event(EngineEvent::ConnectionStatus {
+                    connected: false,
+                    message: format!("{:?}", reason),
+                    polling: false,
+                });
             }
             
-            let delay = std::cmp::min(RECONNECT_DELAY_SECS * (1 + attempt / 3) as u64, 30);
+            crate::metrics::inc(&crate::metrics::METRICS.ws_reconnects);
+            if ws_failures == WSS_FAILURES_BEFORE_POLLING && !self.wss_url.is_empty() {
+                // Сразу в HTTP polling, без задержки переподключения
+                emit_log("WARNING", format!("🔌 WebSocket: {} неудач подряд, переход в режим HTTP polling", ws_failures));
+                continue;
+            }
+            let base = CORE_STATE.read().unwrap().monitor.reconnect_delay.as_secs();
+            let delay = std::cmp::min(base * (1 + attempt / 3) as u64, RECONNECT_MAX_DELAY_SECS.max(base));
             emit_log("INFO", format!("🔌 WebSocket: Повторное подключение через {} сек...", delay));
             sleep(Duration::from_secs(delay)).await;
         }
@@ -372,8 +1048,7 @@ impl WebSocketManager {
     async fn get_http_provider(&self) -> Option<Arc<Provider<Http>>> {
         let urls = { RPC_POOL.read().unwrap().get_fastest_pool(3) };
         for url_str in urls {
-            if let Ok(url) = Url::parse(&url_str) {
-                let provider = Arc::new(Provider::new(Http::new_with_client(url, GLOBAL_HTTP_CLIENT.clone())));
+            if let Some(provider) = http_provider(&url_str) {
                 if timeout(Duration::from_secs(2), provider.get_block_number()).await.is_ok() {
                     return Some(provider);
                 }
@@ -382,86 +1057,140 @@ impl WebSocketManager {
         None
     }
 
+    /// Деградация без WSS: новые блоки через eth_blockNumber, логи пулов, Transfer и фабрик - через eth_getLogs
+    /// теми же обработчиками, что и у подписок. Интервал опроса - среднее время блока сети.
+    /// Раз в WSS_PROBE_INTERVAL_SECS пробуется WSS: ответил - выходим с WssAvailable
+    async fn run_polling(&self, wallets: Vec<Address>) -> DisconnectReason {
+        let subscriptions: Vec<(Address, TokenContext)> = {
+            CORE_STATE.read().unwrap().tokens.iter().map(|(t, c)| (*t, c.clone())).collect()
+        };
+        let owners = pool_owners(&subscriptions).await;
+        let pools_list: Vec<H160> = owners.keys().copied().collect();
+        let watch = WatchSnapshot::new(&subscriptions);
+        let factory_pairs: Vec<(Address, Address)> = subscriptions.iter().map(|(t, c)| (*t, c.quote)).collect();
+        let factory = factory_filter(&factory_pairs);
+        
+        emit_log("WARNING", "🐢 WSS недоступен - режим HTTP polling".into());
+        emit_event(EngineEvent::ConnectionStatus {
+            connected: false,
+            message: "HTTP polling: ожидание ноды".into(),
+            polling: true,
+        });
+        
+        let mut poll_every: Option<Duration> = None;
+        let mut last_block: Option<u64> = None;
+        let mut blocks_seen: u64 = 0;
+        let mut fee_history_misses: u32 = 0;
+        let mut next_probe = Instant::now() + Duration::from_secs(WSS_PROBE_INTERVAL_SECS);
+        
+        loop {
+            if SHUTDOWN_FLAG.load(std::sync::atomic::Ordering::Relaxed) {
+                return DisconnectReason::Shutdown;
+            }
+            if !self.wss_url.is_empty() && Instant::now() >= next_probe {
+                if probe_wss(&self.wss_url).await {
+                    return DisconnectReason::WssAvailable;
+                }
+                next_probe = Instant::now() + Duration::from_secs(WSS_PROBE_INTERVAL_SECS);
+            }
+            
+            if let Some(client) = fastest_http_provider() {
+                match client.get_block_number().await.map(|n| n.as_u64()) {
+                    Ok(head) if last_block.is_none_or(|b| head > b) => {
+                        if poll_every.is_none() {
+                            let every = block_time(client.as_ref(), head).await.unwrap_or(POLL_MAX_INTERVAL).clamp(POLL_MIN_INTERVAL, POLL_MAX_INTERVAL);
+                            poll_every = Some(every);
+                            emit_log("INFO", format!("🐢 HTTP polling: опрос каждые {:.1} сек", every.as_secs_f64()));
+                            emit_event(EngineEvent::ConnectionStatus {
+                                connected: true,
+                                message: format!("HTTP polling ({:.1} сек)", every.as_secs_f64()),
+                                polling: true,
+                            });
+                        }
+                        // Пропущенное за время простоя - не глубже POLL_MAX_LOG_RANGE блоков
+                        let from = last_block.map_or(head, |b| (b + 1).max(head.saturating_sub(POLL_MAX_LOG_RANGE - 1)));
+                        let range = |f: Filter| f.from_block(from).to_block(head);
+                        let transfers = transfer_watch_set();
+                        let (pool_logs, transfer_logs, factory_logs) = futures::join!(
+                            async { if pools_list.is_empty() { Ok(Vec::new()) } else { client.get_logs(&range(Filter::new().address(pools_list.clone()))).await } },
+                            async {
+                                if transfers.is_empty() { return Ok(Vec::new()); }
+                                client.get_logs(&range(Filter::new().event("Transfer(address,address,uint256)").address(transfers.clone()))).await
+                            },
+                            async { match &factory { Some(f) => client.get_logs(&range(f.clone())).await, None => Ok(Vec::new()) } },
+                        );
+                        // Ошибка любого фильтра - блоки не засчитываются, диапазон повторится на следующем опросе
+                        match (pool_logs, transfer_logs, factory_logs) {
+                            (Ok(pool_logs), Ok(transfer_logs), Ok(factory_logs)) => {
+                                for log in &pool_logs {
+                                    for w in owners.get(&log.address).into_iter().flatten() {
+                                        if let Some(reason) = on_pool_log(log, w) {
+                                            return reason;
+                                        }
+                                    }
+                                }
+                                for log in &transfer_logs {
+                                    on_transfer_log(&client, log, &wallets, &watch);
+                                }
+                                if let Some((token, pool)) = factory_logs.iter().find_map(|log| decode_factory_log(log, &factory_pairs)) {
+                                    return DisconnectReason::NewPool(token, pool);
+                                }
+                                blocks_seen += 1;
+                                on_new_block(client.as_ref(), Some(head.into()), blocks_seen, &mut fee_history_misses, &wallets).await;
+                                last_block = Some(head);
+                            }
+                            (p, t, f) => {
+                                let err = [p.err(), t.err(), f.err()].into_iter().flatten().next();
+                                emit_log("WARNING", format!("🐢 HTTP polling: eth_getLogs {}..{} - {:?}", from, head, err));
+                            }
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => emit_log("WARNING", format!("🐢 HTTP polling: eth_blockNumber - {:?}", e)),
+                }
+            }
+            
+            tokio::select! {
+                _ = SUBSCRIPTIONS_CHANGED.notified() => return DisconnectReason::SubscriptionsChanged,
+                _ = sleep(poll_every.unwrap_or(POLL_MAX_INTERVAL)) => {}
+            }
+        }
+    }
+
     async fn run_subscriptions_and_wait(
         &self,
         ws: Arc<Provider<Ws>>,
-        wallets: Vec<Address>,
-        quote_token: Address,
-        pool_targets: Vec<Address>,
-        target_token: Address
+        wallets: Vec<Address>
     ) -> DisconnectReason {
+        // Снимок подписок: (token, контекст) - фильтры строятся по нему до следующей переподписки
+        let subscriptions: Vec<(Address, TokenContext)> = {
+            CORE_STATE.read().unwrap().tokens.iter().map(|(t, c)| (*t, c.clone())).collect()
+        };
+        
         let ws_blocks = ws.clone();
         let wallets_blocks = wallets.clone();
-        let ws_balances = ws.clone();
         
-        let blocks_task = tokio::spawn(async move {
+        let blocks_task = crate::state::spawn(async move {
             match ws_blocks.subscribe_blocks().await {
                 Ok(mut block_stream) => {
                     emit_log("INFO", "📡 Подписка на блоки активна".into());
-                    let idle_timeout = Duration::from_secs(IDLE_TIMEOUT_SECS);
+                    let mut blocks_seen: u64 = 0;
+                    let mut fee_history_misses: u32 = 0;
                     
                     loop {
                         if SHUTDOWN_FLAG.load(std::sync::atomic::Ordering::Relaxed) {
                             return DisconnectReason::Shutdown;
                         }
                         
+                        // Idle timeout из живого конфига: UpdateSettings действует со следующего блока
+                        let idle_timeout = {
+                            let s = CORE_STATE.read().unwrap();
+                            s.monitor.effective_idle_timeout(s.block_time)
+                        };
                         match tokio::time::timeout(idle_timeout, block_stream.next()).await {
-                            Ok(Some(_block)) => {
-                                if let Ok(gas) = ws_blocks.get_gas_price().await {
-                                    CORE_STATE.write().unwrap().gas_price = gas;
-                                    emit_event(EngineEvent::GasPriceUpdate {
-                                        gas_price_gwei: gas.as_u64() as f64 / 1e9
-                                    });
-                                }
-                                
-                                for wallet in wallets_blocks.clone() {
-                                    if let Ok(balance) = ws_balances.get_balance(wallet, None).await {
-                                        let float_val = wei_to_float(balance, 18);
-                                        
-                                        let fuel_job = {
-                                            let s = CORE_STATE.read().unwrap();
-                                            if s.fuel_enabled && balance < s.fuel_threshold && s.fuel_quote_address != Address::zero() {
-                                                // Проверяем лимит попыток (максимум 5, не чаще раза в 60 сек)
-                                                let now = current_timestamp_ms();
-                                                let (attempts, last_ts) = s.auto_fuel_attempts.get(&wallet).unwrap_or(&(0, 0));
-                                                
-                                                if *attempts < 5 && (now - last_ts) > 60000 {
-                                                    s.wallet_keys.get(&wallet).map(|pk| {
-                                                        (pk.clone(), wallet, s.router_address, s.fuel_quote_address, s.fuel_amount, s.chain_id)
-                                                    })
-                                                } else {
-                                                    None
-                                                }
-                                            } else { None }
-                                        };
-                                        
-                                        if let Some((pk, w, r, q, a, cid)) = fuel_job { 
-                                            // Увеличиваем счётчик попыток перед вызовом
-                                            let new_count = {
-                                                let s = CORE_STATE.read().unwrap();
-                                                let (count, _) = s.auto_fuel_attempts.get(&w).unwrap_or(&(0, 0));
-                                                count + 1
-                                            };
-                                            CORE_STATE.write().unwrap().auto_fuel_attempts.insert(w, (new_count, current_timestamp_ms()));
-                                            
-                                            // Вызываем auto_fuel
-                                            let success = execution::run_auto_fuel(pk, w, r, q, a, cid).await;
-                                            
-                                            // При успехе сбрасываем счётчик
-                                            if success {
-                                                CORE_STATE.write().unwrap().auto_fuel_attempts.insert(w, (0, 0));
-                                            }
-                                        }
-                                        
-                                        emit_event(EngineEvent::BalanceUpdate {
-                                            wallet: format!("{:?}", wallet),
-                                            token: "0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee".into(),
-                                            wei: balance.to_string(),
-                                            float_val,
-                                            symbol: "NATIVE".into()
-                                        });
-                                    }
-                                }
+                            Ok(Some(block)) => {
+                                blocks_seen += 1;
+                                on_new_block(ws_blocks.as_ref(), block.number, blocks_seen, &mut fee_history_misses, &wallets_blocks).await;
                             }
                             Ok(None) => return DisconnectReason::StreamEnded("blocks".into()),
                             Err(_) => return DisconnectReason::IdleTimeout,
@@ -474,91 +1203,56 @@ impl WebSocketManager {
 
         let ws_transfers = ws.clone();
         let wallets_transfers = wallets.clone();
-        let quote_transfers = quote_token;
-        let target_token_addr_transfer = target_token;
+        let watch = WatchSnapshot::new(&subscriptions);
         
-        let transfers_task = tokio::spawn(async move {
-            // Отслеживание target_token, чтобы видеть приход монет
-            let mut all_addresses: Vec<Address> = vec![target_token_addr_transfer];
-            if quote_transfers != Address::zero() {
-                all_addresses.push(quote_transfers);
-            }
-            
-            let filter = Filter::new()
-                .event("Transfer(address,address,uint256)")
-                .address(all_addresses);
+        // Набор токенов живёт в CORE_STATE: при его смене (fuel quote) переподписывается только этот поток
+        let transfers_task = crate::state::spawn(async move {
+            loop {
+                let addresses = transfer_watch_set();
+                if addresses.is_empty() {
+                    tokio::select! {
+                        _ = TRANSFER_SET_CHANGED.notified() => {}
+                        _ = sleep(Duration::from_secs(60)) => {}
+                    }
+                    if SHUTDOWN_FLAG.load(std::sync::atomic::Ordering::Relaxed) {
+                        return DisconnectReason::Shutdown;
+                    }
+                    continue;
+                }
                 
-            match ws_transfers.subscribe_logs(&filter).await {
-                Ok(mut transfer_stream) => {
-                    emit_log("INFO", "📡 Подписка на Transfer события активна".into());
-                    
-                    while let Some(log) = transfer_stream.next().await {
-                        if SHUTDOWN_FLAG.load(std::sync::atomic::Ordering::Relaxed) {
-                            return DisconnectReason::Shutdown;
-                        }
-                        
-                        let raw = log.clone().into();
-                        if let Ok(transfer) = <TransferFilter as EthEvent>::decode_log(&raw) {
-                            let is_incoming = wallets_transfers.contains(&transfer.to);
-                            let is_outgoing = wallets_transfers.contains(&transfer.from);
-                            
-                            if is_incoming || is_outgoing {
-                                let ws_clone = ws_transfers.clone();
-                                let addr = log.address;
-                                
-                                if is_incoming {
-                                    let to_addr = transfer.to;
-                                    let ws_inner = ws_clone.clone();
-                                    tokio::spawn(async move {
-                                        let decimals = get_decimals_cached(addr).await;
-                                        let contract = UniversalABI::new(addr, ws_inner);
-                                        if let Ok(new_balance) = contract.balance_of(to_addr).call().await {
-                                            let float_val = wei_to_float(new_balance, decimals);
-                                            emit_event(EngineEvent::BalanceUpdate {
-                                                wallet: format!("{:?}", to_addr),
-                                                token: format!("{:?}", addr),
-                                                wei: new_balance.to_string(),
-                                                float_val,
-                                                symbol: "TOKEN".into()
-                                            });
-                                        }
-                                    });
-                                }
-                                
-                                if is_outgoing {
-                                    let from_addr = transfer.from;
-                                    let ws_inner = ws_clone.clone();
-                                    tokio::spawn(async move {
-                                        let decimals = get_decimals_cached(addr).await;
-                                        let contract = UniversalABI::new(addr, ws_inner);
-                                        if let Ok(new_balance) = contract.balance_of(from_addr).call().await {
-                                            let new_float = wei_to_float(new_balance, decimals);
-                                            emit_event(EngineEvent::BalanceUpdate {
-                                                wallet: format!("{:?}", from_addr),
-                                                token: format!("{:?}", addr),
-                                                wei: new_balance.to_string(),
-                                                float_val: new_float,
-                                                symbol: "TOKEN".into()
-                                            });
-                                        }
-                                    });
-                                }
-                            }
+                let filter = Filter::new()
+                    .event("Transfer(address,address,uint256)")
+                    .address(addresses.clone());
+                let mut transfer_stream = match ws_transfers.subscribe_logs(&filter).await {
+                    Ok(stream) => stream,
+                    Err(e) => return DisconnectReason::Error(format!("subscribe_logs(Transfer): {:?}", e)),
+                };
+                emit_log("INFO", format!("📡 Подписка на Transfer событий {} токенов активна", addresses.len()));
+                
+                loop {
+                    let log = tokio::select! {
+                        log = transfer_stream.next() => log,
+                        _ = TRANSFER_SET_CHANGED.notified() => {
+                            if transfer_watch_set() == addresses { continue; }
+                            break;
                         }
+                    };
+                    let Some(log) = log else { return DisconnectReason::StreamEnded("transfers".into()); };
+                    if SHUTDOWN_FLAG.load(std::sync::atomic::Ordering::Relaxed) {
+                        return DisconnectReason::Shutdown;
                     }
-                    
-                    DisconnectReason::StreamEnded("transfers".into())
+                    on_transfer_log(&ws_transfers, &log, &wallets_transfers, &watch);
                 }
-                Err(e) => DisconnectReason::Error(format!("subscribe_logs(Transfer): {:?}", e))
+                emit_log("DEBUG", "📡 Набор токенов Transfer изменился - переподписка".into());
             }
         });
 
+        // Общий фильтр логов по пулам всех подписок; один пул может принадлежать нескольким токенам
         let ws_pools = ws.clone();
-        let pools_list = pool_targets.clone();
-        let target_token_addr = target_token; 
+        let owners = pool_owners(&subscriptions).await;
         
-        let pools_task = tokio::spawn(async move {
-            if pools_list.is_empty() {
+        let pools_task = crate::state::spawn(async move {
+            if owners.is_empty() {
                 loop {
                     if SHUTDOWN_FLAG.load(std::sync::atomic::Ordering::Relaxed) {
                         return DisconnectReason::Shutdown;
@@ -567,11 +1261,7 @@ impl WebSocketManager {
                 }
             }
             
-            let t_dec = get_decimals_cached(target_token_addr).await;
-            let q_dec = get_decimals_cached(quote_token).await;
-            let (t0, _) = if target_token_addr < quote_token { (target_token_addr, quote_token) } else { (quote_token, target_token_addr) };
-            let t0_is_quote = t0 == quote_token;
-            
+            let pools_list: Vec<H160> = owners.keys().copied().collect();
             let filter = Filter::new().address(pools_list.clone());
             match ws_pools.subscribe_logs(&filter).await {
                 Ok(mut pool_stream) => {
@@ -582,75 +1272,10 @@ impl WebSocketManager {
                             return DisconnectReason::Shutdown;
                         }
                         
-                        let raw = log.clone().into();
-                        
-                        let quote_price_usd = {
-                            let s = CORE_STATE.read().unwrap();
-                            get_quote_price_usd(&s.quote_symbol, &s.usd_prices)
-                        };
-
-                        if let Ok(sync) = <SyncFilter as EthEvent>::decode_log(&raw) {
-                            CORE_STATE.write().unwrap().v2_reserves.insert(log.address, (sync.reserve_0.into(), sync.reserve_1.into()));
-                            
-                            let (liq_usd, price) = calculate_v2_liquidity_usd_and_price(
-                                sync.reserve_0.into(), sync.reserve_1.into(), 
-                                t_dec, q_dec, t0_is_quote, quote_price_usd 
-                            );
-                            
-                            {
-                                let mut s = CORE_STATE.write().unwrap();
-                                if s.selected_pool_address == Some(log.address) {
-                                    s.selected_pool_spot_price = price;
-                                    s.selected_pool_liquidity_usd = liq_usd;
-                                }
-                            }
-
-                            emit_event(EngineEvent::PoolUpdate {
-                                pool_address: format!("{:?}", log.address),
-                                pool_type: "V2".into(),
-                                token: format!("{:?}", target_token_addr),
-                                quote: format!("{:?}", quote_token),
-                                reserve0: Some(sync.reserve_0.to_string()),
-                                reserve1: Some(sync.reserve_1.to_string()),
-                                sqrt_price_x96: None,
-                                tick: None,
-                                liquidity: None,
-                                spot_price: Some(price),
-                                liquidity_usd: Some(liq_usd)
-                            });
-                        }
-                        
-                        if let Ok(swap) = <SwapFilter as EthEvent>::decode_log(&raw) {
-                            let mut s = CORE_STATE.write().unwrap();
-                            if let Some(pool) = s.v3_states.get_mut(&log.address) {
-                                pool.sqrt_price_x96 = swap.sqrt_price_x96;
-                                pool.liquidity = swap.liquidity.into();
-                                pool.tick = swap.tick;
+                        for watch in owners.get(&log.address).into_iter().flatten() {
+                            if let Some(reason) = on_pool_log(&log, watch) {
+                                return reason;
                             }
-                            
-                            let (liq_usd, price) = calculate_v3_liquidity_usd_and_price(
-                                swap.sqrt_price_x96.into(), swap.liquidity, 
-                                t_dec, q_dec, t0_is_quote, quote_price_usd
-                            );
-
-                            if s.selected_pool_address == Some(log.address) {
-                                s.selected_pool_spot_price = price;
-                                s.selected_pool_liquidity_usd = liq_usd;
-                            }
-
-                            emit_event(EngineEvent::PoolUpdate {
-                                pool_address: format!("{:?}", log.address),
-                                pool_type: "V3".into(),
-                                token: format!("{:?}", target_token_addr),
-                                quote: format!("{:?}", quote_token),
-                                reserve0: None,
-                                reserve1: None,
-                                sqrt_price_x96: Some(swap.sqrt_price_x96.to_string()),
-                                tick: Some(swap.tick),
-                                liquidity: Some(swap.liquidity),
-                                spot_price: Some(price),
-                                liquidity_usd: Some(liq_usd)
-                            });
                         }
                     }
                     
@@ -660,57 +1285,48 @@ impl WebSocketManager {
             }
         });
 
-        let ws_pending = ws.clone();
-        
-        let pending_txs_task = tokio::spawn(async move {
-            emit_log("INFO", "📡 Подписка на pending transactions активна".into());
-            let mut check_interval = interval(Duration::from_millis(500));
-            
-            loop {
-                check_interval.tick().await;
-                
-                if SHUTDOWN_FLAG.load(std::sync::atomic::Ordering::Relaxed) {
-                    return DisconnectReason::Shutdown;
-                }
-                
-                let txs_to_check: Vec<H256> = {
-                    CORE_STATE.read().unwrap().pending_txs.iter().cloned().collect()
-                };
-                
-                if txs_to_check.is_empty() {
-                    continue;
+        let ws_factory = ws.clone();
+        let factory_pairs: Vec<(Address, Address)> = subscriptions.iter().map(|(t, c)| (*t, c.quote)).collect();
+
+        let factory_task = crate::state::spawn(async move {
+            let filter = match factory_filter(&factory_pairs) {
+                Some(f) => f,
+                None => loop {
+                    if SHUTDOWN_FLAG.load(std::sync::atomic::Ordering::Relaxed) {
+                        return DisconnectReason::Shutdown;
+                    }
+                    sleep(Duration::from_secs(60)).await;
                 }
-                
-                for tx_hash in txs_to_check {
-                    match ws_pending.get_transaction_receipt(tx_hash).await {
-                        Ok(Some(receipt)) => {
-                            let status = if receipt.status.unwrap_or(U64::zero()) == U64::from(1) { "success" } else { "failed" };
-                            let gas_used = receipt.gas_used.unwrap_or(U256::zero()).as_u64();
-                            let block_num = receipt.block_number.unwrap_or(U64::zero()).as_u64();
-                            let from_addr = receipt.from;
-                            
-                            emit_log("INFO", format!("✅ TX подтверждена: {:?} (статус: {})", tx_hash, status));
-                            
-                            emit_event(EngineEvent::TxConfirmed {
-                                tx_hash: format!("{:?}", tx_hash),
-                                wallet: format!("{:?}", from_addr),
-                                gas_used,
-                                status: status.to_string(),
-                                confirm_block: block_num,
-                                timestamp_ms: current_timestamp_ms()
-                            });
-                            
-                            CORE_STATE.write().unwrap().pending_txs.remove(&tx_hash);
+            };
+
+            match ws_factory.subscribe_logs(&filter).await {
+                Ok(mut factory_stream) => {
+                    emit_log("INFO", "📡 Подписка на создание пулов (фабрики) активна".into());
+
+                    while let Some(log) = factory_stream.next().await {
+                        if SHUTDOWN_FLAG.load(std::sync::atomic::Ordering::Relaxed) {
+                            return DisconnectReason::Shutdown;
                         }
-                        Ok(None) => {}
-                        Err(e) => {
-                            emit_log("WARNING", format!("Ошибка проверки receipt {:?}: {:?}", tx_hash, e));
+                        if let Some((token, pool)) = decode_factory_log(&log, &factory_pairs) {
+                            return DisconnectReason::NewPool(token, pool);
                         }
                     }
+
+                    DisconnectReason::StreamEnded("factory".into())
                 }
+                Err(e) => DisconnectReason::Error(format!("subscribe_logs(factory): {:?}", e))
             }
         });
 
+        // Задачи живут только в рамках этой подписки - при переподписке старые потоки не должны дублировать события.
+        // Guard гасит их и когда сам монитор отменён через abort (Init/Shutdown)
+        let _task_guard = AbortOnDrop(vec![
+            blocks_task.abort_handle(),
+            transfers_task.abort_handle(),
+            pools_task.abort_handle(),
+            factory_task.abort_handle(),
+        ]);
+
         let reason = tokio::select! {
             result = blocks_task => {
                 match result {
@@ -730,12 +1346,15 @@ impl WebSocketManager {
                     Err(e) => DisconnectReason::Error(format!("pools_task join error: {:?}", e))
                 }
             }
-            result = pending_txs_task => {
+            result = factory_task => {
                 match result {
                     Ok(r) => r,
-                    Err(e) => DisconnectReason::Error(format!("pending_txs_task join error: {:?}", e))
+                    Err(e) => DisconnectReason::Error(format!("factory_task join error: {:?}", e))
                 }
             }
+            _ = SUBSCRIPTIONS_CHANGED.notified() => {
+                DisconnectReason::SubscriptionsChanged
+            }
             _ = async {
                 while !SHUTDOWN_FLAG.load(std::sync::atomic::Ordering::Relaxed) {
                     sleep(Duration::from_millis(100)).await;
@@ -749,60 +1368,487 @@ impl WebSocketManager {
     }
 }
 
+/// Проба WSS из режима HTTP polling: соединение поднялось и подписка на блоки принята
+async fn probe_wss(url: &str) -> bool {
+    let probe = async {
+        let ws = Provider::<Ws>::connect(url).await.ok()?;
+        ws.subscribe_blocks().await.ok().map(|_| ())
+    };
+    matches!(timeout(Duration::from_secs(WSS_PROBE_TIMEOUT_SECS), probe).await, Ok(Some(())))
+}
+
+/// Среднее время блока сети по последним POLL_BLOCK_SAMPLE блокам: измеряется один раз за сессию
+/// (интервал HTTP опроса, нижняя граница idle timeout). None - блоки не прочитались
+async fn block_time<M: Middleware>(client: &M, head: u64) -> Option<Duration> {
+    if let Some(known) = CORE_STATE.read().unwrap().block_time { return Some(known); }
+    let sample = POLL_BLOCK_SAMPLE.min(head);
+    if sample == 0 { return None; }
+    let (Ok(Some(latest)), Ok(Some(earlier))) = (client.get_block(head).await, client.get_block(head - sample).await) else {
+        return None;
+    };
+    let secs = latest.timestamp.saturating_sub(earlier.timestamp).as_u64() as f64 / sample as f64;
+    let measured = Duration::from_secs_f64(secs).max(Duration::from_millis(100));
+    CORE_STATE.write().unwrap().block_time = Some(measured);
+    Some(measured)
+}
+
+// ===================== TRANSFER HELPERS =====================
+
+/// Токены, чьи Transfer слушает монитор: подписки и их quote, quote токены сети, fuel quote и wrapped native
+fn transfer_watch_set() -> Vec<Address> {
+    let s = CORE_STATE.read().unwrap();
+    let mut addresses: Vec<Address> = s.tokens.iter()
+        .flat_map(|(token, ctx)| [*token, ctx.quote])
+        .chain(s.quote_tokens.values().copied())
+        .chain([s.fuel_quote_address, s.wrapped_native_address])
+        .filter(|a| !a.is_zero())
+        .collect();
+    addresses.sort();
+    addresses.dedup();
+    addresses
+}
+
+/// Transfer с участием отслеживаемого кошелька - свежий баланс токена у отправителя и получателя
+fn on_transfer_log<M: Middleware + 'static>(client: &Arc<M>, log: &Log, wallets: &[Address], watch: &WatchSnapshot) {
+    let raw = log.clone().into();
+    let Ok(transfer) = <TransferFilter as EthEvent>::decode_log(&raw) else { return; };
+    if !watch.is_empty() {
+        watch.on_transfer(log.address, transfer.from, transfer.to, transfer.value, log.transaction_hash);
+    }
+    let token = log.address;
+    if crate::balances::is_ignored(token) { return; }
+    for (wallet, involved) in [(transfer.to, wallets.contains(&transfer.to)), (transfer.from, wallets.contains(&transfer.from))] {
+        if !involved { continue; }
+        let client = client.clone();
+        crate::state::spawn(async move {
+            let decimals = get_decimals_cached(token).await;
+            let contract = UniversalABI::new(token, client);
+            if let Ok(new_balance) = contract.balance_of(wallet).call().await {
+                emit_event(EngineEvent::BalanceUpdate {
+                    wallet: format!("{:?}", wallet),
+                    token: format!("{:?}", token),
+                    wei: new_balance.to_string(),
+                    float_val: wei_to_float(new_balance, decimals),
+                    symbol: "TOKEN".into()
+                });
+            }
+        });
+    }
+}
+
 // ===================== POOL HELPERS =====================
 
-fn calculate_v2_liquidity_usd_and_price(
-    reserve0: U256, reserve1: U256, 
-    token0_decimals: u8, token1_decimals: u8,
-    token0_is_quote: bool, quote_price_usd: f64
-) -> (f64, f64) {
-    let r0_f = wei_to_float(reserve0, token0_decimals);
-    let r1_f = wei_to_float(reserve1, token1_decimals);
+/// Пара подписки, которой принадлежит пул из общего фильтра логов
+#[derive(Debug, Clone)]
+struct PoolWatch {
+    token: Address,
+    quote: Address,
+    t_dec: u8,
+    q_dec: u8,
+    t0_is_quote: bool,
+    quote_symbol: String,
+}
+
+/// Пул -> подписки, которым он принадлежит (общий фильтр логов по пулам всех подписок)
+async fn pool_owners(subscriptions: &[(Address, TokenContext)]) -> HashMap<H160, Vec<PoolWatch>> {
+    let mut owners: HashMap<H160, Vec<PoolWatch>> = HashMap::new();
+    for (token, ctx) in subscriptions {
+        if ctx.pool_targets.is_empty() { continue; }
+        let watch = pool_watch(*token, ctx.quote).await;
+        for &pool in &ctx.pool_targets {
+            owners.entry(pool).or_default().push(watch.clone());
+        }
+    }
+    owners
+}
+
+async fn pool_watch(token: Address, quote: Address) -> PoolWatch {
+    PoolWatch {
+        token,
+        quote,
+        t_dec: get_decimals_cached(token).await,
+        q_dec: get_decimals_cached(quote).await,
+        t0_is_quote: quote < token,
+        quote_symbol: quote_symbol_for(quote),
+    }
+}
+
+/// Лог пула подписки мимо WS (интеграционные тесты): тот же разбор, что у общего фильтра логов
+#[cfg(feature = "engine-tests")]
+pub(crate) async fn replay_pool_log(token: Address, quote: Address, log: &Log) -> bool {
+    on_pool_log(log, &pool_watch(token, quote).await).is_some()
+}
+
+/// Лог фабрики мимо WS (интеграционные тесты): Some((token, pool)) - монитор переподписался бы на новый пул
+#[cfg(feature = "engine-tests")]
+pub(crate) fn replay_factory_log(log: &Log) -> Option<(Address, H160)> {
+    let pairs: Vec<(Address, Address)> = CORE_STATE.read().unwrap().tokens.iter().map(|(t, c)| (*t, c.quote)).collect();
+    decode_factory_log(log, &pairs)
+}
+
+/// Обработка лога пула (Sync/Burn/Swap) в контексте одной подписки.
+/// Some - монитору нужно переподписаться (пул наполнился ликвидностью)
+fn on_pool_log(log: &Log, w: &PoolWatch) -> Option<DisconnectReason> {
+    let raw = log.clone().into();
     
-    if r0_f == 0.0 || r1_f == 0.0 {
-        return (0.0, 0.0);
+    let quote_price_usd = {
+        let s = CORE_STATE.read().unwrap();
+        get_quote_price_usd(&w.quote_symbol, &s)
+    };
+
+    if let Ok(sync) = <SyncFilter as EthEvent>::decode_log(&raw) {
+        let old_reserves = CORE_STATE.write().unwrap().v2_reserves.insert(log.address, (sync.reserve_0.into(), sync.reserve_1.into()));
+        
+        // Плечо мульти-хоп маршрута: цена считается по всей цепочке резервов
+        if let Some((liq_usd, price)) = update_route_price(w.token, log.address, quote_price_usd) {
+            on_spot_price_changed(w.token, w.quote, price);
+            emit_event(EngineEvent::PoolUpdate {
+                pool_address: format!("{:?}", log.address),
+                pool_type: "V2".into(),
+                token: format!("{:?}", w.token),
+                quote: format!("{:?}", w.quote),
+                quote_symbol: w.quote_symbol.clone(),
+                reserve0: Some(sync.reserve_0.to_string()),
+                reserve1: Some(sync.reserve_1.to_string()),
+                sqrt_price_x96: None,
+                tick: None,
+                liquidity: None,
+                spot_price: Some(price),
+                liquidity_usd: Some(liq_usd)
+            });
+            return None;
+        }
+        
+        let (liq_usd, price) = math::v2_liquidity_usd_and_price(
+            sync.reserve_0.into(), sync.reserve_1.into(), 
+            w.t_dec, w.q_dec, w.t0_is_quote, quote_price_usd 
+        );
+        
+        let (is_selected, no_pool_selected) = update_selected_price(w.token, log.address, liq_usd, price);
+
+        // Объём V2 - изменение quote резерва с прошлого Sync
+        let quote_side = |(r0, r1): (U256, U256)| wei_to_float(if w.t0_is_quote { r0 } else { r1 }, w.q_dec);
+        let volume = old_reserves
+            .map(|old| quote_side((sync.reserve_0.into(), sync.reserve_1.into())) - quote_side(old))
+            .unwrap_or(0.0);
+        candles::record(log.address, price, volume);
+
+        if is_selected {
+            on_spot_price_changed(w.token, w.quote, price);
+            snipe::on_liquidity(w.token, liq_usd);
+        }
+
+        // Пул из PairCreated/PoolCreated создан пустым и в него только что добавили ликвидность - выбираем заново.
+        // Sync любого другого пула, пока пул не выбран, подписку не рвёт
+        if no_pool_selected && liq_usd > 10.0 && take_created_pool(w.token, log.address) {
+            return Some(DisconnectReason::NewPool(w.token, log.address));
+        }
+
+        let block = log.block_number.map(|b| b.as_u64()).unwrap_or(0);
+        let quote_reserve = if w.t0_is_quote {
+            wei_to_float(sync.reserve_0.into(), w.q_dec)
+        } else {
+            wei_to_float(sync.reserve_1.into(), w.q_dec)
+        };
+        if let Some(alert) = check_liquidity_drop(log.address, block, quote_reserve, quote_price_usd) {
+            on_liquidity_alert(alert, log.address, w.token, w.quote);
+        }
+
+        emit_event(EngineEvent::PoolUpdate {
+            pool_address: format!("{:?}", log.address),
+            pool_type: "V2".into(),
+            token: format!("{:?}", w.token),
+            quote: format!("{:?}", w.quote),
+            quote_symbol: w.quote_symbol.clone(),
+            reserve0: Some(sync.reserve_0.to_string()),
+            reserve1: Some(sync.reserve_1.to_string()),
+            sqrt_price_x96: None,
+            tick: None,
+            liquidity: None,
+            spot_price: Some(price),
+            liquidity_usd: Some(liq_usd)
+        });
+    }
+    
+    if let Ok(swap) = <V2SwapFilter as EthEvent>::decode_log(&raw) {
+        let (token_in, token_out, quote_in, quote_out) = if w.t0_is_quote {
+            (swap.amount_1_in, swap.amount_1_out, swap.amount_0_in, swap.amount_0_out)
+        } else {
+            (swap.amount_0_in, swap.amount_0_out, swap.amount_1_in, swap.amount_1_out)
+        };
+        let is_buy = token_out > token_in;
+        let token_net = if is_buy { token_out - token_in } else { token_in - token_out };
+        let quote_net = if quote_in > quote_out { quote_in - quote_out } else { quote_out - quote_in };
+        tape::record(tape::Trade {
+            pool: log.address,
+            token: w.token,
+            is_buy,
+            token_amount: wei_to_float(token_net, w.t_dec),
+            quote_amount: wei_to_float(quote_net, w.q_dec),
+            maker: swap.to,
+            tx_hash: log.transaction_hash,
+        }, quote_price_usd);
     }
     
-    let price_in_quote = if token0_is_quote { r0_f / r1_f } else { r1_f / r0_f };
-    let liq = if token0_is_quote { 2.0 * r0_f * quote_price_usd } else { 2.0 * r1_f * quote_price_usd };
-    (liq, price_in_quote)
+    if let Ok(burn) = <BurnFilter as EthEvent>::decode_log(&raw) {
+        let block = log.block_number.map(|b| b.as_u64()).unwrap_or(0);
+        let burned_quote = if w.t0_is_quote {
+            wei_to_float(burn.amount_0, w.q_dec)
+        } else {
+            wei_to_float(burn.amount_1, w.q_dec)
+        };
+        if let Some(alert) = check_liquidity_burn(log.address, block, burned_quote, quote_price_usd) {
+            on_liquidity_alert(alert, log.address, w.token, w.quote);
+        }
+    }
+    
+    if let Ok(swap) = <SwapFilter as EthEvent>::decode_log(&raw) {
+        let old_tick = {
+            let mut s = CORE_STATE.write().unwrap();
+            s.v3_states.get_mut(&log.address).map(|pool| {
+                let old_tick = pool.tick;
+                pool.sqrt_price_x96 = swap.sqrt_price_x96;
+                pool.liquidity = swap.liquidity.into();
+                pool.tick = swap.tick;
+                old_tick
+            })
+        };
+
+        // Плечо V3 мульти-хоп маршрута
+        if let Some((liq_usd, price)) = update_route_price(w.token, log.address, quote_price_usd) {
+            on_spot_price_changed(w.token, w.quote, price);
+            emit_event(EngineEvent::PoolUpdate {
+                pool_address: format!("{:?}", log.address),
+                pool_type: "V3".into(),
+                token: format!("{:?}", w.token),
+                quote: format!("{:?}", w.quote),
+                quote_symbol: w.quote_symbol.clone(),
+                reserve0: None,
+                reserve1: None,
+                sqrt_price_x96: Some(swap.sqrt_price_x96.to_string()),
+                tick: Some(swap.tick),
+                liquidity: Some(swap.liquidity),
+                spot_price: Some(price),
+                liquidity_usd: Some(liq_usd)
+            });
+            return None;
+        }
+        
+        let (liq_usd, price) = math::v3_liquidity_usd_and_price(
+            swap.sqrt_price_x96, swap.liquidity, 
+            w.t_dec, w.q_dec, w.t0_is_quote, quote_price_usd
+        );
+
+        let refresh_ticks = {
+            let s = CORE_STATE.read().unwrap();
+            s.v3_tick_windows.get(&log.address)
+                .is_some_and(|win| v3_math::needs_refresh(win, old_tick.unwrap_or(swap.tick), swap.tick))
+        };
+        let (is_selected, _) = update_selected_price(w.token, log.address, liq_usd, price);
+
+        // Суммы V3 - изменение балансов пула: отрицательная по токену - пул отдал токен (покупка)
+        let (token_delta, quote_delta) = if w.t0_is_quote { (swap.amount_1, swap.amount_0) } else { (swap.amount_0, swap.amount_1) };
+        let quote_amount = wei_to_float(quote_delta.unsigned_abs(), w.q_dec);
+        candles::record(log.address, price, quote_amount);
+        tape::record(tape::Trade {
+            pool: log.address,
+            token: w.token,
+            is_buy: token_delta.is_negative(),
+            token_amount: wei_to_float(token_delta.unsigned_abs(), w.t_dec),
+            quote_amount,
+            maker: swap.recipient,
+            tx_hash: log.transaction_hash,
+        }, quote_price_usd);
+
+        if refresh_ticks {
+            crate::state::spawn(v3_math::refresh_tick_window(log.address));
+        }
+
+        if is_selected {
+            on_spot_price_changed(w.token, w.quote, price);
+            snipe::on_liquidity(w.token, liq_usd);
+        }
+
+        emit_event(EngineEvent::PoolUpdate {
+            pool_address: format!("{:?}", log.address),
+            pool_type: "V3".into(),
+            token: format!("{:?}", w.token),
+            quote: format!("{:?}", w.quote),
+            quote_symbol: w.quote_symbol.clone(),
+            reserve0: None,
+            reserve1: None,
+            sqrt_price_x96: Some(swap.sqrt_price_x96.to_string()),
+            tick: Some(swap.tick),
+            liquidity: Some(swap.liquidity),
+            spot_price: Some(price),
+            liquidity_usd: Some(liq_usd)
+        });
+    }
+
+    None
 }
 
-fn calculate_v3_liquidity_usd_and_price(
-    sqrt_price_x96: U256, liquidity: u128,
-    token0_decimals: u8, token1_decimals: u8,
-    token0_is_quote: bool, quote_price_usd: f64
-) -> (f64, f64) {
-    if sqrt_price_x96.is_zero() || liquidity == 0 { return (0.0, 0.0); }
-    let sqrt_p = sqrt_price_x96.as_u128() as f64 / 2.0f64.powi(96);
-    let price_token1_per_token0 = sqrt_p * sqrt_p;
+/// Пересчёт цены/ликвидности маршрута токена, если пул - одно из его плеч
+fn update_route_price(token: Address, pool: H160, quote_price_usd: f64) -> Option<(f64, f64)> {
+    let mut s = CORE_STATE.write().unwrap();
+    let ctx = s.tokens.get(&token).filter(|c| c.route_pools.contains(&pool))?;
+    let (liq, price) = calculate_route_liquidity_usd_and_price(&s, &ctx.route, &ctx.route_pools, quote_price_usd);
+    let ctx = s.tokens.get_mut(&token)?;
+    ctx.spot_price = price;
+    ctx.liquidity_usd = liq;
+    Some((liq, price))
+}
+
+/// Обновляет цену выбранного пула токена. Возвращает (пул выбран, у токена ещё нет пула)
+fn update_selected_price(token: Address, pool: H160, liq_usd: f64, price: f64) -> (bool, bool) {
+    let mut s = CORE_STATE.write().unwrap();
+    let Some(ctx) = s.tokens.get_mut(&token) else { return (false, false) };
+    let is_selected = ctx.pool_address == Some(pool);
+    if is_selected {
+        ctx.spot_price = price;
+        ctx.liquidity_usd = liq_usd;
+    }
+    (is_selected, ctx.pool_address.is_none())
+}
+
+/// Хук на изменение спотовой цены выбранного пула: алерты и лимитные ордера
+fn on_spot_price_changed(token: Address, quote: Address, price: f64) {
+    alerts::on_spot_price(token, quote, price);
+    orders::on_spot_price(token, quote, price);
+}
+
+/// Цена a в b и глубина стороны b (в единицах b) для одного плеча маршрута: V2 по резервам, V3 по slot0
+fn route_leg_price_and_depth(s: &BotState, pool: H160, a: Address, b: Address) -> Option<(f64, f64)> {
+    let dec_a = *s.decimals_cache.get(&a).unwrap_or(&18);
+    let dec_b = *s.decimals_cache.get(&b).unwrap_or(&18);
     
-    let price_in_quote = if token0_is_quote { 1.0 / price_token1_per_token0 } else { price_token1_per_token0 };
+    if let Some(&(r0, r1)) = s.v2_reserves.get(&pool) {
+        let (r_a, r_b) = if a < b { (r0, r1) } else { (r1, r0) };
+        let f_a = wei_to_float(r_a, dec_a);
+        let f_b = wei_to_float(r_b, dec_b);
+        if f_a == 0.0 || f_b == 0.0 { return None; }
+        return Some((f_b / f_a, f_b));
+    }
     
-    let tvl = if token0_is_quote {
-        let q_amt = (liquidity as f64 / sqrt_p) / 10f64.powi(token0_decimals as i32);
-        q_amt * quote_price_usd
+    let v3 = s.v3_states.get(&pool)?;
+    if v3.sqrt_price_x96.is_zero() || v3.liquidity.is_zero() { return None; }
+    let sqrt_p = wei_to_float(v3.sqrt_price_x96, 0) / 2.0f64.powi(96);
+    let liq = wei_to_float(v3.liquidity, 0);
+    // sqrt_p^2 - сырая цена token0 в token1
+    if a < b {
+        let price = sqrt_p * sqrt_p * 10f64.powi(dec_a as i32 - dec_b as i32);
+        Some((price, liq * sqrt_p / 10f64.powi(dec_b as i32)))
     } else {
-        let q_amt = (liquidity as f64 * sqrt_p) / 10f64.powi(token1_decimals as i32);
-        q_amt * quote_price_usd
+        let price = 10f64.powi(dec_a as i32 - dec_b as i32) / (sqrt_p * sqrt_p);
+        Some((price, liq / sqrt_p / 10f64.powi(dec_b as i32)))
+    }
+}
+
+/// Цена token в quote и ликвидность маршрута (USD самого слабого плеча).
+/// Идём от quote к токену, накапливая цену каждого промежуточного токена в quote.
+fn calculate_route_liquidity_usd_and_price(s: &BotState, path: &[Address], pools: &[H160], quote_price_usd: f64) -> (f64, f64) {
+    if path.len() < 3 || pools.len() + 1 != path.len() { return (0.0, 0.0); }
+    
+    let mut price_in_quote = 1.0;
+    let mut min_liq = f64::MAX;
+    for i in (0..pools.len()).rev() {
+        let Some((price, depth_b)) = route_leg_price_and_depth(s, pools[i], path[i], path[i + 1]) else {
+            return (0.0, 0.0);
+        };
+        min_liq = min_liq.min(2.0 * depth_b * price_in_quote * quote_price_usd);
+        price_in_quote *= price;
+    }
+    (min_liq, price_in_quote)
+}
+
+/// Rug-детект по Sync: сравнивает quote-резерв с базовой линией на начало блока.
+/// Смотрим только на quote-сторону, чтобы крупные свапы токена не давали ложных срабатываний.
+fn check_liquidity_drop(pool: H160, block: u64, quote_reserve: f64, quote_price_usd: f64) -> Option<(f64, f64, f64)> {
+    let mut s = CORE_STATE.write().unwrap();
+    let threshold = s.rug_alert_pct;
+    let entry = s.liquidity_baselines.entry(pool).or_insert_with(|| LiquidityBaseline {
+        block, baseline_quote: quote_reserve, last_quote: quote_reserve, ..Default::default()
+    });
+
+    // Новый блок - базой становится последний резерв предыдущего блока
+    if entry.block != block {
+        entry.block = block;
+        entry.baseline_quote = entry.last_quote;
+    }
+    entry.last_quote = quote_reserve;
+
+    if threshold <= 0.0 || entry.baseline_quote <= 0.0 || entry.alerted_block == block {
+        return None;
+    }
+
+    let drop_pct = (entry.baseline_quote - quote_reserve) / entry.baseline_quote * 100.0;
+    if drop_pct < threshold {
+        return None;
+    }
+
+    entry.alerted_block = block;
+    Some((2.0 * entry.baseline_quote * quote_price_usd, 2.0 * quote_reserve * quote_price_usd, drop_pct))
+}
+
+/// Rug-детект по Burn: Sync уже применён, поэтому до сжигания резерв был last + burned.
+/// Как и для Sync, алерт только при заданном пороге и сжигании не меньше него
+fn check_liquidity_burn(pool: H160, block: u64, burned_quote: f64, quote_price_usd: f64) -> Option<(f64, f64, f64)> {
+    let mut s = CORE_STATE.write().unwrap();
+    let threshold = s.rug_alert_pct;
+    let entry = s.liquidity_baselines.get_mut(&pool)?;
+    if threshold <= 0.0 || entry.alerted_block == block || burned_quote <= 0.0 {
+        return None;
+    }
+
+    let old_quote = entry.last_quote + burned_quote;
+    let drop_pct = burned_quote / old_quote * 100.0;
+    if drop_pct < threshold {
+        return None;
+    }
+
+    entry.alerted_block = block;
+    Some((2.0 * old_quote * quote_price_usd, 2.0 * entry.last_quote * quote_price_usd, drop_pct))
+}
+
+/// Эмитит LiquidityAlert и, если включено, один раз запускает экстренную продажу
+fn on_liquidity_alert(alert: (f64, f64, f64), pool: H160, token: Address, quote: Address) {
+    let (old_usd, new_usd, drop_pct) = alert;
+    emit_log("ERROR", format!("🚨 Ликвидность пула {:?} упала на {:.1}% (${:.0} → ${:.0})", pool, drop_pct, old_usd, new_usd));
+    emit_event(EngineEvent::LiquidityAlert { pool: format!("{:?}", pool), old_usd, new_usd, drop_pct });
+
+    let should_sell = {
+        let mut s = CORE_STATE.write().unwrap();
+        let armed = s.rug_auto_sell && drop_pct >= s.rug_alert_pct;
+        match s.liquidity_baselines.get_mut(&pool) {
+            Some(entry) if armed && !entry.auto_sold => { entry.auto_sold = true; true }
+            _ => false,
+        }
     };
-    (tvl, price_in_quote)
+
+    if should_sell {
+        emit_log("WARNING", "🚨 Rug auto-sell: продаём 100% токена со всех кошельков".into());
+        crate::state::spawn(async move {
+            let evs = execution::sell_all_tracked(token, quote, RUG_SELL_SLIPPAGE).await;
+            for e in evs { emit_event(e); }
+        });
+    }
 }
 
-fn select_best_pool(mut candidates: Vec<PoolCandidate>, trade_amount_usd: f64) -> Option<PoolCandidate> {
-    emit_log("DEBUG", format!("📊 select_best_pool: {} candidates до фильтра", candidates.len()));
+/// Кандидаты с ликвидностью > 10$, отсортированные по score (первый - лучший)
+fn rank_pools(mut candidates: Vec<PoolCandidate>, trade_amount_usd: f64) -> Vec<PoolCandidate> {
+    emit_log("DEBUG", format!("📊 rank_pools: {} candidates до фильтра", candidates.len()));
     for p in &candidates {
         emit_log("DEBUG", format!("  - {:?}: type={}, liq={}", p.address, p.pool_type, p.liquidity_usd));
     }
     
-    if candidates.is_empty() { return None; }
+    if candidates.is_empty() { return candidates; }
     candidates.retain(|p| p.liquidity_usd > 10.0);
     
-    emit_log("DEBUG", format!("📊 select_best_pool: {} candidates после фильтра (liq > 10)", candidates.len()));
+    emit_log("DEBUG", format!("📊 rank_pools: {} candidates после фильтра (liq > 10)", candidates.len()));
     
     if candidates.is_empty() { 
         emit_log("WARNING", "⚠️ Все пулы отфильтрованы (liq <= 10)".into());
-        return None; 
+        return candidates; 
     }
     candidates.retain(|p| p.liquidity_usd > 10.0);
     let max_liq = candidates.iter().map(|p| p.liquidity_usd).fold(0.0, f64::max);
@@ -810,47 +1856,419 @@ fn select_best_pool(mut candidates: Vec<PoolCandidate>, trade_amount_usd: f64) -
     for p in &mut candidates {
         let norm_liq = if max_liq > 0.0 { p.liquidity_usd / max_liq } else { 0.0 };
         let norm_fee = if max_fee > 0.0 { p.fee_bps as f64 / max_fee } else { 1.0 };
-        let impact = if p.liquidity_usd > 0.0 { (trade_amount_usd / p.liquidity_usd).min(1.0) } else { 1.0 };
+        let impact = match p.impact_pct {
+            Some(pct) => (pct / 100.0).min(1.0),
+            None if p.liquidity_usd > 0.0 => (trade_amount_usd / p.liquidity_usd).min(1.0),
+            None => 1.0,
+        };
         p.score = WEIGHT_LIQUIDITY * norm_liq + WEIGHT_FEE * (1.0 - norm_fee) + WEIGHT_PRICE_IMPACT * (1.0 - impact);
     }
     candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
-    candidates.into_iter().next()
+    candidates
 }
 
-// ===================== PUBLIC API =====================
+fn to_ranked(candidates: Vec<PoolCandidate>) -> Vec<RankedPool> {
+    candidates.into_iter().map(|p| RankedPool {
+        address: p.address, pool_type: p.pool_type, fee: p.fee_bps,
+        liquidity_usd: p.liquidity_usd, spot_price: p.spot_price, score: p.score
+    }).collect()
+}
 
-pub async fn discover_pools(token: Address, quote: Address) -> Vec<Address> {
-    let mut targets = vec![token];
+/// Переоценка пулов всех подписанных токенов
+async fn rescore_pools() {
+    let tokens: Vec<Address> = { CORE_STATE.read().unwrap().tokens.keys().copied().collect() };
+    for token in tokens {
+        rescore_token_pools(token).await;
+    }
+}
+
+/// Переоценка прямых пулов токена по живым v2_reserves/v3_states (без RPC).
+/// Пул меняется, только если score нового лидера выше текущего больше чем на pool_switch_hysteresis_pct.
+/// Ручной выбор (SelectPool) и мульти-хоп маршруты не трогаем.
+async fn rescore_token_pools(token: Address) {
+    let (quote, candidates, current, hysteresis) = {
+        let s = CORE_STATE.read().unwrap();
+        let Some(ctx) = s.tokens.get(&token) else { return };
+        let (quote, Some(current)) = (ctx.quote, ctx.pool_address) else { return };
+        if ctx.pinned_pool.is_some() || !ctx.route.is_empty() || ctx.pool_targets.len() < 2 { return; }
+
+        let quote_price = quote_price_in(&s, quote);
+        let t_dec = *s.decimals_cache.get(&token).unwrap_or(&18);
+        let q_dec = *s.decimals_cache.get(&quote).unwrap_or(&18);
+        let t0_is_quote = quote < token;
+
+        let mut candidates = Vec::new();
+        for &addr in &ctx.pool_targets {
+            if let Some(&(r0, r1)) = s.v2_reserves.get(&addr) {
+                let (liq, prc) = math::v2_liquidity_usd_and_price(r0, r1, t_dec, q_dec, t0_is_quote, quote_price);
+                candidates.push(PoolCandidate {
+                    address: addr, pool_type: "V2".into(), liquidity_usd: liq, fee_bps: s.v2_pool_fee_bps,
+                    sqrt_price_x96: None, tick: None, reserves: Some((r0, r1)),
+                    score: 0.0, spot_price: prc, impact_pct: None
+                });
+            } else if let Some(v3) = s.v3_states.get(&addr).filter(|v3| !v3.sqrt_price_x96.is_zero()) {
+                let (liq, prc) = math::v3_liquidity_usd_and_price(v3.sqrt_price_x96, v3.liquidity.low_u128(), t_dec, q_dec, t0_is_quote, quote_price);
+                // Точный impact только по уже загруженному окну тиков
+                let impact_pct = match s.v3_tick_windows.get(&addr) {
+                    Some(w) if quote_price > 0.0 && DEFAULT_TRADE_USD > liq * v3_math::LOCAL_IMPACT_MIN_SHARE => {
+                        let amount_in = DEFAULT_TRADE_USD / quote_price * 10f64.powi(q_dec as i32);
+                        v3_math::simulate_exact_input(v3, w, amount_in, t0_is_quote)
+                            .map(|out| v3_math::impact_pct(v3, amount_in, out, t0_is_quote))
+                    }
+                    _ => None,
+                };
+                candidates.push(PoolCandidate {
+                    address: addr, pool_type: "V3".into(), liquidity_usd: liq, fee_bps: v3.pool_fee,
+                    sqrt_price_x96: Some(v3.sqrt_price_x96), tick: Some(v3.tick), reserves: None,
+                    score: 0.0, spot_price: prc, impact_pct
+                });
+            }
+        }
+        (quote, candidates, current, s.pool_switch_hysteresis_pct)
+    };
+
+    let ranked = to_ranked(rank_pools(candidates, DEFAULT_TRADE_USD));
+    let Some(best) = ranked.first().cloned() else { return };
+    let current_score = ranked.iter().find(|p| p.address == current).map(|p| p.score);
+    if let Some(ctx) = CORE_STATE.write().unwrap().tokens.get_mut(&token) {
+        ctx.ranked_pools = ranked;
+    }
+
+    if best.address == current {
+        return;
+    }
+    let reason = match current_score {
+        Some(score) if best.score <= score * (1.0 + hysteresis / 100.0) => return,
+        Some(score) => format!("score {:.3} → {:.3} (ликвидность ${:.0})", score, best.score, best.liquidity_usd),
+        None => format!("текущий пул выбыл из кандидатов (ликвидность нового ${:.0})", best.liquidity_usd),
+    };
+
+    // Пока считали, пользователь мог закрепить пул или снять подписку
+    {
+        let s = CORE_STATE.read().unwrap();
+        let Some(ctx) = s.tokens.get(&token) else { return };
+        if ctx.pinned_pool.is_some() || ctx.pool_address != Some(current) { return; }
+    }
+
+    emit_log("WARNING", format!("🔁 Смена пула {:?} ({:?}) → {} {:?}: {}", current, token, best.pool_type, best.address, reason));
+    emit_event(EngineEvent::PoolSwitched {
+        old_address: format!("{:?}", current),
+        new_address: format!("{:?}", best.address),
+        reason,
+    });
+    apply_pool_selection(best, token, quote).await;
+}
+
+// ===================== FACTORY WATCHER =====================
+
+/// Фильтр PairCreated (V2) / PoolCreated (V3) по парам token/quote всех подписок в любом порядке
+fn factory_filter(pairs: &[(Address, Address)]) -> Option<Filter> {
+    if pairs.is_empty() { return None; }
     let (v2_f, v3_f) = { let s = CORE_STATE.read().unwrap(); (s.v2_factory_address, s.v3_factory_address) };
+    let factories: Vec<Address> = [v2_f, v3_f].into_iter().filter(|a| *a != Address::zero()).collect();
+    if factories.is_empty() { return None; }
+
+    // Топики покрывают все перекрёстные сочетания - точное совпадение пары проверяет decode_factory_log
+    let mut addrs: Vec<H256> = pairs.iter().flat_map(|(t, q)| [H256::from(*t), H256::from(*q)]).collect();
+    addrs.sort();
+    addrs.dedup();
+    let pair_topics = ValueOrArray::Array(addrs);
+    Some(Filter::new()
+        .address(factories)
+        .events(["PairCreated(address,address,address,uint256)", "PoolCreated(address,address,uint24,int24,address)"])
+        .topic1(pair_topics.clone())
+        .topic2(pair_topics))
+}
+
+/// Возвращает (token, адрес созданного пула), если лог фабрики относится к одной из пар подписок
+fn decode_factory_log(log: &Log, pairs: &[(Address, Address)]) -> Option<(Address, H160)> {
+    let raw = log.clone().into();
+    let find_token = |t0: Address, t1: Address| pairs.iter()
+        .find(|(token, quote)| (t0 == *token && t1 == *quote) || (t0 == *quote && t1 == *token))
+        .map(|(token, _)| *token);
+
+    let created = if let Ok(created) = <PairCreatedFilter as EthEvent>::decode_log(&raw) {
+        find_token(created.token_0, created.token_1).map(|token| (token, created.pair))
+    } else if let Ok(created) = <PoolCreatedFilter as EthEvent>::decode_log(&raw) {
+        let found = find_token(created.token_0, created.token_1);
+        if found.is_some() {
+            // Запоминаем fee, чтобы prefetch правильно оценил V3 кандидата
+            CORE_STATE.write().unwrap().v3_states.insert(created.pool, V3PoolState {
+                pool_fee: created.fee, ..Default::default()
+            });
+        }
+        found.map(|token| (token, created.pool))
+    } else {
+        None
+    };
+
+    // Пул обычно создаётся пустым: его первая ликвидность (Sync) снова запустит выбор пула
+    let (token, pool) = created?;
+    if let Some(ctx) = CORE_STATE.write().unwrap().tokens.get_mut(&token) {
+        if !ctx.created_pools.contains(&pool) { ctx.created_pools.push(pool); }
+    }
+    Some((token, pool))
+}
+
+/// Пул из события фабрики получил ликвидность: true один раз на пул
+fn take_created_pool(token: Address, pool: H160) -> bool {
+    let mut s = CORE_STATE.write().unwrap();
+    let Some(ctx) = s.tokens.get_mut(&token) else { return false };
+    let before = ctx.created_pools.len();
+    ctx.created_pools.retain(|p| *p != pool);
+    ctx.created_pools.len() != before
+}
+
+/// Опциональный проход: собирает включённые fee tiers из событий FeeAmountEnabled фабрики V3
+/// и добавляет их к настроенному списку. Публичные RPC часто режут get_logs от генезиса - тогда остаётся конфиг.
+pub async fn discover_v3_fee_tiers() {
+    let v3_f = { CORE_STATE.read().unwrap().v3_factory_address };
+    if v3_f == Address::zero() { return; }
+    let Some(p) = discovery_provider().await else { return };
+
+    let filter = Filter::new()
+        .address(v3_f)
+        .event("FeeAmountEnabled(uint24,int24)")
+        .from_block(0u64);
+
+    match p.get_logs(&filter).await {
+        Ok(logs) => {
+            let found: Vec<u32> = logs.iter()
+                .filter_map(|log| <FeeAmountEnabledFilter as EthEvent>::decode_log(&log.clone().into()).ok())
+                .map(|e| e.fee)
+                .collect();
+            let tiers = {
+                let mut s = CORE_STATE.write().unwrap();
+                for fee in found {
+                    if !s.v3_fee_tiers.contains(&fee) { s.v3_fee_tiers.push(fee); }
+                }
+                s.v3_fee_tiers.sort_unstable();
+                s.v3_fee_tiers.clone()
+            };
+            emit_log("INFO", format!("🧮 V3 fee tiers: {:?}", tiers));
+        }
+        Err(e) => emit_log("WARNING", format!("🧮 FeeAmountEnabled недоступны, используем конфиг: {:?}", e)),
+    }
+}
+
+// ===================== PUBLIC API =====================
+
+/// Первый отвечающий HTTP провайдер из 5 самых быстрых RPC
+async fn discovery_provider() -> Option<Arc<Provider<Http>>> {
     let rpc_urls = { RPC_POOL.read().unwrap().get_fastest_pool(5) };
     
-    // === ДОБАВИТЬ ЛОГИ ===
     emit_log("DEBUG", format!("discover_pools: {} RPC URLs", rpc_urls.len()));
     for (i, url) in rpc_urls.iter().enumerate() {
         emit_log("DEBUG", format!("  RPC[{}]: {}...", i, &url[..50.min(url.len())]));
     }
     
-    let provider_http = {
-        let mut result = None;
-        for url in rpc_urls {
-            if let Ok(u) = Url::parse(&url) {
-                let p = Arc::new(Provider::new(Http::new_with_client(u, GLOBAL_HTTP_CLIENT.clone())));
-                match timeout(Duration::from_secs(3), p.get_block_number()).await {
-                    Ok(_) => {
-                        emit_log("DEBUG", format!("RPC OK: {}", &url[..50.min(url.len())]));
-                        result = Some(p);
-                        break;
-                    }
-                    Err(_) => {
-                        emit_log("WARNING", format!("RPC TIMEOUT: {}", &url[..50.min(url.len())]));
-                    }
+    for url in rpc_urls {
+        if let Ok(u) = Url::parse(&url) {
+            let p = Arc::new(Provider::new(Http::new_with_client(u, GLOBAL_HTTP_CLIENT.clone())));
+            match timeout(Duration::from_secs(3), p.get_block_number()).await {
+                Ok(_) => {
+                    emit_log("DEBUG", format!("RPC OK: {}", &url[..50.min(url.len())]));
+                    return Some(p);
+                }
+                Err(_) => {
+                    emit_log("WARNING", format!("RPC TIMEOUT: {}", &url[..50.min(url.len())]));
                 }
             }
         }
-        result
-    }; 
+    }
+    None
+}
+
+/// Ищет 2-hop V2 маршрут token -> X -> quote по всем quote токенам X, если прямой пары нет.
+/// Выбирается маршрут с максимальной ликвидностью самого слабого плеча.
+pub async fn discover_v2_route(token: Address, quote: Address, mids: &[(String, Address)], ref_in: U256) -> Option<PoolRoute> {
+    let v2_f = { CORE_STATE.read().unwrap().v2_factory_address };
+    if v2_f == Address::zero() { return None; }
+    
+    let p = discovery_provider().await?;
+    let factory = UniversalABI::new(v2_f, p.clone());
+    get_decimals_cached(token).await;
+    get_decimals_cached(quote).await;
+    
+    let mut best: Option<PoolRoute> = None;
+    for (sym, mid) in mids {
+        if *mid == quote || *mid == token { continue; }
+        
+        let leg1 = factory.get_pair(token, *mid).call().await.unwrap_or_default();
+        let leg2 = factory.get_pair(*mid, quote).call().await.unwrap_or_default();
+        if leg1 == Address::zero() || leg2 == Address::zero() { continue; }
+        
+        let res1 = UniversalABI::new(leg1, p.clone()).get_reserves().call().await;
+        let res2 = UniversalABI::new(leg2, p.clone()).get_reserves().call().await;
+        let (Ok((a0, a1, _)), Ok((b0, b1, _))) = (res1, res2) else { continue };
+        get_decimals_cached(*mid).await;
+        
+        let path = vec![token, *mid, quote];
+        let pools = vec![leg1, leg2];
+        let (liq, price) = {
+            let mut s = CORE_STATE.write().unwrap();
+            s.v2_reserves.insert(leg1, (a0.into(), a1.into()));
+            s.v2_reserves.insert(leg2, (b0.into(), b1.into()));
+            let quote_price = quote_price_in(&s, quote);
+            calculate_route_liquidity_usd_and_price(&s, &path, &pools, quote_price)
+        };
+        emit_log("DEBUG", format!("🔀 Маршрут через {}: liq={:.0}$, price={}", sym, liq, price));
+        
+        if liq > 10.0 && best.as_ref().is_none_or(|b| liq > b.liquidity_usd) {
+            // Эталонная покупка quote -> mid -> token по резервам обоих плеч
+            let oriented = |r0: u128, r1: u128, t_in: Address, t_out: Address| {
+                if t_in < t_out { (U256::from(r0), U256::from(r1)) } else { (U256::from(r1), U256::from(r0)) }
+            };
+            let (q_in, mid_r_out) = oriented(b0, b1, quote, *mid);
+            let (mid_r_in, t_out) = oriented(a0, a1, *mid, token);
+            let mid_out = execution::v2_amount_out(ref_in, q_in, mid_r_out);
+            let amount_out = execution::v2_amount_out(mid_out, mid_r_in, t_out);
+            best = Some(PoolRoute {
+                pool_type: "V2".into(), path, pools, fees: vec![], via: sym.clone(),
+                liquidity_usd: liq, spot_price: price, amount_out
+            });
+        }
+    }
+    best
+}
+
+/// Самый ликвидный V3 пул пары по всем fee tier: (pool, fee)
+async fn best_v3_pool(factory: &UniversalABI<Provider<Http>>, provider: Arc<Provider<Http>>, a: Address, b: Address) -> Option<(H160, u32)> {
+    let mut best: Option<(H160, u32, u128)> = None;
+    let fee_tiers = { CORE_STATE.read().unwrap().v3_fee_tiers.clone() };
+    for fee in fee_tiers {
+        let pool = factory.get_pool(a, b, fee).call().await.unwrap_or_default();
+        if pool == Address::zero() { continue; }
+        let contract = UniversalABI::new(pool, provider.clone());
+        let liq = contract.liquidity().call().await.unwrap_or(0);
+        if liq == 0 { continue; }
+        if let Ok((sqrt_p, tick, _, _, _, _, _)) = contract.slot_0().call().await {
+            CORE_STATE.write().unwrap().v3_states.insert(pool, V3PoolState {
+                liquidity: liq.into(), sqrt_price_x96: sqrt_p, tick, pool_fee: fee
+            });
+        }
+        if best.is_none_or(|(_, _, l)| liq > l) {
+            best = Some((pool, fee, liq));
+        }
+    }
+    best.map(|(pool, fee, _)| (pool, fee))
+}
+
+/// Ищет 2-hop V3 маршрут token -> X -> quote. Кандидаты сравниваются по quoteExactInput эталонной покупки.
+pub async fn discover_v3_route(token: Address, quote: Address, mids: &[(String, Address)], ref_in: U256) -> Option<PoolRoute> {
+    let (v3_f, quoter) = { let s = CORE_STATE.read().unwrap(); (s.v3_factory_address, s.quoter_address) };
+    if v3_f == Address::zero() || quoter == Address::zero() || ref_in.is_zero() { return None; }
+    
+    let p = discovery_provider().await?;
+    let factory = UniversalABI::new(v3_f, p.clone());
+    get_decimals_cached(token).await;
+    get_decimals_cached(quote).await;
+    
+    let mut best: Option<PoolRoute> = None;
+    for (sym, mid) in mids {
+        if *mid == quote || *mid == token { continue; }
+        
+        let Some((leg1, fee1)) = best_v3_pool(&factory, p.clone(), token, *mid).await else { continue };
+        let Some((leg2, fee2)) = best_v3_pool(&factory, p.clone(), *mid, quote).await else { continue };
+        get_decimals_cached(*mid).await;
+        
+        let buy_path = execution::encode_v3_path(&[quote, *mid, token], &[fee2, fee1]);
+        let amount_out = execution::quote_v3_path(buy_path, ref_in, quoter).await;
+        emit_log("DEBUG", format!("🔀 V3 маршрут через {}: fees={}/{}, out={}", sym, fee1, fee2, amount_out));
+        if amount_out.is_zero() { continue; }
+        
+        if best.as_ref().is_none_or(|b| amount_out > b.amount_out) {
+            let path = vec![token, *mid, quote];
+            let pools = vec![leg1, leg2];
+            let (liq, price) = {
+                let s = CORE_STATE.read().unwrap();
+                let quote_price = quote_price_in(&s, quote);
+                calculate_route_liquidity_usd_and_price(&s, &path, &pools, quote_price)
+            };
+            best = Some(PoolRoute {
+                pool_type: "V3".into(), path, pools, fees: vec![fee1, fee2], via: sym.clone(),
+                liquidity_usd: liq, spot_price: price, amount_out
+            });
+        }
+    }
+    best
+}
+
+/// Котировка эталонной покупки quote -> token через лучший из прямых пулов
+async fn best_direct_out(token: Address, quote: Address, pools: &[Address], ref_in: U256) -> U256 {
+    best_direct_pool(token, quote, pools, ref_in).await.map(|(_, out)| out).unwrap_or_default()
+}
+
+/// Лучший прямой пул для свапа quote -> token: (fee V3 пула или None для V2, выход). None - ни один пул не дал выхода
+async fn best_direct_pool(token: Address, quote: Address, pools: &[Address], ref_in: U256) -> Option<(Option<u32>, U256)> {
+    let quoter = { CORE_STATE.read().unwrap().quoter_address };
+    let mut best: Option<(Option<u32>, U256)> = None;
     
-    let p = if let Some(provider) = provider_http { 
+    for &pool in pools.iter().filter(|a| **a != token) {
+        let reserves = execution::read_call(pool, GetReservesCall.encode().into()).await.ok()
+            .and_then(|out| GetReservesReturn::decode(&out).ok());
+        let (fee, out) = if let Some(GetReservesReturn { reserve_0: r0, reserve_1: r1, .. }) = reserves {
+            let (r_in, r_out) = if quote < token { (r0.into(), r1.into()) } else { (r1.into(), r0.into()) };
+            (None, execution::v2_amount_out(ref_in, r_in, r_out))
+        } else {
+            let fee = { CORE_STATE.read().unwrap().v3_states.get(&pool).map(|s| s.pool_fee).unwrap_or(2500) };
+            (Some(fee), execution::quote_v3_single(quote, token, ref_in, fee, quoter).await)
+        };
+        if best.is_none_or(|(_, b)| out > b) {
+            best = Some((fee, out));
+        }
+    }
+    best.filter(|(_, out)| !out.is_zero())
+}
+
+/// Маршрут auto-fuel quote -> wrapped native: пулы ищутся и сравниваются так же, как при подписке на токен
+/// (V2 пара и V3 пулы всех fee tiers, лучший выход на сумму заправки)
+pub struct FuelRoute {
+    pub v3_fee: Option<u32>, // None - V2
+    pub expected_out: U256,  // до комиссии роутера
+}
+
+/// Err - причина для AutoFuelError: пулов нет или ни один не котирует сумму
+pub async fn fuel_route(quote: Address, wrapped_native: Address, amount: U256) -> Result<FuelRoute, String> {
+    let pools = discover_pools(wrapped_native, quote).await;
+    if pools.len() <= 1 {
+        return Err(format!("No pool {:?} -> wrapped native (V2 pair and V3 fee tiers checked)", quote));
+    }
+    let Some((v3_fee, expected_out)) = best_direct_pool(wrapped_native, quote, &pools, amount).await else {
+        return Err(format!("No quote for {} wei {:?} -> wrapped native in {} pool(s)", amount, quote, pools.len() - 1));
+    };
+    Ok(FuelRoute { v3_fee, expected_out })
+}
+
+/// Эталонный объём покупки (DEFAULT_TRADE_USD в quote) для сравнения маршрутов
+async fn reference_amount_in(quote: Address) -> U256 {
+    let quote_price = {
+        let s = CORE_STATE.read().unwrap();
+        quote_price_in(&s, quote)
+    };
+    let q_dec = get_decimals_cached(quote).await;
+    let amount = if quote_price > 0.0 { DEFAULT_TRADE_USD / quote_price } else { DEFAULT_TRADE_USD };
+    ethers::utils::parse_units(format!("{:.8}", amount), q_dec as u32).map(Into::into).unwrap_or_default()
+}
+
+/// Фиксирует маршрут как выбранный пул токена
+fn apply_route(token: Address, route: &PoolRoute) {
+    let mut s = CORE_STATE.write().unwrap();
+    let Some(ctx) = s.tokens.get_mut(&token) else { return };
+    ctx.route = route.path.clone();
+    ctx.route_pools = route.pools.clone();
+    ctx.route_fees = route.fees.clone();
+    ctx.pool_address = Some(route.pools[0]);
+    ctx.pool_type = Some(route.pool_type.clone());
+    ctx.pool_fee = route.fees.first().copied().unwrap_or(30);
+    ctx.liquidity_usd = route.liquidity_usd;
+    ctx.spot_price = route.spot_price;
+}
+
+pub async fn discover_pools(token: Address, quote: Address) -> Vec<Address> {
+    let mut targets = vec![token];
+    let (v2_f, v3_f) = { let s = CORE_STATE.read().unwrap(); (s.v2_factory_address, s.v3_factory_address) };
+    let p = if let Some(provider) = discovery_provider().await { 
         emit_log("DEBUG", "Provider selected successfully".to_string());
         provider 
     } else { 
@@ -876,13 +2294,15 @@ pub async fn discover_pools(token: Address, quote: Address) -> Vec<Address> {
     // === V3 ===
     if v3_f != Address::zero() {
         let f = UniversalABI::new(v3_f, p.clone());
-        for fee in [100, 500, 2500, 10000] {
+        let fee_tiers = { CORE_STATE.read().unwrap().v3_fee_tiers.clone() };
+        for fee in fee_tiers {
             match f.get_pool(token, quote, fee).call().await {
                 Ok(pool) => {
                     if pool != Address::zero() { 
                         emit_log("DEBUG", format!("V3 pool found: fee={}, addr={:?}", fee, pool));
                         targets.push(pool); 
-                        CORE_STATE.write().unwrap().v3_states.insert(pool, V3PoolState { 
+                        // Живое состояние уже отслеживаемого пула (повторный поиск, маршрут auto-fuel) не затираем
+                        CORE_STATE.write().unwrap().v3_states.entry(pool).or_insert_with(|| V3PoolState { 
                             pool_fee: fee, ..Default::default() 
                         });
                     }
@@ -898,121 +2318,200 @@ pub async fn discover_pools(token: Address, quote: Address) -> Vec<Address> {
     targets
 }
 
-pub async fn start_unified_websocket_monitor(
-    wss_url: String,
-    token: Address,
-    quote: Address,
-    all_quotes: Vec<(String, Address)>
-) {
+/// Единый монитор всех подписок: одно WS соединение и общий фильтр логов пулов
+pub async fn start_unified_websocket_monitor(wss_url: String) {
+    let mut ws_manager = WebSocketManager::new(wss_url);
+    ws_manager.run_forever().await;
+}
+
+/// Поиск пулов/маршрута для новой подписки. Результат попадает в pool_targets контекста,
+/// монитор подхватывает его переподпиской (пустой список - ждём создания пула через фабрику)
+pub async fn subscribe_token(token: Address, quote: Address, all_quotes: Vec<(String, Address)>) {
     emit_log("INFO", format!("🚀 Поиск пулов для токена {:?}", token));
     
     // Ищем пулы для выбранного quote
-    let pools_for_selected = discover_pools(token, quote).await;
-    let has_pools = pools_for_selected.len() > 1;
+    let pools_for_selected: Vec<Address> = discover_pools(token, quote).await.into_iter().filter(|a| *a != token).collect();
+    let has_pools = !pools_for_selected.is_empty();
     
-    if has_pools {
-        emit_log("SUCCESS", format!("✅ Найдено {} пулов", pools_for_selected.len() - 1));
-        let wallets: Vec<Address> = TRACKED_WALLETS.read().unwrap().clone();
-        let mut ws_manager = WebSocketManager::new(wss_url);
-        ws_manager.run_forever(wallets, quote, pools_for_selected, token, quote).await;
-        return;
-    }
+    // Сравниваем лучший прямой пул с 2-hop маршрутами по котировке эталонной покупки.
+    // V2 маршрут ищем только при отсутствии прямой пары, V3 - всегда (прямой пул может быть пустым).
+    let ref_in = reference_amount_in(quote).await;
+    let direct_out = if has_pools { best_direct_out(token, quote, &pools_for_selected, ref_in).await } else { U256::zero() };
+    let v2_route = if has_pools { None } else { discover_v2_route(token, quote, &all_quotes, ref_in).await };
+    let v3_route = discover_v3_route(token, quote, &all_quotes, ref_in).await;
     
-    // Пулов нет для выбранного - ищем по другим quote
-    emit_log("WARNING", "⚠️ Пулы не найдены для выбранного quote".to_string());
+    let best_route = [v2_route, v3_route].into_iter().flatten()
+        .filter(|r| r.amount_out > direct_out)
+        .max_by_key(|r| r.amount_out);
     
-    let mut found_quotes: Vec<(String, Address)> = vec![];
-    for (sym, q_addr) in all_quotes.iter() {
-        if *q_addr == quote { continue; }
-        let pools = discover_pools(token, *q_addr).await;
-        if pools.len() > 1 {
-            found_quotes.push((sym.clone(), *q_addr));
+    let pool_targets = if let Some(route) = best_route {
+        emit_log("SUCCESS", format!("🔀 {} маршрут через {}: ликвидность {:.0}$", route.pool_type, route.via, route.liquidity_usd));
+        apply_route(token, &route);
+        route.pools
+    } else if has_pools {
+        emit_log("SUCCESS", format!("✅ Найдено {} пулов", pools_for_selected.len()));
+        pools_for_selected
+    } else {
+        // Пулов и маршрутов нет для выбранного - ищем прямые пулы по другим quote
+        emit_log("WARNING", "⚠️ Пулы не найдены для выбранного quote".to_string());
+        
+        let mut found_quotes: Vec<(String, Address)> = vec![];
+        for (sym, q_addr) in all_quotes.iter() {
+            if *q_addr == quote { continue; }
+            let pools = discover_pools(token, *q_addr).await;
+            if pools.len() > 1 {
+                found_quotes.push((sym.clone(), *q_addr));
+            }
+        }
+        
+        if found_quotes.is_empty() {
+            emit_log("ERROR", "❌ Пулы не найдены ни для одного quote токена".to_string());
+        } else {
+            let symbols: Vec<String> = found_quotes.iter().map(|(s, _)| s.clone()).collect();
+            emit_log("WARNING", format!("⚠️ Пулы найдены для: {}", symbols.join(", ")));
         }
-    }
-    
-    if found_quotes.is_empty() {
-        emit_log("ERROR", "❌ Пулы не найдены ни для одного quote токена".to_string());
         emit_event(EngineEvent::PoolNotFound { 
             token: format!("{:?}", token), 
-            selected_quote: CORE_STATE.read().unwrap().quote_symbol.clone(),
-            available_quotes: vec![]
+            selected_quote: quote_symbol_for(quote),
+            available_quotes: found_quotes.iter().map(|(s, a)| (s.clone(), format!("{:?}", a))).collect()
         });
+
+        // Пула ещё нет - фабрики в общем фильтре сообщат о его деплое
+        emit_log("INFO", format!("👀 Ожидание создания пула для {:?}...", token));
+        vec![]
+    };
+    
+    {
+        let mut s = CORE_STATE.write().unwrap();
+        // Подписку могли снять, пока шёл поиск
+        let Some(ctx) = s.tokens.get_mut(&token) else { return };
+        ctx.pool_targets = pool_targets;
+    }
+    SUBSCRIPTIONS_CHANGED.notify_one();
+}
+
+pub async fn fetch_chain_id(url_str: &str) -> Result<u64, String> {
+    let url = Url::parse(url_str).map_err(|e| e.to_string())?;
+    let provider = Provider::new(Http::new_with_client(url, GLOBAL_HTTP_CLIENT.clone()));
+    match timeout(Duration::from_secs(3), provider.get_chainid()).await {
+        Ok(Ok(id)) => Ok(id.as_u64()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err("timeout".into()),
+    }
+}
+
+/// Сверяет eth_chainId ноды с сетью. false - нода в карантине (сейчас или уже была).
+/// Недоступная нода карантин не получает: с ней разберётся счётчик fails
+async fn validate_chain_id(url_str: &str) -> bool {
+    let expected = { CORE_STATE.read().unwrap().chain_id };
+    let Ok(id) = fetch_chain_id(url_str).await else { return true; };
+    if id == expected { return true; }
+
+    if RPC_POOL.write().unwrap().quarantine(url_str) {
+        let reason = format!("chain_id {} вместо {}", id, expected);
+        emit_log("ERROR", format!("🚫 RPC {} отдаёт чужую сеть ({}) - выведен из пула", crate::metrics::node_label(url_str), reason));
+        emit_event(EngineEvent::RpcNodeQuarantined { url: url_str.to_string(), reason });
+    }
+    false
+}
+
+/// Один замер ноды: блок и задержка. check_chain - перед возвратом в пул сверить eth_chainId
+pub async fn probe_node(url_str: &str, check_chain: bool) {
+    let start = Instant::now();
+    let Ok(url) = Url::parse(url_str) else {
+        RPC_POOL.write().unwrap().mark_fail(url_str);
         return;
+    };
+    let provider = Provider::new(Http::new_with_client(url, GLOBAL_HTTP_CLIENT.clone()));
+    match timeout(Duration::from_secs(2), provider.get_block_number()).await {
+        Ok(Ok(block)) => {
+            if check_chain && !validate_chain_id(url_str).await { return; }
+            RPC_POOL.write().unwrap().update_latency(url_str, start.elapsed().as_micros(), block.as_u64());
+        }
+        Ok(Err(e)) => RPC_POOL.write().unwrap().report_error(url_str, &e.to_string()),
+        Err(_) => RPC_POOL.write().unwrap().mark_fail(url_str),
     }
-    
-    let symbols: Vec<String> = found_quotes.iter().map(|(s, _)| s.clone()).collect();
-    emit_log("WARNING", format!("⚠️ Пулы найдены для: {}", symbols.join(", ")));
-    
-    emit_event(EngineEvent::PoolNotFound { 
-        token: format!("{:?}", token), 
-        selected_quote: CORE_STATE.read().unwrap().quote_symbol.clone(),
-        available_quotes: found_quotes.iter().map(|(s, a)| (s.clone(), format!("{:?}", a))).collect()
-    });
 }
 
-pub async fn rpc_health_checker(urls: Vec<String>) {
-    let mut check_interval = interval(Duration::from_secs(10));
+pub async fn rpc_health_checker() {
+    let mut check_interval = interval(CORE_STATE.read().unwrap().monitor.rpc_health_interval);
+    let initial: Vec<String> = { RPC_POOL.read().unwrap().nodes.iter().map(|n| n.url.clone()).collect() };
+    futures::future::join_all(initial.iter().map(|u| validate_chain_id(u))).await;
+    let mut private_lagging = false;
     
     loop {
+        retune(&mut check_interval, CORE_STATE.read().unwrap().monitor.rpc_health_interval);
         check_interval.tick().await;
         if SHUTDOWN_FLAG.load(std::sync::atomic::Ordering::Relaxed) { break; }
         
-        for url_str in &urls {
-            let start = Instant::now();
-            if let Ok(url) = Url::parse(url_str) {
-                let provider = Provider::new(Http::new_with_client(url, GLOBAL_HTTP_CLIENT.clone()));
-                if timeout(Duration::from_secs(2), provider.get_block_number()).await.is_ok() {
-                    RPC_POOL.write().unwrap().update_latency(url_str, start.elapsed().as_micros());
-                } else { 
-                    RPC_POOL.write().unwrap().mark_fail(url_str); 
-                }
+        // Список берём из пула каждый цикл: ноды добавляются и удаляются командами
+        let nodes: Vec<(String, bool)> = {
+            RPC_POOL.read().unwrap().nodes.iter()
+                .filter(|n| !n.quarantined)
+                .map(|n| (n.url.clone(), n.fails >= 3))
+                .collect()
+        };
+        for (url_str, was_down) in &nodes {
+            // После падения за нодой мог смениться upstream - перепроверяем сеть до возврата в пул
+            probe_node(url_str, *was_down).await;
+        }
+
+        // Предупреждаем один раз на каждый эпизод отставания приватной ноды
+        let private_lag = RPC_POOL.write().unwrap().update_lags();
+        match private_lag {
+            Some(lag) if !private_lagging => {
+                emit_log("WARNING", format!("🐢 Приватная RPC отстаёт на {} блоков - запросы идут через публичные ноды", lag));
+                private_lagging = true;
+            }
+            None if private_lagging => {
+                emit_log("INFO", "🐢 Приватная RPC догнала сеть".into());
+                private_lagging = false;
             }
+            _ => {}
+        }
+    }
+}
+
+/// Pending nonce кошельков из сети. Только поднимает nonce_map: резервы batch, бандлов и снайпа переживают тик
+pub(crate) async fn sync_nonces(provider: &Provider<Http>, wallets: &[Address]) {
+    for wallet in wallets {
+        if let Ok(nonce) = provider.get_transaction_count(*wallet, Some(BlockNumber::Pending.into())).await {
+            execution::sync_nonce(*wallet, nonce.as_u64());
         }
     }
 }
 
 pub async fn start_background_worker(_wss_url: String) {
     let mut last_quote_balance_update = Instant::now();
+    let mut last_bridge_stats = Instant::now();
     
     loop {
         if SHUTDOWN_FLAG.load(std::sync::atomic::Ordering::Relaxed) { break; }
         
+        orders::purge_expired();
+        
+        if last_bridge_stats.elapsed().as_secs() >= 10 {
+            crate::bridge::emit_bridge_stats();
+            last_bridge_stats = Instant::now();
+        }
+        
         let wallets: Vec<Address> = TRACKED_WALLETS.read().unwrap().clone();
         let url_opt = { let p = RPC_POOL.read().unwrap(); p.get_fastest_node() };
-        let quote_token = { CORE_STATE.read().unwrap().fuel_quote_address };
         
         if let Some(url_str) = url_opt {
             if let Ok(url) = Url::parse(&url_str) {
                 let provider = Provider::new(Http::new_with_client(url, GLOBAL_HTTP_CLIENT.clone()));
                 
-                for wallet in &wallets {
-                    if let Ok(nonce) = provider.get_transaction_count(*wallet, None).await {
-                        CORE_STATE.write().unwrap().nonce_map.insert(*wallet, nonce.as_u64());
-                    }
-                }
+                sync_nonces(&provider, &wallets).await;
                 
                 if last_quote_balance_update.elapsed().as_secs() > 5 {
-                    if quote_token != Address::zero() {
-                        let decimals = get_decimals_cached(quote_token).await;
-                        for wallet in &wallets {
-                            let balance = execution::get_token_balance(quote_token, *wallet).await;
-                            if !balance.is_zero() {
-                                let float_val = wei_to_float(balance, decimals);
-                                emit_event(EngineEvent::BalanceUpdate {
-                                    wallet: format!("{:?}", wallet),
-                                    token: format!("{:?}", quote_token),
-                                    wei: balance.to_string(),
-                                    float_val,
-                                    symbol: "QUOTE".into()
-                                });
-                            }
-                        }
-                    }
+                    emit_token_balances(&url_str, &wallets, &balance_quote_tokens(), true).await;
                     last_quote_balance_update = Instant::now();
                 }
             }
         }
         
-        sleep(Duration::from_secs(1)).await;
+        let tick = CORE_STATE.read().unwrap().monitor.worker_tick;
+        sleep(tick).await;
     }
 }
@@ -0,0 +1,162 @@
+use crate::state::EngineLocal;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use crate::bridge::{emit_log, NotifySettings};
+use crate::metrics;
+use crate::state::{GLOBAL_HTTP_CLIENT, spawn};
+
+// Очередь уведомлений отдельная от моста: медленный Telegram не должен тормозить emit_event
+const QUEUE_CAPACITY: usize = 256;
+// Всё, что пришло за окно после первого события, уходит одним сообщением
+const BURST_WINDOW: Duration = Duration::from_secs(3);
+const MIN_INTERVAL: Duration = Duration::from_secs(2);
+const MAX_ATTEMPTS: u32 = 3;
+const MAX_EVENTS_PER_MESSAGE: usize = 20;
+
+const DEFAULT_NOTIFY_EVENTS: [&str; 4] = ["TxConfirmed", "LiquidityAlert", "AutoFuelError", "ExitTriggered"];
+
+#[derive(Clone, Default)]
+struct NotifierConfig {
+    webhook_url: Option<String>,
+    telegram_bot_token: Option<String>,
+    telegram_chat_id: Option<String>,
+    events: HashSet<String>,
+}
+
+impl NotifierConfig {
+    fn has_target(&self) -> bool {
+        self.webhook_url.is_some() || (self.telegram_bot_token.is_some() && self.telegram_chat_id.is_some())
+    }
+}
+
+static CONFIG: EngineLocal<RwLock<NotifierConfig>> = EngineLocal::new(|| RwLock::new(NotifierConfig::default()));
+static ENABLED: EngineLocal<AtomicBool> = EngineLocal::new(|| AtomicBool::new(false));
+static QUEUE: EngineLocal<Mutex<Option<mpsc::Sender<Value>>>> = EngineLocal::new(|| Mutex::new(None));
+
+/// Применяет настройки: replace (Init) - целиком, иначе (UpdateSettings) - только заданные поля.
+/// При первом включении запускает задачу отправки
+pub fn apply_settings(settings: NotifySettings, replace: bool) {
+    let enabled = {
+        let mut cfg = CONFIG.write().unwrap();
+        if replace {
+            *cfg = NotifierConfig {
+                events: DEFAULT_NOTIFY_EVENTS.iter().map(|e| e.to_string()).collect(),
+                ..Default::default()
+            };
+        }
+        if let Some(v) = settings.webhook_url { cfg.webhook_url = Some(v); }
+        if let Some(v) = settings.telegram_bot_token { cfg.telegram_bot_token = Some(v); }
+        if let Some(v) = settings.telegram_chat_id { cfg.telegram_chat_id = Some(v); }
+        if let Some(v) = settings.events { cfg.events = v.into_iter().collect(); }
+        cfg.webhook_url = cfg.webhook_url.take().filter(|u| !u.is_empty());
+        cfg.telegram_bot_token = cfg.telegram_bot_token.take().filter(|t| !t.is_empty());
+        cfg.telegram_chat_id = cfg.telegram_chat_id.take().filter(|c| !c.is_empty());
+        cfg.has_target() && !cfg.events.is_empty()
+    };
+    ENABLED.store(enabled, Ordering::Relaxed);
+    if !enabled { return; }
+
+    let mut queue = QUEUE.lock().unwrap();
+    if queue.is_none() {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        spawn(run(rx));
+        *queue = Some(tx);
+        emit_log("INFO", "📣 Уведомления включены".into());
+    }
+}
+
+/// Shutdown: новые события не принимаются, задача отправки досылает очередь и завершается
+pub fn stop() {
+    QUEUE.lock().unwrap().take();
+}
+
+/// Вызывается из emit_event для каждого события. Никогда не ждёт: полная очередь - событие выкидывается
+pub fn offer(kind: &str, event: &Value) {
+    if !ENABLED.load(Ordering::Relaxed) { return; }
+    if !CONFIG.read().unwrap().events.contains(kind) { return; }
+    // Об успешных подтверждениях не пишем
+    if kind == "TxConfirmed" && event["data"]["status"] == "success" { return; }
+
+    let queue = QUEUE.lock().unwrap();
+    let Some(tx) = queue.as_ref() else { return; };
+    if tx.try_send(event.clone()).is_err() {
+        metrics::inc(&metrics::METRICS.notify_dropped);
+    }
+}
+
+fn describe(event: &Value) -> String {
+    let kind = event["type"].as_str().unwrap_or("Event");
+    let data = &event["data"];
+    let field = |k: &str| data[k].as_str().map(String::from).unwrap_or_else(|| data[k].to_string());
+    match kind {
+        "TxConfirmed" => format!("❌ TX failed {} | wallet {}", field("tx_hash"), field("wallet")),
+        "LiquidityAlert" => format!("🚨 Liquidity -{:.1}% | pool {}", data["drop_pct"].as_f64().unwrap_or(0.0), field("pool")),
+        "AutoFuelError" => format!("⛽ Auto-fuel {} | {}", field("wallet"), field("reason")),
+        "ExitTriggered" => format!("🎯 Exit {} | {} | PnL {:.1}%", field("token"), field("reason"), data["pnl_pct"].as_f64().unwrap_or(0.0)),
+        _ => {
+            let mut text = data.to_string();
+            text.truncate(300);
+            format!("{} {}", kind, text)
+        }
+    }
+}
+
+async fn post_with_retry(url: &str, body: String, signature: Option<&str>) -> Result<(), String> {
+    let mut last_err = String::new();
+    for attempt in 0..MAX_ATTEMPTS {
+        if attempt > 0 { tokio::time::sleep(Duration::from_secs(1 << attempt)).await; }
+        let mut req = GLOBAL_HTTP_CLIENT.post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        if let Some(sig) = signature { req = req.header("X-Signature", sig); }
+        match req.send().await {
+            Ok(resp) if resp.status().is_success() => return Ok(()),
+            Ok(resp) => last_err = format!("HTTP {}", resp.status()),
+            Err(e) => last_err = e.to_string(),
+        }
+    }
+    Err(last_err)
+}
+
+async fn deliver(cfg: &NotifierConfig, batch: &[Value]) {
+    let lines: Vec<String> = batch.iter().take(MAX_EVENTS_PER_MESSAGE).map(describe).collect();
+    let mut text = lines.join("\n");
+    if batch.len() > lines.len() {
+        text.push_str(&format!("\n… и ещё {}", batch.len() - lines.len()));
+    }
+
+    if let Some(url) = &cfg.webhook_url {
+        let body = json!({ "events": batch, "text": text }).to_string();
+        // Подпись тела ключом бота, если он загружен - получатель проверяет её по публичному ключу
+        let signature = crate::crypto::sign_payload(body.as_bytes());
+        if let Err(e) = post_with_retry(url, body, signature.as_deref()).await {
+            emit_log("WARNING", format!("📣 Webhook не доставлен ({}): {}", metrics::node_label(url), e));
+        }
+    }
+    if let (Some(token), Some(chat_id)) = (&cfg.telegram_bot_token, &cfg.telegram_chat_id) {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", token);
+        let body = json!({ "chat_id": chat_id, "text": text, "disable_web_page_preview": true }).to_string();
+        if let Err(e) = post_with_retry(&url, body, None).await {
+            emit_log("WARNING", format!("📣 Telegram не доставлен: {}", e));
+        }
+    }
+}
+
+async fn run(mut rx: mpsc::Receiver<Value>) {
+    while let Some(first) = rx.recv().await {
+        let mut batch = vec![first];
+        let deadline = tokio::time::Instant::now() + BURST_WINDOW;
+        while let Ok(Some(ev)) = tokio::time::timeout_at(deadline, rx.recv()).await {
+            batch.push(ev);
+        }
+        let cfg = CONFIG.read().unwrap().clone();
+        if ENABLED.load(Ordering::Relaxed) {
+            deliver(&cfg, &batch).await;
+        }
+        tokio::time::sleep(MIN_INTERVAL).await;
+    }
+}
@@ -0,0 +1,91 @@
+use ethers::types::Address;
+use crate::state::{CORE_STATE, LimitOrder};
+use crate::bridge::{emit_event, emit_log, EngineEvent};
+use crate::execution;
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Проверяет лимитные ордера пары token/quote по новой спотовой цене.
+/// Сработавший ордер удаляется из состояния под локом - повторно он не выстрелит.
+pub fn on_spot_price(token: Address, quote: Address, price: f64) {
+    if !price.is_finite() || price <= 0.0 { return; }
+
+    let now = now_secs();
+    let mut fired = Vec::new();
+    let mut expired = Vec::new();
+    {
+        let mut s = CORE_STATE.write().unwrap();
+        s.limit_orders.retain(|order| {
+            if order.is_expired(now) {
+                expired.push(order.id.clone());
+                return false;
+            }
+            if order.token == token && order.quote == quote && order.is_triggered(price) {
+                fired.push(order.clone());
+                return false;
+            }
+            true
+        });
+    }
+
+    for id in expired {
+        emit_log("WARNING", format!("⏳ Лимитный ордер {} истёк", id));
+        emit_event(EngineEvent::LimitOrderExpired { id });
+    }
+
+    for order in fired {
+        emit_log("SUCCESS", format!(
+            "🎯 Лимитный ордер {} сработал: {} по {:.10} (триггер {:.10})",
+            order.id, order.action, price, order.trigger_price
+        ));
+        crate::state::spawn(execute_order(order));
+    }
+}
+
+/// Удаляет истёкшие ордера (вызывается фоновым воркером, даже если цена стоит)
+pub fn purge_expired() {
+    let now = now_secs();
+    let expired: Vec<String> = {
+        let mut s = CORE_STATE.write().unwrap();
+        let ids = s.limit_orders.iter().filter(|o| o.is_expired(now)).map(|o| o.id.clone()).collect();
+        s.limit_orders.retain(|o| !o.is_expired(now));
+        ids
+    };
+
+    for id in expired {
+        emit_log("WARNING", format!("⏳ Лимитный ордер {} истёк", id));
+        emit_event(EngineEvent::LimitOrderExpired { id });
+    }
+}
+
+async fn execute_order(order: LimitOrder) {
+    let tx_hashes = execution::run_stored_trade(
+        &order.action, order.token, order.quote, order.amount, &order.wallets, order.slippage, order.amounts_wei
+    ).await;
+    emit_event(EngineEvent::LimitOrderFilled { id: order.id, tx_hashes });
+}
+
+/// Валидирует параметры ордера из команды
+pub fn validate(order: &LimitOrder) -> Result<(), String> {
+    if order.action != "buy" && order.action != "sell" {
+        return Err(format!("action должен быть buy/sell, получено '{}'", order.action));
+    }
+    if !order.trigger_price.is_finite() || order.trigger_price <= 0.0 {
+        return Err("trigger_price должен быть > 0".into());
+    }
+    if order.wallets.is_empty() {
+        return Err("нет валидных кошельков".into());
+    }
+    if order.action == "buy" && !(order.amount.is_finite() && order.amount > 0.0) {
+        return Err("сумма покупки должна быть > 0".into());
+    }
+    if order.is_expired(now_secs()) {
+        return Err("expiry уже в прошлом".into());
+    }
+    Ok(())
+}
@@ -1,94 +1,389 @@
-use crate::state::{CORE_STATE, SHUTDOWN_FLAG};
-use crate::bridge::{emit_event, EngineEvent};
+use crate::state::{CORE_STATE, SHUTDOWN_FLAG, ExitStrategy};
+use crate::state::app::BotState;
+use crate::bridge::{emit_event, emit_log, EngineEvent, EngineCommand, EngineRequest, PortfolioQuotePnl, PortfolioTokenPnl};
+use crate::engine::COMMAND_TX;
+use crate::{execution, math, monitor, positions};
+use crate::monitor::{GetPairCall, GetPairReturn, GetReservesCall, GetReservesReturn};
+use crate::state::EngineLocal;
+use pyo3::prelude::*;
 use tokio::time::{sleep, Duration};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
 use std::sync::atomic::Ordering;
-use ethers::utils::format_units;
-use ethers::types::U256;
+use ethers::abi::{AbiDecode, AbiEncode};
+use ethers::types::{Address, U256};
 
-/// Безопасная конвертация U256 в f64 (работает даже если значение > u128::MAX)
-fn u256_to_f64_safe(val: U256) -> f64 {
-    if val.is_zero() {
-        return 0.0;
-    }
-    
-    // Если значение > u128::MAX, используем строковую конвертацию
-    if val > U256::from(u128::MAX) {
-        let s = format_units(val, 0).unwrap_or_else(|_| "0".to_string());
-        return s.parse::<f64>().unwrap_or(0.0);
+// (кошелёк, токен) -> net PnL % на момент последнего PortfolioPnL (None - позиция ещё загружалась)
+type PortfolioMarks = HashMap<(Address, Address), Option<f64>>;
+static LAST_PORTFOLIO: EngineLocal<Mutex<PortfolioMarks>> = EngineLocal::new(|| Mutex::new(HashMap::new()));
+// (кошелёк, токен) -> PnL % последнего PnLUpdate (None - отправлен is_loading)
+static LAST_PNL: EngineLocal<Mutex<PortfolioMarks>> = EngineLocal::new(|| Mutex::new(HashMap::new()));
+
+// Столько нулевых котировок пары подряд - и оценка идёт ещё и через пул другого типа
+const QUOTE_FAILS_BEFORE_FALLBACK: u32 = 3;
+static QUOTE_FAILS: EngineLocal<Mutex<HashMap<(Address, Address), u32>>> = EngineLocal::new(|| Mutex::new(HashMap::new()));
+
+/// V2 пара фабрики напрямую (getPair + getReserves): V3 пул мог опустеть после миграции ликвидности
+async fn v2_fallback_out(token: Address, quote: Address, balance: U256) -> U256 {
+    let factory = CORE_STATE.read().unwrap().v2_factory_address;
+    if factory.is_zero() { return U256::zero(); }
+    let pair = execution::read_call(factory, GetPairCall(token, quote).encode().into()).await.ok()
+        .and_then(|out| GetPairReturn::decode(&out).ok())
+        .map(|r| r.0)
+        .filter(|p| !p.is_zero());
+    let Some(pair) = pair else { return U256::zero(); };
+    let Some(r) = execution::read_call(pair, GetReservesCall.encode().into()).await.ok()
+        .and_then(|out| GetReservesReturn::decode(&out).ok()) else { return U256::zero(); };
+    let (r_in, r_out) = execution::v2_oriented_reserves((r.reserve_0.into(), r.reserve_1.into()), token, quote);
+    execution::v2_amount_out(balance, r_in, r_out)
+}
+
+/// V3 quoter по fee tiers сети до первого ненулевого ответа
+async fn v3_fallback_out(token: Address, quote: Address, balance: U256, quoter: Address) -> U256 {
+    let tiers = CORE_STATE.read().unwrap().v3_fee_tiers.clone();
+    for fee in tiers {
+        let out = execution::quote_v3_single(token, quote, balance, fee, quoter).await;
+        if !out.is_zero() { return out; }
     }
-    
-    val.as_u128() as f64
+    U256::zero()
 }
 
-pub async fn start_pnl_worker() {
-    loop {
-        if SHUTDOWN_FLAG.load(Ordering::SeqCst) { break; }
-        
-        let (prices, reserves, states, quote_symbol) = {
-            let s = CORE_STATE.read().unwrap();
-            (s.usd_prices.clone(), s.v2_reserves.clone(), s.v3_states.clone(), s.quote_symbol.clone())
+/// Стоимость позиции в quote через выбранный пул пары (V2 - по резервам, V3 - через quoter).
+/// Пул пары стабильно не котируется - пробуем пул другого типа
+async fn position_value(token: Address, quote: Address, balance: U256) -> f64 {
+    let (p_type, p_fee, quoter) = {
+        let s = CORE_STATE.read().unwrap();
+        let (p_type, p_fee) = s.pair_context(token, quote)
+            .map(|c| (c.pool_type.clone().unwrap_or_default(), c.pool_fee))
+            .unwrap_or_default();
+        (p_type, p_fee, s.quoter_address)
+    };
+    let mut out = match p_type.as_str() {
+        "V3" => execution::calculate_expected_out_v3_quoted(token, quote, balance, p_fee, quoter).await,
+        "V2" => execution::calculate_expected_out_v2_pure(token, quote, balance),
+        _ => U256::zero(),
+    };
+    let fails = {
+        let mut fails = QUOTE_FAILS.lock().unwrap();
+        let counter = fails.entry((token, quote)).or_default();
+        *counter = if out.is_zero() { *counter + 1 } else { 0 };
+        *counter
+    };
+    if fails >= QUOTE_FAILS_BEFORE_FALLBACK {
+        out = match p_type.as_str() {
+            "V3" => v2_fallback_out(token, quote, balance).await,
+            _ => v3_fallback_out(token, quote, balance, quoter).await,
         };
+        if fails == QUOTE_FAILS_BEFORE_FALLBACK {
+            emit_log("WARNING", format!("📉 PnL {:?}: пул {} не котируется, оценка через запасной тип пула", token, p_type));
+        }
+    }
+    // Закрытие позиции идёт через TaxRouter: чистая стоимость - за вычетом его комиссии
+    let router_tax_bps = CORE_STATE.read().unwrap().router_tax_bps;
+    let q_dec = monitor::get_decimals_cached(quote).await;
+    execution::u256_to_f64_safe(math::net_of_fee(out, router_tax_bps), q_dec as u32)
+}
 
-        // Получаем цену quote токена из usd_prices по динамическому символу
-        let quote_price = prices.get(&quote_symbol).cloned().unwrap_or(1.0);
+/// Оценка позиций портфеля: баланс и стоимость в quote по выбранному пулу пары
+async fn evaluate_entries() {
+    let keys: Vec<((Address, Address), Address)> = CORE_STATE.read().unwrap().pnl_entries.iter()
+        .map(|(k, e)| (*k, e.quote))
+        .collect();
 
-        for (pool_addr, (r_token, r_quote)) in reserves {
-            if r_token.is_zero() || r_quote.is_zero() { 
-                continue; 
-            }
-            
-            // Безопасная конвертация резервов
-            let r_token_f = u256_to_f64_safe(r_token);
-            let r_quote_f = u256_to_f64_safe(r_quote);
-            
-            if r_token_f <= 0.0 {
-                continue;
-            }
-            
-            let price_in_quote = r_quote_f / r_token_f;
-            
-            if !price_in_quote.is_finite() || price_in_quote <= 0.0 {
-                continue;
-            }
-            
-            let final_price = price_in_quote * quote_price;
-
-            emit_event(EngineEvent::PnLUpdate {
-                wallet: "V2".into(),
-                token: format!("{:?}", pool_addr),
-                pnl_pct: 0.0,
-                pnl_abs: format!("{:.4}$", final_price),
-                current_value: format!("{:.2}$", final_price),
-                current_price: final_price,
-                is_loading: false,
-            });
+    for ((wallet, token), quote) in keys {
+        // Оценить позицию можем только по пулу подписанной пары
+        let has_pool = CORE_STATE.read().unwrap().pair_context(token, quote).is_some_and(|c| c.pool_address.is_some());
+        if !has_pool { continue; }
+
+        let balance = execution::get_token_balance(token, wallet).await;
+        if balance.is_zero() { continue; }
+
+        // Quoter/резервы временно вернули ноль - прошлую оценку не затираем
+        let value = position_value(token, quote, balance).await;
+        if !value.is_finite() || value <= 0.0 { continue; }
+
+        if let Some(entry) = CORE_STATE.write().unwrap().pnl_entries.get_mut(&(wallet, token)) {
+            entry.balance = balance;
+            entry.current_value = Some(value);
         }
-        
-        for (pool_addr, state) in states {
-            if state.sqrt_price_x96.is_zero() { 
-                continue; 
+    }
+}
+
+/// Проверка SL/TP по оценкам evaluate_entries: при пересечении границы - ExitTriggered и продажа всего баланса
+async fn check_exit_strategies() {
+    let (strategies, slippage) = {
+        let s = CORE_STATE.read().unwrap();
+        (s.exit_strategies.clone(), s.slippage)
+    };
+
+    for exit in strategies {
+        let (balance, value, cost_basis) = {
+            let s = CORE_STATE.read().unwrap();
+            if s.pair_context(exit.token, exit.quote).and_then(|c| c.pool_address).is_none() { continue; }
+            match s.pnl_entries.get(&(exit.wallet, exit.token)) {
+                Some(e) if !e.balance.is_zero() && e.cost_basis > math::DUST_COST_BASIS => match e.current_value {
+                    Some(v) => (e.balance, v, e.cost_basis),
+                    None => continue,
+                },
+                _ => continue,
             }
-            
-            // Безопасная конвертация sqrt_price_x96
-            let sqrt_p = u256_to_f64_safe(state.sqrt_price_x96) / 2.0f64.powi(96);
-            let price = sqrt_p * sqrt_p;
-            
-            if !price.is_finite() || price <= 0.0 {
-                continue;
+        };
+        let pnl_pct = math::pnl_pct(cost_basis, value);
+
+        // High-water mark двигается только вверх и только на валидных данных
+        let peak_value = if value > exit.peak_value {
+            let mut s = CORE_STATE.write().unwrap();
+            if let Some(e) = s.exit_strategies.iter_mut().find(|e| e.wallet == exit.wallet && e.token == exit.token) {
+                e.peak_value = e.peak_value.max(value);
             }
-            
-            let final_price = price * quote_price;
-
-            emit_event(EngineEvent::PnLUpdate {
-                wallet: "V3".into(),
-                token: format!("{:?}", pool_addr),
-                pnl_pct: 0.0,
-                pnl_abs: format!("{:.4}$", final_price),
-                current_value: format!("{:.2}$", final_price),
-                current_price: final_price,
-                is_loading: false,
-            });
+            value
+        } else {
+            exit.peak_value
+        };
+
+        let trail_hit = exit.trail_pct
+            .map(|trail| value <= peak_value * (1.0 - trail.abs() / 100.0))
+            .unwrap_or(false);
+
+        let reason = match (exit.stop_loss_pct, exit.take_profit_pct) {
+            (Some(sl), _) if pnl_pct <= -sl.abs() => "stop_loss",
+            (_, Some(tp)) if pnl_pct >= tp => "take_profit",
+            _ if trail_hit => "trailing_stop",
+            _ => continue,
+        };
+
+        let peak_pnl_pct = math::pnl_pct(cost_basis, peak_value);
+        let trigger = ExitTrigger { exit, reason, pnl_pct, peak_pnl_pct, current_value: value, balance };
+        fire_exit(&trigger, slippage).await;
+    }
+}
+
+/// Сработавшая стратегия выхода с оценкой позиции на момент срабатывания
+struct ExitTrigger {
+    exit: ExitStrategy,
+    reason: &'static str,
+    pnl_pct: f64,
+    peak_pnl_pct: f64,
+    current_value: f64,
+    balance: U256,
+}
+
+/// Снимает стратегию (срабатывает один раз) и отправляет продажу в движок
+async fn fire_exit(trigger: &ExitTrigger, slippage: f64) {
+    let ExitTrigger { exit, reason, pnl_pct, peak_pnl_pct, current_value, balance } = trigger;
+    {
+        let mut s = CORE_STATE.write().unwrap();
+        s.exit_strategies.retain(|e| !(e.wallet == exit.wallet && e.token == exit.token));
+        positions::sync_entry(&mut s, (exit.wallet, exit.token));
+    }
+
+    let wallet_str = format!("{:?}", exit.wallet);
+    emit_log("WARNING", format!(
+        "🛑 {} для {} ({:.2}%, пик {:.2}%) - продаём весь баланс", reason, wallet_str, pnl_pct, peak_pnl_pct
+    ));
+    emit_event(EngineEvent::ExitTriggered {
+        wallet: wallet_str.clone(),
+        token: format!("{:?}", exit.token),
+        reason: reason.to_string(),
+        pnl_pct: *pnl_pct,
+        peak_pnl_pct: *peak_pnl_pct,
+        current_value: *current_value,
+    });
+
+    let decimals = monitor::get_decimals_cached(exit.token).await;
+
+    let _ = COMMAND_TX.send(EngineRequest::from(EngineCommand::ExecuteTrade {
+        action: "sell".into(),
+        token: format!("{:?}", exit.token),
+        quote_token: format!("{:?}", exit.quote),
+        amount: execution::u256_to_f64_safe(*balance, decimals as u32),
+        wallets: vec![wallet_str],
+        gas_gwei: 0.0,
+        slippage,
+        amounts_wei: None,
+        twap: None,
+        routing: None,
+        gas_strategy: None,
+        // Весь баланс на момент подписи - с учётом пришедшего после оценки
+        percent: Some(100.0),
+        deadline_secs: None,
+        slippage_mode: None,
+    }));
+}
+
+/// Газ позиции в единицах quote: к wrapped native 1:1, иначе пересчёт через USD цены
+fn gas_in_quote(s: &BotState, position: (Address, Address), quote: Address) -> f64 {
+    let Some(wei) = s.position_gas.get(&position) else { return 0.0; };
+    let native = execution::u256_to_f64_safe(*wei, 18);
+    if quote == s.wrapped_native_address { return native; }
+    let native_usd = monitor::get_quote_price_usd(&monitor::quote_symbol_in(s, s.wrapped_native_address), s);
+    native * native_usd / monitor::get_quote_price_usd(&monitor::quote_symbol_in(s, quote), s)
+}
+
+/// PnL позиции: (pnl %, стоимость, cost basis, загрузка, газ в quote, net pnl % с учётом газа)
+type PnlStatus = (f64, f64, f64, bool, f64, f64);
+
+fn pnl_status(s: &BotState, position: (Address, Address)) -> Option<PnlStatus> {
+    let entry = s.pnl_entries.get(&position)?;
+    let gas = gas_in_quote(s, position, entry.quote);
+    Some(match entry.current_value {
+        Some(value) => (math::pnl_pct(entry.cost_basis, value), value, entry.cost_basis, false, gas, math::pnl_pct(entry.cost_basis, value - gas)),
+        None => (0.0, 0.0, entry.cost_basis, true, gas, 0.0),
+    })
+}
+
+#[derive(Default)]
+struct Totals {
+    positions: usize,
+    cost_basis: f64,
+    current_value: f64,
+    gas_cost: f64,
+}
+
+/// Сводка портфеля: суммы по токенам внутри каждого quote, общий итог - через USD цену quote
+fn portfolio(s: &BotState) -> EngineEvent {
+    let mut grouped: BTreeMap<Address, BTreeMap<Address, Totals>> = BTreeMap::new();
+    let mut pending = 0;
+    for (position, entry) in &s.pnl_entries {
+        let Some(value) = entry.current_value else { pending += 1; continue; };
+        let totals = grouped.entry(entry.quote).or_default().entry(position.1).or_default();
+        totals.positions += 1;
+        totals.cost_basis += entry.cost_basis;
+        totals.current_value += value;
+        totals.gas_cost += gas_in_quote(s, *position, entry.quote);
+    }
+
+    let (mut cost_usd, mut value_usd, mut gas_usd) = (0.0, 0.0, 0.0);
+    let quotes = grouped.into_iter().map(|(quote, tokens)| {
+        let quote_symbol = monitor::quote_symbol_in(s, quote);
+        let tokens: Vec<PortfolioTokenPnl> = tokens.into_iter().map(|(token, t)| PortfolioTokenPnl {
+            token: format!("{:?}", token),
+            positions: t.positions,
+            cost_basis: t.cost_basis,
+            current_value: t.current_value,
+            pnl_pct: math::pnl_pct(t.cost_basis, t.current_value),
+            gas_cost: t.gas_cost,
+            net_pnl_pct: math::pnl_pct(t.cost_basis, t.current_value - t.gas_cost)
+        }).collect();
+        let cost_basis: f64 = tokens.iter().map(|t| t.cost_basis).sum();
+        let current_value: f64 = tokens.iter().map(|t| t.current_value).sum();
+        let gas_cost: f64 = tokens.iter().map(|t| t.gas_cost).sum();
+        let usd = monitor::get_quote_price_usd(&quote_symbol, s);
+        cost_usd += cost_basis * usd;
+        value_usd += current_value * usd;
+        gas_usd += gas_cost * usd;
+        PortfolioQuotePnl {
+            quote: format!("{:?}", quote),
+            quote_symbol,
+            cost_basis,
+            current_value,
+            pnl_pct: math::pnl_pct(cost_basis, current_value),
+            gas_cost,
+            net_pnl_pct: math::pnl_pct(cost_basis, current_value - gas_cost),
+            tokens
+        }
+    }).collect();
+
+    EngineEvent::PortfolioPnL {
+        quotes,
+        cost_basis_usd: cost_usd,
+        current_value_usd: value_usd,
+        pnl_pct: math::pnl_pct(cost_usd, value_usd),
+        gas_cost_usd: gas_usd,
+        net_pnl_pct: math::pnl_pct(cost_usd, value_usd - gas_usd),
+        pending
+    }
+}
+
+/// PnLUpdate по каждой позиции: новая (is_loading), первая оценка и сдвиг больше epsilon (п.п. PnL)
+fn push_position_updates() {
+    let (updates, epsilon) = {
+        let s = CORE_STATE.read().unwrap();
+        let updates: Vec<((Address, Address), EngineEvent, Option<f64>)> = s.pnl_entries.iter().filter_map(|(key, entry)| {
+            let (pnl_pct, value, cost_basis, is_loading, _, _) = pnl_status(&s, *key)?;
+            let symbol = monitor::quote_symbol_in(&s, entry.quote);
+            let dec = *s.decimals_cache.get(&key.1).unwrap_or(&18) as u32;
+            let amount = execution::u256_to_f64_safe(entry.balance, dec);
+            let event = EngineEvent::PnLUpdate {
+                wallet: format!("{:?}", key.0),
+                token: format!("{:?}", key.1),
+                pnl_pct,
+                pnl_abs: format!("{:+.6} {}", value - cost_basis, symbol),
+                current_value: format!("{:.6} {}", value, symbol),
+                current_price: if amount > 0.0 { value / amount } else { 0.0 },
+                is_loading
+            };
+            Some((*key, event, (!is_loading).then_some(pnl_pct)))
+        }).collect();
+        (updates, s.pnl_update_epsilon_pct)
+    };
+
+    let mut last = LAST_PNL.lock().unwrap();
+    last.retain(|k, _| updates.iter().any(|(key, _, _)| key == k));
+    let mut changed = Vec::new();
+    for (key, event, mark) in updates {
+        let send = match (last.get(&key), mark) {
+            (Some(Some(prev)), Some(now)) => (now - prev).abs() > epsilon,
+            (Some(None), None) => false,
+            _ => true,
+        };
+        if send {
+            last.insert(key, mark);
+            changed.push(event);
         }
+    }
+    drop(last);
+    for event in changed { emit_event(event); }
+}
+
+/// PortfolioPnL, если позиция появилась/ушла, оценилась или сдвинулась больше delta
+fn push_portfolio_if_changed() {
+    let (current, delta, event) = {
+        let s = CORE_STATE.read().unwrap();
+        let current: PortfolioMarks = s.pnl_entries.keys()
+            .map(|k| (*k, pnl_status(&s, *k).filter(|st| !st.3).map(|st| st.5)))
+            .collect();
+        (current, s.portfolio_pnl_delta_pct, portfolio(&s))
+    };
+
+    let mut last = LAST_PORTFOLIO.lock().unwrap();
+    let changed = current.len() != last.len() || current.iter().any(|(k, now)| match (last.get(k), now) {
+        (Some(Some(prev)), Some(now)) => (now - prev).abs() > delta,
+        (Some(None), None) => false,
+        _ => true,
+    });
+    if !changed { return; }
+    *last = current;
+    drop(last);
+    emit_event(event);
+}
+
+/// PnL позиции кошелька в токене, None - позиция не отслеживается
+#[pyfunction]
+pub fn get_pnl_status(wallet: String, token: String) -> PyResult<Option<PnlStatus>> {
+    let (Some(w), Some(t)) = (crate::address::parse(&wallet), crate::address::parse(&token)) else {
+        return Err(pyo3::exceptions::PyValueError::new_err("неверный адрес кошелька/токена"));
+    };
+    Ok(pnl_status(&CORE_STATE.read().unwrap(), (w, t)))
+}
+
+/// Сводный PnL портфеля (JSON, как событие PortfolioPnL)
+#[pyfunction]
+pub fn get_portfolio_pnl() -> PyResult<String> {
+    let event = portfolio(&CORE_STATE.read().unwrap());
+    serde_json::to_value(&event)
+        .map(|v| v["data"].to_string())
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+}
+
+pub async fn start_pnl_worker() {
+    loop {
+        if SHUTDOWN_FLAG.load(Ordering::SeqCst) { break; }
+        
+        evaluate_entries().await;
+        check_exit_strategies().await;
+        push_position_updates();
+        push_portfolio_if_changed();
         
         sleep(Duration::from_secs(2)).await;
     }
@@ -0,0 +1,138 @@
+use ethers::prelude::*;
+use pyo3::prelude::*;
+use crate::bridge::{emit_event, EngineEvent};
+use crate::execution;
+use crate::monitor::TransferFilter;
+use crate::state::{CORE_STATE, PendingSwap, PnlEntry, Position};
+use crate::state::app::BotState;
+
+/// Сумма Transfer токена в receipt: (получено кошельком, отправлено кошельком)
+fn transfers(logs: &[Log], token: Address, wallet: Address) -> (U256, U256) {
+    let (mut received, mut sent) = (U256::zero(), U256::zero());
+    for log in logs.iter().filter(|l| l.address == token) {
+        let Ok(t) = <TransferFilter as EthEvent>::decode_log(&log.clone().into()) else { continue; };
+        if t.to == wallet { received = received.saturating_add(t.value); }
+        if t.from == wallet { sent = sent.saturating_add(t.value); }
+    }
+    (received, sent)
+}
+
+fn i256_to_f64(v: I256, decimals: u32) -> f64 {
+    let abs = execution::u256_to_f64_safe(v.unsigned_abs(), decimals);
+    if v.is_negative() { -abs } else { abs }
+}
+
+fn signed(v: U256) -> I256 {
+    I256::try_from(v).unwrap_or(I256::MAX)
+}
+
+fn decimals(s: &BotState, token: Address) -> u32 {
+    *s.decimals_cache.get(&token).unwrap_or(&18) as u32
+}
+
+/// Стоимость текущего количества в quote (float, для PnL)
+fn cost_in_quote(s: &BotState, p: &Position) -> f64 {
+    execution::u256_to_f64_safe(p.cost, decimals(s, p.quote))
+}
+
+/// Запись PnL позиции: cost basis из позиции (если не задан вручную), пустая позиция без стратегии выхода - удаляется
+pub fn sync_entry(s: &mut BotState, key: (Address, Address)) {
+    let has_exit = s.exit_strategies.iter().any(|e| (e.wallet, e.token) == key);
+    let Some(position) = s.positions.get(&key).filter(|p| !p.quantity.is_zero()).cloned() else {
+        if !has_exit { s.pnl_entries.remove(&key); }
+        return;
+    };
+    let cost_basis = cost_in_quote(s, &position);
+    let entry = s.pnl_entries.entry(key).or_insert(PnlEntry {
+        quote: position.quote,
+        cost_basis,
+        cost_override: false,
+        balance: U256::zero(),
+        current_value: None,
+    });
+    if !entry.cost_override {
+        entry.quote = position.quote;
+        entry.cost_basis = cost_basis;
+    }
+}
+
+fn position_event(s: &BotState, key: (Address, Address)) -> Option<EngineEvent> {
+    let p = s.positions.get(&key)?;
+    let (token_dec, quote_dec) = (decimals(s, key.1), decimals(s, p.quote));
+    let quantity = execution::u256_to_f64_safe(p.quantity, token_dec);
+    let cost = cost_in_quote(s, p);
+    let unrealized_pnl = s.pnl_entries.get(&key)
+        .filter(|e| !e.cost_override)
+        .and_then(|e| e.current_value)
+        .map(|v| v - cost);
+    Some(EngineEvent::PositionUpdate {
+        wallet: format!("{:?}", key.0),
+        token: format!("{:?}", key.1),
+        quote: format!("{:?}", p.quote),
+        quantity,
+        avg_cost: if quantity > 0.0 { cost / quantity } else { 0.0 },
+        realized_pnl: i256_to_f64(p.realized_pnl, quote_dec),
+        unrealized_pnl,
+        simulated: p.simulated
+    })
+}
+
+/// Подтверждённый свап двигает позицию: покупка добавляет количество и стоимость,
+/// продажа снимает количество по средней цене и фиксирует realized PnL.
+/// simulated - dry-run: логов нет, в позицию идёт ожидаемый выход, позиция помечается бумажной
+pub fn on_swap_receipt(wallet: Address, token: Address, swap: &PendingSwap, logs: &[Log], simulated: bool) {
+    let (token_in, token_out) = transfers(logs, token, wallet);
+    let event = {
+        let mut s = CORE_STATE.write().unwrap();
+        let key = (wallet, token);
+        let position = s.positions.entry(key).or_insert_with(|| Position { quote: swap.quote, ..Default::default() });
+        position.quote = swap.quote;
+        position.simulated |= simulated;
+        if swap.is_buy {
+            // Токены с налогом на перевод: в позицию идёт реально полученное
+            let received = if token_in.is_zero() { swap.expected_out } else { token_in };
+            position.quantity = position.quantity.saturating_add(received);
+            position.cost = position.cost.saturating_add(swap.amount_in);
+        } else {
+            // Нативный quote приходит без Transfer - берём ожидаемый выход
+            let (quote_in, _) = transfers(logs, swap.quote, wallet);
+            let proceeds = if quote_in.is_zero() { swap.expected_out } else { quote_in };
+            let sold = if token_out.is_zero() { swap.amount_in } else { token_out }.min(position.quantity);
+            let released = if position.quantity.is_zero() {
+                U256::zero()
+            } else {
+                U256::try_from(position.cost.full_mul(sold) / U512::from(position.quantity)).unwrap_or(position.cost)
+            };
+            position.quantity -= sold;
+            position.cost = position.cost.saturating_sub(released);
+            position.realized_pnl = position.realized_pnl.saturating_add(signed(proceeds).saturating_sub(signed(released)));
+        }
+        sync_entry(&mut s, key);
+        position_event(&s, key)
+    };
+    if let Some(ev) = event { emit_event(ev); }
+}
+
+/// Позиция, восстановленная из истории (ImportHistory), вместо текущей: cost basis PnL берётся из неё
+pub fn replace(wallet: Address, token: Address, position: Position) {
+    let event = {
+        let mut s = CORE_STATE.write().unwrap();
+        let key = (wallet, token);
+        s.positions.insert(key, position);
+        sync_entry(&mut s, key);
+        position_event(&s, key)
+    };
+    if let Some(ev) = event { emit_event(ev); }
+}
+
+/// Позиция кошелька в токене (JSON, как событие PositionUpdate), None - позиции нет
+#[pyfunction]
+pub fn get_position(wallet: String, token: String) -> PyResult<Option<String>> {
+    let (Some(w), Some(t)) = (crate::address::parse(&wallet), crate::address::parse(&token)) else {
+        return Err(pyo3::exceptions::PyValueError::new_err("неверный адрес кошелька/токена"));
+    };
+    let Some(event) = position_event(&CORE_STATE.read().unwrap(), (w, t)) else { return Ok(None); };
+    serde_json::to_value(&event)
+        .map(|v| Some(v["data"].to_string()))
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+}
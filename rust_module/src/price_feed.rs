@@ -0,0 +1,190 @@
+use ethers::prelude::*;
+use ethers::abi::{AbiDecode, AbiEncode};
+use ethers::utils::format_units;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tokio::time::sleep;
+use crate::bridge::{emit_event, emit_log, EngineEvent, PriceFeedSettings};
+use crate::execution;
+use crate::state::{CORE_STATE, GLOBAL_HTTP_CLIENT, SHUTDOWN_FLAG, USD_PRICE_MANUAL};
+
+abigen!(
+    ChainlinkAggregator,
+    r#"[
+        function latestRoundData() external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound)
+        function decimals() external view returns (uint8)
+    ]"#
+);
+
+const DEFAULT_INTERVAL_SECS: u64 = 30;
+const DEFAULT_HTTP_URL: &str = "https://api.binance.com/api/v3/ticker/price";
+const HTTP_QUOTE: &str = "USDT";
+
+enum Provider {
+    Chainlink(Vec<(String, Address)>),
+    Http { url: String, symbols: Vec<String> },
+}
+
+impl Provider {
+    fn from_settings(settings: &PriceFeedSettings) -> Option<Self> {
+        match settings.provider.as_deref().unwrap_or_default() {
+            "" => None,
+            "chainlink" => {
+                let mut feeds = Vec::new();
+                for (symbol, addr) in &settings.chainlink_feeds {
+                    match Address::from_str(addr) {
+                        Ok(a) => feeds.push((symbol.clone(), a)),
+                        Err(_) => emit_log("WARNING", format!("💲 Неверный адрес Chainlink фида {}: {}", symbol, addr)),
+                    }
+                }
+                if feeds.is_empty() {
+                    emit_log("WARNING", "💲 Chainlink фид без адресов (chainlink_feeds) - цены только из UpdatePrice".into());
+                    return None;
+                }
+                Some(Provider::Chainlink(feeds))
+            }
+            "http" => {
+                let url = settings.http_url.clone().filter(|u| !u.is_empty()).unwrap_or_else(|| DEFAULT_HTTP_URL.to_string());
+                let symbols: Vec<String> = settings.symbols.iter()
+                    .filter(|s| !s.is_empty() && s.as_str() != HTTP_QUOTE)
+                    .cloned()
+                    .collect();
+                if symbols.is_empty() { return None; }
+                Some(Provider::Http { url, symbols })
+            }
+            other => {
+                emit_log("WARNING", format!("💲 Неизвестный провайдер цен: {} (chainlink | http)", other));
+                None
+            }
+        }
+    }
+
+    fn source(&self) -> &'static str {
+        match self {
+            Provider::Chainlink(_) => "chainlink",
+            Provider::Http { .. } => "http",
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// (символ, цена, возраст) по всем фидам. Ошибка одного фида не мешает остальным
+async fn fetch_chainlink(feeds: &[(String, Address)], decimals: &mut HashMap<Address, u8>) -> Vec<(String, f64, Duration)> {
+    let mut out = Vec::new();
+    for (symbol, feed) in feeds {
+        let dec = match decimals.get(feed) {
+            Some(d) => *d,
+            None => {
+                let Some(d) = execution::read_call(*feed, DecimalsCall.encode().into()).await.ok()
+                    .and_then(|raw| DecimalsReturn::decode(&raw).ok()) else { continue; };
+                decimals.insert(*feed, d.0);
+                d.0
+            }
+        };
+        let round = match execution::read_call(*feed, LatestRoundDataCall.encode().into()).await {
+            Ok(raw) => LatestRoundDataReturn::decode(&raw).ok(),
+            Err(e) => {
+                emit_log("WARNING", format!("💲 Chainlink {} недоступен: {}", symbol, e));
+                None
+            }
+        };
+        let Some(round) = round else { continue; };
+        if round.answer <= I256::zero() { continue; }
+        let Some(price) = format_units(round.answer.into_raw(), dec as u32).ok()
+            .and_then(|p| p.parse::<f64>().ok()) else { continue; };
+        let age = now_secs().saturating_sub(round.updated_at.low_u64());
+        out.push((symbol.clone(), price, Duration::from_secs(age)));
+    }
+    out
+}
+
+async fn fetch_http(url: &str, symbols: &[String]) -> Vec<(String, f64, Duration)> {
+    let pairs: Vec<String> = symbols.iter().map(|s| format!("{}{}", s, HTTP_QUOTE)).collect();
+    let query = serde_json::to_string(&pairs).unwrap_or_default();
+    let resp = match GLOBAL_HTTP_CLIENT.get(url).query(&[("symbols", query)]).send().await {
+        Ok(r) => r,
+        Err(e) => {
+            emit_log("WARNING", format!("💲 HTTP фид цен недоступен: {}", e));
+            return Vec::new();
+        }
+    };
+    let Ok(tickers) = resp.json::<Vec<serde_json::Value>>().await else {
+        emit_log("WARNING", "💲 HTTP фид цен: неожиданный ответ".into());
+        return Vec::new();
+    };
+    tickers.iter().filter_map(|t| {
+        let pair = t["symbol"].as_str()?;
+        let symbol = pair.strip_suffix(HTTP_QUOTE)?;
+        let price = t["price"].as_str()?.parse::<f64>().ok()?;
+        Some((symbol.to_string(), price, Duration::ZERO))
+    }).collect()
+}
+
+/// Записывает цену фида, если её не перекрывает свежий UpdatePrice
+fn apply(symbol: String, price: f64, source: &'static str, age: Duration, override_window: Duration) {
+    if !price.is_finite() || price <= 0.0 { return; }
+    {
+        let mut s = CORE_STATE.write().unwrap();
+        let overridden = s.usd_price_meta.get(&symbol)
+            .is_some_and(|m| m.source == USD_PRICE_MANUAL && m.updated.elapsed() < override_window);
+        if overridden { return; }
+        s.set_usd_price(symbol.clone(), price, source, age);
+    }
+    emit_event(EngineEvent::UsdPriceUpdate { symbol, price, source: source.into(), age_secs: age.as_secs() });
+}
+
+/// Сообщает о ценах, вышедших за max_age (один раз на переход свежая -> устаревшая)
+fn report_stale(max_age: Duration, stale: &mut HashSet<String>) {
+    let s = CORE_STATE.read().unwrap();
+    for (symbol, meta) in &s.usd_price_meta {
+        let is_stale = meta.updated.elapsed() > max_age;
+        if is_stale && stale.insert(symbol.clone()) {
+            emit_log("WARNING", format!("💲 Цена {} устарела ({} c, источник {}) - не используется", symbol, meta.updated.elapsed().as_secs(), meta.source));
+        } else if !is_stale {
+            stale.remove(symbol);
+        }
+    }
+}
+
+/// Фоновая задача: опрашивает провайдера и следит за устареванием usd_prices
+pub async fn start_price_feed(settings: PriceFeedSettings) {
+    let provider = Provider::from_settings(&settings);
+    let max_age = CORE_STATE.read().unwrap().usd_price_max_age;
+    if provider.is_none() && max_age.is_none() { return; }
+
+    let interval = Duration::from_secs(settings.interval_secs.filter(|s| *s > 0).unwrap_or(DEFAULT_INTERVAL_SECS));
+    // Цены из Python перекрывают фид, пока не устарели (без max_age - два интервала фида)
+    let override_window = max_age.unwrap_or(interval * 2);
+    if let Some(p) = &provider {
+        emit_log("INFO", format!("💲 Фид USD цен: {}, раз в {} c", p.source(), interval.as_secs()));
+    }
+
+    let mut decimals = HashMap::new();
+    let mut stale = HashSet::new();
+    loop {
+        if SHUTDOWN_FLAG.load(Ordering::SeqCst) { break; }
+
+        if let Some(p) = &provider {
+            let prices = match p {
+                Provider::Chainlink(feeds) => fetch_chainlink(feeds, &mut decimals).await,
+                Provider::Http { url, symbols } => fetch_http(url, symbols).await,
+            };
+            for (symbol, price, age) in prices {
+                apply(symbol, price, p.source(), age, override_window);
+            }
+        }
+        if let Some(max_age) = max_age {
+            report_stale(max_age, &mut stale);
+        }
+
+        sleep(interval).await;
+    }
+}
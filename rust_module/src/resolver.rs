@@ -0,0 +1,141 @@
+// Имена вместо hex в полях адресов: метки наблюдаемых адресов и ENS-совместимый реестр сети (ens_registry).
+// Без реестра в Init модуль ничего не делает - команды и события такие же, как без него
+use crate::state::{spawn, EngineLocal, CORE_STATE};
+use crate::bridge::{EngineCommand, EngineEvent, emit_event, emit_log};
+use crate::address::checksum;
+use crate::execution;
+use ethers::abi::AbiDecode;
+use ethers::providers::ens;
+use ethers::types::{Address, Bytes, TransactionRequest};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Сколько верим найденному имени/адресу; промахи кэшируются так же - реестр не опрашивается на каждое событие
+const NAME_TTL: Duration = Duration::from_secs(600);
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Результат поиска и когда он получен; None - имени/адреса в реестре нет
+type Cached<T> = (Option<T>, Instant);
+
+static FORWARD: EngineLocal<Mutex<HashMap<String, Cached<Address>>>> = EngineLocal::new(|| Mutex::new(HashMap::new()));
+static REVERSE: EngineLocal<Mutex<HashMap<Address, Cached<String>>>> = EngineLocal::new(|| Mutex::new(HashMap::new()));
+static REVERSE_IN_FLIGHT: EngineLocal<Mutex<HashSet<Address>>> = EngineLocal::new(|| Mutex::new(HashSet::new()));
+
+/// Новый Init - имена прошлой сети не действуют
+pub fn forget_all() {
+    FORWARD.lock().unwrap().clear();
+    REVERSE.lock().unwrap().clear();
+}
+
+async fn read(to: Address, tx: TransactionRequest) -> Result<Bytes, String> {
+    match tokio::time::timeout(LOOKUP_TIMEOUT, execution::read_call(to, tx.data.unwrap_or_default())).await {
+        Ok(out) => out,
+        Err(_) => Err(format!("нет ответа за {:?}", LOOKUP_TIMEOUT)),
+    }
+}
+
+fn word_address(out: &[u8]) -> Option<Address> {
+    (out.len() >= 32).then(|| Address::from_slice(&out[12..32])).filter(|a| !a.is_zero())
+}
+
+/// resolver(namehash) в реестре, затем addr(namehash) у резолвера. Err - нода не ответила (в кэш не идёт)
+async fn lookup_forward(registry: Address, name: &str) -> Result<Option<Address>, String> {
+    let out = read(registry, ens::get_resolver(registry, name)).await?;
+    let Some(resolver) = word_address(&out) else { return Ok(None); };
+    let out = read(resolver, ens::resolve(resolver, ens::ADDR_SELECTOR, name, None)).await?;
+    Ok(word_address(&out))
+}
+
+/// name() обратной записи <addr>.addr.reverse. Её ставит владелец адреса на любое имя,
+/// поэтому имя принимаем, только если оно само указывает на этот адрес
+async fn lookup_reverse(registry: Address, address: Address) -> Result<Option<String>, String> {
+    let node = ens::reverse_address(address);
+    let out = read(registry, ens::get_resolver(registry, &node)).await?;
+    let Some(resolver) = word_address(&out) else { return Ok(None); };
+    let out = read(resolver, ens::resolve(resolver, ens::NAME_SELECTOR, &node, None)).await?;
+    let Some(name) = String::decode(&out).ok().filter(|n| !n.is_empty()) else { return Ok(None); };
+    Ok((lookup_forward(registry, &name).await? == Some(address)).then_some(name))
+}
+
+/// Метка наблюдаемого адреса (без учёта регистра) или имя в реестре
+async fn resolve_name(registry: Address, input: &str) -> Result<Address, String> {
+    let labelled = CORE_STATE.read().unwrap().watched_addresses.iter()
+        .find(|(_, w)| !w.label.is_empty() && w.label.eq_ignore_ascii_case(input))
+        .map(|(a, _)| *a);
+    if let Some(address) = labelled { return Ok(address); }
+    if !input.contains('.') {
+        return Err(format!("{:?}: не адрес, не метка наблюдаемого адреса и не имя", input));
+    }
+
+    let name = input.to_lowercase();
+    let cached = FORWARD.lock().unwrap().get(&name).filter(|(_, at)| at.elapsed() < NAME_TTL).map(|(a, _)| *a);
+    let found = match cached {
+        Some(found) => found,
+        None => {
+            let found = lookup_forward(registry, &name).await
+                .map_err(|e| format!("имя {} не разрешено: {}", name, e))?;
+            FORWARD.lock().unwrap().insert(name.clone(), (found, Instant::now()));
+            found
+        }
+    };
+    found.ok_or_else(|| format!("имя {} не зарегистрировано", name))
+}
+
+/// Поле адреса команды: 0x... и пустое - как есть; иначе имя заменяется адресом.
+/// Не разрешилось - CommandError по полю и false
+async fn resolve_field(registry: Address, command: &str, field: &str, value: &mut String) -> bool {
+    let input = value.trim();
+    if input.is_empty() || input.starts_with("0x") || input.starts_with("0X") || input.eq_ignore_ascii_case("native") {
+        return true;
+    }
+    match resolve_name(registry, input).await {
+        Ok(address) => {
+            emit_log("INFO", format!("🏷️ {} -> {}", input, checksum(&address)));
+            *value = checksum(&address);
+            true
+        }
+        Err(e) => {
+            emit_event(EngineEvent::invalid_field(command, field, e));
+            false
+        }
+    }
+}
+
+/// Имена в полях адресов команды. false - команда отклонена (CommandError уже отправлен).
+/// Без реестра в Init команда не трогается
+pub async fn resolve_names(cmd: &mut EngineCommand) -> bool {
+    let Some(registry) = CORE_STATE.read().unwrap().ens_registry else { return true; };
+    match cmd {
+        EngineCommand::WatchAddress { address, .. } => resolve_field(registry, "WatchAddress", "address", address).await,
+        EngineCommand::Transfer { to_address, .. } => resolve_field(registry, "Transfer", "to_address", to_address).await,
+        EngineCommand::SweepAll { destination, .. } => resolve_field(registry, "SweepAll", "destination", destination).await,
+        EngineCommand::ExecuteTrade { token, .. } => resolve_field(registry, "ExecuteTrade", "token", token).await,
+        EngineCommand::GetTokenInfo { address } => resolve_field(registry, "GetTokenInfo", "address", address).await,
+        EngineCommand::AddTokenSubscription { token_address, .. } => {
+            resolve_field(registry, "AddTokenSubscription", "token_address", token_address).await
+        }
+        _ => true,
+    }
+}
+
+/// Имя адреса для событий - только из кэша, событие не ждёт RPC. Промах запускает фоновый поиск,
+/// имя появится в следующих событиях. None - без реестра, без ens_reverse_lookup или имени нет
+pub fn cached_name(address: Address) -> Option<String> {
+    let registry = {
+        let s = CORE_STATE.read().unwrap();
+        s.ens_registry.filter(|_| s.ens_reverse_lookup)
+    }?;
+    if let Some((name, at)) = REVERSE.lock().unwrap().get(&address) {
+        if at.elapsed() < NAME_TTL { return name.clone(); }
+    }
+    if REVERSE_IN_FLIGHT.lock().unwrap().insert(address) {
+        spawn(async move {
+            if let Ok(name) = lookup_reverse(registry, address).await {
+                REVERSE.lock().unwrap().insert(address, (name, Instant::now()));
+            }
+            REVERSE_IN_FLIGHT.lock().unwrap().remove(&address);
+        });
+    }
+    None
+}
@@ -0,0 +1,230 @@
+use ethers::prelude::*;
+use crate::state::{CORE_STATE, RankedPool};
+use crate::bridge::{EngineEvent, emit_log};
+use crate::execution::{self, BatchRequest, TradeSettings};
+
+const SPLIT_STEPS: u32 = 100;
+
+/// Модель выхода пула как функция доли x ∈ [0, 1] от общего объёма (в wei)
+enum LegModel {
+    /// V2: точная формула по резервам, fee_mul = 1 - комиссия роутера
+    V2 { r_in: f64, r_out: f64, amount: f64, fee_mul: f64 },
+    /// V3: парабола через (0, 0), (0.5, half), (1, full) по двум вызовам quoter
+    V3 { half: f64, full: f64 },
+}
+
+impl LegModel {
+    fn out(&self, x: f64) -> f64 {
+        match *self {
+            LegModel::V2 { r_in, r_out, amount, fee_mul } => {
+                let a = amount * x * fee_mul;
+                if r_in + a > 0.0 { a * r_out / (r_in + a) } else { 0.0 }
+            }
+            LegModel::V3 { half, full } => {
+                let a = 2.0 * full - 4.0 * half;
+                let b = 4.0 * half - full;
+                (a * x * x + b * x).max(0.0)
+            }
+        }
+    }
+}
+
+pub struct SplitPlan {
+    pub primary: RankedPool,
+    pub secondary: RankedPool,
+    pub primary_share_bps: u64,
+    pub improvement_bps: f64,
+}
+
+/// Точный ожидаемый выход одного пула: V2 по резервам, V3 через quoter с fee пула
+async fn leg_expected_out(pool: &RankedPool, t_in: Address, t_out: Address, amount_in: U256) -> U256 {
+    if amount_in.is_zero() { return U256::zero(); }
+    if pool.pool_type == "V3" {
+        let quoter = { CORE_STATE.read().unwrap().quoter_address };
+        return execution::quote_v3_single(t_in, t_out, amount_in, pool.fee, quoter).await;
+    }
+    let reserves = { CORE_STATE.read().unwrap().v2_reserves.get(&pool.address).copied() };
+    match reserves {
+        Some(r) => {
+            let (r_in, r_out) = execution::v2_oriented_reserves(r, t_in, t_out);
+            execution::v2_amount_out(amount_in, r_in, r_out)
+        }
+        None => U256::zero(),
+    }
+}
+
+async fn leg_model(pool: &RankedPool, t_in: Address, t_out: Address, amount_in: U256) -> Option<LegModel> {
+    if pool.pool_type == "V3" {
+        let half = leg_expected_out(pool, t_in, t_out, amount_in / 2).await;
+        let full = leg_expected_out(pool, t_in, t_out, amount_in).await;
+        if full.is_zero() { return None; }
+        return Some(LegModel::V3 {
+            half: execution::u256_to_f64_safe(half, 0),
+            full: execution::u256_to_f64_safe(full, 0),
+        });
+    }
+    let (reserves, fee_bps) = {
+        let s = CORE_STATE.read().unwrap();
+        (s.v2_reserves.get(&pool.address).copied()?, s.v2_pool_fee_bps)
+    };
+    let (r_in, r_out) = execution::v2_oriented_reserves(reserves, t_in, t_out);
+    if r_in.is_zero() || r_out.is_zero() { return None; }
+    Some(LegModel::V2 {
+        r_in: execution::u256_to_f64_safe(r_in, 0),
+        r_out: execution::u256_to_f64_safe(r_out, 0),
+        amount: execution::u256_to_f64_safe(amount_in, 0),
+        fee_mul: 1.0 - fee_bps as f64 / 10_000.0,
+    })
+}
+
+/// Оптимальное разделение объёма между двумя лучшими пулами.
+/// Максимум суммарного выхода - точка, где маржинальные цены пулов выравниваются; ищем его по сетке 1%.
+pub async fn plan_split(t_in: Address, t_out: Address, amount_in: U256) -> Option<SplitPlan> {
+    let (primary, secondary) = {
+        let s = CORE_STATE.read().unwrap();
+        let ctx = s.pair_context(t_in, t_out)?;
+        // Маршруты через промежуточный quote не делим
+        if !ctx.route.is_empty() || ctx.ranked_pools.len() < 2 { return None; }
+        (ctx.ranked_pools[0].clone(), ctx.ranked_pools[1].clone())
+    };
+
+    let m1 = leg_model(&primary, t_in, t_out, amount_in).await?;
+    let m2 = leg_model(&secondary, t_in, t_out, amount_in).await?;
+
+    let best_single = m1.out(1.0).max(m2.out(1.0));
+    if best_single <= 0.0 { return None; }
+
+    let (best_step, best_total) = (0..=SPLIT_STEPS)
+        .map(|i| {
+            let x = i as f64 / SPLIT_STEPS as f64;
+            (i, m1.out(x) + m2.out(1.0 - x))
+        })
+        .fold((SPLIT_STEPS, 0.0), |acc, cur| if cur.1 > acc.1 { cur } else { acc });
+
+    Some(SplitPlan {
+        primary,
+        secondary,
+        primary_share_bps: best_step as u64 * 10000 / SPLIT_STEPS as u64,
+        improvement_bps: (best_total - best_single) / best_single * 10000.0,
+    })
+}
+
+/// Batch trade с разделением каждого свапа между двумя пулами.
+/// Если выигрыш меньше split_min_improvement_bps - обычный свап через выбранный пул.
+/// percent запроса уже переведён в amounts_wei
+pub async fn run_split_trade(req: BatchRequest, trade: TradeSettings) -> Vec<EngineEvent> {
+    if execution::trading_paused() {
+        return vec![execution::paused_status("SYSTEM".into(), &req.action, req.token, req.amount)];
+    }
+    let BatchRequest { keys, router, action, token, quote, amount, chain_id, amounts_wei, .. } = req;
+    let (t_in, t_out) = if action == "buy" { (quote, token) } else { (token, quote) };
+    let (dec, min_improvement) = {
+        let s = CORE_STATE.read().unwrap();
+        (*s.decimals_cache.get(&t_in).unwrap_or(&18), s.split_min_improvement_bps)
+    };
+
+    // План строим по среднему объёму на кошелёк
+    let reference = match &amounts_wei {
+        Some(amounts) if action == "sell" && !amounts.is_empty() => {
+            let total = amounts.values().filter_map(|w| U256::from_dec_str(w).ok()).fold(U256::zero(), |a, b| a + b);
+            total / U256::from(amounts.len())
+        }
+        _ => execution::resolve_amount_wei("buy", amount, dec, Address::zero(), &None),
+    };
+
+    let plan = plan_split(t_in, t_out, reference).await;
+    let Some(plan) = plan.filter(|p| p.improvement_bps >= min_improvement && p.primary_share_bps < 10000) else {
+        emit_log("INFO", "🔀 Split: выигрыш ниже порога, свап через один пул".into());
+        crate::bridge::emit_event(EngineEvent::RouteReport {
+            token: format!("{:?}", token),
+            action: action.clone(),
+            split: false,
+            primary_pool: None,
+            primary_pct: 100.0,
+            secondary_pool: None,
+            secondary_pct: 0.0,
+            improvement_bps: 0.0,
+        });
+        let req = BatchRequest { keys, router, action, token, quote, amount, chain_id, amounts_wei, percent: None };
+        return execution::run_batch_trade(req, trade).await;
+    };
+
+    let gas = trade.gas.resolve_gwei();
+    let params = execution::applied_params(trade.slippage, gas, trade.deadline_secs, trade.slippage_mode);
+    let primary_pct = plan.primary_share_bps as f64 / 100.0;
+    emit_log("INFO", format!("🔀 Split: {:.0}% {} / {:.0}% {}, выигрыш {:.1} bps",
+        primary_pct, plan.primary.pool_type, 100.0 - primary_pct, plan.secondary.pool_type, plan.improvement_bps));
+    crate::bridge::emit_event(EngineEvent::RouteReport {
+        token: format!("{:?}", token),
+        action: action.clone(),
+        split: true,
+        primary_pool: Some(format!("{:?}", plan.primary.address)),
+        primary_pct,
+        secondary_pool: Some(format!("{:?}", plan.secondary.address)),
+        secondary_pct: 100.0 - primary_pct,
+        improvement_bps: plan.improvement_bps,
+    });
+
+    let mut events = Vec::new();
+    for key in keys {
+        let wallet = execution::signer(&key, chain_id);
+        let wallet_addr = wallet.address();
+
+        let amount_wei = execution::resolve_amount_wei(&action, amount, dec, wallet_addr, &amounts_wei);
+        if amount_wei.is_zero() { continue; }
+
+        let mut nonce = { *CORE_STATE.read().unwrap().nonce_map.get(&wallet_addr).unwrap_or(&0) };
+
+        if action == "sell" {
+            let sell = execution::SellAllowance { router, token, amount_wei, nonce, gas, dec };
+            if let Some(approve_ev) = execution::ensure_sell_allowance(&wallet, sell).await {
+                events.push(approve_ev);
+                continue;
+            }
+        }
+
+        let primary_wei = amount_wei * U256::from(plan.primary_share_bps) / U256::from(10000);
+        let legs = [(&plan.primary, primary_wei), (&plan.secondary, amount_wei - primary_wei)];
+
+        for (pool, leg_wei) in legs {
+            if leg_wei.is_zero() { continue; }
+            // Второе плечо не отправляем, если паузу включили после первого
+            if execution::trading_paused() {
+                events.push(execution::paused_status(format!("{:?}", wallet_addr), &action, token, amount));
+                break;
+            }
+            let exp_out = leg_expected_out(pool, t_in, t_out, leg_wei).await;
+            // Auto-slippage - по impact плеча в своём пуле
+            let (min_out, applied) = match execution::resolve_min_out(&params, t_in, t_out, leg_wei, exp_out, action == "buy").await {
+                Ok(v) => v,
+                Err(message) => {
+                    events.push(execution::rejected_status(format!("{:?}", wallet_addr), &action, token, amount, "Error", message));
+                    break;
+                }
+            };
+            let leg = execution::SwapLeg { pool_type: &pool.pool_type, fee: pool.fee, t_in, t_out };
+            let calldata = execution::encode_swap_calldata(&leg, leg_wei, min_out, wallet_addr, applied.deadline_secs);
+            let leg_amount = amount * execution::u256_to_f64_safe(leg_wei, 0) / execution::u256_to_f64_safe(amount_wei, 0);
+
+            let order = execution::SwapOrder {
+                router, nonce, calldata, gas, action: &action, token, amount: leg_amount, amount_wei: leg_wei, exp_out, dec
+            };
+            if let Some(mut ev) = execution::send_swap(&wallet, order).await {
+                let mut signed = true;
+                if let EngineEvent::TradeStatus { leg, status, applied: stamped, tx_hash, .. } = &mut ev {
+                    *leg = Some(format!("{} {:?}", pool.pool_type, pool.address));
+                    *stamped = Some(applied);
+                    signed = status != "InsufficientGas";
+                    if let Some(hash) = tx_hash { crate::sandwich::watch(hash, pool.address); }
+                }
+                events.push(ev);
+                // Без газа на первое плечо второе тоже не пройдёт, nonce не занят
+                if !signed { break; }
+                nonce += 1;
+            }
+        }
+        CORE_STATE.write().unwrap().nonce_map.insert(wallet_addr, nonce);
+    }
+    execution::stamp_applied(&mut events, &params);
+    events
+}
@@ -0,0 +1,83 @@
+use ethers::types::{Address, Bytes, H256, U256};
+use futures::future::join_all;
+use serde_json::{json, Value};
+use crate::bridge::emit_log;
+use crate::state::GLOBAL_HTTP_CLIENT;
+
+/// Один запрос JSON-RPC батча
+pub enum BatchCall {
+    GasPrice,
+    Balance(Address),
+    Call { to: Address, data: Bytes },
+    Receipt(H256),
+    Transaction(H256),
+}
+
+impl BatchCall {
+    fn request(&self, id: usize) -> Value {
+        let (method, params) = match self {
+            BatchCall::GasPrice => ("eth_gasPrice", json!([])),
+            BatchCall::Balance(addr) => ("eth_getBalance", json!([addr, "latest"])),
+            BatchCall::Call { to, data } => ("eth_call", json!([{ "to": to, "data": data }, "latest"])),
+            BatchCall::Receipt(hash) => ("eth_getTransactionReceipt", json!([hash])),
+            BatchCall::Transaction(hash) => ("eth_getTransactionByHash", json!([hash])),
+        };
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+}
+
+pub type BatchResult = Result<Value, String>;
+
+/// result элемента ответа или текст его ошибки
+fn item_result(item: &Value) -> BatchResult {
+    if let Some(err) = item.get("error") {
+        return Err(err.to_string());
+    }
+    item.get("result").cloned().ok_or_else(|| "empty response".into())
+}
+
+async fn post(url: &str, body: &Value) -> Result<Value, String> {
+    GLOBAL_HTTP_CLIENT.post(url).json(body).send().await.map_err(|e| e.to_string())?
+        .json::<Value>().await.map_err(|e| e.to_string())
+}
+
+/// Все запросы одним POST. Результаты - в порядке запросов, ошибка одного не задевает остальные.
+/// Нода, которая не принимает массив (ответ не массив), получает запросы поштучно и параллельно
+pub async fn batch(url: &str, calls: &[BatchCall]) -> Vec<BatchResult> {
+    if calls.is_empty() { return Vec::new(); }
+    let body = Value::Array(calls.iter().enumerate().map(|(i, c)| c.request(i)).collect());
+
+    if let Ok(Value::Array(items)) = post(url, &body).await {
+        let mut out: Vec<BatchResult> = vec![Err("missing in batch response".into()); calls.len()];
+        for item in &items {
+            let id = item.get("id").and_then(Value::as_u64).map(|id| id as usize);
+            if let Some(slot) = id.and_then(|id| out.get_mut(id)) {
+                *slot = item_result(item);
+            }
+        }
+        return out;
+    }
+
+    emit_log("DEBUG", format!("[BATCH] {} не принимает батчи - {} поштучных запросов", crate::metrics::node_label(url), calls.len()));
+    join_all(calls.iter().enumerate().map(|(i, c)| async move {
+        post(url, &c.request(i)).await.and_then(|item| item_result(&item))
+    })).await
+}
+
+pub fn as_u256(res: &BatchResult) -> Option<U256> {
+    res.as_ref().ok().and_then(|v| serde_json::from_value(v.clone()).ok())
+}
+
+pub fn as_bytes(res: &BatchResult) -> Option<Bytes> {
+    res.as_ref().ok().and_then(|v| serde_json::from_value(v.clone()).ok())
+}
+
+/// Объект ответа (receipt, транзакция): Ok(None) - нода вернула null (ещё нет / не знает),
+/// Err - ошибка запроса или неразборчивый ответ
+pub fn as_object<T: serde::de::DeserializeOwned>(res: &BatchResult) -> Result<Option<T>, String> {
+    match res {
+        Ok(Value::Null) => Ok(None),
+        Ok(v) => serde_json::from_value(v.clone()).map(Some).map_err(|e| e.to_string()),
+        Err(e) => Err(e.clone()),
+    }
+}
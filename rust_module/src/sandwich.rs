@@ -0,0 +1,118 @@
+use ethers::prelude::*;
+use ethers::abi::{AbiDecode, AbiEncode};
+use std::str::FromStr;
+use crate::bridge::{emit_event, emit_log, EngineEvent};
+use crate::execution;
+use crate::monitor::{GetReservesCall, GetReservesReturn, Slot0Call, Slot0Return, SwapFilter, V2SwapFilter};
+use crate::state::{CORE_STATE, fastest_http_provider};
+
+// Свап перед моим в том же направлении меньше этой доли моего входа - обычный трафик, не front-run
+const FRONTRUN_MIN_RATIO: f64 = 0.5;
+
+fn reserves_price(r0: U256, r1: U256) -> Option<f64> {
+    let (r0, r1) = (execution::u256_to_f64_safe(r0, 0), execution::u256_to_f64_safe(r1, 0));
+    (r0 > 0.0 && r1 > 0.0).then(|| r1 / r0)
+}
+
+fn sqrt_price(sqrt_price_x96: U256) -> Option<f64> {
+    let p = execution::u256_to_f64_safe(sqrt_price_x96, 0) / 2f64.powi(96);
+    (p > 0.0).then_some(p * p)
+}
+
+/// Цена пула (token1 за token0 в wei) по кэшу ядра - по ней считался quote
+pub fn cached_price(pool: Address) -> Option<f64> {
+    let s = CORE_STATE.read().unwrap();
+    if let Some(v3) = s.v3_states.get(&pool) {
+        return sqrt_price(v3.sqrt_price_x96);
+    }
+    s.v2_reserves.get(&pool).and_then(|(r0, r1)| reserves_price(*r0, *r1))
+}
+
+/// Живая цена пула: slot0 (V3) или getReserves (V2)
+async fn live_price(pool: Address, is_v3: bool) -> Option<f64> {
+    if is_v3 {
+        let out = execution::read_call(pool, Slot0Call.encode().into()).await.ok()?;
+        sqrt_price(Slot0Return::decode(&out).ok()?.sqrt_price_x96)
+    } else {
+        let out = execution::read_call(pool, GetReservesCall.encode().into()).await.ok()?;
+        let r = GetReservesReturn::decode(&out).ok()?;
+        reserves_price(r.reserve_0.into(), r.reserve_1.into())
+    }
+}
+
+/// Сдвиг цены пула (%) от quote до подписи. None - живое состояние не прочитать
+pub async fn price_moved_pct(pool: Address, is_v3: bool, quoted: f64) -> Option<f64> {
+    let live = live_price(pool, is_v3).await?;
+    Some((live / quoted - 1.0).abs() * 100.0)
+}
+
+/// Отмечает отправленный свап для проверки блока на sandwich после подтверждения
+pub fn watch(tx_hash: &str, pool: Address) {
+    let Ok(hash) = H256::from_str(tx_hash) else { return; };
+    let mut s = CORE_STATE.write().unwrap();
+    if !s.sandwich_detect { return; }
+    if let Some(pending) = s.pending_txs.get_mut(&hash) {
+        pending.sandwich_pool = Some(pool);
+    }
+}
+
+/// Направление (token0 на входе) и объём входа из Swap лога V2/V3
+fn decode_swap(log: &Log) -> Option<(bool, U256)> {
+    let raw: ethers::abi::RawLog = log.clone().into();
+    if let Ok(s) = <V2SwapFilter as EthEvent>::decode_log(&raw) {
+        let token0_in = !s.amount_0_in.is_zero();
+        return Some((token0_in, if token0_in { s.amount_0_in } else { s.amount_1_in }));
+    }
+    let s = <SwapFilter as EthEvent>::decode_log(&raw).ok()?;
+    let token0_in = s.amount_0.is_positive();
+    Some((token0_in, if token0_in { s.amount_0.unsigned_abs() } else { s.amount_1.unsigned_abs() }))
+}
+
+/// Подтверждённый свап: крупный свап того же направления прямо перед моим в блоке - front-run.
+/// Встречный свап сразу после моего - вероятный back-run той же связки
+pub async fn check_block(tx_hash: H256, wallet: Address, token: Address, pool: Address, logs: Vec<Log>, block: u64) {
+    let Some((my_index, (token0_in, amount_in))) = logs.iter()
+        .filter(|l| l.address == pool)
+        .find_map(|l| Some((l.log_index?, decode_swap(l)?))) else { return; };
+    let Some(provider) = fastest_http_provider() else { return; };
+    let filter = Filter::new().address(pool).from_block(block).to_block(block);
+    let block_logs = match provider.get_logs(&filter).await {
+        Ok(l) => l,
+        Err(e) => {
+            emit_log("DEBUG", format!("Sandwich check {:?}: {}", tx_hash, e));
+            return;
+        }
+    };
+    let swaps: Vec<(U256, Option<H256>, bool, U256)> = block_logs.iter()
+        .filter_map(|l| {
+            let (dir, amount) = decode_swap(l)?;
+            Some((l.log_index?, l.transaction_hash, dir, amount))
+        })
+        .collect();
+
+    let Some(&(_, Some(front_tx), front_dir, front_amount)) = swaps.iter()
+        .filter(|(i, ..)| *i < my_index)
+        .max_by_key(|(i, ..)| *i) else { return; };
+    let front_f = execution::u256_to_f64_safe(front_amount, 0);
+    let mine_f = execution::u256_to_f64_safe(amount_in, 0);
+    if front_tx == tx_hash || front_dir != token0_in || front_f < mine_f * FRONTRUN_MIN_RATIO {
+        return;
+    }
+    let back_tx = swaps.iter()
+        .filter(|(i, ..)| *i > my_index)
+        .min_by_key(|(i, ..)| *i)
+        .filter(|(_, _, dir, _)| *dir != token0_in)
+        .and_then(|(_, tx, ..)| *tx);
+
+    emit_log("WARNING", format!("🥪 Возможный sandwich {:?}: front-run {:?} в блоке {}", tx_hash, front_tx, block));
+    emit_event(EngineEvent::SandwichSuspected {
+        tx_hash: format!("{:?}", tx_hash),
+        wallet: format!("{:?}", wallet),
+        token: format!("{:?}", token),
+        pool: format!("{:?}", pool),
+        block,
+        frontrun_tx: format!("{:?}", front_tx),
+        frontrun_amount_in: front_amount.to_string(),
+        backrun_tx: back_tx.map(|h| format!("{:?}", h)),
+    });
+}
@@ -0,0 +1,53 @@
+use tokio::time::{sleep, Duration};
+use std::sync::atomic::Ordering;
+use crate::state::{CORE_STATE, SHUTDOWN_FLAG, CONNECTED, RecurringTrade};
+use crate::bridge::{emit_event, emit_log, EngineEvent};
+use crate::execution;
+
+fn current_timestamp_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// Забирает созревшие расписания и сдвигает их следующий запуск.
+/// Следующий запуск считается от "сейчас", поэтому после паузы пропущенные запуски не догоняются пачкой.
+fn take_due_trades(now: u64) -> Vec<(RecurringTrade, u32)> {
+    let mut due = Vec::new();
+    let mut s = CORE_STATE.write().unwrap();
+
+    s.recurring_trades.retain_mut(|job| {
+        if now < job.next_run_ms { return true; }
+
+        job.executions += 1;
+        job.next_run_ms = now + job.interval_secs * 1000;
+        due.push((job.clone(), job.executions));
+
+        job.max_executions == 0 || job.executions < job.max_executions
+    });
+
+    due
+}
+
+pub async fn start_scheduler() {
+    loop {
+        if SHUTDOWN_FLAG.load(Ordering::SeqCst) { break; }
+        sleep(Duration::from_secs(1)).await;
+
+        // Нет соединения - расписания стоят на паузе
+        if !CONNECTED.load(Ordering::Relaxed) { continue; }
+
+        for (job, execution_number) in take_due_trades(current_timestamp_ms()) {
+            emit_log("INFO", format!("⏰ Расписание {}: запуск #{} ({} {})", job.id, execution_number, job.action, job.amount));
+            let slippage = { CORE_STATE.read().unwrap().slippage };
+
+            crate::state::spawn(async move {
+                let tx_hashes = execution::run_stored_trade(
+                    &job.action, job.token, job.quote, job.amount, &job.wallets, slippage, None
+                ).await;
+                emit_event(EngineEvent::ScheduledTradeExecuted { id: job.id, execution_number, tx_hashes });
+            });
+        }
+    }
+}
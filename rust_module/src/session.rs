@@ -0,0 +1,362 @@
+// Сессия между перезапусками (session_path в Init): pending tx, позиции, лимитные ордера, DCA,
+// закреплённые пулы, счётчики auto-fuel и игнорируемые токены. Ключи кошельков в файл не попадают никогда
+use crate::state::{EngineLocal, CORE_STATE, RPC_POOL, SHUTDOWN_FLAG, LimitOrder, PendingSwap, PendingTx, Position, RecurringTrade};
+use crate::state::app::{BotState, FuelAttempts};
+use crate::bridge::{EngineEvent, REQUEST_ID, current_request_id, emit_event, emit_log};
+use crate::rpc_batch::{self, BatchCall};
+use crate::execution;
+use crate::positions;
+use ethers::types::{Address, Transaction, TransactionReceipt, H160, H256, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Снимок пишется не чаще раза за интервал и только если состояние изменилось
+const SAVE_INTERVAL: Duration = Duration::from_secs(2);
+// Pending tx старше - сверяется по receipt перед повторной регистрацией
+pub const DEFAULT_STALE_AFTER_BLOCKS: u64 = 20;
+
+/// Pending tx без Instant: возраст - по времени отправки
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SavedTx {
+    hash: H256,
+    wallet: Address,
+    sent_at_ms: u64,
+    #[serde(default)]
+    request_id: Option<String>,
+    #[serde(default)]
+    gas_limit: Option<u64>,
+    #[serde(default)]
+    gas_estimated: Option<u64>,
+    #[serde(default)]
+    position: Option<(Address, Address)>,
+    #[serde(default)]
+    swap: Option<PendingSwap>,
+    #[serde(default)]
+    sandwich_pool: Option<Address>,
+    #[serde(default)]
+    gas_price: Option<U256>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SavedPosition {
+    wallet: Address,
+    token: Address,
+    #[serde(flatten)]
+    position: Position,
+}
+
+/// Восстанавливаемая часть CORE_STATE. Порядок детерминирован - снимки сравниваются как строки
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct SessionState {
+    #[serde(default)]
+    pending_txs: Vec<SavedTx>,
+    #[serde(default)]
+    positions: Vec<SavedPosition>,
+    #[serde(default)]
+    limit_orders: Vec<LimitOrder>,
+    #[serde(default)]
+    recurring_trades: Vec<RecurringTrade>,
+    #[serde(default)]
+    pinned_pools: BTreeMap<Address, H160>,
+    #[serde(default)]
+    fuel_attempts: BTreeMap<Address, FuelAttempts>,
+    #[serde(default)]
+    ignored_tokens: BTreeSet<Address>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SessionFile {
+    chain_id: u64,
+    saved_at_ms: u64,
+    #[serde(default)]
+    block_time_ms: Option<u64>,
+    #[serde(flatten)]
+    state: SessionState,
+}
+
+struct Session {
+    path: String,
+    chain_id: u64,
+    // Прочитано при Init и ждёт RestoreSession
+    loaded: Option<SessionFile>,
+    // До RestoreSession снимки не пишутся - иначе непрочитанный файл затёрся бы пустой сессией
+    armed: bool,
+    last_written: String,
+    // Время отправки tx фиксируется один раз: иначе снимок менялся бы на каждом тике
+    sent_at: HashMap<H256, u64>,
+}
+
+static SESSION: EngineLocal<Mutex<Option<Session>>> = EngineLocal::new(|| Mutex::new(None));
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Открывает файл сессии (из Init). Файл другой сети не читается и будет перезаписан
+pub fn open(path: String, chain_id: u64) {
+    let loaded = match std::fs::read_to_string(&path) {
+        Ok(text) => match serde_json::from_str::<SessionFile>(&text) {
+            Ok(file) if file.chain_id == chain_id => Some(file),
+            Ok(file) => {
+                emit_log("WARNING", format!("📼 Сессия {} от другой сети (chain_id {}) - не восстанавливается", path, file.chain_id));
+                None
+            }
+            Err(e) => {
+                emit_log("WARNING", format!("📼 Файл сессии повреждён ({}) - начинаем с пустой", e));
+                None
+            }
+        },
+        Err(_) => None,
+    };
+    if let Some(file) = &loaded {
+        let st = &file.state;
+        emit_log("INFO", format!(
+            "📼 Сохранённая сессия: {} pending tx, {} позиций, {} ордеров, {} DCA - RestoreSession для восстановления",
+            st.pending_txs.len(), st.positions.len(), st.limit_orders.len(), st.recurring_trades.len()
+        ));
+    }
+    let armed = loaded.is_none();
+    *SESSION.lock().unwrap() = Some(Session { path, chain_id, loaded, armed, last_written: String::new(), sent_at: HashMap::new() });
+}
+
+/// Init без session_path: сессия не сохраняется
+pub fn close() {
+    *SESSION.lock().unwrap() = None;
+}
+
+fn snapshot(s: &BotState, sent_at: &mut HashMap<H256, u64>) -> SessionState {
+    let now = now_ms();
+    sent_at.retain(|h, _| s.pending_txs.contains_key(h));
+    let mut pending_txs: Vec<SavedTx> = s.pending_txs.iter().map(|(hash, p)| SavedTx {
+        hash: *hash,
+        wallet: p.wallet,
+        sent_at_ms: *sent_at.entry(*hash).or_insert_with(|| now.saturating_sub(p.inserted_at.elapsed().as_millis() as u64)),
+        request_id: p.request_id.clone(),
+        gas_limit: p.gas_limit,
+        gas_estimated: p.gas_estimated,
+        position: p.position,
+        swap: p.swap.clone(),
+        sandwich_pool: p.sandwich_pool,
+        gas_price: p.gas_price,
+    }).collect();
+    pending_txs.sort_unstable_by_key(|t| t.hash);
+
+    let mut positions: Vec<SavedPosition> = s.positions.iter()
+        .filter(|(_, p)| !p.quantity.is_zero() || !p.realized_pnl.is_zero())
+        .map(|(&(wallet, token), p)| SavedPosition { wallet, token, position: p.clone() })
+        .collect();
+    positions.sort_unstable_by_key(|p| (p.wallet, p.token));
+
+    SessionState {
+        pending_txs,
+        positions,
+        limit_orders: s.limit_orders.clone(),
+        recurring_trades: s.recurring_trades.clone(),
+        pinned_pools: s.tokens.iter().filter_map(|(t, ctx)| ctx.pinned_pool.map(|p| (*t, p)))
+            .chain(s.restored_pins.iter().map(|(t, p)| (*t, *p)))
+            .collect(),
+        fuel_attempts: s.auto_fuel_attempts.iter().map(|(a, f)| (*a, *f)).collect(),
+        ignored_tokens: s.ignored_tokens.iter().copied().collect(),
+    }
+}
+
+fn write_atomic(path: &str, json: &str) -> std::io::Result<()> {
+    let tmp = format!("{}.tmp", path);
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// Пишет снимок, если он отличается от последнего записанного
+pub fn save_now() {
+    let mut guard = SESSION.lock().unwrap();
+    let Some(session) = guard.as_mut().filter(|s| s.armed) else { return; };
+    let (mut state, block_time) = {
+        let s = CORE_STATE.read().unwrap();
+        if s.chain_id != session.chain_id { return; }
+        (snapshot(&s, &mut session.sent_at), s.block_time)
+    };
+    // Dry-run tx в сети нет - после перезапуска их не дождаться
+    state.pending_txs.retain(|t| execution::dry_run_tx(&t.hash).is_none());
+
+    let Ok(payload) = serde_json::to_string(&state) else { return; };
+    if payload == session.last_written { return; }
+    let file = SessionFile {
+        chain_id: session.chain_id,
+        saved_at_ms: now_ms(),
+        block_time_ms: block_time.map(|t| t.as_millis() as u64),
+        state,
+    };
+    let Ok(json) = serde_json::to_string_pretty(&file) else { return; };
+    match write_atomic(&session.path, &json) {
+        Ok(()) => session.last_written = payload,
+        Err(e) => emit_log("WARNING", format!("📼 Не удалось сохранить сессию {}: {}", session.path, e)),
+    }
+}
+
+/// Фоновая запись снимков сессии
+pub async fn start_saver() {
+    loop {
+        tokio::time::sleep(SAVE_INTERVAL).await;
+        if SHUTDOWN_FLAG.load(std::sync::atomic::Ordering::SeqCst) { break; }
+        save_now();
+    }
+}
+
+/// Сколько блоков прошло с отправки tx. None - время блока неизвестно
+fn age_blocks(tx: &SavedTx, now: u64, block_time: Option<Duration>) -> Option<u64> {
+    let block_ms = block_time?.as_millis() as u64;
+    (block_ms > 0).then(|| now.saturating_sub(tx.sent_at_ms) / block_ms)
+}
+
+fn to_pending(tx: &SavedTx, now: u64) -> PendingTx {
+    let age = Duration::from_millis(now.saturating_sub(tx.sent_at_ms));
+    PendingTx {
+        request_id: tx.request_id.clone(),
+        gas_limit: tx.gas_limit,
+        gas_estimated: tx.gas_estimated,
+        position: tx.position,
+        swap: tx.swap.clone(),
+        sandwich_pool: tx.sandwich_pool,
+        wallet: tx.wallet,
+        gas_price: tx.gas_price,
+        inserted_at: Instant::now().checked_sub(age).unwrap_or_else(Instant::now),
+        ..Default::default()
+    }
+}
+
+/// Возвращает сохранённую сессию в CORE_STATE. Уже существующее в памяти (ордер с тем же id, позиция) не перезаписывается.
+/// Свежие pending tx сразу отдаются наблюдателю receipt, старше stale_after_blocks - сначала сверяются с нодой.
+/// discard - сохранённая сессия отбрасывается. После вызова снимки пишутся на диск
+pub async fn restore(stale_after_blocks: Option<u64>, discard: bool) {
+    let loaded = {
+        let mut guard = SESSION.lock().unwrap();
+        let Some(session) = guard.as_mut() else {
+            emit_event(EngineEvent::rejected("RestoreSession", "сессия не сохраняется: в Init не задан session_path".into()));
+            return;
+        };
+        session.armed = true;
+        session.loaded.take()
+    };
+    let Some(file) = loaded.filter(|_| !discard) else {
+        if discard { emit_log("INFO", "📼 Сохранённая сессия отброшена".into()); }
+        emit_event(EngineEvent::SessionRestored { pending_txs: 0, dropped_txs: 0, positions: 0, limit_orders: 0, recurring_trades: 0 });
+        return;
+    };
+
+    let now = now_ms();
+    let now_secs = now / 1000;
+    let stale_after = stale_after_blocks.unwrap_or(DEFAULT_STALE_AFTER_BLOCKS);
+    let st = file.state;
+    let (mut restored_positions, mut restored_orders, mut restored_dca) = (0, 0, 0);
+    let mut stale = Vec::new();
+    let mut pending = 0;
+    {
+        let mut s = CORE_STATE.write().unwrap();
+        let block_time = s.block_time.or(file.block_time_ms.map(Duration::from_millis));
+
+        for saved in st.positions {
+            let key = (saved.wallet, saved.token);
+            if s.positions.contains_key(&key) { continue; }
+            s.positions.insert(key, saved.position);
+            positions::sync_entry(&mut s, key);
+            restored_positions += 1;
+        }
+        for order in st.limit_orders {
+            if order.is_expired(now_secs) {
+                emit_log("INFO", format!("🎯 Ордер {} истёк, пока ядро было остановлено - не восстановлен", order.id));
+                continue;
+            }
+            if s.limit_orders.iter().any(|o| o.id == order.id) { continue; }
+            emit_log("INFO", format!("🎯 Лимитный ордер {} восстановлен: {} при цене {}", order.id, order.action, order.trigger_price));
+            s.limit_orders.push(order);
+            restored_orders += 1;
+        }
+        for job in st.recurring_trades {
+            if s.recurring_trades.iter().any(|j| j.id == job.id) { continue; }
+            s.recurring_trades.push(job);
+            restored_dca += 1;
+        }
+        for (wallet, attempts) in st.fuel_attempts {
+            s.auto_fuel_attempts.entry(wallet).or_insert(attempts);
+        }
+        s.ignored_tokens.extend(st.ignored_tokens);
+        for (token, pool) in st.pinned_pools {
+            match s.tokens.get_mut(&token) {
+                Some(ctx) => { ctx.pinned_pool.get_or_insert(pool); }
+                None => { s.restored_pins.insert(token, pool); }
+            }
+        }
+        for tx in st.pending_txs {
+            if s.pending_txs.contains_key(&tx.hash) { continue; }
+            match age_blocks(&tx, now, block_time) {
+                Some(age) if age <= stale_after => {
+                    s.pending_txs.insert(tx.hash, to_pending(&tx, now));
+                    pending += 1;
+                }
+                _ => stale.push(tx),
+            }
+        }
+    }
+
+    let (reconciled, dropped) = reconcile(stale, now).await;
+    emit_log("SUCCESS", format!(
+        "📼 Сессия восстановлена: {} pending tx ({} снято), {} позиций, {} ордеров, {} DCA",
+        pending + reconciled, dropped, restored_positions, restored_orders, restored_dca
+    ));
+    emit_event(EngineEvent::SessionRestored {
+        pending_txs: pending + reconciled,
+        dropped_txs: dropped,
+        positions: restored_positions,
+        limit_orders: restored_orders,
+        recurring_trades: restored_dca,
+    });
+}
+
+/// Старые pending tx: есть receipt или нода ещё знает tx - наблюдатель её доведёт (receipt двинет позицию),
+/// нода не знает - TxDropped. Ответа нет - tx отдаётся наблюдателю, его проверка по таймауту снимет её сама
+async fn reconcile(stale: Vec<SavedTx>, now: u64) -> (usize, usize) {
+    if stale.is_empty() { return (0, 0); }
+    let url = RPC_POOL.read().unwrap().get_fastest_node();
+    let (receipts, transactions) = match &url {
+        Some(url) => {
+            let receipts = rpc_batch::batch(url, &stale.iter().map(|t| BatchCall::Receipt(t.hash)).collect::<Vec<_>>()).await;
+            let transactions = rpc_batch::batch(url, &stale.iter().map(|t| BatchCall::Transaction(t.hash)).collect::<Vec<_>>()).await;
+            (receipts, transactions)
+        }
+        None => (Vec::new(), Vec::new()),
+    };
+
+    let (mut kept, mut dropped) = (0, 0);
+    for (i, tx) in stale.into_iter().enumerate() {
+        let label = execution::tx_hash_label(&tx.hash);
+        let receipt = receipts.get(i).map(rpc_batch::as_object::<TransactionReceipt>);
+        let known = transactions.get(i).map(rpc_batch::as_object::<Transaction>);
+        let keep = match (receipt, known) {
+            (Some(Ok(Some(_))), _) | (_, Some(Ok(Some(_)))) => true,
+            (Some(Ok(None)), Some(Ok(None))) => false,
+            _ => {
+                emit_log("WARNING", format!("📼 TX {} не сверена с нодой - отдана наблюдателю", label));
+                true
+            }
+        };
+        if keep {
+            CORE_STATE.write().unwrap().pending_txs.entry(tx.hash).or_insert_with(|| to_pending(&tx, now));
+            kept += 1;
+            continue;
+        }
+        dropped += 1;
+        let age_secs = now.saturating_sub(tx.sent_at_ms) / 1000;
+        emit_log("WARNING", format!("🕳️ TX {} из прошлой сессии нода не знает - снята с учёта", label));
+        REQUEST_ID.sync_scope(tx.request_id.or_else(current_request_id), || emit_event(EngineEvent::TxDropped {
+            tx_hash: label,
+            wallet: format!("{:?}", tx.wallet),
+            age_secs,
+        }));
+    }
+    (kept, dropped)
+}
@@ -0,0 +1,241 @@
+use ethers::prelude::*;
+use ethers::abi::{AbiDecode, AbiEncode};
+use ethers::providers::RpcError;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::utils::parse_units;
+use crate::state::EngineLocal;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use crate::bridge::{emit_event, emit_log, EngineEvent};
+use crate::bundles;
+use crate::execution::{self, InsufficientOutputAmount, SwapExactETHForTokensCall};
+use crate::monitor::{self, GetReservesCall, GetReservesReturn};
+use crate::state::{CORE_STATE, WalletKey, fastest_http_provider};
+
+// Снайп без ликвидности дольше этого снимается сам
+pub const SNIPE_TIMEOUT_SECS: u64 = 600;
+
+/// Взведённый снайп: buy уже подписаны в бандле snipe:<token>
+struct Snipe {
+    quote: Address,
+    amount: f64,
+    keys: Vec<WalletKey>,
+    gas: f64,
+    max_buy_tax_pct: f64,
+    min_liquidity_usd: f64,
+    router: Address,
+    chain_id: u64,
+    expires_at: u64,
+}
+
+static SNIPES: EngineLocal<Mutex<HashMap<Address, Snipe>>> = EngineLocal::new(|| Mutex::new(HashMap::new()));
+
+fn bundle_id(token: Address) -> String {
+    format!("snipe:{:?}", token)
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn aborted(token: Address, reason: &str) {
+    emit_log("WARNING", format!("🎯 Снайп {:?} снят: {}", token, reason));
+    emit_event(EngineEvent::SnipeAborted { token: format!("{:?}", token), reason: reason.into() });
+}
+
+fn discard_bundle(token: Address) {
+    let id = bundle_id(token);
+    if bundles::exists(&id) {
+        bundles::discard(&id);
+    }
+}
+
+/// ArmSnipe после разбора: buy amount в quote с кошельков keys и лимиты срабатывания
+pub struct SnipeOrder {
+    pub quote: Address,
+    pub amount: f64,
+    pub keys: Vec<WalletKey>,
+    pub gas: f64,
+    pub max_buy_tax_pct: f64,
+    pub min_liquidity_usd: f64,
+    pub router: Address,
+    pub chain_id: u64,
+}
+
+/// Подписывает buy всех кошельков заранее и ждёт первой ликвидности в паре token/quote
+pub async fn arm(token: Address, order: SnipeOrder, timeout_secs: u64) {
+    let SnipeOrder { quote, amount, keys, gas, max_buy_tax_pct, min_liquidity_usd, router, chain_id } = order;
+    let has_pool = { CORE_STATE.read().unwrap().pair_context(token, quote).map(|c| c.pool_address.is_some()) };
+    match has_pool {
+        None => {
+            emit_event(EngineEvent::invalid_field("ArmSnipe", "token", format!("снайп {:?}: пара не в подписках", token)));
+            return;
+        }
+        Some(true) => {
+            emit_event(EngineEvent::invalid_field("ArmSnipe", "token", format!("снайп {:?}: у пары уже есть ликвидность, используйте обычный buy", token)));
+            return;
+        }
+        Some(false) => {}
+    }
+    disarm(token, "Replaced");
+
+    let id = bundle_id(token);
+    let wallets = keys.len();
+    let req = execution::BatchRequest {
+        keys: keys.clone(), router, action: "buy".into(), token, quote, amount, chain_id, amounts_wei: None, percent: None
+    };
+    bundles::presign_buy(id.clone(), req, gas, timeout_secs).await;
+    if !bundles::exists(&id) {
+        aborted(token, "Pre-sign failed");
+        return;
+    }
+
+    let expires_at = now_secs() + timeout_secs;
+    SNIPES.lock().unwrap().insert(token, Snipe {
+        quote, amount, keys, gas, max_buy_tax_pct, min_liquidity_usd, router, chain_id, expires_at
+    });
+    emit_log("SUCCESS", format!("🎯 Снайп {:?}: {} кошельков, ждём ликвидность до {} сек", token, wallets, timeout_secs));
+    emit_event(EngineEvent::SnipeArmed {
+        token: format!("{:?}", token),
+        quote: format!("{:?}", quote),
+        wallets,
+        expires_at
+    });
+
+    crate::state::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(timeout_secs)).await;
+        // Снайп мог сработать или быть перевзведён - снимаем только этот
+        let expired = SNIPES.lock().unwrap().get(&token).is_some_and(|s| s.expires_at == expires_at);
+        if expired {
+            disarm(token, "Timeout");
+        }
+    });
+}
+
+/// Снимает снайп и его неразосланный бандл. false - снайпа не было
+pub fn disarm(token: Address, reason: &str) -> bool {
+    if SNIPES.lock().unwrap().remove(&token).is_none() {
+        return false;
+    }
+    discard_bundle(token);
+    aborted(token, reason);
+    true
+}
+
+/// Выбранный пул токена сообщил ликвидность: снайп срабатывает, если она не ниже порога
+pub fn on_liquidity(token: Address, liquidity_usd: f64) {
+    let mut snipes = SNIPES.lock().unwrap();
+    let Some(snipe) = snipes.get(&token) else { return; };
+    if liquidity_usd < snipe.min_liquidity_usd {
+        emit_log("DEBUG", format!("🎯 Снайп {:?}: ликвидность {:.0}$ ниже порога {:.0}$, ждём", token, liquidity_usd, snipe.min_liquidity_usd));
+        return;
+    }
+    let Some(snipe) = snipes.remove(&token) else { return; };
+    drop(snipes);
+    crate::state::spawn(fire(token, snipe, liquidity_usd));
+}
+
+async fn fire(token: Address, snipe: Snipe, liquidity_usd: f64) {
+    let id = bundle_id(token);
+    let (pool, direct_v2) = {
+        let s = CORE_STATE.read().unwrap();
+        let ctx = s.pair_context(token, snipe.quote);
+        (
+            ctx.and_then(|c| c.pool_address),
+            ctx.is_some_and(|c| c.pool_type.as_deref() == Some("V2") && c.route.is_empty())
+        )
+    };
+    let Some(pool) = pool else {
+        discard_bundle(token);
+        aborted(token, "Pool not selected");
+        return;
+    };
+
+    let mut buy_tax_pct = None;
+    if direct_v2 {
+        let dec = monitor::get_decimals_cached(snipe.quote).await;
+        let amount_wei: U256 = parse_units(snipe.amount, dec as u32).map(Into::into).unwrap_or_default();
+        let recipient = snipe.keys.first().map(|k| k.address()).unwrap_or_default();
+        match probe_buy_tax(token, snipe.quote, pool, amount_wei, snipe.router, recipient).await {
+            Ok(tax) => buy_tax_pct = tax,
+            Err(reason) => {
+                discard_bundle(token);
+                aborted(token, &reason);
+                return;
+            }
+        }
+        if let Some(tax) = buy_tax_pct.filter(|t| *t > snipe.max_buy_tax_pct) {
+            discard_bundle(token);
+            aborted(token, &format!("Buy tax {:.1}% above {:.1}%", tax, snipe.max_buy_tax_pct));
+            return;
+        }
+    } else {
+        // Ликвидность пришла в V3 или через маршрут - заранее подписанная V2 пара ревертнется
+        discard_bundle(token);
+    }
+    if buy_tax_pct.is_none() {
+        emit_log("WARNING", format!("🎯 Снайп {:?}: налог покупки не проверен (нужен V2 пул к wrapped native)", token));
+    }
+
+    // Бандл мог быть снят (газ/nonce изменились) - подписываем заново по выбранному пулу
+    if !bundles::exists(&id) {
+        let req = execution::BatchRequest {
+            keys: snipe.keys, router: snipe.router, action: "buy".into(), token, quote: snipe.quote,
+            amount: snipe.amount, chain_id: snipe.chain_id, amounts_wei: None, percent: None
+        };
+        bundles::prepare(id.clone(), req, snipe.gas, 0.0, None).await;
+        if !bundles::exists(&id) {
+            aborted(token, "Re-sign failed");
+            return;
+        }
+    }
+
+    emit_log("SUCCESS", format!("🎯 Снайп {:?}: ликвидность {:.0}$ в {:?}, рассылка", token, liquidity_usd, pool));
+    emit_event(EngineEvent::SnipeFired {
+        token: format!("{:?}", token),
+        pool: format!("{:?}", pool),
+        liquidity_usd,
+        buy_tax_pct
+    });
+    bundles::fire(id).await;
+}
+
+/// Симуляция buy через swapExactETHForTokens роутера от имени wrapped native (у него есть баланс):
+/// с amountOutMin = MAX свап ревертится с фактически полученной суммой, её сравниваем с формулой пула.
+/// Ok(None) - симуляция недоступна, Err - buy ревертится по другой причине (honeypot, лимиты)
+async fn probe_buy_tax(token: Address, quote: Address, pool: Address, amount_wei: U256, router: Address, recipient: Address) -> Result<Option<f64>, String> {
+    let wrapped = { CORE_STATE.read().unwrap().wrapped_native_address };
+    if quote != wrapped || amount_wei.is_zero() { return Ok(None); }
+    let Ok(out) = execution::read_call(pool, GetReservesCall.encode().into()).await else { return Ok(None); };
+    let Ok(r) = GetReservesReturn::decode(&out) else { return Ok(None); };
+    let (r_in, r_out) = execution::v2_oriented_reserves((r.reserve_0.into(), r.reserve_1.into()), quote, token);
+    // Формула ядра уже закладывает комиссию пула и роутера
+    let expected = execution::u256_to_f64_safe(execution::v2_amount_out(amount_wei, r_in, r_out), 0);
+    if expected <= 0.0 { return Ok(None); }
+    let Some(provider) = fastest_http_provider() else { return Ok(None); };
+
+    let data = SwapExactETHForTokensCall {
+        amount_out_min: U256::MAX,
+        path: vec![quote, token],
+        to: recipient,
+        deadline: execution::swap_deadline(60)
+    }.encode();
+    let tx: TypedTransaction = TransactionRequest::new().from(wrapped).to(router).value(amount_wei).data(data).into();
+    let err = match provider.call(&tx, None).await {
+        Ok(_) => return Ok(None),
+        Err(e) => e,
+    };
+    let Some(resp) = RpcError::as_error_response(&err) else {
+        emit_log("DEBUG", format!("Snipe tax probe {:?}: {}", token, err));
+        return Ok(None);
+    };
+    let revert = resp.as_revert_data().and_then(|data| <InsufficientOutputAmount as EthError>::decode_with_selector(&data));
+    match revert {
+        Some(e) => {
+            let received = execution::u256_to_f64_safe(e.amount_received, 0);
+            Ok(Some(((1.0 - received / expected) * 100.0).max(0.0)))
+        }
+        _ => Err(format!("Buy simulation reverted: {}", resp.message)),
+    }
+}
@@ -1,7 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, BTreeMap, VecDeque};
 use std::sync::{Arc, RwLock};
-use once_cell::sync::Lazy;
-use ethers::types::{Address, U256, H256, H160};
+use std::time::{Duration, Instant};
+use super::instance::EngineLocal;
+use ethers::types::{Address, U256, I256, H256, H160};
+use ethers::signers::LocalWallet;
+use zeroize::Zeroize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Default, Debug)]
 pub struct V3PoolState {
@@ -11,6 +15,558 @@ pub struct V3PoolState {
     pub pool_fee: u32,
 }
 
+/// Окно инициализированных тиков V3 пула вокруг текущего тика (для локальной симуляции свапа)
+#[derive(Clone, Default, Debug)]
+pub struct V3TickWindow {
+    pub tick_spacing: i32,
+    pub ticks: BTreeMap<i32, i128>, // tick -> liquidityNet
+    pub min_tick: i32,              // границы покрытого окна
+    pub max_tick: i32,
+    pub refreshing: bool,
+}
+
+/// Ключ кошелька в памяти. Храним разобранный LocalWallet, а не hex: секрет k256 затирается при drop.
+/// Arc - чтобы раздавать ключи задачам без копий секрета
+pub type WalletKey = Arc<LocalWallet>;
+
+/// Разбирает hex приватного ключа и затирает исходную строку
+pub fn parse_wallet_key(mut hex: String) -> Option<WalletKey> {
+    let key = hex.trim().parse::<LocalWallet>().ok().map(Arc::new);
+    hex.zeroize();
+    key
+}
+
+/// Дедлайн свапа по умолчанию: 5 минут
+pub const DEFAULT_SWAP_DEADLINE_SECS: u64 = 300;
+
+/// Pending tx без receipt дольше таймаута проверяется через eth_getTransactionByHash (dropped / stuck)
+pub const DEFAULT_PENDING_TX_TIMEOUT_SECS: u64 = 180;
+// Интервал опроса receipt растёт с возрастом tx: age / PENDING_POLL_BACKOFF_DIVISOR, но не реже PENDING_MAX_POLL_INTERVAL
+const PENDING_POLL_BACKOFF_DIVISOR: u32 = 10;
+const PENDING_MAX_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Тайминги монитора (Init / UpdateSettings, поле monitor). Циклы читают их на каждой итерации -
+/// изменение применяется без перезапуска
+#[derive(Clone, Debug)]
+pub struct MonitorConfig {
+    pub idle_timeout: Duration,        // нет блоков дольше - переподключение WSS
+    pub reconnect_delay: Duration,     // базовая задержка переподключения, растёт с числом попыток
+    pub receipt_poll: Duration,        // тик опроса receipt отправленных tx
+    pub worker_tick: Duration,         // фоновый воркер: nonce, quote балансы, истёкшие ордера
+    pub rpc_health_interval: Duration, // проверка latency / блока RPC нод
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Duration::from_secs(30),
+            reconnect_delay: Duration::from_secs(3),
+            receipt_poll: Duration::from_millis(500),
+            worker_tick: Duration::from_secs(1),
+            rpc_health_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+impl MonitorConfig {
+    /// Idle timeout не короче двух блоков сети: иначе переподключение между обычными блоками
+    pub fn effective_idle_timeout(&self, block_time: Option<Duration>) -> Duration {
+        block_time.map_or(self.idle_timeout, |b| self.idle_timeout.max(b * 2))
+    }
+}
+
+/// Особенности сети (Init, chain_quirks): какой тип tx строить, насколько верить газу ноды, время блока
+#[derive(Clone, Debug)]
+pub struct ChainQuirks {
+    pub force_legacy_tx: bool,
+    pub min_gas_price_gwei: f64,        // цена газа любой tx не ниже; 0 - без минимума
+    pub gas_multiplier: f64,            // поправка к газу ноды (eth_gasPrice, feeHistory)
+    pub supports_eip1559: Option<bool>, // None - по feeHistory, tx legacy
+    pub block_time: Option<Duration>,   // задано конфигом - не измеряется
+}
+
+impl Default for ChainQuirks {
+    fn default() -> Self {
+        Self { force_legacy_tx: false, min_gas_price_gwei: 0.0, gas_multiplier: 1.0, supports_eip1559: None, block_time: None }
+    }
+}
+
+impl ChainQuirks {
+    pub fn min_gas_price(&self) -> U256 {
+        U256::from((self.min_gas_price_gwei * 1e9) as u64)
+    }
+
+    /// Газ, который сообщила нода: с поправкой сети и не ниже минимума
+    pub fn network_gas(&self, wei: U256) -> U256 {
+        let scaled = if self.gas_multiplier == 1.0 { wei } else { wei * U256::from((self.gas_multiplier * 1e6) as u64) / U256::from(1_000_000) };
+        scaled.max(self.min_gas_price())
+    }
+
+    /// Оракул feeHistory с той же поправкой: ступени не ниже минимума
+    pub fn network_oracle(&self, o: GasOracle) -> GasOracle {
+        let (m, min) = (self.gas_multiplier, self.min_gas_price_gwei);
+        GasOracle {
+            base_fee_gwei: o.base_fee_gwei * m,
+            base_fee_trend_pct: o.base_fee_trend_pct,
+            priority_gwei: o.priority_gwei.map(|p| p * m),
+            slow_gwei: (o.slow_gwei * m).max(min),
+            standard_gwei: (o.standard_gwei * m).max(min),
+            fast_gwei: (o.fast_gwei * m).max(min),
+        }
+    }
+
+    /// feeHistory не запрашивается у сетей, где EIP-1559 заведомо нет
+    pub fn probe_fee_history(&self) -> bool {
+        self.supports_eip1559 != Some(false)
+    }
+
+    /// type-2 tx - только если сеть явно их принимает и legacy не навязан
+    pub fn eip1559_tx(&self) -> bool {
+        self.supports_eip1559 == Some(true) && !self.force_legacy_tx
+    }
+}
+
+/// Лимит auto-fuel на кошелёк (Init, fuel_settings)
+#[derive(Clone, Copy, Debug)]
+pub struct FuelLimits {
+    pub max_attempts: u32,    // неудачных попыток подряд до карантина
+    pub cooldown: Duration,   // между попытками
+    pub quarantine: Duration, // после исчерпания попыток, затем счётчик сбрасывается
+}
+
+impl Default for FuelLimits {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            cooldown: Duration::from_secs(60),
+            quarantine: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+/// Что делать с кошельком ниже порога на этом блоке
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FuelGate {
+    Attempt,   // попытка уже засчитана
+    Wait,      // cooldown или карантин
+    Exhausted, // попытки только что кончились - карантин начат, сообщить один раз
+}
+
+/// Попытки auto-fuel кошелька с последнего успеха (или пополнения извне)
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct FuelAttempts {
+    pub count: u32,
+    pub last_ms: u64,
+    pub quarantined_at_ms: Option<u64>,
+}
+
+impl FuelAttempts {
+    pub fn gate(&mut self, limits: &FuelLimits, now_ms: u64) -> FuelGate {
+        if let Some(at) = self.quarantined_at_ms {
+            if now_ms.saturating_sub(at) < limits.quarantine.as_millis() as u64 { return FuelGate::Wait; }
+            *self = Self::default();
+        }
+        if self.count >= limits.max_attempts {
+            self.quarantined_at_ms = Some(now_ms);
+            return FuelGate::Exhausted;
+        }
+        if now_ms.saturating_sub(self.last_ms) <= limits.cooldown.as_millis() as u64 { return FuelGate::Wait; }
+        self.count += 1;
+        self.last_ms = now_ms;
+        FuelGate::Attempt
+    }
+}
+
+/// V3 fee tiers по умолчанию: PancakeSwap (2500) + Uniswap (3000)
+pub const DEFAULT_V3_FEE_TIERS: [u32; 5] = [100, 500, 2500, 3000, 10000];
+
+/// Пул-кандидат из ранжирования prefetch (для split-роутинга и ручного выбора пула)
+#[derive(Clone, Debug)]
+pub struct RankedPool {
+    pub address: H160,
+    pub pool_type: String,
+    pub fee: u32,
+    pub liquidity_usd: f64,
+    pub spot_price: f64,
+    pub score: f64,
+}
+
+/// Контекст мониторинга одного токена: пара, выбранный пул/маршрут и кандидаты
+#[derive(Clone, Default, Debug)]
+pub struct TokenContext {
+    pub quote: Address,
+    pub quote_symbol: String,
+    
+    // Выбранный пул
+    pub pool_address: Option<H160>,
+    pub pool_type: Option<String>,
+    pub pool_fee: u32,
+    pub liquidity_usd: f64,
+    pub spot_price: f64,
+    pub pinned_pool: Option<H160>, // пул, выбранный пользователем вместо автоматического
+    
+    // Мульти-хоп маршрут token -> mid -> quote (пусто - прямой пул)
+    pub route: Vec<Address>,
+    pub route_pools: Vec<H160>,
+    pub route_fees: Vec<u32>, // только для V3 маршрута
+    
+    pub ranked_pools: Vec<RankedPool>,
+    pub pool_targets: Vec<H160>, // пулы в общем фильтре подписки (пусто - ждём создания пула)
+    pub created_pools: Vec<H160>, // пулы из PairCreated/PoolCreated, ещё не получившие ликвидность
+}
+
+impl TokenContext {
+    pub fn new(quote: Address, quote_symbol: String) -> Self {
+        Self { quote, quote_symbol, ..Default::default() }
+    }
+}
+
+/// Снимок eth_feeHistory: base fee и перцентили priority fee (всё в gwei)
+#[derive(Clone, Default, Debug)]
+pub struct GasOracle {
+    pub base_fee_gwei: f64,       // base fee следующего блока
+    pub base_fee_trend_pct: f64,  // изменение base fee за окно истории
+    pub priority_gwei: [f64; 4],  // перцентили 25/50/75/95
+    pub slow_gwei: f64,
+    pub standard_gwei: f64,
+    pub fast_gwei: f64,
+}
+
+/// Как выбирать цену газа для сделки: фиксированная или по оракулу в момент подписи
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GasStrategy {
+    Manual(f64),
+    Slow,
+    Standard,
+    Fast,
+}
+
+impl GasStrategy {
+    /// Неизвестная стратегия - ручной газ
+    pub fn parse(s: Option<&str>, manual_gwei: f64) -> Self {
+        match s.map(|s| s.to_lowercase()).as_deref() {
+            Some("slow") => Self::Slow,
+            Some("standard") => Self::Standard,
+            Some("fast") => Self::Fast,
+            _ => Self::Manual(manual_gwei),
+        }
+    }
+
+    /// Цена газа в gwei. Без EIP-1559 (нет оракула) - eth_gasPrice, затем ручной газ
+    pub fn resolve_gwei(&self) -> f64 {
+        let s = CORE_STATE.read().unwrap();
+        let tier = match self {
+            Self::Manual(g) => return *g,
+            Self::Slow => s.gas_oracle.as_ref().map(|o| o.slow_gwei),
+            Self::Standard => s.gas_oracle.as_ref().map(|o| o.standard_gwei),
+            Self::Fast => s.gas_oracle.as_ref().map(|o| o.fast_gwei),
+        };
+        let legacy = s.gas_price.low_u128() as f64 / 1e9;
+        tier.filter(|g| *g > 0.0)
+            .or_else(|| (legacy > 0.0).then_some(legacy))
+            .unwrap_or(s.manual_gas_price_gwei)
+    }
+}
+
+/// Откуда auto-fuel берёт газ
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FuelMode {
+    /// Свап quote-токена самого кошелька на нативную монету
+    Swap,
+    /// Перевод нативной монеты с кошелька-казны
+    Treasury,
+}
+
+impl FuelMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "swap" => Some(Self::Swap),
+            "treasury" => Some(Self::Treasury),
+            _ => None,
+        }
+    }
+}
+
+/// Сколько разрешать роутеру в approve
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ApprovalMode {
+    Unlimited,
+    /// Сумма сделки с небольшим запасом, повторный approve при нехватке allowance
+    Exact,
+}
+
+impl ApprovalMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "unlimited" => Some(Self::Unlimited),
+            "exact" => Some(Self::Exact),
+            _ => None,
+        }
+    }
+}
+
+/// Как выбирается slippage сделки
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SlippageMode {
+    Fixed,
+    /// Price impact сделки + буфер, не выше максимума
+    Auto,
+}
+
+impl SlippageMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "fixed" => Some(Self::Fixed),
+            "auto" => Some(Self::Auto),
+            _ => None,
+        }
+    }
+}
+
+/// Куда рассылать подписанные транзакции
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BroadcastMode {
+    /// Публичные RPC из пула
+    Public,
+    /// Только приватные релеи (tx не попадает в публичный mempool)
+    Private,
+    /// Релеи с небольшой форой, затем публичные RPC
+    Both,
+}
+
+impl BroadcastMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "public" => Some(Self::Public),
+            "private" => Some(Self::Private),
+            "both" => Some(Self::Both),
+            _ => None,
+        }
+    }
+}
+
+/// Базовая линия quote-резерва V2 пула для детекта слива ликвидности
+#[derive(Clone, Default, Debug)]
+pub struct LiquidityBaseline {
+    pub block: u64,
+    pub baseline_quote: f64,
+    pub last_quote: f64,
+    pub alerted_block: u64,
+    pub auto_sold: bool,
+}
+
+/// Направление пересечения ценового порога
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PriceDirection {
+    Above,
+    Below,
+}
+
+impl PriceDirection {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "above" => Some(Self::Above),
+            "below" => Some(Self::Below),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Above => "above",
+            Self::Below => "below",
+        }
+    }
+
+    /// Пересекла ли цена порог при переходе prev -> current
+    pub fn crossed(&self, prev: f64, current: f64, threshold: f64) -> bool {
+        match self {
+            Self::Above => prev < threshold && current >= threshold,
+            Self::Below => prev > threshold && current <= threshold,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PriceAlert {
+    pub id: String,
+    pub token: Address,
+    pub quote: Address,
+    pub threshold: f64,
+    pub direction: PriceDirection,
+    pub repeat: bool,
+    pub last_price: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LimitOrder {
+    pub id: String,
+    pub action: String,
+    pub token: Address,
+    pub quote: Address,
+    pub trigger_price: f64,
+    pub amount: f64,
+    pub wallets: Vec<Address>,
+    pub slippage: f64,
+    pub expiry_ts: u64, // unix секунды, 0 = бессрочно
+    pub amounts_wei: Option<HashMap<String, String>>,
+}
+
+impl LimitOrder {
+    /// Покупка - когда цена опустилась до триггера, продажа - когда поднялась
+    pub fn is_triggered(&self, price: f64) -> bool {
+        if self.action == "buy" { price <= self.trigger_price } else { price >= self.trigger_price }
+    }
+
+    pub fn is_expired(&self, now_secs: u64) -> bool {
+        self.expiry_ts > 0 && now_secs >= self.expiry_ts
+    }
+}
+
+/// Стратегия выхода (SL/TP) для позиции кошелька в токене
+#[derive(Clone, Debug)]
+pub struct ExitStrategy {
+    pub wallet: Address,
+    pub token: Address,
+    pub quote: Address,
+    pub stop_loss_pct: Option<f64>,
+    pub take_profit_pct: Option<f64>,
+    pub trail_pct: Option<f64>,
+    pub peak_value: f64, // high-water mark стоимости позиции (в quote), стартует с cost basis записи PnL
+}
+
+/// Оценка позиции PnL воркером для сводки портфеля
+#[derive(Clone, Debug)]
+pub struct PnlEntry {
+    pub quote: Address,
+    pub cost_basis: f64, // в единицах quote
+    pub cost_override: bool, // cost_basis задан вручную (SetExitStrategy), иначе - из позиции
+    pub balance: U256,
+    pub current_value: Option<f64>, // None - ещё не оценена (загрузка)
+}
+
+/// Позиция кошелька в токене по подтверждённым свапам ядра
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Position {
+    pub quote: Address,
+    pub quantity: U256,
+    pub cost: U256, // quote wei за текущее количество (средняя цена = cost / quantity)
+    pub realized_pnl: I256, // quote wei
+    pub simulated: bool, // в позиции есть бумажные (dry-run) сделки
+}
+
+/// Свап в ожидании receipt: по нему двигается позиция
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingSwap {
+    pub is_buy: bool,
+    pub quote: Address,
+    pub amount_in: U256,
+    pub expected_out: U256,
+}
+
+/// Повторяющаяся сделка (DCA): исполняется планировщиком каждые interval_secs
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecurringTrade {
+    pub id: String,
+    pub action: String,
+    pub token: Address,
+    pub quote: Address,
+    pub amount: f64,
+    pub wallets: Vec<Address>,
+    pub interval_secs: u64,
+    pub max_executions: u32, // 0 = без ограничения
+    pub executions: u32,
+    pub next_run_ms: u64,
+}
+
+/// Копирование покупок наблюдаемого адреса фиксированной суммой
+#[derive(Clone, Debug)]
+pub struct CopyTrade {
+    pub amount: f64,
+    pub wallets: Vec<String>,
+    pub slippage: Option<f64>, // None - общий slippage
+}
+
+/// Чужой адрес под наблюдением: ключей нет, в TRACKED_WALLETS не входит
+#[derive(Clone, Debug)]
+pub struct WatchedAddress {
+    pub label: String,
+    pub copy_trade: Option<CopyTrade>,
+}
+
+/// Отслеживаемая транзакция: команда-источник и заложенный под неё газ
+#[derive(Clone, Debug)]
+pub struct PendingTx {
+    pub request_id: Option<String>,
+    pub gas_limit: Option<u64>,
+    pub gas_estimated: Option<u64>,
+    pub position: Option<(Address, Address)>, // (кошелёк, токен), которому начисляется газ tx
+    pub swap: Option<PendingSwap>,
+    pub sandwich_pool: Option<Address>, // пул свапа для проверки блока на sandwich
+    pub wallet: Address,                 // отправитель - для TxDropped/TxStuck и пересинхронизации nonce
+    pub gas_price: Option<U256>,         // цена газа при отправке - если receipt без effectiveGasPrice
+    pub inserted_at: Instant,
+    pub last_checked: Option<Instant>,       // последний запрос receipt
+    pub stuck_reported_at: Option<Instant>,  // последний TxStuck
+}
+
+impl Default for PendingTx {
+    fn default() -> Self {
+        Self {
+            request_id: None, gas_limit: None, gas_estimated: None, position: None, swap: None, sandwich_pool: None,
+            wallet: Address::zero(), gas_price: None, inserted_at: Instant::now(), last_checked: None, stuck_reported_at: None,
+        }
+    }
+}
+
+impl PendingTx {
+    /// Пора ли снова спрашивать receipt: сразу после отправки - каждый тик,
+    /// дальше интервал растёт с возрастом tx до PENDING_MAX_POLL_INTERVAL
+    pub fn receipt_poll_due(&self, now: Instant) -> bool {
+        let Some(last) = self.last_checked else { return true; };
+        let backoff = (now.duration_since(self.inserted_at) / PENDING_POLL_BACKOFF_DIVISOR).min(PENDING_MAX_POLL_INTERVAL);
+        now.duration_since(last) >= backoff
+    }
+}
+
+/// Свеча цены пула (в quote). ts - начало интервала, unix секунды
+#[derive(Clone, Copy, Debug)]
+pub struct Candle {
+    pub ts: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume_quote: f64,
+}
+
+impl Candle {
+    /// Свеча без сделок: все цены равны цене открытия
+    pub fn flat(ts: u64, price: f64) -> Self {
+        Self { ts, open: price, high: price, low: price, close: price, volume_quote: 0.0 }
+    }
+}
+
+/// Текущая (открытая) свеча и кольцевой буфер закрытых
+#[derive(Clone, Debug, Default)]
+pub struct CandleSeries {
+    pub current: Option<Candle>,
+    pub history: VecDeque<Candle>,
+}
+
+pub const DEFAULT_CANDLE_INTERVALS: [u64; 3] = [1, 5, 60];
+pub const DEFAULT_CANDLE_HISTORY: usize = 500;
+
+/// Источник USD цены: UpdatePrice из Python главнее фида ядра, пока не устарел
+pub const USD_PRICE_MANUAL: &str = "manual";
+
+/// Когда и откуда пришла цена из usd_prices
+#[derive(Clone, Debug)]
+pub struct UsdPriceMeta {
+    pub updated: Instant,
+    pub source: &'static str,
+}
+
 pub struct BotState {
     // Network
     pub chain_id: u64,
@@ -21,18 +577,47 @@ pub struct BotState {
     pub native_address: Address,
     pub wrapped_native_address: Address,
     pub wss_url: String,
+    pub network_name: String, // файл конфига сети из Init, пусто - не передан
+    pub ens_registry: Option<Address>, // None - имена в командах не разрешаются
+    pub ens_reverse_lookup: bool,
+    
+    pub head_block: u64, // последний блок из подписки newHeads
+    pub block_time: Option<Duration>, // среднее время блока сети, None - ещё не измерено
+    pub monitor: MonitorConfig,
+    pub chain_quirks: ChainQuirks,
     
     // Gas & Trading
     pub gas_price: U256,
+    pub gas_oracle: Option<GasOracle>, // None - сеть без EIP-1559 или feeHistory ещё не получен
     pub slippage: f64,
     pub manual_gas_price_gwei: f64,
+    pub swap_deadline_secs: u64, // дедлайн свапа по умолчанию (сек от подписи)
+    pub pending_tx_timeout_secs: u64, // после - проверка tx на dropped / stuck
+    pub v2_pool_fee_bps: u32,    // комиссия V2 пулов за роутером сети
+    pub router_tax_bps: u32,     // комиссия TaxRouter с каждого свапа
+    pub slippage_mode: SlippageMode,
+    pub auto_slippage_buffer_bps: f64, // auto: запас сверх impact
+    pub auto_slippage_max_pct: f64,    // auto: потолок; impact выше - сделка отклоняется
+    pub sandwich_precheck: bool,       // сверка живой цены пула с quote перед подписью
+    pub sandwich_max_move_pct: f64,
+    pub sandwich_detect: bool,         // поиск front-run в блоке подтверждённого свапа
+    pub batch_burst: bool,             // batch trade: рассылка всех кошельков разом после подписи
     
     // Wallets
-    pub wallet_keys: HashMap<Address, String>,
+    pub wallet_keys: HashMap<Address, WalletKey>,
     pub nonce_map: HashMap<Address, u64>,
+    pub watched_addresses: HashMap<Address, WatchedAddress>,
+    
+    // Балансы и фильтр пыли (balances.rs)
+    pub balances: HashMap<(Address, Address), (U256, f64)>, // (кошелёк, токен) -> последний баланс, и отфильтрованный тоже
+    pub ignored_tokens: HashSet<Address>, // ни BalanceUpdate, ни balanceOf по Transfer
+    pub min_balance_usd: f64, // 0 - без USD порога
+    pub min_balance_wei: HashMap<Address, U256>, // native - под balances::NATIVE
     
     // Prices & Decimals
     pub usd_prices: HashMap<String, f64>,
+    pub usd_price_meta: HashMap<String, UsdPriceMeta>,
+    pub usd_price_max_age: Option<Duration>, // None - цены не устаревают
     pub decimals_cache: HashMap<Address, u8>,
     
     // V2 Pools
@@ -40,13 +625,55 @@ pub struct BotState {
     
     // V3 Pools
     pub v3_states: HashMap<H160, V3PoolState>,
+    pub v3_fee_tiers: Vec<u32>,
+    pub v3_tick_windows: HashMap<H160, V3TickWindow>,
+    
+    // Свечи: (пул, интервал в секундах) -> серия
+    pub candles: HashMap<(H160, u64), CandleSeries>,
+    pub candle_intervals: Vec<u64>,
+    pub candle_history: usize, // сколько закрытых свечей хранить на серию
     
     // Auto-fuel
     pub fuel_enabled: bool,
     pub fuel_threshold: U256,
     pub fuel_amount: U256,
     pub fuel_quote_address: Address,
-    pub auto_fuel_attempts: HashMap<Address, (u32, u64)>, // (count, last_timestamp_ms)
+    pub fuel_limits: FuelLimits,
+    pub fuel_mode: FuelMode,
+    pub fuel_treasury: Address,         // Treasury: кошелёк-казна (ключ в wallet_keys)
+    pub fuel_treasury_reserve: U256,    // ниже этого казна не опускается
+    pub auto_fuel_attempts: HashMap<Address, FuelAttempts>,
+    
+    // Rug-alert
+    pub rug_alert_pct: f64,
+    pub rug_auto_sell: bool,
+    pub liquidity_baselines: HashMap<H160, LiquidityBaseline>,
+    
+    // Price alerts
+    pub price_alerts: Vec<PriceAlert>,
+    
+    // Limit orders
+    pub limit_orders: Vec<LimitOrder>,
+    
+    // Stop-loss / Take-profit
+    pub exit_strategies: Vec<ExitStrategy>,
+    
+    // Портфель: (кошелёк, токен) -> оценка. PortfolioPnL при сдвиге позиции больше delta (п.п. PnL)
+    pub pnl_entries: HashMap<(Address, Address), PnlEntry>,
+    pub portfolio_pnl_delta_pct: f64,
+    pub pnl_update_epsilon_pct: f64, // PnLUpdate позиции - при сдвиге больше (п.п. PnL)
+    
+    // Позиции: (кошелёк, токен) -> количество и средняя цена
+    pub positions: HashMap<(Address, Address), Position>,
+    
+    // Газ по позициям (wei, включая упавшие tx). Approve и auto-fuel начисляются только по флагам
+    pub position_gas: HashMap<(Address, Address), U256>,
+    pub last_traded_token: HashMap<Address, Address>, // кошелёк -> токен последнего свапа (для auto-fuel)
+    pub attribute_approve_gas: bool,
+    pub attribute_fuel_gas: bool,
+    
+    // DCA
+    pub recurring_trades: Vec<RecurringTrade>,
     
     // Quote Symbol - для динамического получения USD цены
     pub quote_symbol: String,
@@ -54,26 +681,102 @@ pub struct BotState {
     // Quote_Tokens - для поиска и фильтрации пулов 
     // по квотируемым токенам
     pub quote_tokens: HashMap<String, Address>,
+    pub balance_quotes: Option<Vec<String>>, // символы quote_tokens, чьи балансы опрашиваются; None - все (wrapped native и fuel quote - всегда)
+    
+    // === ПОДПИСКИ НА ТОКЕНЫ (token -> контекст мониторинга) ===
+    pub tokens: HashMap<Address, TokenContext>,
+    pub max_tokens: usize, // лимит одновременных подписок (размер общего фильтра логов)
+    pub pool_switch_hysteresis_pct: f64, // на сколько % score нового лидера должен превышать текущий
+    
+    // Split-роутинг: минимальный выигрыш (bps), ради которого платим газ за второй свап
+    pub split_min_improvement_bps: f64,
     
-    // === ВЫБОР ЛУЧШЕГО ПУЛА ===
-    pub selected_pool_address: Option<H160>,
-    pub selected_pool_type: Option<String>,
-    pub selected_pool_fee: u32,
-    pub selected_pool_liquidity_usd: f64,
-    pub selected_pool_spot_price: f64,
+    // Лимиты газа: estimate_gas × safety, константы - если оценка не удалась
+    pub gas_limit_swap: u64,
+    pub gas_limit_approve: u64,
+    pub gas_limit_safety: f64,
+    
+    pub approval_mode: ApprovalMode,
+    pub auto_approve: bool, // фоновый безлимитный approve токена и quote при подписке
+    pub wrap_gas_reserve: U256, // сколько нативного оставлять на газ при wrap "max"
+    pub min_native_reserve: U256, // ни одна tx не опускает нативный баланс ниже (газ на следующую сделку)
+    pub disperse_address: Option<Address>, // disperseEther контракт: DisperseNative одной tx
+    
+    // MEV-защита: приватные релеи (eth_sendRawTransaction) в пул чтения не входят
+    pub broadcast_mode: BroadcastMode,
+    pub private_relay_urls: Vec<String>,
+    
+    // Локеры LP (UNCX, PinkLock...) для AuditToken
+    pub lp_lockers: Vec<Address>,
+    
+    // Закреплённые пулы из RestoreSession для токенов, на которые ещё не подписались: токен -> пул
+    pub restored_pins: HashMap<Address, H160>,
     
     // === ОТСЛЕЖИВАНИЕ ТРАНЗАКЦИЙ ===
-    pub pending_txs: std::collections::HashSet<H256>,
+    pub pending_txs: HashMap<H256, PendingTx>,
+}
+
+impl BotState {
+    /// Контекст подписки по паре адресов свапа (token/quote в любом порядке)
+    pub fn pair_context(&self, a: Address, b: Address) -> Option<&TokenContext> {
+        self.tokens.get(&a).filter(|c| c.quote == b)
+            .or_else(|| self.tokens.get(&b).filter(|c| c.quote == a))
+    }
+
+    /// Записывает USD цену. age - сколько лет цене у источника (для Chainlink - с updatedAt раунда)
+    pub fn set_usd_price(&mut self, symbol: String, price: f64, source: &'static str, age: Duration) {
+        let updated = Instant::now().checked_sub(age).unwrap_or_else(Instant::now);
+        self.usd_price_meta.insert(symbol.clone(), UsdPriceMeta { updated, source });
+        self.usd_prices.insert(symbol, price);
+    }
+
+    /// USD цена символа, если она не устарела
+    pub fn fresh_usd_price(&self, symbol: &str) -> Option<f64> {
+        let price = *self.usd_prices.get(symbol)?;
+        if let (Some(max_age), Some(meta)) = (self.usd_price_max_age, self.usd_price_meta.get(symbol)) {
+            if meta.updated.elapsed() > max_age { return None; }
+        }
+        Some(price)
+    }
 }
 
-pub static CORE_STATE: Lazy<Arc<RwLock<BotState>>> = Lazy::new(|| {
+pub static CORE_STATE: EngineLocal<Arc<RwLock<BotState>>> = EngineLocal::new(|| {
     Arc::new(RwLock::new(BotState {
         chain_id: 0,
         nonce_map: HashMap::new(),
+        watched_addresses: HashMap::new(),
+        head_block: 0,
+        block_time: None,
+        chain_quirks: ChainQuirks::default(),
+        network_name: String::new(),
+        ens_registry: None,
+        ens_reverse_lookup: false,
+        monitor: MonitorConfig::default(),
         gas_price: U256::zero(),
+        gas_oracle: None,
         slippage: 15.0,
         manual_gas_price_gwei: 0.1,
+        swap_deadline_secs: DEFAULT_SWAP_DEADLINE_SECS,
+        pending_tx_timeout_secs: DEFAULT_PENDING_TX_TIMEOUT_SECS,
+        v2_pool_fee_bps: crate::math::DEFAULT_V2_FEE_BPS,
+        router_tax_bps: crate::math::DEFAULT_ROUTER_TAX_BPS,
+        slippage_mode: SlippageMode::Fixed,
+        auto_slippage_buffer_bps: 50.0,
+        auto_slippage_max_pct: 30.0,
+        sandwich_precheck: false,
+        sandwich_max_move_pct: 1.0,
+        sandwich_detect: true,
+        batch_burst: false,
+        balances: HashMap::new(),
+        ignored_tokens: HashSet::new(),
+        min_balance_usd: 0.0,
+        min_balance_wei: HashMap::new(),
         usd_prices: HashMap::new(),
+        usd_price_meta: HashMap::new(),
+        usd_price_max_age: None,
+        candles: HashMap::new(),
+        candle_intervals: DEFAULT_CANDLE_INTERVALS.to_vec(),
+        candle_history: DEFAULT_CANDLE_HISTORY,
         router_address: Address::zero(),
         quoter_address: Address::zero(),
         v2_factory_address: Address::zero(),
@@ -85,18 +788,51 @@ pub static CORE_STATE: Lazy<Arc<RwLock<BotState>>> = Lazy::new(|| {
         decimals_cache: HashMap::new(),
         v2_reserves: HashMap::new(),
         v3_states: HashMap::new(),
+        v3_fee_tiers: DEFAULT_V3_FEE_TIERS.to_vec(),
+        v3_tick_windows: HashMap::new(),
         fuel_enabled: false,
         fuel_threshold: U256::zero(),
         fuel_amount: U256::zero(),
         fuel_quote_address: Address::zero(),
+        fuel_limits: FuelLimits::default(),
+        fuel_mode: FuelMode::Swap,
+        fuel_treasury: Address::zero(),
+        fuel_treasury_reserve: U256::zero(),
         auto_fuel_attempts: HashMap::new(),
+        rug_alert_pct: 50.0,
+        rug_auto_sell: false,
+        liquidity_baselines: HashMap::new(),
+        price_alerts: Vec::new(),
+        limit_orders: Vec::new(),
+        exit_strategies: Vec::new(),
+        pnl_entries: HashMap::new(),
+        portfolio_pnl_delta_pct: 1.0,
+        pnl_update_epsilon_pct: 0.1,
+        positions: HashMap::new(),
+        position_gas: HashMap::new(),
+        last_traded_token: HashMap::new(),
+        attribute_approve_gas: false,
+        attribute_fuel_gas: false,
+        recurring_trades: Vec::new(),
         quote_symbol: String::new(),
         quote_tokens: HashMap::new(),
-        selected_pool_address: None,
-        selected_pool_type: None,
-        selected_pool_fee: 0,
-        selected_pool_liquidity_usd: 0.0,
-        selected_pool_spot_price: 0.0,
-        pending_txs: std::collections::HashSet::new(),
+        balance_quotes: None,
+        tokens: HashMap::new(),
+        max_tokens: 5,
+        pool_switch_hysteresis_pct: 20.0,
+        split_min_improvement_bps: 10.0,
+        gas_limit_swap: 500_000,
+        gas_limit_approve: 100_000,
+        gas_limit_safety: 1.3,
+        approval_mode: ApprovalMode::Unlimited,
+        auto_approve: true,
+        wrap_gas_reserve: U256::from(2_000_000_000_000_000u64), // 0.002
+        min_native_reserve: U256::zero(),
+        disperse_address: None,
+        broadcast_mode: BroadcastMode::Public,
+        private_relay_urls: Vec::new(),
+        lp_lockers: Vec::new(),
+        restored_pins: HashMap::new(),
+        pending_txs: HashMap::new(),
     }))
 });
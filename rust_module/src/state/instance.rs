@@ -0,0 +1,173 @@
+// Экземпляры ядра: у каждого своё состояние, RPC пул, очередь команд, очередь событий и задачи на общем RUNTIME.
+// Статики экземпляра объявлены как EngineLocal - обращение идёт к копии движка текущей задачи.
+// Движок задачи задаётся ENGINE_ID (scope), без него - движок по умолчанию (старый однодвижковый API).
+// destroy_engine_id освобождает значения движка, когда у него не остаётся задач и вызовов with_engine
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+use std::ops::Deref;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::task::JoinHandle;
+use super::RUNTIME;
+
+pub type EngineId = u64;
+
+/// Движок однодвижкового API (push_to_engine / pop_from_bridge без engine_id)
+pub const DEFAULT_ENGINE: EngineId = 0;
+
+tokio::task_local! {
+    static ENGINE_ID: EngineId;
+}
+
+// Созданные через create_engine (движок по умолчанию есть всегда)
+static ENGINES: RwLock<BTreeSet<EngineId>> = RwLock::new(BTreeSet::new());
+static NEXT_ENGINE_ID: AtomicU64 = AtomicU64::new(DEFAULT_ENGINE + 1);
+
+/// Значения EngineLocal одного движка по адресу статика. Box не двигается при перестройке карты -
+/// ссылка из deref действительна, пока запись движка не удалена
+#[derive(Default)]
+struct EngineSlots {
+    values: BTreeMap<usize, Box<dyn Any + Send + Sync>>,
+    // Задачи (spawn) и вызовы with_engine, которые сейчас работают от имени движка
+    live: usize,
+    destroyed: bool,
+}
+
+static SLOTS: RwLock<BTreeMap<EngineId, EngineSlots>> = RwLock::new(BTreeMap::new());
+
+/// Движок занят задачей или with_engine: пока такой есть, значения движка не освобождаются.
+/// Движок по умолчанию живёт весь процесс и не считается
+struct Live(EngineId);
+
+impl Live {
+    fn enter(id: EngineId) -> Option<Self> {
+        if id == DEFAULT_ENGINE { return None; }
+        // with_engine удалённого движка: его значения освободит последний выход
+        let destroyed = !engine_exists(id);
+        SLOTS.write().unwrap().entry(id).or_insert_with(|| EngineSlots { destroyed, ..Default::default() }).live += 1;
+        Some(Live(id))
+    }
+}
+
+impl Drop for Live {
+    fn drop(&mut self) {
+        let released = {
+            let mut slots = SLOTS.write().unwrap();
+            let Some(engine) = slots.get_mut(&self.0) else { return; };
+            engine.live -= 1;
+            if engine.live > 0 || !engine.destroyed { return; }
+            slots.remove(&self.0)
+        };
+        // Drop значений (каналы, сокеты) - вне блокировки
+        drop(released);
+    }
+}
+
+/// Движок текущей задачи
+pub fn current_engine() -> EngineId {
+    ENGINE_ID.try_with(|id| *id).unwrap_or(DEFAULT_ENGINE)
+}
+
+pub fn create_engine_id() -> EngineId {
+    let id = NEXT_ENGINE_ID.fetch_add(1, Ordering::Relaxed);
+    ENGINES.write().unwrap().insert(id);
+    id
+}
+
+/// Движок удалён (или не создан - create_engine упал на разборе конфига): id больше не существует,
+/// значения его EngineLocal освобождаются сразу или после последней его задачи
+pub fn destroy_engine_id(id: EngineId) {
+    if id == DEFAULT_ENGINE { return; }
+    ENGINES.write().unwrap().remove(&id);
+    let released = {
+        let mut slots = SLOTS.write().unwrap();
+        match slots.get_mut(&id) {
+            Some(engine) if engine.live > 0 => { engine.destroyed = true; None }
+            _ => slots.remove(&id),
+        }
+    };
+    drop(released);
+}
+
+/// Значения удалённого движка освобождены (у него не осталось задач)
+#[cfg(feature = "engine-tests")]
+pub fn engine_released(id: EngineId) -> bool {
+    !engine_exists(id) && !SLOTS.read().unwrap().contains_key(&id)
+}
+
+pub fn engine_exists(id: EngineId) -> bool {
+    id == DEFAULT_ENGINE || ENGINES.read().unwrap().contains(&id)
+}
+
+/// Все движки, по умолчанию - первым
+pub fn engine_ids() -> Vec<EngineId> {
+    std::iter::once(DEFAULT_ENGINE).chain(ENGINES.read().unwrap().iter().copied()).collect()
+}
+
+/// Синхронный код (pyfunction) от имени движка
+pub fn with_engine<R>(id: EngineId, f: impl FnOnce() -> R) -> R {
+    let _live = Live::enter(id);
+    ENGINE_ID.sync_scope(id, f)
+}
+
+/// spawn на общем RUNTIME с движком текущей задачи: tokio::spawn напрямую потерял бы его,
+/// и задача работала бы с движком по умолчанию
+pub fn spawn<F>(fut: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let id = current_engine();
+    let live = Live::enter(id);
+    RUNTIME.spawn(async move {
+        // Объявлен первым - отпускается после future задачи (и её drop внутри scope движка)
+        let _live = live;
+        ENGINE_ID.scope(id, fut).await
+    })
+}
+
+/// Статик с отдельным значением на каждый движок. Значение создаётся при первом обращении из движка
+/// и живёт, пока жив движок (движок по умолчанию - до конца процесса, как обычный Lazy статик)
+pub struct EngineLocal<T: 'static> {
+    init: fn() -> T,
+}
+
+impl<T: Send + Sync> EngineLocal<T> {
+    pub const fn new(init: fn() -> T) -> Self {
+        Self { init }
+    }
+
+    fn slot(value: &(dyn Any + Send + Sync)) -> *const T {
+        value.downcast_ref::<T>().expect("EngineLocal: чужой тип в слоте") as *const T
+    }
+}
+
+impl<T: Send + Sync> Deref for EngineLocal<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        let id = current_engine();
+        let key = self as *const Self as usize;
+        let found = SLOTS.read().unwrap().get(&id).and_then(|e| e.values.get(&key)).map(|v| Self::slot(v.as_ref()));
+        let ptr = match found {
+            Some(ptr) => ptr,
+            None => {
+                // init вне блокировки: он сам может обращаться к статикам и запускать задачи (COMMAND_TX)
+                let value: Box<dyn Any + Send + Sync> = Box::new((self.init)());
+                let mut slots = SLOTS.write().unwrap();
+                let values = &mut slots.entry(id).or_default().values;
+                let (ptr, lost) = match values.entry(key) {
+                    std::collections::btree_map::Entry::Occupied(e) => (Self::slot(e.get().as_ref()), Some(value)),
+                    std::collections::btree_map::Entry::Vacant(e) => (Self::slot(e.insert(value).as_ref()), None),
+                };
+                drop(slots);
+                drop(lost);
+                ptr
+            }
+        };
+        // SAFETY: значение лежит в Box в SLOTS и удаляется только вместе с движком - когда у него нет
+        // ни задач, ни with_engine. Код, который обращается к статику от имени движка, работает внутри них
+        unsafe { &*ptr }
+    }
+}
@@ -2,8 +2,12 @@ pub mod runtime;
 pub mod app;
 pub mod network;
 pub mod monitor;
+pub mod providers;
+pub mod instance;
 
-pub use runtime::{RUNTIME, GLOBAL_HTTP_CLIENT};
-pub use app::{CORE_STATE, V3PoolState}; 
-pub use network::{RPC_POOL, RpcNode, SHUTDOWN_FLAG};
-pub use monitor::{TRACKED_WALLETS, MONITOR_HANDLE, INTERNAL_HANDLE, RPC_CHECKER_HANDLE, PNL_HANDLE};
\ No newline at end of file
+pub use runtime::{RUNTIME, GLOBAL_HTTP_CLIENT, CONFIG_DIR};
+pub use instance::{EngineId, EngineLocal, DEFAULT_ENGINE, current_engine, engine_ids, engine_exists, with_engine, spawn};
+pub use app::{CORE_STATE, V3PoolState, V3TickWindow, TokenContext, LiquidityBaseline, PriceAlert, PriceDirection, LimitOrder, ExitStrategy, PnlEntry, Position, PendingSwap, RecurringTrade, RankedPool, GasOracle, PendingTx, MonitorConfig, ChainQuirks, FuelLimits, FuelGate, FuelMode, CopyTrade, WatchedAddress, Candle, CandleSeries, DEFAULT_CANDLE_INTERVALS, DEFAULT_CANDLE_HISTORY, USD_PRICE_MANUAL, ApprovalMode, BroadcastMode, SlippageMode, GasStrategy, WalletKey, parse_wallet_key, DEFAULT_V3_FEE_TIERS}; 
+pub use network::{RPC_POOL, DEFAULT_MAX_BLOCK_LAG, SHUTDOWN_FLAG, TRADING_PAUSED, DRY_RUN, CONNECTED};
+pub use providers::{http_provider, fastest_http_provider, set_ws_provider, ws_provider, invalidate_providers};
+pub use monitor::{TRACKED_WALLETS, MONITOR_HANDLE, DISCOVERY_HANDLES, SUBSCRIPTIONS_CHANGED, TRANSFER_SET_CHANGED, INTERNAL_HANDLE, RECEIPT_HANDLE, RPC_CHECKER_HANDLE, PNL_HANDLE, SCHEDULER_HANDLE, METRICS_HANDLE, PRICE_FEED_HANDLE, CANDLE_HANDLE, TWAP_HANDLES, FEE_TIERS_HANDLE, SESSION_HANDLE, AbortOnDrop};
\ No newline at end of file
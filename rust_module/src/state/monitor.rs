@@ -1,18 +1,62 @@
 use std::sync::{Arc, RwLock, Mutex};
-use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use super::instance::EngineLocal;
 use ethers::types::Address;
 use tokio::task::AbortHandle;
+use tokio::sync::Notify;
 
-pub static TRACKED_WALLETS: Lazy<Arc<RwLock<Vec<Address>>>> = Lazy::new(|| Arc::new(RwLock::new(Vec::new())));
+pub static TRACKED_WALLETS: EngineLocal<Arc<RwLock<Vec<Address>>>> = EngineLocal::new(|| Arc::new(RwLock::new(Vec::new())));
 
 // Хендл для основного монитора (универсальный, который слушает пулы и свапы)
-pub static MONITOR_HANDLE: Lazy<Arc<Mutex<Option<AbortHandle>>>> = Lazy::new(|| Arc::new(Mutex::new(None)));
+pub static MONITOR_HANDLE: EngineLocal<Arc<Mutex<Option<AbortHandle>>>> = EngineLocal::new(|| Arc::new(Mutex::new(None)));
+
+// Поиск пулов по каждому подписанному токену (отменяется при отписке)
+pub static DISCOVERY_HANDLES: EngineLocal<Arc<Mutex<HashMap<Address, AbortHandle>>>> = EngineLocal::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+// Набор подписок изменился - монитор пересобирает общий фильтр логов
+pub static SUBSCRIPTIONS_CHANGED: EngineLocal<Notify> = EngineLocal::new(Notify::new);
+
+// Сменился набор токенов вне подписок (fuel quote) - переподписка только на Transfer, без переподключения
+pub static TRANSFER_SET_CHANGED: EngineLocal<Notify> = EngineLocal::new(Notify::new);
 
 // Хендл для внутренних воркеров (газ, балансы) 
-pub static INTERNAL_HANDLE: Lazy<Arc<Mutex<Option<AbortHandle>>>> = Lazy::new(|| Arc::new(Mutex::new(None)));
+pub static INTERNAL_HANDLE: EngineLocal<Arc<Mutex<Option<AbortHandle>>>> = EngineLocal::new(|| Arc::new(Mutex::new(None)));
+
+// Хендл опроса receipt отправленных tx (HTTP пул, живёт независимо от WSS)
+pub static RECEIPT_HANDLE: EngineLocal<Arc<Mutex<Option<AbortHandle>>>> = EngineLocal::new(|| Arc::new(Mutex::new(None)));
 
 // Хендл для RPC Health Checker
-pub static RPC_CHECKER_HANDLE: Lazy<Arc<Mutex<Option<AbortHandle>>>> = Lazy::new(|| Arc::new(Mutex::new(None)));
+pub static RPC_CHECKER_HANDLE: EngineLocal<Arc<Mutex<Option<AbortHandle>>>> = EngineLocal::new(|| Arc::new(Mutex::new(None)));
 
 // Хендл для PnL калькулятора
-pub static PNL_HANDLE: Lazy<Arc<Mutex<Option<AbortHandle>>>> = Lazy::new(|| Arc::new(Mutex::new(None)));
\ No newline at end of file
+pub static PNL_HANDLE: EngineLocal<Arc<Mutex<Option<AbortHandle>>>> = EngineLocal::new(|| Arc::new(Mutex::new(None)));
+
+// Хендл для планировщика повторяющихся сделок (DCA)
+pub static SCHEDULER_HANDLE: EngineLocal<Arc<Mutex<Option<AbortHandle>>>> = EngineLocal::new(|| Arc::new(Mutex::new(None)));
+
+// Хендл HTTP сервера /metrics
+pub static METRICS_HANDLE: EngineLocal<Arc<Mutex<Option<AbortHandle>>>> = EngineLocal::new(|| Arc::new(Mutex::new(None)));
+
+// Хендл фида USD цен (Chainlink / HTTP)
+pub static PRICE_FEED_HANDLE: EngineLocal<Arc<Mutex<Option<AbortHandle>>>> = EngineLocal::new(|| Arc::new(Mutex::new(None)));
+
+// Хендл закрытия свечей по таймеру (интервалы без сделок)
+pub static CANDLE_HANDLE: EngineLocal<Arc<Mutex<Option<AbortHandle>>>> = EngineLocal::new(|| Arc::new(Mutex::new(None)));
+
+// Хендлы активных TWAP исполнений (отменяются при Shutdown/Init)
+pub static TWAP_HANDLES: EngineLocal<Arc<Mutex<Vec<AbortHandle>>>> = EngineLocal::new(|| Arc::new(Mutex::new(Vec::new())));
+
+// Хендл записи снимков сессии (session_path в Init)
+pub static SESSION_HANDLE: EngineLocal<Arc<Mutex<Option<AbortHandle>>>> = EngineLocal::new(|| Arc::new(Mutex::new(None)));
+
+// Хендл опроса fee tiers V3 фабрики при Init
+pub static FEE_TIERS_HANDLE: EngineLocal<Arc<Mutex<Option<AbortHandle>>>> = EngineLocal::new(|| Arc::new(Mutex::new(None)));
+
+/// Отменяет дочерние задачи при выходе из области - в том числе когда саму родительскую задачу отменили abort
+pub struct AbortOnDrop(pub Vec<AbortHandle>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        for handle in &self.0 { handle.abort(); }
+    }
+}
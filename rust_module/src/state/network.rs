@@ -1,6 +1,8 @@
-use std::sync::{Arc, RwLock};
-use once_cell::sync::Lazy;
-use std::sync::atomic::AtomicBool;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use super::instance::EngineLocal;
 
 #[derive(Debug, Clone)]
 pub struct RpcNode {
@@ -8,61 +10,201 @@ pub struct RpcNode {
     pub latency: u128,
     pub is_private: bool, 
     pub fails: u32,
+    pub quarantined: bool, // отдаёт чужую сеть - из пула не возвращается никогда
+    pub block: u64, // последний блок, который отдала нода
+    pub lag: u64,   // отставание от максимума по пулу
+    pub pinned: bool, // закреплена пользователем: выбирается первой среди живых
+    pub limiter: Option<Arc<RateLimiter>>, // None - без лимита
+    pub requests: Arc<AtomicU64>, // сколько раз нода выдана под запросы
+    pub penalty_until: Option<Instant>, // после 429 нода отдыхает до этого момента
+    pub penalty: Duration, // текущий шаг штрафа: удваивается на 429, тает на успешных замерах
 }
 
+impl RpcNode {
+    pub fn new(url: String, is_private: bool) -> Self {
+        Self {
+            url, latency: u128::MAX, is_private, fails: 0, quarantined: false, block: 0, lag: 0, pinned: false,
+            limiter: None, requests: Arc::new(AtomicU64::new(0)), penalty_until: None, penalty: Duration::ZERO,
+        }
+    }
+
+    /// Забирает токен из бюджета ноды. false - лимит на эту секунду исчерпан
+    fn try_acquire(&self) -> bool {
+        let ok = self.limiter.as_ref().is_none_or(|l| l.try_acquire());
+        if ok { self.requests.fetch_add(1, Ordering::Relaxed); }
+        ok
+    }
+
+    fn rate_limited(&self) -> bool {
+        self.penalty_until.is_some_and(|t| Instant::now() < t)
+    }
+
+    /// Порядок выбора: закреплённые, затем приватная (если не медленнее на 50ms), затем по задержке
+    fn preference(a: &RpcNode, b: &RpcNode) -> std::cmp::Ordering {
+        let a_latency = a.latency;
+        let b_latency = b.latency;
+        let priority_threshold = 50000u128;
+        
+        if a.pinned != b.pinned {
+            b.pinned.cmp(&a.pinned)
+        } else if a.is_private && !b.is_private {
+            if a_latency < b_latency.saturating_add(priority_threshold) {
+                std::cmp::Ordering::Less
+            } else {
+                std::cmp::Ordering::Greater
+            }
+        } else if !a.is_private && b.is_private {
+            if b_latency < a_latency.saturating_add(priority_threshold) {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Less
+            }
+        } else {
+            a_latency.cmp(&b_latency)
+        }
+    }
+}
+
+/// Token bucket: rps токенов в секунду, запас не больше rps (всплеск до секунды трафика)
+#[derive(Debug)]
+pub struct RateLimiter {
+    rps: f64,
+    state: Mutex<(f64, Instant)>, // (токены, момент пополнения)
+}
+
+impl RateLimiter {
+    pub fn new(rps: f64) -> Self {
+        Self { rps, state: Mutex::new((rps, Instant::now())) }
+    }
+
+    pub fn rps(&self) -> f64 {
+        self.rps
+    }
+
+    fn try_acquire(&self) -> bool {
+        let mut st = self.state.lock().unwrap();
+        let now = Instant::now();
+        st.0 = (st.0 + now.duration_since(st.1).as_secs_f64() * self.rps).min(self.rps);
+        st.1 = now;
+        if st.0 >= 1.0 {
+            st.0 -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// Штраф за HTTP 429: первый - 5s, дальше удваивается до 5 минут
+const PENALTY_BASE: Duration = Duration::from_secs(5);
+const PENALTY_MAX: Duration = Duration::from_secs(300);
+
+/// Ответ ноды означает превышение её лимита, а не отказ
+pub fn is_rate_limit_error(message: &str) -> bool {
+    let m = message.to_lowercase();
+    m.contains("429") || m.contains("too many requests") || m.contains("rate limit") || m.contains("-32005")
+}
+
+// Допустимое отставание ноды в блоках, если сеть не задала своё (BSC: 3s блоки)
+pub const DEFAULT_MAX_BLOCK_LAG: u64 = 5;
+
 #[derive(Debug, Default)]
 pub struct RpcPoolState {
     pub nodes: Vec<RpcNode>,
+    pub max_block_lag: u64, // 0 - отставание не учитывается
+    pub rate_limits: HashMap<String, f64>, // rps по url из конфига сети
+    pub public_rps: Option<f64>, // лимит публичных нод без своей записи в rate_limits
 }
 
 impl RpcPoolState {
+    pub fn usable(&self, n: &RpcNode) -> bool {
+        n.fails < 3 && !n.quarantined && !n.rate_limited() && (self.max_block_lag == 0 || n.lag <= self.max_block_lag)
+    }
+
+    /// Добавляет ноду с лимитом из конфига сети (приватная по умолчанию без лимита)
+    pub fn add_node(&mut self, url: String, is_private: bool) {
+        let rps = self.rate_limits.get(&url).copied()
+            .or(if is_private { None } else { self.public_rps })
+            .filter(|r| *r > 0.0);
+        let mut node = RpcNode::new(url, is_private);
+        node.limiter = rps.map(|r| Arc::new(RateLimiter::new(r)));
+        self.nodes.push(node);
+    }
+
+    /// Лучшая живая нода, у которой есть бюджет; выдача списывает токен.
+    /// Если бюджет кончился у всех - лучшая по задержке: превысить лимит лучше, чем остаться без RPC
     pub fn get_fastest_node(&self) -> Option<String> {
-        self.nodes.iter()
-            .filter(|n| n.fails < 3)
-            .min_by(|a, b| {
-                let a_latency = a.latency;
-                let b_latency = b.latency;
-                let priority_threshold = 50000u128;
-                
-                if a.is_private && !b.is_private {
-                    if a_latency < b_latency.saturating_add(priority_threshold) {
-                        std::cmp::Ordering::Less
-                    } else {
-                        std::cmp::Ordering::Greater
-                    }
-                } else if !a.is_private && b.is_private {
-                    if b_latency < a_latency.saturating_add(priority_threshold) {
-                        std::cmp::Ordering::Greater
-                    } else {
-                        std::cmp::Ordering::Less
-                    }
-                } else {
-                    a_latency.cmp(&b_latency)
-                }
-            })
-            .map(|n| n.url.clone())
+        let mut candidates: Vec<&RpcNode> = self.nodes.iter().filter(|n| self.usable(n)).collect();
+        let best = candidates.iter().copied().min_by(|a, b| RpcNode::preference(a, b))?;
+        while let Some((idx, node)) = candidates.iter().enumerate().min_by(|a, b| RpcNode::preference(a.1, b.1)) {
+            if node.try_acquire() { return Some(node.url.clone()); }
+            candidates.swap_remove(idx);
+        }
+        best.requests.fetch_add(1, Ordering::Relaxed);
+        Some(best.url.clone())
     }
 
     pub fn get_fastest_pool(&self, limit: usize) -> Vec<String> {
         let mut sorted = self.nodes.clone();
         sorted.sort_by(|a, b| {
+            if a.pinned != b.pinned { return b.pinned.cmp(&a.pinned); }
             match (a.is_private, b.is_private) {
                 (true, false) => std::cmp::Ordering::Less,  // приватный всегда первый
                 (false, true) => std::cmp::Ordering::Greater,
                 _ => a.latency.cmp(&b.latency),  // оба одинаковые - по latency
             }
         });
-        sorted.iter()
-            .filter(|n| n.fails < 3)
+        let alive: Vec<&RpcNode> = sorted.iter().filter(|n| self.usable(n)).collect();
+        let mut picked: Vec<String> = alive.iter()
+            .filter(|n| n.try_acquire())
             .take(limit)
             .map(|n| n.url.clone())
-            .collect()
+            .collect();
+        if picked.is_empty() {
+            if let Some(n) = alive.first() {
+                n.requests.fetch_add(1, Ordering::Relaxed);
+                picked.push(n.url.clone());
+            }
+        }
+        picked
     }
 
-    pub fn update_latency(&mut self, url: &str, latency: u128) {
+    pub fn update_latency(&mut self, url: &str, latency: u128, block: u64) {
         if let Some(node) = self.nodes.iter_mut().find(|n| n.url == url) {
             node.latency = latency;
             node.fails = 0;
+            node.block = block;
+            if !node.rate_limited() {
+                node.penalty /= 2;
+            }
+        }
+    }
+
+    /// Пересчитывает отставание от максимального блока среди живых нод.
+    /// Возвращает отставание приватной ноды, если оно превышает порог
+    pub fn update_lags(&mut self) -> Option<u64> {
+        let max_block = self.nodes.iter()
+            .filter(|n| n.fails < 3 && !n.quarantined)
+            .map(|n| n.block)
+            .max()
+            .unwrap_or(0);
+        for node in self.nodes.iter_mut() {
+            node.lag = max_block.saturating_sub(node.block);
+        }
+        self.nodes.iter()
+            .find(|n| n.is_private && n.fails < 3 && !n.quarantined)
+            .map(|n| n.lag)
+            .filter(|lag| self.max_block_lag > 0 && *lag > self.max_block_lag)
+    }
+
+    /// true - нода была в строю и только что выведена
+    pub fn quarantine(&mut self, url: &str) -> bool {
+        match self.nodes.iter_mut().find(|n| n.url == url) {
+            Some(node) if !node.quarantined => {
+                node.quarantined = true;
+                true
+            }
+            _ => false,
         }
     }
 
@@ -70,11 +212,38 @@ impl RpcPoolState {
         if let Some(node) = self.nodes.iter_mut().find(|n| n.url == url) {
             node.fails += 1;
         }
+        crate::metrics::rpc_fail(url);
+    }
+
+    /// 429: нода жива, но просит сбавить темп - временный штраф вместо счётчика fails
+    pub fn mark_rate_limited(&mut self, url: &str) {
+        if let Some(node) = self.nodes.iter_mut().find(|n| n.url == url) {
+            node.penalty = (node.penalty * 2).clamp(PENALTY_BASE, PENALTY_MAX);
+            node.penalty_until = Some(Instant::now() + node.penalty);
+        }
+    }
+
+    /// Ошибка запроса к ноде: 429 - штраф, остальное - в счётчик fails
+    pub fn report_error(&mut self, url: &str, message: &str) {
+        if is_rate_limit_error(message) {
+            self.mark_rate_limited(url);
+        } else {
+            self.mark_fail(url);
+        }
     }
 }
 
-pub static RPC_POOL: Lazy<Arc<RwLock<RpcPoolState>>> = Lazy::new(|| {
+pub static RPC_POOL: EngineLocal<Arc<RwLock<RpcPoolState>>> = EngineLocal::new(|| {
     Arc::new(RwLock::new(RpcPoolState::default()))
 });
 
-pub static SHUTDOWN_FLAG: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
+pub static SHUTDOWN_FLAG: EngineLocal<AtomicBool> = EngineLocal::new(|| AtomicBool::new(false));
+
+// Kill-switch: пока true, ни одна транзакция не подписывается (мониторинг продолжает работать)
+pub static TRADING_PAUSED: EngineLocal<AtomicBool> = EngineLocal::new(|| AtomicBool::new(false));
+
+// Dry-run: всё до подписи и оценки газа включительно, но без рассылки - синтетические хеши
+pub static DRY_RUN: EngineLocal<AtomicBool> = EngineLocal::new(|| AtomicBool::new(false));
+
+// Последний ConnectionStatus, отправленный в Python (планировщик ставит сделки на паузу при обрыве)
+pub static CONNECTED: EngineLocal<AtomicBool> = EngineLocal::new(|| AtomicBool::new(false));
@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use once_cell::sync::Lazy;
+use super::instance::EngineLocal;
+use ethers::providers::{Http, Provider, Ws};
+use url::Url;
+use super::{GLOBAL_HTTP_CLIENT, RPC_POOL};
+
+// Http провайдеры по url поверх общего клиента: Provider не собирается заново на каждый вызов
+static HTTP_PROVIDERS: Lazy<RwLock<HashMap<String, Arc<Provider<Http>>>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+// Живое WS соединение монитора: read-only вызовы идут через него без HTTP запросов
+static WS_PROVIDER: EngineLocal<RwLock<Option<Arc<Provider<Ws>>>>> = EngineLocal::new(|| RwLock::new(None));
+
+pub fn http_provider(url: &str) -> Option<Arc<Provider<Http>>> {
+    if let Some(p) = HTTP_PROVIDERS.read().unwrap().get(url) {
+        return Some(p.clone());
+    }
+    let u = Url::parse(url).ok()?;
+    let p = Arc::new(Provider::new(Http::new_with_client(u, GLOBAL_HTTP_CLIENT.clone())));
+    HTTP_PROVIDERS.write().unwrap().insert(url.to_string(), p.clone());
+    Some(p)
+}
+
+/// Провайдер лучшей ноды пула (с учётом бюджета запросов)
+pub fn fastest_http_provider() -> Option<Arc<Provider<Http>>> {
+    let url = { RPC_POOL.read().unwrap().get_fastest_node() }?;
+    http_provider(&url)
+}
+
+/// Монитор выставляет при подключении и снимает при обрыве
+pub fn set_ws_provider(ws: Option<Arc<Provider<Ws>>>) {
+    *WS_PROVIDER.write().unwrap() = ws;
+}
+
+pub fn ws_provider() -> Option<Arc<Provider<Ws>>> {
+    WS_PROVIDER.read().unwrap().clone()
+}
+
+/// Сброс кэша при смене url (UpdateSettings, RemoveRpcNode, повторный Init)
+pub fn invalidate_providers(url: Option<&str>) {
+    let mut cache = HTTP_PROVIDERS.write().unwrap();
+    match url {
+        Some(u) => { cache.remove(u); }
+        None => cache.clear(),
+    }
+}
@@ -1,6 +1,8 @@
 use once_cell::sync::Lazy;
 use tokio::runtime::Runtime;
 use reqwest::Client;
+use std::path::PathBuf;
+use std::sync::RwLock;
 
 pub static RUNTIME: Lazy<Runtime> = Lazy::new(|| {
     Runtime::new().unwrap()
@@ -15,3 +17,6 @@ pub static GLOBAL_HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
         .build()
         .unwrap()
 });
+
+/// Каталог конфигов сетей (set_config_dir), общий для всех движков. None - поиск по умолчанию
+pub static CONFIG_DIR: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
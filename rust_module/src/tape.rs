@@ -0,0 +1,78 @@
+use crate::state::EngineLocal;
+use ethers::types::{Address, H160, H256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use crate::bridge::{emit_event, EngineEvent};
+use crate::metrics;
+use crate::state::{CORE_STATE, TRACKED_WALLETS};
+
+// Лента сделок одного пула: больше - выкидываем, иначе активный пул забьёт мост
+const MAX_TRADES_PER_SEC: u32 = 20;
+
+// пул -> (секунда окна, сделок в окне)
+static RATE: EngineLocal<Mutex<HashMap<H160, (u64, u32)>>> = EngineLocal::new(|| Mutex::new(HashMap::new()));
+
+/// Сделка пула в единицах токенов (до проверки лимита)
+pub struct Trade {
+    pub pool: H160,
+    pub token: Address,
+    pub is_buy: bool,
+    pub token_amount: f64,
+    pub quote_amount: f64,
+    pub maker: Address,
+    pub tx_hash: Option<H256>,
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn within_rate(pool: H160, now: u64) -> bool {
+    let mut rate = RATE.lock().unwrap();
+    let entry = rate.entry(pool).or_insert((now, 0));
+    if entry.0 != now { *entry = (now, 0); }
+    entry.1 += 1;
+    entry.1 <= MAX_TRADES_PER_SEC
+}
+
+/// Лента только по выбранному пулу токена: плечи маршрута и кандидаты не пишутся
+fn is_selected_pool(token: Address, pool: H160) -> bool {
+    CORE_STATE.read().unwrap().tokens.get(&token)
+        .is_some_and(|c| c.pool_address == Some(pool) && c.route_pools.is_empty())
+}
+
+pub fn record(trade: Trade, quote_price_usd: f64) {
+    if trade.token_amount <= 0.0 && trade.quote_amount <= 0.0 { return; }
+    if !is_selected_pool(trade.token, trade.pool) { return; }
+    let now = now_secs();
+    if !within_rate(trade.pool, now) {
+        metrics::inc(&metrics::METRICS.tape_skipped);
+        return;
+    }
+
+    // Своя сделка: получатель - наш кошелёк, либо tx ещё в списке отправленных нами
+    let is_own = TRACKED_WALLETS.read().unwrap().contains(&trade.maker)
+        || trade.tx_hash.is_some_and(|h| CORE_STATE.read().unwrap().pending_txs.contains_key(&h));
+
+    emit_event(EngineEvent::TradeTape {
+        pool: format!("{:?}", trade.pool),
+        token: format!("{:?}", trade.token),
+        side: if trade.is_buy { "buy".into() } else { "sell".into() },
+        token_amount: trade.token_amount,
+        quote_amount: trade.quote_amount,
+        usd_value: trade.quote_amount * quote_price_usd,
+        maker: format!("{:?}", trade.maker),
+        tx_hash: trade.tx_hash.map(|h| format!("{:?}", h)).unwrap_or_default(),
+        ts: now,
+        is_own,
+        maker_name: crate::resolver::cached_name(trade.maker)
+    });
+}
+
+/// Отписка от пула - счётчик окна больше не нужен
+pub fn forget(pool: H160) {
+    RATE.lock().unwrap().remove(&pool);
+}
@@ -0,0 +1,131 @@
+// Вход для интеграционных тестов (tests/, фичи engine-tests и anvil-tests):
+// команды и события ходят тем же JSON, что и через Python
+use ethers::types::{Address, U256};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use crate::bridge::transport;
+use crate::engine;
+
+pub use crate::execution::{v2_amount_out, v2_oriented_reserves};
+
+/// Команда в формате EngineCommand ({"type": ..., "data": {...}})
+pub fn push_command(command: serde_json::Value) -> Result<(), String> {
+    engine::dispatch_command(command.to_string())
+}
+
+/// Сырой JSON команды как есть - для проверки разбора мусора
+pub fn push_raw_command(command_json: &str) -> Result<(), String> {
+    engine::dispatch_command(command_json.to_string())
+}
+
+/// Забирает накопленные события моста ({"type": ..., "data": {...}})
+pub fn pop_events(max_items: usize) -> Vec<serde_json::Value> {
+    transport::pop_batch(max_items).iter().filter_map(|json| serde_json::from_str(json).ok()).collect()
+}
+
+/// Новый экземпляр ядра с Init по конфигу (data команды Init)
+pub fn create_engine(network_config: serde_json::Value) -> Result<crate::state::EngineId, String> {
+    engine::start_engine(serde_json::json!({"type": "Init", "data": network_config}).to_string())
+}
+
+/// push_command в заданный движок
+pub fn push_command_to(engine_id: crate::state::EngineId, command: serde_json::Value) -> Result<(), String> {
+    if !crate::state::engine_exists(engine_id) {
+        return Err(format!("Unknown engine_id: {}", engine_id));
+    }
+    crate::state::with_engine(engine_id, || push_command(command))
+}
+
+/// destroy_engine: Shutdown движка и освобождение его состояния
+pub fn destroy_engine(engine_id: crate::state::EngineId) -> Result<(), String> {
+    engine::stop_engine(engine_id)
+}
+
+/// Состояние удалённого движка освобождено - у него не осталось задач
+pub fn engine_released(engine_id: crate::state::EngineId) -> bool {
+    crate::state::instance::engine_released(engine_id)
+}
+
+/// get_metrics_text движка
+pub fn metrics_text(engine_id: crate::state::EngineId) -> String {
+    crate::state::with_engine(engine_id, crate::metrics::render)
+}
+
+/// Фильтр событий движка: allow - пропускать только types, иначе всё кроме них
+pub fn set_event_filter(engine_id: crate::state::EngineId, allow: bool, types: &[&str]) {
+    crate::state::with_engine(engine_id, || {
+        crate::bridge::set_filter_mode(allow);
+        crate::bridge::set_filter_types(types.iter().map(|t| t.to_string()).collect());
+    });
+}
+
+/// pop_events из заданного движка
+pub fn pop_events_from(engine_id: crate::state::EngineId, max_items: usize) -> Vec<serde_json::Value> {
+    crate::state::with_engine(engine_id, || pop_events(max_items))
+}
+
+/// withdraw auto-fuel с ценой газа, которую ядро выбрало бы сейчас (None - цена неизвестна)
+pub fn fuel_withdraw_tx(wrapped_native: Address, amount: U256) -> Option<TypedTransaction> {
+    let gas_price = crate::state::RUNTIME.block_on(crate::execution::resolve_gas_price())?;
+    Some(crate::execution::withdraw_tx(wrapped_native, amount, 0, gas_price))
+}
+
+/// Пул выбран для подписки token, как после discovery: уходит PoolDetected
+pub fn select_pool(token: Address, quote: Address, pool: Address, pool_type: &str, liquidity_usd: f64, spot_price: f64) {
+    let ranked = crate::state::RankedPool { address: pool, pool_type: pool_type.to_string(), fee: 0, liquidity_usd, spot_price, score: 0.0 };
+    crate::state::RUNTIME.block_on(crate::monitor::apply_pool_selection(ranked, token, quote));
+}
+
+/// Sync V2 пула подписки через разбор монитора: уходит PoolUpdate.
+/// true - монитор переподписался бы (пул фабрики получил ликвидность)
+pub fn pool_sync(token: Address, quote: Address, pool: Address, reserve0: U256, reserve1: U256) -> bool {
+    use ethers::contract::EthEvent;
+    let log = ethers::types::Log {
+        address: pool,
+        topics: vec![crate::monitor::SyncFilter::signature()],
+        data: ethers::abi::encode(&[ethers::abi::Token::Uint(reserve0), ethers::abi::Token::Uint(reserve1)]).into(),
+        ..Default::default()
+    };
+    crate::state::RUNTIME.block_on(crate::monitor::replay_pool_log(token, quote, &log))
+}
+
+/// Burn V2 пула подписки в блоке block через разбор монитора (rug-алерт по сжиганию ликвидности)
+pub fn pool_burn(token: Address, quote: Address, pool: Address, amount0: U256, amount1: U256, block: u64) {
+    use ethers::contract::EthEvent;
+    let log = ethers::types::Log {
+        address: pool,
+        topics: vec![crate::monitor::BurnFilter::signature(), Address::zero().into(), Address::zero().into()],
+        data: ethers::abi::encode(&[ethers::abi::Token::Uint(amount0), ethers::abi::Token::Uint(amount1)]).into(),
+        block_number: Some(block.into()),
+        ..Default::default()
+    };
+    crate::state::RUNTIME.block_on(crate::monitor::replay_pool_log(token, quote, &log));
+}
+
+/// PairCreated V2 фабрики через разбор монитора: Some((token, pair)) - пара одной из подписок
+pub fn pair_created(factory: Address, token0: Address, token1: Address, pair: Address) -> Option<(Address, Address)> {
+    use ethers::contract::EthEvent;
+    let log = ethers::types::Log {
+        address: factory,
+        topics: vec![crate::monitor::PairCreatedFilter::signature(), token0.into(), token1.into()],
+        data: ethers::abi::encode(&[ethers::abi::Token::Address(pair), ethers::abi::Token::Uint(U256::one())]).into(),
+        ..Default::default()
+    };
+    crate::monitor::replay_factory_log(&log)
+}
+
+/// Синхронизация nonce отслеживаемых кошельков, как на тике фонового воркера
+pub async fn sync_nonces() {
+    let Some(provider) = crate::state::fastest_http_provider() else { return; };
+    let wallets = crate::state::TRACKED_WALLETS.read().unwrap().clone();
+    crate::monitor::sync_nonces(&provider, &wallets).await;
+}
+
+/// Тик воркера, на котором сеть вернула pending nonce chain_nonce для кошелька
+pub fn sync_nonce(wallet: Address, chain_nonce: u64) {
+    crate::execution::sync_nonce(wallet, chain_nonce);
+}
+
+/// Ожидаемый выход V2 по резервам, которые ядро держит для подписанной пары
+pub fn expected_out_v2(token_in: Address, token_out: Address, amount_in: U256) -> U256 {
+    crate::execution::calculate_expected_out_v2_pure(token_in, token_out, amount_in)
+}
@@ -0,0 +1,149 @@
+use crate::state::EngineLocal;
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{mpsc, Mutex};
+use crate::bridge::emit_log;
+
+/// Метаданные токена, которые не меняются у живого контракта
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TokenMeta {
+    #[serde(default)]
+    pub decimals: Option<u8>,
+    #[serde(default)]
+    pub symbol: String,
+    #[serde(default)]
+    pub name: String,
+}
+
+/// chain_id (строкой - ключ JSON) -> адрес -> метаданные
+type CacheFile = HashMap<String, HashMap<Address, TokenMeta>>;
+
+struct TokenCache {
+    path: String,
+    chain_id: u64,
+    entries: CacheFile,
+    tx: mpsc::Sender<String>,
+}
+
+static TOKEN_CACHE: EngineLocal<Mutex<Option<TokenCache>>> = EngineLocal::new(|| Mutex::new(None));
+
+/// Открывает кэш (из Init) и возвращает decimals текущей сети для CORE_STATE.decimals_cache
+pub fn open(path: String, chain_id: u64) -> HashMap<Address, u8> {
+    let mut guard = TOKEN_CACHE.lock().unwrap();
+    if let Some(c) = guard.as_mut().filter(|c| c.path == path) {
+        c.chain_id = chain_id;
+        return decimals_for(c);
+    }
+
+    let entries: CacheFile = match std::fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+            emit_log("WARNING", format!("🗃️ Кэш токенов повреждён ({}), начинаем с пустого", e));
+            HashMap::new()
+        }),
+        Err(_) => HashMap::new(),
+    };
+
+    // Запись на диск - в отдельном потоке, горячий путь только кладёт снимок в канал
+    let (tx, rx) = mpsc::channel();
+    let writer_path = path.clone();
+    let spawned = std::thread::Builder::new()
+        .name("token-cache".into())
+        .spawn(move || writer_loop(writer_path, rx));
+    if let Err(e) = spawned {
+        emit_log("ERROR", format!("🗃️ Не удалось запустить запись кэша токенов: {}", e));
+        return HashMap::new();
+    }
+
+    let cache = TokenCache { path, chain_id, entries, tx };
+    let decimals = decimals_for(&cache);
+    emit_log("INFO", format!("🗃️ Кэш токенов: {} записей для сети {}", decimals.len(), chain_id));
+    *guard = Some(cache);
+    decimals
+}
+
+fn decimals_for(cache: &TokenCache) -> HashMap<Address, u8> {
+    cache.entries.get(&cache.chain_id.to_string())
+        .map(|m| m.iter().filter_map(|(a, meta)| meta.decimals.map(|d| (*a, d))).collect())
+        .unwrap_or_default()
+}
+
+/// Изменяет запись токена текущей сети и ставит сохранение в очередь
+fn update(token: Address, f: impl FnOnce(&mut TokenMeta)) {
+    let mut guard = TOKEN_CACHE.lock().unwrap();
+    let Some(cache) = guard.as_mut() else { return; };
+    let chain = cache.entry_chain();
+    f(cache.entries.entry(chain).or_default().entry(token).or_default());
+    cache.flush();
+}
+
+impl TokenCache {
+    fn entry_chain(&self) -> String {
+        self.chain_id.to_string()
+    }
+
+    fn flush(&self) {
+        if let Ok(json) = serde_json::to_string(&self.entries) {
+            let _ = self.tx.send(json);
+        }
+    }
+}
+
+pub fn store_decimals(token: Address, decimals: u8) {
+    update(token, |m| m.decimals = Some(decimals));
+}
+
+pub fn store_info(token: Address, symbol: &str, name: &str) {
+    update(token, |m| {
+        m.symbol = symbol.to_string();
+        m.name = name.to_string();
+    });
+}
+
+/// (symbol, name) из кэша, если они уже известны
+pub fn cached_info(token: Address) -> Option<(String, String)> {
+    let guard = TOKEN_CACHE.lock().unwrap();
+    let cache = guard.as_ref()?;
+    let meta = cache.entries.get(&cache.entry_chain())?.get(&token)?;
+    (!meta.symbol.is_empty()).then(|| (meta.symbol.clone(), meta.name.clone()))
+}
+
+/// Удаляет токен (или все токены текущей сети) из файла. Возвращает число удалённых записей
+pub fn clear(token: Option<Address>) -> usize {
+    let mut guard = TOKEN_CACHE.lock().unwrap();
+    let Some(cache) = guard.as_mut() else { return 0; };
+    let chain = cache.entry_chain();
+    let Some(tokens) = cache.entries.get_mut(&chain) else { return 0; };
+    let removed = match token {
+        Some(t) => tokens.remove(&t).map_or(0, |_| 1),
+        None => {
+            let n = tokens.len();
+            tokens.clear();
+            n
+        }
+    };
+    if removed > 0 { cache.flush(); }
+    removed
+}
+
+fn write_atomic(path: &str, json: &str) -> std::io::Result<()> {
+    let tmp = format!("{}.tmp", path);
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, path)
+}
+
+fn writer_loop(path: String, rx: mpsc::Receiver<String>) {
+    let mut warned = false;
+    while let Ok(mut json) = rx.recv() {
+        // Промежуточные снимки не нужны - пишем только последний
+        if let Some(latest) = rx.try_iter().last() { json = latest; }
+        match write_atomic(&path, &json) {
+            Ok(()) => warned = false,
+            Err(e) if !warned => {
+                emit_log("WARNING", format!("🗃️ Не удалось сохранить кэш токенов: {}", e));
+                warned = true;
+            }
+            Err(_) => {}
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use crate::state::EngineLocal;
+use ethers::abi::{AbiDecode, AbiEncode};
+use ethers::types::{Address, Bytes, U256};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use crate::bridge::EngineEvent;
+use crate::execution::{self, NameCall, SymbolCall, TotalSupplyCall, TotalSupplyReturn};
+use crate::monitor;
+use crate::token_cache;
+
+/// symbol/name за сессию: повторный SwitchToken на тот же токен не ходит в RPC
+static METADATA: EngineLocal<RwLock<HashMap<Address, (String, String)>>> = EngineLocal::new(|| RwLock::new(HashMap::new()));
+
+/// string из ABI, а для старых токенов вроде MKR - bytes32 с нулями в хвосте
+fn decode_text(out: &[u8]) -> Option<String> {
+    let text = match String::decode(out) {
+        Ok(s) => s,
+        Err(_) if out.len() == 32 => {
+            let end = out.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+            String::from_utf8_lossy(&out[..end]).into_owned()
+        }
+        Err(_) => return None,
+    };
+    let text = text.trim_matches(char::from(0)).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Один повтор: первый вызов на холодном старте часто упирается в таймаут ноды
+async fn fetch_text(token: Address, data: Bytes) -> Option<String> {
+    for _ in 0..2 {
+        if let Ok(out) = execution::read_call(token, data.clone()).await {
+            return decode_text(&out);
+        }
+    }
+    None
+}
+
+/// (symbol, name) токена: память -> файл кэша -> RPC
+pub async fn token_info(token: Address) -> (String, String) {
+    if let Some(info) = METADATA.read().unwrap().get(&token) { return info.clone(); }
+    if let Some(info) = token_cache::cached_info(token) {
+        METADATA.write().unwrap().insert(token, info.clone());
+        return info;
+    }
+
+    let (symbol, name) = tokio::join!(
+        fetch_text(token, SymbolCall.encode().into()),
+        fetch_text(token, NameCall.encode().into())
+    );
+    let Some(symbol) = symbol else {
+        // Не кэшируем: при следующем обращении попробуем снова
+        return (String::new(), name.unwrap_or_default());
+    };
+    let name = name.unwrap_or_else(|| symbol.clone());
+    token_cache::store_info(token, &symbol, &name);
+    METADATA.write().unwrap().insert(token, (symbol.clone(), name.clone()));
+    (symbol, name)
+}
+
+/// Полное описание токена для GetTokenInfo. totalSupply не кэшируется - он меняется
+pub async fn describe(token: Address) -> EngineEvent {
+    let ((symbol, name), decimals, supply) = tokio::join!(
+        token_info(token),
+        monitor::get_decimals_cached(token),
+        execution::read_call(token, TotalSupplyCall.encode().into())
+    );
+    let total_supply = supply.ok()
+        .and_then(|out| TotalSupplyReturn::decode(&out).ok())
+        .map_or(U256::zero(), |r| r.0);
+    EngineEvent::TokenInfo {
+        address: format!("{:?}", token),
+        symbol,
+        name,
+        decimals,
+        total_supply: total_supply.to_string(),
+    }
+}
+
+/// Сброс памяти (None - все токены): при смене сети и по ClearTokenCache
+pub fn forget(token: Option<Address>) {
+    let mut m = METADATA.write().unwrap();
+    match token {
+        Some(t) => { m.remove(&t); }
+        None => m.clear(),
+    }
+}
@@ -0,0 +1,199 @@
+use ethers::prelude::*;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use url::Url;
+
+use crate::state::{CORE_STATE, RPC_POOL, GLOBAL_HTTP_CLIENT, V3PoolState, V3TickWindow};
+use crate::bridge::emit_log;
+use crate::execution;
+use crate::math;
+
+abigen!(
+    V3TickLens,
+    r#"[
+        function tickSpacing() external view returns (int24)
+        function tickBitmap(int16) external view returns (uint256)
+        function ticks(int24) external view returns (uint128 liquidityGross, int128 liquidityNet, uint256 feeGrowthOutside0X128, uint256 feeGrowthOutside1X128, int56 tickCumulativeOutside, uint160 secondsPerLiquidityOutsideX128, uint32 secondsOutside, bool initialized)
+    ]"#
+);
+
+/// Сколько слов tickBitmap (по 256 сжатых тиков) грузим по каждую сторону от текущего
+const WORD_RADIUS: i32 = 2;
+/// Ограничение на число ticks() вызовов за одну загрузку окна
+const MAX_TICKS: usize = 200;
+
+/// Локальная симуляция нужна, когда сделка больше этой доли ликвидности в текущем диапазоне
+pub const LOCAL_IMPACT_MIN_SHARE: f64 = 0.01;
+
+fn sqrt_price_at_tick(tick: i32) -> f64 {
+    1.0001f64.powf(tick as f64 / 2.0)
+}
+
+/// Загружает инициализированные тики (tick -> liquidityNet) в окне вокруг текущего тика
+async fn fetch_tick_window(pool: H160, current_tick: i32) -> Option<V3TickWindow> {
+    let url = { RPC_POOL.read().unwrap().get_fastest_node() }?;
+    let provider = Arc::new(Provider::new(Http::new_with_client(Url::parse(&url).ok()?, GLOBAL_HTTP_CLIENT.clone())));
+    let contract = V3TickLens::new(pool, provider);
+
+    let spacing = contract.tick_spacing().call().await.ok()?;
+    if spacing <= 0 { return None; }
+
+    let word = current_tick.div_euclid(spacing) >> 8;
+    let words: Vec<i32> = (word - WORD_RADIUS..=word + WORD_RADIUS).collect();
+    let bitmaps = futures::future::join_all(
+        words.iter().map(|&w| {
+            let c = contract.clone();
+            async move { c.tick_bitmap(w as i16).call().await.ok() }
+        })
+    ).await;
+
+    let mut initialized = Vec::new();
+    for (&w, bitmap) in words.iter().zip(bitmaps) {
+        let bitmap = bitmap?;
+        if bitmap.is_zero() { continue; }
+        for bit in 0..256 {
+            if bitmap.bit(bit) {
+                initialized.push(((w << 8) + bit as i32) * spacing);
+            }
+        }
+    }
+
+    let mut min_tick = (word - WORD_RADIUS) * 256 * spacing;
+    let mut max_tick = (word + WORD_RADIUS + 1) * 256 * spacing - 1;
+
+    // Слишком много тиков - берём ближайшие к цене и сужаем окно до них
+    if initialized.len() > MAX_TICKS {
+        initialized.sort_by_key(|t| (t - current_tick).abs());
+        initialized.truncate(MAX_TICKS);
+        min_tick = *initialized.iter().min()?;
+        max_tick = *initialized.iter().max()?;
+    }
+
+    let nets = futures::future::join_all(
+        initialized.iter().map(|&t| {
+            let c = contract.clone();
+            async move { c.ticks(t).call().await.ok().map(|info| (t, info.1)) }
+        })
+    ).await;
+
+    let mut ticks = BTreeMap::new();
+    for entry in nets {
+        let (t, net) = entry?;
+        ticks.insert(t, net);
+    }
+
+    Some(V3TickWindow { tick_spacing: spacing, ticks, min_tick, max_tick, refreshing: false })
+}
+
+/// Перезагружает окно тиков пула (параллельные перезагрузки одного пула схлопываются)
+pub async fn refresh_tick_window(pool: H160) {
+    let tick = {
+        let mut s = CORE_STATE.write().unwrap();
+        let Some(tick) = s.v3_states.get(&pool).map(|p| p.tick) else { return };
+        let window = s.v3_tick_windows.entry(pool).or_default();
+        if window.refreshing { return; }
+        window.refreshing = true;
+        tick
+    };
+
+    let fetched = fetch_tick_window(pool, tick).await;
+
+    let mut s = CORE_STATE.write().unwrap();
+    match fetched {
+        Some(window) => {
+            emit_log("DEBUG", format!("🧮 Тики {:?}: {} инициализированных в [{}, {}]", pool, window.ticks.len(), window.min_tick, window.max_tick));
+            s.v3_tick_windows.insert(pool, window);
+        }
+        None => {
+            emit_log("DEBUG", format!("🧮 Не удалось загрузить тики {:?}, impact считаем через quoter", pool));
+            s.v3_tick_windows.remove(&pool);
+        }
+    }
+}
+
+/// Swap пересёк инициализированный тик или ушёл за окно - окно надо пересобрать вокруг новой цены
+pub fn needs_refresh(window: &V3TickWindow, old_tick: i32, new_tick: i32) -> bool {
+    if window.tick_spacing <= 0 || window.refreshing { return false; }
+    if new_tick < window.min_tick || new_tick > window.max_tick { return true; }
+    let (lo, hi) = if old_tick < new_tick { (old_tick, new_tick) } else { (new_tick, old_tick) };
+    window.ticks.range(lo + 1..=hi).next().is_some()
+}
+
+/// Точная симуляция exact-input свапа с переходами через границы тиков (в сырых единицах токенов).
+/// None - свап выходит за загруженное окно, ответ должен дать quoter.
+pub fn simulate_exact_input(pool: &V3PoolState, window: &V3TickWindow, amount_in: f64, zero_for_one: bool) -> Option<f64> {
+    if window.tick_spacing <= 0 || pool.sqrt_price_x96.is_zero() || amount_in <= 0.0 { return None; }
+
+    let mut sqrt_p = execution::u256_to_f64_safe(pool.sqrt_price_x96, 0) / 2f64.powi(96);
+    let mut liquidity = execution::u256_to_f64_safe(pool.liquidity, 0);
+    let mut tick = pool.tick;
+    // Комиссия пропорциональна входу каждого шага, поэтому можно снять её со всей суммы сразу
+    let mut remaining = amount_in * (1.0 - pool.pool_fee as f64 / 1_000_000.0);
+    let mut out = 0.0;
+
+    while remaining > 0.0 {
+        let next = if zero_for_one {
+            window.ticks.range(..=tick).next_back()
+        } else {
+            window.ticks.range(tick + 1..).next()
+        };
+        let (&next_tick, &net) = next?;
+        let sqrt_t = sqrt_price_at_tick(next_tick);
+
+        if zero_for_one {
+            let needed = liquidity * (1.0 / sqrt_t - 1.0 / sqrt_p);
+            if liquidity > 0.0 && remaining < needed {
+                let sqrt_new = 1.0 / (1.0 / sqrt_p + remaining / liquidity);
+                out += liquidity * (sqrt_p - sqrt_new);
+                break;
+            }
+            out += liquidity * (sqrt_p - sqrt_t);
+            remaining -= needed;
+            liquidity -= net as f64;
+            tick = next_tick - 1;
+        } else {
+            let needed = liquidity * (sqrt_t - sqrt_p);
+            if liquidity > 0.0 && remaining < needed {
+                let sqrt_new = sqrt_p + remaining / liquidity;
+                out += liquidity * (1.0 / sqrt_p - 1.0 / sqrt_new);
+                break;
+            }
+            out += liquidity * (1.0 / sqrt_p - 1.0 / sqrt_t);
+            remaining -= needed;
+            liquidity += net as f64;
+            tick = next_tick;
+        }
+        sqrt_p = sqrt_t;
+        liquidity = liquidity.max(0.0);
+    }
+    Some(out)
+}
+
+/// Price impact (%) относительно спот-цены пула, включая комиссию
+pub fn impact_pct(pool: &V3PoolState, amount_in: f64, amount_out: f64, zero_for_one: bool) -> f64 {
+    let sqrt_p = execution::u256_to_f64_safe(pool.sqrt_price_x96, 0) / 2f64.powi(96);
+    let spot = sqrt_p * sqrt_p;
+    let ideal = if zero_for_one { amount_in * spot } else { amount_in / spot };
+    if ideal <= 0.0 { return 0.0; }
+    ((ideal - amount_out) / ideal * 100.0).clamp(0.0, 100.0)
+}
+
+/// Ожидаемый выход по закэшированному окну тиков, без RPC
+pub fn local_expected_out(pool: H160, t_in: Address, t_out: Address, amount_in: U256) -> Option<U256> {
+    let s = CORE_STATE.read().unwrap();
+    let state = s.v3_states.get(&pool)?;
+    let window = s.v3_tick_windows.get(&pool)?;
+    let out = simulate_exact_input(state, window, execution::u256_to_f64_safe(amount_in, 0), t_in < t_out)?;
+    U256::from_dec_str(&format!("{:.0}", out.floor())).ok()
+}
+
+/// Оценка по одному диапазону тиков (sqrtPrice, liquidity, tick из v3_states), когда окна тиков нет.
+/// (выход, пересекает ли сделка границу диапазона) - при пересечении выход приблизителен
+pub fn single_range_expected_out(pool: H160, t_in: Address, t_out: Address, amount_in: U256) -> Option<(U256, bool)> {
+    let s = CORE_STATE.read().unwrap();
+    let state = s.v3_states.get(&pool)?;
+    let spacing = s.v3_tick_windows.get(&pool).map(|w| w.tick_spacing).filter(|sp| *sp > 0)
+        .or_else(|| math::v3_tick_spacing(state.pool_fee))?;
+    if state.liquidity.bits() > 128 { return None; }
+    math::v3_single_range_out(state.sqrt_price_x96, state.liquidity.as_u128(), state.tick, spacing, state.pool_fee, amount_in, t_in < t_out)
+}
@@ -0,0 +1,269 @@
+use pyo3::prelude::*;
+use ethers::signers::{LocalWallet, MnemonicBuilder, Signer};
+use ethers::signers::coins_bip39::{English, Mnemonic};
+use ethers::types::Address;
+use aes_gcm::{Aes256Gcm, Nonce};
+use aes_gcm::aead::{Aead, KeyInit, AeadCore};
+use rand::RngCore;
+use rand::rngs::OsRng;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+use crate::crypto::{derive_key_from_password, SALT_SIZE, NONCE_SIZE};
+use crate::state::{EngineId, EngineLocal, WalletKey, parse_wallet_key, with_engine};
+use crate::engine;
+
+/// Зашифрованное хранилище ключей кошельков: salt | nonce | AES-GCM(JSON StoreData).
+/// В памяти держим только шифр, выведенный из мастер-пароля, сам пароль не сохраняется
+struct WalletStore {
+    path: PathBuf,
+    salt: [u8; SALT_SIZE],
+    cipher: Aes256Gcm,
+    wallets: HashMap<Address, WalletKey>,
+    mnemonic: Option<StoredMnemonic>,
+}
+
+/// BIP-39 фраза и опциональный passphrase ("25-е слово")
+struct StoredMnemonic {
+    phrase: Zeroizing<String>,
+    passphrase: Option<Zeroizing<String>>,
+}
+
+/// Расшифрованное содержимое файла. Первые версии хранили просто массив ключей
+#[derive(Serialize, Deserialize)]
+struct StoreData {
+    keys: Vec<String>,
+    #[serde(default)]
+    mnemonic: Option<String>,
+    #[serde(default)]
+    passphrase: Option<String>,
+}
+
+impl Drop for StoreData {
+    fn drop(&mut self) {
+        self.keys.iter_mut().for_each(|k| k.zeroize());
+        self.mnemonic.zeroize();
+        self.passphrase.zeroize();
+    }
+}
+
+// У каждого движка своё открытое хранилище: его кошельки регистрируются в этом же движке
+static WALLET_STORE: EngineLocal<Mutex<Option<WalletStore>>> = EngineLocal::new(|| Mutex::new(None));
+
+fn value_err(msg: impl Into<String>) -> PyErr {
+    pyo3::exceptions::PyValueError::new_err(msg.into())
+}
+
+fn locked_err() -> PyErr {
+    pyo3::exceptions::PyRuntimeError::new_err("Wallet store is locked. Call open_wallet_store first")
+}
+
+fn key_hex(key: &LocalWallet) -> Zeroizing<String> {
+    Zeroizing::new(hex::encode(key.signer().to_bytes()))
+}
+
+/// Расшифровывает содержимое файла хранилища. Err - неверный пароль или повреждённый файл
+fn decrypt_store(content: &[u8], master_password: &str) -> PyResult<(Aes256Gcm, Vec<WalletKey>, Option<StoredMnemonic>)> {
+    if content.len() < SALT_SIZE + NONCE_SIZE {
+        return Err(value_err("Invalid wallet store format"));
+    }
+    let (salt, rest) = content.split_at(SALT_SIZE);
+    let (nonce_bytes, encrypted) = rest.split_at(NONCE_SIZE);
+    let cipher = Aes256Gcm::new(&derive_key_from_password(master_password, salt));
+    let plaintext = Zeroizing::new(cipher.decrypt(Nonce::from_slice(nonce_bytes), encrypted)
+        .map_err(|_| value_err("Decryption failed. Wrong master password?"))?);
+    let mut data: StoreData = serde_json::from_slice(&plaintext)
+        .or_else(|_| serde_json::from_slice(&plaintext).map(|keys| StoreData { keys, mnemonic: None, passphrase: None }))
+        .map_err(|_| value_err("Invalid wallet store data after decryption"))?;
+    let keys = std::mem::take(&mut data.keys).into_iter().filter_map(parse_wallet_key).collect();
+    let mnemonic = data.mnemonic.take().map(|phrase| StoredMnemonic {
+        phrase: Zeroizing::new(phrase),
+        passphrase: data.passphrase.take().map(Zeroizing::new),
+    });
+    Ok((cipher, keys, mnemonic))
+}
+
+impl WalletStore {
+    /// Перезаписывает файл целиком с новым nonce (tmp + rename, чтобы сбой не оставил половину файла)
+    fn persist(&self) -> PyResult<()> {
+        let data = StoreData {
+            keys: self.wallets.values().map(|k| key_hex(k).to_string()).collect(),
+            mnemonic: self.mnemonic.as_ref().map(|m| m.phrase.to_string()),
+            passphrase: self.mnemonic.as_ref().and_then(|m| m.passphrase.as_ref()).map(|p| p.to_string()),
+        };
+        let plaintext = Zeroizing::new(serde_json::to_vec(&data).map_err(|e| value_err(e.to_string()))?);
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let encrypted = self.cipher.encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| pyo3::exceptions::PyRuntimeError::new_err("Encryption failed"))?;
+
+        let mut content = Vec::with_capacity(SALT_SIZE + NONCE_SIZE + encrypted.len());
+        content.extend_from_slice(&self.salt);
+        content.extend_from_slice(&nonce);
+        content.extend_from_slice(&encrypted);
+
+        if let Some(parent) = self.path.parent() { std::fs::create_dir_all(parent)?; }
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Сохраняет ключ и регистрирует кошелёк в ядре тем же путём, что и AddWallet
+    fn add(&mut self, key: WalletKey) -> PyResult<String> {
+        let addr = key.address();
+        self.wallets.insert(addr, key.clone());
+        self.persist()?;
+        engine::register_wallet(addr, key);
+        Ok(format!("{:?}", addr))
+    }
+}
+
+/// Вызов pyfunction от имени движка (engine_id=None - движок по умолчанию)
+fn in_engine<T>(engine_id: Option<EngineId>, f: impl FnOnce() -> PyResult<T>) -> PyResult<T> {
+    let id = crate::bridge::resolve_engine(engine_id)?;
+    with_engine(id, f)
+}
+
+fn with_store<T>(f: impl FnOnce(&mut WalletStore) -> PyResult<T>) -> PyResult<T> {
+    let mut guard = WALLET_STORE.lock().unwrap();
+    f(guard.as_mut().ok_or_else(locked_err)?)
+}
+
+fn stored_key(address: &str) -> PyResult<WalletKey> {
+    let addr = Address::from_str(address).map_err(|_| value_err(format!("Invalid address: {}", address)))?;
+    with_store(|s| s.wallets.get(&addr).cloned().ok_or_else(|| value_err(format!("Wallet {:?} is not in the store", addr))))
+}
+
+/// Открывает (или создаёт) хранилище и регистрирует все его кошельки. Возвращает их адреса
+#[pyfunction]
+#[pyo3(signature = (path, master_password, engine_id=None))]
+pub fn open_wallet_store(path: String, master_password: &str, engine_id: Option<EngineId>) -> PyResult<Vec<String>> {
+    in_engine(engine_id, || open_store(path, master_password))
+}
+
+fn open_store(path: String, master_password: &str) -> PyResult<Vec<String>> {
+    let path = PathBuf::from(path);
+    let store = if path.exists() {
+        let content = std::fs::read(&path)?;
+        let (cipher, keys, mnemonic) = decrypt_store(&content, master_password)?;
+        let mut salt = [0u8; SALT_SIZE];
+        salt.copy_from_slice(&content[..SALT_SIZE]);
+        WalletStore { path, salt, cipher, wallets: keys.into_iter().map(|k| (k.address(), k)).collect(), mnemonic }
+    } else {
+        let mut salt = [0u8; SALT_SIZE];
+        OsRng.fill_bytes(&mut salt);
+        let cipher = Aes256Gcm::new(&derive_key_from_password(master_password, &salt));
+        let store = WalletStore { path, salt, cipher, wallets: HashMap::new(), mnemonic: None };
+        store.persist()?;
+        store
+    };
+
+    let addresses = store.wallets.iter().map(|(addr, key)| {
+        engine::register_wallet(*addr, key.clone());
+        format!("{:?}", addr)
+    }).collect();
+    *WALLET_STORE.lock().unwrap() = Some(store);
+    Ok(addresses)
+}
+
+/// Генерирует count новых кошельков (OsRng). Ключи попадают только в хранилище
+#[pyfunction]
+#[pyo3(signature = (count, engine_id=None))]
+pub fn create_wallet(count: usize, engine_id: Option<EngineId>) -> PyResult<Vec<String>> {
+    in_engine(engine_id, || with_store(|s| (0..count).map(|_| s.add(Arc::new(LocalWallet::new(&mut OsRng)))).collect()))
+}
+
+#[pyfunction]
+#[pyo3(signature = (private_key_hex, engine_id=None))]
+pub fn import_wallet(private_key_hex: String, engine_id: Option<EngineId>) -> PyResult<String> {
+    let key = parse_wallet_key(private_key_hex).ok_or_else(|| value_err("Invalid private key"))?;
+    in_engine(engine_id, || with_store(|s| s.add(key)))
+}
+
+/// Web3 Secret Storage (UTC/JSON) для кошелька из хранилища. Возвращает путь к файлу
+#[pyfunction]
+#[pyo3(signature = (address, password, path, engine_id=None))]
+pub fn export_keystore(address: String, password: &str, path: String, engine_id: Option<EngineId>) -> PyResult<String> {
+    let key = in_engine(engine_id, || stored_key(&address))?;
+    let target = Path::new(&path);
+    let (dir, name) = if target.is_dir() {
+        let stamp = chrono::Utc::now().format("%Y-%m-%dT%H-%M-%S%.fZ");
+        (target.to_path_buf(), format!("UTC--{}--{}", stamp, hex::encode(key.address())))
+    } else {
+        let dir = target.parent().map(Path::to_path_buf).unwrap_or_default();
+        let name = target.file_name().and_then(|n| n.to_str())
+            .ok_or_else(|| value_err(format!("Invalid keystore path: {}", path)))?;
+        (dir, name.to_string())
+    };
+    let secret = Zeroizing::new(key.signer().to_bytes().to_vec());
+    LocalWallet::encrypt_keystore(&dir, &mut OsRng, secret.as_slice(), password, Some(&name))
+        .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(format!("Keystore export failed: {}", e)))?;
+    Ok(dir.join(name).to_string_lossy().into_owned())
+}
+
+#[pyfunction]
+#[pyo3(signature = (path, password, engine_id=None))]
+pub fn import_keystore(path: String, password: &str, engine_id: Option<EngineId>) -> PyResult<String> {
+    let wallet = LocalWallet::decrypt_keystore(&path, password)
+        .map_err(|e| value_err(format!("Keystore decryption failed: {}", e)))?;
+    in_engine(engine_id, || with_store(|s| s.add(Arc::new(wallet))))
+}
+
+/// Единственный способ получить приватный ключ в Python: только с мастер-паролем хранилища
+#[pyfunction]
+#[pyo3(signature = (address, master_password, engine_id=None))]
+pub fn export_private_key(address: String, master_password: &str, engine_id: Option<EngineId>) -> PyResult<String> {
+    let (key, store_path) = in_engine(engine_id, || Ok((stored_key(&address)?, with_store(|s| Ok(s.path.clone()))?)))?;
+    decrypt_store(&std::fs::read(store_path)?, master_password)?;
+    Ok(format!("0x{}", key_hex(&key).as_str()))
+}
+
+/// Новая BIP-39 фраза (12/15/18/21/24 слова), сохраняется в хранилище зашифрованной.
+/// Уже сохранённую фразу перезаписывает только overwrite=True - иначе кошельки старой фразы потеряются
+#[pyfunction]
+#[pyo3(signature = (words=12, passphrase=None, overwrite=false, engine_id=None))]
+pub fn generate_mnemonic(words: usize, passphrase: Option<String>, overwrite: bool, engine_id: Option<EngineId>) -> PyResult<String> {
+    let mnemonic = Mnemonic::<English>::new_with_count(&mut OsRng, words)
+        .map_err(|e| value_err(format!("Invalid word count: {}", e)))?;
+    let phrase = Zeroizing::new(mnemonic.to_phrase());
+    in_engine(engine_id, || with_store(|s| {
+        if s.mnemonic.is_some() && !overwrite {
+            return Err(value_err("Mnemonic is already stored. Pass overwrite=True to replace it"));
+        }
+        s.mnemonic = Some(StoredMnemonic { phrase: phrase.clone(), passphrase: passphrase.map(Zeroizing::new) });
+        s.persist()
+    }))?;
+    Ok(phrase.to_string())
+}
+
+/// Кошельки по пути m/44'/60'/0'/0/i, i = start_index..start_index+count.
+/// mnemonic None - сохранённая фраза; явная фраза нигде не сохраняется, только её ключи.
+/// Одна и та же фраза и passphrase всегда дают те же адреса
+#[pyfunction]
+#[pyo3(signature = (mnemonic=None, start_index=0, count=1, passphrase=None, engine_id=None))]
+pub fn derive_wallets(mnemonic: Option<String>, start_index: u32, count: u32, passphrase: Option<String>, engine_id: Option<EngineId>) -> PyResult<Vec<String>> {
+    let mnemonic = mnemonic.map(Zeroizing::new);
+    let passphrase = passphrase.map(Zeroizing::new);
+    in_engine(engine_id, || with_store(|s| {
+        let (phrase, passphrase) = match (&mnemonic, &s.mnemonic) {
+            (Some(phrase), _) => (phrase.clone(), passphrase.clone()),
+            (None, Some(stored)) => (stored.phrase.clone(), passphrase.clone().or_else(|| stored.passphrase.clone())),
+            (None, None) => return Err(value_err("No stored mnemonic. Call generate_mnemonic or pass a phrase")),
+        };
+        let end = start_index.checked_add(count).ok_or_else(|| value_err("Derivation index overflow"))?;
+        let mut builder = MnemonicBuilder::<English>::default().phrase(phrase.trim());
+        if let Some(p) = passphrase.as_deref() { builder = builder.password(p); }
+
+        let mut derived = Vec::with_capacity(count as usize);
+        for index in start_index..end {
+            let wallet = builder.clone().index(index)
+                .and_then(|b| b.build())
+                .map_err(|e| value_err(format!("Derivation failed: {}", e)))?;
+            derived.push(Arc::new(wallet));
+        }
+        derived.into_iter().map(|key| s.add(key)).collect()
+    }))
+}
@@ -0,0 +1,83 @@
+use ethers::types::{Address, H160, H256, U256};
+use std::collections::HashMap;
+use crate::bridge::{emit_event, emit_log, EngineEvent, EngineCommand, EngineRequest};
+use crate::engine::COMMAND_TX;
+use crate::execution;
+use crate::state::{CORE_STATE, TokenContext, WatchedAddress};
+
+/// Снимок наблюдаемых адресов и пулов подписок - живёт до следующей переподписки монитора
+pub struct WatchSnapshot {
+    watched: HashMap<Address, WatchedAddress>,
+    token_pools: HashMap<Address, Vec<H160>>,
+}
+
+impl WatchSnapshot {
+    pub fn new(subscriptions: &[(Address, TokenContext)]) -> Self {
+        let watched = CORE_STATE.read().unwrap().watched_addresses.clone();
+        let token_pools = subscriptions.iter()
+            .map(|(t, c)| (*t, c.pool_targets.iter().chain(c.route_pools.iter()).copied().collect()))
+            .collect();
+        Self { watched, token_pools }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watched.is_empty()
+    }
+
+    /// Transfer подписанного токена с участием наблюдаемого адреса.
+    /// Контрагент - пул токена: buy/sell, иначе просто in/out
+    pub fn on_transfer(&self, token: Address, from: Address, to: Address, value: U256, tx_hash: Option<H256>) {
+        // Переводы quote токенов не интересуют
+        let Some(pools) = self.token_pools.get(&token) else { return; };
+        for (addr, counterparty, incoming) in [(to, from, true), (from, to, false)] {
+            let Some(watch) = self.watched.get(&addr) else { continue; };
+            let direction = match (incoming, pools.contains(&counterparty)) {
+                (true, true) => "buy",
+                (false, true) => "sell",
+                (true, false) => "in",
+                (false, false) => "out",
+            };
+            let decimals = *CORE_STATE.read().unwrap().decimals_cache.get(&token).unwrap_or(&18);
+            let amount = execution::u256_to_f64_safe(value, decimals as u32);
+            emit_event(EngineEvent::WatchedAddressActivity {
+                label: watch.label.clone(),
+                address: format!("{:?}", addr),
+                direction: direction.into(),
+                token: format!("{:?}", token),
+                amount,
+                tx_hash: tx_hash.map(|h| format!("{:?}", h)).unwrap_or_default(),
+                name: crate::resolver::cached_name(addr),
+            });
+            if direction == "buy" {
+                copy_buy(watch, token);
+            }
+        }
+    }
+}
+
+/// Копирует покупку наблюдаемого адреса фиксированной суммой с наших кошельков
+fn copy_buy(watch: &WatchedAddress, token: Address) {
+    let Some(copy) = &watch.copy_trade else { return; };
+    let (quote, slippage) = {
+        let s = CORE_STATE.read().unwrap();
+        let Some(ctx) = s.tokens.get(&token) else { return; };
+        (ctx.quote, copy.slippage.unwrap_or(s.slippage))
+    };
+    emit_log("WARNING", format!("🐋 {} купил {:?} - копируем на {} {:?}", watch.label, token, copy.amount, quote));
+    let _ = COMMAND_TX.send(EngineRequest::from(EngineCommand::ExecuteTrade {
+        action: "buy".into(),
+        token: format!("{:?}", token),
+        quote_token: format!("{:?}", quote),
+        amount: copy.amount,
+        wallets: copy.wallets.clone(),
+        gas_gwei: 0.0,
+        slippage,
+        amounts_wei: None,
+        twap: None,
+        routing: None,
+        gas_strategy: None,
+        percent: None,
+        deadline_secs: None,
+        slippage_mode: None,
+    }));
+}
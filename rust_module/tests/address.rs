@@ -0,0 +1,50 @@
+// Разбор и checksum адресов (dexbot_core::address): любой регистр на входе, EIP-55 на выходе
+use dexbot_core::address::*;
+use serde_json::json;
+
+const USDT_LOWER: &str = "0xdac17f958d2ee523a2206206994597c13d831ec7";
+const USDT: &str = "0xdAC17F958D2ee523a2206206994597C13D831ec7";
+
+#[test]
+fn parse_accepts_any_case_and_rejects_non_addresses() {
+    let a = parse(USDT_LOWER).unwrap();
+    assert_eq!(parse(&USDT_LOWER.to_uppercase().replacen("0X", "0x", 1)), Some(a));
+    assert_eq!(checksum(&a), USDT);
+    for bad in ["", "0x", "dac17f958d2ee523a2206206994597c13d831ec7", "0xdac17f958d2ee523a2206206994597c13d831ec", "0xnot-an-address-at-all-not-an-address-00"] {
+        assert!(parse(bad).is_none(), "{:?}", bad);
+    }
+}
+
+#[test]
+fn parse_checked_catches_checksum_typos() {
+    assert!(parse_checked(USDT).is_ok());
+    assert!(parse_checked(USDT_LOWER).is_ok());
+    // Смешанный регистр с одной буквой не в том регистре - опечатка
+    let err = parse_checked("0xDAC17F958D2ee523a2206206994597C13D831ec7").unwrap_err();
+    assert!(err.contains(USDT), "{}", err);
+}
+
+#[test]
+fn normalize_event_checksums_values_and_keys_only() {
+    let hash = format!("0x{}", "ab".repeat(32));
+    let mut event = json!({
+        "type": "PoolDetected",
+        "data": {
+            "token": USDT_LOWER,
+            "pools": [USDT_LOWER],
+            "balances": {USDT_LOWER: "1"},
+            "tx_hash": hash,
+            "amount": "0xde0b6b3a7640000",
+            "message": format!("pool {}", USDT_LOWER)
+        }
+    });
+    normalize_event(&mut event);
+    let data = &event["data"];
+    assert_eq!(data["token"], USDT);
+    assert_eq!(data["pools"][0], USDT);
+    assert_eq!(data["balances"][USDT], "1");
+    assert_eq!(data["tx_hash"], hash);
+    assert_eq!(data["amount"], "0xde0b6b3a7640000");
+    // Свободный текст не трогаем
+    assert_eq!(data["message"], format!("pool {}", USDT_LOWER));
+}
@@ -0,0 +1,157 @@
+// E2E пути исполнения на форке Anvil: buy, sell с approve, отказ по slippage,
+// fee-on-transfer, порядок nonce двух быстрых сделок и nonce бандла, переживший синхронизацию воркера.
+// cargo test --no-default-features --features anvil-tests --test anvil_execution
+#![cfg(feature = "anvil-tests")]
+
+mod support;
+
+use dexbot_core::testing;
+use ethers::types::U256;
+use ethers::utils::parse_ether;
+use serde_json::json;
+use std::time::Duration;
+use support::{fork, send, serial, wait_for, Outcome};
+
+fn ether(amount: &str) -> U256 {
+    parse_ether(amount).expect("amount")
+}
+
+#[tokio::test]
+async fn buy_matches_v2_formula() {
+    let _guard = serial().await;
+    let f = fork();
+    let token = f.deploy_token("PLAIN", 0).await;
+    f.seed_v2_pair(token, ether("1000000"), ether("10")).await;
+    f.subscribe(token).await;
+    f.fund_quote(1, ether("1")).await;
+
+    let amount_in = ether("0.1");
+    let expected = testing::expected_out_v2(f.net.wrapped_native, token, amount_in);
+    assert!(!expected.is_zero(), "ядро не видит резервы пары");
+
+    let before = f.balance_of(token, f.address(1)).await;
+    f.trade("buy", token, 1, 0.1, None, 5.0);
+    let outcome = f.trade_outcome(1, token).await;
+    assert!(matches!(outcome, Outcome::Confirmed(_)), "buy: {:?}", outcome);
+
+    let received = f.balance_of(token, f.address(1)).await - before;
+    // Формула ядра и фактический выход пула с комиссией роутера расходятся не больше чем на 0.5%
+    let diff = if received > expected { received - expected } else { expected - received };
+    assert!(diff <= expected / 200, "получено {} против ожидаемых {}", received, expected);
+}
+
+#[tokio::test]
+async fn sell_approves_router_inline() {
+    let _guard = serial().await;
+    let f = fork();
+    let token = f.deploy_token("SELL", 0).await;
+    f.seed_v2_pair(token, ether("1000000"), ether("10")).await;
+    f.subscribe(token).await;
+    f.fund_quote(2, ether("1")).await;
+
+    f.trade("buy", token, 2, 0.05, None, 5.0);
+    assert!(matches!(f.trade_outcome(2, token).await, Outcome::Confirmed(_)));
+    assert!(f.allowance(token, f.address(2)).await.is_zero(), "allowance до продажи должен быть пуст");
+
+    f.trade("sell", token, 2, 0.0, Some(100.0), 5.0);
+    let outcome = f.trade_outcome(2, token).await;
+    assert!(matches!(outcome, Outcome::Confirmed(_)), "sell: {:?}", outcome);
+    assert!(!f.allowance(token, f.address(2)).await.is_zero(), "продажа не выдала approve роутеру");
+    assert!(f.balance_of(token, f.address(2)).await.is_zero());
+}
+
+#[tokio::test]
+async fn fee_on_transfer_sell_needs_slippage_room() {
+    let _guard = serial().await;
+    let f = fork();
+    // 10% сжигается на каждом переводе не от владельца
+    let token = f.deploy_token("TAX", 1000).await;
+    f.seed_v2_pair(token, ether("1000000"), ether("10")).await;
+    f.subscribe(token).await;
+    f.fund_quote(3, ether("1")).await;
+
+    let amount_in = ether("0.1");
+    let expected = testing::expected_out_v2(f.net.wrapped_native, token, amount_in);
+    f.trade("buy", token, 3, 0.1, None, 20.0);
+    assert!(matches!(f.trade_outcome(3, token).await, Outcome::Confirmed(_)));
+    let received = f.balance_of(token, f.address(3)).await;
+    // Налог покупки виден как недополученные ~10%
+    assert!(received < expected * 95 / 100, "fee-on-transfer не учтён: {} из {}", received, expected);
+    assert!(received > expected * 85 / 100, "получено слишком мало: {} из {}", received, expected);
+
+    // Продажа теряет ещё 10% на переводе в пару - 1% slippage её отклоняет
+    f.trade("sell", token, 3, 0.0, Some(50.0), 1.0);
+    let outcome = f.trade_outcome(3, token).await;
+    assert!(!matches!(outcome, Outcome::Confirmed(_)), "sell с 1% slippage прошёл: {:?}", outcome);
+
+    f.trade("sell", token, 3, 0.0, Some(50.0), 25.0);
+    let outcome = f.trade_outcome(3, token).await;
+    assert!(matches!(outcome, Outcome::Confirmed(_)), "sell с запасом slippage: {:?}", outcome);
+}
+
+#[tokio::test]
+async fn rapid_trades_take_consecutive_nonces() {
+    let _guard = serial().await;
+    let f = fork();
+    let token = f.deploy_token("NONCE", 0).await;
+    f.seed_v2_pair(token, ether("1000000"), ether("10")).await;
+    f.subscribe(token).await;
+    f.fund_quote(4, ether("1")).await;
+
+    // Вторая сделка уходит до подтверждения первой
+    f.trade("buy", token, 4, 0.01, None, 5.0);
+    f.trade("buy", token, 4, 0.01, None, 5.0);
+    let first = f.trade_outcome(4, token).await;
+    let second = f.trade_outcome(4, token).await;
+    let (Outcome::Confirmed(a), Outcome::Confirmed(b)) = (&first, &second) else {
+        panic!("сделки: {:?} / {:?}", first, second);
+    };
+    let (mut na, mut nb) = (f.tx_nonce(a).await, f.tx_nonce(b).await);
+    if na > nb { std::mem::swap(&mut na, &mut nb); }
+    assert_eq!(nb, na + 1, "nonce {} и {} не подряд", na, nb);
+}
+
+#[tokio::test]
+async fn bundle_nonce_survives_worker_sync() {
+    let _guard = serial().await;
+    let f = fork();
+    let token = f.deploy_token("BUNDLE", 0).await;
+    f.seed_v2_pair(token, ether("1000000"), ether("10")).await;
+    f.subscribe(token).await;
+    f.fund_quote(4, ether("1")).await;
+
+    let wallet = format!("{:?}", f.address(4));
+    send(json!({"type": "PrepareBundle", "data": {
+        "id": "nonce-hold",
+        "action": "buy",
+        "token": format!("{:?}", token),
+        "quote": format!("{:?}", f.net.wrapped_native),
+        "amount": 0.01,
+        "wallets": [wallet],
+        "gas_gwei": 3.0,
+        "slippage": 5.0
+    }}));
+    let prepared = wait_for(|e| e["type"] == "BundlePrepared" && e["data"]["id"] == "nonce-hold", Duration::from_secs(30))
+        .await
+        .expect("BundlePrepared");
+    let bundle_nonce = prepared["data"]["nonces"][&wallet].as_u64().expect("nonce бандла");
+
+    // Сеть не знает о неразосланном бандле - тик воркера не должен отдать его nonce обычной сделке
+    testing::sync_nonces().await;
+    f.trade("buy", token, 4, 0.01, None, 5.0);
+    let token_label = format!("{:?}", token);
+    let status = wait_for(|e| e["type"] == "TradeStatus" && e["data"]["wallet"] == wallet.as_str() && e["data"]["token_address"] == token_label.as_str(), Duration::from_secs(60))
+        .await
+        .expect("TradeStatus");
+    let trade_hash = status["data"]["tx_hash"].as_str().unwrap_or_else(|| panic!("сделка не ушла: {}", status)).to_string();
+    assert_ne!(f.tx_nonce(&trade_hash).await, bundle_nonce, "сделка заняла nonce бандла");
+
+    // Сделка стоит за nonce бандла: после FireBundle подтверждаются обе
+    send(json!({"type": "FireBundle", "data": {"id": "nonce-hold"}}));
+    let fired = wait_for(|e| e["type"] == "BundleFired", Duration::from_secs(30)).await.expect("BundleFired");
+    assert_eq!(fired["data"]["sent"], 1, "бандл: {}", fired);
+    let confirmed = wait_for(|e| e["type"] == "TxConfirmed" && e["data"]["tx_hash"] == trade_hash.as_str(), Duration::from_secs(60))
+        .await
+        .expect("TxConfirmed");
+    assert_eq!(confirmed["data"]["status"], "success");
+}
@@ -0,0 +1,43 @@
+// ImportHistory на форке Anvil: покупка одним своим кошельком и перевод части токенов другому -
+// у получателя та же средняя цена, а не бесплатные токены.
+// cargo test --no-default-features --features anvil-tests --test anvil_history
+#![cfg(feature = "anvil-tests")]
+
+mod support;
+
+use ethers::prelude::*;
+use ethers::utils::parse_ether;
+use serde_json::json;
+use std::time::Duration;
+use support::{fork, send, serial, wait_for, IFixtureErc20, Outcome};
+
+#[tokio::test]
+async fn own_wallet_transfer_keeps_cost_basis() {
+    let _guard = serial().await;
+    let f = fork();
+    let token = f.deploy_token("HIST", 0).await;
+    f.seed_v2_pair(token, parse_ether("1000000").unwrap(), parse_ether("10").unwrap()).await;
+    f.subscribe(token).await;
+    f.fund_quote(3, parse_ether("1").unwrap()).await;
+    let from_block = f.client(0).get_block_number().await.expect("block").as_u64();
+
+    f.trade("buy", token, 3, 0.1, None, 5.0);
+    assert!(matches!(f.trade_outcome(3, token).await, Outcome::Confirmed(_)));
+    let bought = f.balance_of(token, f.address(3)).await;
+    IFixtureErc20::new(token, f.client(3))
+        .transfer(f.address(4), bought / 2).send().await.expect("transfer").await.expect("transfer receipt");
+
+    let (wallet, token_str) = (format!("{:?}", f.address(4)), format!("{:?}", token));
+    send(json!({"type": "ImportHistory", "data": {"wallet": wallet, "token": token_str, "from_block": from_block}}));
+    let update = wait_for(
+        |e| e["type"] == "PositionUpdate" && e["data"]["wallet"].as_str().is_some_and(|w| w.eq_ignore_ascii_case(&wallet)),
+        Duration::from_secs(60)
+    ).await.expect("PositionUpdate");
+
+    // Половина покупки за 0.1 quote - стоимость 0.05, средняя цена как у покупателя
+    let data = &update["data"];
+    let quantity = data["quantity"].as_f64().unwrap();
+    let cost = quantity * data["avg_cost"].as_f64().unwrap();
+    assert!((cost - 0.05).abs() < 1e-6, "стоимость перевода {} вместо 0.05: {}", cost, update);
+    assert_eq!(data["realized_pnl"].as_f64(), Some(0.0));
+}
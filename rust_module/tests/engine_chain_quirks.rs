@@ -0,0 +1,77 @@
+// chain_quirks из Init: неизвестные поля игнорируются, невалидные значения отклоняются, GetState отдаёт действующие.
+// Сеть не нужна: RPC указывают в закрытый порт, ядро работает с недоступными нодами.
+// cargo test --no-default-features --features engine-tests --test engine_chain_quirks
+#![cfg(feature = "engine-tests")]
+
+use dexbot_core::testing;
+use ethers::types::{Address, U256};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+const WRAPPED: &str = "0x0000000000000000000000000000000000000005";
+
+fn init(chain_quirks: Value) {
+    testing::push_command(json!({"type": "Init", "data": {
+        "rpc_url": "http://127.0.0.1:9",
+        "wss_url": "ws://127.0.0.1:9",
+        "chain_id": 5555,
+        "router": "0x0000000000000000000000000000000000000001",
+        "quoter": "0x0000000000000000000000000000000000000002",
+        "v2_factory": "0x0000000000000000000000000000000000000003",
+        "v3_factory": "0x0000000000000000000000000000000000000004",
+        "wrapped_native": WRAPPED,
+        "native_address": "0x0000000000000000000000000000000000000000",
+        "wallets": [],
+        "public_rpc_urls": [],
+        "fuel_settings": {},
+        "max_block_lag": 0,
+        "chain_quirks": chain_quirks
+    }})).expect("Init");
+}
+
+/// События до StateSnapshot включительно
+fn until_snapshot() -> Vec<Value> {
+    testing::push_command(json!({"type": "GetState"})).expect("GetState");
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut events = Vec::new();
+    while Instant::now() < deadline {
+        for event in testing::pop_events(256) {
+            let done = event["type"] == "StateSnapshot";
+            events.push(event);
+            if done { return events; }
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("нет StateSnapshot");
+}
+
+#[test]
+fn init_applies_chain_quirks() {
+    init(json!({
+        "force_legacy_tx": true,
+        "min_gas_price_gwei": 3.0,
+        "gas_multiplier": -1.0,
+        "supports_eip1559": false,
+        "block_time_secs": 0.75,
+        "added_in_a_later_version": {"x": 1}
+    }));
+    let events = until_snapshot();
+    let snapshot = events.last().unwrap();
+    let quirks = &snapshot["data"]["chain_quirks"];
+    assert_eq!(quirks["force_legacy_tx"], true, "{}", quirks);
+    assert_eq!(quirks["min_gas_price_gwei"], 3.0);
+    assert_eq!(quirks["supports_eip1559"], false);
+    assert_eq!(quirks["block_time_secs"], 0.75);
+    // Отрицательный множитель отклонён - действует 1.0
+    assert_eq!(quirks["gas_multiplier"], 1.0);
+    assert!(
+        events.iter().any(|e| e["type"] == "CommandError" && e["data"]["field"] == "chain_quirks.gas_multiplier"),
+        "нет CommandError по gas_multiplier"
+    );
+    // Время блока из конфига - без замера
+    assert_eq!(snapshot["data"]["monitor"]["block_time_ms"], 750);
+
+    // Ручной газ ядра (0.1 gwei) ниже минимума сети - tx идёт по минимуму
+    let tx = testing::fuel_withdraw_tx(WRAPPED.parse::<Address>().unwrap(), U256::one()).expect("цена газа");
+    assert_eq!(tx.gas_price(), Some(U256::from(3_000_000_000u64)));
+}
@@ -0,0 +1,98 @@
+// ReloadNetworkConfig: "${VAR}" из окружения, публичные RPC и комиссии применяются на лету,
+// смена роутера только отмечается как требующая Init. Сеть не нужна: RPC указывают в закрытый порт.
+// cargo test --no-default-features --features engine-tests --test engine_config_reload
+#![cfg(feature = "engine-tests")]
+
+use dexbot_core::testing;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+const NETWORK: &str = "engine_config_reload_test";
+
+fn write_network(router: &str, public_rpc: &str) {
+    std::fs::create_dir_all("networks").unwrap();
+    let config = json!({
+        "name": "Reload Test",
+        "db_path": "reload_test.db",
+        "chain_id": 7777,
+        "rpc_url": "http://127.0.0.1:9",
+        "native_currency_symbol": "ETH",
+        "native_currency_address": "0x0000000000000000000000000000000000000000",
+        "explorer_url": "",
+        "dex_router_address": router,
+        "v2_factory_address": "0x0000000000000000000000000000000000000003",
+        "v3_factory_address": "0x0000000000000000000000000000000000000004",
+        "v3_quoter_address": "0x0000000000000000000000000000000000000002",
+        "public_rpc_urls": [public_rpc],
+        "default_quote_currency": "WETH",
+        "quote_tokens": {"WETH": "0x0000000000000000000000000000000000000005"},
+        "v2_pool_fee_bps": 25
+    });
+    std::fs::write(format!("networks/{}.json", NETWORK), config.to_string()).unwrap();
+}
+
+fn wait_for(request_id: &str, kinds: &[&str]) -> Value {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while Instant::now() < deadline {
+        for event in testing::pop_events(256) {
+            if event["request_id"] == request_id && kinds.iter().any(|k| event["type"] == *k) {
+                return event;
+            }
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("{}: нет {:?}", request_id, kinds);
+}
+
+#[test]
+fn reload_applies_safe_changes_and_reports_reinit() {
+    // Ядро читает networks/ относительно рабочего каталога - работаем во временном
+    let dir = std::env::temp_dir().join(format!("engine_config_reload_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::env::set_current_dir(&dir).unwrap();
+
+    testing::push_command(json!({"type": "Init", "request_id": "init", "data": {
+        "rpc_url": "http://127.0.0.1:9",
+        "wss_url": "ws://127.0.0.1:9",
+        "chain_id": 7777,
+        "router": "0x0000000000000000000000000000000000000001",
+        "quoter": "0x0000000000000000000000000000000000000002",
+        "v2_factory": "0x0000000000000000000000000000000000000003",
+        "v3_factory": "0x0000000000000000000000000000000000000004",
+        "wrapped_native": "0x0000000000000000000000000000000000000005",
+        "native_address": "0x0000000000000000000000000000000000000000",
+        "wallets": [],
+        "public_rpc_urls": ["http://127.0.0.1:9/old"],
+        "fuel_settings": {},
+        "quote_tokens": {"WETH": "0x0000000000000000000000000000000000000005"},
+        "max_block_lag": 0,
+        "network_name": NETWORK
+    }})).expect("Init");
+    wait_for("init", &["EngineReady"]);
+
+    // Переменная не задана - ошибка называет её и поле, состояние не тронуто
+    write_network("0x0000000000000000000000000000000000000009", "${ENGINE_RELOAD_TEST_RPC}");
+    testing::push_command(json!({"type": "ReloadNetworkConfig", "request_id": "unset", "data": {}})).expect("reload");
+    let error = wait_for("unset", &["CommandError", "ConfigReloaded"]);
+    assert_eq!(error["type"], "CommandError", "{}", error);
+    let message = error["data"]["message"].as_str().unwrap();
+    assert!(message.contains("ENGINE_RELOAD_TEST_RPC") && message.contains("public_rpc_urls[0]"), "{}", message);
+
+    std::env::set_var("ENGINE_RELOAD_TEST_RPC", "http://127.0.0.1:9/new");
+    testing::push_command(json!({"type": "ReloadNetworkConfig", "request_id": "reload", "data": {}})).expect("reload");
+    let reloaded = wait_for("reload", &["ConfigReloaded", "CommandError"]);
+    std::fs::remove_dir_all(&dir).ok();
+    assert_eq!(reloaded["type"], "ConfigReloaded", "{}", reloaded);
+    assert_eq!(reloaded["data"]["network"], NETWORK);
+    assert_eq!(reloaded["data"]["changed"], json!(["v2_pool_fee_bps", "public_rpc_urls"]));
+    assert_eq!(reloaded["data"]["requires_reinit"], json!(["dex_router_address"]));
+
+    testing::push_command(json!({"type": "GetState", "request_id": "state"})).expect("GetState");
+    let snapshot = wait_for("state", &["StateSnapshot"]);
+    assert_eq!(snapshot["data"]["v2_pool_fee_bps"], 25);
+    // Роутер остался прежним до нового Init
+    assert_eq!(snapshot["data"]["router"], "0x0000000000000000000000000000000000000001");
+    let urls: Vec<&str> = snapshot["data"]["rpc_nodes"].as_array().unwrap().iter()
+        .filter_map(|n| n["url"].as_str()).collect();
+    assert!(urls.contains(&"http://127.0.0.1:9/new") && !urls.contains(&"http://127.0.0.1:9/old"), "{:?}", urls);
+}
@@ -0,0 +1,68 @@
+// validate_config в Init: опечатка в адресе отклоняет Init до старта сессии, недоступная нода - только предупреждение.
+// Сеть не нужна: RPC указывают в закрытый порт.
+// cargo test --no-default-features --features engine-tests --test engine_config_validation
+#![cfg(feature = "engine-tests")]
+
+use dexbot_core::testing;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+// USDT с испорченной контрольной суммой EIP-55 (первая буква в другом регистре)
+const TYPO_ADDRESS: &str = "0xDAC17F958D2ee523a2206206994597C13D831ec7";
+
+fn init(router: &str, request_id: &str) {
+    testing::push_command(json!({"type": "Init", "request_id": request_id, "data": {
+        "rpc_url": "http://127.0.0.1:9",
+        "wss_url": "ws://127.0.0.1:9",
+        "chain_id": 6666,
+        "router": router,
+        "quoter": "0x0000000000000000000000000000000000000002",
+        "v2_factory": "0x0000000000000000000000000000000000000003",
+        "v3_factory": "",
+        "wrapped_native": "0x0000000000000000000000000000000000000005",
+        "native_address": "0x0000000000000000000000000000000000000000",
+        "wallets": [],
+        "public_rpc_urls": [],
+        "fuel_settings": {},
+        "quote_tokens": {"WETH": "0x0000000000000000000000000000000000000005"},
+        "max_block_lag": 0,
+        "validate_config": true
+    }})).expect("Init");
+}
+
+/// События команды request_id до первого из kinds
+fn wait_for(request_id: &str, kinds: &[&str]) -> Vec<Value> {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let mut events = Vec::new();
+    while Instant::now() < deadline {
+        for event in testing::pop_events(256) {
+            if event["request_id"] != request_id { continue; }
+            let done = kinds.iter().any(|k| event["type"] == *k);
+            events.push(event);
+            if done { return events; }
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("{}: нет {:?}", request_id, kinds);
+}
+
+#[test]
+fn init_validation_rejects_typos_and_tolerates_offline_node() {
+    init(TYPO_ADDRESS, "typo");
+    let events = wait_for("typo", &["CommandError"]);
+    let error = events.last().unwrap();
+    assert_eq!(error["data"]["field"], "router", "{}", error);
+    assert!(error["data"]["message"].as_str().unwrap().contains("0xdAC17F958D2ee523a2206206994597C13D831ec7"), "{}", error);
+    // Отклонённый Init не стартует сессию
+    std::thread::sleep(Duration::from_millis(300));
+    assert!(testing::pop_events(256).iter().all(|e| e["type"] != "EngineReady"));
+
+    // Адреса в порядке, нода недоступна: живые проверки пропущены с предупреждением, Init проходит
+    init("0x0000000000000000000000000000000000000001", "offline");
+    let events = wait_for("offline", &["EngineReady", "CommandError"]);
+    assert_eq!(events.last().unwrap()["type"], "EngineReady", "{:?}", events);
+    assert!(
+        events.iter().any(|e| e["type"] == "Log" && e["data"]["message"].as_str().unwrap_or_default().contains("Init.rpc_url")),
+        "нет предупреждения о недоступной ноде: {:?}", events
+    );
+}
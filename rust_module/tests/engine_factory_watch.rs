@@ -0,0 +1,74 @@
+// Переподписка монитора на новый пул: только пул из PairCreated/PoolCreated фабрики и один раз,
+// когда в него пришла ликвидность. Sync чужого пула, пока пул не выбран, монитор не рвёт.
+// cargo test --no-default-features --features engine-tests --test engine_factory_watch
+#![cfg(feature = "engine-tests")]
+
+use dexbot_core::testing;
+use ethers::types::{Address, U256};
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+const FACTORY: &str = "0x0000000000000000000000000000000000000003";
+const TOKEN: &str = "0x0000000000000000000000000000000000000011";
+const USDC: &str = "0x0000000000000000000000000000000000000033";
+const POOL: &str = "0x0000000000000000000000000000000000000044";
+const OTHER_POOL: &str = "0x0000000000000000000000000000000000000055";
+
+fn wait_for(kind: &str) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !testing::pop_events(256).iter().any(|e| e["type"] == kind) {
+        assert!(Instant::now() < deadline, "нет {}", kind);
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn addr(a: &str) -> Address {
+    a.parse().unwrap()
+}
+
+/// Sync пула пары: TOKEN (18) - token0, USDC (6) - token1
+fn sync(pool: &str, usdc: u64) -> bool {
+    testing::pool_sync(addr(TOKEN), addr(USDC), addr(pool), U256::exp10(21), U256::from(usdc) * U256::exp10(6))
+}
+
+#[test]
+fn only_factory_pools_trigger_reselection_once() {
+    testing::push_command(json!({"type": "Init", "data": {
+        "rpc_url": "http://127.0.0.1:9/factory",
+        "wss_url": "ws://127.0.0.1:9/factory",
+        "chain_id": 3333,
+        "router": "0x0000000000000000000000000000000000000001",
+        "quoter": "0x0000000000000000000000000000000000000002",
+        "v2_factory": FACTORY,
+        "v3_factory": "0x0000000000000000000000000000000000000004",
+        "wrapped_native": "0x0000000000000000000000000000000000000005",
+        "native_address": "0x0000000000000000000000000000000000000000",
+        "wallets": [],
+        "public_rpc_urls": [],
+        "fuel_settings": {},
+        "quote_symbol": "WETH",
+        "quote_tokens": {"WETH": "0x0000000000000000000000000000000000000005", "USDC": USDC},
+        "max_block_lag": 0
+    }})).expect("Init");
+    wait_for("EngineReady");
+    for (address, decimals) in [(TOKEN, 18), (USDC, 6)] {
+        testing::push_command(json!({"type": "UpdateTokenDecimals", "data": {"address": address, "decimals": decimals}})).expect("UpdateTokenDecimals");
+    }
+    testing::push_command(json!({"type": "AddTokenSubscription", "data": {
+        "token_address": TOKEN, "quote_address": USDC, "quote_symbol": "USDC"
+    }})).expect("AddTokenSubscription");
+    testing::push_command(json!({"type": "GetState", "request_id": "subscribed"})).expect("GetState");
+    wait_for("StateSnapshot");
+
+    // Пул не выбран, но пул не из фабрики - ликвидность в нём не повод переподписываться
+    assert!(!sync(OTHER_POOL, 5000));
+
+    // PairCreated чужой пары не касается подписки
+    assert_eq!(testing::pair_created(addr(FACTORY), addr(OTHER_POOL), addr(USDC), addr(OTHER_POOL)), None);
+    assert_eq!(testing::pair_created(addr(FACTORY), addr(TOKEN), addr(USDC), addr(POOL)), Some((addr(TOKEN), addr(POOL))));
+
+    // Пыль в новом пуле - ещё ждём; первая настоящая ликвидность - выбор заново, и только один раз
+    assert!(!sync(POOL, 1));
+    assert!(sync(POOL, 5000));
+    assert!(!sync(POOL, 6000));
+}
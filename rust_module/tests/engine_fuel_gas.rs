@@ -0,0 +1,62 @@
+// Auto-fuel сразу после Init: газ блока ещё не пришёл, tx не должна уйти с нулевой ценой.
+// RPC в закрытом порту - eth_gasPrice недоступен, остаётся ручной газ.
+// cargo test --no-default-features --features engine-tests --test engine_fuel_gas
+#![cfg(feature = "engine-tests")]
+
+use dexbot_core::testing;
+use ethers::types::{Address, U256};
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+const WRAPPED_NATIVE: &str = "0x0000000000000000000000000000000000000005";
+
+fn init() {
+    testing::push_command(json!({"type": "Init", "data": {
+        "rpc_url": "http://127.0.0.1:9/fuel",
+        "wss_url": "ws://127.0.0.1:9/fuel",
+        "chain_id": 3333,
+        "router": "0x0000000000000000000000000000000000000001",
+        "quoter": "0x0000000000000000000000000000000000000002",
+        "v2_factory": "0x0000000000000000000000000000000000000003",
+        "v3_factory": "0x0000000000000000000000000000000000000004",
+        "wrapped_native": WRAPPED_NATIVE,
+        "native_address": "0x0000000000000000000000000000000000000000",
+        "wallets": [],
+        "public_rpc_urls": [],
+        "fuel_settings": {},
+        "quote_symbol": "WETH",
+        "quote_tokens": {"WETH": WRAPPED_NATIVE},
+        "max_block_lag": 0
+    }})).expect("Init");
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !testing::pop_events(256).iter().any(|e| e["type"] == "EngineReady") {
+        assert!(Instant::now() < deadline, "нет EngineReady");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn set_manual_gas(gwei: f64) {
+    testing::push_command(json!({"type": "UpdateSettings", "data": {"gas_price_gwei": gwei}})).expect("UpdateSettings");
+    // Очередь ядра выполняет команды по порядку: снимок GetState - значит UpdateSettings уже применён
+    testing::push_command(json!({"type": "GetState", "request_id": "manual_gas"})).expect("GetState");
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !testing::pop_events(256).iter().any(|e| e["type"] == "StateSnapshot" && e["request_id"] == "manual_gas") {
+        assert!(Instant::now() < deadline, "нет StateSnapshot");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn fresh_init_fuel_tx_uses_manual_gas_then_refuses_unknown() {
+    init();
+    let w_n: Address = WRAPPED_NATIVE.parse().unwrap();
+    let amount = U256::exp10(16);
+
+    set_manual_gas(3.0);
+    let tx = testing::fuel_withdraw_tx(w_n, amount).expect("газ из ручной настройки");
+    assert_eq!(tx.gas_price(), Some(U256::from(3_000_000_000u64)));
+
+    // Ни блока, ни ручного газа, ни eth_gasPrice - tx не строится вовсе
+    set_manual_gas(0.0);
+    assert!(testing::fuel_withdraw_tx(w_n, amount).is_none());
+}
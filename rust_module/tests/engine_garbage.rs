@@ -0,0 +1,176 @@
+// Мусорные и невалидные команды не роняют цикл движка: на каждую - CommandError, ядро отвечает дальше.
+// cargo test --no-default-features --features engine-tests --test engine_garbage
+#![cfg(feature = "engine-tests")]
+
+use dexbot_core::testing;
+use proptest::prelude::*;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Отправляет команду с уникальным request_id и ждёт первое событие с ним
+fn request(mut command: Value) -> Value {
+    let id = format!("req-{}", NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    command["request_id"] = json!(id);
+    let _ = testing::push_command(command);
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        assert!(Instant::now() < deadline, "нет ответа на {}", id);
+        if let Some(e) = testing::pop_events(256).into_iter().find(|e| e["request_id"] == id.as_str()) { return e; }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+fn assert_responsive() {
+    let state = request(json!({"type": "GetState"}));
+    assert_eq!(state["type"], "StateSnapshot", "вместо снимка: {}", state);
+}
+
+fn assert_field_error(command: Value, name: &str, field: &str) {
+    let e = request(command);
+    assert_eq!(e["type"], "CommandError", "{}", e);
+    assert_eq!(e["data"]["command"], name, "{}", e);
+    assert_eq!(e["data"]["field"], field, "{}", e);
+}
+
+#[test]
+fn malformed_addresses_are_rejected_per_field() {
+    assert_field_error(json!({"type": "CalcImpact", "data": {
+        "token_address": "0xnot-an-address", "quote_address": "0x0000000000000000000000000000000000000005",
+        "amount_in": 1.0, "is_buy": true
+    }}), "CalcImpact", "token_address");
+    assert_field_error(json!({"type": "ExecuteTrade", "data": {
+        "action": "buy", "token": "0x0000000000000000000000000000000000000001", "quote_token": "",
+        "amount": 1.0, "wallets": []
+    }}), "ExecuteTrade", "quote_token");
+    assert_field_error(json!({"type": "Init", "data": {
+        "rpc_url": "http://127.0.0.1:9", "wss_url": "ws://127.0.0.1:9", "chain_id": 1,
+        "router": "router", "quoter": "0x0000000000000000000000000000000000000002",
+        "v2_factory": "", "v3_factory": "", "wrapped_native": "", "native_address": "",
+        "wallets": [], "public_rpc_urls": [], "fuel_settings": {}, "quote_symbol": "", "quote_tokens": {}
+    }}), "Init", "router");
+    // Опечатка в необязательном адресе или кошельке - не нулевой адрес и не молча выброшенный кошелёк
+    assert_field_error(json!({"type": "Init", "data": {
+        "rpc_url": "http://127.0.0.1:9", "wss_url": "ws://127.0.0.1:9", "chain_id": 1,
+        "router": "0x0000000000000000000000000000000000000001", "quoter": "0x0000000000000000000000000000000000000002",
+        "v2_factory": "0x00000000000000000000000000000000000003", "v3_factory": "", "wrapped_native": "", "native_address": "",
+        "wallets": [], "public_rpc_urls": [], "fuel_settings": {}, "quote_symbol": "", "quote_tokens": {}
+    }}), "Init", "v2_factory");
+    assert_field_error(json!({"type": "ExecuteTrade", "data": {
+        "action": "buy", "token": "0x0000000000000000000000000000000000000001",
+        "quote_token": "0x0000000000000000000000000000000000000005",
+        "amount": 1.0, "wallets": ["0xABC"]
+    }}), "ExecuteTrade", "wallets");
+    assert_field_error(json!({"type": "ImportHistory", "data": {
+        "wallet": "0x0000000000000000000000000000000000000021", "token": "0x31"
+    }}), "ImportHistory", "token");
+    assert_field_error(json!({"type": "AddTokenSubscription", "data": {
+        "token_address": "0x0000000000000000000000000000000000000031", "quote_address": "0x05"
+    }}), "AddTokenSubscription", "quote_address");
+    assert_field_error(json!({"type": "SelectPool", "data": {
+        "token_address": "0x0000000000000000000000000000000000000031", "pool_address": "pool"
+    }}), "SelectPool", "pool_address");
+    assert_field_error(json!({"type": "Transfer", "data": {
+        "from_wallet": "0x0000000000000000000000000000000000000021", "to_address": "0x0000000000000000000000000000000000000022",
+        "token": "0xZZ", "amount": "1"
+    }}), "Transfer", "token");
+    assert_field_error(json!({"type": "RevokeApproval", "data": {
+        "wallet": "0x0000000000000000000000000000000000000021", "token": "0x0000000000000000000000000000000000000031",
+        "spender": "router"
+    }}), "RevokeApproval", "spender");
+    assert_field_error(json!({"type": "GetCandles", "data": {"pool": "0x1234", "interval": 60}}), "GetCandles", "pool");
+    assert_field_error(json!({"type": "RemoveWallet", "data": {"address": ""}}), "RemoveWallet", "address");
+}
+
+#[test]
+fn invalid_arguments_are_rejected_per_field() {
+    assert_field_error(json!({"type": "WrapNative", "data": {
+        "wallets": ["0x0000000000000000000000000000000000000021"], "amount": "lots"
+    }}), "WrapNative", "amount");
+    assert_field_error(json!({"type": "UnwrapNative", "data": {
+        "wallets": ["0x0000000000000000000000000000000000000021"], "amount": "-1"
+    }}), "UnwrapNative", "amount");
+    assert_field_error(json!({"type": "Transfer", "data": {
+        "from_wallet": "0x0000000000000000000000000000000000000021", "to_address": "0x0000000000000000000000000000000000000022",
+        "amount": "0"
+    }}), "Transfer", "amount");
+    assert_field_error(json!({"type": "Transfer", "data": {
+        "from_wallet": "0x0000000000000000000000000000000000000029", "to_address": "0x0000000000000000000000000000000000000022",
+        "amount": "1"
+    }}), "Transfer", "from_wallet");
+    assert_field_error(json!({"type": "DisperseNative", "data": {
+        "from_wallet": "0x0000000000000000000000000000000000000021",
+        "recipients": [["0x0000000000000000000000000000000000000022", 0.0]]
+    }}), "DisperseNative", "recipients");
+    assert_field_error(json!({"type": "SetPriceAlert", "data": {
+        "id": "a", "token": "0x0000000000000000000000000000000000000031", "quote": "0x0000000000000000000000000000000000000005",
+        "price": 1.0, "direction": "sideways"
+    }}), "SetPriceAlert", "direction");
+    assert_field_error(json!({"type": "UpdateSettings", "data": {"approval_mode": "sometimes"}}), "UpdateSettings", "approval_mode");
+    assert_field_error(json!({"type": "AddRpcNode", "data": {"url": "not a url", "is_private": false}}), "AddRpcNode", "url");
+    assert_field_error(json!({"type": "AddWallet", "data": {
+        "address": "0x0000000000000000000000000000000000000021", "private_key": "0x1234"
+    }}), "AddWallet", "private_key");
+
+    // Отказ команды целиком - command без поля
+    let e = request(json!({"type": "ScheduleRecurringTrade", "data": {
+        "id": "d", "action": "hold", "token": "0x0000000000000000000000000000000000000031",
+        "quote": "0x0000000000000000000000000000000000000005", "amount": 1.0,
+        "wallets": ["0x0000000000000000000000000000000000000021"], "interval_secs": 60, "max_executions": 1
+    }}));
+    assert_eq!(e["type"], "CommandError", "{}", e);
+    assert_eq!(e["data"]["command"], "ScheduleRecurringTrade", "{}", e);
+    assert!(e["data"]["field"].is_null(), "{}", e);
+    assert_field_error(json!({"type": "UpdateSettings", "data": {
+        "min_balance_wei": {"0x31": "1000"}
+    }}), "UpdateSettings", "min_balance_wei.0x31");
+
+    // Не разобранная команда: тип известен из JSON, поле - нет
+    let e = request(json!({"type": "ExecuteTrade", "data": {"action": 42}}));
+    assert_eq!(e["type"], "CommandError");
+    assert_eq!(e["data"]["command"], "ExecuteTrade");
+    assert_responsive();
+}
+
+fn garbage_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<f64>().prop_map(|f| json!(f)),
+        any::<i64>().prop_map(Value::from),
+        ".{0,48}".prop_map(Value::from),
+    ];
+    leaf.prop_recursive(3, 24, 6, |inner| prop_oneof![
+        prop::collection::vec(inner.clone(), 0..6).prop_map(Value::from),
+        prop::collection::hash_map(".{0,12}", inner, 0..6).prop_map(|m| json!(m)),
+    ])
+}
+
+// Команды с адресами в data; Init/Shutdown не трогаем - с валидными полями они законно меняют сессию
+const COMMAND_TYPES: [&str; 5] = ["CalcImpact", "ExecuteTrade", "AddTokenSubscription", "RemoveTokenSubscription", "NoSuchCommand"];
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn garbage_json_keeps_engine_alive(raw in ".{0,256}") {
+        let _ = testing::push_raw_command(&raw);
+        let _ = testing::pop_events(4096);
+    }
+
+    #[test]
+    fn garbage_commands_keep_engine_alive(kind in prop::sample::select(&COMMAND_TYPES[..]), data in garbage_value()) {
+        let _ = testing::push_command(json!({"type": kind, "data": data}));
+        let _ = testing::pop_events(4096);
+    }
+}
+
+#[test]
+fn responsive_after_garbage() {
+    for raw in ["", "{", "null", "[]", "{\"type\": null}", "{\"type\": \"GetState\", \"data\": {\"x\": 1}}", "\u{0}"] {
+        let _ = testing::push_raw_command(raw);
+    }
+    assert_responsive();
+}
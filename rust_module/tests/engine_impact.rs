@@ -0,0 +1,42 @@
+// CalcImpact на каждое нажатие: устаревшие суммы перебиваются последней, ответ - один.
+// cargo test --no-default-features --features engine-tests --test engine_impact
+#![cfg(feature = "engine-tests")]
+
+use dexbot_core::testing;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+const TOKEN: &str = "0x0000000000000000000000000000000000000011";
+const QUOTE: &str = "0x0000000000000000000000000000000000000022";
+
+fn calc_impact(amount_in: f64) {
+    testing::push_command(json!({"type": "CalcImpact", "data": {
+        "token_address": TOKEN, "quote_address": QUOTE, "amount_in": amount_in, "is_buy": true
+    }})).expect("CalcImpact");
+}
+
+fn impact_updates(wait: Duration) -> Vec<Value> {
+    let deadline = Instant::now() + wait;
+    let mut updates = Vec::new();
+    while Instant::now() < deadline {
+        updates.extend(testing::pop_events(256).into_iter().filter(|e| e["type"] == "ImpactUpdate"));
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    updates
+}
+
+#[test]
+fn typing_produces_one_update_for_final_amount() {
+    for amount in [1.0, 12.0, 123.0, 1234.0, 12345.0] {
+        calc_impact(amount);
+    }
+    let updates = impact_updates(Duration::from_secs(2));
+    assert_eq!(updates.len(), 1, "ImpactUpdate: {:?}", updates);
+    assert_eq!(updates[0]["data"]["amount_in"], 12345.0);
+
+    // Другая сумма с тем же impact - новый ответ, не дубликат
+    calc_impact(7.0);
+    let updates = impact_updates(Duration::from_secs(2));
+    assert_eq!(updates.len(), 1, "ImpactUpdate: {:?}", updates);
+    assert_eq!(updates[0]["data"]["amount_in"], 7.0);
+}
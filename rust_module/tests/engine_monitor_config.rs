@@ -0,0 +1,38 @@
+// Тайминги монитора через UpdateSettings: значения вне границ ограничиваются, GetState отдаёт действующие.
+// cargo test --no-default-features --features engine-tests --test engine_monitor_config
+#![cfg(feature = "engine-tests")]
+
+use dexbot_core::testing;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+fn monitor_state() -> Value {
+    testing::push_command(json!({"type": "GetState"})).expect("GetState");
+    let deadline = Instant::now() + Duration::from_secs(2);
+    while Instant::now() < deadline {
+        if let Some(snapshot) = testing::pop_events(256).into_iter().find(|e| e["type"] == "StateSnapshot") {
+            return snapshot["data"]["monitor"].clone();
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("нет StateSnapshot");
+}
+
+#[test]
+fn update_settings_applies_and_clamps_monitor_timings() {
+    let defaults = monitor_state();
+    assert_eq!(defaults["idle_timeout_secs"], 30.0);
+    assert_eq!(defaults["receipt_poll_ms"], 500);
+    assert_eq!(defaults["block_time_ms"], Value::Null);
+
+    testing::push_command(json!({"type": "UpdateSettings", "data": {"monitor": {
+        "idle_timeout_secs": 1, "receipt_poll_ms": 250, "worker_tick_ms": 999_999
+    }}})).expect("UpdateSettings");
+    let monitor = monitor_state();
+    assert_eq!(monitor["idle_timeout_secs"], 5.0, "{}", monitor);
+    assert_eq!(monitor["receipt_poll_ms"], 250);
+    assert_eq!(monitor["worker_tick_ms"], 60_000);
+    // Не переданные поля не меняются
+    assert_eq!(monitor["reconnect_delay_secs"], 3.0);
+    assert_eq!(monitor["rpc_health_secs"], 10.0);
+}
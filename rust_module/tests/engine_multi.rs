@@ -0,0 +1,123 @@
+// Несколько экземпляров ядра в одном процессе: у каждого своё состояние, события помечены engine_id.
+// Сеть не нужна: RPC указывают в закрытый порт, ядро работает с недоступными нодами.
+// cargo test --no-default-features --features engine-tests --test engine_multi
+#![cfg(feature = "engine-tests")]
+
+use dexbot_core::testing;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+fn config(chain_id: u64) -> Value {
+    json!({
+        "rpc_url": format!("http://127.0.0.1:9/chain-{}", chain_id),
+        "wss_url": format!("ws://127.0.0.1:9/chain-{}", chain_id),
+        "chain_id": chain_id,
+        "router": "0x0000000000000000000000000000000000000001",
+        "quoter": "0x0000000000000000000000000000000000000002",
+        "v2_factory": "0x0000000000000000000000000000000000000003",
+        "v3_factory": "0x0000000000000000000000000000000000000004",
+        "wrapped_native": "0x0000000000000000000000000000000000000005",
+        "native_address": "0x0000000000000000000000000000000000000000",
+        "wallets": [],
+        "public_rpc_urls": [],
+        "fuel_settings": {},
+        "quote_symbol": "WETH",
+        "quote_tokens": {"WETH": "0x0000000000000000000000000000000000000005"},
+        "max_block_lag": 0
+    })
+}
+
+fn wait_for(engine_id: u64, kind: &str) -> Value {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    loop {
+        assert!(Instant::now() < deadline, "движок {}: нет {}", engine_id, kind);
+        let batch = testing::pop_events_from(engine_id, 256);
+        for event in &batch {
+            assert_eq!(event["engine_id"], engine_id, "чужое событие в очереди движка {}: {}", engine_id, event);
+        }
+        if let Some(event) = batch.into_iter().find(|e| e["type"] == kind) { return event; }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn engines_keep_separate_state_and_tag_events() {
+    let a = testing::create_engine(config(3333)).expect("движок A");
+    let b = testing::create_engine(config(4444)).expect("движок B");
+    assert_ne!(a, b);
+    wait_for(a, "EngineReady");
+    wait_for(b, "EngineReady");
+
+    for (id, chain_id) in [(a, 3333), (b, 4444)] {
+        testing::push_command_to(id, json!({"type": "GetState", "request_id": "state"})).expect("GetState");
+        let snapshot = wait_for(id, "StateSnapshot");
+        assert_eq!(snapshot["data"]["chain_id"], chain_id);
+        assert_eq!(snapshot["request_id"], "state");
+    }
+
+    // Неразобранный Init - движок не создаётся
+    assert!(testing::create_engine(json!({"chain_id": "garbage"})).is_err());
+}
+
+/// События движка до kind включительно и всё, что успело встать в очередь до него
+fn drain_until(engine_id: u64, kind: &str) -> Vec<Value> {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let mut events = Vec::new();
+    loop {
+        assert!(Instant::now() < deadline, "движок {}: нет {}", engine_id, kind);
+        events.extend(testing::pop_events_from(engine_id, 256));
+        if events.iter().any(|e| e["type"] == kind) {
+            events.extend(testing::pop_events_from(engine_id, 256));
+            return events;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn events_sent(engine_id: u64) -> u64 {
+    let text = testing::metrics_text(engine_id);
+    let line = text.lines().find(|l| l.starts_with("dexbot_bridge_events_total ")).expect("dexbot_bridge_events_total");
+    line.rsplit(' ').next().unwrap().parse().unwrap()
+}
+
+#[test]
+fn metrics_and_event_filter_are_per_engine() {
+    let a = testing::create_engine(config(5555)).expect("движок A");
+    let b = testing::create_engine(config(6666)).expect("движок B");
+    wait_for(a, "EngineReady");
+    wait_for(b, "EngineReady");
+
+    // Повторный Init сбрасывает метрики только своего движка
+    let sent_a = events_sent(a);
+    assert!(sent_a > 0);
+    testing::push_command_to(b, json!({"type": "Init", "data": config(6666)})).expect("Init B");
+    wait_for(b, "EngineReady");
+    assert!(events_sent(a) >= sent_a, "Init движка B сбросил метрики A");
+
+    // Фильтр A не действует на B
+    testing::set_event_filter(a, false, &["TradingPaused"]);
+    for id in [a, b] {
+        testing::push_command_to(id, json!({"type": "PauseTrading"})).expect("PauseTrading");
+        testing::push_command_to(id, json!({"type": "GetState", "request_id": "filter"})).expect("GetState");
+    }
+    assert!(!drain_until(a, "StateSnapshot").iter().any(|e| e["type"] == "TradingPaused"));
+    assert!(drain_until(b, "StateSnapshot").iter().any(|e| e["type"] == "TradingPaused"));
+}
+
+#[test]
+fn destroyed_engine_releases_its_state() {
+    let id = testing::create_engine(config(7777)).expect("движок");
+    wait_for(id, "EngineReady");
+    testing::destroy_engine(id).expect("destroy_engine");
+
+    assert!(testing::push_command_to(id, json!({"type": "GetState"})).is_err());
+    assert!(testing::destroy_engine(id).is_err());
+    assert!(testing::destroy_engine(0).is_err(), "движок по умолчанию не удаляется");
+
+    // Shutdown останавливает задачи движка, после последней его значения освобождаются
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while !testing::engine_released(id) {
+        assert!(Instant::now() < deadline, "состояние движка {} не освобождено", id);
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,70 @@
+// min_native_reserve_wei из конфига сети приходит в Init, UpdateSettings меняет его до следующего Init.
+// Невалидное значение отклоняет Init до остановки текущей сессии.
+// cargo test --no-default-features --features engine-tests --test engine_native_reserve
+#![cfg(feature = "engine-tests")]
+
+use dexbot_core::testing;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+fn init(min_native_reserve_wei: Option<&str>) {
+    testing::push_command(json!({"type": "Init", "data": {
+        "rpc_url": "http://127.0.0.1:9/reserve",
+        "wss_url": "ws://127.0.0.1:9/reserve",
+        "chain_id": 4444,
+        "router": "0x0000000000000000000000000000000000000001",
+        "quoter": "0x0000000000000000000000000000000000000002",
+        "v2_factory": "0x0000000000000000000000000000000000000003",
+        "v3_factory": "0x0000000000000000000000000000000000000004",
+        "wrapped_native": "0x0000000000000000000000000000000000000005",
+        "native_address": "0x0000000000000000000000000000000000000000",
+        "wallets": [],
+        "public_rpc_urls": [],
+        "fuel_settings": {},
+        "max_block_lag": 0,
+        "min_native_reserve_wei": min_native_reserve_wei
+    }})).expect("Init");
+}
+
+/// События до StateSnapshot включительно
+fn until_snapshot() -> Vec<Value> {
+    testing::push_command(json!({"type": "GetState"})).expect("GetState");
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut events = Vec::new();
+    while Instant::now() < deadline {
+        for event in testing::pop_events(256) {
+            let done = event["type"] == "StateSnapshot";
+            events.push(event);
+            if done { return events; }
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("нет StateSnapshot");
+}
+
+fn reserve() -> Value {
+    until_snapshot().last().unwrap()["data"]["min_native_reserve_wei"].clone()
+}
+
+#[test]
+fn init_loads_reserve_and_update_settings_overrides_it() {
+    init(Some("10000000000000000"));
+    assert_eq!(reserve(), "10000000000000000");
+
+    // Runtime override держится до следующего Init
+    testing::push_command(json!({"type": "UpdateSettings", "data": {"min_native_reserve_wei": "5"}})).expect("UpdateSettings");
+    assert_eq!(reserve(), "5");
+
+    // Не число - Init отклонён, сессия и резерв прежние
+    init(Some("0.01"));
+    let events = until_snapshot();
+    assert!(
+        events.iter().any(|e| e["type"] == "CommandError" && e["data"]["field"] == "min_native_reserve_wei"),
+        "нет CommandError по min_native_reserve_wei"
+    );
+    assert_eq!(events.last().unwrap()["data"]["min_native_reserve_wei"], "5");
+
+    // Сеть без резерва
+    init(None);
+    assert_eq!(reserve(), "0");
+}
@@ -0,0 +1,89 @@
+// Синхронизация nonce на тике воркера не отдаёт заново зарезервированные nonce:
+// buy снайпа подписан заранее, сеть о нём не знает - nonce_map не опускается ниже него,
+// как и ниже nonce отправленных tx, которых отстающая нода ещё не видит.
+// RPC в закрытом порту: газ - фолбэк, баланс не прочитан и подпись не блокирует.
+// cargo test --no-default-features --features engine-tests --test engine_nonce_sync
+#![cfg(feature = "engine-tests")]
+
+use dexbot_core::testing;
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::Address;
+use ethers::utils::to_checksum;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+const TOKEN: &str = "0x0000000000000000000000000000000000000011";
+const WRAPPED_NATIVE: &str = "0x0000000000000000000000000000000000000005";
+const KEY: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+fn wait_for(kind: &str) -> Value {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        if let Some(ev) = testing::pop_events(256).into_iter().find(|e| e["type"] == kind) {
+            return ev;
+        }
+        assert!(Instant::now() < deadline, "нет {}", kind);
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// nonce_map ядра для кошелька из GetState
+fn local_nonce(wallet: Address) -> u64 {
+    testing::push_command(json!({"type": "GetState", "request_id": "nonces"})).expect("GetState");
+    let snapshot = wait_for("StateSnapshot");
+    snapshot["data"]["nonces"][to_checksum(&wallet, None)].as_u64().unwrap_or_else(|| panic!("нет nonce: {}", snapshot["data"]["nonces"]))
+}
+
+#[test]
+fn worker_sync_keeps_presigned_snipe_nonce() {
+    let wallet = KEY.parse::<LocalWallet>().unwrap().address();
+    testing::push_command(json!({"type": "Init", "data": {
+        "rpc_url": "http://127.0.0.1:9/nonce",
+        "wss_url": "ws://127.0.0.1:9/nonce",
+        "chain_id": 3333,
+        "router": "0x0000000000000000000000000000000000000001",
+        "quoter": "0x0000000000000000000000000000000000000002",
+        "v2_factory": "0x0000000000000000000000000000000000000003",
+        "v3_factory": "0x0000000000000000000000000000000000000004",
+        "wrapped_native": WRAPPED_NATIVE,
+        "native_address": "0x0000000000000000000000000000000000000000",
+        "wallets": [[format!("{:?}", wallet), KEY]],
+        "public_rpc_urls": [],
+        "fuel_settings": {},
+        "quote_symbol": "WETH",
+        "quote_tokens": {"WETH": WRAPPED_NATIVE},
+        "max_block_lag": 0
+    }})).expect("Init");
+    wait_for("EngineReady");
+    for address in [TOKEN, WRAPPED_NATIVE] {
+        testing::push_command(json!({"type": "UpdateTokenDecimals", "data": {"address": address, "decimals": 18}})).expect("UpdateTokenDecimals");
+    }
+    testing::push_command(json!({"type": "AddTokenSubscription", "data": {
+        "token_address": TOKEN, "quote_address": WRAPPED_NATIVE, "quote_symbol": "WETH"
+    }})).expect("AddTokenSubscription");
+
+    // Пула ещё нет - снайп подписывает buy с первым свободным nonce
+    testing::push_command(json!({"type": "ArmSnipe", "data": {
+        "token": TOKEN,
+        "quote": WRAPPED_NATIVE,
+        "amount": 0.01,
+        "wallets": [format!("{:?}", wallet)],
+        "max_gas_gwei": 3.0,
+        "max_buy_tax_pct": 10.0,
+        "min_liquidity_usd": 0.0
+    }})).expect("ArmSnipe");
+    wait_for("SnipeArmed");
+    assert_eq!(local_nonce(wallet), 1);
+
+    // Pending nonce сети всё ещё 0: следующая сделка не должна получить nonce снайпа
+    testing::sync_nonce(wallet, 0);
+    assert_eq!(local_nonce(wallet), 1, "синхронизация отдала nonce снайпа");
+
+    // Сеть ушла вперёд (tx не от ядра) - счётчик догоняет её
+    testing::sync_nonce(wallet, 5);
+    assert_eq!(local_nonce(wallet), 5);
+
+    // Нода ещё не видит отправленные tx (pending 3 при локальном 5) - их nonce не выдаются повторно
+    testing::sync_nonce(wallet, 3);
+    assert_eq!(local_nonce(wallet), 5, "синхронизация опустила nonce ниже отправленных tx");
+}
@@ -0,0 +1,85 @@
+// PoolDetected и PoolUpdate несут пару подписки: token, quote и символ quote из quote_tokens Init.
+// RPC в закрытом порту - пул выбирается и Sync разбирается без сети.
+// cargo test --no-default-features --features engine-tests --test engine_pool_events
+#![cfg(feature = "engine-tests")]
+
+use dexbot_core::testing;
+use ethers::types::{Address, U256};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+const TOKEN: &str = "0x0000000000000000000000000000000000000011";
+const USDC: &str = "0x0000000000000000000000000000000000000033";
+const POOL: &str = "0x0000000000000000000000000000000000000044";
+
+/// Поля пары из data события - как их разбирает потребитель
+#[derive(Deserialize)]
+struct PoolPair {
+    token: String,
+    quote: String,
+    quote_symbol: String,
+}
+
+fn wait_for(kind: &str) -> Value {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        assert!(Instant::now() < deadline, "нет {}", kind);
+        if let Some(event) = testing::pop_events(256).into_iter().find(|e| e["type"] == kind) { return event; }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn pair_of(event: Value) -> PoolPair {
+    serde_json::from_value(event["data"].clone()).expect("token/quote/quote_symbol в data")
+}
+
+fn assert_usdc_pair(pair: &PoolPair) {
+    assert_eq!(pair.token.to_lowercase(), TOKEN);
+    assert_eq!(pair.quote.to_lowercase(), USDC);
+    assert_eq!(pair.quote_symbol, "USDC");
+}
+
+#[test]
+fn pool_events_carry_token_quote_and_quote_symbol() {
+    testing::push_command(json!({"type": "Init", "data": {
+        "rpc_url": "http://127.0.0.1:9/pools",
+        "wss_url": "ws://127.0.0.1:9/pools",
+        "chain_id": 3333,
+        "router": "0x0000000000000000000000000000000000000001",
+        "quoter": "0x0000000000000000000000000000000000000002",
+        "v2_factory": "0x0000000000000000000000000000000000000003",
+        "v3_factory": "0x0000000000000000000000000000000000000004",
+        "wrapped_native": "0x0000000000000000000000000000000000000005",
+        "native_address": "0x0000000000000000000000000000000000000000",
+        "wallets": [],
+        "public_rpc_urls": [],
+        "fuel_settings": {},
+        "quote_symbol": "WETH",
+        "quote_tokens": {"WETH": "0x0000000000000000000000000000000000000005", "USDC": USDC},
+        "max_block_lag": 0
+    }})).expect("Init");
+    wait_for("EngineReady");
+
+    for (address, decimals) in [(TOKEN, 18), (USDC, 6)] {
+        testing::push_command(json!({"type": "UpdateTokenDecimals", "data": {"address": address, "decimals": decimals}})).expect("UpdateTokenDecimals");
+    }
+    testing::push_command(json!({"type": "AddTokenSubscription", "data": {
+        "token_address": TOKEN, "quote_address": USDC, "quote_symbol": "USDC"
+    }})).expect("AddTokenSubscription");
+    // Команды выполняются по очереди: снимок приходит после подписки
+    testing::push_command(json!({"type": "GetState", "request_id": "subscribed"})).expect("GetState");
+    wait_for("StateSnapshot");
+
+    let (token, quote, pool): (Address, Address, Address) = (TOKEN.parse().unwrap(), USDC.parse().unwrap(), POOL.parse().unwrap());
+    testing::select_pool(token, quote, pool, "V2", 5000.0, 2.0);
+    let detected = wait_for("PoolDetected");
+    assert_eq!(detected["data"]["address"].as_str().unwrap().to_lowercase(), POOL);
+    assert_usdc_pair(&pair_of(detected));
+
+    // 1000 токенов (18) против 2000 USDC (6): token0 = TOKEN, он меньше адресом
+    testing::pool_sync(token, quote, pool, U256::exp10(21), U256::from(2_000_000_000u64));
+    let update = wait_for("PoolUpdate");
+    assert_eq!(update["data"]["pool_address"].as_str().unwrap().to_lowercase(), POOL);
+    assert_usdc_pair(&pair_of(update));
+}
@@ -0,0 +1,95 @@
+// Повторный Init (смена сети) без утечки задач прошлой сессии и без потери первых событий новой.
+// Сеть не нужна: RPC указывают в закрытый порт, ядро работает с недоступными нодами.
+// cargo test --no-default-features --features engine-tests --test engine_reinit
+#![cfg(feature = "engine-tests")]
+
+use dexbot_core::testing;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+const CHAIN_A: u64 = 1111;
+const CHAIN_B: u64 = 2222;
+
+fn rpc_url(chain_id: u64) -> String {
+    format!("http://127.0.0.1:9/chain-{}", chain_id)
+}
+
+fn init(chain_id: u64, request_id: Option<String>) {
+    let mut command = json!({"type": "Init", "data": {
+        "rpc_url": rpc_url(chain_id),
+        "wss_url": format!("ws://127.0.0.1:9/chain-{}", chain_id),
+        "chain_id": chain_id,
+        "router": "0x0000000000000000000000000000000000000001",
+        "quoter": "0x0000000000000000000000000000000000000002",
+        "v2_factory": "0x0000000000000000000000000000000000000003",
+        "v3_factory": "0x0000000000000000000000000000000000000004",
+        "wrapped_native": "0x0000000000000000000000000000000000000005",
+        "native_address": "0x0000000000000000000000000000000000000000",
+        "wallets": [],
+        "public_rpc_urls": [],
+        "fuel_settings": {},
+        "quote_symbol": "WETH",
+        "quote_tokens": {"WETH": "0x0000000000000000000000000000000000000005"},
+        "max_block_lag": 0
+    }});
+    if let Some(id) = request_id {
+        command["request_id"] = json!(id);
+    }
+    testing::push_command(command).expect("Init");
+}
+
+/// События до n-го EngineReady включительно отбрасываются, возвращаются пришедшие после него за window
+fn events_after_ready(n: usize, timeout: Duration, window: Duration) -> Vec<Value> {
+    let deadline = Instant::now() + timeout;
+    let (mut seen, mut after) = (0, Vec::new());
+    while seen < n {
+        assert!(Instant::now() < deadline, "дошло {} EngineReady из {}", seen, n);
+        let batch = testing::pop_events(256);
+        if batch.is_empty() { std::thread::sleep(Duration::from_millis(10)); }
+        for event in batch {
+            if seen == n { after.push(event); } else if event["type"] == "EngineReady" { seen += 1; }
+        }
+    }
+    let until = Instant::now() + window;
+    while Instant::now() < until {
+        after.extend(testing::pop_events(256));
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    after
+}
+
+#[test]
+fn rapid_reinit_keeps_sessions_apart() {
+    const ROUNDS: usize = 6;
+    // Init уходят подряд, не дожидаясь EngineReady: каждая следующая сессия рвёт предыдущую на старте
+    for round in 0..ROUNDS {
+        let chain_id = if round % 2 == 0 { CHAIN_A } else { CHAIN_B };
+        init(chain_id, Some(format!("init-{}", round)));
+    }
+    // Последний Init без request_id: его события проходят обычную дедупликацию
+    init(CHAIN_B, None);
+
+    let after = events_after_ready(ROUNDS + 1, Duration::from_secs(60), Duration::from_secs(3));
+    let old_marker = format!("chain-{}", CHAIN_A);
+    for event in &after {
+        let raw = event.to_string();
+        assert!(!raw.contains(&old_marker), "событие сети {} после EngineReady сети {}: {}", CHAIN_A, CHAIN_B, raw);
+        assert!(event.get("request_id").is_none(), "событие прошлого Init после нового EngineReady: {}", raw);
+    }
+    // Тот же ConnectionStatus, что и у прошлой сессии, - кэш дедупликации сброшен, он не проглочен
+    assert!(
+        after.iter().any(|e| e["type"] == "ConnectionStatus" && e["data"]["connected"] == true),
+        "ConnectionStatus новой сессии проглочен как дубликат"
+    );
+
+    testing::push_command(json!({"type": "GetState", "request_id": "state"})).expect("GetState");
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let snapshot = loop {
+        assert!(Instant::now() < deadline, "нет StateSnapshot");
+        if let Some(e) = testing::pop_events(256).into_iter().find(|e| e["type"] == "StateSnapshot") { break e; }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    assert_eq!(snapshot["data"]["chain_id"], CHAIN_B);
+    let nodes = snapshot["data"]["rpc_nodes"].as_array().expect("rpc_nodes");
+    assert!(!nodes.is_empty() && nodes.iter().all(|n| n["url"] == rpc_url(CHAIN_B)), "ноды прошлой сети: {:?}", nodes);
+}
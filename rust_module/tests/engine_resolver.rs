@@ -0,0 +1,80 @@
+// Имена в полях адресов: метка наблюдаемого адреса разрешается без RPC, имя при недоступной ноде - CommandError по полю.
+// Сеть не нужна: RPC указывают в закрытый порт.
+// cargo test --no-default-features --features engine-tests --test engine_resolver
+#![cfg(feature = "engine-tests")]
+
+use dexbot_core::testing;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+const WHALE: &str = "0x0000000000000000000000000000000000000011";
+
+fn init(request_id: &str, ens_registry: &str) {
+    testing::push_command(json!({"type": "Init", "request_id": request_id, "data": {
+        "rpc_url": "http://127.0.0.1:9",
+        "wss_url": "ws://127.0.0.1:9",
+        "chain_id": 8888,
+        "router": "0x0000000000000000000000000000000000000001",
+        "quoter": "0x0000000000000000000000000000000000000002",
+        "v2_factory": "0x0000000000000000000000000000000000000003",
+        "v3_factory": "",
+        "wrapped_native": "0x0000000000000000000000000000000000000005",
+        "native_address": "0x0000000000000000000000000000000000000000",
+        "wallets": [],
+        "public_rpc_urls": [],
+        "fuel_settings": {},
+        "max_block_lag": 0,
+        "ens_registry": ens_registry
+    }})).expect("Init");
+}
+
+/// События команды request_id до первого из kinds
+fn wait_for(request_id: &str, kinds: &[&str]) -> Vec<Value> {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let mut events = Vec::new();
+    while Instant::now() < deadline {
+        for event in testing::pop_events(256) {
+            if event["request_id"] != request_id { continue; }
+            let done = kinds.iter().any(|k| event["type"] == *k);
+            events.push(event);
+            if done { return events; }
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("{}: нет {:?}", request_id, kinds);
+}
+
+fn watch(request_id: &str, address: &str, label: &str) {
+    testing::push_command(json!({"type": "WatchAddress", "request_id": request_id, "data": {"address": address, "label": label}}))
+        .expect("WatchAddress");
+}
+
+#[test]
+fn names_resolve_only_with_registry() {
+    init("init", "0x0000000000000000000000000000000000000eee");
+    wait_for("init", &["EngineReady"]);
+    watch("whale", WHALE, "Whale");
+    wait_for("whale", &["Log"]);
+
+    // Метка - без обращения к реестру
+    watch("label", "whale", "again");
+    let events = wait_for("label", &["Log", "CommandError"]);
+    let log = events.last().unwrap();
+    assert_eq!(log["type"], "Log", "{}", log);
+    assert!(log["data"]["message"].as_str().unwrap().contains(WHALE), "{}", log);
+
+    // Имя при недоступной ноде - отказ по полю, а не нулевой адрес
+    watch("ens", "vitalik.eth", "");
+    let error = wait_for("ens", &["CommandError"]).pop().unwrap();
+    assert_eq!(error["data"]["command"], "WatchAddress", "{}", error);
+    assert_eq!(error["data"]["field"], "address", "{}", error);
+    assert!(error["data"]["message"].as_str().unwrap().contains("vitalik.eth"), "{}", error);
+
+    // Без реестра имя не разрешается - поле отклоняется как некорректный адрес
+    init("plain", "");
+    wait_for("plain", &["EngineReady"]);
+    watch("plain-ens", "vitalik.eth", "");
+    let error = wait_for("plain-ens", &["CommandError"]).pop().unwrap();
+    assert_eq!(error["data"]["field"], "address", "{}", error);
+    assert!(error["data"]["message"].as_str().unwrap().contains("некорректный адрес"), "{}", error);
+}
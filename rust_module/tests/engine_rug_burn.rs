@@ -0,0 +1,83 @@
+// Rug-алерт по Burn: LiquidityAlert только при заданном rug_alert_pct и сжигании не меньше порога.
+// Без порога и на мелком Burn ядро молчит.
+// cargo test --no-default-features --features engine-tests --test engine_rug_burn
+#![cfg(feature = "engine-tests")]
+
+use dexbot_core::testing;
+use ethers::types::{Address, U256};
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+const TOKEN: &str = "0x0000000000000000000000000000000000000011";
+const USDC: &str = "0x0000000000000000000000000000000000000033";
+const POOL: &str = "0x0000000000000000000000000000000000000044";
+
+fn wait_for(kind: &str) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !testing::pop_events(256).iter().any(|e| e["type"] == kind) {
+        assert!(Instant::now() < deadline, "нет {}", kind);
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn addr(a: &str) -> Address {
+    a.parse().unwrap()
+}
+
+/// rug_alert_pct применён, когда GetState после UpdateSettings вернул снимок - команды идут по порядку
+fn set_rug_alert_pct(pct: f64) {
+    testing::push_command(json!({"type": "UpdateSettings", "data": {"rug_alert_pct": pct}})).expect("UpdateSettings");
+    testing::push_command(json!({"type": "GetState", "request_id": "rug_alert_pct"})).expect("GetState");
+    wait_for("StateSnapshot");
+}
+
+/// Burn usdc USDC (token1) в блоке block: сколько LiquidityAlert ушло
+fn burn(usdc: u64, block: u64) -> usize {
+    testing::pool_burn(addr(TOKEN), addr(USDC), addr(POOL), U256::zero(), U256::from(usdc) * U256::exp10(6), block);
+    testing::pop_events(256).iter().filter(|e| e["type"] == "LiquidityAlert").count()
+}
+
+#[test]
+fn burn_alert_respects_threshold() {
+    testing::push_command(json!({"type": "Init", "data": {
+        "rpc_url": "http://127.0.0.1:9/rug",
+        "wss_url": "ws://127.0.0.1:9/rug",
+        "chain_id": 3333,
+        "router": "0x0000000000000000000000000000000000000001",
+        "quoter": "0x0000000000000000000000000000000000000002",
+        "v2_factory": "0x0000000000000000000000000000000000000003",
+        "v3_factory": "0x0000000000000000000000000000000000000004",
+        "wrapped_native": "0x0000000000000000000000000000000000000005",
+        "native_address": "0x0000000000000000000000000000000000000000",
+        "wallets": [],
+        "public_rpc_urls": [],
+        "fuel_settings": {},
+        "quote_symbol": "WETH",
+        "quote_tokens": {"WETH": "0x0000000000000000000000000000000000000005", "USDC": USDC},
+        "max_block_lag": 0
+    }})).expect("Init");
+    wait_for("EngineReady");
+    for (address, decimals) in [(TOKEN, 18), (USDC, 6)] {
+        testing::push_command(json!({"type": "UpdateTokenDecimals", "data": {"address": address, "decimals": decimals}})).expect("UpdateTokenDecimals");
+    }
+    testing::push_command(json!({"type": "AddTokenSubscription", "data": {
+        "token_address": TOKEN, "quote_address": USDC, "quote_symbol": "USDC"
+    }})).expect("AddTokenSubscription");
+    testing::push_command(json!({"type": "GetState", "request_id": "subscribed"})).expect("GetState");
+    wait_for("StateSnapshot");
+
+    // Базовая линия пула: 5000 USDC после Sync
+    testing::select_pool(addr(TOKEN), addr(USDC), addr(POOL), "V2", 10000.0, 5.0);
+    testing::pool_sync(addr(TOKEN), addr(USDC), addr(POOL), U256::exp10(21), U256::from(5000) * U256::exp10(6));
+
+    // Порог не задан - даже сжигание почти всей ликвидности не алерт
+    set_rug_alert_pct(0.0);
+    assert_eq!(burn(4000, 1), 0);
+
+    // 1000 из 6000 (~17%) ниже порога 50%
+    set_rug_alert_pct(50.0);
+    assert_eq!(burn(1000, 2), 0);
+
+    // 6000 из 11000 (~55%) - выше порога: один алерт
+    assert_eq!(burn(6000, 3), 1);
+}
@@ -0,0 +1,106 @@
+// Сессия между Init: ордер и игнорируемый токен попадают в файл без ключей кошелька, повторный Init читает файл,
+// RestoreSession возвращает ордер, отменённый до восстановления, и игнор-лист. Сеть не нужна: RPC указывают в закрытый порт.
+// cargo test --no-default-features --features engine-tests --test engine_session
+#![cfg(feature = "engine-tests")]
+
+use dexbot_core::testing;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+const WALLET: &str = "0x0000000000000000000000000000000000000021";
+const SPAM: &str = "0x0000000000000000000000000000000000000041";
+const KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+fn init(request_id: &str, session_path: &str) {
+    testing::push_command(json!({"type": "Init", "request_id": request_id, "data": {
+        "rpc_url": "http://127.0.0.1:9",
+        "wss_url": "ws://127.0.0.1:9",
+        "chain_id": 6666,
+        "router": "0x0000000000000000000000000000000000000001",
+        "quoter": "0x0000000000000000000000000000000000000002",
+        "v2_factory": "0x0000000000000000000000000000000000000003",
+        "v3_factory": "",
+        "wrapped_native": "0x0000000000000000000000000000000000000005",
+        "native_address": "0x0000000000000000000000000000000000000000",
+        "wallets": [[WALLET, KEY]],
+        "public_rpc_urls": [],
+        "fuel_settings": {},
+        "max_block_lag": 0,
+        "session_path": session_path
+    }})).expect("Init");
+}
+
+fn wait_for(request_id: &str, kinds: &[&str]) -> Value {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while Instant::now() < deadline {
+        for event in testing::pop_events(256) {
+            if event["request_id"] == request_id && kinds.iter().any(|k| event["type"] == *k) {
+                return event;
+            }
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("{}: нет {:?}", request_id, kinds);
+}
+
+fn state(request_id: &str) -> Value {
+    testing::push_command(json!({"type": "GetState", "request_id": request_id})).expect("GetState");
+    wait_for(request_id, &["StateSnapshot"])["data"].clone()
+}
+
+fn limit_orders(request_id: &str) -> u64 {
+    state(request_id)["limit_orders"].as_u64().unwrap()
+}
+
+#[test]
+fn limit_order_survives_reinit_without_keys_on_disk() {
+    let path = std::env::temp_dir().join(format!("engine_session_{}.json", std::process::id()));
+    let path_str = path.to_str().unwrap();
+    std::fs::remove_file(&path).ok();
+
+    init("init", path_str);
+    wait_for("init", &["EngineReady"]);
+    // Файла ещё нет - восстанавливать нечего, дальше снимки пишутся
+    testing::push_command(json!({"type": "RestoreSession", "request_id": "empty", "data": {}})).expect("RestoreSession");
+    let empty = wait_for("empty", &["SessionRestored", "CommandError"]);
+    assert_eq!(empty["type"], "SessionRestored", "{}", empty);
+    assert_eq!(empty["data"]["limit_orders"], 0);
+
+    testing::push_command(json!({"type": "PlaceLimitOrder", "request_id": "place", "data": {
+        "id": "o1",
+        "action": "buy",
+        "token": "0x0000000000000000000000000000000000000031",
+        "quote": "0x0000000000000000000000000000000000000005",
+        "trigger_price": 0.5,
+        "amount": 1.0,
+        "wallets": [WALLET],
+        "slippage": 10.0
+    }})).expect("PlaceLimitOrder");
+    testing::push_command(json!({"type": "IgnoreToken", "data": {"address": SPAM}})).expect("IgnoreToken");
+
+    // Снимок пишется фоном - ждём ордер и игнор-лист в файле
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let text = loop {
+        let text = std::fs::read_to_string(&path).unwrap_or_default();
+        if text.contains("\"o1\"") && text.contains(SPAM) { break text; }
+        assert!(Instant::now() < deadline, "ордер не записан в {}", path_str);
+        std::thread::sleep(Duration::from_millis(100));
+    };
+    assert!(!text.contains(KEY), "ключ кошелька в файле сессии");
+
+    // Новый Init читает файл; ордер, отменённый до RestoreSession, возвращается из файла
+    init("reinit", path_str);
+    wait_for("reinit", &["EngineReady"]);
+    assert_eq!(state("ignored-before")["ignored_tokens"], json!([]));
+    testing::push_command(json!({"type": "CancelLimitOrder", "request_id": "cancel", "data": {"id": "o1"}})).expect("CancelLimitOrder");
+    assert_eq!(limit_orders("before"), 0);
+
+    testing::push_command(json!({"type": "RestoreSession", "request_id": "restore", "data": {}})).expect("RestoreSession");
+    let restored = wait_for("restore", &["SessionRestored", "CommandError"]);
+    std::fs::remove_file(&path).ok();
+    assert_eq!(restored["type"], "SessionRestored", "{}", restored);
+    assert_eq!(restored["data"]["limit_orders"], 1, "{}", restored);
+    let after = state("after");
+    assert_eq!(after["limit_orders"], 1);
+    assert_eq!(after["ignored_tokens"], json!([SPAM]));
+}
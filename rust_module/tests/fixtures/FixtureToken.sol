@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+// Минимальный ERC-20 для тестов на форке. fee_bps > 0 - fee-on-transfer:
+// с переводов, где ни одна сторона не владелец, сжигается fee_bps / 10000
+contract FixtureToken {
+    string public name;
+    string public symbol;
+    uint8 public constant decimals = 18;
+    uint public totalSupply;
+    uint public immutable feeBps;
+    address public immutable owner;
+    mapping(address => uint) public balanceOf;
+    mapping(address => mapping(address => uint)) public allowance;
+
+    event Transfer(address indexed from, address indexed to, uint value);
+    event Approval(address indexed owner, address indexed spender, uint value);
+
+    constructor(string memory _name, string memory _symbol, uint _supply, uint _feeBps) {
+        name = _name;
+        symbol = _symbol;
+        feeBps = _feeBps;
+        owner = msg.sender;
+        totalSupply = _supply;
+        balanceOf[msg.sender] = _supply;
+        emit Transfer(address(0), msg.sender, _supply);
+    }
+
+    function approve(address spender, uint value) external returns (bool) {
+        allowance[msg.sender][spender] = value;
+        emit Approval(msg.sender, spender, value);
+        return true;
+    }
+
+    function transfer(address to, uint value) external returns (bool) {
+        _transfer(msg.sender, to, value);
+        return true;
+    }
+
+    function transferFrom(address from, address to, uint value) external returns (bool) {
+        uint allowed = allowance[from][msg.sender];
+        if (allowed != type(uint).max) {
+            require(allowed >= value, "allowance");
+            allowance[from][msg.sender] = allowed - value;
+        }
+        _transfer(from, to, value);
+        return true;
+    }
+
+    function _transfer(address from, address to, uint value) internal {
+        require(balanceOf[from] >= value, "balance");
+        balanceOf[from] -= value;
+        uint fee = (from == owner || to == owner) ? 0 : value * feeBps / 10000;
+        if (fee > 0) {
+            totalSupply -= fee;
+            emit Transfer(from, address(0), fee);
+        }
+        balanceOf[to] += value - fee;
+        emit Transfer(from, to, value - fee);
+    }
+}
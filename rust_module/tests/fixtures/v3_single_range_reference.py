@@ -0,0 +1,113 @@
+# Референсная реализация для tests/fixtures/v3_single_range_reference.json: порт на Python целочисленных
+# TickMath, SqrtPriceMath и SwapMath Uniswap V3 для свапа внутри одного диапазона.
+# Не записи QuoterV2 с сети: кейсы сверяют ядро с независимой реализацией тех же формул.
+# python3 tests/fixtures/v3_single_range_reference.py > tests/fixtures/v3_single_range_reference.json
+import json
+
+Q96 = 1 << 96
+MAX_U256 = (1 << 256) - 1
+MAGIC = [
+    0xfff97272373d413259a46990580e213a, 0xfff2e50f5f656932ef12357cf3c7fdcc, 0xffe5caca7e10e4e61c3624eaa0941cd0,
+    0xffcb9843d60f6159c9db58835c926644, 0xff973b41fa98c081472e6896dfb254c0, 0xff2ea16466c96a3843ec78b326b52861,
+    0xfe5dee046a99a2a811c461f1969c3053, 0xfcbe86c7900a88aedcffc83b479aa3a4, 0xf987a7253ac413176f2b074cf7815e54,
+    0xf3392b0822b70005940c7a398e4b70f3, 0xe7159475a2c29b7443b29c7fa6e889d9, 0xd097f3bdfd2022b8845ad8f792aa5825,
+    0xa9f746462d870fdf8a65dc1f90e061e5, 0x70d869a156d2a1b890bb3df62baf32f7, 0x31be135f97d08fd981231505542fcfa6,
+    0x9aa508b5b7a84e1c677de54f3e99bc9, 0x5d6af8dedb81196699c329225ee604, 0x2216e584f5fa1ea926041bedfe98,
+    0x48a170391f7dc42444e8fa2,
+]
+
+
+def sqrt_ratio_at_tick(tick):
+    abs_tick = abs(tick)
+    ratio = 0xfffcb933bd6fad37aa2d162d1a594001 if abs_tick & 1 else 1 << 128
+    for i, m in enumerate(MAGIC):
+        if abs_tick & (2 << i):
+            ratio = (ratio * m) >> 128
+    if tick > 0:
+        ratio = MAX_U256 // ratio
+    return (ratio >> 32) + (1 if ratio % (1 << 32) else 0)
+
+
+def div_up(a, b):
+    return -(-a // b)
+
+
+def next_sqrt_from_amount0(sqrt_p, liq, amount):
+    numerator1 = liq << 96
+    product = amount * sqrt_p
+    if product <= MAX_U256 and numerator1 + product <= MAX_U256:
+        return div_up(numerator1 * sqrt_p, numerator1 + product)
+    return div_up(numerator1, numerator1 // sqrt_p + amount)
+
+
+def next_sqrt_from_amount1(sqrt_p, liq, amount):
+    return sqrt_p + (amount << 96) // liq
+
+
+def amount0_delta(a, b, liq, up):
+    a, b = min(a, b), max(a, b)
+    n1, n2 = liq << 96, b - a
+    return div_up(div_up(n1 * n2, b), a) if up else n1 * n2 // b // a
+
+
+def amount1_delta(a, b, liq, up):
+    a, b = min(a, b), max(a, b)
+    return div_up(liq * (b - a), Q96) if up else liq * (b - a) // Q96
+
+
+def swap_step(sqrt_p, target, liq, amount, fee):
+    """SwapMath.computeSwapStep, exact input: (sqrt_next, amount_out, дошли ли до target)"""
+    zero_for_one = sqrt_p >= target
+    less_fee = amount * (1_000_000 - fee) // 1_000_000
+    amount_in = amount0_delta(target, sqrt_p, liq, True) if zero_for_one else amount1_delta(sqrt_p, target, liq, True)
+    if less_fee >= amount_in:
+        sqrt_next = target
+    else:
+        sqrt_next = next_sqrt_from_amount0(sqrt_p, liq, less_fee) if zero_for_one else next_sqrt_from_amount1(sqrt_p, liq, less_fee)
+    out = amount1_delta(sqrt_next, sqrt_p, liq, False) if zero_for_one else amount0_delta(sqrt_p, sqrt_next, liq, False)
+    return sqrt_next, out, sqrt_next == target
+
+
+SPACING = {100: 1, 500: 10, 2500: 50, 3000: 60, 10000: 200}
+
+# (tick, смещение цены внутри диапазона в долях шага, ликвидность, fee)
+POOLS = [
+    (0, 0.5, 10**18, 3000),
+    (69_081, 0.3, 3 * 10**21, 500),          # ~1000 token1 за token0
+    (-276_325, 0.6, 2 * 10**17, 500),        # USDC(6)/WETH(18)
+    (-58_300, 0.5, 7 * 10**22, 2500),        # 18/18 с ценой ~0.003
+    (120_000, 0.9, 10**15, 10_000),
+    (-1, 0.5, 5 * 10**20, 100),
+]
+
+cases = []
+for tick, frac, liq, fee in POOLS:
+    spacing = SPACING[fee]
+    lower = (tick // spacing) * spacing
+    lo, hi = sqrt_ratio_at_tick(lower), sqrt_ratio_at_tick(lower + spacing)
+    sqrt_p = lo + int((hi - lo) * frac)
+    # Текущий тик пула - по цене, а не по параметру
+    cur = lower
+    while sqrt_ratio_at_tick(cur + 1) <= sqrt_p:
+        cur += 1
+    for zero_for_one in (True, False):
+        target = lo if zero_for_one else hi
+        # Вход, которого хватает ровно до границы диапазона (с комиссией)
+        full = amount0_delta(target, sqrt_p, liq, True) if zero_for_one else amount1_delta(sqrt_p, target, liq, True)
+        full = full * 1_000_000 // (1_000_000 - fee)
+        for share in (0.001, 0.25, 0.9, 3.0):
+            amount = max(1, int(full * share))
+            sqrt_next, out, reached = swap_step(sqrt_p, target, liq, amount, fee)
+            crosses = share > 1
+            if not crosses:
+                assert not reached
+            else:
+                # За границей ликвидность уже другая - фиксируем только флаг
+                out = None
+            cases.append({
+                "sqrt_price_x96": str(sqrt_p), "liquidity": str(liq), "tick": cur, "tick_spacing": spacing, "fee": fee,
+                "amount_in": str(amount), "zero_for_one": zero_for_one,
+                "amount_out": None if out is None else str(out), "crosses": crosses,
+            })
+
+print(json.dumps(cases, indent=1))
@@ -0,0 +1,229 @@
+// Крайние случаи и инварианты чистой математики пулов (dexbot_core::math)
+use dexbot_core::math::*;
+use ethers::types::{Address, U256};
+use proptest::prelude::*;
+
+fn units(amount: u64, decimals: u32) -> U256 {
+    U256::from(amount) * U256::exp10(decimals as usize)
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= b.abs() * 1e-9
+}
+
+// sqrtPriceX96 для сырой цены token0 в token1 (wei за wei)
+fn sqrt_x96(raw_price: f64) -> U256 {
+    U256::from_dec_str(&format!("{:.0}", raw_price.sqrt() * 2f64.powi(96))).unwrap()
+}
+
+#[test]
+fn u256_to_f64_handles_extreme_decimals() {
+    for decimals in [0u32, 6, 8, 18, 24] {
+        assert!(close(u256_to_f64_safe(units(1234, decimals), decimals), 1234.0), "decimals {}", decimals);
+    }
+    assert_eq!(u256_to_f64_safe(U256::zero(), 18), 0.0);
+    // Больше u128 - без паники
+    assert!(u256_to_f64_safe(U256::MAX, 0) > 1e77);
+    assert!(close(u256_to_f64_safe(U256::MAX, 18), u256_to_f64_safe(U256::MAX, 0) / 1e18));
+    // decimals сверх лимита format_units досчитываются, а не обрезаются
+    assert!(close(u256_to_f64_safe(U256::exp10(76), 80), 1e-4));
+}
+
+#[test]
+fn u256_to_f64_above_u128_and_decimals_bounds() {
+    let above = U256::from(u128::MAX) + U256::one();
+    assert!(close(u256_to_f64_safe(above, 0), 2f64.powi(128)));
+    assert!(close(u256_to_f64_safe(above * U256::from(1000u64), 18), 2f64.powi(128) * 1e-15));
+    // Границы format_units: 0 и 77
+    assert_eq!(u256_to_f64_safe(U256::from(42u64), 0), 42.0);
+    assert!(close(u256_to_f64_safe(U256::exp10(77), 77), 1.0));
+    // Выше 77 - format_units с 77 и досчёт делением
+    assert!(close(u256_to_f64_safe(U256::exp10(77), 78), 0.1));
+    assert!(close(u256_to_f64_safe(U256::exp10(77), 200), 1e-123));
+}
+
+#[test]
+fn pnl_pct_stays_finite_on_dust_cost_basis() {
+    assert!(close(pnl_pct(100.0, 150.0), 50.0));
+    assert!(close(pnl_pct(100.0, 0.0), -100.0));
+    // Пылевой cost basis дал бы 1e14% и больше, вплоть до inf/NaN
+    for basis in [0.0, 1e-300, f64::MIN_POSITIVE, DUST_COST_BASIS, -1.0, f64::NAN] {
+        assert_eq!(pnl_pct(basis, 1.0), 0.0, "cost basis {}", basis);
+    }
+    let above_dust = pnl_pct(DUST_COST_BASIS * 2.0, 1.0);
+    assert!(above_dust.is_finite() && above_dust > 0.0);
+    assert_eq!(pnl_pct(1.0, f64::INFINITY), 0.0);
+}
+
+#[test]
+fn f64_to_u256_truncates_and_rejects_garbage() {
+    assert_eq!(f64_to_u256(1.5, 6), U256::from(1_500_000));
+    assert_eq!(f64_to_u256(1.9, 0), U256::from(1));
+    assert_eq!(f64_to_u256(0.0000001, 6), U256::zero());
+    assert_eq!(f64_to_u256(-1.0, 18), U256::zero());
+    assert_eq!(f64_to_u256(f64::NAN, 18), U256::zero());
+    assert_eq!(f64_to_u256(f64::INFINITY, 18), U256::zero());
+}
+
+#[test]
+fn v2_amount_out_edges() {
+    let r = units(1_000, 18);
+    assert_eq!(v2_amount_out(U256::zero(), r, r, DEFAULT_V2_FEE_BPS), U256::zero());
+    assert_eq!(v2_amount_out(r, U256::zero(), U256::zero(), DEFAULT_V2_FEE_BPS), U256::zero());
+    // Пустой вход пары: любой вход забирает весь резерв выхода, но не больше
+    assert_eq!(v2_amount_out(U256::one(), U256::zero(), r, DEFAULT_V2_FEE_BPS), r);
+    // Вход больше u128 и максимальные резервы не переполняются
+    let huge = U256::from(u128::MAX) * U256::from(1_000u64);
+    assert!(v2_amount_out(huge, U256::MAX, U256::MAX, DEFAULT_V2_FEE_BPS) < U256::MAX);
+    assert!(v2_amount_out(U256::MAX, r, r, DEFAULT_V2_FEE_BPS) < r);
+}
+
+#[test]
+fn v2_amount_out_fee_is_configurable() {
+    let (r_in, r_out, amount) = (units(1_000, 18), units(1_000, 18), units(1, 18));
+    let pancake = v2_amount_out(amount, r_in, r_out, 25);
+    let uniswap = v2_amount_out(amount, r_in, r_out, 30);
+    let no_fee = v2_amount_out(amount, r_in, r_out, 0);
+    assert!(no_fee > pancake && pancake > uniswap);
+    // Эталон Uniswap V2 getAmountOut (997/1000)
+    let reference = amount * U256::from(997) * r_out / (r_in * U256::from(1000) + amount * U256::from(997));
+    assert_eq!(uniswap, reference);
+}
+
+#[test]
+fn net_of_fee_deducts_bps() {
+    assert_eq!(net_of_fee(U256::from(10_000), DEFAULT_ROUTER_TAX_BPS), U256::from(9_990));
+    assert_eq!(net_of_fee(U256::from(10_000), 0), U256::from(10_000));
+    assert_eq!(net_of_fee(U256::from(10_000), 20_000), U256::zero());
+    // Без переполнения на U256::MAX
+    assert!(net_of_fee(U256::MAX, 30) < U256::MAX);
+}
+
+#[test]
+fn oriented_reserves_follow_address_order() {
+    let low = Address::from_low_u64_be(1);
+    let high = Address::from_low_u64_be(2);
+    let reserves = (U256::from(10), U256::from(20));
+    assert_eq!(v2_oriented_reserves(reserves, low, high), (U256::from(10), U256::from(20)));
+    assert_eq!(v2_oriented_reserves(reserves, high, low), (U256::from(20), U256::from(10)));
+}
+
+#[test]
+fn v2_price_with_mixed_decimals_both_orders() {
+    // 1 токен (18 dec) = 2 USDT (6 dec), в пуле 1000 токенов
+    let token_r = units(1_000, 18);
+    let quote_r = units(2_000, 6);
+    let (liq_a, price_a) = v2_liquidity_usd_and_price(quote_r, token_r, 18, 6, true, 1.0);
+    let (liq_b, price_b) = v2_liquidity_usd_and_price(token_r, quote_r, 18, 6, false, 1.0);
+    assert!(close(price_a, 2.0) && close(price_b, 2.0), "{} / {}", price_a, price_b);
+    assert!(close(liq_a, 4_000.0) && close(liq_b, 4_000.0));
+    for (t_dec, q_dec) in [(0u8, 18u8), (8, 6), (24, 18), (18, 24)] {
+        let (_, price) = v2_liquidity_usd_and_price(units(3, q_dec as u32), units(1, t_dec as u32), t_dec, q_dec, true, 1.0);
+        assert!(close(price, 3.0), "dec {}/{}: {}", t_dec, q_dec, price);
+    }
+    assert_eq!(v2_liquidity_usd_and_price(U256::zero(), token_r, 18, 6, true, 1.0), (0.0, 0.0));
+}
+
+#[test]
+fn v3_price_with_mixed_decimals_both_orders() {
+    // Токен 18 dec стоит 2 USDT (6 dec)
+    // token0 = токен: сырая цена token0 в token1 = 2e6 / 1e18
+    let (_, price) = v3_liquidity_usd_and_price(sqrt_x96(2e6 / 1e18), 10u128.pow(18), 18, 6, false, 1.0);
+    assert!((price - 2.0).abs() < 1e-6, "token0 = токен: {}", price);
+    // token0 = USDT: сырая цена token0 в token1 = 1e18 / 2e6
+    let (_, price) = v3_liquidity_usd_and_price(sqrt_x96(1e18 / 2e6), 10u128.pow(18), 18, 6, true, 1.0);
+    assert!((price - 2.0).abs() < 1e-6, "token0 = quote: {}", price);
+    // 8 и 24 decimals
+    let (_, price) = v3_liquidity_usd_and_price(sqrt_x96(5.0 * 1e24 / 1e8), 10u128.pow(18), 8, 24, false, 1.0);
+    assert!((price - 5.0).abs() < 1e-6, "8/24: {}", price);
+    assert_eq!(v3_liquidity_usd_and_price(U256::zero(), 1, 18, 18, true, 1.0), (0.0, 0.0));
+    assert_eq!(v3_liquidity_usd_and_price(sqrt_x96(1.0), 0, 18, 18, true, 1.0), (0.0, 0.0));
+}
+
+#[test]
+fn ideal_out_converts_between_decimals() {
+    // Цена 2 USDT за токен: buy 10 USDT -> 5 токенов, sell 5 токенов -> 10 USDT
+    assert_eq!(ideal_out(units(10, 6), 6, 18, 2.0, true), units(5, 18));
+    assert_eq!(ideal_out(units(5, 18), 18, 6, 2.0, false), units(10, 6));
+    assert_eq!(ideal_out(units(5, 18), 18, 0, 2.0, false), U256::from(10));
+    assert_eq!(ideal_out(U256::zero(), 18, 18, 2.0, true), U256::zero());
+    assert_eq!(ideal_out(units(1, 18), 18, 18, 0.0, true), U256::zero());
+    assert_eq!(ideal_out(units(1, 18), 18, 18, f64::NAN, true), U256::zero());
+}
+
+#[test]
+fn price_impact_bounds() {
+    assert_eq!(price_impact_pct(U256::zero(), U256::from(1), 18), 0.0);
+    assert_eq!(price_impact_pct(U256::from(100), U256::from(150), 18), 0.0);
+    assert!(close(price_impact_pct(units(100, 6), units(90, 6), 6), 10.0));
+}
+
+#[test]
+fn v3_single_range_matches_python_reference_impl() {
+    // Референсная реализация - порт TickMath/SqrtPriceMath/SwapMath на Python (fixtures/v3_single_range_reference.py),
+    // не котировки QuoterV2 с сети
+    let cases: Vec<serde_json::Value> = serde_json::from_str(include_str!("fixtures/v3_single_range_reference.json")).unwrap();
+    let dec = |v: &serde_json::Value| U256::from_dec_str(v.as_str().unwrap()).unwrap();
+    for case in &cases {
+        let (out, crosses) = v3_single_range_out(
+            dec(&case["sqrt_price_x96"]),
+            case["liquidity"].as_str().unwrap().parse().unwrap(),
+            case["tick"].as_i64().unwrap() as i32,
+            case["tick_spacing"].as_i64().unwrap() as i32,
+            case["fee"].as_u64().unwrap() as u32,
+            dec(&case["amount_in"]),
+            case["zero_for_one"].as_bool().unwrap(),
+        ).expect("оценка");
+        assert_eq!(crosses, case["crosses"].as_bool().unwrap(), "{}", case);
+        if !crosses {
+            assert_eq!(out, dec(&case["amount_out"]), "{}", case);
+        }
+    }
+}
+
+#[test]
+fn v3_single_range_edges() {
+    let sqrt = U256::one() << 96;
+    assert_eq!(v3_single_range_out(U256::zero(), 1, 0, 60, 3000, U256::one(), true), None);
+    assert_eq!(v3_single_range_out(sqrt, 0, 0, 60, 3000, U256::one(), true), None);
+    assert_eq!(v3_single_range_out(sqrt, 1, 0, 0, 3000, U256::one(), true), None);
+    // Вход целиком уходит в комиссию
+    assert_eq!(v3_single_range_out(sqrt, 10u128.pow(18), 0, 60, 3000, U256::one(), true), Some((U256::zero(), false)));
+    // Вход больше u128 не переполняет и помечается как пересечение
+    let (_, crosses) = v3_single_range_out(sqrt, 10u128.pow(18), 0, 60, 3000, U256::MAX, true).unwrap();
+    assert!(crosses);
+    assert_eq!(v3_tick_spacing(2500), Some(50));
+    assert_eq!(v3_tick_spacing(1234), None);
+}
+
+fn any_u256() -> impl Strategy<Value = U256> {
+    any::<[u64; 4]>().prop_map(U256)
+}
+
+proptest! {
+    #[test]
+    fn v2_out_below_reserve(amount in any_u256(), r_in in any_u256(), r_out in any_u256(), fee in 0u32..10_000) {
+        prop_assert!(v2_amount_out(amount, r_in, r_out, fee) <= r_out);
+    }
+
+    #[test]
+    fn v2_out_monotonic_in_amount(a in any::<u128>(), b in any::<u128>(), r_in in 1u128.., r_out in any::<u128>(), fee in 0u32..1_000) {
+        let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+        let (r_in, r_out) = (U256::from(r_in), U256::from(r_out));
+        prop_assert!(v2_amount_out(U256::from(lo), r_in, r_out, fee) <= v2_amount_out(U256::from(hi), r_in, r_out, fee));
+    }
+
+    #[test]
+    fn v2_out_decreases_with_fee(amount in any::<u128>(), r_in in 1u128.., r_out in any::<u128>(), fee in 0u32..9_999) {
+        let (amount, r_in, r_out) = (U256::from(amount), U256::from(r_in), U256::from(r_out));
+        prop_assert!(v2_amount_out(amount, r_in, r_out, fee + 1) <= v2_amount_out(amount, r_in, r_out, fee));
+    }
+
+    #[test]
+    fn v2_price_independent_of_token_order(q in 1u64.., t in 1u64.., t_dec in 0u8..=24, q_dec in 0u8..=24) {
+        let (q, t) = (U256::from(q), U256::from(t));
+        let a = v2_liquidity_usd_and_price(q, t, t_dec, q_dec, true, 1.0);
+        let b = v2_liquidity_usd_and_price(t, q, t_dec, q_dec, false, 1.0);
+        prop_assert_eq!(a, b);
+    }
+}
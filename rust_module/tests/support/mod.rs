@@ -0,0 +1,331 @@
+// Общая обвязка E2E тестов на форке: Anvil, фикстуры токена и V2 пары, ожидание событий ядра.
+// Переменные окружения:
+//   ANVIL_RPC_URL   - уже запущенный форк (http); ws берётся тем же адресом со схемой ws
+//   ANVIL_FORK_URL  - апстрим для своего anvil (по умолчанию rpc_url из networks/<ANVIL_NETWORK>.json)
+//   ANVIL_NETWORK   - конфиг сети из networks/ (по умолчанию bsc)
+//   ANVIL_PATH      - путь к бинарнику anvil, SOLC_PATH - к solc
+#![allow(dead_code)]
+
+use dexbot_core::testing;
+use ethers::prelude::*;
+use ethers::solc::Solc;
+use ethers::utils::{Anvil, AnvilInstance};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+abigen!(
+    IFixtureFactory,
+    r#"[
+        function createPair(address tokenA, address tokenB) external returns (address pair)
+        function getPair(address tokenA, address tokenB) external view returns (address pair)
+    ]"#;
+
+    IFixturePair,
+    r#"[
+        function mint(address to) external returns (uint liquidity)
+        function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast)
+    ]"#;
+
+    IFixtureErc20,
+    r#"[
+        function balanceOf(address owner) external view returns (uint)
+        function allowance(address owner, address spender) external view returns (uint)
+        function approve(address spender, uint value) external returns (bool)
+        function transfer(address to, uint value) external returns (bool)
+        function deposit() external payable
+    ]"#;
+);
+
+// Ключи мнемоники anvil по умолчанию: ими же засеян форк, запущенный извне
+const ANVIL_KEYS: [&str; 5] = [
+    "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+    "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+    "5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cdab365a",
+    "7c852118294e51e653712a81e05800f419141751be58f605c371e15141b007a6",
+    "47e179ec197488593b187f80a00eb0da91f1b9d0b13f8733639f19c30a34926a",
+];
+
+pub type Client = SignerMiddleware<Provider<Http>, LocalWallet>;
+
+/// Адреса сети из networks/<name>.json, нужные фикстурам и Init
+pub struct NetworkFixture {
+    pub router: Address,
+    pub quoter: Address,
+    pub v2_factory: Address,
+    pub v3_factory: Address,
+    pub wrapped_native: Address,
+    pub native_address: Address,
+    pub quote_symbol: String,
+    pub rpc_url: String,
+}
+
+impl NetworkFixture {
+    fn load(name: &str) -> Self {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../networks").join(format!("{}.json", name));
+        let raw = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        let cfg: Value = serde_json::from_str(&raw).expect("networks json");
+        let addr = |key: &str| cfg[key].as_str().and_then(|s| s.parse().ok()).unwrap_or_else(|| panic!("{}: нет {}", name, key));
+        let quote_symbol = cfg["default_quote_currency"].as_str().unwrap_or("WETH").to_string();
+        let wrapped_native = cfg["quote_tokens"][&quote_symbol].as_str().and_then(|s| s.parse().ok()).expect("wrapped native");
+        Self {
+            router: addr("dex_router_address"),
+            quoter: addr("v3_quoter_address"),
+            v2_factory: addr("v2_factory_address"),
+            v3_factory: addr("v3_factory_address"),
+            wrapped_native,
+            native_address: addr("native_currency_address"),
+            quote_symbol,
+            rpc_url: cfg["rpc_url"].as_str().unwrap_or_default().to_string(),
+        }
+    }
+}
+
+/// Форк и проинициализированное на нём ядро - один на процесс тестов
+pub struct Fork {
+    _anvil: Option<AnvilInstance>,
+    pub http_url: String,
+    pub ws_url: String,
+    pub chain_id: u64,
+    pub net: NetworkFixture,
+    pub wallets: Vec<LocalWallet>,
+}
+
+static FORK: OnceLock<Fork> = OnceLock::new();
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+// События, прочитанные из моста, но ещё не востребованные тестом
+static BACKLOG: Lazy<Mutex<Vec<Value>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Состояние ядра общее - тесты идут строго по одному
+pub async fn serial() -> tokio::sync::MutexGuard<'static, ()> {
+    SERIAL.lock().await
+}
+
+pub fn fork() -> &'static Fork {
+    FORK.get_or_init(Fork::start)
+}
+
+impl Fork {
+    fn start() -> Self {
+        let net = NetworkFixture::load(&std::env::var("ANVIL_NETWORK").unwrap_or_else(|_| "bsc".into()));
+        let (anvil, http_url, ws_url) = match std::env::var("ANVIL_RPC_URL") {
+            Ok(url) => {
+                let ws = url.replacen("http", "ws", 1);
+                (None, url, ws)
+            }
+            Err(_) => {
+                let upstream = std::env::var("ANVIL_FORK_URL").unwrap_or_else(|_| net.rpc_url.clone());
+                let mut anvil = Anvil::new().fork(upstream);
+                if let Ok(path) = std::env::var("ANVIL_PATH") {
+                    anvil = anvil.path(path);
+                }
+                let instance = anvil.spawn();
+                let (http, ws) = (instance.endpoint(), instance.ws_endpoint());
+                (Some(instance), http, ws)
+            }
+        };
+
+        let chain_id = match &anvil {
+            Some(instance) => instance.chain_id(),
+            None => query_chain_id(http_url.clone()),
+        };
+        let wallets: Vec<LocalWallet> = ANVIL_KEYS.iter()
+            .map(|k| k.parse::<LocalWallet>().expect("anvil key").with_chain_id(chain_id))
+            .collect();
+
+        let fork = Self { _anvil: anvil, http_url, ws_url, chain_id, net, wallets };
+        fork.init_engine();
+        fork
+    }
+
+    fn init_engine(&self) {
+        let wallets: Vec<(String, String)> = self.wallets.iter().zip(ANVIL_KEYS)
+            .map(|(w, k)| (format!("{:?}", w.address()), format!("0x{}", k)))
+            .collect();
+        let mut quote_tokens = serde_json::Map::new();
+        quote_tokens.insert(self.net.quote_symbol.clone(), json!(format!("{:?}", self.net.wrapped_native)));
+        send(json!({"type": "Init", "data": {
+            "rpc_url": self.http_url,
+            "wss_url": self.ws_url,
+            "chain_id": self.chain_id,
+            "router": format!("{:?}", self.net.router),
+            "quoter": format!("{:?}", self.net.quoter),
+            "v2_factory": format!("{:?}", self.net.v2_factory),
+            "v3_factory": format!("{:?}", self.net.v3_factory),
+            "wrapped_native": format!("{:?}", self.net.wrapped_native),
+            "native_address": format!("{:?}", self.net.native_address),
+            "wallets": wallets,
+            "public_rpc_urls": [],
+            "fuel_settings": {},
+            "quote_symbol": self.net.quote_symbol,
+            "quote_tokens": quote_tokens,
+            "max_block_lag": 0
+        }}));
+        wait_blocking(|e| e["type"] == "EngineReady", Duration::from_secs(60)).expect("EngineReady");
+        // Тесты проверяют approve в самой продаже, фоновый approve при подписке его бы опередил
+        send(json!({"type": "UpdateSettings", "data": { "auto_approve": false }}));
+    }
+
+    pub fn client(&self, wallet: usize) -> Arc<Client> {
+        let provider = Provider::<Http>::try_from(self.http_url.as_str()).expect("http url");
+        Arc::new(SignerMiddleware::new(provider, self.wallets[wallet].clone()))
+    }
+
+    pub fn address(&self, wallet: usize) -> Address {
+        self.wallets[wallet].address()
+    }
+
+    /// Деплоит FixtureToken от кошелька 0 (владелец освобождён от fee)
+    pub async fn deploy_token(&self, symbol: &str, fee_bps: u64) -> Address {
+        let source = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/FixtureToken.sol");
+        let output = Solc::default().compile_source(&source).expect("solc");
+        let (abi, bytecode, _) = output.find("FixtureToken").expect("FixtureToken artifact").into_parts_or_default();
+        let factory = ContractFactory::new(abi, bytecode, self.client(0));
+        let supply = U256::exp10(27);
+        let contract = factory
+            .deploy((format!("Fixture {}", symbol), symbol.to_string(), supply, U256::from(fee_bps)))
+            .expect("constructor args")
+            .send()
+            .await
+            .expect("deploy FixtureToken");
+        contract.address()
+    }
+
+    /// Создаёт V2 пару token/wrapped native на форкнутой фабрике и кладёт в неё ликвидность
+    pub async fn seed_v2_pair(&self, token: Address, token_amount: U256, native_amount: U256) -> Address {
+        let client = self.client(0);
+        let factory = IFixtureFactory::new(self.net.v2_factory, client.clone());
+        factory.create_pair(token, self.net.wrapped_native).send().await.expect("createPair").await.expect("createPair receipt");
+        let pair = factory.get_pair(token, self.net.wrapped_native).call().await.expect("getPair");
+
+        let weth = IFixtureErc20::new(self.net.wrapped_native, client.clone());
+        weth.deposit().value(native_amount).send().await.expect("deposit").await.expect("deposit receipt");
+        weth.transfer(pair, native_amount).send().await.expect("weth transfer").await.expect("weth receipt");
+        IFixtureErc20::new(token, client.clone())
+            .transfer(pair, token_amount).send().await.expect("token transfer").await.expect("token receipt");
+        IFixturePair::new(pair, client).mint(self.address(0)).send().await.expect("mint").await.expect("mint receipt");
+        pair
+    }
+
+    /// Wrapped native на кошелёк и approve роутеру: buy идёт через quote
+    pub async fn fund_quote(&self, wallet: usize, amount: U256) {
+        let weth = IFixtureErc20::new(self.net.wrapped_native, self.client(wallet));
+        weth.deposit().value(amount).send().await.expect("deposit").await.expect("deposit receipt");
+        weth.approve(self.net.router, U256::MAX).send().await.expect("approve").await.expect("approve receipt");
+    }
+
+    pub async fn balance_of(&self, token: Address, owner: Address) -> U256 {
+        IFixtureErc20::new(token, self.client(0)).balance_of(owner).call().await.expect("balanceOf")
+    }
+
+    pub async fn allowance(&self, token: Address, owner: Address) -> U256 {
+        IFixtureErc20::new(token, self.client(0)).allowance(owner, self.net.router).call().await.expect("allowance")
+    }
+
+    pub async fn tx_nonce(&self, hash: &str) -> u64 {
+        let hash: TxHash = hash.parse().expect("tx hash");
+        let tx = self.client(0).get_transaction(hash).await.expect("getTransaction").expect("tx exists");
+        tx.nonce.as_u64()
+    }
+
+    /// Подписка ядра на token/wrapped native; возвращает PoolDetected
+    pub async fn subscribe(&self, token: Address) -> Value {
+        send(json!({"type": "AddTokenSubscription", "data": {
+            "token_address": format!("{:?}", token),
+            "quote_address": format!("{:?}", self.net.wrapped_native),
+            "quote_symbol": self.net.quote_symbol
+        }}));
+        let token = format!("{:?}", token);
+        wait_for(|e| e["type"] == "PoolDetected" && same_addr(&e["data"]["token"], &token), Duration::from_secs(30))
+            .await
+            .expect("PoolDetected")
+    }
+
+    /// ExecuteTrade одним кошельком в фиксированном режиме проскальзывания
+    pub fn trade(&self, action: &str, token: Address, wallet: usize, amount: f64, percent: Option<f64>, slippage: f64) {
+        send(json!({"type": "ExecuteTrade", "data": {
+            "action": action,
+            "token": format!("{:?}", token),
+            "quote_token": format!("{:?}", self.net.wrapped_native),
+            "amount": amount,
+            "wallets": [format!("{:?}", self.address(wallet))],
+            "gas_gwei": 3.0,
+            "slippage": slippage,
+            "percent": percent,
+            "slippage_mode": "fixed"
+        }}));
+    }
+
+    /// Итог свапа кошелька: TradeStatus Sent -> TxConfirmed, либо отказ до отправки
+    pub async fn trade_outcome(&self, wallet: usize, token: Address) -> Outcome {
+        let (wallet, token) = (format!("{:?}", self.address(wallet)), format!("{:?}", token));
+        let status = wait_for(
+            |e| e["type"] == "TradeStatus" && same_addr(&e["data"]["wallet"], &wallet) && same_addr(&e["data"]["token_address"], &token),
+            Duration::from_secs(60)
+        ).await.expect("TradeStatus");
+        let data = &status["data"];
+        let Some(hash) = data["tx_hash"].as_str().map(str::to_string) else {
+            return Outcome::Rejected(data["message"].as_str().unwrap_or_default().to_string());
+        };
+        let confirmed = wait_for(
+            |e| e["type"] == "TxConfirmed" && e["data"]["tx_hash"].as_str() == Some(hash.as_str()),
+            Duration::from_secs(60)
+        ).await.expect("TxConfirmed");
+        match confirmed["data"]["status"].as_str() {
+            Some("success") => Outcome::Confirmed(hash),
+            _ => Outcome::Reverted(hash),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Outcome {
+    Confirmed(String),
+    Reverted(String),
+    Rejected(String),
+}
+
+pub fn send(command: Value) {
+    testing::push_command(command).expect("command json");
+}
+
+fn same_addr(value: &Value, addr: &str) -> bool {
+    value.as_str().is_some_and(|v| v.eq_ignore_ascii_case(addr))
+}
+
+fn take_matching(pred: &impl Fn(&Value) -> bool) -> Option<Value> {
+    let mut backlog = BACKLOG.lock().unwrap_or_else(|e| e.into_inner());
+    backlog.extend(testing::pop_events(1024));
+    let idx = backlog.iter().position(pred)?;
+    Some(backlog.remove(idx))
+}
+
+/// Ждёт первое событие под условие; остальные остаются в backlog для следующих ожиданий
+pub async fn wait_for(pred: impl Fn(&Value) -> bool, limit: Duration) -> Option<Value> {
+    let start = Instant::now();
+    while start.elapsed() < limit {
+        if let Some(ev) = take_matching(&pred) { return Some(ev); }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    None
+}
+
+fn wait_blocking(pred: impl Fn(&Value) -> bool, limit: Duration) -> Option<Value> {
+    let start = Instant::now();
+    while start.elapsed() < limit {
+        if let Some(ev) = take_matching(&pred) { return Some(ev); }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    None
+}
+
+// Запуск форка синхронный (OnceLock) и идёт внутри runtime теста - RPC уходит в свой поток
+fn query_chain_id(http_url: String) -> u64 {
+    std::thread::spawn(move || {
+        let provider = Provider::<Http>::try_from(http_url.as_str()).expect("http url");
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().expect("runtime");
+        runtime.block_on(provider.get_chainid()).expect("chain id").as_u64()
+    }).join().expect("chain id thread")
+}
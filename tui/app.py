@@ -130,6 +130,9 @@ class TxStatusTracker:
         
         return result
     
+    def drop_tx(self, tx_hash: str) -> Optional[Dict[str, Any]]:
+        return self._pending_txs.pop(tx_hash.lower(), None)
+    
     def get_position(self, wallet: str, token: str) -> List[Dict[str, Any]]:
         position_key = f"{wallet.lower()}:{token.lower()}"
         return self._positions.get(position_key,[])
@@ -199,10 +202,14 @@ class StatusGas(Static):
         self.update(f"⛽ Gas: [bold {color}]{gas_gwei:.1f} Gwei[/]")
 
 class StatusConnection(Static):
-    def update_content(self, connected: bool, message: str = ""):
-        icon = "🟢" if connected else "🔴"
-        status = "WS: Connected" if connected else f"WS: {message[:15]}"
-        color = "green" if connected else "red"
+    def update_content(self, connected: bool, message: str = "", polling: bool = False):
+        if polling:
+            # WSS недоступен - данные идут HTTP опросом, с задержкой до блока
+            icon, status, color = ("🟡" if connected else "🔴"), "HTTP: Polling", "yellow"
+        else:
+            icon = "🟢" if connected else "🔴"
+            status = "WS: Connected" if connected else f"WS: {message[:15]}"
+            color = "green" if connected else "red"
         self.update(f"{icon} [{color}]{status}[/]")
 
 # ===================== ГЛАВНОЕ ПРИЛОЖЕНИЕ =====================
@@ -266,6 +273,7 @@ class TradingApp(App):
         self._current_pool_info: Dict[str, Any] = {}
         self._current_token_address: Optional[str] = None
         self._current_quote_address: Optional[str] = None
+        self._subscribed_token_address: Optional[str] = None  # TUI торгует одним токеном за раз
         
         self._market_data: Dict[str, Any] = self._get_empty_market_data()
         self._balance_cache: Dict[str, Dict[str, float]] = {}
@@ -291,8 +299,31 @@ class TradingApp(App):
             "ImpactUpdate": self._evt_impact_update,
             "TxSent": self._evt_tx_sent,
             "TxConfirmed": self._evt_tx_confirmed,
+            "TxDropped": self._evt_tx_dropped,
+            "TxStuck": self._evt_tx_stuck,
             "TradeStatus": self._handle_trade_status,
             "AutoFuelError": self._evt_autofuel_error,
+            "TradingPaused": self._evt_trading_paused,
+            "TradingResumed": self._evt_trading_resumed,
+            "SweepSummary": self._evt_sweep_summary,
+            "RpcNodeQuarantined": self._evt_rpc_quarantined,
+            "TokenInfo": self._evt_token_info,
+            "UsdPriceUpdate": self._evt_usd_price_update,
+            "WatchedAddressActivity": self._evt_watched_activity,
+            "HolderScanProgress": self._evt_holder_progress,
+            "HolderReport": self._evt_holder_report,
+            "HistoryImportProgress": self._evt_history_progress,
+            "TokenAudit": self._evt_token_audit,
+            "PortfolioPnL": self._evt_portfolio_pnl,
+            "PositionUpdate": self._evt_position_update,
+            "SandwichSuspected": self._evt_sandwich_suspected,
+            "ApprovalStatus": self._evt_approval_status,
+            "BundlePrepared": self._evt_bundle_prepared,
+            "BundleFired": self._evt_bundle_fired,
+            "BundleInvalidated": self._evt_bundle_invalidated,
+            "SnipeArmed": self._evt_snipe_armed,
+            "SnipeFired": self._evt_snipe_fired,
+            "SnipeAborted": self._evt_snipe_aborted,
             "Log": self._evt_log
         }
 
@@ -484,10 +515,15 @@ class TradingApp(App):
     async def _evt_connection_status(self, data: dict):
         connected = data.get("connected", False)
         message = data.get("message", "")
-        self._update_status_widget(StatusConnection, connected, message)
+        polling = data.get("polling", False)
+        self._update_status_widget(StatusConnection, connected, message, polling)
         self._update_status_widget(StatusRPC, "OK" if connected else "ERROR", connected)
         
-        if connected: 
+        if polling:
+            await log.warning(f"<yellow>[WS]</yellow> Режим HTTP polling: {message}")
+            if connected:
+                self.notify("🟡 WSS недоступен - HTTP polling", severity="warning")
+        elif connected: 
             await log.success(f"<green>[WS]</green> WebSocket подключен: {message}")
             self.notify("🔌 WebSocket подключен", severity="information")
         else: 
@@ -564,8 +600,9 @@ class TradingApp(App):
                 )
         
         await self._update_token_pair_display()
-        await log.success(f"<green>[POOL]</green> {data.get('pool_type')} found: {data.get('address', '')[:10]}... TVL=${data.get('liquidity_usd', 0):,.0f}")
-        self.notify(f"🏊 Пул: {data.get('pool_type')} TVL ${data.get('liquidity_usd', 0):,.0f}", severity="information", timeout=4)
+        via = f" via {data['via']}" if data.get('via') else ""
+        await log.success(f"<green>[POOL]</green> {data.get('pool_type')}{via} found: {data.get('address', '')[:10]}... TVL=${data.get('liquidity_usd', 0):,.0f}")
+        self.notify(f"🏊 Пул: {data.get('pool_type')}{via} TVL ${data.get('liquidity_usd', 0):,.0f}", severity="information", timeout=4)
         
     async def _evt_pool_error(self, data: dict):
         await log.error(f"[POOL_ERROR] FULL DATA: {data}")
@@ -639,11 +676,11 @@ class TradingApp(App):
         
         is_buy = data.get('is_buy', True)
         impact_pct = data.get('impact_pct', 0.0)
+        side = 'impact_buy' if is_buy else 'impact_sell'
 
-        if is_buy: 
-            self._market_data['impact_buy'] = impact_pct
-        else: 
-            self._market_data['impact_sell'] = impact_pct
+        self._market_data[side] = impact_pct
+        # Локальная оценка с пересечением тика - показываем "~"
+        self._market_data[f'{side}_estimate'] = bool(data.get('is_estimate', False))
 
         self.ui_update_queue.put_nowait("refresh_market_data")
 
@@ -654,6 +691,9 @@ class TradingApp(App):
         action = data.get('action', '')
         amount = data.get('amount', 0)
         token = data.get('token', '')
+        route = data.get('route', 'public')
+        if route != 'public':
+            await log.info(f"🕶️ TX {tx_hash[:16]}... отправлена через {route}")
         
         # === DEBUG: Что пришло из Rust ===
         #await log.debug(f"[TX_SENT] INCOMING | tx_hash={tx_hash[:16] if tx_hash else 'None'}... | action='{action}' | wallet={wallet[:10] if wallet else 'None'}... | token={token[:10] if token else 'None'}... | amount={amount}")
@@ -678,6 +718,8 @@ class TradingApp(App):
         tx_hash = data.get('tx_hash', '')
         status_raw = data.get('status', '')
         gas_used = data.get('gas_used', 0)
+        fee_usd = data.get('fee_usd')
+        fee_text = f"Fee: {data.get('fee_native_float', 0.0):.6f}" + (f" (${fee_usd:.2f})" if fee_usd is not None else "")
         
         # Нормализуем status
         status = status_raw.lower() if isinstance(status_raw, str) else str(status_raw)
@@ -709,7 +751,7 @@ class TradingApp(App):
             #short_wallet = self._short_wallet(wallet) if wallet else "???"
             
             if status == "success":
-                await log.success(f"<green>[TX CONFIRMED]</green> {action_ru} | Latency: {latency_ms:.0f}ms")
+                await log.success(f"<green>[TX CONFIRMED]</green> {action_ru} | Latency: {latency_ms:.0f}ms | {fee_text}")
                 self.notify(f"{action_emoji} {action_ru} успешна!\nLatency: {latency_ms:.0f}ms", severity="information", title=f"{action_ru}")
                 
                 # === DEBUG: Проверка условия закрытия позиции ===
@@ -736,16 +778,175 @@ class TradingApp(App):
                 #else:
                 #    await log.debug(f"[TX_CONFIRMED] NOT CLOSING POSITION | reason: action='{action}' (need 'sell'), wallet={'SET' if wallet else 'EMPTY'}, token={'SET' if token else 'EMPTY'}")
             else:
-                await log.error(f"<red>[TX FAILED]</red> {action_ru} | Latency: {latency_ms:.0f}ms")
+                await log.error(f"<red>[TX FAILED]</red> {action_ru} | Latency: {latency_ms:.0f}ms | {fee_text}")
                 self.notify(f"❌ {action_ru} ошибка!\nLatency: {latency_ms:.0f}ms", severity="error", title=f"{action_ru}")
         else:
             # Транзакция не найдена в трекере - возможно перезапуск или пропущенный TxSent
             await log.warning(f"<yellow>[TX_CONFIRMED]</yellow> tx_hash={tx_hash[:16] if tx_hash else 'None'}... NOT FOUND in tracker (restart or missed TxSent?)")
 
+    async def _evt_tx_dropped(self, data: dict):
+        tx_hash = data.get("tx_hash", "")
+        self._tx_tracker.drop_tx(tx_hash)
+        await log.warning(f"<yellow>[TX DROPPED]</yellow> {tx_hash[:16]}... пропала из мемпула через {data.get('age_secs', 0)}с")
+        self.notify(f"🕳️ Транзакция пропала из мемпула\n{tx_hash[:16]}...", severity="warning", title="TX", timeout=20)
+
+    async def _evt_tx_stuck(self, data: dict):
+        tx_hash = data.get("tx_hash", "")
+        await log.warning(f"<yellow>[TX STUCK]</yellow> {tx_hash[:16]}... висит {data.get('age_secs', 0)}с | nonce={data.get('nonce')} | gas={data.get('gas_price_gwei', 0):.2f} gwei")
+        self.notify(f"🐌 Транзакция висит {data.get('age_secs', 0)}с - поднимите газ и повторите", severity="warning", title="TX", timeout=20)
+
     async def _evt_autofuel_error(self, data: dict):
         reason = data.get("reason", "unknown_error")
         self.notify(f"⛽ Ошибка автозакупки газа: {reason}", severity="error", timeout=20)
 
+    async def _evt_rpc_quarantined(self, data: dict):
+        reason = data.get("reason", "")
+        self.notify(f"🚫 RPC выведен из пула: {reason}", severity="error", title="RPC", timeout=20)
+
+    async def _evt_token_info(self, data: dict):
+        symbol = data.get("symbol") or "?"
+        await log.info(f"<cyan>[TOKEN]</cyan> {symbol} ({data.get('name', '')}) | decimals={data.get('decimals')} | supply={data.get('total_supply')} | {data.get('address')}")
+
+    async def _evt_usd_price_update(self, data: dict):
+        # Цена от фида ядра - держим кэш TUI в синхроне с usd_prices
+        symbol = data.get("symbol")
+        price = data.get("price")
+        if symbol and price:
+            self.cache.set_quote_price(symbol, float(price))
+
+    async def _evt_watched_activity(self, data: dict):
+        direction = data.get("direction", "")
+        label = data.get("label", "")
+        msg = f"{label}: {direction} {data.get('amount', 0):.4f} {data.get('token', '')}"
+        await log.info(f"<magenta>[WATCH]</magenta> {msg} | tx {data.get('tx_hash', '')}")
+        if direction in ("buy", "sell"):
+            self.notify(f"🐋 {msg}", severity="information", title="Watchlist")
+
+    async def _evt_holder_progress(self, data: dict):
+        total = data.get("total_blocks") or 1
+        pct = data.get("scanned_blocks", 0) / total * 100
+        await log.debug(f"<cyan>[HOLDERS]</cyan> {data.get('token')}: {pct:.0f}% блоков, {data.get('transfers', 0)} переводов")
+
+    async def _evt_history_progress(self, data: dict):
+        total = data.get("total_blocks") or 1
+        pct = data.get("scanned_blocks", 0) / total * 100
+        await log.debug(f"<cyan>[HISTORY]</cyan> {data.get('wallet')} / {data.get('token')}: {pct:.0f}% блоков, {data.get('transfers', 0)} переводов")
+
+    async def _evt_holder_report(self, data: dict):
+        top = data.get("top_holders") or []
+        top5_pct = sum(h[2] for h in top[:5])
+        partial = "" if data.get("complete") else " (неполный скан)"
+        await log.info(
+            f"<cyan>[HOLDERS]</cyan> {data.get('token')}: держателей {data.get('holder_count')}{partial} | "
+            f"топ-5 {top5_pct:.1f}% | LP {data.get('lp_pct', 0):.1f}% | сожжено {data.get('burned_pct', 0):.1f}%"
+        )
+        for address, balance, pct in top:
+            await log.info(f"    {address} {balance:,.2f} ({pct:.2f}%)")
+
+    async def _evt_token_audit(self, data: dict):
+        flags = data.get("flags") or []
+        red = {"mint", "blacklist", "proxy", "lp_unlocked"}
+        if red.intersection(flags):
+            badge, severity = "🔴", "error"
+        elif any(f not in ("owner_unknown", "lp_unknown") for f in flags):
+            badge, severity = "🟡", "warning"
+        else:
+            badge, severity = "🟢", "information"
+        owner = "renounced" if data.get("ownership_renounced") else (data.get("owner") or "?")
+        lp = data.get("lp_locked_pct")
+        lp_text = f"{lp:.0f}%" if lp is not None else "?"
+        msg = f"{badge} {data.get('token')}: {', '.join(flags) or 'чисто'} | owner {owner} | LP locked {lp_text}"
+        await log.info(f"<cyan>[AUDIT]</cyan> {msg}")
+        self.notify(msg, severity=severity, title="Token audit", timeout=10)
+
+    async def _evt_position_update(self, data: dict):
+        unrealized = data.get("unrealized_pnl")
+        unrealized_text = f"{unrealized:+.6f}" if unrealized is not None else "?"
+        await log.info(
+            f"<cyan>[POSITION{' · SIM' if data.get('simulated') else ''}]</cyan> {self._short_wallet(data.get('wallet', ''))} {data.get('token')}: "
+            f"{data.get('quantity', 0):,.4f} @ {data.get('avg_cost', 0):.10f} | "
+            f"realized {data.get('realized_pnl', 0):+.6f} | unrealized {unrealized_text}"
+        )
+
+    async def _evt_bundle_prepared(self, data: dict):
+        nonces = data.get("nonces") or {}
+        await log.info(
+            f"<cyan>[BUNDLE]</cyan> {data.get('id')}: {data.get('action')} {data.get('token')} — "
+            f"подписано {len(nonces)} tx, дедлайн {data.get('expires_at')}"
+        )
+
+    async def _evt_bundle_fired(self, data: dict):
+        await log.success(
+            f"<green>[BUNDLE]</green> {data.get('id')}: отправлено {data.get('sent', 0)}, "
+            f"ошибок {data.get('failed', 0)} за {data.get('elapsed_ms', 0)} мс"
+        )
+
+    async def _evt_bundle_invalidated(self, data: dict):
+        await log.warning(f"<yellow>[BUNDLE]</yellow> {data.get('id')} снят: {data.get('reason')}")
+        self.notify(f"📦 Бандл {data.get('id')} снят: {data.get('reason')}", severity="warning", title="Bundle")
+
+    async def _evt_snipe_armed(self, data: dict):
+        await log.info(
+            f"<cyan>[SNIPE]</cyan> {data.get('token')}: взведён на {data.get('wallets', 0)} кошельков, "
+            f"снятие в {data.get('expires_at')}"
+        )
+
+    async def _evt_snipe_fired(self, data: dict):
+        tax = data.get("buy_tax_pct")
+        tax_text = f"{tax:.1f}%" if tax is not None else "не проверен"
+        await log.success(
+            f"<green>[SNIPE]</green> {data.get('token')}: ликвидность ${data.get('liquidity_usd', 0):,.0f} "
+            f"в {data.get('pool')}, налог {tax_text}"
+        )
+        self.notify(f"🎯 Снайп {data.get('token')} разослан", severity="information", title="Snipe")
+
+    async def _evt_snipe_aborted(self, data: dict):
+        await log.warning(f"<yellow>[SNIPE]</yellow> {data.get('token')} снят: {data.get('reason')}")
+        self.notify(f"🎯 Снайп снят: {data.get('reason')}", severity="warning", title="Snipe")
+
+    async def _evt_approval_status(self, data: dict):
+        allowance = "unlimited" if data.get("is_unlimited") else data.get("allowance_wei", "0")
+        await log.info(
+            f"<cyan>[APPROVAL]</cyan> {self._short_wallet(data.get('wallet', ''))} {data.get('token')} → "
+            f"{data.get('spender')}: {allowance}"
+        )
+
+    async def _evt_sandwich_suspected(self, data: dict):
+        backrun = data.get("backrun_tx")
+        suffix = f", back-run {backrun}" if backrun else ""
+        await log.warning(
+            f"<yellow>[SANDWICH]</yellow> {self._short_wallet(data.get('wallet', ''))} tx {data.get('tx_hash')}: "
+            f"front-run {data.get('frontrun_tx')} в блоке {data.get('block')}{suffix}"
+        )
+        self.notify("🥪 Возможный sandwich", severity="warning", title="Anti-sandwich", timeout=10)
+
+    async def _evt_portfolio_pnl(self, data: dict):
+        quotes = " | ".join(
+            f"{q.get('quote_symbol') or q.get('quote')}: {q.get('current_value', 0):.4f} ({q.get('pnl_pct', 0):+.2f}%)"
+            for q in data.get("quotes") or []
+        )
+        pending = data.get("pending", 0)
+        suffix = f" | загрузка: {pending}" if pending else ""
+        await log.info(
+            f"<cyan>[PORTFOLIO]</cyan> ${data.get('current_value_usd', 0):,.2f} ({data.get('pnl_pct', 0):+.2f}%, "
+            f"net {data.get('net_pnl_pct', 0):+.2f}% после газа ${data.get('gas_cost_usd', 0):,.2f}) | {quotes}{suffix}"
+        )
+
+    async def _evt_trading_paused(self, data: dict):
+        await log.warning("<yellow>[ENGINE]</yellow> Торговля на паузе: транзакции не отправляются")
+        self.notify("⏸️ ТОРГОВЛЯ НА ПАУЗЕ", severity="warning", title="Kill-switch", timeout=10)
+
+    async def _evt_trading_resumed(self, data: dict):
+        await log.success("<green>[ENGINE]</green> Торговля возобновлена")
+        self.notify("▶️ Торговля возобновлена", severity="information", title="Kill-switch")
+
+    async def _evt_sweep_summary(self, data: dict):
+        swept, skipped, failed = data.get('swept', 0), data.get('skipped', 0), data.get('failed', 0)
+        total = data.get('total_moved', 0.0)
+        dest = self._short_wallet(data.get('destination', ''))
+        await log.success(f"<green>[SWEEP]</green> → {dest}: {swept} отправлено, {skipped} пусто, {failed} ошибок, всего {total:.6f}")
+        self.notify(f"🧹 Sweep: {swept} кошельков, {total:.6f}", severity="warning" if failed else "information", title="Sweep")
+
     async def _evt_log(self, data: dict):
         level = data.get('level', 'INFO')
         msg = data.get('message', '')
@@ -780,9 +981,11 @@ class TradingApp(App):
         token_decimals = data.get('token_decimals', 18)
         
         action_ru = "Покупка" if action == "buy" else "Продажа"
+        if data.get('dry_run'):
+            action_ru = f"[DRY-RUN] {action_ru}"
         action_emoji = "🟢" if action == "buy" else "🔴"
         short_wallet = self._short_wallet(wallet) if wallet else "???"
-        explorer_url = f"{self.app_config.EXPLORER_URL}tx/{tx_hash}" if tx_hash else ""
+        explorer_url = f"{self.app_config.EXPLORER_URL}tx/{tx_hash}" if tx_hash and not data.get('dry_run') else ""
         
         #await log.debug(f"[TRADE_STATUS] status={status} | action={action} | wallet={short_wallet} | token={token_display}...")
         
@@ -839,6 +1042,14 @@ class TradingApp(App):
                         self.cache.add_token_balance(wallet, token_address, sold_wei, decimals=token_decimals, save_to_db=True)
                 except (ValueError, TypeError):
                     pass
+        
+        elif status == "paused":
+            await log.warning(f"<yellow>[PAUSED]</yellow> {action} {short_wallet} не отправлена: торговля на паузе")
+        
+        elif status == "insufficientgas":
+            native_symbol = self.app_config.NATIVE_CURRENCY_SYMBOL
+            await log.error(f"<red>[NO GAS]</red> {action} {short_wallet} не отправлена: недостаточно {native_symbol} (gas limit {data.get('gas_limit')})")
+            self.notify(f"⛽ {short_wallet}: недостаточно {native_symbol} для газа", severity="error", title="Газ", timeout=8)
 
 
     async def _update_position_memory_on_send(self, action: str, wallet: str, token_address: str, amount: float, tokens_received, tokens_sold, token_decimals: int):
@@ -879,7 +1090,8 @@ class TradingApp(App):
             "address": data.get('address', ''),
             "fee": data.get('fee', 0),
             "liquidity_usd": float(data.get('liquidity_usd', 0)),
-            "token": data.get('token', '')
+            "token": data.get('token', ''),
+            "quote_symbol": data.get('quote_symbol', '')
         }
         self._update_market_data_from_pool(data)
         self.ui_update_queue.put_nowait("refresh_market_data")
@@ -1035,7 +1247,7 @@ class TradingApp(App):
                     token_symbol = meta['symbol']
                     self._market_data['token_symbol'] = token_symbol
             
-            quote_symbol, _ = self._get_quote_info()
+            quote_symbol = self._current_pool_info.get('quote_symbol') or self._get_quote_info()[0]
             pool_type = self._current_pool_info.get('pool_type', '')
             
             if pool_type:
@@ -1096,7 +1308,8 @@ class TradingApp(App):
             self._update_trade_buttons_state()
             
             if self.bridge and token_to_unsubscribe:
-                self.bridge.send(EngineCommand.unsubscribe_token(token_to_unsubscribe))
+                self.bridge.send(EngineCommand.remove_token_subscription(token_to_unsubscribe))
+                self._subscribed_token_address = None
         except Exception as e:
             await log.error(f"[TUI] Error clearing token state: {e}")
 
@@ -1119,7 +1332,11 @@ class TradingApp(App):
         self._current_quote_address = quote_address.lower() if quote_address else None
 
         if self.bridge: 
-            self.bridge.send(EngineCommand.switch_token(token_address, quote_address, quote_symbol))
+            # Новый адрес вставлен поверх старого - предыдущую подписку снимаем
+            if self._subscribed_token_address and self._subscribed_token_address != token_address.lower():
+                self.bridge.send(EngineCommand.remove_token_subscription(self._subscribed_token_address))
+            self.bridge.send(EngineCommand.add_token_subscription(token_address, quote_address, quote_symbol))
+            self._subscribed_token_address = token_address.lower()
 
         self._trigger_impact_calc()
 
@@ -1195,13 +1412,13 @@ class TradingApp(App):
         if self._current_token_address:
             quote_address = self.app_config.QUOTE_TOKENS.get(quote_symbol, "")
             if self.bridge: 
-                self.bridge.send(EngineCommand.unsubscribe_token(self._current_token_address))
+                self.bridge.send(EngineCommand.remove_token_subscription(self._current_token_address))
                 self._market_data = self._get_empty_market_data()
                 self._current_pool_info = {}
                 self.is_pool_loading = True
                 self._update_trade_buttons_state()
                 await asyncio.sleep(0.3)
-                self.bridge.send(EngineCommand.switch_token(
+                self.bridge.send(EngineCommand.add_token_subscription(
                     self._current_token_address, quote_address, quote_symbol  
                 ))
 
@@ -1223,7 +1440,7 @@ class TradingApp(App):
             self.bridge.send(EngineCommand.update_settings(quote_symbol=quote_symbol))
         
         if self._current_token_address and self.bridge:
-            self.bridge.send(EngineCommand.switch_token(
+            self.bridge.send(EngineCommand.add_token_subscription(
                 self._current_token_address, quote_address, quote_symbol
             ))
 
@@ -1306,6 +1523,8 @@ class TradingApp(App):
                     row_key = row_key.plain
                 
                 await self.cache.delete_wallet(row_key)
+                if self.bridge:
+                    self.bridge.send(EngineCommand.remove_wallet(row_key))
                 self._trigger_wallets_refresh()
                 self.notify("Кошелек удален", severity="information")
         except Exception as e:
@@ -1518,8 +1737,8 @@ class TradingApp(App):
                 Text(pool_str, style="cyan"),
                 Text(f"${liq_usd:,.0f}", style="green"),
                 Text(f"${current_price_usd:.8f}", style="yellow"),
-                Text(f"{impact_buy:.2f}%", style=ib_color),
-                Text(f"{impact_sell:.2f}%", style=is_color),
+                Text(f"{'~' if self._market_data.get('impact_buy_estimate') else ''}{impact_buy:.2f}%", style=ib_color),
+                Text(f"{'~' if self._market_data.get('impact_sell_estimate') else ''}{impact_sell:.2f}%", style=is_color),
                 Text(pnl_str, style=pnl_color)
             )
         except Exception: pass